@@ -0,0 +1,39 @@
+use crate::ast::expression::Expression;
+use crate::ast::program::Program;
+use crate::ast::types::Type;
+use crate::error::semantic_error::SemanticErrorKind;
+
+/// A literal of an enumerated type, which stands for its ordinal
+#[derive(Debug)]
+pub struct Enumerator {
+    pub r#type: Type,
+    pub ordinal: i64
+}
+
+impl Expression for Enumerator {
+    type Error = SemanticErrorKind;
+
+    fn get_type(&self, _program: &Program) -> Type {
+        self.r#type.clone()
+    }
+
+    fn as_number(&self, _program: &Program) -> Result<i64, Self::Error> {
+        Ok(self.ordinal)
+    }
+}
+
+/// `nil`, the value of every pointer type that points to no variable
+#[derive(Debug)]
+pub struct Nil;
+
+impl Expression for Nil {
+    type Error = SemanticErrorKind;
+
+    fn get_type(&self, _program: &Program) -> Type {
+        Type::Nil
+    }
+
+    fn as_number(&self, _program: &Program) -> Result<i64, Self::Error> {
+        Err(SemanticErrorKind::InvalidLimit)
+    }
+}
@@ -1,16 +1,18 @@
-// pub mod constants;
+pub mod constants;
 pub mod variables;
 pub mod operators;
-// pub mod constants;
 
+use std::any::Any;
 use std::fmt::Debug;
 use std::ops::Deref;
-use crate::ast::program::Program;
+use crate::ast::expression::constants::{Enumerator, Nil};
+use crate::ast::expression::operators::{Call, Comparison, In, Logic, Not, Product, Signed, Sum};
+use crate::ast::expression::variables::VarRef;
+use crate::ast::program::{Dialect, Program};
 use crate::ast::types::Type;
 use crate::error::semantic_error::{SemanticError, SemanticErrorKind};
-use crate::lexer::Token;
 
-pub trait Expression: Debug + Send + Sync {
+pub trait Expression: Debug + Send + Sync + Any {
     type Error;
 
     fn get_type(&self, program: &Program) -> Type;
@@ -19,9 +21,13 @@ pub trait Expression: Debug + Send + Sync {
         vec![]
     }
     fn is_valid(&self, program: &Program) -> bool {
-        self.validate(program).len() == 0
+        self.validate(program).is_empty()
     }
     fn as_number(&self, program: &Program) -> Result<i64, Self::Error>;
+    /// The variable this expression names, if it is one
+    fn as_variable(&self) -> Option<&VarRef> {
+        None
+    }
 }
 
 #[derive(Debug)]
@@ -40,6 +46,64 @@ impl ExBox {
     }
 }
 
+/// What an expression is, for the passes that walk expressions after they
+/// have been checked
+#[derive(Debug, Clone, Copy)]
+pub enum Node<'a> {
+    Integer(i64),
+    Real(f64),
+    Char(char),
+    /// A literal of no characters or more than one
+    Str(&'a str),
+    Boolean(bool),
+    Enumerator(&'a Enumerator),
+    Nil,
+    Variable(&'a VarRef),
+    Call(&'a Call),
+    Set(&'a [ExBox]),
+    In(&'a In),
+    Comparison(&'a Comparison),
+    Sum(&'a Sum),
+    Signed(&'a Signed),
+    Product(&'a Product),
+    Not(&'a Not),
+    Logic(&'a Logic),
+    Invalid
+}
+
+impl ExBox {
+    pub fn node(&self) -> Node<'_> {
+        let expr: &dyn Any = &*self.expr;
+        macro_rules! downcast {
+            ($($ty: ty => |$expr: ident| $node: expr),*) => {
+                $(if let Some($expr) = expr.downcast_ref::<$ty>() {
+                    return $node;
+                })*
+            }
+        }
+        downcast! {
+            i64 => |value| Node::Integer(*value),
+            f64 => |value| Node::Real(*value),
+            char => |value| Node::Char(*value),
+            String => |value| Node::Str(value),
+            bool => |value| Node::Boolean(*value),
+            Enumerator => |literal| Node::Enumerator(literal),
+            Nil => |_nil| Node::Nil,
+            VarRef => |variable| Node::Variable(variable),
+            Call => |call| Node::Call(call),
+            Vec<ExBox> => |set| Node::Set(set),
+            In => |op| Node::In(op),
+            Comparison => |op| Node::Comparison(op),
+            Sum => |op| Node::Sum(op),
+            Signed => |op| Node::Signed(op),
+            Product => |op| Node::Product(op),
+            Not => |op| Node::Not(op),
+            Logic => |op| Node::Logic(op)
+        }
+        Node::Invalid
+    }
+}
+
 impl std::ops::Deref for ExBox {
     type Target = dyn Expression<Error = SemanticErrorKind>;
 
@@ -63,22 +127,21 @@ impl Expression for ExBox {
 
     fn validate(&self, program: &Program) -> Vec<Self::Error> {
         self.expr.validate(program).into_iter()
-            .map(|kind| SemanticError {
-                range: self.range.clone(),
-                kind
-            })
+            .map(|kind| SemanticError::new(self.range.clone(), kind))
             .collect()
     }
 
     fn as_number(&self, program: &Program) -> Result<i64, Self::Error> {
-        self.expr.as_number(program).map_err(|kind| SemanticError {
-            range: self.range.clone(),
-            kind
-        })
+        self.expr.as_number(program)
+            .map_err(|kind| SemanticError::new(self.range.clone(), kind))
+    }
+
+    fn as_variable(&self) -> Option<&VarRef> {
+        self.expr.as_variable()
     }
 }
 
-impl<E> Expression for Box<dyn Expression<Error = E>> {
+impl<E: 'static> Expression for Box<dyn Expression<Error = E>> {
     type Error = E;
 
     fn get_type(&self, program: &Program) -> Type {
@@ -96,6 +159,10 @@ impl<E> Expression for Box<dyn Expression<Error = E>> {
     fn as_number(&self, program: &Program) -> Result<i64, Self::Error> {
         self.deref().as_number(program)
     }
+
+    fn as_variable(&self) -> Option<&VarRef> {
+        self.deref().as_variable()
+    }
 }
 
 #[derive(Debug)]
@@ -104,11 +171,11 @@ pub struct Invalid;
 impl Expression for Invalid {
     type Error = SemanticErrorKind;
 
-    fn get_type(&self, program: &Program) -> Type {
+    fn get_type(&self, _program: &Program) -> Type {
         Type::Invalid
     }
 
-    fn as_number(&self, program: &Program) -> Result<i64, Self::Error> {
+    fn as_number(&self, _program: &Program) -> Result<i64, Self::Error> {
         Ok(0)
     }
 }
@@ -120,7 +187,7 @@ impl Expression for i64 {
         Type::Integer
     }
 
-    fn as_number(&self, program: &Program) -> Result<i64, Self::Error> {
+    fn as_number(&self, _program: &Program) -> Result<i64, Self::Error> {
         Ok(*self)
     }
 }
@@ -132,7 +199,7 @@ impl Expression for f64 {
         Type::Real
     }
 
-    fn as_number(&self, program: &Program) -> Result<i64, Self::Error> {
+    fn as_number(&self, _program: &Program) -> Result<i64, Self::Error> {
         Err(SemanticErrorKind::InvalidLimit)
     }
 }
@@ -144,7 +211,7 @@ impl Expression for char {
         Type::Char
     }
 
-    fn as_number(&self, program: &Program) -> Result<i64, Self::Error> {
+    fn as_number(&self, _program: &Program) -> Result<i64, Self::Error> {
         Ok(*self as i64)
     }
 }
@@ -156,7 +223,32 @@ impl Expression for bool {
         Type::Boolean
     }
 
-    fn as_number(&self, program: &Program) -> Result<i64, Self::Error> {
+    fn as_number(&self, _program: &Program) -> Result<i64, Self::Error> {
         Ok(*self as i64)
     }
-}
\ No newline at end of file
+}
+/// A string literal, a `packed array [1..n] of char` as standard Pascal has
+/// it; only Turbo Pascal has the empty string
+impl Expression for String {
+    type Error = SemanticErrorKind;
+
+    fn get_type(&self, _program: &Program) -> Type {
+        match self.chars().count() {
+            0 => Type::String(0),
+            length => Type::ArrayOf(vec![Type::Range(Box::new(Type::Integer), 1..length as isize)],
+                                    Box::new(Type::Char))
+        }
+    }
+
+    fn validate(&self, program: &Program) -> Vec<Self::Error> {
+        if self.is_empty() && program.dialect == Dialect::Iso {
+            vec![SemanticErrorKind::Extension("''".to_string())]
+        } else {
+            vec![]
+        }
+    }
+
+    fn as_number(&self, _program: &Program) -> Result<i64, Self::Error> {
+        Err(SemanticErrorKind::InvalidLimit)
+    }
+}
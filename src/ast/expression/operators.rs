@@ -1,16 +1,10 @@
-use std::sync::mpsc::SendError;
+use std::fmt::{Display, Formatter};
 use crate::ast::expression::{ExBox, Expression};
-use crate::ast::program::Program;
+use crate::ast::program::{Dialect, Program, ScopeId, Symbol};
+use crate::ast::standard::Builtin;
+use crate::ast::subprogram::Subprogram;
 use crate::ast::types::Type;
-use crate::error::semantic_error::SemanticErrorKind;
-
-macro_rules! validate {
-    ($s:expr, $p:expr) => {
-        if !$s.is_valid($p) {
-            return Type::Invalid;
-        }
-    }
-}
+use crate::error::semantic_error::{SemanticError, SemanticErrorKind};
 
 trait IntoSemanticErrorKind {
     type Out;
@@ -35,33 +29,210 @@ impl<Ok, Err: Into<SemanticErrorKind>> IntoSemanticErrorKind for Result<Ok, Err>
     }
 }
 
-fn validate_primitives<Err: Into<SemanticErrorKind>>(
+/// Applies `check` to the operand types of a binary operator. `Type::Invalid`
+/// poisons the operation: its error has already been reported, so the result
+/// is invalid as well but nothing further is reported about it
+fn binary_type<Err: Into<SemanticErrorKind>>(
     left: &impl Expression<Error = Err>,
     right: &impl Expression<Error = Err>,
-    program: &Program
+    program: &Program,
+    check: impl FnOnce(Type, Type) -> Result<Type, SemanticErrorKind>
+) -> Result<Type, SemanticErrorKind> {
+    let left = left.get_type(program);
+    let right = right.get_type(program);
+    if left == Type::Invalid || right == Type::Invalid {
+        Ok(Type::Invalid)
+    } else {
+        check(left, right)
+    }
+}
+
+/// Validates both operands of a binary operator and, on top of their own
+/// errors, reports the error produced by `check` on their types
+fn validate_binary<Err: Into<SemanticErrorKind>>(
+    left: &impl Expression<Error = Err>,
+    right: &impl Expression<Error = Err>,
+    program: &Program,
+    check: impl FnOnce(Type, Type) -> Result<Type, SemanticErrorKind>
 ) -> Vec<SemanticErrorKind> {
     let mut out = left.validate(program).into_kind();
     out.append(&mut right.validate(program).into_kind());
-    let left_type = left.get_type(program);
-    let right_type = left.get_type(program);
-    if !(left.get_type(program).is_primitive() && right.get_type(program).is_primitive()) {
-        out.push(SemanticErrorKind::TypeError {
-            expected: Type::PRIMITIVE.to_vec(),
-            got: if left_type.is_primitive() { right_type } else { left_type }
-        })
+    if let Err(err) = binary_type(left, right, program, check) {
+        out.push(err);
     }
     out
 }
 
-fn arithmetic_operation(left: &impl Expression,
-                        right: &impl Expression,
-                        program: &Program) -> Type {
-    let left_type = left.get_type(program);
-    let right_type = right.get_type(program);
-    if left_type == right_type {
-        left_type // can be either integer or real
+/// The same as `binary_type`, for operators with a single operand
+fn unary_type<Err: Into<SemanticErrorKind>>(
+    operand: &impl Expression<Error = Err>,
+    program: &Program,
+    check: impl FnOnce(Type) -> Result<Type, SemanticErrorKind>
+) -> Result<Type, SemanticErrorKind> {
+    match operand.get_type(program) {
+        Type::Invalid => Ok(Type::Invalid),
+        ty => check(ty)
+    }
+}
+
+fn validate_unary<Err: Into<SemanticErrorKind>>(
+    operand: &impl Expression<Error = Err>,
+    program: &Program,
+    check: impl FnOnce(Type) -> Result<Type, SemanticErrorKind>
+) -> Vec<SemanticErrorKind> {
+    let mut out = operand.validate(program).into_kind();
+    if let Err(err) = unary_type(operand, program, check) {
+        out.push(err);
+    }
+    out
+}
+
+/// Picks the operand to blame when only one of the two is acceptable
+fn offending(left: Type, right: Type, accepts: impl Fn(&Type) -> bool) -> Type {
+    if accepts(&left) { right } else { left }
+}
+
+fn set_of_any() -> Type {
+    Type::SetOf(Box::new(Type::Invalid))
+}
+
+/// The error of constant arithmetic whose result an integer cannot hold
+fn overflow() -> SemanticErrorKind {
+    SemanticErrorKind::InvalidConstant("Integer overflow".to_string())
+}
+
+/// `+`, `-` and `*`: integers stay integers, any real operand promotes the
+/// result to real and two compatible sets give a set
+fn arithmetic_type(left: Type, right: Type) -> Result<Type, SemanticErrorKind> {
+    if left.is_numeric() && right.is_numeric() {
+        return Ok(if left.is_integer() && right.is_integer() { Type::Integer } else { Type::Real });
+    }
+    match (&left, &right) {
+        (Type::SetOf(base), Type::SetOf(_)) if left.is_compatible(&right) =>
+            Ok(Type::SetOf(Box::new(base.host()))),
+        (Type::SetOf(_), Type::SetOf(_)) => Err(SemanticErrorKind::TypeError {
+            expected: vec![left],
+            got: right
+        }),
+        _ => {
+            let accepts = |ty: &Type| ty.is_numeric() || matches!(ty, Type::SetOf(_));
+            let mut expected = Type::NUMERIC.to_vec();
+            expected.push(set_of_any());
+            Err(SemanticErrorKind::TypeError {
+                expected,
+                got: offending(left, right, accepts)
+            })
+        }
+    }
+}
+
+/// `+` and `-`, where Turbo Pascal also joins strings and characters with `+`
+fn sum_type(op: &SumOp, dialect: Dialect, left: Type, right: Type) -> Result<Type, SemanticErrorKind> {
+    if *op == SumOp::Add && dialect == Dialect::Turbo && left.is_textual() && right.is_textual() {
+        Ok(Type::String(255))
+    } else {
+        arithmetic_type(left, right)
+    }
+}
+
+fn real_division_type(left: Type, right: Type) -> Result<Type, SemanticErrorKind> {
+    if left.is_numeric() && right.is_numeric() {
+        Ok(Type::Real)
+    } else {
+        Err(SemanticErrorKind::TypeError {
+            expected: Type::NUMERIC.to_vec(),
+            got: offending(left, right, Type::is_numeric)
+        })
+    }
+}
+
+fn integer_division_type(left: Type, right: Type) -> Result<Type, SemanticErrorKind> {
+    if left.is_integer() && right.is_integer() {
+        Ok(Type::Integer)
+    } else {
+        Err(SemanticErrorKind::TypeError {
+            expected: vec![Type::Integer],
+            got: offending(left, right, Type::is_integer)
+        })
+    }
+}
+
+fn boolean_type(left: Type, right: Type) -> Result<Type, SemanticErrorKind> {
+    if left == Type::Boolean && right == Type::Boolean {
+        Ok(Type::Boolean)
+    } else {
+        Err(SemanticErrorKind::TypeError {
+            expected: vec![Type::Boolean],
+            got: offending(left, right, |ty| *ty == Type::Boolean)
+        })
+    }
+}
+
+fn signed_type(operand: Type) -> Result<Type, SemanticErrorKind> {
+    if operand.is_numeric() {
+        Ok(operand.host())
+    } else {
+        Err(SemanticErrorKind::TypeError {
+            expected: Type::NUMERIC.to_vec(),
+            got: operand
+        })
+    }
+}
+
+fn negation_type(operand: Type) -> Result<Type, SemanticErrorKind> {
+    boolean_type(Type::Boolean, operand)
+}
+
+fn is_comparable(ty: &Type) -> bool {
+    ty.is_numeric() || ty.is_ordinal() || ty.is_textual()
+        || matches!(ty, Type::SetOf(_) | Type::Pointer(_) | Type::Nil)
+}
+
+/// Standard Pascal only compares strings of the same length, where Turbo
+/// Pascal compares any strings and characters
+fn comparison_type(op: &CompOp, dialect: Dialect, left: Type, right: Type) -> Result<Type, SemanticErrorKind> {
+    if !is_comparable(&left) || !is_comparable(&right) {
+        return Err(SemanticErrorKind::UnsupportedOperation {
+            operation: op.to_string(),
+            operand: offending(left, right, is_comparable)
+        });
+    }
+    let compatible = (left.is_numeric() && right.is_numeric())
+        || (left.is_string() && left == right)
+        || (dialect == Dialect::Turbo && left.is_textual() && right.is_textual())
+        || (!left.is_string() && left.is_compatible(&right));
+    if !compatible {
+        return Err(SemanticErrorKind::TypeError {
+            expected: vec![left],
+            got: right
+        });
+    }
+    let ordered = match left {
+        Type::SetOf(_) => !matches!(op, CompOp::Lt | CompOp::Bg),
+        Type::Pointer(_) | Type::Nil => matches!(op, CompOp::Eq | CompOp::Neq),
+        _ => true
+    };
+    if ordered {
+        Ok(Type::Boolean)
     } else {
-        Type::Real
+        Err(SemanticErrorKind::UnsupportedOperation {
+            operation: op.to_string(),
+            operand: left
+        })
+    }
+}
+
+fn membership_type(sample: Type, set: Type) -> Result<Type, SemanticErrorKind> {
+    match &set {
+        Type::SetOf(base) if sample.is_ordinal() && sample.is_compatible(base) => Ok(Type::Boolean),
+        Type::SetOf(base) => Err(SemanticErrorKind::TypeError {
+            expected: vec![*base.clone()],
+            got: sample
+        }),
+        _ => Err(SemanticErrorKind::TypeError {
+            expected: vec![set_of_any()],
+            got: set
+        })
     }
 }
 
@@ -77,43 +248,20 @@ impl<Err: Into<SemanticErrorKind>,
     type Error = SemanticErrorKind;
 
     fn get_type(&self, program: &Program) -> Type {
-        validate!(self, program);
-        Type::Boolean
+        binary_type(&self.sample, &self.set, program, membership_type)
+            .unwrap_or(Type::Invalid)
     }
 
     fn validate(&self, program: &Program) -> Vec<SemanticErrorKind> {
-        let mut out = self.sample.validate(program).into_kind();
-        if out.len() > 0 {
-            return out;
-        }
-        out.append(&mut self.set.validate(program).into_kind());
-        if out.len() > 0 {
-            return out;
-        }
-        let set_type = self.set.get_type(program);
-        if let Type::SetOf(ty) = self.set.get_type(program) {
-            let sample_type = self.sample.get_type(program);
-            if sample_type != *ty {
-                out.push(SemanticErrorKind::TypeError {
-                    expected: vec![*ty.clone()],
-                    got: sample_type
-                });
-            }
-        } else {
-            out.push(SemanticErrorKind::TypeError {
-                expected: vec![Type::SetOf(Box::new(Type::Invalid))],
-                got: set_type
-            });
-        }
-        out
+        validate_binary(&self.sample, &self.set, program, membership_type)
     }
 
-    fn as_number(&self, program: &Program) -> Result<i64, Self::Error> {
+    fn as_number(&self, _program: &Program) -> Result<i64, Self::Error> {
         Err(SemanticErrorKind::InvalidLimit)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Eq, PartialEq)]
 pub enum CompOp {
     Bg,
     Lt,
@@ -123,6 +271,19 @@ pub enum CompOp {
     Eq
 }
 
+impl Display for CompOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            CompOp::Bg => ">",
+            CompOp::Lt => "<",
+            CompOp::Bge => ">=",
+            CompOp::Lte => "<=",
+            CompOp::Neq => "<>",
+            CompOp::Eq => "="
+        })
+    }
+}
+
 #[derive(Debug)]
 pub struct Comparison<Left: Expression = ExBox,
                       Right: Expression = ExBox> {
@@ -137,12 +298,14 @@ impl<Err: Into<SemanticErrorKind>,
     type Error = SemanticErrorKind;
 
     fn get_type(&self, program: &Program) -> Type {
-        validate!(self, program);
-        Type::Boolean
+        binary_type(&self.left, &self.right, program,
+                    |left, right| comparison_type(&self.op, program.dialect, left, right))
+            .unwrap_or(Type::Invalid)
     }
 
     fn validate(&self, program: &Program) -> Vec<SemanticErrorKind> {
-        validate_primitives(&self.left, &self.right, program)
+        validate_binary(&self.left, &self.right, program,
+                        |left, right| comparison_type(&self.op, program.dialect, left, right))
     }
 
     fn as_number(&self, program: &Program) -> Result<i64, Self::Error> {
@@ -165,9 +328,9 @@ pub enum SumOp {
     Sub,
 }
 
-impl Into<bool> for SumOp {
-    fn into(self) -> bool {
-        self == SumOp::Add
+impl From<SumOp> for bool {
+    fn from(op: SumOp) -> bool {
+        op == SumOp::Add
     }
 }
 
@@ -185,21 +348,51 @@ impl<Err: Into<SemanticErrorKind>,
     type Error = SemanticErrorKind;
 
     fn get_type(&self, program: &Program) -> Type {
-        validate!(self, program);
-        arithmetic_operation(&self.left, &self.right, program)
+        binary_type(&self.left, &self.right, program,
+                    |left, right| sum_type(&self.op, program.dialect, left, right))
+            .unwrap_or(Type::Invalid)
     }
 
     fn validate(&self, program: &Program) -> Vec<SemanticErrorKind> {
-        validate_primitives(&self.left, &self.right, program)
+        validate_binary(&self.left, &self.right, program,
+                        |left, right| sum_type(&self.op, program.dialect, left, right))
     }
 
     fn as_number(&self, program: &Program) -> Result<i64, Self::Error> {
         let left = self.left.as_number(program).into_kind()?;
         let right = self.right.as_number(program).into_kind()?;
-        Ok(match self.op {
-            SumOp::Add => left + right,
-            SumOp::Sub => left - right
-        })
+        match self.op {
+            SumOp::Add => left.checked_add(right),
+            SumOp::Sub => left.checked_sub(right)
+        }.ok_or_else(overflow)
+    }
+}
+
+#[derive(Debug)]
+pub struct Signed<E: Expression = ExBox> {
+    pub op: SumOp,
+    pub operand: E
+}
+
+impl<Err: Into<SemanticErrorKind>,
+    Exp: Expression<Error = Err>> Expression for Signed<Exp> {
+    type Error = SemanticErrorKind;
+
+    fn get_type(&self, program: &Program) -> Type {
+        unary_type(&self.operand, program, signed_type)
+            .unwrap_or(Type::Invalid)
+    }
+
+    fn validate(&self, program: &Program) -> Vec<SemanticErrorKind> {
+        validate_unary(&self.operand, program, signed_type)
+    }
+
+    fn as_number(&self, program: &Program) -> Result<i64, Self::Error> {
+        let operand = self.operand.as_number(program).into_kind()?;
+        match self.op {
+            SumOp::Add => Some(operand),
+            SumOp::Sub => operand.checked_neg()
+        }.ok_or_else(overflow)
     }
 }
 
@@ -211,6 +404,16 @@ pub enum ProdOp {
     Mod
 }
 
+impl ProdOp {
+    fn result_type(&self, left: Type, right: Type) -> Result<Type, SemanticErrorKind> {
+        match self {
+            ProdOp::Mul => arithmetic_type(left, right),
+            ProdOp::RDiv => real_division_type(left, right),
+            ProdOp::Div | ProdOp::Mod => integer_division_type(left, right)
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Product<Left: Expression = ExBox,
                    Right: Expression = ExBox> {
@@ -225,67 +428,45 @@ impl<Err: Into<SemanticErrorKind>,
     type Error = SemanticErrorKind;
 
     fn get_type(&self, program: &Program) -> Type {
-        validate!(self, program);
-        if self.op == ProdOp::RDiv {
-            return Type::Real
-        }
-        arithmetic_operation(&self.left, &self.right, program)
+        binary_type(&self.left, &self.right, program,
+                    |left, right| self.op.result_type(left, right))
+            .unwrap_or(Type::Invalid)
     }
 
     fn validate(&self, program: &Program) -> Vec<SemanticErrorKind> {
-        let mut out = validate_primitives(&self.left, &self.right, program);
-        if out.len() > 0 {
-            return out;
-        }
-        let left_type = self.left.get_type(program);
-        let right_type = self.right.get_type(program);
-        if self.op == ProdOp::Div && [&left_type, &right_type].contains(&&Type::Real) {
-            const ALLOWED: &[Type] = &[Type::Integer, Type::Char, Type::Boolean];
-            out.push(SemanticErrorKind::TypeError {
-                expected: ALLOWED.to_vec(),
-                got: if ALLOWED.contains(&left_type) { right_type } else { left_type }
-            });
-        }
-        out
+        validate_binary(&self.left, &self.right, program,
+                        |left, right| self.op.result_type(left, right))
     }
 
     fn as_number(&self, program: &Program) -> Result<i64, Self::Error> {
         let left = self.left.as_number(program).into_kind()?;
         let right = self.right.as_number(program).into_kind()?;
-        Ok(match self.op {
-            ProdOp::Mul => left * right,
+        if right == 0 && matches!(self.op, ProdOp::Div | ProdOp::Mod) {
+            return Err(SemanticErrorKind::InvalidConstant("Division by zero".to_string()));
+        }
+        match self.op {
+            ProdOp::Mul => left.checked_mul(right),
             ProdOp::RDiv => return Err(SemanticErrorKind::InvalidLimit),
-            ProdOp::Div => left / right,
-            ProdOp::Mod => left % right
-        })
+            ProdOp::Div => left.checked_div(right),
+            ProdOp::Mod => left.checked_rem(right)
+        }.ok_or_else(overflow)
     }
 }
 
 #[derive(Debug)]
-pub struct Not<E: Expression = ExBox>(E);
+pub struct Not<E: Expression = ExBox>(pub E);
 
 impl<Err: Into<SemanticErrorKind>,
     Exp: Expression<Error = Err>> Expression for Not<Exp> {
     type Error = SemanticErrorKind;
 
     fn get_type(&self, program: &Program) -> Type {
-        validate!(self, program);
-        Type::Boolean
+        unary_type(&self.0, program, negation_type)
+            .unwrap_or(Type::Invalid)
     }
 
     fn validate(&self, program: &Program) -> Vec<SemanticErrorKind> {
-        let mut out = self.0.validate(program).into_kind();
-        if out.len() > 0 {
-            return out
-        }
-        let ty = self.0.get_type(program);
-        if ty != Type::Boolean {
-            out.push(SemanticErrorKind::TypeError {
-                expected: vec![Type::Boolean],
-                got: ty
-            })
-        }
-        out
+        validate_unary(&self.0, program, negation_type)
     }
 
     fn as_number(&self, program: &Program) -> Result<i64, Self::Error> {
@@ -313,13 +494,12 @@ impl<Err: Into<SemanticErrorKind>,
     type Error = SemanticErrorKind;
 
     fn get_type(&self, program: &Program) -> Type {
-        validate!(self, program);
-        Type::Boolean
+        binary_type(&self.left, &self.right, program, boolean_type)
+            .unwrap_or(Type::Invalid)
     }
 
-    fn is_valid(&self, program: &Program) -> bool {
-        self.right.get_type(program) == Type::Boolean
-            && self.left.get_type(program) == Type::Boolean
+    fn validate(&self, program: &Program) -> Vec<SemanticErrorKind> {
+        validate_binary(&self.left, &self.right, program, boolean_type)
     }
 
     fn as_number(&self, program: &Program) -> Result<i64, Self::Error> {
@@ -332,22 +512,228 @@ impl<Err: Into<SemanticErrorKind>,
     }
 }
 
+/// How an argument of `write` is written, as in `x:10:2`: right-aligned in
+/// a field of `width` characters and, for a real, in fixed point with
+/// `decimals` digits after the point
 #[derive(Debug)]
-pub struct Call<E: Expression = ExBox> {
+pub struct Format {
+    pub width: ExBox,
+    pub decimals: Option<ExBox>
+}
+
+#[derive(Debug)]
+pub struct Call {
     pub name: String,
-    pub args: Vec<E>
+    pub args: Vec<ExBox>,
+    /// The format of each argument, if it was given one
+    pub formats: Vec<Option<Format>>,
+    /// The scope the call was written in
+    pub scope: ScopeId
 }
 
-impl<Err: Into<SemanticErrorKind>,
-    Exp: Expression<Error = Err>> Expression for Call<Exp> {
+impl Call {
+    /// Validates the call as a statement, where procedures may be called as
+    /// well as functions
+    pub fn validate_call(&self, program: &Program) -> Vec<SemanticErrorKind> {
+        let mut out = self.args.iter()
+            .flat_map(|arg| arg.validate(program).into_kind())
+            .collect::<Vec<SemanticErrorKind>>();
+        out.append(&mut self.validate_formats(program));
+        match program.lookup(self.scope, &self.name) {
+            Some((_, Symbol::Subprogram(subprogram))) =>
+                out.append(&mut self.validate_arguments(subprogram, program)),
+            Some(_) => out.push(SemanticErrorKind::NotCallable(self.name.clone())),
+            None => match Builtin::lookup(&self.name) {
+                Some(builtin) if builtin.is_extension() && program.dialect == Dialect::Iso =>
+                    out.push(SemanticErrorKind::Extension(self.name.clone())),
+                Some(builtin) => out.append(&mut self.validate_builtin(builtin, program)),
+                None => out.push(SemanticErrorKind::UndeclaredIdentifier(self.name.clone()))
+            }
+        }
+        out
+    }
+
+    /// Checks the formats of the arguments, which only the values `write`
+    /// and `writeln` write to text and the number `str` converts take, with
+    /// decimals only for reals
+    fn validate_formats(&self, program: &Program) -> Vec<SemanticErrorKind> {
+        let text = match self.args.first().map(|arg| arg.get_type(program)) {
+            Some(Type::File(element)) => *element == Type::Char,
+            _ => true
+        };
+        let builtin = self.builtin(program);
+        let writes = text && matches!(builtin, Some(Builtin::Write) | Some(Builtin::Writeln));
+        let located = |range, kind| SemanticErrorKind::Located(Box::new(SemanticError::new(range, kind)));
+        let mut out = vec![];
+        for (index, (arg, format)) in self.args.iter().zip(&self.formats).enumerate() {
+            let format = match format {
+                Some(format) => format,
+                None => continue
+            };
+            let ty = arg.get_type(program);
+            let converts = index == 0 && builtin == Some(Builtin::Str);
+            if !(writes || converts) || matches!(ty, Type::File(_)) {
+                let end = format.decimals.as_ref().unwrap_or(&format.width).range.end;
+                out.push(located(format.width.range.start..end, SemanticErrorKind::MisplacedFormat));
+                continue;
+            }
+            for expr in std::iter::once(&format.width).chain(&format.decimals) {
+                let mut errors = expr.validate(program).into_kind();
+                let got = expr.get_type(program);
+                if errors.is_empty() && got != Type::Invalid && !got.is_integer() {
+                    errors.push(SemanticErrorKind::TypeError { expected: vec![Type::Integer], got });
+                }
+                out.extend(errors.into_iter().map(|kind| located(expr.range.clone(), kind)));
+            }
+            match &format.decimals {
+                Some(decimals) if ty != Type::Invalid && ty != Type::Real => out.push(located(decimals.range.clone(),
+                    SemanticErrorKind::UnsupportedOperation {
+                        operation: "decimal places".to_string(),
+                        operand: ty
+                    })),
+                _ => {}
+            }
+        }
+        out
+    }
+
+    /// The standard subprogram called, unless a declaration shadows it
+    fn builtin(&self, program: &Program) -> Option<Builtin> {
+        match program.lookup(self.scope, &self.name) {
+            Some(_) => None,
+            None => Builtin::lookup(&self.name)
+        }
+    }
+
+    fn validate_builtin(&self, builtin: Builtin, program: &Program) -> Vec<SemanticErrorKind> {
+        if !builtin.arity().contains(&self.args.len()) {
+            return vec![SemanticErrorKind::ArgumentCount {
+                name: self.name.clone(),
+                expected: *builtin.arity().start(),
+                got: self.args.len()
+            }];
+        }
+        let types = self.args.iter()
+            .map(|arg| arg.get_type(program))
+            .collect::<Vec<Type>>();
+        let mut out = self.args.iter()
+            .enumerate()
+            .filter_map(|(index, arg)| match builtin.var_parameter(index) {
+                Some(param) if arg.as_variable().is_none() =>
+                    Some((index, SemanticErrorKind::VariableRequired(param.to_string()))),
+                _ => None
+            })
+            .collect::<Vec<(usize, SemanticErrorKind)>>();
+        if !types.contains(&Type::Invalid) {
+            out.append(&mut builtin.check_arguments(&types));
+        }
+        out.into_iter()
+            .map(|(index, kind)| SemanticErrorKind::Located(Box::new(
+                SemanticError::new(self.args[index].range.clone(), kind))))
+            .collect()
+    }
+
+    fn validate_arguments(&self, subprogram: &Subprogram, program: &Program) -> Vec<SemanticErrorKind> {
+        if subprogram.params.len() != self.args.len() {
+            return vec![SemanticErrorKind::ArgumentCount {
+                name: self.name.clone(),
+                expected: subprogram.params.len(),
+                got: self.args.len()
+            }];
+        }
+        subprogram.params.iter()
+            .zip(self.args.iter())
+            .filter_map(|(param, arg)| {
+                let ty = arg.get_type(program);
+                let kind = if ty == Type::Invalid {
+                    return None
+                } else if param.by_ref && arg.as_variable().is_none() {
+                    SemanticErrorKind::VariableRequired(param.name.clone())
+                } else if (param.by_ref && param.r#type != ty) || !param.r#type.accepts(&ty) {
+                    SemanticErrorKind::TypeError {
+                        expected: vec![param.r#type.clone()],
+                        got: ty
+                    }
+                } else {
+                    return None
+                };
+                Some(SemanticErrorKind::Located(Box::new(SemanticError::new(arg.range.clone(), kind))))
+            })
+            .collect()
+    }
+}
+
+impl Expression for Call {
     type Error = SemanticErrorKind;
 
-    fn get_type(&self, _: &Program) -> Type {
-        todo!()
+    fn get_type(&self, program: &Program) -> Type {
+        if let Some(builtin) = self.builtin(program) {
+            let args = self.args.iter()
+                .map(|arg| arg.get_type(program))
+                .collect::<Vec<Type>>();
+            return if args.contains(&Type::Invalid) {
+                Type::Invalid
+            } else {
+                builtin.result_type(&args).unwrap_or(Type::Invalid)
+            };
+        }
+        match program.lookup(self.scope, &self.name) {
+            Some((_, Symbol::Subprogram(subprogram))) => subprogram.result.clone()
+                .unwrap_or(Type::Invalid),
+            _ => Type::Invalid
+        }
+    }
+
+    fn validate(&self, program: &Program) -> Vec<SemanticErrorKind> {
+        let mut out = self.validate_call(program);
+        let is_function = match program.lookup(self.scope, &self.name) {
+            Some((_, Symbol::Subprogram(subprogram))) => subprogram.is_function(),
+            Some(_) => true,
+            None => Builtin::lookup(&self.name).is_none_or(|builtin| builtin.is_function())
+        };
+        if !is_function {
+            out.push(SemanticErrorKind::NotAValue(self.name.clone()));
+        }
+        out
     }
 
     fn as_number(&self, program: &Program) -> Result<i64, Self::Error> {
-        Err(SemanticErrorKind::InvalidLimit)
+        let builtin = self.builtin(program).ok_or(SemanticErrorKind::InvalidLimit)?;
+        let args = self.args.iter()
+            .map(|arg| arg.as_number(program).map_err(Into::into))
+            .collect::<Result<Vec<i64>, SemanticErrorKind>>()?;
+        builtin.fold(&args).ok_or(SemanticErrorKind::InvalidLimit)
+    }
+}
+
+trait SetConstructor {
+    fn element_type(&self, program: &Program) -> Result<Type, SemanticErrorKind>;
+}
+
+impl<Err: Into<SemanticErrorKind>,
+    E: Expression<Error = Err>> SetConstructor for Vec<E> {
+    /// The type of the first element, which every other one must share
+    fn element_type(&self, program: &Program) -> Result<Type, SemanticErrorKind> {
+        let types = self.iter()
+            .map(|el| el.get_type(program))
+            .collect::<Vec<Type>>();
+        if types.contains(&Type::Invalid) {
+            return Ok(Type::Invalid);
+        }
+        let ty = types[0].clone();
+        if !ty.is_ordinal() {
+            return Err(SemanticErrorKind::UnsupportedOperation {
+                operation: "set of".to_string(),
+                operand: ty
+            });
+        }
+        match types.into_iter().find(|el| !el.is_compatible(&ty)) {
+            Some(got) => Err(SemanticErrorKind::TypeError {
+                expected: vec![ty],
+                got
+            }),
+            None => Ok(ty)
+        }
     }
 }
 
@@ -356,35 +742,285 @@ impl<Err: Into<SemanticErrorKind>,
     type Error = SemanticErrorKind;
 
     fn get_type(&self, program: &Program) -> Type {
-        Type::SetOf(Box::new(self[0].get_type(program)))
+        self.element_type(program)
+            .map(|ty| match ty {
+                Type::Invalid => Type::Invalid,
+                ty => Type::SetOf(Box::new(ty.host()))
+            })
+            .unwrap_or(Type::Invalid)
     }
 
     fn validate(&self, program: &Program) -> Vec<SemanticErrorKind> {
-        let ty = self[0].get_type(program);
-        let mut out = self.into_iter()
+        let mut out = self.iter()
             .flat_map(|el| el.validate(program).into_kind())
             .collect::<Vec<SemanticErrorKind>>();
-        if out.len() > 0 {
-            return out;
+        if let Err(err) = self.element_type(program) {
+            out.push(err);
         }
-        out.append(&mut self.into_iter()
-            .filter_map(|obj| {
-                let ety = obj.get_type(program);
-                if ety != ty {
-                    Some(ety)
-                } else {
-                    None
-                }
-            })
-            .map(|got| SemanticErrorKind::TypeError {
-                expected: vec![ty.clone()],
-                got
-            })
-            .collect());
         out
     }
 
-    fn as_number(&self, program: &Program) -> Result<i64, Self::Error> {
+    fn as_number(&self, _program: &Program) -> Result<i64, Self::Error> {
         Err(SemanticErrorKind::InvalidLimit)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use super::*;
+
+    /// An operand that only carries its type
+    #[derive(Debug)]
+    struct Typed(Type);
+
+    impl Expression for Typed {
+        type Error = SemanticErrorKind;
+
+        fn get_type(&self, _program: &Program) -> Type {
+            self.0.clone()
+        }
+
+        fn as_number(&self, _program: &Program) -> Result<i64, Self::Error> {
+            Err(SemanticErrorKind::InvalidLimit)
+        }
+    }
+
+    fn program() -> Program {
+        Program::new("program test;".to_string()).0
+    }
+
+    fn string(len: usize) -> Type {
+        Type::ArrayOf(vec![Type::Range(Box::new(Type::Integer), 1..len as isize)], Box::new(Type::Char))
+    }
+
+    fn range() -> Type {
+        Type::Range(Box::new(Type::Integer), 1..10)
+    }
+
+    fn color() -> Type {
+        Type::Enum(vec!["red".to_string(), "green".to_string()])
+    }
+
+    fn operands() -> Vec<Type> {
+        let mut record = HashMap::new();
+        record.insert("x".to_string(), Type::Integer);
+        vec![
+            Type::Integer,
+            Type::Real,
+            Type::Boolean,
+            Type::Char,
+            color(),
+            range(),
+            Type::SetOf(Box::new(Type::Integer)),
+            Type::SetOf(Box::new(Type::Char)),
+            string(5),
+            string(3),
+            Type::ArrayOf(vec![Type::Range(Box::new(Type::Integer), 0..3)], Box::new(Type::Integer)),
+            Type::Record(record),
+            Type::Pointer(Box::new(Type::Integer)),
+            Type::Pointer(Box::new(Type::Char)),
+            Type::Nil,
+        ]
+    }
+
+    /// Runs `build` over every pair of operands and checks it against the
+    /// pairs in `valid`, which must type as the given result
+    fn check_matrix<E>(name: &str, valid: &[(Type, Type, Type)], build: impl Fn(Typed, Typed) -> E)
+        where E: Expression<Error = SemanticErrorKind> {
+        let program = program();
+        for left in operands() {
+            for right in operands() {
+                let expr = build(Typed(left.clone()), Typed(right.clone()));
+                let expected = valid.iter()
+                    .find(|(l, r, _)| *l == left && *r == right)
+                    .map(|(_, _, result)| result.clone());
+                match expected {
+                    Some(result) => {
+                        assert!(expr.is_valid(&program), "{} {} {} should be valid: {:?}",
+                                left, name, right, expr.validate(&program));
+                        assert_eq!(expr.get_type(&program), result, "{} {} {}", left, name, right);
+                    }
+                    None => {
+                        assert_eq!(expr.validate(&program).len(), 1, "{} {} {} should be invalid",
+                                   left, name, right);
+                        assert_eq!(expr.get_type(&program), Type::Invalid, "{} {} {}", left, name, right);
+                    }
+                }
+            }
+        }
+    }
+
+    fn numeric(integer: Type, mixed: Type) -> Vec<(Type, Type, Type)> {
+        vec![
+            (Type::Integer, Type::Integer, integer.clone()),
+            (Type::Integer, range(), integer.clone()),
+            (range(), Type::Integer, integer.clone()),
+            (range(), range(), integer),
+            (Type::Integer, Type::Real, mixed.clone()),
+            (Type::Real, Type::Integer, mixed.clone()),
+            (range(), Type::Real, mixed.clone()),
+            (Type::Real, range(), mixed.clone()),
+            (Type::Real, Type::Real, mixed),
+        ]
+    }
+
+    fn sets() -> Vec<(Type, Type, Type)> {
+        let integers = Type::SetOf(Box::new(Type::Integer));
+        let chars = Type::SetOf(Box::new(Type::Char));
+        vec![
+            (integers.clone(), integers.clone(), integers),
+            (chars.clone(), chars.clone(), chars),
+        ]
+    }
+
+    fn with_result(pairs: Vec<(Type, Type, Type)>, result: Type) -> Vec<(Type, Type, Type)> {
+        pairs.into_iter()
+            .map(|(left, right, _)| (left, right, result.clone()))
+            .collect()
+    }
+
+    fn arithmetic() -> Vec<(Type, Type, Type)> {
+        let mut valid = numeric(Type::Integer, Type::Real);
+        valid.append(&mut sets());
+        valid
+    }
+
+    fn ordinals() -> Vec<(Type, Type, Type)> {
+        let mut valid = numeric(Type::Boolean, Type::Boolean);
+        for ty in [Type::Boolean, Type::Char, color(), string(5), string(3)] {
+            valid.push((ty.clone(), ty, Type::Boolean));
+        }
+        valid
+    }
+
+    #[test]
+    fn sum() {
+        check_matrix("+", &arithmetic(), |left, right| Sum { left, right, op: SumOp::Add });
+        check_matrix("-", &arithmetic(), |left, right| Sum { left, right, op: SumOp::Sub });
+    }
+
+    #[test]
+    fn product() {
+        check_matrix("*", &arithmetic(), |left, right| Product { left, right, op: ProdOp::Mul });
+        check_matrix("/", &numeric(Type::Real, Type::Real),
+                     |left, right| Product { left, right, op: ProdOp::RDiv });
+        let integers = vec![
+            (Type::Integer, Type::Integer, Type::Integer),
+            (Type::Integer, range(), Type::Integer),
+            (range(), Type::Integer, Type::Integer),
+            (range(), range(), Type::Integer),
+        ];
+        check_matrix("div", &integers, |left, right| Product { left, right, op: ProdOp::Div });
+        check_matrix("mod", &integers, |left, right| Product { left, right, op: ProdOp::Mod });
+    }
+
+    #[test]
+    fn constant_arithmetic_is_checked() {
+        let program = program();
+        let fault = |result: Result<i64, SemanticErrorKind>| match result {
+            Err(SemanticErrorKind::InvalidConstant(fault)) => fault,
+            result => panic!("{:?} is not a fault", result)
+        };
+        assert_eq!(Product { left: 7, right: -2, op: ProdOp::Div }.as_number(&program).ok(), Some(-3));
+        assert_eq!(fault(Product { left: 5, right: 0, op: ProdOp::Div }.as_number(&program)), "Division by zero");
+        assert_eq!(fault(Product { left: 5, right: 0, op: ProdOp::Mod }.as_number(&program)), "Division by zero");
+        assert_eq!(fault(Product { left: i64::MIN, right: -1, op: ProdOp::Div }.as_number(&program)),
+                   "Integer overflow");
+        assert_eq!(fault(Product { left: i64::MAX, right: 2, op: ProdOp::Mul }.as_number(&program)),
+                   "Integer overflow");
+        assert_eq!(fault(Sum { left: i64::MAX, right: 1, op: SumOp::Add }.as_number(&program)), "Integer overflow");
+        assert_eq!(fault(Sum { left: i64::MIN, right: 1, op: SumOp::Sub }.as_number(&program)), "Integer overflow");
+        assert_eq!(fault(Signed { operand: i64::MIN, op: SumOp::Sub }.as_number(&program)), "Integer overflow");
+    }
+
+    #[test]
+    fn logic() {
+        let booleans = vec![(Type::Boolean, Type::Boolean, Type::Boolean)];
+        check_matrix("and", &booleans, |left, right| Logic { left, right, op: LogicOp::And });
+        check_matrix("or", &booleans, |left, right| Logic { left, right, op: LogicOp::Or });
+    }
+
+    #[test]
+    fn comparison() {
+        let pointers = vec![
+            (Type::Pointer(Box::new(Type::Integer)), Type::Pointer(Box::new(Type::Integer)), Type::Boolean),
+            (Type::Pointer(Box::new(Type::Char)), Type::Pointer(Box::new(Type::Char)), Type::Boolean),
+            (Type::Pointer(Box::new(Type::Integer)), Type::Nil, Type::Boolean),
+            (Type::Nil, Type::Pointer(Box::new(Type::Integer)), Type::Boolean),
+            (Type::Pointer(Box::new(Type::Char)), Type::Nil, Type::Boolean),
+            (Type::Nil, Type::Pointer(Box::new(Type::Char)), Type::Boolean),
+            (Type::Nil, Type::Nil, Type::Boolean),
+        ];
+        let mut equality = ordinals();
+        equality.append(&mut with_result(sets(), Type::Boolean));
+        equality.append(&mut pointers.clone());
+        let mut inclusion = ordinals();
+        inclusion.append(&mut with_result(sets(), Type::Boolean));
+
+        check_matrix("=", &equality, |left, right| Comparison { left, right, op: CompOp::Eq });
+        check_matrix("<>", &equality, |left, right| Comparison { left, right, op: CompOp::Neq });
+        check_matrix("<=", &inclusion, |left, right| Comparison { left, right, op: CompOp::Lte });
+        check_matrix(">=", &inclusion, |left, right| Comparison { left, right, op: CompOp::Bge });
+        check_matrix("<", &ordinals(), |left, right| Comparison { left, right, op: CompOp::Lt });
+        check_matrix(">", &ordinals(), |left, right| Comparison { left, right, op: CompOp::Bg });
+    }
+
+    #[test]
+    fn membership() {
+        let integers = Type::SetOf(Box::new(Type::Integer));
+        let chars = Type::SetOf(Box::new(Type::Char));
+        let valid = vec![
+            (Type::Integer, integers.clone(), Type::Boolean),
+            (range(), integers, Type::Boolean),
+            (Type::Char, chars, Type::Boolean),
+        ];
+        check_matrix("in", &valid, |sample, set| In { sample, set });
+    }
+
+    #[test]
+    fn unary() {
+        let program = program();
+        for ty in operands() {
+            let negated = Signed { op: SumOp::Sub, operand: Typed(ty.clone()) };
+            let inverted = Not(Typed(ty.clone()));
+            assert_eq!(negated.is_valid(&program), ty.is_numeric(), "-{}", ty);
+            assert_eq!(inverted.is_valid(&program), ty == Type::Boolean, "not {}", ty);
+        }
+        let negated = Signed { op: SumOp::Sub, operand: Typed(range()) };
+        assert_eq!(negated.get_type(&program), Type::Integer);
+    }
+
+    #[test]
+    fn invalid_operands_are_silent() {
+        let program = program();
+        for ty in operands() {
+            let invalid = || Typed(Type::Invalid);
+            let operand = || Typed(ty.clone());
+            let expressions: Vec<Box<dyn Expression<Error = SemanticErrorKind>>> = vec![
+                Box::new(Sum { left: invalid(), right: operand(), op: SumOp::Add }),
+                Box::new(Product { left: operand(), right: invalid(), op: ProdOp::Div }),
+                Box::new(Comparison { left: invalid(), right: operand(), op: CompOp::Lt }),
+                Box::new(Logic { left: operand(), right: invalid(), op: LogicOp::Or }),
+                Box::new(In { sample: invalid(), set: operand() }),
+                Box::new(In { sample: operand(), set: invalid() }),
+                Box::new(Signed { op: SumOp::Sub, operand: invalid() }),
+                Box::new(Not(invalid())),
+                Box::new(vec![operand(), invalid()]),
+            ];
+            for expr in expressions {
+                assert!(expr.is_valid(&program), "{:?}", expr);
+                assert_eq!(expr.get_type(&program), Type::Invalid, "{:?}", expr);
+            }
+        }
+    }
+
+    #[test]
+    fn set_constructor() {
+        let program = program();
+        let set = vec![Typed(range()), Typed(Type::Integer)];
+        assert_eq!(set.get_type(&program), Type::SetOf(Box::new(Type::Integer)));
+        assert!(!vec![Typed(Type::Integer), Typed(Type::Char)].is_valid(&program));
+        assert!(!vec![Typed(Type::Real)].is_valid(&program));
+    }
+}
@@ -1,23 +1,202 @@
 use crate::ast::expression::{ExBox, Expression};
-use crate::ast::program::Program;
+use crate::ast::program::{Program, ScopeId, Symbol};
+use crate::ast::standard::Builtin;
 use crate::ast::types::Type;
-use crate::error::semantic_error::SemanticErrorKind;
+use crate::error::semantic_error::{SemanticError, SemanticErrorKind};
 
 #[derive(Debug)]
 pub enum VarRef<E: Expression = ExBox> {
-    Immediate(String),
+    Immediate(String, ScopeId),
     Field(Box<VarRef>, String),
-    Index(Box<VarRef>, Vec<E>)
+    Index(Box<VarRef>, Vec<E>),
+    /// The variable a pointer points to, `p^`
+    Deref(Box<VarRef>)
 }
 
-impl<E, Exp: Expression<Error = E>> Expression for VarRef<Exp> {
+impl<E: Expression> VarRef<E> {
+    /// The name of the variable this reference starts from
+    pub fn name(&self) -> &str {
+        match self {
+            VarRef::Immediate(name, _) => name,
+            VarRef::Field(base, _) | VarRef::Index(base, _) | VarRef::Deref(base) => base.name()
+        }
+    }
+
+    /// The scope the reference was written in
+    pub fn scope(&self) -> ScopeId {
+        match self {
+            VarRef::Immediate(_, scope) => *scope,
+            VarRef::Field(base, _) | VarRef::Index(base, _) | VarRef::Deref(base) => base.scope()
+        }
+    }
+}
+
+/// The type left after indexing `ty` with `count` subscripts, if it has
+/// that many dimensions
+fn indexed(ty: Type, count: usize) -> Option<Type> {
+    match ty {
+        _ if count == 0 => Some(ty),
+        Type::ArrayOf(dims, element) if count < dims.len() =>
+            Some(Type::ArrayOf(dims[count..].to_vec(), element)),
+        Type::ArrayOf(dims, element) => indexed(*element, count - dims.len()),
+        // a string is indexed by the position of a character
+        Type::String(_) => indexed(Type::Char, count - 1),
+        Type::Invalid => Some(Type::Invalid),
+        _ => None
+    }
+}
+
+/// The index types of the first `count` dimensions of `ty`, as far as it has them
+fn dimensions(ty: Type, count: usize) -> Vec<Type> {
+    match ty {
+        Type::ArrayOf(dims, _) if count <= dims.len() => dims[..count].to_vec(),
+        Type::ArrayOf(mut dims, element) => {
+            dims.append(&mut dimensions(*element, count - dims.len()));
+            dims
+        },
+        Type::String(_) => vec![Type::Integer],
+        _ => vec![]
+    }
+}
+
+impl Expression for VarRef {
     type Error = SemanticErrorKind;
 
-    fn get_type(&self, _program: &Program) -> Type {
-        todo!()
+    fn get_type(&self, program: &Program) -> Type {
+        match self {
+            VarRef::Immediate(name, scope) => match program.lookup(*scope, name) {
+                Some((_, Symbol::Variable(ty))) => ty.clone(),
+                Some((_, Symbol::Constant(constant))) => constant.get_type(program),
+                Some((_, Symbol::Subprogram(subprogram))) if subprogram.params.is_empty() =>
+                    subprogram.result.clone().unwrap_or(Type::Invalid),
+                Some(_) => Type::Invalid,
+                None => Builtin::lookup(name)
+                    .filter(|builtin| builtin.arity().contains(&0))
+                    .and_then(|builtin| builtin.result_type(&[]))
+                    .unwrap_or(Type::Invalid)
+            },
+            VarRef::Field(record, field) => match record.get_type(program) {
+                Type::Record(fields) => fields.get(field)
+                    .cloned()
+                    .unwrap_or(Type::Invalid),
+                _ => Type::Invalid
+            },
+            VarRef::Index(array, indices) => indexed(array.get_type(program), indices.len())
+                .unwrap_or(Type::Invalid),
+            VarRef::Deref(pointer) => match pointer.get_type(program) {
+                // the buffer variable of a file
+                Type::File(ty) => *ty,
+                ty => program.pointee(&ty)
+            }
+        }
+    }
+
+    fn validate(&self, program: &Program) -> Vec<SemanticErrorKind> {
+        match self {
+            VarRef::Immediate(name, scope) => match program.lookup(*scope, name) {
+                Some((_, Symbol::Variable(_))) | Some((_, Symbol::Constant(_))) => vec![],
+                Some((_, Symbol::Subprogram(subprogram))) if !subprogram.is_function() =>
+                    vec![SemanticErrorKind::NotAValue(name.clone())],
+                Some((_, Symbol::Subprogram(subprogram))) if !subprogram.params.is_empty() =>
+                    vec![SemanticErrorKind::ArgumentCount {
+                        name: name.clone(),
+                        expected: subprogram.params.len(),
+                        got: 0
+                    }],
+                Some((_, Symbol::Subprogram(_))) => vec![],
+                Some((_, Symbol::Type(_))) => vec![SemanticErrorKind::NotAValue(name.clone())],
+                None => match Builtin::lookup(name) {
+                    Some(builtin) if !builtin.is_function() => vec![SemanticErrorKind::NotAValue(name.clone())],
+                    Some(builtin) if !builtin.arity().contains(&0) => vec![SemanticErrorKind::ArgumentCount {
+                        name: name.clone(),
+                        expected: *builtin.arity().start(),
+                        got: 0
+                    }],
+                    Some(_) => vec![],
+                    None => vec![SemanticErrorKind::UndeclaredIdentifier(name.clone())]
+                }
+            },
+            VarRef::Field(record, field) => {
+                let mut out = record.validate(program);
+                if !out.is_empty() {
+                    return out;
+                }
+                let record = record.get_type(program);
+                match &record {
+                    Type::Record(fields) if fields.contains_key(field) => {},
+                    Type::Invalid => {},
+                    Type::Record(_) => out.push(SemanticErrorKind::UnknownField {
+                        record,
+                        field: field.clone()
+                    }),
+                    _ => out.push(SemanticErrorKind::UnsupportedOperation {
+                        operation: format!(".{}", field),
+                        operand: record
+                    })
+                }
+                out
+            },
+            VarRef::Index(array, indices) => {
+                let mut out = array.validate(program);
+                for index in indices {
+                    out.extend(index.validate(program).into_iter().map(Into::into));
+                }
+                if !out.is_empty() {
+                    return out;
+                }
+                let constant = match &**array {
+                    VarRef::Immediate(name, scope) => matches!(program.lookup(*scope, name), Some((_, Symbol::Constant(_)))),
+                    _ => false
+                };
+                let array = array.get_type(program);
+                // a string constant is a value, not a variable holding characters
+                if constant || indexed(array.clone(), indices.len()).is_none() {
+                    out.push(SemanticErrorKind::UnsupportedOperation {
+                        operation: "[]".to_string(),
+                        operand: array
+                    });
+                    return out;
+                }
+                for (index, dim) in indices.iter().zip(dimensions(array, indices.len())) {
+                    let ty = index.get_type(program);
+                    if ty != Type::Invalid && dim != Type::Invalid && !dim.is_compatible(&ty) {
+                        out.push(SemanticErrorKind::Located(Box::new(SemanticError::new(index.range.clone(),
+                            SemanticErrorKind::TypeError {
+                                expected: vec![dim],
+                                got: ty
+                            }))));
+                    }
+                }
+                out
+            },
+            VarRef::Deref(pointer) => {
+                let out = pointer.validate(program);
+                if !out.is_empty() {
+                    return out;
+                }
+                match pointer.get_type(program) {
+                    Type::Pointer(_) | Type::File(_) | Type::Invalid => vec![],
+                    ty => vec![SemanticErrorKind::UnsupportedOperation {
+                        operation: "^".to_string(),
+                        operand: ty
+                    }]
+                }
+            }
+        }
     }
 
     fn as_number(&self, program: &Program) -> Result<i64, Self::Error> {
-        Err(SemanticErrorKind::InvalidLimit)
+        match self {
+            VarRef::Immediate(name, scope) => match program.lookup(*scope, name) {
+                Some((_, Symbol::Constant(constant))) => constant.as_number(program)
+                    .map_err(Into::into),
+                _ => Err(SemanticErrorKind::InvalidLimit)
+            },
+            _ => Err(SemanticErrorKind::InvalidLimit)
+        }
+    }
+
+    fn as_variable(&self) -> Option<&VarRef> {
+        Some(self)
     }
-}
\ No newline at end of file
+}
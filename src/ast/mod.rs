@@ -1,3 +1,6 @@
 pub mod types;
 pub mod expression;
 pub mod program;
+pub mod statement;
+pub mod subprogram;
+pub mod standard;
@@ -1,34 +1,1131 @@
 use std::collections::HashMap;
-use crate::grammar::ProgramParser;
-use crate::ast::expression::{ExBox, Expression, Invalid};
+use std::ops::Range;
+use crate::grammar::{ProgramParser, ReferenceParser};
+use crate::ast::expression::{ExBox, Expression, Node};
+use crate::ast::expression::variables::VarRef;
+use crate::ast::expression::constants::Enumerator;
+use crate::ast::standard::Builtin;
+use crate::ast::statement::Statement;
+use crate::ast::subprogram::{Parameter, Subprogram};
 use crate::ast::types::Type;
 use crate::error::parse_error::ParsingError;
-use crate::error::PositionBuilder;
+use crate::error::{PositionBuilder, Throwable};
+use crate::error::semantic_error::{SemanticError, SemanticErrorKind};
 use crate::lexer::{Lexer, Token};
 
-pub struct Program {
-    pub name: String,
+pub type ScopeId = usize;
+
+#[derive(Debug, Default)]
+pub struct Scope {
+    pub parent: Option<ScopeId>,
     pub constants: HashMap<String, ExBox>,
-    pub globals: HashMap<String, Type>,
+    pub variables: HashMap<String, Type>,
     pub types: HashMap<String, Type>,
+    pub subprograms: HashMap<String, Subprogram>,
+    /// Where each name of the scope was declared
+    pub declarations: HashMap<String, Range<usize>>
+}
+
+impl Scope {
+    fn new(parent: Option<ScopeId>) -> Scope {
+        Scope {
+            parent,
+            ..Scope::default()
+        }
+    }
+}
+
+pub enum Symbol<'a> {
+    Constant(&'a ExBox),
+    Variable(&'a Type),
+    Type(&'a Type),
+    Subprogram(&'a Subprogram)
+}
+
+/// The run-time checks compiled into a program. Division by zero and `nil`
+/// pointers are always checked.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Checks {
+    /// `{$R+}`: array indices, assignments to subranges, `chr`, `succ` and
+    /// `pred` stay within their bounds
+    pub range: bool,
+    /// `{$Q+}`: integer arithmetic does not overflow
+    pub overflow: bool
+}
+
+impl Default for Checks {
+    fn default() -> Checks {
+        Checks {
+            range: true,
+            overflow: false
+        }
+    }
+}
+
+impl Checks {
+    /// Applies switches written as in a directive, such as `R-,Q+`, telling
+    /// whether they were all understood; unknown switches are left alone
+    pub fn apply(&mut self, switches: &str) -> bool {
+        let mut understood = true;
+        for switch in switches.split(',').map(str::trim) {
+            let on = match switch.get(1..) {
+                Some("+") => true,
+                Some("-") => false,
+                _ => {
+                    understood = false;
+                    continue;
+                }
+            };
+            match &switch[..1] {
+                "R" | "r" => self.range = on,
+                "Q" | "q" => self.overflow = on,
+                _ => understood = false
+            }
+        }
+        understood
+    }
+}
+
+/// The Pascal a program is written in
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum Dialect {
+    /// ISO 7185 standard Pascal
+    #[default]
+    Iso,
+    /// Standard Pascal with the `string` type and its functions of Turbo
+    /// and Free Pascal
+    Turbo
+}
+
+impl Dialect {
+    pub fn parse(name: &str) -> Option<Dialect> {
+        match name {
+            "iso" => Some(Dialect::Iso),
+            "turbo" => Some(Dialect::Turbo),
+            _ => None
+        }
+    }
+}
+
+pub struct Program {
+    pub name: String,
+    /// The files named in the heading, `input` and `output` included
+    pub parameters: Vec<(String, Range<usize>)>,
+    pub scopes: Vec<Scope>,
+    /// The scope declarations are currently added to while parsing
+    pub scope: ScopeId,
+    pub body: Vec<Statement>,
+    /// Errors found while the declarations were being parsed
+    pub errors: Vec<SemanticError>,
     pub positioner: PositionBuilder,
+    /// The checks in effect where no directive says otherwise
+    pub checks: Checks,
+    /// The `{$...}` directives of the source, with where they are
+    pub directives: Vec<(usize, String)>,
+    pub dialect: Dialect,
+    /// The names the pointer types of the type definitions being parsed
+    /// point to that are not declared yet, which the definitions after
+    /// them must declare; none outside of type definitions
+    forward: Option<Vec<(String, Range<usize>)>>
 }
 
 impl Program {
+    /// The scope of the predeclared identifiers, which the program may redefine
+    pub const STANDARD: ScopeId = 0;
+    pub const GLOBAL: ScopeId = 1;
+
     pub fn new (src: String) -> (Self, Vec<ParsingError<Token>>) {
+        Program::with_dialect(src, Dialect::Iso)
+    }
+
+    pub fn with_dialect(src: String, dialect: Dialect) -> (Self, Vec<ParsingError<Token>>) {
+        let mut standard = Scope::new(None);
+        standard.types = [
+            ("integer", Type::Integer),
+            ("real", Type::Real),
+            ("boolean", Type::Boolean),
+            ("char", Type::Char),
+            ("text", Type::File(Box::new(Type::Char)))
+        ].iter()
+            .map(|(name, ty)| (name.to_string(), ty.clone()))
+            .collect();
+        if dialect == Dialect::Turbo {
+            standard.types.insert("string".to_string(), Type::String(255));
+        }
+        standard.variables = ["input", "output"].iter()
+            .map(|name| (name.to_string(), Type::File(Box::new(Type::Char))))
+            .collect();
         let mut program = Program {
             name: "".to_string(),
-            constants: HashMap::new(),
-            globals: HashMap::new(),
-            types: HashMap::new(),
+            parameters: vec![],
+            scopes: vec![standard, Scope::new(Some(Program::STANDARD))],
+            scope: Program::GLOBAL,
+            body: vec![],
+            errors: vec![],
             positioner: PositionBuilder::new(src.clone()),
+            checks: Checks::default(),
+            directives: vec![],
+            dialect,
+            forward: None
         };
         let mut errors = vec![];
+        let mut lexer = Lexer::new(&src[..]);
         if let Err(err) = ProgramParser::new()
-            .parse(&mut program, Lexer::new(&src[..])) {
+            .parse(&mut program, &mut lexer) {
             errors.push(err.into())
         };
+        program.directives = lexer.directives;
         (program, errors)
     }
+
+    /// The checks in effect at `offset` in the source, each directive
+    /// switching them from where it is on
+    pub fn checks_at(&self, offset: usize) -> Checks {
+        let mut checks = self.checks;
+        for (_, switches) in self.directives.iter().take_while(|(start, _)| *start < offset) {
+            checks.apply(switches);
+        }
+        checks
+    }
+
+    /// Parses a variable such as `a[i].next^` as if it were written in
+    /// `scope`, for the debugger; it still has to be validated
+    pub fn reference(&self, text: &str, scope: ScopeId) -> Result<ExBox, ParsingError<Token>> {
+        // the variable is only parsed, so none of the declarations are needed
+        let mut scratch = Program {
+            name: self.name.clone(),
+            parameters: vec![],
+            scopes: vec![],
+            scope,
+            body: vec![],
+            errors: vec![],
+            positioner: PositionBuilder::new(text.to_string()),
+            checks: self.checks,
+            directives: vec![],
+            dialect: self.dialect,
+            forward: None
+        };
+        ReferenceParser::new()
+            .parse(&mut scratch, Lexer::new(text))
+            .map_err(|err| err.into())
+    }
+
+    /// Finds the closest declaration of `name` visible from `scope`, along
+    /// with the scope it was declared in
+    pub fn lookup(&self, scope: ScopeId, name: &str) -> Option<(ScopeId, Symbol<'_>)> {
+        let mut current = Some(scope);
+        while let Some(id) = current {
+            let scope = &self.scopes[id];
+            let symbol = scope.constants.get(name).map(Symbol::Constant)
+                .or_else(|| scope.variables.get(name).map(Symbol::Variable))
+                .or_else(|| scope.types.get(name).map(Symbol::Type))
+                .or_else(|| scope.subprograms.get(name).map(Symbol::Subprogram));
+            if let Some(symbol) = symbol {
+                return Some((id, symbol));
+            }
+            current = scope.parent;
+        }
+        None
+    }
+
+    /// Whether `inner` is `outer` or nested in it
+    pub fn encloses(&self, outer: ScopeId, inner: ScopeId) -> bool {
+        let mut current = Some(inner);
+        while let Some(id) = current {
+            if id == outer {
+                return true;
+            }
+            current = self.scopes[id].parent;
+        }
+        false
+    }
+
+    /// Records that `name` is declared at `range` in the current scope,
+    /// reporting it if the scope already declares it
+    pub fn declare(&mut self, name: &str, range: Range<usize>) {
+        let declarations = &mut self.scopes[self.scope].declarations;
+        match declarations.get(name) {
+            Some(first) => self.errors.push(SemanticError::new(range,
+                SemanticErrorKind::Redeclaration {
+                    name: name.to_string(),
+                    first: first.clone()
+                })),
+            None => {
+                declarations.insert(name.to_string(), range);
+            }
+        }
+    }
+
+    pub fn header(&mut self, name: String, parameters: Vec<(String, Range<usize>)>) {
+        self.name = name;
+        self.parameters = parameters;
+    }
+
+    /// Declares a constant, reporting its value if working it out divides by
+    /// zero or overflows
+    pub fn declare_constant(&mut self, name: String, range: Range<usize>, value: ExBox) {
+        self.declare(&name, range);
+        let fault = value.as_number(self).err()
+            .filter(|err| matches!(err.kind, SemanticErrorKind::InvalidConstant(_)));
+        self.errors.extend(fault);
+        self.scopes[self.scope].constants.entry(name).or_insert(value);
+    }
+
+    pub fn declare_type(&mut self, name: String, range: Range<usize>, ty: Type) {
+        self.declare(&name, range);
+        self.scopes[self.scope].types.entry(name).or_insert(ty);
+    }
+
+    pub fn declare_variable(&mut self, name: String, range: Range<usize>, ty: Type) {
+        self.declare(&name, range);
+        self.scopes[self.scope].variables.entry(name).or_insert(ty);
+    }
+
+    /// Declares a subprogram in the current scope and enters its own scope,
+    /// where its parameters are declared
+    pub fn begin_subprogram(&mut self,
+                            name: String,
+                            params: Vec<(Parameter, Range<usize>)>,
+                            result: Option<Type>,
+                            range: Range<usize>) {
+        self.declare(&name, range.clone());
+        let parent = self.scope;
+        self.scopes.push(Scope::new(Some(parent)));
+        self.scope = self.scopes.len() - 1;
+        for (param, range) in &params {
+            self.declare_variable(param.name.clone(), range.clone(), param.r#type.clone());
+            // a file cannot be copied, so it is only passed by reference
+            if !param.by_ref && param.r#type.has_file() {
+                self.errors.push(SemanticError::new(range.clone(), SemanticErrorKind::UnsupportedOperation {
+                    operation: "value parameter".to_string(),
+                    operand: param.r#type.clone()
+                }));
+            }
+        }
+        if let Some(ty) = result.as_ref().filter(|ty| ty.has_file()) {
+            self.errors.push(SemanticError::new(range.clone(), SemanticErrorKind::UnsupportedOperation {
+                operation: "function result".to_string(),
+                operand: ty.clone()
+            }));
+        }
+        let subprogram = Subprogram {
+            name: name.clone(),
+            params: params.into_iter()
+                .map(|(param, _)| param)
+                .collect(),
+            result,
+            scope: self.scope,
+            body: vec![],
+            range
+        };
+        self.scopes[parent].subprograms.entry(name).or_insert(subprogram);
+    }
+
+    /// Gives the subprogram whose scope is the current one its body, and
+    /// returns to the enclosing scope
+    pub fn end_subprogram(&mut self, body: Vec<Statement>) {
+        let scope = self.scope;
+        let parent = self.scopes[scope].parent.unwrap_or(Program::GLOBAL);
+        if let Some(subprogram) = self.scopes[parent].subprograms.values_mut()
+            .find(|subprogram| subprogram.scope == scope) {
+            subprogram.body = body;
+        }
+        self.scope = parent;
+    }
+
+    /// Builds a record type, reporting fields declared more than once
+    pub fn record_type(&mut self, fields: Vec<(String, Range<usize>, Type)>) -> Type {
+        let mut declarations: HashMap<String, Range<usize>> = HashMap::new();
+        let mut record = HashMap::new();
+        for (name, range, ty) in fields {
+            match declarations.get(&name) {
+                Some(first) => self.errors.push(SemanticError::new(range,
+                    SemanticErrorKind::Redeclaration {
+                        name,
+                        first: first.clone()
+                    })),
+                None => {
+                    declarations.insert(name.clone(), range);
+                    record.insert(name, ty);
+                }
+            }
+        }
+        Type::Record(record)
+    }
+
+    /// Resolves a type name, reporting it if it has not been declared
+    pub fn lookup_type(&mut self, name: &str, range: Range<usize>) -> Type {
+        match self.lookup(self.scope, name) {
+            Some((_, Symbol::Type(ty))) => ty.clone(),
+            _ => {
+                let kind = if name == "string" && self.dialect == Dialect::Iso {
+                    SemanticErrorKind::Extension(name.to_string())
+                } else {
+                    SemanticErrorKind::UndeclaredIdentifier(name.to_string())
+                };
+                self.errors.push(SemanticError::new(range, kind));
+                Type::Invalid
+            }
+        }
+    }
+
+    /// Builds `^name`. A pointer to a type of the program names it, so that
+    /// a record can point to its own type, and in type definitions the type
+    /// may be declared after the pointer type, once they are all parsed.
+    pub fn pointer_type(&mut self, name: String, range: Range<usize>) -> Type {
+        let declared = match self.lookup(self.scope, &name) {
+            Some((scope, Symbol::Type(ty))) => Some((scope, ty.clone())),
+            _ => None
+        };
+        match (declared, &mut self.forward) {
+            (Some((Program::STANDARD, ty)), _) => Type::Pointer(Box::new(ty)),
+            (Some((scope, _)), _) => Type::Pointer(Box::new(Type::Named(name, scope))),
+            (None, Some(forward)) => {
+                forward.push((name.clone(), range));
+                Type::Pointer(Box::new(Type::Named(name, self.scope)))
+            },
+            (None, None) => match self.lookup_type(&name, range) {
+                Type::Invalid => Type::Invalid,
+                ty => Type::Pointer(Box::new(ty))
+            }
+        }
+    }
+
+    /// Starts type definitions, whose pointer types may name the types
+    /// defined after them
+    pub fn begin_types(&mut self) {
+        self.forward = Some(vec![]);
+    }
+
+    /// Reports the pointer types of the type definitions just parsed that
+    /// name a type they do not declare
+    pub fn end_types(&mut self) {
+        for (name, range) in self.forward.take().unwrap_or_default() {
+            if !self.scopes[self.scope].types.contains_key(&name) {
+                self.errors.push(SemanticError::new(range, SemanticErrorKind::UndeclaredIdentifier(name)));
+            }
+        }
+    }
+
+    /// The type a pointer of type `pointer` points to, the type it names
+    /// looked up
+    pub fn pointee(&self, pointer: &Type) -> Type {
+        match pointer {
+            Type::Pointer(ty) => match &**ty {
+                Type::Named(name, scope) => self.scopes[*scope].types.get(name).cloned().unwrap_or(Type::Invalid),
+                ty => ty.clone()
+            },
+            _ => Type::Invalid
+        }
+    }
+
+    /// Builds `string[size]`, reporting sizes a string cannot have
+    pub fn string_type(&mut self, name: &str, size: (isize, Type), range: Range<usize>) -> Type {
+        let kind = match (self.lookup_type(name, range.clone()), size) {
+            (Type::Invalid, _) | (_, (_, Type::Invalid)) => return Type::Invalid,
+            (Type::String(_), (_, ty)) if !ty.is_integer() => SemanticErrorKind::TypeError {
+                expected: vec![Type::Integer],
+                got: ty.host()
+            },
+            (Type::String(_), (size, _)) if (1..=255).contains(&size) => return Type::String(size as usize),
+            (Type::String(_), (size, _)) => SemanticErrorKind::StringSize(size),
+            (ty, _) => SemanticErrorKind::UnsupportedOperation {
+                operation: "[...]".to_string(),
+                operand: ty
+            }
+        };
+        self.errors.push(SemanticError::new(range, kind));
+        Type::Invalid
+    }
+
+    /// Builds an enumerated type, declaring each of its literals as a constant
+    /// standing for its ordinal
+    pub fn enum_type(&mut self, literals: Vec<(String, Range<usize>)>) -> Type {
+        let ty = Type::Enum(literals.iter()
+            .map(|(name, _)| name.clone())
+            .collect());
+        for (ordinal, (name, range)) in literals.into_iter().enumerate() {
+            let literal = Enumerator {
+                r#type: ty.clone(),
+                ordinal: ordinal as i64
+            };
+            self.declare_constant(name, range.clone(), ExBox::new(literal, range));
+        }
+        ty
+    }
+
+    /// Builds the subrange `low..high` of the type both limits share,
+    /// reporting limits of different types or in the wrong order
+    pub fn subrange(&mut self, low: (isize, Type), high: (isize, Type), range: Range<usize>) -> Type {
+        let kind = match (&low.1, &high.1) {
+            (Type::Invalid, _) | (_, Type::Invalid) => return Type::Invalid,
+            (left, right) if !left.is_compatible(right) => SemanticErrorKind::TypeError {
+                expected: vec![left.host()],
+                got: right.host()
+            },
+            _ if low.0 > high.0 => SemanticErrorKind::EmptyRange {
+                low: low.1.ordinal_name(low.0),
+                high: high.1.ordinal_name(high.0)
+            },
+            _ => return Type::Range(Box::new(low.1.host()), low.0..high.0)
+        };
+        self.errors.push(SemanticError::new(range, kind));
+        Type::Invalid
+    }
+
+    /// Builds `file of ty`, whose elements are written one to a line, so
+    /// that they have to be values `read` can parse
+    pub fn file_type(&mut self, ty: Type, range: Range<usize>) -> Type {
+        if [Type::Integer, Type::Real, Type::Char].contains(&ty.host()) {
+            Type::File(Box::new(ty))
+        } else {
+            if ty != Type::Invalid {
+                self.errors.push(SemanticError::new(range, SemanticErrorKind::FileComponent(ty)));
+            }
+            Type::Invalid
+        }
+    }
+
+    /// Checks that `ty` can index the dimension of an array
+    pub fn index_type(&mut self, ty: Type, range: Range<usize>) -> Type {
+        if ty.is_ordinal() || ty == Type::Invalid {
+            ty
+        } else {
+            self.errors.push(SemanticError::new(range, SemanticErrorKind::UnsupportedOperation {
+                operation: "array [...]".to_string(),
+                operand: ty
+            }));
+            Type::Invalid
+        }
+    }
+
+    /// Evaluates a constant used as a limit, with its type, reporting it if it
+    /// cannot be
+    pub fn lookup_limit(&mut self, name: &str, range: Range<usize>) -> (isize, Type) {
+        let limit = match self.lookup(self.scope, name) {
+            // a constant that cannot be worked out is reported where it is
+            // declared
+            Some((_, Symbol::Constant(constant))) => match constant.as_number(self) {
+                Ok(value) => Ok((value as isize, constant.get_type(self))),
+                Err(SemanticError { kind: SemanticErrorKind::InvalidConstant(_), .. }) =>
+                    return (0, Type::Invalid),
+                Err(err) => Err(SemanticError::new(range.clone(), err.kind))
+            },
+            _ => Err(SemanticError::new(range.clone(),
+                SemanticErrorKind::UndeclaredIdentifier(name.to_string())))
+        };
+        limit.unwrap_or_else(|err| {
+            self.errors.push(err);
+            (0, Type::Invalid)
+        })
+    }
+
+    /// Checks that the parameters of the program are declared as files, as
+    /// the standard input and output are
+    fn validate_parameters(&self) -> Vec<SemanticError> {
+        let mut out = vec![];
+        let mut seen: HashMap<&str, &Range<usize>> = HashMap::new();
+        for (name, range) in &self.parameters {
+            if let Some(first) = seen.insert(name, range) {
+                out.push(SemanticError::new(range.clone(), SemanticErrorKind::Redeclaration {
+                    name: name.clone(),
+                    first: first.clone()
+                }));
+                continue;
+            }
+            let kind = match self.lookup(Program::GLOBAL, name) {
+                // a variable of a type that is not valid already has its error
+                Some((_, Symbol::Variable(Type::File(_) | Type::Invalid))) => continue,
+                Some((_, Symbol::Variable(ty))) => SemanticErrorKind::TypeError {
+                    expected: vec![Type::File(Box::new(Type::Char))],
+                    got: ty.clone()
+                },
+                _ => SemanticErrorKind::UndeclaredIdentifier(name.clone())
+            };
+            out.push(SemanticError::new(range.clone(), kind));
+        }
+        out
+    }
+
+    /// Whether the program uses files other than by reading and writing
+    /// the standard input and output implicitly, which only the interpreter
+    /// runs
+    pub fn uses_files(&self) -> bool {
+        let holds_file = |ty: &Type| match ty {
+            Type::Pointer(ty) => ty.has_file(),
+            ty => ty.has_file()
+        };
+        self.parameters.iter().any(|(name, _)| name != "input" && name != "output")
+            || self.scopes.iter().skip(Program::GLOBAL)
+                .any(|scope| scope.variables.values().any(holds_file) || scope.types.values().any(holds_file))
+            || self.scopes.iter()
+                .flat_map(|scope| scope.subprograms.values())
+                .flat_map(|subprogram| subprogram.body.iter())
+                .chain(self.body.iter())
+                .flat_map(Statement::expressions)
+                .any(|expr| self.mentions_file(expr))
+    }
+
+    /// Checks that the files bound to the parameters of the program, by
+    /// name, are bound to parameters and that every parameter but `input`
+    /// and `output` has one
+    pub fn check_bindings(&self, bindings: &[(String, String)]) -> Result<(), String> {
+        let parameter = |name: &str| self.parameters.iter().any(|(parameter, _)| parameter == name);
+        if let Some((name, _)) = bindings.iter().find(|(name, _)| !parameter(name)) {
+            return Err(format!("--bind names '{}', which is not a parameter of program {}", name, self.name));
+        }
+        let bound = |name: &str| bindings.iter().any(|(binding, _)| binding == name);
+        match self.parameters.iter().find(|(name, _)| name != "input" && name != "output" && !bound(name)) {
+            Some((name, _)) => Err(format!("the parameter '{}' needs a file, given with --bind {}=<file>", name, name)),
+            None => Ok(())
+        }
+    }
+
+    /// Whether a file is passed to a subprogram or has its buffer used
+    /// anywhere in `expr`
+    fn mentions_file(&self, expr: &ExBox) -> bool {
+        match expr.node() {
+            Node::Variable(variable) => self.variable_mentions_file(variable),
+            Node::Call(call) => call.args.iter()
+                .any(|arg| arg.get_type(self).has_file() || self.mentions_file(arg)),
+            Node::Set(members) => members.iter().any(|member| self.mentions_file(member)),
+            Node::In(op) => self.mentions_file(&op.sample) || self.mentions_file(&op.set),
+            Node::Comparison(op) => self.mentions_file(&op.left) || self.mentions_file(&op.right),
+            Node::Sum(op) => self.mentions_file(&op.left) || self.mentions_file(&op.right),
+            Node::Product(op) => self.mentions_file(&op.left) || self.mentions_file(&op.right),
+            Node::Logic(op) => self.mentions_file(&op.left) || self.mentions_file(&op.right),
+            Node::Signed(op) => self.mentions_file(&op.operand),
+            Node::Not(op) => self.mentions_file(&op.0),
+            _ => false
+        }
+    }
+
+    fn variable_mentions_file(&self, variable: &VarRef) -> bool {
+        match variable {
+            VarRef::Immediate(..) => false,
+            VarRef::Field(record, _) => self.variable_mentions_file(record),
+            VarRef::Index(array, indices) => self.variable_mentions_file(array)
+                || indices.iter().any(|index| self.mentions_file(index)),
+            VarRef::Deref(inner) => matches!(inner.get_type(self), Type::File(_)) || self.variable_mentions_file(inner)
+        }
+    }
+
+    /// Whether the program uses the strings of Turbo Pascal, which only the
+    /// interpreter runs: a string type, a string subprogram, or `+` and the
+    /// comparisons applied to other text than two equally long strings
+    pub fn uses_strings(&self) -> bool {
+        self.dialect == Dialect::Turbo && (self.scopes.iter().skip(Program::GLOBAL)
+            .any(|scope| scope.variables.values().any(Type::has_string) || scope.types.values().any(Type::has_string)
+                || scope.subprograms.values().any(|subprogram| subprogram.result.as_ref().is_some_and(Type::has_string)))
+            || self.scopes.iter()
+                .flat_map(|scope| scope.subprograms.values())
+                .flat_map(|subprogram| subprogram.body.iter())
+                .chain(self.body.iter())
+                .flat_map(Statement::expressions)
+                .any(|expr| self.mentions_string(expr)))
+    }
+
+    /// Whether anything in `expr` needs strings, as `uses_strings` tells
+    fn mentions_string(&self, expr: &ExBox) -> bool {
+        if expr.get_type(self).has_string() {
+            return true;
+        }
+        match expr.node() {
+            Node::Variable(variable) => self.variable_mentions_string(variable),
+            Node::Call(call) => (self.lookup(call.scope, &call.name).is_none()
+                && Builtin::lookup(&call.name).is_some_and(|builtin| builtin.is_extension()))
+                || call.args.iter().any(|arg| self.mentions_string(arg)),
+            Node::Set(members) => members.iter().any(|member| self.mentions_string(member)),
+            Node::In(op) => self.mentions_string(&op.sample) || self.mentions_string(&op.set),
+            Node::Comparison(op) => {
+                let (left, right) = (op.left.get_type(self), op.right.get_type(self));
+                (left.is_textual() && right.is_textual() && left != right)
+                    || self.mentions_string(&op.left) || self.mentions_string(&op.right)
+            },
+            Node::Sum(op) => self.mentions_string(&op.left) || self.mentions_string(&op.right),
+            Node::Product(op) => self.mentions_string(&op.left) || self.mentions_string(&op.right),
+            Node::Logic(op) => self.mentions_string(&op.left) || self.mentions_string(&op.right),
+            Node::Signed(op) => self.mentions_string(&op.operand),
+            Node::Not(op) => self.mentions_string(&op.0),
+            _ => false
+        }
+    }
+
+    fn variable_mentions_string(&self, variable: &VarRef) -> bool {
+        match variable {
+            VarRef::Immediate(..) => false,
+            VarRef::Field(record, _) | VarRef::Deref(record) => self.variable_mentions_string(record),
+            VarRef::Index(array, indices) => self.variable_mentions_string(array)
+                || indices.iter().any(|index| self.mentions_string(index))
+        }
+    }
+
+    /// Whether the program declares a set of ordinals outside 0..255, or
+    /// builds a set of constants outside it, which only the interpreter and
+    /// the p-code machine hold
+    pub fn uses_wide_sets(&self) -> bool {
+        self.scopes.iter().skip(Program::GLOBAL)
+            .any(|scope| scope.variables.values().any(Type::has_wide_set) || scope.types.values().any(Type::has_wide_set))
+            || self.scopes.iter()
+                .flat_map(|scope| scope.subprograms.values())
+                .flat_map(|subprogram| subprogram.body.iter())
+                .chain(self.body.iter())
+                .flat_map(Statement::expressions)
+                .any(|expr| self.mentions_wide_set(expr))
+    }
+
+    /// Whether a set of constants outside 0..255 is built anywhere in `expr`
+    fn mentions_wide_set(&self, expr: &ExBox) -> bool {
+        match expr.node() {
+            Node::Variable(variable) => self.variable_mentions_wide_set(variable),
+            Node::Call(call) => call.args.iter().any(|arg| self.mentions_wide_set(arg)),
+            Node::Set(members) => members.iter().any(|member| {
+                member.as_number(self).is_ok_and(|value| !(0..=255).contains(&value)) || self.mentions_wide_set(member)
+            }),
+            Node::In(op) => self.mentions_wide_set(&op.sample) || self.mentions_wide_set(&op.set),
+            Node::Comparison(op) => self.mentions_wide_set(&op.left) || self.mentions_wide_set(&op.right),
+            Node::Sum(op) => self.mentions_wide_set(&op.left) || self.mentions_wide_set(&op.right),
+            Node::Product(op) => self.mentions_wide_set(&op.left) || self.mentions_wide_set(&op.right),
+            Node::Logic(op) => self.mentions_wide_set(&op.left) || self.mentions_wide_set(&op.right),
+            Node::Signed(op) => self.mentions_wide_set(&op.operand),
+            Node::Not(op) => self.mentions_wide_set(&op.0),
+            _ => false
+        }
+    }
+
+    fn variable_mentions_wide_set(&self, variable: &VarRef) -> bool {
+        match variable {
+            VarRef::Immediate(..) => false,
+            VarRef::Field(record, _) | VarRef::Deref(record) => self.variable_mentions_wide_set(record),
+            VarRef::Index(array, indices) => self.variable_mentions_wide_set(array)
+                || indices.iter().any(|index| self.mentions_wide_set(index))
+        }
+    }
+
+    /// Walks every declaration and statement, collecting their semantic errors
+    pub fn validate(&self) -> Vec<SemanticError> {
+        let mut out = self.errors.clone();
+        out.append(&mut self.validate_parameters());
+        for scope in &self.scopes {
+            for constant in scope.constants.values() {
+                out.append(&mut constant.validate(self));
+            }
+            for subprogram in scope.subprograms.values() {
+                for statement in &subprogram.body {
+                    out.append(&mut statement.validate(self));
+                }
+            }
+        }
+        for statement in &self.body {
+            out.append(&mut statement.validate(self));
+        }
+        out
+    }
+
+    /// The parse errors `errors` and the semantic errors of the program
+    /// together, in the order they are found in the source
+    pub fn diagnostics(&self, errors: Vec<ParsingError<Token>>) -> Vec<Box<dyn Throwable>> {
+        let mut errors = errors.into_iter()
+            .map(|err| Box::new(err) as Box<dyn Throwable>)
+            .chain(self.validate().into_iter()
+                .map(|err| Box::new(err) as Box<dyn Throwable>))
+            .collect::<Vec<Box<dyn Throwable>>>();
+        errors.sort_by_cached_key(|err| {
+            let position = err.position(&self.positioner);
+            (position.line_no, position.offset.start)
+        });
+        errors
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DECLARATIONS: &str = "
+        program faults;
+        limit = 10;
+        type vec = array [1..limit] of integer;
+        type point = record x: integer; y: real end;
+        var i, j: integer;
+            r: real;
+            v: vec;
+            p: point;
+            c: char;
+            b: boolean;
+    ";
+
+    /// Parses `body` after the shared declarations and returns how many
+    /// diagnostics it produces
+    fn diagnostics(body: &str) -> usize {
+        let (program, errors) = Program::new(format!("{}begin\n{}\nend.", DECLARATIONS, body));
+        assert!(errors.is_empty(), "{:?}", errors);
+        program.validate().len()
+    }
+
+    #[test]
+    fn clean_program() {
+        assert_eq!(diagnostics("
+            i := 1; r := i * 2.5; c := 'x';
+            for i := 1 to limit do v[i] := i div 2;
+            while b and (i < limit) do i := i + 1;
+            p.y := p.x / 2;
+            b := i in [1, 2, 3]
+        "), 0);
+    }
+
+    #[test]
+    fn one_diagnostic_per_fault() {
+        let cases = [
+            ("i := 1 + 'a'", 1),
+            ("i := (1 + 'a') * 2 - 3", 1),
+            ("b := not (1 + 'a') or (c < 2)", 2),
+            ("i := -(k + 1) div 2", 1),
+            ("i := v[k] + v[c]", 2),
+            ("i := v['a' + 1]", 1),
+            ("r := p.z * 2", 1),
+            ("i := q.x + q[1]", 2),
+            ("b := (i + true) in [1, 2]", 1),
+            ("b := i in [1, 'a' + 2]", 1),
+            ("if k + 1 > 2 then i := c", 2),
+            ("while (k = 1) and (r div 2 = 0) do i := i", 2),
+            ("for i := k to limit do j := r", 2),
+            ("for r := 1 to 2 do ;", 1),
+            ("repeat k := 1 until k", 2),
+            ("case k of 1: i := 0; 2: i := 'a' end", 2),
+            ("limit := i + c", 1),
+            ("x := 1", 1),
+        ];
+        for (body, faults) in cases.iter() {
+            assert_eq!(diagnostics(body), *faults, "{}", body);
+        }
+    }
+
+    #[test]
+    fn standard_subprograms() {
+        assert_eq!(diagnostics("
+            i := abs(-3) + sqr(i) + trunc(sqrt(r) * ln(2)) + round(exp(r));
+            r := abs(r) + sqr(r) + sin(r) + cos(1) + arctan(r);
+            b := odd(i) and not eof and eoln;
+            c := chr(ord(c) + 1);
+            read(i, r, c); readln;
+            write('x', i, r, c, b); writeln
+        "), 0);
+        let cases = [
+            ("i := sqr(r)", 1),
+            ("write(v)", 1),
+            ("read(i + 1)", 1),
+            ("write", 1),
+            ("new(i)", 1),
+            ("i := writeln", 1),
+            ("i := ord", 1),
+            ("eof := true", 1),
+            ("b := odd(r) or odd(c)", 2),
+            ("writeln(abs('c'), chr(r))", 2),
+        ];
+        for (body, faults) in cases.iter() {
+            assert_eq!(diagnostics(body), *faults, "{}", body);
+        }
+    }
+
+    #[test]
+    fn enumerated_types() {
+        let diagnostics = |body: &str| {
+            let (program, errors) = Program::new(format!("
+                program enums;
+                type color = (red, green, blue);
+                     fruit = (apple, pear);
+                     warm = red..green;
+                     table = array [color, 1..3] of integer;
+                var c: color; w: warm; f: fruit; t: table; i: integer;
+                begin {} end.
+            ", body));
+            assert!(errors.is_empty(), "{:?}", errors);
+            program.validate().len()
+        };
+        assert_eq!(diagnostics("
+            c := succ(red); w := pred(green);
+            i := ord(blue) + ord(c);
+            for c := red to blue do t[c, ord(c) + 1] := ord(c);
+            case c of red, green: w := red; blue: c := w end;
+            if (c < blue) and (f = pear) then c := red
+        "), 0);
+        let cases = [
+            ("if c < f then ;", 1),
+            ("c := apple", 1),
+            ("t[1, 1] := 0", 1),
+            ("t[c, red] := 0", 1),
+            ("case c of red: ; apple: end", 1),
+            ("for c := red to pear do ;", 1),
+            ("i := ord(1.5) + succ(2.5)", 2),
+            ("i := ord(red, green)", 1),
+        ];
+        for (body, faults) in cases.iter() {
+            assert_eq!(diagnostics(body), *faults, "{}", body);
+        }
+    }
+
+    #[test]
+    fn subranges_take_the_type_of_their_limits() {
+        let (program, errors) = Program::new("
+            program ranges;
+            type color = (red, green, blue);
+                 warm = red..green;
+                 digit = '0'..'9';
+                 mixed = 1..'9';
+                 empty = blue..red;
+                 bad = array [real] of integer;
+            begin end.
+        ".to_string());
+        assert!(errors.is_empty());
+        let types = &program.scopes[Program::GLOBAL].types;
+        let color = types["color"].clone();
+        assert_eq!(types["warm"], Type::Range(Box::new(color), 0..1));
+        assert_eq!(types["digit"], Type::Range(Box::new(Type::Char), 48..57));
+        assert_eq!(program.validate().len(), 3);
+    }
+
+    #[test]
+    fn redeclarations_point_at_the_first_declaration() {
+        let src = "
+            program dups;
+            const limit = 10;
+                  limit = 20;
+            type color = (red, green, limit);
+                 point = record x, y: integer; x: real end;
+            var red: char;
+            procedure p(a, b: integer; var a: real);
+            var b: char;
+            begin end;
+            function p: integer;
+            begin p := 1 end;
+            begin end.
+        ";
+        let (program, errors) = Program::new(src.to_string());
+        assert!(errors.is_empty());
+        let redeclared = program.validate().into_iter()
+            .map(|err| match err.kind {
+                SemanticErrorKind::Redeclaration { name, first } => (name, src[first].to_string()),
+                kind => panic!("unexpected {:?}", kind)
+            })
+            .collect::<Vec<(String, String)>>();
+        let expected = ["limit", "limit", "x", "red", "a", "b", "p"].iter()
+            .map(|name| (name.to_string(), name.to_string()))
+            .collect::<Vec<(String, String)>>();
+        assert_eq!(redeclared, expected);
+    }
+
+    #[test]
+    fn invalid_declarations_are_reported_once() {
+        let (program, errors) = Program::new("
+            program faults;
+            type list = array [1..10] of node;
+            var l: list;
+                n: node;
+            begin
+                l[1] := n;
+                n := l[2] + 1;
+                l.next := 3
+            end.
+        ".to_string());
+        assert!(errors.is_empty());
+        // both uses of `node`, and the field access on an array
+        assert_eq!(program.validate().len(), 3);
+    }
+
+    #[test]
+    fn files_and_program_parameters() {
+        let (program, errors) = Program::new("
+            program files(input, output, data, log, n, missing, data);
+            type cell = record a: integer end;
+            var data: text; log: file of real; n: integer;
+                cells: file of cell;
+                pair: array [1..2] of text;
+            procedure copy(var source: text; target: text); begin end;
+            begin
+                reset(data); rewrite(log); write(log, 1, 2.5); readln(data, n);
+                if eof(log) or eoln(data) or eoln then get(data);
+                log^ := n; put(log); writeln(output, log^);
+                pair[1] := data;
+                writeln(log, n);
+                write(log, 'x');
+                eoln(log);
+                reset(n)
+            end.
+        ".to_string());
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(program.uses_files());
+        let kinds = program.validate().into_iter()
+            .map(|err| err.kind)
+            .collect::<Vec<_>>();
+        // the file of records and the file passed by value, the parameters,
+        // then the assignment, writeln and write to a typed file, eoln of
+        // one and resetting an integer
+        assert!(matches!(&kinds[..], [
+            SemanticErrorKind::FileComponent(Type::Record(_)),
+            SemanticErrorKind::UnsupportedOperation { .. },
+            SemanticErrorKind::TypeError { got: Type::Integer, .. },
+            SemanticErrorKind::UndeclaredIdentifier(_),
+            SemanticErrorKind::Redeclaration { .. },
+            SemanticErrorKind::UnsupportedOperation { .. },
+            SemanticErrorKind::UnsupportedOperation { .. },
+            SemanticErrorKind::TypeError { got: Type::Char, .. },
+            SemanticErrorKind::TypeError { got: Type::File(_), .. },
+            SemanticErrorKind::UnsupportedOperation { .. }
+        ]), "{:?}", kinds);
+        let (plain, _) = Program::new("program plain(input, output); begin writeln(1) end.".to_string());
+        assert!(plain.validate().is_empty() && !plain.uses_files());
+    }
+
+    #[test]
+    fn strings_are_a_turbo_extension() {
+        let src = "
+            program strings;
+            const empty = '';
+            var s: string; t: string[8]; w: packed array [1..3] of char;
+                bad: string[300]; worse: string['x']; odd: integer[4];
+                n, code: integer;
+            begin
+                w := 'abc'; s := 'it''s'; t := s + w;
+                n := length(s) + pos('s', t);
+                str(n:3, t); val(t, n, code);
+                insert(copy(s, 1, 2), t, 1); delete(w, 1, 1);
+                if (s < w) or (w = 'xyz') then writeln(concat(s, t, 'u'))
+            end.
+        ";
+        let (iso, errors) = Program::new(src.to_string());
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(!iso.uses_strings());
+        let kinds = iso.validate().into_iter()
+            .filter(|err| matches!(err.kind, SemanticErrorKind::Extension(_)))
+            .count();
+        // the empty literal, the string types and the eight builtins
+        assert_eq!(kinds, 13);
+
+        let (turbo, errors) = Program::with_dialect(src.to_string(), Dialect::Turbo);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert!(turbo.uses_strings());
+        let kinds = turbo.validate().into_iter()
+            .map(|err| err.kind)
+            .collect::<Vec<_>>();
+        // the limit out of range, the character limit, the integer
+        // indexed like a string and the string deleted from an array
+        assert!(matches!(&kinds[..], [
+            SemanticErrorKind::StringSize(300),
+            SemanticErrorKind::TypeError { .. },
+            SemanticErrorKind::UnsupportedOperation { .. },
+            SemanticErrorKind::TypeError { .. }
+        ]), "{:?}", kinds);
+        let (plain, _) = Program::with_dialect("program plain; begin writeln('ab') end.".to_string(), Dialect::Turbo);
+        assert!(plain.validate().is_empty() && !plain.uses_strings());
+    }
+
+    #[test]
+    fn wide_sets_are_found() {
+        let wide = |declarations: &str, body: &str| {
+            let (program, errors) = Program::new(format!("program sets; {} begin {} end.", declarations, body));
+            assert!(errors.is_empty(), "{:?}", errors);
+            program.uses_wide_sets()
+        };
+        assert!(!wide("type byte = 0..255; var s: set of char; b: set of byte; e: set of boolean;", "b := [0, 255]"));
+        assert!(wide("type neg = -5..5; var n: set of neg;", ""));
+        assert!(wide("var s: set of integer;", ""));
+        assert!(wide("type big = 0..2000; bits = set of big; cell = record s: bits end; cells = array [1..3] of cell;", ""));
+        assert!(wide("var b: boolean;", "b := 1000 in [1000]"));
+        assert!(!wide("var b: boolean;", "b := 1000 in [1, 2]"));
+    }
+
+    #[test]
+    fn directives_apply_from_where_they_are() {
+        let src = "
+            program switches; { a comment } (* and another *)
+            var i: integer;
+            begin
+                i := 1; {$R-,q+}
+                i := 2; {$Q-}
+                i := 3
+            end.
+        ";
+        let (program, errors) = Program::new(src.to_string());
+        assert!(errors.is_empty(), "{:?}", errors);
+        let at = |text: &str| program.checks_at(src.find(text).unwrap());
+        assert_eq!(at("i := 1"), Checks::default());
+        assert_eq!(at("i := 2"), Checks { range: false, overflow: true });
+        assert_eq!(at("i := 3"), Checks { range: false, overflow: false });
+        assert!(!Checks::default().apply("R+,X-"));
+    }
+
+    /// The lines from 1 and titles of the diagnostics of `src`, in the order
+    /// they are printed
+    fn diagnosed(src: &str) -> Vec<(usize, String)> {
+        let (program, errors) = Program::new(src.to_string());
+        program.diagnostics(errors).iter()
+            .map(|err| (err.position(&program.positioner).line_no + 1, err.title()))
+            .collect()
+    }
+
+    #[test]
+    fn diagnostics_in_source_order() {
+        let error = |line: usize, title: &str| (line, title.to_string());
+        // the undeclared identifier is found while parsing, the type errors
+        // in the bodies of the statements after it
+        assert_eq!(diagnosed("program order;
+            var i: integer; b: boolean;
+            begin
+                i := 'a';
+                k := 1;
+                while i do b := 1;
+                for i := 1 to 2 do
+                    begin case i of 1: b := 2 end end
+            end.
+        "), vec![
+            error(4, "Type Error"),
+            error(5, "Undeclared Identifier"),
+            error(6, "Type Error"),
+            error(6, "Type Error"),
+            error(8, "Type Error"),
+        ]);
+        // a parse error is listed with the semantic errors, where it is
+        assert_eq!(diagnosed("program order;
+            var i: integer;
+                i: real;
+            begin
+                if i then i := i +
+            end.
+        "), vec![error(3, "Redeclaration"), error(6, &crate::error::ERROR.to_string())]);
+    }
+
+    #[test]
+    fn faulty_constants_where_declared() {
+        let error = |line: usize, title: &str| (line, title.to_string());
+        // the constant is reported once, and not again where it is a limit
+        assert_eq!(diagnosed("program constants;
+            const zero = 0;
+                  x = 1 div zero;
+                  big = 9223372036854775807 + 1;
+                  fine = 7 div 2;
+            var a: array [1..x] of integer;
+            begin
+                a[fine] := 0
+            end.
+        "), vec![error(3, "Invalid Constant"), error(4, "Invalid Constant")]);
+    }
+
+    #[test]
+    fn pointers_to_types_declared_later() {
+        let error = |line: usize, title: &str| (line, title.to_string());
+        // a pointer type may name a type declared after it in the same
+        // type definitions, but not one declared in none of them
+        assert_eq!(diagnosed("program lists;
+            type list = ^node;
+                 node = record value: integer; next: list end;
+                 tree = ^leaf;
+            type later = ^list;
+            var p: list; t: ^missing;
+            begin
+                p := nil; new(p); p^.next := p; p^.next^.value := 1;
+                if p^.next <> nil then dispose(p)
+            end.
+        "), vec![error(4, "Undeclared Identifier"), error(6, "Undeclared Identifier")]);
+    }
+}
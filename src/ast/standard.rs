@@ -0,0 +1,336 @@
+use std::ops::RangeInclusive;
+use crate::ast::types::Type;
+use crate::error::semantic_error::SemanticErrorKind;
+
+/// A predeclared subprogram, found when no declaration in scope shadows it.
+/// This is the one definition of each of them the checker and every backend
+/// refer to.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Builtin {
+    Abs,
+    Sqr,
+    Sqrt,
+    Sin,
+    Cos,
+    Arctan,
+    Exp,
+    Ln,
+    Trunc,
+    Round,
+    Odd,
+    Ord,
+    Chr,
+    Succ,
+    Pred,
+    Eof,
+    Eoln,
+    Read,
+    Readln,
+    Write,
+    Writeln,
+    New,
+    Dispose,
+    Pack,
+    Unpack,
+    Reset,
+    Rewrite,
+    Get,
+    Put,
+    Length,
+    Copy,
+    Pos,
+    Concat,
+    Insert,
+    Delete,
+    Str,
+    Val
+}
+
+/// The error for an argument that does not fit, if it does not
+fn expect(index: usize, ty: &Type, fits: bool, expected: &[Type]) -> Option<(usize, SemanticErrorKind)> {
+    if fits {
+        None
+    } else {
+        Some((index, SemanticErrorKind::TypeError {
+            expected: expected.to_vec(),
+            got: ty.clone()
+        }))
+    }
+}
+
+/// The error for an argument `operation` cannot be applied to, if it cannot
+fn supports(index: usize, ty: &Type, fits: bool, operation: &str) -> Option<(usize, SemanticErrorKind)> {
+    if fits {
+        None
+    } else {
+        Some((index, SemanticErrorKind::UnsupportedOperation {
+            operation: operation.to_string(),
+            operand: ty.clone()
+        }))
+    }
+}
+
+/// Whether `write` can print values of type `ty`
+fn is_printable(ty: &Type) -> bool {
+    ty.is_textual() || Type::PRIMITIVE.contains(&ty.host())
+}
+
+/// Whether `read` can parse values of type `ty`, a string taking the rest
+/// of the line
+fn is_readable(ty: &Type) -> bool {
+    matches!(ty, Type::String(_)) || [Type::Integer, Type::Real, Type::Char].contains(&ty.host())
+}
+
+/// The arguments of the string subprograms, each string, integer or number
+/// at the index it is expected
+fn check_strings(args: &[Type], strings: &[usize], integers: &[usize], numbers: &[usize])
+    -> Vec<(usize, SemanticErrorKind)> {
+    let string = Type::String(255);
+    let text = strings.iter()
+        .filter_map(|index| expect(*index, &args[*index], args[*index].is_textual(), std::slice::from_ref(&string)));
+    let integer = integers.iter()
+        .filter_map(|index| expect(*index, &args[*index], args[*index].is_integer(), &[Type::Integer]));
+    let number = numbers.iter()
+        .filter_map(|index| expect(*index, &args[*index], args[*index].is_numeric(), Type::NUMERIC));
+    text.chain(integer).chain(number).collect()
+}
+
+/// The text file the first of `args` names, and the other arguments, or
+/// the standard file and all of them
+fn text_arguments(args: &[Type]) -> (usize, Option<&Type>, &[Type]) {
+    match args.first() {
+        Some(Type::File(element)) => (1, Some(element), &args[1..]),
+        _ => (0, None, args)
+    }
+}
+
+/// The arguments of `read` and `write`, which may start with the file they
+/// read or write; a typed file takes values of its elements and is not
+/// made of lines
+fn check_transfer(builtin: &Builtin, args: &[Type], fits: fn(&Type) -> bool) -> Vec<(usize, SemanticErrorKind)> {
+    let name = builtin.name();
+    let (first, element, rest) = text_arguments(args);
+    let items = rest.iter()
+        .enumerate()
+        .map(|(index, ty)| (index + first, ty));
+    match element {
+        Some(element) if *element != Type::Char => {
+            if matches!(builtin, Builtin::Readln | Builtin::Writeln) {
+                return supports(0, &args[0], false, name).into_iter().collect();
+            }
+            items.filter_map(|(index, ty)| {
+                let fits = if *builtin == Builtin::Read { ty.accepts(element) } else { element.accepts(ty) };
+                expect(index, ty, fits, std::slice::from_ref(element))
+            })
+                .collect()
+        },
+        _ => items.filter_map(|(index, ty)| supports(index, ty, fits(ty), name))
+            .collect()
+    }
+}
+
+/// The arguments of `pack(a, i, z)` or `unpack(z, a, i)`, checked by position
+/// in the `pack` order
+fn check_packing(array: (usize, &Type), index: (usize, &Type), packed: (usize, &Type))
+    -> Vec<(usize, SemanticErrorKind)> {
+    let (dim, element) = match array.1 {
+        Type::ArrayOf(dims, element) if dims.len() == 1 => (&dims[0], element),
+        ty => return supports(array.0, ty, false, "pack").into_iter().collect()
+    };
+    let mut out = expect(index.0, index.1, dim.is_compatible(index.1), &[dim.host()])
+        .into_iter()
+        .collect::<Vec<_>>();
+    match packed.1 {
+        Type::ArrayOf(dims, packed_element) if dims.len() == 1 && packed_element == element => {},
+        ty => out.push((packed.0, SemanticErrorKind::TypeError {
+            expected: vec![array.1.clone()],
+            got: ty.clone()
+        }))
+    }
+    out
+}
+
+impl Builtin {
+    pub const ALL: &'static [Builtin] = &[
+        Builtin::Abs, Builtin::Sqr, Builtin::Sqrt, Builtin::Sin, Builtin::Cos, Builtin::Arctan,
+        Builtin::Exp, Builtin::Ln, Builtin::Trunc, Builtin::Round, Builtin::Odd, Builtin::Ord,
+        Builtin::Chr, Builtin::Succ, Builtin::Pred, Builtin::Eof, Builtin::Eoln, Builtin::Read,
+        Builtin::Readln, Builtin::Write, Builtin::Writeln, Builtin::New, Builtin::Dispose,
+        Builtin::Pack, Builtin::Unpack, Builtin::Reset, Builtin::Rewrite, Builtin::Get, Builtin::Put,
+        Builtin::Length, Builtin::Copy, Builtin::Pos, Builtin::Concat, Builtin::Insert, Builtin::Delete,
+        Builtin::Str, Builtin::Val
+    ];
+
+    pub fn lookup(name: &str) -> Option<Builtin> {
+        Builtin::ALL.iter()
+            .find(|builtin| builtin.name() == name)
+            .copied()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Builtin::Abs => "abs",
+            Builtin::Sqr => "sqr",
+            Builtin::Sqrt => "sqrt",
+            Builtin::Sin => "sin",
+            Builtin::Cos => "cos",
+            Builtin::Arctan => "arctan",
+            Builtin::Exp => "exp",
+            Builtin::Ln => "ln",
+            Builtin::Trunc => "trunc",
+            Builtin::Round => "round",
+            Builtin::Odd => "odd",
+            Builtin::Ord => "ord",
+            Builtin::Chr => "chr",
+            Builtin::Succ => "succ",
+            Builtin::Pred => "pred",
+            Builtin::Eof => "eof",
+            Builtin::Eoln => "eoln",
+            Builtin::Read => "read",
+            Builtin::Readln => "readln",
+            Builtin::Write => "write",
+            Builtin::Writeln => "writeln",
+            Builtin::New => "new",
+            Builtin::Dispose => "dispose",
+            Builtin::Pack => "pack",
+            Builtin::Unpack => "unpack",
+            Builtin::Reset => "reset",
+            Builtin::Rewrite => "rewrite",
+            Builtin::Get => "get",
+            Builtin::Put => "put",
+            Builtin::Length => "length",
+            Builtin::Copy => "copy",
+            Builtin::Pos => "pos",
+            Builtin::Concat => "concat",
+            Builtin::Insert => "insert",
+            Builtin::Delete => "delete",
+            Builtin::Str => "str",
+            Builtin::Val => "val"
+        }
+    }
+
+    /// The string subprograms of Turbo Pascal, which standard Pascal does
+    /// not have
+    pub fn is_extension(&self) -> bool {
+        matches!(self, Builtin::Length | Builtin::Copy | Builtin::Pos | Builtin::Concat
+            | Builtin::Insert | Builtin::Delete | Builtin::Str | Builtin::Val)
+    }
+
+    pub fn is_function(&self) -> bool {
+        !matches!(self, Builtin::Read | Builtin::Readln | Builtin::Write | Builtin::Writeln
+            | Builtin::New | Builtin::Dispose | Builtin::Pack | Builtin::Unpack
+            | Builtin::Reset | Builtin::Rewrite | Builtin::Get | Builtin::Put
+            | Builtin::Insert | Builtin::Delete | Builtin::Str | Builtin::Val)
+    }
+
+    /// How many arguments the subprogram takes, `write`, `read` and `concat`
+    /// taking any number of them
+    pub fn arity(&self) -> RangeInclusive<usize> {
+        match self {
+            Builtin::Eof | Builtin::Eoln => 0..=1,
+            Builtin::Read | Builtin::Write | Builtin::Concat => 1..=usize::MAX,
+            Builtin::Readln | Builtin::Writeln => 0..=usize::MAX,
+            Builtin::Pos | Builtin::Str => 2..=2,
+            Builtin::Pack | Builtin::Unpack | Builtin::Copy | Builtin::Insert | Builtin::Delete
+            | Builtin::Val => 3..=3,
+            _ => 1..=1
+        }
+    }
+
+    /// The name of the `var` parameter at `index`, if it is one
+    pub fn var_parameter(&self, index: usize) -> Option<&'static str> {
+        match (self, index) {
+            (Builtin::Read, _) | (Builtin::Readln, _) => Some("v"),
+            (Builtin::New, 0) => Some("p"),
+            (Builtin::Pack, 2) => Some("z"),
+            (Builtin::Unpack, 1) => Some("a"),
+            (Builtin::Reset, _) | (Builtin::Rewrite, _) | (Builtin::Get, _) | (Builtin::Put, _)
+            | (Builtin::Eof, _) | (Builtin::Eoln, _) => Some("f"),
+            (Builtin::Insert, 1) | (Builtin::Delete, 0) | (Builtin::Str, 1) => Some("s"),
+            (Builtin::Val, 1) => Some("v"),
+            (Builtin::Val, 2) => Some("code"),
+            _ => None
+        }
+    }
+
+    /// The arguments of types `args` that do not fit, by index, with why.
+    /// Their count has been checked against the arity already.
+    pub fn check_arguments(&self, args: &[Type]) -> Vec<(usize, SemanticErrorKind)> {
+        let name = self.name();
+        match self {
+            Builtin::Abs | Builtin::Sqr | Builtin::Sqrt | Builtin::Sin | Builtin::Cos
+            | Builtin::Arctan | Builtin::Exp | Builtin::Ln | Builtin::Trunc | Builtin::Round =>
+                expect(0, &args[0], args[0].is_numeric(), Type::NUMERIC).into_iter().collect(),
+            Builtin::Odd | Builtin::Chr =>
+                expect(0, &args[0], args[0].is_integer(), &[Type::Integer]).into_iter().collect(),
+            Builtin::Ord | Builtin::Succ | Builtin::Pred =>
+                supports(0, &args[0], args[0].is_ordinal(), name).into_iter().collect(),
+            Builtin::Eof => args.first()
+                .and_then(|ty| supports(0, ty, matches!(ty, Type::File(_)), name))
+                .into_iter()
+                .collect(),
+            Builtin::Eoln => args.first()
+                .and_then(|ty| {
+                    let text = Type::File(Box::new(Type::Char));
+                    expect(0, ty, *ty == text, &[text])
+                })
+                .into_iter()
+                .collect(),
+            Builtin::Read | Builtin::Readln => check_transfer(self, args, is_readable),
+            Builtin::Write | Builtin::Writeln => check_transfer(self, args, is_printable),
+            Builtin::Reset | Builtin::Rewrite | Builtin::Get | Builtin::Put =>
+                supports(0, &args[0], matches!(args[0], Type::File(_)), name).into_iter().collect(),
+            Builtin::New | Builtin::Dispose =>
+                supports(0, &args[0], matches!(args[0], Type::Pointer(_)), name).into_iter().collect(),
+            Builtin::Pack => check_packing((0, &args[0]), (1, &args[1]), (2, &args[2])),
+            Builtin::Unpack => check_packing((1, &args[1]), (2, &args[2]), (0, &args[0])),
+            Builtin::Length => check_strings(args, &[0], &[], &[]),
+            Builtin::Copy => check_strings(args, &[0], &[1, 2], &[]),
+            Builtin::Pos => check_strings(args, &[0, 1], &[], &[]),
+            Builtin::Concat => check_strings(args, &(0..args.len()).collect::<Vec<_>>(), &[], &[]),
+            Builtin::Val => check_strings(args, &[0], &[2], &[1]),
+            // the string these change has to be a string variable
+            Builtin::Insert | Builtin::Delete | Builtin::Str => {
+                let (string, others) = match self {
+                    Builtin::Insert => (1, check_strings(args, &[0], &[2], &[])),
+                    Builtin::Delete => (0, check_strings(args, &[], &[1, 2], &[])),
+                    _ => (1, check_strings(args, &[], &[], &[0]))
+                };
+                let ty = &args[string];
+                others.into_iter()
+                    .chain(expect(string, ty, matches!(ty, Type::String(_)), &[Type::String(255)]))
+                    .collect()
+            }
+        }
+    }
+
+    /// The type of the result for arguments of types `args`, `None` for a
+    /// procedure
+    pub fn result_type(&self, args: &[Type]) -> Option<Type> {
+        Some(match self {
+            Builtin::Abs | Builtin::Sqr | Builtin::Succ | Builtin::Pred =>
+                args.first().map(Type::host).unwrap_or(Type::Invalid),
+            Builtin::Sqrt | Builtin::Sin | Builtin::Cos | Builtin::Arctan | Builtin::Exp | Builtin::Ln =>
+                Type::Real,
+            Builtin::Trunc | Builtin::Round | Builtin::Ord | Builtin::Length | Builtin::Pos => Type::Integer,
+            Builtin::Copy | Builtin::Concat => Type::String(255),
+            Builtin::Odd | Builtin::Eof | Builtin::Eoln => Type::Boolean,
+            Builtin::Chr => Type::Char,
+            _ => return None
+        })
+    }
+
+    /// The result for the constant ordinal arguments `args`, if it can be
+    /// computed before running the program
+    pub fn fold(&self, args: &[i64]) -> Option<i64> {
+        match (self, args) {
+            (Builtin::Abs, [value]) => value.checked_abs(),
+            (Builtin::Sqr, [value]) => value.checked_mul(*value),
+            (Builtin::Odd, [value]) => Some(value.rem_euclid(2)),
+            (Builtin::Ord, [value]) | (Builtin::Chr, [value]) => Some(*value),
+            (Builtin::Succ, [value]) => value.checked_add(1),
+            (Builtin::Pred, [value]) => value.checked_sub(1),
+            _ => None
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use std::ops::Range;
+use crate::ast::expression::{ExBox, Expression};
+use crate::ast::expression::operators::Call;
+use crate::ast::expression::variables::VarRef;
+use crate::ast::program::{Program, Symbol};
+use crate::ast::standard::Builtin;
+use crate::ast::types::Type;
+use crate::error::semantic_error::{SemanticError, SemanticErrorKind};
+
+#[derive(Debug)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub range: Range<usize>
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum Direction {
+    To,
+    Downto
+}
+
+#[derive(Debug)]
+pub struct CaseArm {
+    pub labels: Vec<ExBox>,
+    pub body: Statement
+}
+
+#[derive(Debug)]
+pub enum StatementKind {
+    Empty,
+    Assignment {
+        target: VarRef,
+        /// Where the target is, which faults in following it point at
+        target_range: Range<usize>,
+        value: ExBox
+    },
+    Call(Call),
+    Compound(Vec<Statement>),
+    If {
+        condition: ExBox,
+        then: Box<Statement>,
+        otherwise: Option<Box<Statement>>
+    },
+    While {
+        condition: ExBox,
+        body: Box<Statement>
+    },
+    Repeat {
+        body: Vec<Statement>,
+        condition: ExBox
+    },
+    For {
+        variable: VarRef,
+        from: ExBox,
+        direction: Direction,
+        to: ExBox,
+        body: Box<Statement>
+    },
+    Case {
+        selector: ExBox,
+        arms: Vec<CaseArm>
+    }
+}
+
+fn expect_boolean(condition: &ExBox, program: &Program) -> Vec<SemanticError> {
+    let mut out = condition.validate(program);
+    let ty = condition.get_type(program);
+    if out.is_empty() && ![Type::Boolean, Type::Invalid].contains(&ty) {
+        out.push(SemanticError::new(condition.range.clone(), SemanticErrorKind::TypeError {
+            expected: vec![Type::Boolean],
+            got: ty
+        }));
+    }
+    out
+}
+
+fn validate_all(statements: &[Statement], program: &Program) -> Vec<SemanticError> {
+    statements.iter()
+        .flat_map(|statement| statement.validate(program))
+        .collect()
+}
+
+impl Statement {
+    pub fn new (kind: StatementKind, range: Range<usize>) -> Statement {
+        Statement {
+            kind, range
+        }
+    }
+
+    /// The expressions of the statement and of the statements in it
+    pub fn expressions(&self) -> Vec<&ExBox> {
+        fn nested(statements: &[Statement]) -> Vec<&ExBox> {
+            statements.iter()
+                .flat_map(Statement::expressions)
+                .collect()
+        }
+        match &self.kind {
+            StatementKind::Empty => vec![],
+            StatementKind::Assignment { value, .. } => vec![value],
+            StatementKind::Call(call) => call.args.iter().collect(),
+            StatementKind::Compound(body) => nested(body),
+            StatementKind::If { condition, then, otherwise } => {
+                let mut out = vec![condition];
+                out.extend(then.expressions());
+                out.extend(otherwise.iter().flat_map(|otherwise| otherwise.expressions()));
+                out
+            },
+            StatementKind::While { condition, body } => {
+                let mut out = vec![condition];
+                out.extend(body.expressions());
+                out
+            },
+            StatementKind::Repeat { body, condition } => {
+                let mut out = nested(body);
+                out.push(condition);
+                out
+            },
+            StatementKind::For { from, to, body, .. } => {
+                let mut out = vec![from, to];
+                out.extend(body.expressions());
+                out
+            },
+            StatementKind::Case { selector, arms } => {
+                let mut out = vec![selector];
+                for arm in arms {
+                    out.extend(arm.body.expressions());
+                }
+                out
+            }
+        }
+    }
+
+    fn located(&self, kinds: Vec<SemanticErrorKind>) -> Vec<SemanticError> {
+        kinds.into_iter()
+            .map(|kind| SemanticError::new(self.range.clone(), kind))
+            .collect()
+    }
+
+    /// The type of the variable `target` names, or why it cannot be assigned to
+    fn target_type(&self, target: &VarRef, program: &Program) -> Result<Type, Vec<SemanticError>> {
+        let name = target.name();
+        match program.lookup(target.scope(), name) {
+            // the result of a function is assigned to through its name, from inside it
+            Some((_, Symbol::Subprogram(subprogram)))
+                if subprogram.is_function()
+                    && matches!(target, VarRef::Immediate(..))
+                    && program.encloses(subprogram.scope, target.scope()) =>
+                return Ok(subprogram.result.clone().unwrap_or(Type::Invalid)),
+            None if Builtin::lookup(name).is_some() =>
+                return Err(self.located(vec![SemanticErrorKind::NotAssignable(name.to_string())])),
+            Some((_, Symbol::Variable(_))) | None => {},
+            Some(_) => return Err(self.located(vec![SemanticErrorKind::NotAssignable(name.to_string())]))
+        }
+        let errors = self.located(target.validate(program));
+        if errors.is_empty() {
+            Ok(target.get_type(program))
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Checks that `target` names a variable that can hold a value of type `value`
+    fn validate_target(&self, target: &VarRef, value: Type, program: &Program) -> Vec<SemanticError> {
+        let ty = match self.target_type(target, program) {
+            Ok(ty) => ty,
+            Err(errors) => return errors
+        };
+        if ty.has_file() {
+            return self.located(vec![SemanticErrorKind::UnsupportedOperation {
+                operation: ":=".to_string(),
+                operand: ty
+            }]);
+        }
+        if ty != Type::Invalid && value != Type::Invalid && !ty.accepts(&value) {
+            self.located(vec![SemanticErrorKind::TypeError {
+                expected: vec![ty],
+                got: value
+            }])
+        } else {
+            vec![]
+        }
+    }
+
+    /// Checks the control variable of a `for` loop against its limits
+    fn validate_control(&self, variable: &VarRef, from: &ExBox, to: &ExBox, program: &Program) -> Vec<SemanticError> {
+        let out = self.validate_target(variable, from.get_type(program), program);
+        let ty = self.target_type(variable, program).unwrap_or(Type::Invalid);
+        let to_type = to.get_type(program);
+        if !out.is_empty() || ty == Type::Invalid || to_type == Type::Invalid {
+            out
+        } else if !ty.is_ordinal() {
+            self.located(vec![SemanticErrorKind::UnsupportedOperation {
+                operation: "for".to_string(),
+                operand: ty
+            }])
+        } else if !ty.accepts(&to_type) {
+            vec![SemanticError::new(to.range.clone(), SemanticErrorKind::TypeError {
+                expected: vec![ty],
+                got: to_type
+            })]
+        } else {
+            out
+        }
+    }
+
+    pub fn validate(&self, program: &Program) -> Vec<SemanticError> {
+        match &self.kind {
+            StatementKind::Empty => vec![],
+            StatementKind::Assignment { target, value, .. } => {
+                let mut out = value.validate(program);
+                if out.is_empty() {
+                    out.append(&mut self.validate_target(target, value.get_type(program), program));
+                }
+                out
+            },
+            StatementKind::Call(call) => self.located(call.validate_call(program)),
+            StatementKind::Compound(body) => validate_all(body, program),
+            StatementKind::If { condition, then, otherwise } => {
+                let mut out = expect_boolean(condition, program);
+                out.append(&mut then.validate(program));
+                if let Some(otherwise) = otherwise {
+                    out.append(&mut otherwise.validate(program));
+                }
+                out
+            },
+            StatementKind::While { condition, body } => {
+                let mut out = expect_boolean(condition, program);
+                out.append(&mut body.validate(program));
+                out
+            },
+            StatementKind::Repeat { body, condition } => {
+                let mut out = validate_all(body, program);
+                out.append(&mut expect_boolean(condition, program));
+                out
+            },
+            StatementKind::For { variable, from, to, body, .. } => {
+                let mut out = from.validate(program);
+                out.append(&mut to.validate(program));
+                if out.is_empty() {
+                    out = self.validate_control(variable, from, to, program);
+                }
+                out.append(&mut body.validate(program));
+                out
+            },
+            StatementKind::Case { selector, arms } => {
+                let mut out = selector.validate(program);
+                let ty = selector.get_type(program);
+                if out.is_empty() && ty != Type::Invalid && !ty.is_ordinal() {
+                    out.push(SemanticError::new(selector.range.clone(), SemanticErrorKind::UnsupportedOperation {
+                        operation: "case".to_string(),
+                        operand: ty.clone()
+                    }));
+                }
+                for arm in arms {
+                    for label in &arm.labels {
+                        let mut errors = label.validate(program);
+                        if errors.is_empty() {
+                            if let Err(err) = label.as_number(program) {
+                                errors.push(err);
+                            } else if ty.is_ordinal() && label.get_type(program) != Type::Invalid
+                                && !ty.is_compatible(&label.get_type(program)) {
+                                errors.push(SemanticError::new(label.range.clone(), SemanticErrorKind::TypeError {
+                                    expected: vec![ty.clone()],
+                                    got: label.get_type(program)
+                                }));
+                            }
+                        }
+                        out.append(&mut errors);
+                    }
+                    out.append(&mut arm.body.validate(program));
+                }
+                out
+            }
+        }
+    }
+}
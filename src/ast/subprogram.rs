@@ -0,0 +1,30 @@
+use std::ops::Range;
+use crate::ast::program::ScopeId;
+use crate::ast::statement::Statement;
+use crate::ast::types::Type;
+
+#[derive(Debug, Clone)]
+pub struct Parameter {
+    pub name: String,
+    pub r#type: Type,
+    /// Passed as `var`, by reference
+    pub by_ref: bool
+}
+
+#[derive(Debug)]
+pub struct Subprogram {
+    pub name: String,
+    pub params: Vec<Parameter>,
+    /// The type of the result of a function, `None` for a procedure
+    pub result: Option<Type>,
+    /// The scope holding the parameters and locals
+    pub scope: ScopeId,
+    pub body: Vec<Statement>,
+    pub range: Range<usize>
+}
+
+impl Subprogram {
+    pub fn is_function(&self) -> bool {
+        self.result.is_some()
+    }
+}
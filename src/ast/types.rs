@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::ops::Range;
+use crate::ast::program::ScopeId;
 
 #[derive(Clone, Eq, PartialEq, Debug)]
 pub enum Type {
@@ -9,22 +10,183 @@ pub enum Type {
     Boolean,
     Char,
     SetOf(Box<Type>),
-    ArrayOf(Vec<Range<usize>>, Box<Type>),
+    /// The index type of every dimension, and the type of the elements
+    ArrayOf(Vec<Type>, Box<Type>),
     Record(HashMap<String, Type>),
     Enum(Vec<String>),
-    Range(Range<isize>),
+    /// A subrange of an ordinal host type, with both bounds inclusive
+    Range(Box<Type>, Range<isize>),
+    Pointer(Box<Type>),
+    /// The type declared under a name in a scope, which a pointer type
+    /// points to so that a record can hold pointers to its own type. It is
+    /// looked up as the pointer is followed.
+    Named(String, ScopeId),
+    /// The type of `nil`, which every pointer type accepts
+    Nil,
+    /// A sequence of values of the type, `text` being a file of characters
+    /// read and written a line at a time
+    File(Box<Type>),
+    /// A Turbo Pascal string of up to this many characters, `string` itself
+    /// holding 255
+    String(usize),
     Invalid
 }
 
 impl Type {
     pub const PRIMITIVE: &'static [Type] = &[Type::Integer, Type::Real, Type::Char, Type::Boolean];
+    pub const NUMERIC: &'static [Type] = &[Type::Integer, Type::Real];
 
     pub fn is_primitive(&self) -> bool {
+        matches!(self, Type::Integer | Type::Real | Type::Boolean | Type::Char)
+    }
+
+    /// Integers and their subranges
+    pub fn is_integer(&self) -> bool {
+        self.host() == Type::Integer
+    }
+
+    pub fn is_numeric(&self) -> bool {
+        self.is_integer() || *self == Type::Real
+    }
+
+    pub fn is_ordinal(&self) -> bool {
+        matches!(self, Type::Integer | Type::Char | Type::Boolean | Type::Enum(_) | Type::Range(..))
+    }
+
+    /// A `packed array [1..n] of char`, the only string type of standard Pascal
+    pub fn is_string(&self) -> bool {
         match self {
-            Type::Integer | Type::Real | Type::Boolean | Type::Char => true,
+            Type::ArrayOf(dims, ty) => dims.len() == 1 && **ty == Type::Char
+                && matches!(&dims[0], Type::Range(host, range) if **host == Type::Integer && range.start == 1),
             _ => false
         }
     }
+
+    /// Strings, characters and `packed array [1..n] of char`, which all
+    /// convert to strings
+    pub fn is_textual(&self) -> bool {
+        matches!(self, Type::String(_) | Type::Char) || self.is_string()
+    }
+
+    /// Whether values of the type are or hold files, which cannot be
+    /// assigned or passed by value
+    pub fn has_file(&self) -> bool {
+        match self {
+            Type::File(_) => true,
+            Type::ArrayOf(_, element) => element.has_file(),
+            Type::Record(fields) => fields.values().any(Type::has_file),
+            _ => false
+        }
+    }
+
+    /// Whether values of the type are or hold Turbo Pascal strings
+    pub fn has_string(&self) -> bool {
+        match self {
+            Type::String(_) => true,
+            Type::ArrayOf(_, element) | Type::Pointer(element) => element.has_string(),
+            Type::Record(fields) => fields.values().any(Type::has_string),
+            _ => false
+        }
+    }
+
+    /// Whether values of the type are or hold sets of ordinals that reach
+    /// past 0..255, which compiled programs cannot hold
+    pub fn has_wide_set(&self) -> bool {
+        match self {
+            Type::SetOf(base) => !base.bounds().is_some_and(|bounds| bounds.start >= 0 && bounds.end <= 255),
+            Type::ArrayOf(_, element) | Type::Pointer(element) => element.has_wide_set(),
+            Type::Record(fields) => fields.values().any(Type::has_wide_set),
+            _ => false
+        }
+    }
+
+    /// The type a subrange is taken from, or the type itself
+    pub fn host(&self) -> Type {
+        match self {
+            Type::Range(host, _) => *host.clone(),
+            ty => ty.clone()
+        }
+    }
+
+    /// The smallest and largest ordinal value of an ordinal type, if it has
+    /// bounds of its own
+    pub fn bounds(&self) -> Option<Range<isize>> {
+        match self {
+            Type::Boolean => Some(0..1),
+            Type::Char => Some(0..255),
+            Type::Enum(variants) => Some(0..variants.len() as isize - 1),
+            Type::Range(_, range) => Some(range.clone()),
+            _ => None
+        }
+    }
+
+    /// The bounds a value of type `source` is checked against when it is
+    /// assigned to a variable of this subrange type, unless it always fits
+    pub fn narrowing(&self, source: &Type) -> Option<Range<isize>> {
+        let bounds = match self {
+            Type::Range(_, range) => range.clone(),
+            _ => return None
+        };
+        match source.bounds() {
+            Some(from) if bounds.start <= from.start && from.end <= bounds.end => None,
+            _ => Some(bounds)
+        }
+    }
+
+    /// The index type of the first dimension of an array, and the type of
+    /// what indexing that dimension gives
+    pub fn dimension(&self) -> Option<(Type, Type)> {
+        match self {
+            Type::ArrayOf(dims, element) if dims.len() > 1 =>
+                Some((dims[0].clone(), Type::ArrayOf(dims[1..].to_vec(), element.clone()))),
+            Type::ArrayOf(dims, element) => Some((dims[0].clone(), *element.clone())),
+            Type::String(_) => Some((Type::Integer, Type::Char)),
+            _ => None
+        }
+    }
+
+    /// The fields of a record sorted by name, the order they are laid out in
+    pub fn fields(&self) -> Vec<(&String, &Type)> {
+        let mut fields = match self {
+            Type::Record(fields) => fields.iter().collect::<Vec<_>>(),
+            _ => vec![]
+        };
+        fields.sort_by_key(|(name, _)| *name);
+        fields
+    }
+
+    /// Whether values of the two types can meet in the same operation
+    pub fn is_compatible(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::SetOf(left), Type::SetOf(right)) => left.is_compatible(right),
+            (Type::Pointer(left), Type::Pointer(right)) => left == right,
+            (Type::Pointer(_) | Type::Nil, Type::Pointer(_) | Type::Nil) => true,
+            (Type::String(_), Type::String(_)) => true,
+            (left, right) => left.host() == right.host()
+        }
+    }
+
+    /// Whether a value of type `value` can be assigned to a variable of this type
+    pub fn accepts(&self, value: &Type) -> bool {
+        match (self, value) {
+            (Type::Real, value) if value.is_integer() => true,
+            // a longer value is cut short when it is assigned
+            (Type::String(_), value) => value.is_textual(),
+            (target, value) => target.is_compatible(value)
+        }
+    }
+
+    /// How the ordinal `value` of this type is written in Pascal
+    pub fn ordinal_name(&self, value: isize) -> String {
+        match self.host() {
+            Type::Char => format!("'{}'", (value as u8) as char),
+            Type::Boolean => (value != 0).to_string(),
+            Type::Enum(variants) => variants.get(value as usize)
+                .cloned()
+                .unwrap_or_else(|| value.to_string()),
+            _ => value.to_string()
+        }
+    }
 }
 
 impl Display for Type {
@@ -35,32 +197,41 @@ impl Display for Type {
             Type::Boolean => "Boolean".to_string(),
             Type::Char => "Character".to_string(),
             Type::SetOf(ty) => format!("Set of {}", ty),
-            Type::ArrayOf(range, ty) =>
+            Type::ArrayOf(dims, ty) =>
                 format!("Array of {} [{}]", ty,
-                        range.into_iter()
-                             .map(|range| format!("{}..{}", range.start, range.end))
+                        dims.iter()
+                             .map(|dim| dim.to_string())
                              .collect::<Vec<String>>()
                              .join(";")),
             Type::Record(record) =>
-                format!("Record {}{}",
-                    record.into_iter()
+                format!("Record {} {}",
+                    record.iter()
                         .map(|(key, value)| format!("{}: {}", key, value))
                         .take(3)
                         .collect::<Vec<String>>()
-                        .join(";"),
+                        .join("; "),
                     if record.len() > 3 { "... end" } else { "end" }
                 ),
             Type::Enum(variants) => format!("({}{})",
-                    variants.into_iter()
+                    variants.iter()
                         .take(3)
-                        .map(|var| var.clone())
+                        .cloned()
                         .collect::<Vec<String>>()
                         .join(","),
                     if variants.len() > 3 { "..." } else { "" }
             ),
-            Type::Range(range) => format!("{}..{}", range.start, range.end),
+            Type::Range(_, range) => format!("{}..{}",
+                                             self.ordinal_name(range.start),
+                                             self.ordinal_name(range.end)),
+            Type::Pointer(ty) => format!("^{}", ty),
+            Type::Named(name, _) => name.clone(),
+            Type::Nil => "nil".to_string(),
+            Type::File(ty) if **ty == Type::Char => "Text".to_string(),
+            Type::File(ty) => format!("File of {}", ty),
+            Type::String(255) => "String".to_string(),
+            Type::String(capacity) => format!("String[{}]", capacity),
             Type::Invalid => "<???>".to_string()
         };
         write!(f, "{}", explanation)
     }
-}
\ No newline at end of file
+}
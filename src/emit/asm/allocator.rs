@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+use super::{Class, Function, Inst, VReg};
+
+/// The registers kept across calls, which values live across a call can be
+/// given. Reals are spilled across calls, System V keeping no `%xmm`.
+pub const CALLEE_SAVED: &[&str] = &["%rbx", "%r12", "%r13", "%r14", "%r15"];
+/// The other registers the allocator hands out. `%rax` and `%r11` are left
+/// out for the code selected for an instruction to work in.
+pub const CALLER_SAVED: &[&str] = &["%rcx", "%rdx", "%rsi", "%rdi", "%r8", "%r9", "%r10"];
+/// The registers for reals. `%xmm0` to `%xmm7` pass arguments and are left
+/// out like `%rax`.
+pub const FLOAT_REGISTERS: &[&str] = &["%xmm8", "%xmm9", "%xmm10", "%xmm11", "%xmm12", "%xmm13", "%xmm14", "%xmm15"];
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Location {
+    Register(&'static str),
+    /// A spill slot, relative to `%rbp`
+    Stack(i32)
+}
+
+/// Where every virtual register of a function lives
+#[derive(Debug, Clone)]
+pub struct Allocation {
+    pub locations: Vec<Location>,
+    /// How many bytes below `%rbp` the function takes with its spill slots
+    pub stack: i32,
+    /// The callee-saved registers the function uses, with the slots they are
+    /// saved in
+    pub saved: Vec<(&'static str, i32)>
+}
+
+/// A set of virtual registers
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Set(Vec<u64>);
+
+impl Set {
+    fn new(size: usize) -> Set {
+        Set(vec![0; size.div_ceil(64)])
+    }
+
+    fn insert(&mut self, reg: VReg) {
+        self.0[reg / 64] |= 1 << (reg % 64);
+    }
+
+    fn remove(&mut self, reg: VReg) {
+        self.0[reg / 64] &= !(1 << (reg % 64));
+    }
+
+    fn union(&mut self, other: &Set) {
+        for (word, other) in self.0.iter_mut().zip(&other.0) {
+            *word |= other;
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = VReg> + '_ {
+        self.0.iter().enumerate().flat_map(|(index, word)| (0..64)
+            .filter(move |bit| word >> bit & 1 == 1)
+            .map(move |bit| index * 64 + bit))
+    }
+}
+
+/// The instructions that can run after each one
+fn successors(code: &[Inst]) -> Vec<Vec<usize>> {
+    let labels = code.iter().enumerate()
+        .filter_map(|(index, inst)| match inst {
+            Inst::Label(label) => Some((*label, index)),
+            _ => None
+        })
+        .collect::<HashMap<_, _>>();
+    code.iter().enumerate()
+        .map(|(index, inst)| match inst {
+            Inst::Jump(label) => vec![labels[label]],
+            Inst::Branch(_, yes, no) => vec![labels[yes], labels[no]],
+            Inst::Return(_) => vec![],
+            _ if index + 1 < code.len() => vec![index + 1],
+            _ => vec![]
+        })
+        .collect()
+}
+
+/// The virtual registers live into and out of every instruction
+fn liveness(function: &Function) -> (Vec<Set>, Vec<Set>) {
+    let code = &function.code;
+    let registers = function.classes.len();
+    let successors = successors(code);
+    let mut live_in = vec![Set::new(registers); code.len()];
+    let mut live_out = vec![Set::new(registers); code.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for index in (0..code.len()).rev() {
+            let mut out = Set::new(registers);
+            for successor in &successors[index] {
+                out.union(&live_in[*successor]);
+            }
+            let mut live = out.clone();
+            if let Some(def) = code[index].def() {
+                live.remove(def);
+            }
+            for reg in code[index].uses() {
+                live.insert(reg);
+            }
+            if live != live_in[index] || out != live_out[index] {
+                changed = true;
+                live_in[index] = live;
+                live_out[index] = out;
+            }
+        }
+    }
+    (live_in, live_out)
+}
+
+/// Gives every virtual register of a function a register or a stack slot
+/// by linear scan: the live interval of every virtual register is walked in
+/// the order of their starts, and when no register is free, the one of the
+/// active intervals ending last, the current one included, is spilled.
+pub fn allocate(function: &Function) -> Allocation {
+    let registers = function.classes.len();
+    let (live_in, live_out) = liveness(function);
+
+    let mut intervals = vec![(usize::MAX, 0); registers];
+    let mut across_call = vec![false; registers];
+    for (index, inst) in function.code.iter().enumerate() {
+        for reg in live_in[index].iter().chain(inst.def()) {
+            let (start, end) = &mut intervals[reg];
+            *start = (*start).min(index);
+            *end = (*end).max(index);
+        }
+        if let Inst::Call { result, .. } = inst {
+            for reg in live_out[index].iter().filter(|reg| Some(*reg) != *result) {
+                across_call[reg] = true;
+            }
+        }
+    }
+
+    let mut order = (0..registers).filter(|reg| intervals[*reg].0 != usize::MAX).collect::<Vec<_>>();
+    order.sort_by_key(|reg| intervals[*reg]);
+    let mut locations = vec![Location::Stack(0); registers];
+    let mut stack = function.stack;
+    let mut spill = |locations: &mut Vec<Location>, reg: VReg| {
+        stack += 8;
+        locations[reg] = Location::Stack(-stack);
+    };
+    let mut active: Vec<VReg> = vec![];
+    for reg in order {
+        let (start, end) = intervals[reg];
+        active.retain(|other| intervals[*other].1 >= start);
+        let candidates: Vec<&'static str> = match (function.classes[reg], across_call[reg]) {
+            (Class::Int, true) => CALLEE_SAVED.to_vec(),
+            (Class::Int, false) => CALLER_SAVED.iter().chain(CALLEE_SAVED).copied().collect(),
+            (Class::Real, true) => vec![],
+            (Class::Real, false) => FLOAT_REGISTERS.to_vec()
+        };
+        let taken = |register: &'static str, locations: &Vec<Location>| active.iter()
+            .any(|other| locations[*other] == Location::Register(register));
+        match candidates.iter().find(|register| !taken(register, &locations)) {
+            Some(register) => {
+                locations[reg] = Location::Register(register);
+                active.push(reg);
+            },
+            None => {
+                let victim = active.iter()
+                    .filter(|other| matches!(locations[**other], Location::Register(register) if candidates.contains(&register)))
+                    .max_by_key(|other| intervals[**other].1)
+                    .copied();
+                match victim {
+                    Some(victim) if intervals[victim].1 > end => {
+                        locations[reg] = locations[victim];
+                        spill(&mut locations, victim);
+                        active.retain(|other| *other != victim);
+                        active.push(reg);
+                    },
+                    _ => spill(&mut locations, reg)
+                }
+            }
+        }
+    }
+
+    let mut saved = vec![];
+    for register in CALLEE_SAVED {
+        if locations.contains(&Location::Register(register)) {
+            stack += 8;
+            saved.push((*register, -stack));
+        }
+    }
+    Allocation { locations, stack, saved }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emit::asm::{BinOp, Convention, Operand};
+
+    fn function(classes: Vec<Class>, code: Vec<Inst>) -> Function {
+        Function { name: "f".to_string(), code, classes, stack: 0, frame: (0, 0) }
+    }
+
+    #[test]
+    fn values_live_across_calls_get_callee_saved_registers() {
+        let allocation = allocate(&function(vec![Class::Int, Class::Int, Class::Real], vec![
+            Inst::Mov(0, Operand::Imm(1)),
+            Inst::Mov(1, Operand::Imm(2)),
+            Inst::FloatConst(2, 0),
+            Inst::Call { convention: Convention::C, function: "g".to_string(), args: vec![Operand::Reg(1)], result: None },
+            Inst::Bin(BinOp::Add, 1, Operand::Reg(0), Operand::Reg(0)),
+            Inst::Return(Operand::Reg(2))
+        ]));
+        assert_eq!(allocation.locations[0], Location::Register("%rbx"));
+        assert_eq!(allocation.locations[1], Location::Register("%rcx"));
+        assert_eq!(allocation.locations[2], Location::Stack(-8));
+        assert_eq!(allocation.saved, vec![("%rbx", -16)]);
+    }
+
+    #[test]
+    fn the_interval_ending_last_is_spilled() {
+        // more values live at once than there are registers, the first
+        // one living longest
+        let count = CALLER_SAVED.len() + CALLEE_SAVED.len() + 1;
+        let mut code = (0..count).map(|reg| Inst::Mov(reg, Operand::Imm(reg as i64))).collect::<Vec<_>>();
+        code.extend((1..count).map(|reg| Inst::Bin(BinOp::Add, reg, Operand::Reg(reg), Operand::Imm(1))));
+        code.push(Inst::Return(Operand::Reg(0)));
+        let allocation = allocate(&function(vec![Class::Int; count], code));
+        assert_eq!(allocation.locations[0], Location::Stack(-8));
+        assert!(allocation.locations[1..].iter().all(|location| matches!(location, Location::Register(_))));
+    }
+}
@@ -0,0 +1,549 @@
+use crate::ast::program::{Program, ScopeId};
+use crate::ast::subprogram::Subprogram;
+use crate::ast::types::Type;
+use crate::emit::{callee, is_block, levels, routine, size, subprogram_of, Layout, Sites};
+use crate::ir::{self, Arg, Op, Procedure, Quad};
+use super::{Address, BinOp, Class, Cond, Convention, FloatOp, Function, Inst, Module, Operand, VReg};
+
+/// The symbol of the frame of the main program
+pub const GLOBALS: &str = "globals";
+
+/// Lowers the quadruples of a program to virtual registers, every
+/// temporary becoming one of its own
+pub fn lower(program: &Program, module: &ir::Module) -> Module {
+    let mut lowering = Lowering {
+        program,
+        levels: levels(program),
+        layouts: Layout::all(program),
+        strings: vec![],
+        tables: vec![],
+        sites: Sites::default(),
+        results: vec![],
+        code: vec![],
+        classes: vec![],
+        labels: 0,
+        stack: 0,
+        base: 0,
+        scope: Program::GLOBAL,
+        slots: vec![],
+        params: vec![]
+    };
+    let (main, subprograms) = module.procedures.split_last().expect("the main program is lowered last");
+    let mut functions = subprograms.iter()
+        .map(|procedure| {
+            let subprogram = subprogram_of(program, procedure.scope).expect("procedures are lowered from subprograms");
+            lowering.subprogram(subprogram, procedure)
+        })
+        .collect::<Vec<_>>();
+    let (function, globals) = lowering.main(main);
+    functions.push(function);
+    let mut sites = lowering.sites.texts(program);
+    sites.push(String::new());
+    let sites = sites.iter().map(|text| lowering.intern(text)).collect();
+    Module {
+        name: program.name.clone(),
+        functions,
+        globals,
+        strings: lowering.strings,
+        tables: lowering.tables,
+        sites,
+        results: lowering.results
+    }
+}
+
+struct Lowering<'a> {
+    program: &'a Program,
+    levels: Vec<usize>,
+    layouts: Vec<Layout>,
+    strings: Vec<String>,
+    tables: Vec<Vec<usize>>,
+    sites: Sites,
+    results: Vec<(String, i32)>,
+    code: Vec<Inst>,
+    classes: Vec<Class>,
+    labels: usize,
+    /// How many bytes below `%rbp` are taken so far
+    stack: i32,
+    /// Where the Pascal frame of the function starts, relative to `%rbp`
+    base: i32,
+    scope: ScopeId,
+    /// Where each memory temporary of the procedure is in its frame
+    slots: Vec<i32>,
+    /// The parameters of the next call
+    params: Vec<Arg>
+}
+
+impl<'a> Lowering<'a> {
+    /// Starts a function whose Pascal frame is that of the scope of
+    /// `procedure`, followed by its memory temporaries, and gives the size of
+    /// that frame
+    fn begin(&mut self, procedure: &Procedure) -> i32 {
+        self.scope = procedure.scope;
+        self.code = vec![];
+        // the temporaries are the first virtual registers
+        self.classes = vec![Class::Int; procedure.temps];
+        self.labels = procedure.fresh_label();
+        let mut offset = (self.layouts[procedure.scope].size + 7) / 8 * 8;
+        self.slots.clear();
+        for size in &procedure.slots {
+            self.slots.push(offset);
+            offset += (size + 7) / 8 * 8;
+        }
+        self.stack = if procedure.scope == Program::GLOBAL { 0 } else { offset };
+        self.base = -self.stack;
+        offset
+    }
+
+    fn finish(&mut self, name: &str) -> Function {
+        let frame = if self.scope == Program::GLOBAL { (0, 0) } else { (self.base, -self.base) };
+        Function {
+            name: name.to_string(),
+            code: std::mem::take(&mut self.code),
+            classes: std::mem::take(&mut self.classes),
+            stack: self.stack,
+            frame
+        }
+    }
+
+    fn body(&mut self, procedure: &Procedure) {
+        for (index, block) in procedure.blocks.iter().enumerate() {
+            self.push(Inst::Label(block.label));
+            for quad in &block.quads {
+                self.quad(procedure, quad);
+            }
+            if let Some(next) = procedure.blocks.get(index + 1) {
+                if !block.quads.last().is_some_and(|quad| quad.op.is_terminator()) {
+                    self.push(Inst::Jump(next.label));
+                }
+            }
+        }
+    }
+
+    fn subprogram(&mut self, subprogram: &Subprogram, procedure: &Procedure) -> Function {
+        self.begin(procedure);
+        let link = self.incoming(0);
+        self.push(Inst::Store(Address::Frame(self.base), link));
+        for (index, param) in subprogram.params.iter().enumerate() {
+            let value = self.incoming(index + 1);
+            let field = Address::Frame(self.base + self.layouts[subprogram.scope].fields[&param.name]);
+            if is_block(&param.r#type) && !param.by_ref {
+                let to = Operand::Reg(self.lea(field));
+                self.procedure_call("pas_copy", vec![to, value, Operand::Imm(size(&param.r#type) as i64)]);
+            } else {
+                self.push(Inst::Store(field, value));
+            }
+        }
+        self.body(procedure);
+        self.finish(&procedure.name)
+    }
+
+    /// The main program, and the size of its static frame, its memory
+    /// temporaries included
+    fn main(&mut self, procedure: &Procedure) -> (Function, i32) {
+        let globals = self.begin(procedure);
+        self.body(procedure);
+        (self.finish("main"), globals)
+    }
+
+    fn push(&mut self, inst: Inst) {
+        self.code.push(inst);
+    }
+
+    fn reg(&mut self, class: Class) -> VReg {
+        self.classes.push(class);
+        self.classes.len() - 1
+    }
+
+    fn label(&mut self) -> usize {
+        self.labels += 1;
+        self.labels
+    }
+
+    /// The index of a string constant
+    fn intern(&mut self, text: &str) -> usize {
+        self.strings.iter().position(|known| known == text).unwrap_or_else(|| {
+            self.strings.push(text.to_string());
+            self.strings.len() - 1
+        })
+    }
+
+    /// The address of a NUL-terminated string constant
+    fn string(&mut self, text: &str) -> Operand {
+        let index = self.intern(text);
+        Operand::Reg(self.lea(Address::Symbol(format!(".LS{}", index), 0)))
+    }
+
+    fn incoming(&mut self, slot: usize) -> Operand {
+        let reg = self.reg(Class::Int);
+        self.push(Inst::Incoming(reg, slot));
+        Operand::Reg(reg)
+    }
+
+    fn load(&mut self, class: Class, address: Address) -> VReg {
+        let reg = self.reg(class);
+        self.push(Inst::Load(reg, address));
+        reg
+    }
+
+    fn lea(&mut self, address: Address) -> VReg {
+        let reg = self.reg(Class::Int);
+        self.push(Inst::Lea(reg, address));
+        reg
+    }
+
+    fn call(&mut self, convention: Convention, function: &str, args: Vec<Operand>, result: Option<Class>) -> Operand {
+        let result = result.map(|class| self.reg(class));
+        self.push(Inst::Call { convention, function: function.to_string(), args, result });
+        result.map_or(Operand::Imm(0), Operand::Reg)
+    }
+
+    /// A call to a runtime function giving an integer
+    fn runtime(&mut self, function: &str, args: Vec<Operand>) -> Operand {
+        self.call(Convention::C, function, args, Some(Class::Int))
+    }
+
+    /// A call to a runtime procedure
+    fn procedure_call(&mut self, function: &str, args: Vec<Operand>) {
+        self.call(Convention::C, function, args, None);
+    }
+
+    /// A call to a runtime function giving a real
+    fn real_call(&mut self, function: &str, args: Vec<Operand>) -> VReg {
+        match self.call(Convention::C, function, args, Some(Class::Real)) {
+            Operand::Reg(reg) => reg,
+            Operand::Imm(_) => unreachable!("results are in registers")
+        }
+    }
+
+    /// The frame of `scope`, the innermost one reachable from the running
+    /// procedure
+    fn frame_of(&mut self, scope: ScopeId) -> Address {
+        if scope == Program::GLOBAL {
+            return Address::Symbol(GLOBALS.to_string(), 0);
+        }
+        let mut frame = Address::Frame(self.base);
+        // the link is at the start of a frame
+        for _ in self.levels[scope]..self.levels[self.scope] {
+            frame = Address::Based(self.load(Class::Int, frame), 0);
+        }
+        frame
+    }
+
+    /// Where a variable or a memory temporary is
+    fn address(&mut self, arg: &Arg) -> Address {
+        match arg {
+            Arg::Var(scope, name) => {
+                let layout = &self.layouts[*scope];
+                let offset = layout.fields.get(name).copied().unwrap_or(layout.result);
+                self.frame_of(*scope).offset(offset)
+            },
+            Arg::Slot(slot) if self.scope == Program::GLOBAL => Address::Symbol(GLOBALS.to_string(), self.slots[slot - 1]),
+            Arg::Slot(slot) => Address::Frame(self.base + self.slots[slot - 1]),
+            arg => unreachable!("{:?} is not in memory", arg)
+        }
+    }
+
+    /// The value of a field, the bits of a real
+    fn operand(&mut self, arg: &Arg) -> Operand {
+        match arg {
+            Arg::Int(value) => Operand::Imm(*value),
+            Arg::Real(value) => Operand::Imm(value.to_bits() as i64),
+            Arg::Temp(temp) => Operand::Reg(temp - 1),
+            arg => {
+                let address = self.address(arg);
+                Operand::Reg(self.load(Class::Int, address))
+            }
+        }
+    }
+
+    /// The value of a field in a register
+    fn int(&mut self, arg: &Arg) -> VReg {
+        match self.operand(arg) {
+            Operand::Reg(reg) => reg,
+            value => {
+                let reg = self.reg(Class::Int);
+                self.push(Inst::Mov(reg, value));
+                reg
+            }
+        }
+    }
+
+    /// The real of a field in a register
+    fn real(&mut self, arg: &Arg) -> VReg {
+        let reg = self.reg(Class::Real);
+        match arg {
+            Arg::Int(value) => self.push(Inst::FloatConst(reg, (*value as f64).to_bits())),
+            Arg::Real(value) => self.push(Inst::FloatConst(reg, value.to_bits())),
+            arg => {
+                let bits = self.operand(arg);
+                self.push(Inst::Mov(reg, bits));
+            }
+        }
+        reg
+    }
+
+    fn assign(&mut self, result: &Arg, value: Operand) {
+        match result {
+            Arg::Temp(temp) => self.push(Inst::Mov(temp - 1, value)),
+            variable => {
+                let address = self.address(variable);
+                self.push(Inst::Store(address, value));
+            }
+        }
+    }
+
+    /// Assigns the bits of the real in `value`
+    fn assign_real(&mut self, result: &Arg, value: VReg) {
+        self.assign(result, Operand::Reg(value));
+    }
+
+    fn quad(&mut self, procedure: &Procedure, quad: &Quad) {
+        let (arg1, arg2, result) = (&quad.arg1, &quad.arg2, &quad.result);
+        let site = Operand::Imm(self.sites.of(self.program, quad) as i64);
+        let integer = |op| match op {
+            Op::Add => Some(BinOp::Add),
+            Op::Sub => Some(BinOp::Sub),
+            Op::Mul => Some(BinOp::Mul),
+            _ => None
+        };
+        let compare = |op| match op {
+            Op::Eq | Op::FEq => Some(Cond::Eq),
+            Op::Ne | Op::FNe => Some(Cond::Ne),
+            Op::Lt | Op::FLt => Some(Cond::Lt),
+            Op::Le | Op::FLe => Some(Cond::Le),
+            Op::Gt | Op::FGt => Some(Cond::Gt),
+            Op::Ge | Op::FGe => Some(Cond::Ge),
+            _ => None
+        };
+        let arithmetic = |op| match op {
+            Op::FAdd => Some(FloatOp::Add),
+            Op::FSub => Some(FloatOp::Sub),
+            Op::FMul => Some(FloatOp::Mul),
+            _ => None
+        };
+        if let Some(op) = integer(quad.op) {
+            let (left, right) = (self.operand(arg1), self.operand(arg2));
+            let reg = self.reg(Class::Int);
+            self.push(Inst::Bin(op, reg, left, right));
+            return self.assign(result, Operand::Reg(reg));
+        }
+        if let Some(cond) = compare(quad.op) {
+            let reg = self.reg(Class::Int);
+            if matches!(quad.op, Op::FEq | Op::FNe | Op::FLt | Op::FLe | Op::FGt | Op::FGe) {
+                let (left, right) = (self.real(arg1), self.real(arg2));
+                self.push(Inst::FloatCmp(cond, reg, left, right));
+            } else {
+                let (left, right) = (self.operand(arg1), self.operand(arg2));
+                self.push(Inst::Cmp(cond, reg, left, right));
+            }
+            return self.assign(result, Operand::Reg(reg));
+        }
+        if let Some(op) = arithmetic(quad.op) {
+            let (left, right) = (self.real(arg1), self.real(arg2));
+            let reg = self.reg(Class::Real);
+            self.push(Inst::Float(op, reg, left, right));
+            return self.assign_real(result, reg);
+        }
+        match quad.op {
+            Op::Assign => {
+                let value = self.operand(arg1);
+                self.assign(result, value);
+            },
+            Op::Address => {
+                let address = self.address(arg1);
+                let reg = self.lea(address);
+                self.assign(result, Operand::Reg(reg));
+            },
+            Op::Load => {
+                let base = self.int(arg1);
+                let reg = self.load(Class::Int, Address::Based(base, 0));
+                self.assign(result, Operand::Reg(reg));
+            },
+            Op::Store => {
+                let value = self.operand(arg1);
+                let base = self.int(result);
+                self.push(Inst::Store(Address::Based(base, 0), value));
+            },
+            Op::Div | Op::Mod => {
+                let (left, right) = (self.operand(arg1), self.operand(arg2));
+                let function = if quad.op == Op::Div { "pas_div" } else { "pas_mod" };
+                let value = self.runtime(function, vec![left, right, site]);
+                self.assign(result, value);
+            },
+            Op::Neg => {
+                let value = self.operand(arg1);
+                let reg = self.reg(Class::Int);
+                self.push(Inst::Neg(reg, value));
+                self.assign(result, Operand::Reg(reg));
+            },
+            Op::Not | Op::Odd => {
+                let value = self.operand(arg1);
+                let op = if quad.op == Op::Not { BinOp::Xor } else { BinOp::And };
+                let reg = self.reg(Class::Int);
+                self.push(Inst::Bin(op, reg, value, Operand::Imm(1)));
+                self.assign(result, Operand::Reg(reg));
+            },
+            Op::Abs => {
+                let value = self.operand(arg1);
+                let value = self.runtime("pas_abs", vec![value]);
+                self.assign(result, value);
+            },
+            Op::FDiv => {
+                let (left, right) = (self.real(arg1), self.real(arg2));
+                let reg = self.real_call("pas_rdiv", vec![Operand::Reg(left), Operand::Reg(right), site]);
+                self.assign_real(result, reg);
+            },
+            Op::FNeg | Op::FAbs => {
+                let value = self.real(arg1);
+                let reg = self.reg(Class::Real);
+                self.push(if quad.op == Op::FNeg { Inst::FloatNeg(reg, value) } else { Inst::FloatAbs(reg, value) });
+                self.assign_real(result, reg);
+            },
+            Op::Float => {
+                let value = self.operand(arg1);
+                let reg = self.reg(Class::Real);
+                self.push(Inst::Widen(reg, value));
+                self.assign_real(result, reg);
+            },
+            Op::Trunc | Op::Round => {
+                let value = self.real(arg1);
+                let function = if quad.op == Op::Trunc { "pas_trunc" } else { "pas_round" };
+                let value = self.runtime(function, vec![Operand::Reg(value)]);
+                self.assign(result, value);
+            },
+            Op::Sqrt | Op::Ln => {
+                let value = self.real(arg1);
+                let function = if quad.op == Op::Sqrt { "pas_sqrt" } else { "pas_ln" };
+                let reg = self.real_call(function, vec![Operand::Reg(value), site]);
+                self.assign_real(result, reg);
+            },
+            Op::Sin | Op::Cos | Op::Exp | Op::Arctan => {
+                let value = self.real(arg1);
+                let function = match quad.op {
+                    Op::Sin => "sin",
+                    Op::Cos => "cos",
+                    Op::Exp => "exp",
+                    _ => "atan"
+                };
+                let reg = self.real_call(function, vec![Operand::Reg(value)]);
+                self.assign_real(result, reg);
+            },
+            Op::Check | Op::Bounds | Op::NoCase => {
+                let ty = match arg2 {
+                    Arg::Type(ty) => ty,
+                    arg => unreachable!("{:?} is not a type", arg)
+                };
+                let value = self.operand(arg1);
+                if quad.op == Op::NoCase {
+                    return self.no_case(value, ty, site);
+                }
+                let bounds = ty.bounds().expect("checked types have bounds");
+                let (low, high) = (Operand::Imm(bounds.start as i64), Operand::Imm(bounds.end as i64));
+                if quad.op == Op::Check {
+                    let name = self.string(&ty.to_string());
+                    self.procedure_call("pas_check", vec![value, low, high, name, site]);
+                } else {
+                    self.procedure_call("pas_index", vec![value, low, high, site]);
+                }
+            },
+            Op::Deref => {
+                let value = self.operand(arg1);
+                let value = self.runtime("pas_deref", vec![value, site]);
+                self.assign(result, value);
+            },
+            Op::Goto => self.push(Inst::Jump(quad.target().expect("jumps have a target"))),
+            Op::If | Op::IfFalse => {
+                let condition = self.operand(arg1);
+                let (target, next) = (quad.target().expect("jumps have a target"), self.label());
+                let (yes, no) = if quad.op == Op::If { (target, next) } else { (next, target) };
+                self.push(Inst::Branch(condition, yes, no));
+                self.push(Inst::Label(next));
+            },
+            Op::Param => self.params.push(arg1.clone()),
+            Op::Call => self.call_quad(quad),
+            Op::Return => self.exit(procedure, arg1),
+            op => unreachable!("{} is lowered above", op)
+        }
+    }
+
+    /// Reports a value no case label matches, with its kind and the names of
+    /// the literals of an enumeration
+    fn no_case(&mut self, value: Operand, ty: &Type, site: Operand) {
+        let (kind, names, count) = match ty {
+            Type::Char => ('c', Operand::Imm(0), 0),
+            Type::Boolean => ('b', Operand::Imm(0), 0),
+            Type::Enum(names) => {
+                let table = names.iter().map(|name| self.intern(name)).collect();
+                self.tables.push(table);
+                let symbol = format!(".LN{}", self.tables.len() - 1);
+                ('e', Operand::Reg(self.lea(Address::Symbol(symbol, 0))), names.len())
+            },
+            _ => ('i', Operand::Imm(0), 0)
+        };
+        self.procedure_call("pas_no_case", vec![value, Operand::Imm(kind as i64), names, Operand::Imm(count as i64), site]);
+    }
+
+    /// Leaves a procedure. A block a function gives is copied out of its
+    /// frame first, which goes as it returns.
+    fn exit(&mut self, procedure: &Procedure, value: &Arg) {
+        if procedure.scope == Program::GLOBAL {
+            let code = self.call(Convention::CInt, "pas_exit", vec![], Some(Class::Int));
+            return self.push(Inst::Return(code));
+        }
+        let returns = subprogram_of(self.program, procedure.scope).and_then(|subprogram| subprogram.result.as_ref());
+        let value = match returns {
+            Some(ty) if is_block(ty) => {
+                let symbol = format!("{}.result", procedure.name);
+                if !self.results.iter().any(|(known, _)| *known == symbol) {
+                    self.results.push((symbol.clone(), size(ty)));
+                }
+                let buffer = Operand::Reg(self.lea(Address::Symbol(symbol, 0)));
+                let from = self.operand(value);
+                self.procedure_call("pas_copy", vec![buffer.clone(), from, Operand::Imm(size(ty) as i64)]);
+                buffer
+            },
+            Some(_) => self.operand(value),
+            None => Operand::Imm(0)
+        };
+        self.push(Inst::Return(value));
+    }
+
+    /// A call to a subprogram, with the parameters of the quadruples before
+    /// it, or to a routine of the runtime
+    fn call_quad(&mut self, quad: &Quad) {
+        let params = std::mem::take(&mut self.params);
+        let name = match &quad.arg1 {
+            Arg::Name(name) => name,
+            arg => unreachable!("{:?} is not a procedure", arg)
+        };
+        let value = match callee(self.program, name) {
+            Some(subprogram) => {
+                let parent = self.program.scopes[subprogram.scope].parent.expect("subprograms are in a scope");
+                let frame = self.frame_of(parent);
+                let mut args = vec![Operand::Reg(self.lea(frame))];
+                for param in &params {
+                    args.push(self.operand(param));
+                }
+                self.call(Convention::Pascal, name, args, subprogram.result.as_ref().map(|_| Class::Int))
+            },
+            None => {
+                let routine = routine(name);
+                let mut args = vec![];
+                for (param, kind) in params.iter().zip(routine.params.chars()) {
+                    args.push(match kind {
+                        'f' => Operand::Reg(self.real(param)),
+                        _ => self.operand(param)
+                    });
+                }
+                if routine.checked {
+                    args.push(Operand::Imm(self.sites.number(&quad.range) as i64));
+                }
+                let class = routine.result.map(|kind| if kind == 'f' { Class::Real } else { Class::Int });
+                self.call(Convention::C, routine.function, args, class)
+            }
+        };
+        if !quad.result.is_none() {
+            self.assign(&quad.result, value);
+        }
+    }
+}
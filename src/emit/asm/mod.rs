@@ -0,0 +1,249 @@
+pub mod lower;
+pub mod allocator;
+pub mod x86;
+
+use crate::ast::program::Program;
+use crate::ir::opt::optimize;
+
+/// A virtual register, numbered in its function
+pub type VReg = usize;
+
+/// The registers a virtual register can be given
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Class {
+    /// Integers, ordinals and addresses
+    Int,
+    Real
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Operand {
+    Reg(VReg),
+    Imm(i64)
+}
+
+/// A place in memory, with a displacement in bytes
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Address {
+    /// In the stack frame of the function, relative to `%rbp`
+    Frame(i32),
+    /// In a data section
+    Symbol(String, i32),
+    /// Relative to an address in a register
+    Based(VReg, i32)
+}
+
+impl Address {
+    pub fn offset(self, by: i32) -> Address {
+        match self {
+            Address::Frame(offset) => Address::Frame(offset + by),
+            Address::Symbol(symbol, offset) => Address::Symbol(symbol, offset + by),
+            Address::Based(base, offset) => Address::Based(base, offset + by)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    And,
+    Or,
+    Xor
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FloatOp {
+    Add,
+    Sub,
+    Mul
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Cond {
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge
+}
+
+/// How a function takes its arguments and gives its result
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Convention {
+    /// Pascal subprograms: the static link and then every argument in a
+    /// stack slot of its own, and the bits of the result in `%rax`
+    Pascal,
+    /// System V C functions
+    C,
+    /// System V C functions giving an `int`
+    CInt
+}
+
+/// An instruction of the code between the quadruples and x86-64, on
+/// as many virtual registers as it needs
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inst {
+    Label(usize),
+    Mov(VReg, Operand),
+    Bin(BinOp, VReg, Operand, Operand),
+    Neg(VReg, Operand),
+    /// Whether the comparison holds, as 0 or 1
+    Cmp(Cond, VReg, Operand, Operand),
+    Float(FloatOp, VReg, VReg, VReg),
+    FloatNeg(VReg, VReg),
+    FloatAbs(VReg, VReg),
+    /// Whether the comparison holds, as 0 or 1; NaN is only unequal
+    FloatCmp(Cond, VReg, VReg, VReg),
+    /// Converts an integer to a real
+    Widen(VReg, Operand),
+    /// The real with the given bits
+    FloatConst(VReg, u64),
+    Load(VReg, Address),
+    Store(Address, Operand),
+    /// The address itself
+    Lea(VReg, Address),
+    /// The argument in a slot of a Pascal call, the static link being 0
+    Incoming(VReg, usize),
+    Call {
+        convention: Convention,
+        function: String,
+        args: Vec<Operand>,
+        result: Option<VReg>
+    },
+    Jump(usize),
+    /// Goes to the first label if the operand is not 0, else to the second
+    Branch(Operand, usize, usize),
+    Return(Operand)
+}
+
+impl Inst {
+    /// The virtual registers the instruction reads
+    pub fn uses(&self) -> Vec<VReg> {
+        let mut regs = vec![];
+        let mut operand = |operand: &Operand| if let Operand::Reg(reg) = operand {
+            regs.push(*reg);
+        };
+        match self {
+            Inst::Mov(_, value) | Inst::Neg(_, value) | Inst::Widen(_, value)
+            | Inst::Branch(value, ..) | Inst::Return(value) => operand(value),
+            Inst::Bin(_, _, left, right) | Inst::Cmp(_, _, left, right) => {
+                operand(left);
+                operand(right);
+            },
+            Inst::Store(address, value) => {
+                operand(value);
+                if let Address::Based(base, _) = address {
+                    regs.push(*base);
+                }
+            },
+            Inst::Call { args, .. } => args.iter().for_each(operand),
+            Inst::Float(_, _, left, right) | Inst::FloatCmp(_, _, left, right) => {
+                regs.push(*left);
+                regs.push(*right);
+            },
+            Inst::FloatNeg(_, value) | Inst::FloatAbs(_, value) => regs.push(*value),
+            Inst::Load(_, Address::Based(base, _)) | Inst::Lea(_, Address::Based(base, _)) => regs.push(*base),
+            _ => {}
+        }
+        regs
+    }
+
+    /// The virtual register the instruction writes
+    pub fn def(&self) -> Option<VReg> {
+        match self {
+            Inst::Mov(reg, _) | Inst::Bin(_, reg, ..) | Inst::Neg(reg, _) | Inst::Cmp(_, reg, ..)
+            | Inst::Float(_, reg, ..) | Inst::FloatNeg(reg, _) | Inst::FloatAbs(reg, _)
+            | Inst::FloatCmp(_, reg, ..) | Inst::Widen(reg, _) | Inst::FloatConst(reg, _)
+            | Inst::Load(reg, _) | Inst::Lea(reg, _) | Inst::Incoming(reg, _) => Some(*reg),
+            Inst::Call { result, .. } => *result,
+            _ => None
+        }
+    }
+}
+
+/// A subprogram, or the main program, lowered to virtual registers
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub name: String,
+    pub code: Vec<Inst>,
+    /// The class of every virtual register
+    pub classes: Vec<Class>,
+    /// How many bytes below `%rbp` the Pascal frame and the temporaries take
+    pub stack: i32,
+    /// Where the Pascal frame starts, relative to `%rbp`, and its size
+    pub frame: (i32, i32)
+}
+
+/// A whole program lowered to virtual registers
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub name: String,
+    pub functions: Vec<Function>,
+    /// The size of the frame of the main program, which is static
+    pub globals: i32,
+    /// The string constants, the names of enumeration literals among them
+    pub strings: Vec<String>,
+    /// The tables of enumeration literal names, as indices into `strings`
+    pub tables: Vec<Vec<usize>>,
+    /// The texts runtime errors are printed with, by the number of their
+    /// place, as indices into `strings`
+    pub sites: Vec<usize>,
+    /// The buffers functions copy the arrays and records they give to, with
+    /// their size
+    pub results: Vec<(String, i32)>
+}
+
+/// Translates a checked program into x86-64 assembly for the GNU
+/// assembler, under the System V ABI, from its quadruples optimized at
+/// `level`. Assemble and link it with the runtime from `--emit runtime`.
+pub fn emit(program: &Program, level: u8) -> String {
+    let (module, _) = optimize(crate::ir::lower::lower(program), level);
+    x86::assemble(&lower::lower(program, &module))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+    use super::*;
+    use crate::emit::tests::*;
+
+    /// Assembles a program, links it with the runtime and runs it
+    fn run(name: &str, program: &Program, input: &str) -> (String, Option<String>) {
+        let dir = scratch(&format!("asm-{}", name));
+        std::fs::write(dir.join("program.s"), emit(program, 0)).unwrap();
+        std::fs::write(dir.join("runtime.c"), format!("#define PAS_API\n{}", crate::emit::c::RUNTIME)).unwrap();
+        let linked = Command::new("cc")
+            .args(["-o", "program", "program.s", "runtime.c", "-lm"])
+            .current_dir(&dir)
+            .status()
+            .unwrap();
+        assert!(linked.success(), "{} does not assemble", name);
+        let outcome = execute(&mut Command::new(dir.join("program")), input);
+        std::fs::remove_dir_all(dir).unwrap();
+        outcome
+    }
+
+    #[test]
+    #[cfg_attr(not(all(target_arch = "x86_64", target_os = "linux")), ignore = "needs an x86-64 Linux host")]
+    fn samples_behave_as_interpreted() {
+        require("cc");
+        for (name, program, input) in samples() {
+            assert_eq!(run(&name, &program, &input), interpret(&program, &input), "{}", name);
+        }
+    }
+
+    #[test]
+    #[cfg_attr(not(all(target_arch = "x86_64", target_os = "linux")), ignore = "needs an x86-64 Linux host")]
+    fn runtime_errors() {
+        require("cc");
+        for (index, body) in FAULTS.iter().enumerate() {
+            let program = fault(body);
+            let (_, error) = run(&format!("fault-{}", index), &program, "");
+            let (_, expected) = interpret(&program, "");
+            assert_eq!(error, expected, "{}", body);
+        }
+    }
+}
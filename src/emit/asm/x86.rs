@@ -0,0 +1,405 @@
+use std::fmt::Write;
+use super::{Address, BinOp, Class, Cond, Convention, FloatOp, Function, Inst, Module, Operand};
+use super::allocator::{allocate, Allocation, Location};
+use super::lower::GLOBALS;
+
+const INTEGER_ARGUMENTS: &[&str] = &["%rdi", "%rsi", "%rdx", "%rcx", "%r8", "%r9"];
+const FLOAT_ARGUMENTS: &[&str] = &["%xmm0", "%xmm1", "%xmm2", "%xmm3", "%xmm4", "%xmm5", "%xmm6", "%xmm7"];
+
+/// Writes a lowered program as GNU assembler source
+pub fn assemble(module: &Module) -> String {
+    let mut out = format!("# program {}, compiled from Pascal; link with the runtime from --emit runtime\n\n\t.text\n", module.name);
+    for (index, function) in module.functions.iter().enumerate() {
+        let allocation = allocate(function);
+        Assembler { out: &mut out, function, allocation: &allocation, index }.function();
+    }
+    let _ = writeln!(out, "\n\t.section .rodata");
+    for (index, string) in module.strings.iter().enumerate() {
+        let _ = writeln!(out, ".LS{}:\n\t.string \"{}\"", index, escape(string));
+    }
+    let _ = writeln!(out, "\n\t.section .data.rel.ro,\"aw\"\n\t.p2align 3");
+    for (index, table) in module.tables.iter().enumerate() {
+        let _ = writeln!(out, ".LN{}:", index);
+        for string in table {
+            let _ = writeln!(out, "\t.quad .LS{}", string);
+        }
+    }
+    let _ = writeln!(out, "\t.globl pas_sites\npas_sites:");
+    for string in &module.sites {
+        let _ = writeln!(out, "\t.quad .LS{}", string);
+    }
+    let _ = writeln!(out, "\n\t.bss\n\t.p2align 4\n{}:\n\t.zero {}", GLOBALS, module.globals.max(8));
+    for (symbol, size) in &module.results {
+        let _ = writeln!(out, "\t.p2align 3\n{}:\n\t.zero {}", symbol, size);
+    }
+    let _ = writeln!(out, "\n\t.section .note.GNU-stack,\"\",@progbits");
+    out
+}
+
+/// A string as the GNU assembler reads it, every byte outside printable
+/// ASCII in octal
+fn escape(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b' '..=b'~' if byte != b'"' && byte != b'\\' => (byte as char).to_string(),
+            _ => format!("\\{:03o}", byte)
+        })
+        .collect()
+}
+
+/// Whether an immediate fits the sign-extended 32 bits of most instructions
+fn fits(value: i64) -> bool {
+    value >= i32::MIN as i64 && value <= i32::MAX as i64
+}
+
+fn suffix(cond: Cond) -> &'static str {
+    match cond {
+        Cond::Eq => "e",
+        Cond::Ne => "ne",
+        Cond::Lt => "l",
+        Cond::Gt => "g",
+        Cond::Le => "le",
+        Cond::Ge => "ge"
+    }
+}
+
+struct Assembler<'a> {
+    out: &'a mut String,
+    function: &'a Function,
+    allocation: &'a Allocation,
+    /// The number of the function, which keeps its labels apart
+    index: usize
+}
+
+impl<'a> Assembler<'a> {
+    fn emit(&mut self, text: &str) {
+        let _ = writeln!(self.out, "\t{}", text);
+    }
+
+    fn label(&self, label: usize) -> String {
+        format!(".L{}_{}", self.index, label)
+    }
+
+    fn location(&self, reg: usize) -> String {
+        match self.allocation.locations[reg] {
+            Location::Register(register) => register.to_string(),
+            Location::Stack(offset) => format!("{}(%rbp)", offset)
+        }
+    }
+
+    fn in_register(&self, reg: usize) -> bool {
+        matches!(self.allocation.locations[reg], Location::Register(_))
+    }
+
+    fn is_float(&self, reg: usize) -> bool {
+        matches!(self.allocation.locations[reg], Location::Register(register) if register.starts_with("%xmm"))
+    }
+
+    /// An operand as an instruction can take it, through `scratch` if it is
+    /// an immediate wider than 32 bits
+    fn operand(&mut self, operand: &Operand, scratch: &str) -> String {
+        match operand {
+            Operand::Imm(value) if fits(*value) => format!("${}", value),
+            Operand::Imm(value) => {
+                self.emit(&format!("movabsq ${}, {}", value, scratch));
+                scratch.to_string()
+            },
+            Operand::Reg(reg) => self.location(*reg)
+        }
+    }
+
+    /// Copies 64 bits between any two places, through `%rax` if neither is
+    /// a register
+    fn move_bits(&mut self, from: &str, to: &str) {
+        if from == to {
+            return;
+        }
+        let register = |place: &str| place.starts_with('%');
+        if !register(from) && !register(to) {
+            self.emit(&format!("movq {}, %rax", from));
+            self.emit(&format!("movq %rax, {}", to));
+        } else {
+            self.emit(&format!("movq {}, {}", from, to));
+        }
+    }
+
+    /// Moves an operand to a place
+    fn move_operand(&mut self, operand: &Operand, to: &str) {
+        match operand {
+            Operand::Imm(value) if fits(*value) && !to.starts_with("%xmm") => self.emit(&format!("movq ${}, {}", value, to)),
+            Operand::Imm(value) => {
+                self.emit(&format!("movabsq ${}, %rax", value));
+                self.move_bits("%rax", to);
+            },
+            Operand::Reg(reg) => {
+                let from = self.location(*reg);
+                self.move_bits(&from, to);
+            }
+        }
+    }
+
+    /// A memory operand for an address, loading its base into `%r11` if it
+    /// is spilled
+    fn memory(&mut self, address: &Address) -> String {
+        match address {
+            Address::Frame(offset) => format!("{}(%rbp)", offset),
+            Address::Symbol(symbol, 0) => format!("{}(%rip)", symbol),
+            Address::Symbol(symbol, offset) => format!("{}+{}(%rip)", symbol, offset),
+            Address::Based(base, offset) if self.in_register(*base) => format!("{}({})", offset, self.location(*base)),
+            Address::Based(base, offset) => {
+                let location = self.location(*base);
+                self.emit(&format!("movq {}, %r11", location));
+                format!("{}(%r11)", offset)
+            }
+        }
+    }
+
+    fn function(&mut self) {
+        let function = self.function;
+        let size = (self.allocation.stack + 15) / 16 * 16;
+        if function.name == "main" {
+            self.emit(".globl main");
+        }
+        let _ = writeln!(self.out, "\t.type {0}, @function\n{0}:", function.name);
+        self.emit("pushq %rbp");
+        self.emit("movq %rsp, %rbp");
+        if size > 0 {
+            self.emit(&format!("subq ${}, %rsp", size));
+        }
+        for (register, slot) in &self.allocation.saved {
+            self.emit(&format!("movq {}, {}(%rbp)", register, slot));
+        }
+        let (base, bytes) = function.frame;
+        if bytes > 0 {
+            // the variables start out zero, so that pointers are nil
+            self.emit(&format!("leaq {}(%rbp), %rdi", base));
+            self.emit(&format!("movq ${}, %rcx", bytes / 8));
+            self.emit("xorl %eax, %eax");
+            self.emit("rep stosq");
+        }
+        for inst in &function.code {
+            self.inst(inst);
+        }
+        let _ = writeln!(self.out, ".L{}_return:", self.index);
+        for (register, slot) in &self.allocation.saved {
+            self.emit(&format!("movq {}(%rbp), {}", slot, register));
+        }
+        self.emit("leave");
+        self.emit("ret");
+        let _ = writeln!(self.out, "\t.size {0}, .-{0}\n", function.name);
+    }
+
+    fn inst(&mut self, inst: &Inst) {
+        match inst {
+            Inst::Label(label) => {
+                let label = self.label(*label);
+                let _ = writeln!(self.out, "{}:", label);
+            },
+            Inst::Mov(reg, value) => {
+                let to = self.location(*reg);
+                self.move_operand(value, &to);
+            },
+            Inst::Bin(op, reg, left, right) => {
+                let mnemonic = match op {
+                    BinOp::Add => "addq",
+                    BinOp::Sub => "subq",
+                    BinOp::Mul => "imulq",
+                    BinOp::And => "andq",
+                    BinOp::Or => "orq",
+                    BinOp::Xor => "xorq"
+                };
+                let to = self.location(*reg);
+                // the result is built in its register unless the right
+                // operand is there too
+                let work = if self.in_register(*reg) && *right != Operand::Reg(*reg) { to.clone() } else { "%rax".to_string() };
+                self.move_operand(left, &work);
+                let right = self.operand(right, "%r11");
+                self.emit(&format!("{} {}, {}", mnemonic, right, work));
+                self.move_bits(&work, &to);
+            },
+            Inst::Neg(reg, value) => {
+                self.move_operand(value, "%rax");
+                self.emit("negq %rax");
+                let to = self.location(*reg);
+                self.move_bits("%rax", &to);
+            },
+            Inst::Cmp(cond, reg, left, right) => {
+                self.move_operand(left, "%rax");
+                let right = self.operand(right, "%r11");
+                self.emit(&format!("cmpq {}, %rax", right));
+                self.emit(&format!("set{} %al", suffix(*cond)));
+                self.emit("movzbl %al, %eax");
+                let to = self.location(*reg);
+                self.move_bits("%rax", &to);
+            },
+            Inst::Float(op, reg, left, right) => {
+                let mnemonic = match op {
+                    FloatOp::Add => "addsd",
+                    FloatOp::Sub => "subsd",
+                    FloatOp::Mul => "mulsd"
+                };
+                let left = self.location(*left);
+                self.move_bits(&left, "%xmm0");
+                let right = self.location(*right);
+                self.emit(&format!("{} {}, %xmm0", mnemonic, right));
+                let to = self.location(*reg);
+                self.move_bits("%xmm0", &to);
+            },
+            Inst::FloatNeg(reg, value) | Inst::FloatAbs(reg, value) => {
+                let from = self.location(*value);
+                self.move_bits(&from, "%rax");
+                self.emit(if matches!(inst, Inst::FloatNeg(..)) { "btcq $63, %rax" } else { "btrq $63, %rax" });
+                let to = self.location(*reg);
+                self.move_bits("%rax", &to);
+            },
+            Inst::FloatCmp(cond, reg, left, right) => {
+                // `ucomisd` sets the flags as an unsigned comparison would,
+                // and the parity flag for NaN
+                let (left, right) = match cond {
+                    Cond::Lt | Cond::Le => (right, left),
+                    _ => (left, right)
+                };
+                let left = self.location(*left);
+                self.move_bits(&left, "%xmm0");
+                let right = self.location(*right);
+                self.emit(&format!("ucomisd {}, %xmm0", right));
+                match cond {
+                    Cond::Eq => {
+                        self.emit("sete %al");
+                        self.emit("setnp %r11b");
+                        self.emit("andb %r11b, %al");
+                    },
+                    Cond::Ne => {
+                        self.emit("setne %al");
+                        self.emit("setp %r11b");
+                        self.emit("orb %r11b, %al");
+                    },
+                    Cond::Lt | Cond::Gt => self.emit("seta %al"),
+                    Cond::Le | Cond::Ge => self.emit("setae %al")
+                }
+                self.emit("movzbl %al, %eax");
+                let to = self.location(*reg);
+                self.move_bits("%rax", &to);
+            },
+            Inst::Widen(reg, value) => {
+                let from = match value {
+                    Operand::Reg(value) => self.location(*value),
+                    Operand::Imm(_) => {
+                        self.move_operand(value, "%rax");
+                        "%rax".to_string()
+                    }
+                };
+                self.emit(&format!("cvtsi2sdq {}, %xmm0", from));
+                let to = self.location(*reg);
+                self.move_bits("%xmm0", &to);
+            },
+            Inst::FloatConst(reg, bits) => {
+                self.emit(&format!("movabsq ${}, %rax", *bits as i64));
+                let to = self.location(*reg);
+                self.move_bits("%rax", &to);
+            },
+            Inst::Load(reg, address) => {
+                let from = self.memory(address);
+                let to = self.location(*reg);
+                self.move_bits(&from, &to);
+            },
+            Inst::Store(address, value) => {
+                let to = self.memory(address);
+                self.move_operand(value, &to);
+            },
+            Inst::Lea(reg, address) => {
+                let from = self.memory(address);
+                let to = self.location(*reg);
+                if self.in_register(*reg) {
+                    self.emit(&format!("leaq {}, {}", from, to));
+                } else {
+                    self.emit(&format!("leaq {}, %rax", from));
+                    self.move_bits("%rax", &to);
+                }
+            },
+            Inst::Incoming(reg, slot) => {
+                let to = self.location(*reg);
+                self.move_bits(&format!("{}(%rbp)", 16 + 8 * slot), &to);
+            },
+            Inst::Call { convention: Convention::Pascal, function, args, result } => {
+                let area = (8 * args.len() as i32 + 15) / 16 * 16;
+                self.emit(&format!("subq ${}, %rsp", area));
+                for (slot, arg) in args.iter().enumerate() {
+                    self.move_operand(arg, &format!("{}(%rsp)", 8 * slot));
+                }
+                self.emit(&format!("call {}", function));
+                self.emit(&format!("addq ${}, %rsp", area));
+                if let Some(result) = result {
+                    let to = self.location(*result);
+                    self.move_bits("%rax", &to);
+                }
+            },
+            Inst::Call { convention, function, args, result } => {
+                // the arguments go through the stack, so that none is
+                // overwritten by another on its way to its register
+                let classes = args.iter()
+                    .map(|arg| match arg {
+                        Operand::Reg(reg) => self.function.classes[*reg],
+                        Operand::Imm(_) => Class::Int
+                    })
+                    .collect::<Vec<_>>();
+                for arg in args {
+                    match arg {
+                        Operand::Reg(reg) if self.is_float(*reg) => {
+                            self.emit("subq $8, %rsp");
+                            let from = self.location(*reg);
+                            self.emit(&format!("movq {}, (%rsp)", from));
+                        },
+                        arg => {
+                            let arg = self.operand(arg, "%rax");
+                            self.emit(&format!("pushq {}", arg));
+                        }
+                    }
+                }
+                let mut integers = INTEGER_ARGUMENTS[..classes.iter().filter(|class| **class == Class::Int).count()].iter();
+                let mut floats = FLOAT_ARGUMENTS[..classes.iter().filter(|class| **class == Class::Real).count()].iter();
+                for class in classes.iter().rev() {
+                    match class {
+                        Class::Int => self.emit(&format!("popq {}", integers.next_back().expect("at most six integer arguments"))),
+                        Class::Real => {
+                            self.emit(&format!("movq (%rsp), {}", floats.next_back().expect("at most eight real arguments")));
+                            self.emit("addq $8, %rsp");
+                        }
+                    }
+                }
+                self.emit(&format!("call {}", function));
+                if let Some(result) = result {
+                    let to = self.location(*result);
+                    match self.function.classes[*result] {
+                        Class::Real => self.move_bits("%xmm0", &to),
+                        Class::Int => {
+                            if *convention == Convention::CInt {
+                                self.emit("movslq %eax, %rax");
+                            }
+                            self.move_bits("%rax", &to);
+                        }
+                    }
+                }
+            },
+            Inst::Jump(label) => {
+                let label = self.label(*label);
+                self.emit(&format!("jmp {}", label));
+            },
+            Inst::Branch(Operand::Imm(value), yes, no) => {
+                let label = self.label(if *value != 0 { *yes } else { *no });
+                self.emit(&format!("jmp {}", label));
+            },
+            Inst::Branch(condition, yes, no) => {
+                let condition = self.operand(condition, "%rax");
+                self.emit(&format!("cmpq $0, {}", condition));
+                let (yes, no) = (self.label(*yes), self.label(*no));
+                self.emit(&format!("jne {}", yes));
+                self.emit(&format!("jmp {}", no));
+            },
+            Inst::Return(value) => {
+                self.move_operand(value, "%rax");
+                self.emit(&format!("jmp .L{}_return", self.index));
+            }
+        }
+    }
+}
@@ -0,0 +1,443 @@
+use std::fmt::Write;
+use crate::ast::program::{Program, ScopeId};
+use crate::ast::subprogram::Subprogram;
+use crate::ast::types::Type;
+use crate::emit::{callee, is_block, levels, routine, size, subprogram_of, Layout, Sites};
+use crate::ir::{Arg, Module, Op, Procedure, Quad};
+use crate::ir::lower::lower;
+use crate::ir::opt::optimize;
+
+/// The runtime every translated program starts with
+pub const RUNTIME: &str = include_str!("runtime.c");
+
+/// What the code of the quadruples works with: words of memory at
+/// addresses kept as integers, and reals kept as their bits
+const WORDS: &str = "\
+#define PAS_WORD(address) (*(int64_t *) (intptr_t) (address))
+#define PAS_POINTER(address) ((void *) (intptr_t) (address))
+
+static double pas_real(int64_t bits) {
+    double value;
+    memcpy(&value, &bits, sizeof value);
+    return value;
+}
+
+static int64_t pas_bits(double value) {
+    int64_t bits;
+    memcpy(&bits, &value, sizeof bits);
+    return bits;
+}
+";
+
+/// Translates a checked program into a standalone C99 file from its
+/// quadruples, each of which becomes a statement. Temporaries are `int64_t`
+/// locals, a real being kept as its bits, and the frames are arrays of
+/// words laid out as in the other translations: that of the main program is
+/// static, and every subprogram takes the frame of the scope it is declared
+/// in and keeps it as the first word of its own.
+///
+/// The quadruples are optimized at `level`, as `-O` gives it.
+pub fn emit(program: &Program, level: u8) -> String {
+    let mut generator = Generator {
+        program,
+        levels: levels(program),
+        layouts: Layout::all(program),
+        out: String::new(),
+        scope: Program::GLOBAL,
+        slots: vec![],
+        params: vec![],
+        sites: Sites::default()
+    };
+    let (module, _) = optimize(lower(program), level);
+    generator.generate(module)
+}
+
+struct Generator<'a> {
+    program: &'a Program,
+    levels: Vec<usize>,
+    layouts: Vec<Layout>,
+    out: String,
+    scope: ScopeId,
+    /// Where each memory temporary of the procedure is in its frame
+    slots: Vec<i32>,
+    /// The parameters of the next call
+    params: Vec<Arg>,
+    sites: Sites
+}
+
+/// The C function of a procedure, whose name has a dot
+fn function_name(name: &str) -> String {
+    name.replace('.', "_")
+}
+
+fn integer(value: i64) -> String {
+    if value == i64::MIN {
+        "INT64_MIN".to_string()
+    } else if value as i32 as i64 == value {
+        value.to_string()
+    } else {
+        format!("INT64_C({})", value)
+    }
+}
+
+fn string(text: &str) -> String {
+    let escaped = text.chars()
+        .map(|c| match c {
+            '"' | '\\' => format!("\\{}", c),
+            ' '..='~' => c.to_string(),
+            c => format!("\\{:03o}", c as u32 & 0xff)
+        })
+        .collect::<String>();
+    format!("\"{}\"", escaped)
+}
+
+impl<'a> Generator<'a> {
+    fn generate(&mut self, module: Module) -> String {
+        let program = self.program;
+        let (main, subprograms) = module.procedures.split_last().expect("the main program is lowered last");
+        let mut prototypes = String::new();
+        let mut functions = String::new();
+        for procedure in subprograms {
+            let subprogram = subprogram_of(program, procedure.scope).expect("procedures are lowered from subprograms");
+            let _ = writeln!(prototypes, "{};", self.signature(subprogram, procedure));
+            functions.push_str(&self.subprogram(subprogram, procedure));
+        }
+        let (main, globals) = self.main(main);
+        let sites = self.sites.texts(program).iter()
+            .map(|text| format!("    {},\n", string(text)))
+            .collect::<String>();
+        format!("/* program {}, compiled from Pascal; build with cc -std=c99 -fwrapv -lm */\n\n\
+                 {}\n{}\nstatic int64_t globals[{}];\n\nconst char *const pas_sites[] = {{\n{}    \"\"\n}};\n\n{}\n{}{}",
+                program.name, RUNTIME, WORDS, globals, sites, prototypes, functions, main)
+    }
+
+    fn line(&mut self, text: &str) {
+        let _ = writeln!(self.out, "    {}", text);
+    }
+
+    fn signature(&self, subprogram: &Subprogram, procedure: &Procedure) -> String {
+        let params = (1..=subprogram.params.len())
+            .map(|index| format!(", int64_t p{}", index))
+            .collect::<String>();
+        let result = if subprogram.result.is_some() { "int64_t" } else { "void" };
+        format!("static {} {}(int64_t link{})", result, function_name(&procedure.name), params)
+    }
+
+    /// Lays out the memory temporaries of a procedure after the variables of
+    /// its scope, giving the size of its frame in words
+    fn begin(&mut self, procedure: &Procedure) -> i32 {
+        self.out.clear();
+        self.scope = procedure.scope;
+        let mut offset = (self.layouts[procedure.scope].size + 7) / 8 * 8;
+        self.slots.clear();
+        for size in &procedure.slots {
+            self.slots.push(offset);
+            offset += (size + 7) / 8 * 8;
+        }
+        (offset / 8).max(1)
+    }
+
+    /// The declarations of the temporaries and the statements of the
+    /// quadruples
+    fn body(&mut self, procedure: &Procedure) -> String {
+        let temps = (1..=procedure.temps).map(|temp| format!("t{} = 0", temp)).collect::<Vec<_>>()
+            .chunks(10)
+            .map(|temps| format!("    int64_t {};\n", temps.join(", ")))
+            .collect::<String>();
+        for block in &procedure.blocks {
+            let _ = writeln!(self.out, "L{}:", block.label);
+            for quad in &block.quads {
+                self.quad(procedure, quad);
+            }
+        }
+        temps
+    }
+
+    fn subprogram(&mut self, subprogram: &Subprogram, procedure: &Procedure) -> String {
+        let words = self.begin(procedure);
+        let temps = self.body(procedure);
+        let body = std::mem::take(&mut self.out);
+        self.line(&format!("int64_t frame[{}] = {{0}};", words));
+        self.line("int64_t f = (intptr_t) frame;");
+        self.out.push_str(&temps);
+        self.line("PAS_WORD(f) = link;");
+        for (index, param) in subprogram.params.iter().enumerate() {
+            let offset = self.layouts[subprogram.scope].fields[&param.name];
+            if is_block(&param.r#type) && !param.by_ref {
+                self.line(&format!("pas_copy(PAS_POINTER(f + {}), PAS_POINTER(p{}), {});", offset, index + 1, size(&param.r#type)));
+            } else {
+                self.line(&format!("PAS_WORD(f + {}) = p{};", offset, index + 1));
+            }
+        }
+        format!("{} {{\n{}{}}}\n\n", self.signature(subprogram, procedure), std::mem::take(&mut self.out), body)
+    }
+
+    /// The main program, and the size in words of its static frame, its
+    /// memory temporaries included
+    fn main(&mut self, procedure: &Procedure) -> (String, i32) {
+        let words = self.begin(procedure);
+        let temps = self.body(procedure);
+        let body = std::mem::take(&mut self.out);
+        (format!("int main(void) {{\n    int64_t f = (intptr_t) globals;\n{}{}}}\n", temps, body), words)
+    }
+
+    /// The address of the frame of `scope`, the innermost one reachable from
+    /// the running procedure
+    fn frame_of(&self, scope: ScopeId) -> String {
+        if scope == self.scope {
+            return "f".to_string();
+        }
+        if scope == Program::GLOBAL {
+            return "(intptr_t) globals".to_string();
+        }
+        // the link is at the start of a frame
+        let mut frame = "f".to_string();
+        for _ in self.levels[scope]..self.levels[self.scope] {
+            frame = format!("PAS_WORD({})", frame);
+        }
+        frame
+    }
+
+    /// The address of a variable or a memory temporary
+    fn address(&self, arg: &Arg) -> String {
+        match arg {
+            Arg::Var(scope, name) => {
+                let layout = &self.layouts[*scope];
+                let offset = layout.fields.get(name).copied().unwrap_or(layout.result);
+                format!("{} + {}", self.frame_of(*scope), offset)
+            },
+            Arg::Slot(slot) => format!("f + {}", self.slots[slot - 1]),
+            arg => unreachable!("{:?} is not in memory", arg)
+        }
+    }
+
+    /// A temporary or a variable, to assign
+    fn place(&self, arg: &Arg) -> String {
+        match arg {
+            Arg::Temp(temp) => format!("t{}", temp),
+            variable => format!("PAS_WORD({})", self.address(variable))
+        }
+    }
+
+    /// The value of a field, the bits of a real, and nothing for a field
+    /// that is not a value
+    fn value(&self, arg: &Arg) -> String {
+        match arg {
+            Arg::Int(value) => integer(*value),
+            Arg::Real(value) => integer(value.to_bits() as i64),
+            Arg::None | Arg::Label(_) | Arg::Name(_) | Arg::Type(_) => String::new(),
+            arg => self.place(arg)
+        }
+    }
+
+    /// The real of a field
+    fn real(&self, arg: &Arg) -> String {
+        match arg {
+            Arg::None | Arg::Label(_) | Arg::Name(_) | Arg::Type(_) => String::new(),
+            Arg::Int(value) => self.real(&Arg::Real(*value as f64)),
+            Arg::Real(value) if value.is_finite() && value.is_sign_negative() => format!("({:?})", value),
+            Arg::Real(value) if value.is_finite() => format!("{:?}", value),
+            arg => format!("pas_real({})", self.value(arg))
+        }
+    }
+
+    fn quad(&mut self, procedure: &Procedure, quad: &Quad) {
+        let (arg1, arg2, result) = (&quad.arg1, &quad.arg2, &quad.result);
+        let (a, b) = (self.value(arg1), self.value(arg2));
+        let (x, y) = (self.real(arg1), self.real(arg2));
+        // integer constants are an `int` in C, which arithmetic on them alone
+        // would overflow
+        let wide = if matches!(arg1, Arg::Int(_)) { format!("(int64_t) {}", a) } else { a.clone() };
+        let value = match quad.op {
+            Op::Assign => a,
+            Op::Address => self.address(arg1),
+            Op::Load => format!("PAS_WORD({})", a),
+            Op::Store => return self.line(&format!("PAS_WORD({}) = {};", self.value(result), a)),
+            Op::Add => format!("{} + {}", wide, b),
+            Op::Sub => format!("{} - {}", wide, b),
+            Op::Mul => format!("{} * {}", wide, b),
+            Op::Div => format!("pas_div({}, {}, {})", a, b, self.sites.number(&quad.range)),
+            Op::Mod => format!("pas_mod({}, {}, {})", a, b, self.sites.number(&quad.range)),
+            Op::Neg => format!("-({})", wide),
+            Op::FAdd => format!("pas_bits({} + {})", x, y),
+            Op::FSub => format!("pas_bits({} - {})", x, y),
+            Op::FMul => format!("pas_bits({} * {})", x, y),
+            Op::FDiv => format!("pas_bits(pas_rdiv({}, {}, {}))", x, y, self.sites.number(&quad.range)),
+            Op::FNeg => format!("pas_bits(-({}))", x),
+            Op::Eq => format!("{} == {}", a, b),
+            Op::Ne => format!("{} != {}", a, b),
+            Op::Lt => format!("{} < {}", a, b),
+            Op::Le => format!("{} <= {}", a, b),
+            Op::Gt => format!("{} > {}", a, b),
+            Op::Ge => format!("{} >= {}", a, b),
+            Op::FEq => format!("{} == {}", x, y),
+            Op::FNe => format!("{} != {}", x, y),
+            Op::FLt => format!("{} < {}", x, y),
+            Op::FLe => format!("{} <= {}", x, y),
+            Op::FGt => format!("{} > {}", x, y),
+            Op::FGe => format!("{} >= {}", x, y),
+            Op::Not => format!("{} ^ 1", a),
+            Op::Odd => format!("{} & 1", a),
+            Op::Abs => format!("pas_abs({})", a),
+            Op::FAbs => format!("pas_bits(fabs({}))", x),
+            Op::Float => format!("pas_bits((double) {})", a),
+            Op::Trunc => format!("pas_trunc({})", x),
+            Op::Round => format!("pas_round({})", x),
+            Op::Sqrt => format!("pas_bits(pas_sqrt({}, {}))", x, self.sites.number(&quad.range)),
+            Op::Ln => format!("pas_bits(pas_ln({}, {}))", x, self.sites.number(&quad.range)),
+            Op::Sin => format!("pas_bits(sin({}))", x),
+            Op::Cos => format!("pas_bits(cos({}))", x),
+            Op::Exp => format!("pas_bits(exp({}))", x),
+            Op::Arctan => format!("pas_bits(atan({}))", x),
+            Op::Check | Op::Bounds | Op::NoCase => {
+                let ty = match arg2 {
+                    Arg::Type(ty) => ty,
+                    arg => unreachable!("{:?} is not a type", arg)
+                };
+                let site = self.sites.number(&quad.range);
+                let statement = match quad.op {
+                    Op::NoCase => self.no_case(&a, ty, site),
+                    op => {
+                        let bounds = ty.bounds().expect("checked types have bounds");
+                        let (low, high) = (integer(bounds.start as i64), integer(bounds.end as i64));
+                        if op == Op::Check {
+                            format!("pas_check({}, {}, {}, {}, {});", a, low, high, string(&ty.to_string()), site)
+                        } else {
+                            format!("pas_index({}, {}, {}, {});", a, low, high, site)
+                        }
+                    }
+                };
+                return self.line(&statement);
+            },
+            Op::Deref => format!("(intptr_t) pas_deref(PAS_POINTER({}), {})", a, self.sites.number(&quad.range)),
+            Op::Goto => return self.line(&format!("goto L{};", quad.target().expect("jumps have a target"))),
+            Op::If => return self.line(&format!("if ({}) goto L{};", a, quad.target().expect("jumps have a target"))),
+            Op::IfFalse => return self.line(&format!("if (!{}) goto L{};", a, quad.target().expect("jumps have a target"))),
+            Op::Param => return self.params.push(arg1.clone()),
+            Op::Call => return self.call(quad),
+            Op::Return => return self.exit(procedure, arg1)
+        };
+        let statement = format!("{} = {};", self.place(result), value);
+        self.line(&statement);
+    }
+
+    /// What the runtime reports of a value no case label matches: its kind,
+    /// and the names of the literals of an enumeration
+    fn no_case(&self, value: &str, ty: &Type, site: usize) -> String {
+        let (kind, names, count) = match ty {
+            Type::Char => ('c', "NULL".to_string(), 0),
+            Type::Boolean => ('b', "NULL".to_string(), 0),
+            Type::Enum(names) => {
+                let names = names.iter().map(|name| string(name)).collect::<Vec<_>>();
+                ('e', format!("(const char *const []) {{{}}}", names.join(", ")), names.len())
+            },
+            _ => ('i', "NULL".to_string(), 0)
+        };
+        format!("pas_no_case({}, '{}', {}, {}, {});", value, kind, names, count, site)
+    }
+
+    /// Leaves a procedure. A block a function gives is copied out of its
+    /// frame first, which goes as it returns.
+    fn exit(&mut self, procedure: &Procedure, value: &Arg) {
+        if procedure.scope == Program::GLOBAL {
+            return self.line("return pas_exit();");
+        }
+        let returns = subprogram_of(self.program, procedure.scope).and_then(|subprogram| subprogram.result.as_ref());
+        match returns {
+            Some(ty) if is_block(ty) => {
+                let statement = format!("{{ static int64_t result[{}]; pas_copy(result, PAS_POINTER({}), {}); return (intptr_t) result; }}",
+                                        size(ty) / 8, self.value(value), size(ty));
+                self.line(&statement);
+            },
+            Some(_) => {
+                let statement = format!("return {};", self.value(value));
+                self.line(&statement);
+            },
+            None => self.line("return;")
+        }
+    }
+
+    /// A call to a subprogram, with the parameters of the quadruples before
+    /// it, or to a routine of the runtime
+    fn call(&mut self, quad: &Quad) {
+        let params = std::mem::take(&mut self.params);
+        let name = match &quad.arg1 {
+            Arg::Name(name) => name,
+            arg => unreachable!("{:?} is not a procedure", arg)
+        };
+        let (call, kind) = match callee(self.program, name) {
+            Some(subprogram) => {
+                let parent = self.program.scopes[subprogram.scope].parent.expect("subprograms are in a scope");
+                let mut args = vec![self.frame_of(parent)];
+                args.extend(params.iter().map(|param| self.value(param)));
+                (format!("{}({})", function_name(name), args.join(", ")), 'i')
+            },
+            None => {
+                let routine = routine(name);
+                let mut args = params.iter().zip(routine.params.chars())
+                    .map(|(param, kind)| match kind {
+                        'f' => self.real(param),
+                        'p' => format!("PAS_POINTER({})", self.value(param)),
+                        _ => self.value(param)
+                    })
+                    .collect::<Vec<_>>();
+                if routine.checked {
+                    args.push(self.sites.number(&quad.range).to_string());
+                }
+                (format!("{}({})", routine.function, args.join(", ")), routine.result.unwrap_or('i'))
+            }
+        };
+        let statement = if quad.result.is_none() {
+            format!("{};", call)
+        } else {
+            let value = match kind {
+                'f' => format!("pas_bits({})", call),
+                'p' => format!("(intptr_t) {}", call),
+                _ => call
+            };
+            format!("{} = {};", self.place(&quad.result), value)
+        };
+        self.line(&statement);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+    use super::*;
+    use crate::emit::tests::*;
+
+    /// Compiles a program with the system C compiler and runs it
+    fn run(name: &str, program: &Program, input: &str) -> (String, Option<String>) {
+        let dir = scratch(&format!("c-{}", name));
+        std::fs::write(dir.join("program.c"), emit(program, 0)).unwrap();
+        let compiled = Command::new("cc")
+            .args(["-std=c99", "-fwrapv", "-o", "program", "program.c", "-lm"])
+            .current_dir(&dir)
+            .status()
+            .unwrap();
+        assert!(compiled.success(), "{} does not compile", name);
+        let outcome = execute(&mut Command::new(dir.join("program")), input);
+        std::fs::remove_dir_all(dir).unwrap();
+        outcome
+    }
+
+    #[test]
+    fn samples_behave_as_interpreted() {
+        require("cc");
+        for (name, program, input) in samples() {
+            assert_eq!(run(&name, &program, &input), interpret(&program, &input), "{}", name);
+        }
+    }
+
+    #[test]
+    fn runtime_errors() {
+        require("cc");
+        for (index, body) in FAULTS.iter().enumerate() {
+            let program = fault(body);
+            let (_, error) = run(&format!("fault-{}", index), &program, "");
+            let (_, expected) = interpret(&program, "");
+            assert_eq!(error, expected, "{}", body);
+        }
+    }
+}
@@ -0,0 +1,749 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use crate::ast::program::{Program, ScopeId};
+use crate::ast::subprogram::Subprogram;
+use crate::ast::types::Type;
+use crate::emit::{callee, is_block, levels, routine, size, source_line, subprogram_of, Layout, Sites};
+use crate::ir::{Arg, Module, Op, Procedure, Quad};
+use crate::ir::lower::lower;
+use crate::ir::opt::optimize;
+
+/// The functions of the C runtime the translation calls
+const DECLARATIONS: &str = "\
+declare void @pas_readln(i32)
+declare i64 @pas_read_integer(i32)
+declare double @pas_read_real(i32)
+declare i64 @pas_read_char(i32)
+declare void @pas_write_integer(i64)
+declare void @pas_write_real(double)
+declare void @pas_write_char(i64)
+declare void @pas_write_boolean(i64)
+declare void @pas_write_string(ptr, i64)
+declare void @pas_write_integer_width(i64, i64, i32)
+declare void @pas_write_real_width(double, i64, i32)
+declare void @pas_write_real_fixed(double, i64, i64, i32)
+declare void @pas_write_char_width(i64, i64, i32)
+declare void @pas_write_boolean_width(i64, i64, i32)
+declare void @pas_write_string_width(ptr, i64, i64, i32)
+declare void @pas_writeln()
+declare i32 @pas_exit()
+declare i64 @pas_eof()
+declare i64 @pas_eoln()
+declare i64 @pas_div(i64, i64, i32)
+declare i64 @pas_mod(i64, i64, i32)
+declare double @pas_rdiv(double, double, i32)
+declare i64 @pas_abs(i64)
+declare i64 @pas_add_checked(i64, i64, i32)
+declare i64 @pas_sub_checked(i64, i64, i32)
+declare i64 @pas_mul_checked(i64, i64, i32)
+declare i64 @pas_div_checked(i64, i64, i32)
+declare i64 @pas_neg_checked(i64, i32)
+declare i64 @pas_abs_checked(i64, i32)
+declare double @pas_sqrt(double, i32)
+declare double @pas_ln(double, i32)
+declare i64 @pas_trunc(double)
+declare i64 @pas_round(double)
+declare i64 @pas_check(i64, i64, i64, ptr, i32)
+declare i64 @pas_index(i64, i64, i64, i32)
+declare void @pas_no_case(i64, i8 signext, ptr, i64, i32)
+declare i64 @pas_compare(ptr, ptr, i64)
+declare void @pas_zero(ptr, i64)
+declare void @pas_copy(ptr, ptr, i64)
+declare void @pas_set_add(ptr, i64, i32)
+declare i64 @pas_set_has(ptr, i64)
+declare void @pas_set_combine(ptr, ptr, ptr, i64)
+declare i64 @pas_set_eq(ptr, ptr)
+declare i64 @pas_set_le(ptr, ptr)
+declare ptr @pas_new(i64)
+declare ptr @pas_deref(ptr, i32)
+declare void @pas_dispose(ptr, i32)
+declare double @sin(double)
+declare double @cos(double)
+declare double @exp(double)
+declare double @atan(double)
+declare double @llvm.fabs.f64(double)
+declare void @llvm.memset.p0.i64(ptr, i8, i64, i1)
+";
+
+/// The metadata every module starts with: the compile unit, the source
+/// file, the one subroutine type all functions share, and the module flags
+const COMPILE_UNIT: usize = 0;
+const FILE: usize = 1;
+const SUBROUTINE_TYPE: usize = 2;
+const FLAGS: usize = 3;
+
+/// Translates a checked program into textual LLVM IR, with opaque pointers
+/// and a line table, from its quadruples. Temporaries are `i64` stack slots
+/// that `mem2reg` promotes, a real being kept as its bits, and the frames are
+/// arrays of words laid out as in the C translation: that of the main
+/// program is a global, and every subprogram takes the frame of the scope it
+/// is declared in and keeps it as the first word of its own. Input, output
+/// and the runtime checks are calls into the C runtime, given by
+/// `--emit runtime`.
+///
+/// The quadruples are optimized at `level`, as `-O` gives it.
+pub fn emit(program: &Program, level: u8) -> String {
+    let mut generator = Generator {
+        program,
+        levels: levels(program),
+        layouts: Layout::all(program),
+        strings: vec![],
+        constants: String::new(),
+        metadata: vec![
+            "distinct !DICompileUnit(language: DW_LANG_Pascal83, file: !1, producer: \"mini-pascal500\", \
+             isOptimized: false, runtimeVersion: 0, emissionKind: LineTablesOnly)".to_string(),
+            format!("!DIFile(filename: \"{}.p\", directory: \"\")", program.name),
+            "!DISubroutineType(types: !{})".to_string(),
+            "!{i32 2, !\"Debug Info Version\", i32 3}".to_string(),
+            "!{i32 2, !\"Dwarf Version\", i32 4}".to_string()
+        ],
+        locations: HashMap::new(),
+        sites: Sites::default(),
+        out: String::new(),
+        scope: Program::GLOBAL,
+        debug_scope: 0,
+        location: None,
+        values: 0,
+        slots: vec![],
+        params: vec![],
+        next: None
+    };
+    let (module, _) = optimize(lower(program), level);
+    generator.generate(module)
+}
+
+struct Generator<'a> {
+    program: &'a Program,
+    levels: Vec<usize>,
+    layouts: Vec<Layout>,
+    /// The string constants defined so far
+    strings: Vec<String>,
+    /// The definitions of the constants
+    constants: String,
+    /// The metadata nodes, numbered by their index
+    metadata: Vec<String>,
+    /// The `DILocation` of every line, by subprogram
+    locations: HashMap<(usize, usize), usize>,
+    sites: Sites,
+    out: String,
+    scope: ScopeId,
+    /// The `DISubprogram` of the function being generated
+    debug_scope: usize,
+    /// The `DILocation` of the code being generated
+    location: Option<usize>,
+    values: usize,
+    /// Where each memory temporary of the procedure is in its frame
+    slots: Vec<i32>,
+    /// The parameters of the next call
+    params: Vec<Arg>,
+    /// The block after the one being generated, which it falls through to
+    next: Option<usize>
+}
+
+/// The exact constant of a real, which LLVM takes as the hexadecimal of its
+/// bits
+fn real(value: f64) -> String {
+    format!("0x{:016X}", value.to_bits())
+}
+
+impl<'a> Generator<'a> {
+    fn generate(&mut self, module: Module) -> String {
+        let program = self.program;
+        let (main, subprograms) = module.procedures.split_last().expect("the main program is lowered last");
+        for procedure in subprograms {
+            let subprogram = subprogram_of(program, procedure.scope).expect("procedures are lowered from subprograms");
+            self.subprogram(subprogram, procedure);
+        }
+        let globals = self.main(main);
+        let mut sites = self.sites.texts(program).iter().map(|text| format!("ptr {}", self.string(text))).collect::<Vec<_>>();
+        sites.push(format!("ptr {}", self.string("")));
+        let _ = writeln!(self.constants, "@pas_sites = constant [{} x ptr] [{}]", sites.len(), sites.join(", "));
+
+        let metadata = self.metadata.iter().enumerate()
+            .map(|(index, node)| format!("!{} = {}\n", index, node))
+            .collect::<String>();
+        format!("; program {0}, compiled from Pascal\n\
+                 ; build with llc -relocation-model=pic {0}.ll and link with the runtime from --emit runtime\n\
+                 source_filename = \"{0}.p\"\n\n\
+                 @globals = internal global [{1} x i64] zeroinitializer, align 8\n{2}\n{3}\n{4}\n\
+                 !llvm.dbg.cu = !{{!{5}}}\n!llvm.module.flags = !{{!{6}, !{7}}}\n\n{8}",
+                program.name, globals, self.constants, DECLARATIONS, self.out,
+                COMPILE_UNIT, FLAGS, FLAGS + 1, metadata)
+    }
+
+    /// Makes the code generated next be located on `line`
+    fn locate(&mut self, line: usize) {
+        let key = (line, self.debug_scope);
+        let id = match self.locations.get(&key) {
+            Some(id) => *id,
+            None => {
+                let id = self.metadata.len();
+                self.metadata.push(format!("!DILocation(line: {}, scope: !{})", line, self.debug_scope));
+                self.locations.insert(key, id);
+                id
+            }
+        };
+        self.location = Some(id);
+    }
+
+    fn instruction(&mut self, text: &str) {
+        match self.location {
+            Some(location) => {
+                let _ = writeln!(self.out, "  {}, !dbg !{}", text, location);
+            },
+            None => {
+                let _ = writeln!(self.out, "  {}", text);
+            }
+        }
+    }
+
+    /// Emits an instruction giving a value, and names the value
+    fn value(&mut self, text: &str) -> String {
+        self.values += 1;
+        let name = format!("%v{}", self.values);
+        self.instruction(&format!("{} = {}", name, text));
+        name
+    }
+
+    /// A constant holding a NUL-terminated string
+    fn string(&mut self, text: &str) -> String {
+        if let Some(index) = self.strings.iter().position(|known| known == text) {
+            return format!("@.str.{}", index);
+        }
+        let bytes = text.bytes()
+            .map(|byte| match byte {
+                b' '..=b'~' if byte != b'"' && byte != b'\\' => (byte as char).to_string(),
+                _ => format!("\\{:02X}", byte)
+            })
+            .collect::<String>();
+        let index = self.strings.len();
+        let _ = writeln!(self.constants, "@.str.{} = private unnamed_addr constant [{} x i8] c\"{}\\00\"",
+                         index, text.len() + 1, bytes);
+        self.strings.push(text.to_string());
+        format!("@.str.{}", index)
+    }
+
+    /// Starts a function and its `DISubprogram`, laying out the memory
+    /// temporaries of its procedure after the variables of its scope, and
+    /// gives the size of its frame in words
+    fn begin(&mut self, procedure: &Procedure, name: &str, start: usize) -> i32 {
+        self.scope = procedure.scope;
+        let line = source_line(self.program, &(start..start));
+        self.debug_scope = self.metadata.len();
+        self.metadata.push(format!("distinct !DISubprogram(name: \"{}\", scope: !{}, file: !{}, line: {}, type: !{}, \
+                                    scopeLine: {}, spFlags: DISPFlagDefinition, unit: !{})",
+                                   name, FILE, FILE, line, SUBROUTINE_TYPE, line, COMPILE_UNIT));
+        self.location = None;
+        self.values = 0;
+        let mut offset = (self.layouts[procedure.scope].size + 7) / 8 * 8;
+        self.slots.clear();
+        for size in &procedure.slots {
+            self.slots.push(offset);
+            offset += (size + 7) / 8 * 8;
+        }
+        (offset / 8).max(1)
+    }
+
+    /// The temporaries and the code of the quadruples, after what the entry
+    /// block already holds
+    fn body(&mut self, procedure: &Procedure) {
+        for temp in 1..=procedure.temps {
+            self.instruction(&format!("%t{} = alloca i64, align 8", temp));
+            self.instruction(&format!("store i64 0, ptr %t{}, align 8", temp));
+        }
+        let first = procedure.blocks.first().expect("procedures have a block").label;
+        self.instruction(&format!("br label %L{}", first));
+        for (index, block) in procedure.blocks.iter().enumerate() {
+            self.next = procedure.blocks.get(index + 1).map(|block| block.label);
+            let _ = writeln!(self.out, "L{}:", block.label);
+            for quad in &block.quads {
+                self.locate(quad.line);
+                self.quad(procedure, quad);
+            }
+            if !block.quads.last().is_some_and(|quad| quad.op.is_terminator()) {
+                let next = self.next.expect("the last block returns");
+                self.instruction(&format!("br label %L{}", next));
+            }
+        }
+        self.out.push_str("}\n\n");
+    }
+
+    fn subprogram(&mut self, subprogram: &Subprogram, procedure: &Procedure) {
+        let words = self.begin(procedure, &subprogram.name, subprogram.range.start);
+        let params = (1..=subprogram.params.len())
+            .map(|index| format!(", i64 %p{}", index))
+            .collect::<String>();
+        let result = if subprogram.result.is_some() { "i64" } else { "void" };
+        let _ = writeln!(self.out, "define internal {} @{}(i64 %link{}) !dbg !{} {{\nentry:",
+                         result, procedure.name, params, self.debug_scope);
+        self.locate(source_line(self.program, &subprogram.range));
+        self.instruction(&format!("%frame = alloca [{} x i64], align 8", words));
+        self.instruction(&format!("call void @llvm.memset.p0.i64(ptr %frame, i8 0, i64 {}, i1 false)", words * 8));
+        self.instruction("%f = ptrtoint ptr %frame to i64");
+        self.instruction("store i64 %link, ptr %frame, align 8");
+        for (index, param) in subprogram.params.iter().enumerate() {
+            let offset = self.layouts[subprogram.scope].fields[&param.name];
+            let field = self.value(&format!("getelementptr i8, ptr %frame, i64 {}", offset));
+            if is_block(&param.r#type) && !param.by_ref {
+                let from = self.value(&format!("inttoptr i64 %p{} to ptr", index + 1));
+                self.instruction(&format!("call void @pas_copy(ptr {}, ptr {}, i64 {})", field, from, size(&param.r#type)));
+            } else {
+                self.instruction(&format!("store i64 %p{}, ptr {}, align 8", index + 1, field));
+            }
+        }
+        self.body(procedure);
+    }
+
+    /// The main program, giving the size in words of its frame, a global
+    /// with its memory temporaries
+    fn main(&mut self, procedure: &Procedure) -> i32 {
+        let words = self.begin(procedure, &self.program.name, 0);
+        let _ = writeln!(self.out, "define i32 @main() !dbg !{} {{\nentry:", self.debug_scope);
+        self.locate(1);
+        self.instruction("%f = ptrtoint ptr @globals to i64");
+        self.body(procedure);
+        words
+    }
+
+    /// The address of the frame of `scope`, the innermost one reachable from
+    /// the running procedure
+    fn frame_of(&mut self, scope: ScopeId) -> String {
+        if scope == self.scope {
+            return "%f".to_string();
+        }
+        if scope == Program::GLOBAL {
+            return "ptrtoint (ptr @globals to i64)".to_string();
+        }
+        // the link is at the start of a frame
+        let mut frame = "%f".to_string();
+        for _ in self.levels[scope]..self.levels[self.scope] {
+            let pointer = self.value(&format!("inttoptr i64 {} to ptr", frame));
+            frame = self.value(&format!("load i64, ptr {}, align 8", pointer));
+        }
+        frame
+    }
+
+    /// The address of a variable or a memory temporary
+    fn address(&mut self, arg: &Arg) -> String {
+        match arg {
+            Arg::Var(scope, name) => {
+                let layout = &self.layouts[*scope];
+                let offset = layout.fields.get(name).copied().unwrap_or(layout.result);
+                let frame = self.frame_of(*scope);
+                self.value(&format!("add i64 {}, {}", frame, offset))
+            },
+            Arg::Slot(slot) => {
+                let offset = self.slots[slot - 1];
+                self.value(&format!("add i64 %f, {}", offset))
+            },
+            arg => unreachable!("{:?} is not in memory", arg)
+        }
+    }
+
+    /// The pointer to where a temporary or a variable is kept
+    fn place(&mut self, arg: &Arg) -> String {
+        match arg {
+            Arg::Temp(temp) => format!("%t{}", temp),
+            variable => {
+                let address = self.address(variable);
+                self.value(&format!("inttoptr i64 {} to ptr", address))
+            }
+        }
+    }
+
+    fn pointer(&mut self, arg: &Arg) -> String {
+        let address = self.int(arg);
+        self.value(&format!("inttoptr i64 {} to ptr", address))
+    }
+
+    /// The value of a field, the bits of a real
+    fn int(&mut self, arg: &Arg) -> String {
+        match arg {
+            Arg::Int(value) => value.to_string(),
+            Arg::Real(value) => (value.to_bits() as i64).to_string(),
+            arg => {
+                let place = self.place(arg);
+                self.value(&format!("load i64, ptr {}, align 8", place))
+            }
+        }
+    }
+
+    /// The real of a field
+    fn real(&mut self, arg: &Arg) -> String {
+        match arg {
+            Arg::Int(value) => real(*value as f64),
+            Arg::Real(value) => real(*value),
+            arg => {
+                let bits = self.int(arg);
+                self.value(&format!("bitcast i64 {} to double", bits))
+            }
+        }
+    }
+
+    fn assign(&mut self, result: &Arg, value: &str) {
+        let place = self.place(result);
+        self.instruction(&format!("store i64 {}, ptr {}, align 8", value, place));
+    }
+
+    /// Assigns the bits of a real
+    fn assign_real(&mut self, result: &Arg, value: &str) {
+        let bits = self.value(&format!("bitcast double {} to i64", value));
+        self.assign(result, &bits);
+    }
+
+    fn quad(&mut self, procedure: &Procedure, quad: &Quad) {
+        let (arg1, arg2, result) = (&quad.arg1, &quad.arg2, &quad.result);
+        let site = self.sites.of(self.program, quad);
+        let integer = |op| match op {
+            Op::Add => Some("add"),
+            Op::Sub => Some("sub"),
+            Op::Mul => Some("mul"),
+            _ => None
+        };
+        let compare = |op| match op {
+            Op::Eq => Some("icmp eq"),
+            Op::Ne => Some("icmp ne"),
+            Op::Lt => Some("icmp slt"),
+            Op::Le => Some("icmp sle"),
+            Op::Gt => Some("icmp sgt"),
+            Op::Ge => Some("icmp sge"),
+            Op::FEq => Some("fcmp oeq"),
+            Op::FNe => Some("fcmp une"),
+            Op::FLt => Some("fcmp olt"),
+            Op::FLe => Some("fcmp ole"),
+            Op::FGt => Some("fcmp ogt"),
+            Op::FGe => Some("fcmp oge"),
+            _ => None
+        };
+        let arithmetic = |op| match op {
+            Op::FAdd => Some("fadd"),
+            Op::FSub => Some("fsub"),
+            Op::FMul => Some("fmul"),
+            _ => None
+        };
+        let libm = |op| match op {
+            Op::Sin => Some("sin"),
+            Op::Cos => Some("cos"),
+            Op::Exp => Some("exp"),
+            Op::Arctan => Some("atan"),
+            Op::FAbs => Some("llvm.fabs.f64"),
+            _ => None
+        };
+        if let Some(instruction) = integer(quad.op) {
+            let (a, b) = (self.int(arg1), self.int(arg2));
+            let value = self.value(&format!("{} i64 {}, {}", instruction, a, b));
+            return self.assign(result, &value);
+        }
+        if let Some(instruction) = compare(quad.op) {
+            let (a, b, ty) = if instruction.starts_with('f') {
+                (self.real(arg1), self.real(arg2), "double")
+            } else {
+                (self.int(arg1), self.int(arg2), "i64")
+            };
+            let holds = self.value(&format!("{} {} {}, {}", instruction, ty, a, b));
+            let value = self.value(&format!("zext i1 {} to i64", holds));
+            return self.assign(result, &value);
+        }
+        if let Some(instruction) = arithmetic(quad.op) {
+            let (x, y) = (self.real(arg1), self.real(arg2));
+            let value = self.value(&format!("{} double {}, {}", instruction, x, y));
+            return self.assign_real(result, &value);
+        }
+        if let Some(function) = libm(quad.op) {
+            let x = self.real(arg1);
+            let value = self.value(&format!("call double @{}(double {})", function, x));
+            return self.assign_real(result, &value);
+        }
+        match quad.op {
+            Op::Assign => {
+                let a = self.int(arg1);
+                self.assign(result, &a);
+            },
+            Op::Address => {
+                let address = self.address(arg1);
+                self.assign(result, &address);
+            },
+            Op::Load => {
+                let pointer = self.pointer(arg1);
+                let value = self.value(&format!("load i64, ptr {}, align 8", pointer));
+                self.assign(result, &value);
+            },
+            Op::Store => {
+                let value = self.int(arg1);
+                let pointer = self.pointer(result);
+                self.instruction(&format!("store i64 {}, ptr {}, align 8", value, pointer));
+            },
+            Op::Div | Op::Mod => {
+                let (a, b) = (self.int(arg1), self.int(arg2));
+                let function = if quad.op == Op::Div { "pas_div" } else { "pas_mod" };
+                let value = self.value(&format!("call i64 @{}(i64 {}, i64 {}, i32 {})", function, a, b, site));
+                self.assign(result, &value);
+            },
+            Op::Neg => {
+                let a = self.int(arg1);
+                let value = self.value(&format!("sub i64 0, {}", a));
+                self.assign(result, &value);
+            },
+            Op::FDiv => {
+                let (x, y) = (self.real(arg1), self.real(arg2));
+                let value = self.value(&format!("call double @pas_rdiv(double {}, double {}, i32 {})", x, y, site));
+                self.assign_real(result, &value);
+            },
+            Op::FNeg => {
+                let x = self.real(arg1);
+                let value = self.value(&format!("fneg double {}", x));
+                self.assign_real(result, &value);
+            },
+            Op::Not | Op::Odd => {
+                let a = self.int(arg1);
+                let instruction = if quad.op == Op::Not { "xor" } else { "and" };
+                let value = self.value(&format!("{} i64 {}, 1", instruction, a));
+                self.assign(result, &value);
+            },
+            Op::Abs => {
+                let a = self.int(arg1);
+                let value = self.value(&format!("call i64 @pas_abs(i64 {})", a));
+                self.assign(result, &value);
+            },
+            Op::Float => {
+                let a = self.int(arg1);
+                let value = self.value(&format!("sitofp i64 {} to double", a));
+                self.assign_real(result, &value);
+            },
+            Op::Trunc | Op::Round => {
+                let x = self.real(arg1);
+                let function = if quad.op == Op::Trunc { "pas_trunc" } else { "pas_round" };
+                let value = self.value(&format!("call i64 @{}(double {})", function, x));
+                self.assign(result, &value);
+            },
+            Op::Sqrt | Op::Ln => {
+                let x = self.real(arg1);
+                let function = if quad.op == Op::Sqrt { "pas_sqrt" } else { "pas_ln" };
+                let value = self.value(&format!("call double @{}(double {}, i32 {})", function, x, site));
+                self.assign_real(result, &value);
+            },
+            Op::Check | Op::Bounds | Op::NoCase => {
+                let ty = match arg2 {
+                    Arg::Type(ty) => ty,
+                    arg => unreachable!("{:?} is not a type", arg)
+                };
+                let a = self.int(arg1);
+                if quad.op == Op::NoCase {
+                    self.no_case(&a, ty, site);
+                    return self.instruction("unreachable");
+                }
+                let bounds = ty.bounds().expect("checked types have bounds");
+                if quad.op == Op::Check {
+                    let name = self.string(&ty.to_string());
+                    self.instruction(&format!("call i64 @pas_check(i64 {}, i64 {}, i64 {}, ptr {}, i32 {})",
+                                              a, bounds.start, bounds.end, name, site));
+                } else {
+                    self.instruction(&format!("call i64 @pas_index(i64 {}, i64 {}, i64 {}, i32 {})",
+                                              a, bounds.start, bounds.end, site));
+                }
+            },
+            Op::Deref => {
+                let pointer = self.pointer(arg1);
+                let checked = self.value(&format!("call ptr @pas_deref(ptr {}, i32 {})", pointer, site));
+                let value = self.value(&format!("ptrtoint ptr {} to i64", checked));
+                self.assign(result, &value);
+            },
+            Op::Goto => {
+                let target = quad.target().expect("jumps have a target");
+                self.instruction(&format!("br label %L{}", target));
+            },
+            Op::If | Op::IfFalse => {
+                let a = self.int(arg1);
+                let holds = self.value(&format!("icmp ne i64 {}, 0", a));
+                let target = quad.target().expect("jumps have a target");
+                let next = self.next.expect("a conditional jump falls through to a block");
+                let (then, otherwise) = if quad.op == Op::If { (target, next) } else { (next, target) };
+                self.instruction(&format!("br i1 {}, label %L{}, label %L{}", holds, then, otherwise));
+            },
+            Op::Param => self.params.push(arg1.clone()),
+            Op::Call => self.call(quad),
+            Op::Return => self.exit(procedure, arg1),
+            op => unreachable!("{} is translated above", op)
+        }
+    }
+
+    /// Reports a value no case label matches, with its kind and the names of
+    /// the literals of an enumeration
+    fn no_case(&mut self, value: &str, ty: &Type, site: usize) {
+        let (kind, names, count) = match ty {
+            Type::Char => ('c', "null".to_string(), 0),
+            Type::Boolean => ('b', "null".to_string(), 0),
+            Type::Enum(names) => {
+                let strings = names.iter().map(|name| format!("ptr {}", self.string(name))).collect::<Vec<_>>();
+                let table = format!("@.names.{}", self.strings.len());
+                let _ = writeln!(self.constants, "{} = private unnamed_addr constant [{} x ptr] [{}]",
+                                 table, strings.len(), strings.join(", "));
+                ('e', table, names.len())
+            },
+            _ => ('i', "null".to_string(), 0)
+        };
+        self.instruction(&format!("call void @pas_no_case(i64 {}, i8 signext {}, ptr {}, i64 {}, i32 {})",
+                                  value, kind as u8, names, count, site));
+    }
+
+    /// Leaves a procedure. A block a function gives is copied out of its
+    /// frame first, which goes as it returns.
+    fn exit(&mut self, procedure: &Procedure, value: &Arg) {
+        if procedure.scope == Program::GLOBAL {
+            let code = self.value("call i32 @pas_exit()");
+            return self.instruction(&format!("ret i32 {}", code));
+        }
+        let returns = subprogram_of(self.program, procedure.scope).and_then(|subprogram| subprogram.result.as_ref());
+        match returns {
+            Some(ty) if is_block(ty) => {
+                let buffer = format!("@\"{}.result\"", procedure.name);
+                let _ = writeln!(self.constants, "{} = internal global [{} x i64] zeroinitializer, align 8",
+                                 buffer, size(ty) / 8);
+                let from = self.pointer(value);
+                self.instruction(&format!("call void @pas_copy(ptr {}, ptr {}, i64 {})", buffer, from, size(ty)));
+                let address = self.value(&format!("ptrtoint ptr {} to i64", buffer));
+                self.instruction(&format!("ret i64 {}", address));
+            },
+            Some(_) => {
+                let value = self.int(value);
+                self.instruction(&format!("ret i64 {}", value));
+            },
+            None => self.instruction("ret void")
+        }
+    }
+
+    /// A call to a subprogram, with the parameters of the quadruples before
+    /// it, or to a routine of the runtime
+    fn call(&mut self, quad: &Quad) {
+        let params = std::mem::take(&mut self.params);
+        let name = match &quad.arg1 {
+            Arg::Name(name) => name,
+            arg => unreachable!("{:?} is not a procedure", arg)
+        };
+        let (call, kind) = match callee(self.program, name) {
+            Some(subprogram) => {
+                let parent = self.program.scopes[subprogram.scope].parent.expect("subprograms are in a scope");
+                let mut args = vec![format!("i64 {}", self.frame_of(parent))];
+                for param in &params {
+                    args.push(format!("i64 {}", self.int(param)));
+                }
+                let result = if subprogram.result.is_some() { "i64" } else { "void" };
+                (format!("call {} @{}({})", result, name, args.join(", ")), subprogram.result.as_ref().map(|_| 'i'))
+            },
+            None => {
+                let routine = routine(name);
+                let mut args = vec![];
+                for (param, kind) in params.iter().zip(routine.params.chars()) {
+                    args.push(match kind {
+                        'f' => format!("double {}", self.real(param)),
+                        'p' => format!("ptr {}", self.pointer(param)),
+                        _ => format!("i64 {}", self.int(param))
+                    });
+                }
+                if routine.checked {
+                    args.push(format!("i32 {}", self.sites.number(&quad.range)));
+                }
+                let result = match routine.result {
+                    Some('f') => "double",
+                    Some('p') => "ptr",
+                    Some(_) => "i64",
+                    None => "void"
+                };
+                (format!("call {} @{}({})", result, routine.function, args.join(", ")), routine.result)
+            }
+        };
+        if quad.result.is_none() || kind.is_none() {
+            return self.instruction(&call);
+        }
+        let value = self.value(&call);
+        match kind {
+            Some('f') => self.assign_real(&quad.result, &value),
+            Some('p') => {
+                let address = self.value(&format!("ptrtoint ptr {} to i64", value));
+                self.assign(&quad.result, &address);
+            },
+            _ => self.assign(&quad.result, &value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+    use super::*;
+    use crate::emit::tests::*;
+
+    /// The flags LLVM releases before opaque pointers were the default
+    /// need to read the translation
+    fn llc_flags() -> Vec<&'static str> {
+        let version = Command::new("llc").arg("--version").output().unwrap();
+        let version = String::from_utf8_lossy(&version.stdout);
+        let major = version.split("LLVM version ").nth(1)
+            .and_then(|rest| rest.split('.').next())
+            .and_then(|major| major.parse::<u32>().ok())
+            .unwrap_or(u32::MAX);
+        let mut flags = vec!["-relocation-model=pic"];
+        if major < 15 {
+            flags.push("-opaque-pointers");
+        }
+        flags
+    }
+
+    /// Compiles a program with llc, links it with the runtime and runs it
+    fn run(name: &str, program: &Program, input: &str) -> (String, Option<String>) {
+        let dir = scratch(&format!("llvm-{}", name));
+        std::fs::write(dir.join("program.ll"), emit(program, 0)).unwrap();
+        std::fs::write(dir.join("runtime.c"), format!("#define PAS_API\n{}", crate::emit::c::RUNTIME)).unwrap();
+        let compiled = Command::new("llc")
+            .args(llc_flags())
+            .args(["-o", "program.s", "program.ll"])
+            .current_dir(&dir)
+            .status()
+            .unwrap();
+        assert!(compiled.success(), "{} does not compile", name);
+        let linked = Command::new("cc")
+            .args(["-o", "program", "program.s", "runtime.c", "-lm"])
+            .current_dir(&dir)
+            .status()
+            .unwrap();
+        assert!(linked.success(), "{} does not link", name);
+        let outcome = execute(&mut Command::new(dir.join("program")), input);
+        std::fs::remove_dir_all(dir).unwrap();
+        outcome
+    }
+
+    #[test]
+    fn samples_behave_as_interpreted() {
+        require("llc");
+        require("cc");
+        for (name, program, input) in samples() {
+            assert_eq!(run(&name, &program, &input), interpret(&program, &input), "{}", name);
+        }
+    }
+
+    #[test]
+    fn runtime_errors() {
+        require("llc");
+        require("cc");
+        for (index, body) in FAULTS.iter().enumerate() {
+            let program = fault(body);
+            let (_, error) = run(&format!("fault-{}", index), &program, "");
+            let (_, expected) = interpret(&program, "");
+            assert_eq!(error, expected, "{}", body);
+        }
+    }
+
+    #[test]
+    fn debug_locations() {
+        let program = check("
+            program located;
+            var i: integer;
+            begin
+              i := 6 div i
+            end.
+        ");
+        let ir = emit(&program, 0);
+        assert!(ir.contains("!7 = !DILocation(line: 5, scope: !5)"), "{}", ir);
+        assert!(ir.contains("call i64 @pas_div(i64 6, i64 %v3, i32 0), !dbg !7"), "{}", ir);
+    }
+}
@@ -0,0 +1,821 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::ops::Range;
+use crate::ast::program::{Program, ScopeId};
+use crate::ast::subprogram::Subprogram;
+use crate::ast::types::Type;
+use crate::emit::{callee, levels, size, subprogram_of, Layout, Sites};
+use crate::ir::{Arg, Module, Op, Procedure, Quad};
+use crate::ir::lower::lower;
+use crate::ir::opt::optimize;
+
+#[cfg(test)]
+mod simulator;
+
+/// The runtime every program is assembled with, after its own code
+const RUNTIME: &str = include_str!("runtime.s");
+
+/// Translates a checked program into MIPS assembly for SPIM and MARS, with
+/// the runtime appended, from its quadruples. Integers are 64 bits as in
+/// the interpreter, kept in pairs of registers with the low word first.
+/// Every temporary of a procedure has 8 bytes in its frame, which each
+/// quadruple loads its operands from and stores its result to.
+///
+/// The frame of the main program is static. A subprogram is called with
+/// the frame of the scope it is declared in and its arguments pushed, in
+/// that order, and builds its activation record below them: the return
+/// address and the frame pointer of its caller on top, then a frame laid
+/// out as in the other translations, which $fp points to. Results are
+/// given in $v0 and $v1, a real as its bits and a block as its address.
+///
+/// The quadruples are optimized at `level`, as `-O` gives it.
+pub fn emit(program: &Program, level: u8) -> String {
+    let mut generator = Generator {
+        program,
+        levels: levels(program),
+        layouts: Layout::all(program),
+        data: String::new(),
+        constants: HashMap::new(),
+        out: String::new(),
+        branches: 0,
+        scope: Program::GLOBAL,
+        temps: 0,
+        slots: vec![],
+        params: vec![],
+        sites: Sites::default(),
+        line: 0,
+        range: 0..0
+    };
+    let (module, _) = optimize(lower(program), level);
+    generator.generate(module)
+}
+
+struct Generator<'a> {
+    program: &'a Program,
+    levels: Vec<usize>,
+    layouts: Vec<Layout>,
+    /// The data segment, but for the frame of the main program
+    data: String,
+    /// The labels of the constants laid out so far, by their directive
+    constants: HashMap<String, String>,
+    /// The code of the procedure being generated
+    out: String,
+    /// The labels the code of quadruples branches to within itself, which
+    /// the labels of blocks are not
+    branches: usize,
+    scope: ScopeId,
+    /// Where the temporaries of the procedure start in its frame
+    temps: i32,
+    /// Where each memory temporary of the procedure is in its frame
+    slots: Vec<i32>,
+    /// The parameters of the next call
+    params: Vec<Arg>,
+    sites: Sites,
+    /// The line of the last quadruple generated
+    line: usize,
+    /// The source of the last quadruple generated, which runtime errors are
+    /// reported at
+    range: Range<usize>
+}
+
+/// How a routine of the runtime the quadruples call takes its parameters:
+/// `i` an integer in the next pair of $a registers starting at an even
+/// one, `w` a word in the next $a register and `f` a real in $f12; and how
+/// it gives its result: in $v0 and $v1, in $v0 alone, or in $f0
+struct Routine {
+    label: &'static str,
+    params: &'static str,
+    result: Option<char>,
+    /// Whether it takes the place it reports runtime errors at
+    checked: bool
+}
+
+fn routine(name: &str) -> Routine {
+    let (label, params, result, checked) = match name {
+        "write_integer" => ("pas_write_integer", "i", None, false),
+        "write_real" => ("pas_write_real", "f", None, false),
+        "write_char" => ("pas_write_char", "w", None, false),
+        "write_boolean" => ("pas_write_boolean", "w", None, false),
+        "write_string" => ("pas_write_string", "ww", None, false),
+        "write_integer_width" => ("pas_write_integer_width", "ii", None, true),
+        "write_real_width" => ("pas_write_real_width", "fi", None, true),
+        "write_real_fixed" => ("pas_write_real_fixed", "fii", None, true),
+        "write_char_width" => ("pas_write_char_width", "wi", None, true),
+        "write_boolean_width" => ("pas_write_boolean_width", "wi", None, true),
+        "write_string_width" => ("pas_write_string_width", "wwi", None, true),
+        "writeln" => ("pas_writeln", "", None, false),
+        "read_integer" => ("pas_read_integer", "", Some('i'), true),
+        "read_real" => ("pas_read_real", "", Some('f'), true),
+        "read_char" => ("pas_read_char", "", Some('i'), true),
+        "readln" => ("pas_readln", "", None, true),
+        "eof" => ("pas_eof", "", Some('i'), false),
+        "eoln" => ("pas_eoln", "", Some('i'), false),
+        "new" => ("pas_new", "w", Some('w'), false),
+        "dispose" => ("pas_dispose", "w", None, true),
+        "zero" => ("pas_zero", "ww", None, false),
+        "copy" => ("pas_copy", "www", None, false),
+        "set_add" => ("pas_set_add", "wi", None, true),
+        "set_has" => ("pas_set_has", "wi", Some('w'), false),
+        "set_combine" => ("pas_set_combine", "wwww", None, false),
+        "set_eq" => ("pas_set_eq", "ww", Some('w'), false),
+        "set_le" => ("pas_set_le", "ww", Some('w'), false),
+        "compare" => ("pas_compare", "www", Some('w'), false),
+        "add_checked" => ("pas_add_checked", "ii", Some('i'), true),
+        "sub_checked" => ("pas_sub_checked", "ii", Some('i'), true),
+        "mul_checked" => ("pas_mul_checked", "ii", Some('i'), true),
+        "div_checked" => ("pas_div_checked", "ii", Some('i'), true),
+        "neg_checked" => ("pas_neg_checked", "i", Some('i'), true),
+        "abs_checked" => ("pas_abs_checked", "i", Some('i'), true),
+        name => unreachable!("the runtime has no routine '{}'", name)
+    };
+    Routine { label, params, result, checked }
+}
+
+/// Whether an offset fits the immediate of a load or a store
+fn fits(offset: i32) -> bool {
+    (-32768..32768).contains(&offset)
+}
+
+impl<'a> Generator<'a> {
+    fn generate(&mut self, module: Module) -> String {
+        let program = self.program;
+        let (main, subprograms) = module.procedures.split_last().expect("the main program is lowered last");
+        let (main, globals) = self.main(main);
+        let mut functions = String::new();
+        for procedure in subprograms {
+            let subprogram = subprogram_of(program, procedure.scope).expect("procedures are lowered from subprograms");
+            functions.push_str(&self.subprogram(subprogram, procedure));
+        }
+        let mut sites = self.sites.texts(program).iter()
+            .map(|text| {
+                let quoted = text.chars()
+                    .map(|c| match c {
+                        '\n' => "\\n".to_string(),
+                        '"' | '\\' => format!("\\{}", c),
+                        c => c.to_string()
+                    })
+                    .collect::<String>();
+                self.constant(0, format!(".asciiz \"{}\"", quoted))
+            })
+            .collect::<Vec<_>>();
+        sites.push(self.constant(0, ".asciiz \"\"".to_string()));
+        let _ = writeln!(self.data, "        .align 2\npas_sites:\n        .word {}", sites.join(", "));
+        format!("# program {0}, compiled from Pascal\n\
+                 # run with spim -file {0}.s or java -jar Mars.jar nc {0}.s, the runtime being included\n\n\
+                 \x20       .data\n\
+                 \x20       .align 3\n\
+                 pas_globals:\n\
+                 \x20       .space {1}\n\
+                 {2}\n\
+                 \x20       .text\n\
+                 \x20       .globl main\n\
+                 {3}{4}\n{5}",
+                program.name, globals.max(8), self.data, main, functions, RUNTIME)
+    }
+
+    fn op(&mut self, text: &str) {
+        let _ = writeln!(self.out, "        {}", text);
+    }
+
+    fn place(&mut self, label: &str) {
+        let _ = writeln!(self.out, "{}:", label);
+    }
+
+    /// The label of a constant in the data segment, laid out once
+    fn constant(&mut self, align: u32, directive: String) -> String {
+        if let Some(label) = self.constants.get(&directive) {
+            return label.clone();
+        }
+        let label = format!("C{}", self.constants.len() + 1);
+        if align > 0 {
+            let _ = writeln!(self.data, "        .align {}", align);
+        }
+        let _ = writeln!(self.data, "{}:\n        {}", label, directive);
+        self.constants.insert(directive, label.clone());
+        label
+    }
+
+    /// The label and the length of a string in the data segment
+    fn string(&mut self, text: &str) -> (String, usize) {
+        let quoted = text.chars()
+            .map(|c| match c {
+                '"' | '\\' => format!("\\{}", c),
+                c => c.to_string()
+            })
+            .collect::<String>();
+        (self.constant(0, format!(".ascii \"{}\"", quoted)), text.len())
+    }
+
+    /// The label of the words of a table
+    fn words(&mut self, words: &[String]) -> String {
+        self.constant(2, format!(".word {}", words.join(", ")))
+    }
+
+    /// The label of the bounds of an ordinal type, followed by its name if it
+    /// has one, as the runtime checks them
+    fn bounds(&mut self, ty: &Type, name: bool) -> String {
+        let bounds = ty.bounds().expect("checked types have bounds");
+        let mut words = [bounds.start as i64, bounds.end as i64].iter()
+            .flat_map(|bound| [(*bound as i32).to_string(), ((*bound >> 32) as i32).to_string()])
+            .collect::<Vec<_>>();
+        if name {
+            let (label, length) = self.string(&ty.to_string());
+            words.push(label);
+            words.push(length.to_string());
+        }
+        self.words(&words)
+    }
+
+    /// A load or a store of `register` at `offset` from `base`, through $t8
+    /// if the offset is too large for the instruction
+    fn access(&mut self, instruction: &str, register: &str, offset: i32, base: &str) {
+        if fits(offset) {
+            self.op(&format!("{} {}, {}({})", instruction, register, offset, base));
+        } else {
+            self.op(&format!("li $t8, {}", offset));
+            self.op(&format!("addu $t8, $t8, {}", base));
+            self.op(&format!("{} {}, 0($t8)", instruction, register));
+        }
+    }
+
+    /// Adds an offset to a register
+    fn add(&mut self, register: &str, offset: i32) {
+        if offset == 0 {
+        } else if fits(offset) {
+            self.op(&format!("addiu {0}, {0}, {1}", register, offset));
+        } else {
+            self.op(&format!("li $t8, {}", offset));
+            self.op(&format!("addu {0}, {0}, $t8", register));
+        }
+    }
+
+    /// Sets two registers to an integer
+    fn integer(&mut self, value: i64, low: &str, high: &str) {
+        self.op(&format!("li {}, {}", low, value as i32));
+        self.op(&format!("li {}, {}", high, (value >> 32) as i32));
+    }
+
+    /// Pushes the integer in two registers
+    fn push(&mut self, low: &str, high: &str) {
+        self.op("addiu $sp, $sp, -8");
+        self.op(&format!("sw {}, 0($sp)", low));
+        self.op(&format!("sw {}, 4($sp)", high));
+    }
+
+    /// Calls a routine of the runtime that reports runtime errors at the
+    /// source of the quadruple being generated
+    fn checked(&mut self, routine: &str) {
+        let site = self.sites.number(&self.range);
+        self.op(&format!("li $t9, {}", site));
+        self.op(&format!("jal {}", routine));
+    }
+
+    /// Lays out the temporaries of a procedure after the variables of its
+    /// scope, giving the size of its frame
+    fn begin(&mut self, procedure: &Procedure) -> i32 {
+        self.out.clear();
+        self.scope = procedure.scope;
+        self.line = 0;
+        self.temps = (self.layouts[procedure.scope].size + 7) / 8 * 8;
+        let mut offset = self.temps + 8 * procedure.temps as i32;
+        self.slots.clear();
+        for size in &procedure.slots {
+            self.slots.push(offset);
+            offset += (size + 7) / 8 * 8;
+        }
+        offset
+    }
+
+    fn body(&mut self, procedure: &Procedure, end: &str) {
+        let quads = procedure.blocks.iter().map(|block| block.quads.len()).sum::<usize>();
+        let mut index = 0;
+        for block in &procedure.blocks {
+            self.place(&format!("L{}", block.label));
+            for quad in &block.quads {
+                index += 1;
+                self.range = quad.range.clone();
+                if quad.line != self.line {
+                    self.line = quad.line;
+                    let _ = writeln!(self.out, "# line {}", quad.line);
+                }
+                match quad.op {
+                    // the main program leaves through the runtime
+                    Op::Return if procedure.scope == Program::GLOBAL => {
+                        self.op("li $v0, 10");
+                        self.op("syscall");
+                    },
+                    Op::Return => {
+                        if !quad.arg1.is_none() {
+                            self.load(&quad.arg1, "$v0", "$v1");
+                        }
+                        if index < quads {
+                            self.op(&format!("b {}", end));
+                        }
+                    },
+                    _ => self.quad(quad)
+                }
+            }
+        }
+    }
+
+    fn subprogram(&mut self, subprogram: &Subprogram, procedure: &Procedure) -> String {
+        let frame = (self.begin(procedure) + 7) / 8 * 8;
+        let end = format!("{}_end", procedure.name);
+        self.body(procedure, &end);
+        let body = std::mem::take(&mut self.out);
+        // the arguments are above the saved registers, the last lowest
+        let argument = |index: usize| frame + 8 + 8 * (subprogram.params.len() - 1 - index) as i32;
+        let link = frame + 8 + 8 * subprogram.params.len() as i32;
+
+        let _ = writeln!(self.out, "\n{}:", procedure.name);
+        self.add("$sp", -frame - 8);
+        self.access("sw", "$ra", frame + 4, "$sp");
+        self.access("sw", "$fp", frame, "$sp");
+        self.op("move $fp, $sp");
+        self.op("move $a0, $fp");
+        self.op(&format!("li $a1, {}", frame));
+        self.op("jal pas_zero");
+        self.access("lw", "$t0", link, "$fp");
+        self.op("sw $t0, 0($fp)");
+        for (index, param) in subprogram.params.iter().enumerate() {
+            let offset = self.layouts[subprogram.scope].fields[&param.name];
+            if matches!(param.r#type, Type::ArrayOf(..) | Type::Record(_) | Type::SetOf(_)) && !param.by_ref {
+                self.op("move $a0, $fp");
+                self.add("$a0", offset);
+                self.access("lw", "$a1", argument(index), "$fp");
+                self.op(&format!("li $a2, {}", size(&param.r#type)));
+                self.op("jal pas_copy");
+            } else {
+                self.access("lw", "$t0", argument(index), "$fp");
+                self.access("lw", "$t1", argument(index) + 4, "$fp");
+                self.access("sw", "$t0", offset, "$fp");
+                self.access("sw", "$t1", offset + 4, "$fp");
+            }
+        }
+        self.out.push_str(&body);
+        self.place(&end);
+        self.access("lw", "$ra", frame + 4, "$fp");
+        self.op("move $sp, $fp");
+        self.add("$sp", link + 8);
+        self.access("lw", "$fp", frame, "$fp");
+        self.op("jr $ra");
+        std::mem::take(&mut self.out)
+    }
+
+    /// The main program, and the size of its static frame, its temporaries
+    /// included
+    fn main(&mut self, procedure: &Procedure) -> (String, i32) {
+        let frame = self.begin(procedure);
+        let _ = writeln!(self.out, "main:");
+        // reals on the stack are aligned to 8 bytes
+        self.op("li $t0, -8");
+        self.op("and $sp, $sp, $t0");
+        self.op("la $fp, pas_globals");
+        self.body(procedure, "");
+        (std::mem::take(&mut self.out), frame)
+    }
+
+    /// Sets $t7 to the address of the frame of `scope`, the innermost one
+    /// reachable from the running procedure, and gives the register holding
+    /// it
+    fn frame_of(&mut self, scope: ScopeId) -> &'static str {
+        if scope == self.scope {
+            return "$fp";
+        }
+        if scope == Program::GLOBAL {
+            self.op("la $t7, pas_globals");
+            return "$t7";
+        }
+        self.op("move $t7, $fp");
+        // the link is at the start of a frame
+        for _ in self.levels[scope]..self.levels[self.scope] {
+            self.op("lw $t7, 0($t7)");
+        }
+        "$t7"
+    }
+
+    /// The register and the offset from it a temporary or a variable is at
+    fn location(&mut self, arg: &Arg) -> (&'static str, i32) {
+        match arg {
+            Arg::Temp(temp) => ("$fp", self.temps + 8 * (*temp as i32 - 1)),
+            Arg::Var(scope, name) => {
+                let offset = self.layouts[*scope].fields.get(name).copied()
+                    .unwrap_or(self.layouts[*scope].result);
+                (self.frame_of(*scope), offset)
+            },
+            arg => unreachable!("{:?} is not in memory", arg)
+        }
+    }
+
+    /// Sets `low` and `high` to the value of a field, the bits of a real
+    fn load(&mut self, arg: &Arg, low: &str, high: &str) {
+        match arg {
+            Arg::Int(value) => self.integer(*value, low, high),
+            Arg::Real(value) => self.integer(value.to_bits() as i64, low, high),
+            arg => {
+                let (base, offset) = self.location(arg);
+                self.access("lw", low, offset, base);
+                self.access("lw", high, offset + 4, base);
+            }
+        }
+    }
+
+    /// Stores two registers to the temporary or the variable of a field
+    fn store(&mut self, arg: &Arg, low: &str, high: &str) {
+        let (base, offset) = self.location(arg);
+        self.access("sw", low, offset, base);
+        self.access("sw", high, offset + 4, base);
+    }
+
+    /// Sets a floating-point register to the real of a field
+    fn load_real(&mut self, arg: &Arg, register: &str) {
+        match arg {
+            Arg::Int(value) => self.load_real(&Arg::Real(*value as f64), register),
+            Arg::Real(value) => {
+                let directive = if value.is_finite() {
+                    format!(".double {:?}", value)
+                } else {
+                    let bits = value.to_bits();
+                    format!(".word {}, {}", bits as u32 as i32, (bits >> 32) as u32 as i32)
+                };
+                let label = self.constant(3, directive);
+                self.op(&format!("la $t0, {}", label));
+                self.op(&format!("ldc1 {}, 0($t0)", register));
+            },
+            arg => {
+                let (base, offset) = self.location(arg);
+                self.access("ldc1", register, offset, base);
+            }
+        }
+    }
+
+    fn store_real(&mut self, arg: &Arg, register: &str) {
+        let (base, offset) = self.location(arg);
+        self.access("sdc1", register, offset, base);
+    }
+
+    /// Sets $v0 to whether the integer in the registers `left` is less than
+    /// the one in `right`
+    fn less(&mut self, left: (&str, &str), right: (&str, &str)) {
+        self.op(&format!("slt $t4, {}, {}", left.1, right.1));
+        self.op(&format!("xor $t5, {}, {}", left.1, right.1));
+        self.op("sltiu $t5, $t5, 1");
+        self.op(&format!("sltu $t6, {}, {}", left.0, right.0));
+        self.op("and $t6, $t6, $t5");
+        self.op("or $v0, $t4, $t6");
+    }
+
+    /// The product of the integers in $a0 and $a1 and in $a2 and $a3, the
+    /// bits past 64 dropped
+    fn multiply(&mut self) {
+        self.op("multu $a0, $a2");
+        self.op("mflo $v0");
+        self.op("mfhi $v1");
+        self.op("mul $t0, $a0, $a3");
+        self.op("addu $v1, $v1, $t0");
+        self.op("mul $t0, $a1, $a2");
+        self.op("addu $v1, $v1, $t0");
+    }
+
+    fn quad(&mut self, quad: &Quad) {
+        let (arg1, arg2, result) = (&quad.arg1, &quad.arg2, &quad.result);
+        match quad.op {
+            Op::Assign => {
+                self.load(arg1, "$t0", "$t1");
+                self.store(result, "$t0", "$t1");
+            },
+            Op::Address => {
+                match arg1 {
+                    Arg::Slot(slot) => {
+                        let offset = self.slots[slot - 1];
+                        self.op("move $t0, $fp");
+                        self.add("$t0", offset);
+                    },
+                    variable => {
+                        let (base, offset) = self.location(variable);
+                        self.op(&format!("move $t0, {}", base));
+                        self.add("$t0", offset);
+                    }
+                }
+                self.store(result, "$t0", "$zero");
+            },
+            Op::Load => {
+                self.load(arg1, "$t0", "$t1");
+                self.op("lw $t2, 0($t0)");
+                self.op("lw $t3, 4($t0)");
+                self.store(result, "$t2", "$t3");
+            },
+            Op::Store => {
+                self.load(arg1, "$t2", "$t3");
+                self.load(result, "$t0", "$t1");
+                self.op("sw $t2, 0($t0)");
+                self.op("sw $t3, 4($t0)");
+            },
+            Op::FAdd | Op::FSub | Op::FMul | Op::FDiv | Op::FNeg | Op::FAbs | Op::Sqrt | Op::Ln | Op::Sin
+            | Op::Cos | Op::Exp | Op::Arctan => {
+                self.load_real(arg1, "$f12");
+                if quad.op.is_binary() {
+                    self.load_real(arg2, "$f14");
+                }
+                match quad.op {
+                    Op::FAdd => self.op("add.d $f0, $f12, $f14"),
+                    Op::FSub => self.op("sub.d $f0, $f12, $f14"),
+                    Op::FMul => self.op("mul.d $f0, $f12, $f14"),
+                    Op::FDiv => self.checked("pas_rdiv"),
+                    Op::FNeg => self.op("neg.d $f0, $f12"),
+                    Op::FAbs => self.op("abs.d $f0, $f12"),
+                    Op::Sqrt => self.checked("pas_sqrt"),
+                    Op::Ln => self.checked("pas_ln"),
+                    Op::Arctan => self.op("jal pas_atan"),
+                    op => self.op(&format!("jal pas_{}", op))
+                }
+                self.store_real(result, "$f0");
+            },
+            Op::FEq | Op::FNe | Op::FLt | Op::FLe | Op::FGt | Op::FGe => {
+                self.load_real(arg1, "$f12");
+                self.load_real(arg2, "$f14");
+                // NaN is unordered, and only unequal to anything
+                let (test, left, right, holds) = match quad.op {
+                    Op::FEq => ("eq", "$f12", "$f14", true),
+                    Op::FNe => ("eq", "$f12", "$f14", false),
+                    Op::FLt => ("lt", "$f12", "$f14", true),
+                    Op::FGt => ("lt", "$f14", "$f12", true),
+                    Op::FLe => ("le", "$f12", "$f14", true),
+                    _ => ("le", "$f14", "$f12", true)
+                };
+                self.branches += 1;
+                let end = format!("B{}", self.branches);
+                self.op(&format!("li $v0, {}", holds as i32));
+                self.op(&format!("c.{}.d {}, {}", test, left, right));
+                self.op(&format!("bc1t {}", end));
+                self.op(&format!("li $v0, {}", !holds as i32));
+                self.place(&end);
+                self.store(result, "$v0", "$zero");
+            },
+            Op::Trunc | Op::Round => {
+                self.load_real(arg1, "$f12");
+                self.op(&format!("jal pas_{}", quad.op));
+                self.store(result, "$v0", "$v1");
+            },
+            Op::Float => {
+                self.load(arg1, "$a0", "$a1");
+                self.op("jal pas_widen");
+                self.store_real(result, "$f0");
+            },
+            Op::Check | Op::Bounds | Op::NoCase => {
+                self.load(arg1, "$a0", "$a1");
+                let ty = match arg2 {
+                    Arg::Type(ty) => ty,
+                    arg => unreachable!("{:?} is not a type", arg)
+                };
+                let descriptor = match quad.op {
+                    Op::Check => self.bounds(ty, true),
+                    Op::Bounds => self.bounds(ty, false),
+                    _ => self.cases(ty)
+                };
+                self.op(&format!("la $a2, {}", descriptor));
+                self.checked(match quad.op {
+                    Op::Check => "pas_check",
+                    Op::Bounds => "pas_index",
+                    _ => "pas_no_case"
+                });
+            },
+            Op::Deref => {
+                self.load(arg1, "$a0", "$a1");
+                self.checked("pas_deref");
+                self.store(result, "$v0", "$zero");
+            },
+            Op::Goto => self.op(&format!("b L{}", quad.target().expect("jumps have a target"))),
+            Op::If | Op::IfFalse => {
+                self.load(arg1, "$t0", "$t1");
+                let branch = if quad.op == Op::If { "bnez" } else { "beqz" };
+                self.op(&format!("{} $t0, L{}", branch, quad.target().expect("jumps have a target")));
+            },
+            Op::Param => self.params.push(arg1.clone()),
+            Op::Call => self.call(quad),
+            Op::Return => unreachable!("returns are generated with their procedure"),
+            _ => {
+                self.load(arg1, "$a0", "$a1");
+                if quad.op.is_binary() {
+                    self.load(arg2, "$a2", "$a3");
+                }
+                self.integer_op(quad.op);
+                self.store(result, "$v0", "$v1");
+            }
+        }
+    }
+
+    /// Sets $v0 and $v1 to the result of an integer operator on $a0 and $a1
+    /// and on $a2 and $a3
+    fn integer_op(&mut self, op: Op) {
+        match op {
+            Op::Add => {
+                self.op("addu $v0, $a0, $a2");
+                self.op("sltu $t0, $v0, $a0");
+                self.op("addu $v1, $a1, $a3");
+                self.op("addu $v1, $v1, $t0");
+            },
+            Op::Sub => {
+                self.op("sltu $t0, $a0, $a2");
+                self.op("subu $v0, $a0, $a2");
+                self.op("subu $v1, $a1, $a3");
+                self.op("subu $v1, $v1, $t0");
+            },
+            Op::Mul => self.multiply(),
+            Op::Div => self.checked("pas_div"),
+            Op::Mod => self.checked("pas_mod"),
+            Op::Neg => {
+                self.op("sltu $t0, $zero, $a0");
+                self.op("subu $v0, $zero, $a0");
+                self.op("subu $v1, $zero, $a1");
+                self.op("subu $v1, $v1, $t0");
+            },
+            Op::Abs => self.op("jal pas_abs"),
+            Op::Not | Op::Odd => {
+                self.op(if op == Op::Not { "xori $v0, $a0, 1" } else { "andi $v0, $a0, 1" });
+                self.op("li $v1, 0");
+            },
+            Op::Eq | Op::Ne => {
+                self.op("xor $t0, $a0, $a2");
+                self.op("xor $t1, $a1, $a3");
+                self.op("or $t0, $t0, $t1");
+                self.op(if op == Op::Eq { "sltiu $v0, $t0, 1" } else { "sltu $v0, $zero, $t0" });
+                self.op("li $v1, 0");
+            },
+            _ => {
+                let (left, right) = (("$a0", "$a1"), ("$a2", "$a3"));
+                match op {
+                    Op::Lt | Op::Ge => self.less(left, right),
+                    _ => self.less(right, left)
+                }
+                if matches!(op, Op::Le | Op::Ge) {
+                    self.op("xori $v0, $v0, 1");
+                }
+                self.op("li $v1, 0");
+            }
+        }
+    }
+
+    /// The label of what the runtime reports of a value no case label
+    /// matches: its kind, and the names of the literals of an enumeration
+    fn cases(&mut self, ty: &Type) -> String {
+        let (kind, names, count) = match ty {
+            Type::Char => ('c', "0".to_string(), 0),
+            Type::Boolean => ('b', "0".to_string(), 0),
+            Type::Enum(names) => {
+                let table = names.iter()
+                    .flat_map(|name| {
+                        let (label, length) = self.string(name);
+                        [label, length.to_string()]
+                    })
+                    .collect::<Vec<_>>();
+                ('e', self.words(&table), names.len())
+            },
+            _ => ('i', "0".to_string(), 0)
+        };
+        self.words(&[(kind as u8).to_string(), names, count.to_string()])
+    }
+
+    /// A call to a subprogram, with the parameters of the quadruples before
+    /// it, or to a routine of the runtime
+    fn call(&mut self, quad: &Quad) {
+        let params = std::mem::take(&mut self.params);
+        let name = match &quad.arg1 {
+            Arg::Name(name) => name,
+            arg => unreachable!("{:?} is not a procedure", arg)
+        };
+        if let Some(subprogram) = callee(self.program, name) {
+            let parent = self.program.scopes[subprogram.scope].parent.expect("subprograms are in a scope");
+            let frame = self.frame_of(parent);
+            self.op(&format!("move $t0, {}", frame));
+            self.push("$t0", "$zero");
+            for param in &params {
+                self.load(param, "$t0", "$t1");
+                self.push("$t0", "$t1");
+            }
+            self.op(&format!("jal {}", name));
+            if !quad.result.is_none() {
+                self.store(&quad.result, "$v0", "$v1");
+            }
+            return;
+        }
+        let routine = routine(name);
+        let mut register = 0;
+        for (param, kind) in params.iter().zip(routine.params.chars()) {
+            match kind {
+                'i' => {
+                    register += register % 2;
+                    self.load(param, &format!("$a{}", register), &format!("$a{}", register + 1));
+                    register += 2;
+                },
+                'w' => {
+                    self.load(param, &format!("$a{}", register), "$t1");
+                    register += 1;
+                },
+                _ => self.load_real(param, "$f12")
+            }
+        }
+        if routine.checked {
+            self.checked(routine.label);
+        } else {
+            self.op(&format!("jal {}", routine.label));
+        }
+        match routine.result {
+            Some('i') => self.store(&quad.result, "$v0", "$v1"),
+            Some('w') => {
+                self.op("sra $v1, $v0, 31");
+                self.store(&quad.result, "$v0", "$v1");
+            },
+            Some(_) => self.store_real(&quad.result, "$f0"),
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emit::tests::*;
+
+    #[test]
+    fn samples_behave_as_interpreted() {
+        for (name, program, input) in samples() {
+            assert_eq!(simulator::run(&emit(&program, 0), &input), interpret(&program, &input), "{}", name);
+        }
+    }
+
+    #[test]
+    fn optimized_samples_behave_as_interpreted() {
+        for (name, program, input) in samples() {
+            for level in 1..=2 {
+                assert_eq!(simulator::run(&emit(&program, level), &input), interpret(&program, &input), "{} at -O{}", name, level);
+            }
+        }
+    }
+
+    #[test]
+    fn runtime_errors() {
+        for body in FAULTS {
+            let program = fault(body);
+            let (_, expected) = interpret(&program, "");
+            for level in 0..=2 {
+                let (_, error) = simulator::run(&emit(&program, level), "");
+                assert_eq!(error, expected.clone(), "{} at -O{}", body, level);
+            }
+        }
+    }
+
+    #[test]
+    fn checked_arithmetic_that_fits() {
+        let program = check("
+            program fits; {$Q+}
+            var i, j: integer;
+            begin
+              i := -9223372036854775807 - 1; j := 1;
+              writeln(i * j, ' ', j * i, ' ', i div j, ' ', i + 0, ' ', i - (0 - 1));
+              i := -3037000499; j := 3037000499;
+              writeln(i * j, ' ', sqr(i), ' ', abs(i), ' ', -i, ' ', j * 3037000499, ' ', i * (0 - j));
+              j := 4294967296;
+              writeln(j * 2147483647, ' ', j * (0 - 2147483648), ' ', succ(i), ' ', pred(i))
+            end.
+        ");
+        let expected = interpret(&program, "");
+        for level in 0..=2 {
+            assert_eq!(simulator::run(&emit(&program, level), ""), expected, "at -O{}", level);
+        }
+    }
+
+    #[test]
+    fn tail_calls_run_in_constant_stack() {
+        let program = check("
+            program deep;
+            procedure count(k: integer);
+            begin
+              if k > 0 then count(k - 1)
+            end;
+            begin
+              count(2000000);
+              writeln(1)
+            end.
+        ");
+        assert_eq!(simulator::run(&emit(&program, 2), ""), ("1\n".to_string(), None));
+    }
+
+    #[test]
+    fn subprograms_build_activation_records() {
+        let assembly = emit(&check("
+            program frames;
+            function twice(n: integer): integer;
+            begin
+              twice := 2 * n
+            end;
+            begin
+              writeln(twice(21))
+            end.
+        "), 0);
+        let function = &assembly[assembly.find("\ntwice.").unwrap()..assembly.find("\npas_put_text:").unwrap()];
+        assert!(function.contains("sw $ra, ") && function.contains("sw $fp, ") && function.contains("jr $ra"), "{}", function);
+        assert!(assembly.contains(".globl main\nmain:\n"), "{}", assembly);
+        assert_eq!(simulator::run(&assembly, ""), ("42\n".to_string(), None));
+    }
+}
@@ -0,0 +1,3207 @@
+# The runtime of compiled Pascal programs: input and output through the
+# syscalls of SPIM and MARS, 64-bit integer arithmetic, reals, sets, heap
+# variables and the checks that stop a program with a runtime error. The
+# messages are the ones the interpreter gives.
+#
+# Routines take their arguments in $a0 to $a3 and in $f12 and $f14, an
+# integer in two registers with its low word first, and give their result
+# in $v0 and $v1 or in $f0. Those that can fail take the number of the
+# place they report in $t9, which no routine changes. They keep $s0 to $s7, $fp and $sp.
+
+        .data
+pas_m_error:    .asciiz "Runtime Error "
+pas_m_true:     .asciiz "true"
+pas_m_false:    .asciiz "false"
+pas_m_nan:      .asciiz "NaN"
+pas_m_inf:      .asciiz "inf"
+pas_m_end:      .asciiz "Read past the end of the input"
+pas_m_expected: .asciiz "Expected "
+pas_m_got:      .asciiz " in the input, got '"
+pas_m_integer:  .asciiz "Integer"
+pas_m_real:     .asciiz "Real"
+pas_m_zero:     .asciiz "Division by zero"
+pas_m_overflow: .asciiz "Integer overflow"
+pas_m_defined:  .asciiz "' is not defined for "
+pas_m_sqrt:     .asciiz "sqrt"
+pas_m_ln:       .asciiz "ln"
+pas_m_value:    .asciiz "The value "
+pas_m_outside:  .asciiz " is outside of "
+pas_m_index:    .asciiz "Index "
+pas_m_bounds:   .asciiz " is outside of the bounds "
+pas_m_dots:     .asciiz ".."
+pas_m_case:     .asciiz "No case label matches "
+pas_m_nil:      .asciiz "The pointer does not point to a variable"
+pas_m_disposed: .asciiz "The variable the pointer points to has been disposed"
+pas_m_members:  .asciiz "0..255"
+pas_m_field:    .asciiz "Field widths and decimals must be at least 1, got "
+
+        .align 2
+# Input is read a character ahead, a last line without a newline ending
+# like the others
+pas_ahead:      .word -2
+pas_pending:    .word -2
+pas_in_line:    .word 0
+# The range of the members of sets, as checked by pas_check
+pas_members:    .word 0, 0, 255, 0, pas_m_members, 6
+pas_byte:       .space 4
+# The digits of a number being written, and the characters of one read
+pas_digits:     .space 32
+pas_token:      .space 72
+
+# Reals are written and read exactly, in numbers of 48 words
+        .align 3
+pas_big_r:      .space 192
+pas_big_s:      .space 192
+pas_big_minus:  .space 192
+pas_big_plus:   .space 192
+pas_big_t:      .space 192
+
+# log10(2), and a margin for estimating the power of ten above a real
+pas_real_constants:
+        .double 0.30102999566398120, 1e-10
+pas_exp_constants:
+        .double 709.782712893383973096, -745.13321910194110842
+        .double 1.44269504088896338700, 6.93147180369123816490e-01, 1.90821492927058770002e-10
+pas_exp_poly:
+        .double 4.13813679705723846039e-08, -1.65339022054652515390e-06, 6.61375632143793436117e-05
+        .double -2.77777777770155933842e-03, 1.66666666666666019037e-01
+pas_log_constants:
+        .double 1.41421356237309504880, 6.93147180369123816490e-01, 1.90821492927058770002e-10
+pas_log_even:
+        .double 1.531383769920937332e-01, 2.222219843214978396e-01, 3.999999999940941908e-01
+pas_log_odd:
+        .double 1.479819860511658591e-01, 1.818357216161805012e-01, 2.857142874366239149e-01
+        .double 6.666666666666735130e-01
+# 2 / pi, 2^52, the factor splitting reals after Dekker, and a quarter
+# turn in four reals making 212 bits
+pas_turn_constants:
+        .double 6.36619772367581382433e-01, 4503599627370496.0, 134217729.0
+        .double 1.5707963267948966, 6.123233995736766e-17, -1.4973849048591698e-33, 5.562271104316826e-50
+pas_sin_poly:
+        .double 1.58969099521155010221e-10, -2.50507602534068634195e-08, 2.75573137070700676789e-06
+        .double -1.98412698298579493134e-04, 8.33333333332248946124e-03
+pas_sin_constants:
+        .double -1.66666666666666324348e-01
+pas_cos_poly:
+        .double -1.13596475577881948265e-11, 2.08757232129817482790e-09, -2.75573143513906633035e-07
+        .double 2.48015872894767294178e-05, -1.38888888888741095749e-03, 4.16666666666666019037e-02
+# The arctangents of 0.5, 1, 1.5 and infinity, each as a real and what
+# it lacks
+pas_atan_constants:
+        .double 4.63647609000806093515e-01, 2.26987774529616870924e-17
+        .double 7.85398163397448278999e-01, 3.06161699786838301793e-17
+        .double 9.82793723247329054082e-01, 1.39033110312309984516e-17
+        .double 1.57079632679489655800, 6.12323399573676603587e-17
+pas_atan_even:
+        .double 1.62858201153657823623e-02, 4.97687799461593236017e-02, 6.66107313738753120669e-02
+        .double 9.09088713343650656196e-02, 1.42857142725034663711e-01, 3.33333333333329318027e-01
+pas_atan_odd:
+        .double -3.65315727442169155270e-02, -5.83357013379057348645e-02, -7.69187620504482999495e-02
+        .double -1.11111104054623557880e-01, -1.99999999998764832476e-01
+
+        .text
+
+# Output, to the output (1) or the error output (2) in $a0
+
+# Writes the $a2 bytes at $a1
+pas_put_text:
+        li $v0, 15
+        syscall
+        jr $ra
+
+# Writes the byte in $a1
+pas_put:
+        la $t0, pas_byte
+        sb $a1, 0($t0)
+        move $a1, $t0
+        li $a2, 1
+        li $v0, 15
+        syscall
+        jr $ra
+
+# Writes the string at $a1, which ends with a 0 byte
+pas_put_string:
+        move $a2, $a1
+pas_put_string_next:
+        lbu $t0, 0($a2)
+        beqz $t0, pas_put_string_end
+        addiu $a2, $a2, 1
+        b pas_put_string_next
+pas_put_string_end:
+        subu $a2, $a2, $a1
+        li $v0, 15
+        syscall
+        jr $ra
+
+# Writes the integer in $a2 and $a3
+pas_put_integer:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        jal pas_integer_text
+        move $a1, $v0
+        move $a2, $v1
+        li $v0, 15
+        syscall
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 8
+        jr $ra
+
+# The text of the integer in $a2 and $a3, at $v0 with its length in $v1,
+# dividing its magnitude by 10 in pieces of 16 bits
+pas_integer_text:
+        move $t0, $a2
+        move $t1, $a3
+        bgez $t1, pas_integer_text_positive
+        sltu $t2, $zero, $t0
+        subu $t0, $zero, $t0
+        subu $t1, $zero, $t1
+        subu $t1, $t1, $t2
+pas_integer_text_positive:
+        la $t6, pas_digits
+        addiu $t6, $t6, 24
+        move $t7, $t6
+        li $t3, 10
+pas_integer_text_digit:
+        divu $t1, $t3
+        mflo $t1
+        mfhi $t2
+        sll $t2, $t2, 16
+        srl $t4, $t0, 16
+        or $t4, $t4, $t2
+        divu $t4, $t3
+        mflo $t5
+        mfhi $t2
+        sll $t2, $t2, 16
+        andi $t4, $t0, 0xffff
+        or $t4, $t4, $t2
+        divu $t4, $t3
+        mflo $t4
+        mfhi $t2
+        sll $t5, $t5, 16
+        or $t0, $t5, $t4
+        addiu $t2, $t2, 48
+        addiu $t6, $t6, -1
+        sb $t2, 0($t6)
+        or $t2, $t0, $t1
+        bnez $t2, pas_integer_text_digit
+        bgez $a3, pas_integer_text_end
+        li $t2, 45
+        addiu $t6, $t6, -1
+        sb $t2, 0($t6)
+pas_integer_text_end:
+        move $v0, $t6
+        subu $v1, $t7, $t6
+        jr $ra
+
+# Writes the boolean in $a2
+pas_put_boolean:
+        la $a1, pas_m_false
+        beqz $a2, pas_put_string
+        la $a1, pas_m_true
+        b pas_put_string
+
+# Writes the real in $f12 as the shortest decimal that reads back as the
+# same real, the nearest of them if there are several, without an
+# exponent. The digits are generated as Burger and Dybvig do: the real is
+# r / s, and the decimals reading back as it are the ones from
+# (r - m-) / s to (r + m+) / s.
+pas_put_real:
+        addiu $sp, $sp, -40
+        sw $ra, 0($sp)
+        sw $s0, 4($sp)
+        sw $s1, 8($sp)
+        sw $s2, 12($sp)
+        sw $s3, 16($sp)
+        sw $s4, 20($sp)
+        sw $s5, 24($sp)
+        sw $s6, 28($sp)
+        sw $s7, 32($sp)
+        move $s0, $a0
+        c.eq.d $f12, $f12
+        bc1t pas_put_real_number
+        la $a1, pas_m_nan
+        jal pas_put_string
+        b pas_put_real_end
+pas_put_real_number:
+        mfc1 $s5, $f12
+        mfc1 $s6, $f13
+        bgez $s6, pas_put_real_positive
+        li $a1, 45
+        jal pas_put
+        sll $s6, $s6, 1
+        srl $s6, $s6, 1
+pas_put_real_positive:
+        li $t0, 0x7ff00000
+        bne $s6, $t0, pas_put_real_finite
+        bnez $s5, pas_put_real_finite
+        move $a0, $s0
+        la $a1, pas_m_inf
+        jal pas_put_string
+        b pas_put_real_end
+pas_put_real_finite:
+        or $t0, $s6, $s5
+        bnez $t0, pas_put_real_nonzero
+        move $a0, $s0
+        li $a1, 48
+        jal pas_put
+        b pas_put_real_end
+pas_put_real_nonzero:
+        srl $t2, $s6, 20
+        li $t0, 0x000fffff
+        and $s6, $s6, $t0
+        # the real below a power of two is nearer than the one above
+        or $t0, $s6, $s5
+        sltiu $t0, $t0, 1
+        sltiu $t1, $t2, 2
+        xori $t1, $t1, 1
+        and $s4, $t0, $t1
+        beqz $t2, pas_put_real_subnormal
+        lui $t0, 0x10
+        or $s6, $s6, $t0
+        addiu $s3, $t2, -1075
+        b pas_put_real_mantissa
+pas_put_real_subnormal:
+        li $s3, -1074
+pas_put_real_mantissa:
+        # halfway reads back as the real with an even mantissa
+        andi $s1, $s5, 1
+        xori $s1, $s1, 1
+        # the power of ten above the real, estimated from its binary
+        # exponent and one too low at worst
+        li $t0, 31
+        clz $t1, $s6
+        subu $t0, $t0, $t1
+        bnez $s6, pas_put_real_bits
+        li $t0, -1
+        clz $t1, $s5
+        subu $t0, $t0, $t1
+pas_put_real_bits:
+        addu $t0, $t0, $s3
+        addiu $t0, $t0, 32
+        mtc1 $t0, $f0
+        cvt.d.w $f0, $f0
+        la $t0, pas_real_constants
+        ldc1 $f2, 0($t0)
+        mul.d $f0, $f0, $f2
+        ldc1 $f2, 8($t0)
+        sub.d $f0, $f0, $f2
+        ceil.w.d $f0, $f0
+        mfc1 $s2, $f0
+        # shift is the binary exponent if it is positive, else 0
+        move $s7, $s3
+        bgez $s7, pas_put_real_scale
+        li $s7, 0
+pas_put_real_scale:
+        la $a0, pas_big_r
+        move $a1, $s5
+        move $a2, $s6
+        jal pas_big_set
+        la $a0, pas_big_r
+        addu $a1, $s7, $s4
+        addiu $a1, $a1, 1
+        jal pas_big_shl
+        la $a0, pas_big_s
+        li $a1, 1
+        li $a2, 0
+        jal pas_big_set
+        la $a0, pas_big_s
+        subu $a1, $s7, $s3
+        addu $a1, $a1, $s4
+        addiu $a1, $a1, 1
+        jal pas_big_shl
+        la $a0, pas_big_minus
+        li $a1, 1
+        li $a2, 0
+        jal pas_big_set
+        la $a0, pas_big_minus
+        move $a1, $s7
+        jal pas_big_shl
+        la $a0, pas_big_plus
+        li $a1, 1
+        li $a2, 0
+        jal pas_big_set
+        la $a0, pas_big_plus
+        addu $a1, $s7, $s4
+        jal pas_big_shl
+        bltz $s2, pas_put_real_small
+        la $a0, pas_big_s
+        move $a1, $s2
+        jal pas_big_pow10
+        b pas_put_real_fixup
+pas_put_real_small:
+        subu $s3, $zero, $s2
+        la $a0, pas_big_r
+        move $a1, $s3
+        jal pas_big_pow10
+        la $a0, pas_big_minus
+        move $a1, $s3
+        jal pas_big_pow10
+        la $a0, pas_big_plus
+        move $a1, $s3
+        jal pas_big_pow10
+pas_put_real_fixup:
+        la $a0, pas_big_t
+        la $a1, pas_big_r
+        la $a2, pas_big_plus
+        jal pas_big_add
+        la $a0, pas_big_t
+        la $a1, pas_big_s
+        jal pas_big_compare
+        subu $t0, $zero, $s1
+        slt $t0, $t0, $v0
+        beqz $t0, pas_put_real_times10
+        addiu $s2, $s2, 1
+        b pas_put_real_digits
+pas_put_real_times10:
+        jal pas_big_times10
+pas_put_real_digits:
+        li $s3, 0
+pas_put_real_next:
+        li $s4, 0
+pas_put_real_subtract:
+        la $a0, pas_big_r
+        la $a1, pas_big_s
+        jal pas_big_compare
+        bltz $v0, pas_put_real_tests
+        la $a0, pas_big_r
+        la $a1, pas_big_s
+        jal pas_big_sub
+        addiu $s4, $s4, 1
+        b pas_put_real_subtract
+pas_put_real_tests:
+        la $a0, pas_big_r
+        la $a1, pas_big_minus
+        jal pas_big_compare
+        slt $s5, $v0, $s1
+        la $a0, pas_big_t
+        la $a1, pas_big_r
+        la $a2, pas_big_plus
+        jal pas_big_add
+        la $a0, pas_big_t
+        la $a1, pas_big_s
+        jal pas_big_compare
+        subu $t0, $zero, $s1
+        slt $s6, $t0, $v0
+        or $t0, $s5, $s6
+        bnez $t0, pas_put_real_last
+        la $t0, pas_digits
+        addu $t0, $t0, $s3
+        addiu $t1, $s4, 48
+        sb $t1, 0($t0)
+        addiu $s3, $s3, 1
+        jal pas_big_times10
+        b pas_put_real_next
+pas_put_real_last:
+        # the last digit rounds the rest, up when it is half
+        and $t0, $s5, $s6
+        beqz $t0, pas_put_real_round
+        la $a0, pas_big_t
+        la $a1, pas_big_r
+        la $a2, pas_big_r
+        jal pas_big_add
+        la $a0, pas_big_t
+        la $a1, pas_big_s
+        jal pas_big_compare
+        slt $s6, $v0, $zero
+        xori $s6, $s6, 1
+pas_put_real_round:
+        la $t0, pas_digits
+        addu $t0, $t0, $s3
+        addu $t1, $s4, $s6
+        addiu $t1, $t1, 48
+        sb $t1, 0($t0)
+        addiu $s3, $s3, 1
+pas_put_real_trim:
+        sltiu $t0, $s3, 2
+        bnez $t0, pas_put_real_write
+        la $t0, pas_digits
+        addu $t0, $t0, $s3
+        lbu $t1, -1($t0)
+        li $t2, 48
+        bne $t1, $t2, pas_put_real_write
+        addiu $s3, $s3, -1
+        b pas_put_real_trim
+pas_put_real_write:
+        # $s2 digits are before the point, of the $s3 there are
+        bgtz $s2, pas_put_real_whole
+        move $a0, $s0
+        li $a1, 48
+        jal pas_put
+        move $a0, $s0
+        li $a1, 46
+        jal pas_put
+pas_put_real_zeros:
+        beqz $s2, pas_put_real_fraction
+        move $a0, $s0
+        li $a1, 48
+        jal pas_put
+        addiu $s2, $s2, 1
+        b pas_put_real_zeros
+pas_put_real_fraction:
+        move $a0, $s0
+        la $a1, pas_digits
+        move $a2, $s3
+        jal pas_put_text
+        b pas_put_real_end
+pas_put_real_whole:
+        slt $t0, $s2, $s3
+        bnez $t0, pas_put_real_point
+        move $a0, $s0
+        la $a1, pas_digits
+        move $a2, $s3
+        jal pas_put_text
+pas_put_real_pad:
+        beq $s3, $s2, pas_put_real_end
+        move $a0, $s0
+        li $a1, 48
+        jal pas_put
+        addiu $s3, $s3, 1
+        b pas_put_real_pad
+pas_put_real_point:
+        move $a0, $s0
+        la $a1, pas_digits
+        move $a2, $s2
+        jal pas_put_text
+        move $a0, $s0
+        li $a1, 46
+        jal pas_put
+        move $a0, $s0
+        la $a1, pas_digits
+        addu $a1, $a1, $s2
+        subu $a2, $s3, $s2
+        jal pas_put_text
+pas_put_real_end:
+        lw $ra, 0($sp)
+        lw $s0, 4($sp)
+        lw $s1, 8($sp)
+        lw $s2, 12($sp)
+        lw $s3, 16($sp)
+        lw $s4, 20($sp)
+        lw $s5, 24($sp)
+        lw $s6, 28($sp)
+        lw $s7, 32($sp)
+        addiu $sp, $sp, 40
+        jr $ra
+
+pas_write_integer:
+        move $a2, $a0
+        move $a3, $a1
+        li $a0, 1
+        b pas_put_integer
+
+# Writes the real in $f12 without a field width, in floating-point form
+# in the default field of 22 characters
+pas_write_real:
+        li $a0, 22
+        li $a1, 0
+        b pas_write_real_width
+
+pas_write_char:
+        move $a1, $a0
+        li $a0, 1
+        b pas_put
+
+pas_write_boolean:
+        move $a2, $a0
+        li $a0, 1
+        b pas_put_boolean
+
+# Writes the $a1 characters of the string at $a0
+pas_write_string:
+        addiu $sp, $sp, -16
+        sw $ra, 0($sp)
+        sw $s0, 4($sp)
+        sw $s1, 8($sp)
+        move $s0, $a0
+        move $s1, $a1
+pas_write_string_next:
+        beqz $s1, pas_write_string_end
+        li $a0, 1
+        lw $a1, 0($s0)
+        jal pas_put
+        addiu $s0, $s0, 8
+        addiu $s1, $s1, -1
+        b pas_write_string_next
+pas_write_string_end:
+        lw $ra, 0($sp)
+        lw $s0, 4($sp)
+        lw $s1, 8($sp)
+        addiu $sp, $sp, 16
+        jr $ra
+
+pas_writeln:
+        li $a0, 1
+        li $a1, 10
+        b pas_put
+
+# Field widths
+
+# Checks that the field width or number of decimals in $a0 and $a1 is at
+# least 1, changing no register but $t0
+pas_field:
+        bgtz $a1, pas_field_end
+        bltz $a1, pas_field_fail
+        sltu $t0, $zero, $a0
+        bnez $t0, pas_field_end
+pas_field_fail:
+        move $s0, $a0
+        move $s1, $a1
+        jal pas_fail_begin
+        li $a0, 2
+        la $a1, pas_m_field
+        jal pas_put_string
+        li $a0, 2
+        move $a2, $s0
+        move $a3, $s1
+        jal pas_put_integer
+        b pas_fail_end
+pas_field_end:
+        jr $ra
+
+# Writes the spaces that right-align $a1 characters in a field of $a0
+pas_pad:
+        addiu $sp, $sp, -16
+        sw $ra, 0($sp)
+        sw $s0, 4($sp)
+        sw $s1, 8($sp)
+        move $s0, $a0
+        move $s1, $a1
+pas_pad_next:
+        slt $t0, $s1, $s0
+        beqz $t0, pas_pad_end
+        li $a0, 1
+        li $a1, 32
+        jal pas_put
+        addiu $s1, $s1, 1
+        b pas_pad_next
+pas_pad_end:
+        lw $ra, 0($sp)
+        lw $s0, 4($sp)
+        lw $s1, 8($sp)
+        addiu $sp, $sp, 16
+        jr $ra
+
+# Writes the integer in $a0 and $a1 in a field of $a2 and $a3
+pas_write_integer_width:
+        addiu $sp, $sp, -16
+        sw $ra, 0($sp)
+        sw $s0, 4($sp)
+        sw $s1, 8($sp)
+        move $s0, $a0
+        move $s1, $a1
+        move $a0, $a2
+        move $a1, $a3
+        jal pas_field
+        move $a2, $s0
+        move $a3, $s1
+        jal pas_integer_text
+        move $s0, $v0
+        move $s1, $v1
+        move $a1, $v1
+        jal pas_pad
+        li $a0, 1
+        move $a1, $s0
+        move $a2, $s1
+        jal pas_put_text
+        lw $ra, 0($sp)
+        lw $s0, 4($sp)
+        lw $s1, 8($sp)
+        addiu $sp, $sp, 16
+        jr $ra
+
+# Writes the character in $a0 in a field of $a2 and $a3
+pas_write_char_width:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        sw $s0, 4($sp)
+        move $s0, $a0
+        move $a0, $a2
+        move $a1, $a3
+        jal pas_field
+        li $a1, 1
+        jal pas_pad
+        li $a0, 1
+        move $a1, $s0
+        jal pas_put
+        lw $ra, 0($sp)
+        lw $s0, 4($sp)
+        addiu $sp, $sp, 8
+        jr $ra
+
+# Writes the boolean in $a0 in a field of $a2 and $a3, cut to its first
+# characters if the field is narrower
+pas_write_boolean_width:
+        addiu $sp, $sp, -16
+        sw $ra, 0($sp)
+        sw $s0, 4($sp)
+        sw $s1, 8($sp)
+        la $s0, pas_m_false
+        li $s1, 5
+        beqz $a0, pas_write_boolean_width_field
+        la $s0, pas_m_true
+        li $s1, 4
+pas_write_boolean_width_field:
+        move $a0, $a2
+        move $a1, $a3
+        jal pas_field
+        slt $t0, $a0, $s1
+        beqz $t0, pas_write_boolean_width_pad
+        move $s1, $a0
+pas_write_boolean_width_pad:
+        move $a1, $s1
+        jal pas_pad
+        li $a0, 1
+        move $a1, $s0
+        move $a2, $s1
+        jal pas_put_text
+        lw $ra, 0($sp)
+        lw $s0, 4($sp)
+        lw $s1, 8($sp)
+        addiu $sp, $sp, 16
+        jr $ra
+
+# Writes the $a1 characters of the string at $a0 in a field of $a2 and
+# $a3, cut to their first ones if the field is narrower
+pas_write_string_width:
+        addiu $sp, $sp, -16
+        sw $ra, 0($sp)
+        sw $s0, 4($sp)
+        sw $s1, 8($sp)
+        move $s0, $a0
+        move $s1, $a1
+        move $a0, $a2
+        move $a1, $a3
+        jal pas_field
+        slt $t0, $a0, $s1
+        beqz $t0, pas_write_string_width_pad
+        move $s1, $a0
+pas_write_string_width_pad:
+        move $a1, $s1
+        jal pas_pad
+        move $a0, $s0
+        move $a1, $s1
+        jal pas_write_string
+        lw $ra, 0($sp)
+        lw $s0, 4($sp)
+        lw $s1, 8($sp)
+        addiu $sp, $sp, 16
+        jr $ra
+
+# Writes NaN, inf or -inf for the real in $f12 in a field of $a0, giving
+# in $v0 whether the real is one of them
+pas_put_special:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        mfc1 $t0, $f12
+        mfc1 $t1, $f13
+        srl $t2, $t1, 20
+        andi $t2, $t2, 0x7ff
+        li $t3, 0x7ff
+        li $v0, 0
+        bne $t2, $t3, pas_put_special_end
+        li $a1, 3
+        sll $t2, $t1, 12
+        or $t2, $t2, $t0
+        bnez $t2, pas_put_special_write
+        srl $t2, $t1, 31
+        addu $a1, $a1, $t2
+pas_put_special_write:
+        jal pas_pad
+        li $a0, 1
+        jal pas_put_real
+        li $v0, 1
+pas_put_special_end:
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 8
+        jr $ra
+
+# Sets r and s to the real whose bits are in $a0 and $a1, positive and
+# finite, as r / s from 0.1 up to 1 times 10 to the power of $v0. Zero is
+# 0 / 1 times 10.
+pas_exact:
+        addiu $sp, $sp, -24
+        sw $ra, 0($sp)
+        sw $s0, 4($sp)
+        sw $s1, 8($sp)
+        sw $s2, 12($sp)
+        sw $s3, 16($sp)
+        move $s0, $a0
+        move $s1, $a1
+        la $a0, pas_big_r
+        move $a1, $s0
+        move $a2, $s1
+        jal pas_big_set
+        la $a0, pas_big_s
+        li $a1, 1
+        li $a2, 0
+        jal pas_big_set
+        li $s2, 1
+        or $t0, $s0, $s1
+        beqz $t0, pas_exact_end
+        srl $t2, $s1, 20
+        li $t0, 0x000fffff
+        and $s1, $s1, $t0
+        beqz $t2, pas_exact_subnormal
+        lui $t0, 0x10
+        or $s1, $s1, $t0
+        addiu $s3, $t2, -1075
+        b pas_exact_mantissa
+pas_exact_subnormal:
+        li $s3, -1074
+pas_exact_mantissa:
+        la $a0, pas_big_r
+        move $a1, $s0
+        move $a2, $s1
+        jal pas_big_set
+        # the power of ten above the real, estimated as pas_put_real does
+        li $t0, 31
+        clz $t1, $s1
+        subu $t0, $t0, $t1
+        bnez $s1, pas_exact_bits
+        li $t0, -1
+        clz $t1, $s0
+        subu $t0, $t0, $t1
+pas_exact_bits:
+        addu $t0, $t0, $s3
+        addiu $t0, $t0, 32
+        mtc1 $t0, $f0
+        cvt.d.w $f0, $f0
+        la $t0, pas_real_constants
+        ldc1 $f2, 0($t0)
+        mul.d $f0, $f0, $f2
+        ldc1 $f2, 8($t0)
+        sub.d $f0, $f0, $f2
+        ceil.w.d $f0, $f0
+        mfc1 $s2, $f0
+        # the mantissa times 2 to the power of the exponent
+        bltz $s3, pas_exact_fraction
+        la $a0, pas_big_r
+        move $a1, $s3
+        jal pas_big_shl
+        b pas_exact_scale
+pas_exact_fraction:
+        la $a0, pas_big_s
+        subu $a1, $zero, $s3
+        jal pas_big_shl
+pas_exact_scale:
+        bltz $s2, pas_exact_small
+        la $a0, pas_big_s
+        move $a1, $s2
+        jal pas_big_pow10
+        b pas_exact_fixup
+pas_exact_small:
+        la $a0, pas_big_r
+        subu $a1, $zero, $s2
+        jal pas_big_pow10
+pas_exact_fixup:
+        # an estimate one too low leaves r / s at 1 or above
+        la $a0, pas_big_r
+        la $a1, pas_big_s
+        jal pas_big_compare
+        bltz $v0, pas_exact_end
+        la $a0, pas_big_s
+        li $a1, 10
+        li $a2, 0
+        jal pas_big_mul
+        addiu $s2, $s2, 1
+pas_exact_end:
+        move $v0, $s2
+        lw $ra, 0($sp)
+        lw $s0, 4($sp)
+        lw $s1, 8($sp)
+        lw $s2, 12($sp)
+        lw $s3, 16($sp)
+        addiu $sp, $sp, 24
+        jr $ra
+
+# Multiplies r by 10 and takes the next digit of r / s out of it
+pas_digit:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        sw $s0, 4($sp)
+        la $a0, pas_big_r
+        li $a1, 10
+        li $a2, 0
+        jal pas_big_mul
+        li $s0, 0
+pas_digit_next:
+        la $a0, pas_big_r
+        la $a1, pas_big_s
+        jal pas_big_compare
+        bltz $v0, pas_digit_end
+        la $a0, pas_big_r
+        la $a1, pas_big_s
+        jal pas_big_sub
+        addiu $s0, $s0, 1
+        b pas_digit_next
+pas_digit_end:
+        move $v0, $s0
+        lw $ra, 0($sp)
+        lw $s0, 4($sp)
+        addiu $sp, $sp, 8
+        jr $ra
+
+# Generates the first $a0 digits of r / s as pas_exact leaves them,
+# keeping r in pas_big_minus for pas_put_digits. Gives in $v0 the last of
+# the digits that is not 9, -1 if there is none, and in $v1 whether they
+# round up: when the rest is above half of the last one, or half with the
+# last one odd.
+pas_rounding:
+        addiu $sp, $sp, -24
+        sw $ra, 0($sp)
+        sw $s0, 4($sp)
+        sw $s1, 8($sp)
+        sw $s2, 12($sp)
+        sw $s3, 16($sp)
+        move $s0, $a0
+        li $s1, -1
+        li $s2, 0
+        li $s3, 0
+        la $a0, pas_big_plus
+        li $a1, 0
+        li $a2, 0
+        jal pas_big_set
+        la $a0, pas_big_minus
+        la $a1, pas_big_r
+        la $a2, pas_big_plus
+        jal pas_big_add
+pas_rounding_next:
+        beq $s2, $s0, pas_rounding_half
+        jal pas_digit
+        move $s3, $v0
+        li $t0, 9
+        beq $v0, $t0, pas_rounding_nine
+        move $s1, $s2
+pas_rounding_nine:
+        addiu $s2, $s2, 1
+        b pas_rounding_next
+pas_rounding_half:
+        la $a0, pas_big_t
+        la $a1, pas_big_r
+        la $a2, pas_big_r
+        jal pas_big_add
+        la $a0, pas_big_t
+        la $a1, pas_big_s
+        jal pas_big_compare
+        andi $t0, $s3, 1
+        addu $t0, $t0, $v0
+        slt $v1, $zero, $t0
+        move $v0, $s1
+        lw $ra, 0($sp)
+        lw $s0, 4($sp)
+        lw $s1, 8($sp)
+        lw $s2, 12($sp)
+        lw $s3, 16($sp)
+        addiu $sp, $sp, 24
+        jr $ra
+
+# Writes the $a0 digits pas_rounding generated, rounded as it found with the
+# last digit not 9 in $a1 and whether they round up in $a2, and a point
+# after the first $a3 characters. A 1 comes first when all of them roll
+# over.
+pas_put_digits:
+        addiu $sp, $sp, -32
+        sw $ra, 0($sp)
+        sw $s0, 4($sp)
+        sw $s1, 8($sp)
+        sw $s2, 12($sp)
+        sw $s3, 16($sp)
+        sw $s4, 20($sp)
+        sw $s5, 24($sp)
+        move $s0, $a0
+        move $s1, $a1
+        move $s2, $a2
+        move $s3, $a3
+        li $s4, 0
+        li $s5, 0
+        la $a0, pas_big_r
+        la $a1, pas_big_minus
+        la $a2, pas_big_plus
+        jal pas_big_add
+        beqz $s2, pas_put_digits_next
+        bgez $s1, pas_put_digits_next
+        li $a1, 49
+        jal pas_put_digits_one
+pas_put_digits_next:
+        beq $s5, $s0, pas_put_digits_end
+        jal pas_digit
+        move $a1, $v0
+        beqz $s2, pas_put_digits_write
+        slt $t0, $s1, $s5
+        beqz $t0, pas_put_digits_last
+        li $a1, 0
+        b pas_put_digits_write
+pas_put_digits_last:
+        bne $s1, $s5, pas_put_digits_write
+        addiu $a1, $a1, 1
+pas_put_digits_write:
+        addiu $a1, $a1, 48
+        jal pas_put_digits_one
+        addiu $s5, $s5, 1
+        b pas_put_digits_next
+pas_put_digits_end:
+        lw $ra, 0($sp)
+        lw $s0, 4($sp)
+        lw $s1, 8($sp)
+        lw $s2, 12($sp)
+        lw $s3, 16($sp)
+        lw $s4, 20($sp)
+        lw $s5, 24($sp)
+        addiu $sp, $sp, 32
+        jr $ra
+
+# Writes the character in $a1 for pas_put_digits, then the point if it
+# is due
+pas_put_digits_one:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        li $a0, 1
+        jal pas_put
+        addiu $s4, $s4, 1
+        bne $s4, $s3, pas_put_digits_one_end
+        li $a0, 1
+        li $a1, 46
+        jal pas_put
+pas_put_digits_one_end:
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 8
+        jr $ra
+
+# Writes the real in $f12 in floating-point form in a field of $a0 and
+# $a1: a sign, one digit before the point and as many after it as fill
+# the field, then an exponent of 2 digits or more
+pas_write_real_width:
+        addiu $sp, $sp, -40
+        sw $ra, 0($sp)
+        sw $s0, 4($sp)
+        sw $s1, 8($sp)
+        sw $s2, 12($sp)
+        sw $s3, 16($sp)
+        sw $s4, 20($sp)
+        sw $s5, 24($sp)
+        sw $s6, 28($sp)
+        sw $s7, 32($sp)
+        jal pas_field
+        move $s2, $a0
+        jal pas_put_special
+        bnez $v0, pas_write_real_end
+        # the field is 8 characters at least, 6 of them not digits
+        slti $t0, $s2, 8
+        beqz $t0, pas_write_real_width_sign
+        li $s2, 8
+pas_write_real_width_sign:
+        addiu $s7, $s2, -6
+        mfc1 $s5, $f12
+        mfc1 $s6, $f13
+        jal pas_real_sign
+        li $a0, 1
+        li $a1, 32
+        beqz $v0, pas_write_real_width_digits
+        li $a1, 45
+pas_write_real_width_digits:
+        jal pas_put
+        move $a0, $s5
+        move $a1, $s6
+        jal pas_exact
+        addiu $s3, $v0, -1
+        move $a0, $s7
+        jal pas_rounding
+        move $s0, $v0
+        move $s1, $v1
+        beqz $s1, pas_write_real_width_put
+        bgez $s0, pas_write_real_width_put
+        # all the digits roll over to 1 and zeros, a power of ten higher
+        addiu $s7, $s7, -1
+        addiu $s3, $s3, 1
+pas_write_real_width_put:
+        move $a0, $s7
+        move $a1, $s0
+        move $a2, $s1
+        li $a3, 1
+        jal pas_put_digits
+        li $a0, 1
+        li $a1, 69
+        jal pas_put
+        li $a0, 1
+        li $a1, 43
+        bgez $s3, pas_write_real_width_exponent
+        li $a1, 45
+        subu $s3, $zero, $s3
+pas_write_real_width_exponent:
+        jal pas_put
+        slti $t0, $s3, 10
+        beqz $t0, pas_write_real_width_end
+        li $a0, 1
+        li $a1, 48
+        jal pas_put
+pas_write_real_width_end:
+        li $a0, 1
+        move $a2, $s3
+        li $a3, 0
+        jal pas_put_integer
+        b pas_write_real_end
+
+# Whether the real whose bits are in $s5 and $s6 is below zero, clearing
+# its sign
+pas_real_sign:
+        srl $v0, $s6, 31
+        sll $s6, $s6, 1
+        srl $s6, $s6, 1
+        or $t0, $s5, $s6
+        sltu $t0, $zero, $t0
+        and $v0, $v0, $t0
+        jr $ra
+
+# Writes the real in $f12 in fixed-point form with the number of decimals
+# in $a2 and $a3, in a field of $a0 and $a1
+pas_write_real_fixed:
+        addiu $sp, $sp, -40
+        sw $ra, 0($sp)
+        sw $s0, 4($sp)
+        sw $s1, 8($sp)
+        sw $s2, 12($sp)
+        sw $s3, 16($sp)
+        sw $s4, 20($sp)
+        sw $s5, 24($sp)
+        sw $s6, 28($sp)
+        sw $s7, 32($sp)
+        jal pas_field
+        move $s2, $a0
+        move $a0, $a2
+        move $a1, $a3
+        jal pas_field
+        move $s3, $a0
+        move $a0, $s2
+        jal pas_put_special
+        bnez $v0, pas_write_real_end
+        mfc1 $s5, $f12
+        mfc1 $s6, $f13
+        jal pas_real_sign
+        move $s4, $v0
+        move $a0, $s5
+        move $a1, $s6
+        jal pas_exact
+        # the digits up to the last decimal, none if the real is below it
+        addu $s7, $v0, $s3
+        li $s0, -1
+        li $s1, 0
+        bgez $s7, pas_write_real_fixed_round
+        li $s7, 0
+        b pas_write_real_fixed_length
+pas_write_real_fixed_round:
+        move $a0, $s7
+        jal pas_rounding
+        move $s0, $v0
+        move $s1, $v1
+pas_write_real_fixed_length:
+        # $s5 digits are written, one more when all of them roll over,
+        # and $s6 before the point, with 0 for none
+        move $s5, $s7
+        beqz $s1, pas_write_real_fixed_before
+        bgez $s0, pas_write_real_fixed_before
+        addiu $s5, $s5, 1
+pas_write_real_fixed_before:
+        subu $s6, $s5, $s3
+        bgtz $s6, pas_write_real_fixed_pad
+        li $s6, 1
+pas_write_real_fixed_pad:
+        move $a0, $s2
+        addu $a1, $s6, $s3
+        addiu $a1, $a1, 1
+        addu $a1, $a1, $s4
+        jal pas_pad
+        beqz $s4, pas_write_real_fixed_number
+        li $a0, 1
+        li $a1, 45
+        jal pas_put
+pas_write_real_fixed_number:
+        subu $a3, $s5, $s3
+        bgtz $a3, pas_write_real_fixed_digits
+        li $a0, 1
+        li $a1, 48
+        jal pas_put
+        li $a0, 1
+        li $a1, 46
+        jal pas_put
+        subu $s6, $s3, $s5
+pas_write_real_fixed_zeros:
+        beqz $s6, pas_write_real_fixed_fraction
+        li $a0, 1
+        li $a1, 48
+        jal pas_put
+        addiu $s6, $s6, -1
+        b pas_write_real_fixed_zeros
+pas_write_real_fixed_fraction:
+        li $a3, -1
+pas_write_real_fixed_digits:
+        move $a0, $s7
+        move $a1, $s0
+        move $a2, $s1
+        jal pas_put_digits
+pas_write_real_end:
+        lw $ra, 0($sp)
+        lw $s0, 4($sp)
+        lw $s1, 8($sp)
+        lw $s2, 12($sp)
+        lw $s3, 16($sp)
+        lw $s4, 20($sp)
+        lw $s5, 24($sp)
+        lw $s6, 28($sp)
+        lw $s7, 32($sp)
+        addiu $sp, $sp, 40
+        jr $ra
+
+# Runtime errors, written as "Runtime Error " and a message that ends
+# with the text of the place, from the table at pas_sites. They never
+# return.
+
+pas_fail_begin:
+        li $a0, 2
+        la $a1, pas_m_error
+        b pas_put_string
+
+pas_fail_end:
+        la $t0, pas_sites
+        sll $t1, $t9, 2
+        addu $t0, $t0, $t1
+        lw $a1, 0($t0)
+        li $a0, 2
+        jal pas_put_string
+        li $a0, 1
+        li $v0, 17
+        syscall
+
+# Fails with the message at $a1
+pas_fail:
+        move $s0, $a1
+        jal pas_fail_begin
+        li $a0, 2
+        move $a1, $s0
+        jal pas_put_string
+        b pas_fail_end
+
+# Input
+
+# The next byte of the input, -1 at its end
+pas_getc:
+        la $t0, pas_pending
+        lw $v0, 0($t0)
+        li $t1, -2
+        beq $v0, $t1, pas_getc_read
+        sw $t1, 0($t0)
+        jr $ra
+pas_getc_read:
+        li $a0, 0
+        la $a1, pas_byte
+        li $a2, 1
+        li $v0, 14
+        syscall
+        blez $v0, pas_getc_end
+        la $t0, pas_byte
+        lbu $v0, 0($t0)
+        jr $ra
+pas_getc_end:
+        li $v0, -1
+        jr $ra
+
+pas_peek:
+        la $t0, pas_ahead
+        lw $v0, 0($t0)
+        li $t1, -2
+        bne $v0, $t1, pas_peek_end
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        jal pas_getc
+        li $t1, 13
+        bne $v0, $t1, pas_peek_line
+        jal pas_getc
+        li $t1, 10
+        beq $v0, $t1, pas_peek_line
+        la $t0, pas_pending
+        sw $v0, 0($t0)
+        li $v0, 13
+pas_peek_line:
+        li $t1, -1
+        bne $v0, $t1, pas_peek_keep
+        la $t0, pas_in_line
+        lw $t0, 0($t0)
+        beqz $t0, pas_peek_keep
+        li $v0, 10
+pas_peek_keep:
+        la $t0, pas_ahead
+        sw $v0, 0($t0)
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 8
+pas_peek_end:
+        jr $ra
+
+pas_next:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        jal pas_peek
+        la $t0, pas_ahead
+        li $t1, -2
+        sw $t1, 0($t0)
+        li $t1, -1
+        beq $v0, $t1, pas_next_end
+        xori $t1, $v0, 10
+        sltu $t1, $zero, $t1
+        la $t0, pas_in_line
+        sw $t1, 0($t0)
+pas_next_end:
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 8
+        jr $ra
+
+pas_eof:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        jal pas_peek
+        addiu $v0, $v0, 1
+        sltiu $v0, $v0, 1
+        li $v1, 0
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 8
+        jr $ra
+
+pas_eoln:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        jal pas_peek
+        xori $t0, $v0, 10
+        sltiu $t0, $t0, 1
+        addiu $t1, $v0, 1
+        sltiu $t1, $t1, 1
+        or $v0, $t0, $t1
+        li $v1, 0
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 8
+        jr $ra
+
+pas_end_of_input:
+        la $a1, pas_m_end
+        b pas_fail
+
+pas_read_char:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        jal pas_next
+        li $t0, -1
+        beq $v0, $t0, pas_end_of_input
+        li $t0, 10
+        bne $v0, $t0, pas_read_char_end
+        li $v0, 32
+pas_read_char_end:
+        li $v1, 0
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 8
+        jr $ra
+
+# Whether the character in $a1 is a sign ($a0 = 0), a digit (1) or in a
+# word (2)
+pas_accepts:
+        bnez $a0, pas_accepts_digit
+        xori $t0, $a1, 43
+        sltiu $t0, $t0, 1
+        xori $t1, $a1, 45
+        sltiu $t1, $t1, 1
+        or $v0, $t0, $t1
+        jr $ra
+pas_accepts_digit:
+        li $t0, 1
+        bne $a0, $t0, pas_accepts_word
+        addiu $t0, $a1, -48
+        sltiu $v0, $t0, 10
+        jr $ra
+pas_accepts_word:
+        li $v0, 0
+        li $t0, 32
+        beq $a1, $t0, pas_accepts_end
+        li $t0, 9
+        beq $a1, $t0, pas_accepts_end
+        li $t0, 10
+        beq $a1, $t0, pas_accepts_end
+        li $t0, 13
+        beq $a1, $t0, pas_accepts_end
+        li $v0, 1
+pas_accepts_end:
+        jr $ra
+
+# Appends the characters of the kind $a0 ahead to the token of length $a1,
+# giving its length
+pas_take:
+        addiu $sp, $sp, -16
+        sw $ra, 0($sp)
+        sw $s0, 4($sp)
+        sw $s1, 8($sp)
+        move $s0, $a0
+        move $s1, $a1
+pas_take_next:
+        jal pas_peek
+        li $t0, -1
+        beq $v0, $t0, pas_take_end
+        move $a0, $s0
+        move $a1, $v0
+        jal pas_accepts
+        beqz $v0, pas_take_end
+        sltiu $t0, $s1, 63
+        beqz $t0, pas_take_end
+        jal pas_next
+        la $t0, pas_token
+        addu $t0, $t0, $s1
+        sb $v0, 0($t0)
+        addiu $s1, $s1, 1
+        b pas_take_next
+pas_take_end:
+        move $v0, $s1
+        lw $ra, 0($sp)
+        lw $s0, 4($sp)
+        lw $s1, 8($sp)
+        addiu $sp, $sp, 16
+        jr $ra
+
+# Reads the characters of a number, a real if $a0 is not 0, or of the
+# word that stands where one was expected, to pas_token, giving their
+# count
+pas_read_number:
+        addiu $sp, $sp, -16
+        sw $ra, 0($sp)
+        sw $s0, 4($sp)
+        sw $s1, 8($sp)
+        move $s0, $a0
+pas_read_number_space:
+        jal pas_peek
+        li $t0, 32
+        beq $v0, $t0, pas_read_number_skip
+        addiu $t0, $v0, -9
+        sltiu $t0, $t0, 5
+        beqz $t0, pas_read_number_start
+pas_read_number_skip:
+        jal pas_next
+        b pas_read_number_space
+pas_read_number_start:
+        li $t0, -1
+        beq $v0, $t0, pas_end_of_input
+        li $a0, 0
+        li $a1, 0
+        jal pas_take
+        li $a0, 1
+        move $a1, $v0
+        jal pas_take
+        move $s1, $v0
+        beqz $s0, pas_read_number_word
+        jal pas_peek
+        li $t0, 46
+        bne $v0, $t0, pas_read_number_exponent
+        jal pas_next
+        la $t0, pas_token
+        addu $t0, $t0, $s1
+        sb $v0, 0($t0)
+        li $a0, 1
+        addiu $a1, $s1, 1
+        jal pas_take
+        move $s1, $v0
+pas_read_number_exponent:
+        jal pas_peek
+        ori $v0, $v0, 32
+        li $t0, 101
+        bne $v0, $t0, pas_read_number_word
+        jal pas_next
+        la $t0, pas_token
+        addu $t0, $t0, $s1
+        li $t1, 101
+        sb $t1, 0($t0)
+        li $a0, 0
+        addiu $a1, $s1, 1
+        jal pas_take
+        li $a0, 1
+        move $a1, $v0
+        jal pas_take
+        move $s1, $v0
+pas_read_number_word:
+        bnez $s1, pas_read_number_end
+        li $a0, 2
+        li $a1, 0
+        jal pas_take
+        move $s1, $v0
+pas_read_number_end:
+        move $v0, $s1
+        lw $ra, 0($sp)
+        lw $s0, 4($sp)
+        lw $s1, 8($sp)
+        addiu $sp, $sp, 16
+        jr $ra
+
+# Fails on the $a1 characters read where the type named at $a0 was
+# expected
+pas_invalid_input:
+        move $s0, $a0
+        move $s1, $a1
+        jal pas_fail_begin
+        li $a0, 2
+        la $a1, pas_m_expected
+        jal pas_put_string
+        li $a0, 2
+        move $a1, $s0
+        jal pas_put_string
+        li $a0, 2
+        la $a1, pas_m_got
+        jal pas_put_string
+        li $a0, 2
+        la $a1, pas_token
+        move $a2, $s1
+        jal pas_put_text
+        li $a0, 2
+        li $a1, 39
+        jal pas_put
+        b pas_fail_end
+
+pas_read_integer:
+        addiu $sp, $sp, -16
+        sw $ra, 0($sp)
+        sw $s0, 4($sp)
+        sw $s1, 8($sp)
+        sw $s2, 12($sp)
+        li $a0, 0
+        jal pas_read_number
+        move $s0, $v0
+        li $s1, 0
+        li $s2, 0
+        li $a0, 0
+        la $t0, pas_token
+        lbu $a1, 0($t0)
+        jal pas_accepts
+        beqz $v0, pas_read_integer_digits
+        la $t0, pas_token
+        lbu $t0, 0($t0)
+        xori $t0, $t0, 45
+        sltiu $s2, $t0, 1
+        li $s1, 1
+pas_read_integer_digits:
+        sltu $t0, $s1, $s0
+        beqz $t0, pas_read_integer_invalid
+        li $t4, 0
+        li $t5, 0
+pas_read_integer_next:
+        beq $s1, $s0, pas_read_integer_end
+        la $t0, pas_token
+        addu $t0, $t0, $s1
+        lbu $t6, 0($t0)
+        addiu $t6, $t6, -48
+        sltiu $t0, $t6, 10
+        beqz $t0, pas_read_integer_invalid
+        # the magnitude of the lowest integer is one more than the highest
+        li $t0, 0x0ccccccc
+        sltu $t1, $t0, $t5
+        bnez $t1, pas_read_integer_invalid
+        bne $t5, $t0, pas_read_integer_add
+        li $t0, 0xcccccccc
+        sltu $t1, $t0, $t4
+        bnez $t1, pas_read_integer_invalid
+        bne $t4, $t0, pas_read_integer_add
+        addiu $t0, $s2, 7
+        sltu $t1, $t0, $t6
+        bnez $t1, pas_read_integer_invalid
+pas_read_integer_add:
+        li $t0, 10
+        multu $t4, $t0
+        mflo $t1
+        mfhi $t2
+        mul $t5, $t5, $t0
+        addu $t5, $t5, $t2
+        addu $t4, $t1, $t6
+        sltu $t0, $t4, $t1
+        addu $t5, $t5, $t0
+        addiu $s1, $s1, 1
+        b pas_read_integer_next
+pas_read_integer_end:
+        move $v0, $t4
+        move $v1, $t5
+        beqz $s2, pas_read_integer_return
+        sltu $t0, $zero, $v0
+        subu $v0, $zero, $v0
+        subu $v1, $zero, $v1
+        subu $v1, $v1, $t0
+pas_read_integer_return:
+        lw $ra, 0($sp)
+        lw $s0, 4($sp)
+        lw $s1, 8($sp)
+        lw $s2, 12($sp)
+        addiu $sp, $sp, 16
+        jr $ra
+pas_read_integer_invalid:
+        la $a0, pas_m_integer
+        move $a1, $s0
+        b pas_invalid_input
+
+# Reads a real, rounded once to the nearest. Its digits make a number r
+# and a power of ten one s, whose quotient is taken to 58 bits and rounded
+# with whether anything remains.
+pas_read_real:
+        addiu $sp, $sp, -40
+        sw $ra, 0($sp)
+        sw $s0, 4($sp)
+        sw $s1, 8($sp)
+        sw $s2, 12($sp)
+        sw $s3, 16($sp)
+        sw $s4, 20($sp)
+        sw $s5, 24($sp)
+        sw $s6, 28($sp)
+        sw $s7, 32($sp)
+        li $a0, 1
+        jal pas_read_number
+        move $s0, $v0
+        la $a0, pas_big_r
+        li $a1, 0
+        li $a2, 0
+        jal pas_big_set
+        # $s1 is the character read, $s2 whether the real is negative, $s3
+        # the count of significant digits, $s4 of all digits, $s5 the
+        # exponent and $s6 whether the digits are after the point
+        li $s1, 0
+        li $s2, 0
+        li $s3, 0
+        li $s4, 0
+        li $s5, 0
+        li $s6, 0
+        li $a0, 0
+        la $t0, pas_token
+        lbu $a1, 0($t0)
+        jal pas_accepts
+        beqz $v0, pas_read_real_digits
+        la $t0, pas_token
+        lbu $t0, 0($t0)
+        xori $t0, $t0, 45
+        sltiu $s2, $t0, 1
+        li $s1, 1
+pas_read_real_digits:
+        beq $s1, $s0, pas_read_real_exponent
+        la $t0, pas_token
+        addu $t0, $t0, $s1
+        lbu $t7, 0($t0)
+        li $t0, 46
+        bne $t7, $t0, pas_read_real_digit
+        bnez $s6, pas_read_real_exponent
+        li $s6, 1
+        addiu $s1, $s1, 1
+        b pas_read_real_digits
+pas_read_real_digit:
+        addiu $s7, $t7, -48
+        sltiu $t0, $s7, 10
+        beqz $t0, pas_read_real_exponent
+        la $a0, pas_big_r
+        li $a1, 10
+        move $a2, $s7
+        jal pas_big_mul
+        or $t0, $s3, $s7
+        beqz $t0, pas_read_real_insignificant
+        addiu $s3, $s3, 1
+pas_read_real_insignificant:
+        subu $s5, $s5, $s6
+        addiu $s4, $s4, 1
+        addiu $s1, $s1, 1
+        b pas_read_real_digits
+pas_read_real_exponent:
+        beqz $s4, pas_read_real_invalid
+        beq $s1, $s0, pas_read_real_scale
+        la $t0, pas_token
+        addu $t0, $t0, $s1
+        lbu $t0, 0($t0)
+        li $t1, 101
+        bne $t0, $t1, pas_read_real_invalid
+        addiu $s1, $s1, 1
+        # $s6 is the power, $s7 whether it is negative and $s4 the count
+        # of its digits
+        li $s6, 0
+        li $s7, 0
+        li $s4, 0
+        beq $s1, $s0, pas_read_real_invalid
+        li $a0, 0
+        la $t0, pas_token
+        addu $t0, $t0, $s1
+        lbu $a1, 0($t0)
+        jal pas_accepts
+        beqz $v0, pas_read_real_power
+        la $t0, pas_token
+        addu $t0, $t0, $s1
+        lbu $t0, 0($t0)
+        xori $t0, $t0, 45
+        sltiu $s7, $t0, 1
+        addiu $s1, $s1, 1
+pas_read_real_power:
+        beq $s1, $s0, pas_read_real_powered
+        la $t0, pas_token
+        addu $t0, $t0, $s1
+        lbu $t0, 0($t0)
+        addiu $t0, $t0, -48
+        sltiu $t1, $t0, 10
+        beqz $t1, pas_read_real_invalid
+        # past any real, however many digits there are
+        sltiu $t1, $s6, 100000
+        beqz $t1, pas_read_real_past
+        li $t1, 10
+        mul $s6, $s6, $t1
+        addu $s6, $s6, $t0
+pas_read_real_past:
+        addiu $s4, $s4, 1
+        addiu $s1, $s1, 1
+        b pas_read_real_power
+pas_read_real_powered:
+        beqz $s4, pas_read_real_invalid
+        beqz $s7, pas_read_real_add
+        subu $s6, $zero, $s6
+pas_read_real_add:
+        addu $s5, $s5, $s6
+pas_read_real_scale:
+        mtc1 $zero, $f0
+        mtc1 $zero, $f1
+        beqz $s3, pas_read_real_sign
+        addu $t0, $s3, $s5
+        slti $t1, $t0, -324
+        bnez $t1, pas_read_real_sign
+        slti $t1, $t0, 311
+        bnez $t1, pas_read_real_finite
+        lui $t0, 0x7ff0
+        mtc1 $t0, $f1
+        b pas_read_real_sign
+pas_read_real_finite:
+        la $a0, pas_big_s
+        li $a1, 1
+        li $a2, 0
+        jal pas_big_set
+        bltz $s5, pas_read_real_fraction
+        la $a0, pas_big_r
+        move $a1, $s5
+        jal pas_big_pow10
+        b pas_read_real_quotient
+pas_read_real_fraction:
+        la $a0, pas_big_s
+        subu $a1, $zero, $s5
+        jal pas_big_pow10
+pas_read_real_quotient:
+        # a quotient from 2 to the power of 56 to 2 to the power of 58,
+        # $s6 being the shift that makes it so
+        la $a0, pas_big_s
+        jal pas_big_bits
+        move $s6, $v0
+        la $a0, pas_big_r
+        jal pas_big_bits
+        subu $s6, $s6, $v0
+        addiu $s6, $s6, 57
+        bltz $s6, pas_read_real_divisor
+        la $a0, pas_big_r
+        move $a1, $s6
+        jal pas_big_shl
+        b pas_read_real_divide
+pas_read_real_divisor:
+        la $a0, pas_big_s
+        subu $a1, $zero, $s6
+        jal pas_big_shl
+pas_read_real_divide:
+        la $a0, pas_big_t
+        la $a1, pas_big_s
+        li $a2, 192
+        jal pas_copy
+        la $a0, pas_big_t
+        li $a1, 57
+        jal pas_big_shl
+        # the quotient is in $s3 and $s4, its bit $s7 found next
+        li $s3, 0
+        li $s4, 0
+        li $s7, 57
+pas_read_real_bit:
+        la $a0, pas_big_r
+        la $a1, pas_big_t
+        jal pas_big_compare
+        bltz $v0, pas_read_real_shift
+        la $a0, pas_big_r
+        la $a1, pas_big_t
+        jal pas_big_sub
+        li $t1, 1
+        slti $t0, $s7, 32
+        beqz $t0, pas_read_real_high
+        sllv $t1, $t1, $s7
+        or $s3, $s3, $t1
+        b pas_read_real_shift
+pas_read_real_high:
+        addiu $t0, $s7, -32
+        sllv $t1, $t1, $t0
+        or $s4, $s4, $t1
+pas_read_real_shift:
+        beqz $s7, pas_read_real_divided
+        la $a0, pas_big_t
+        jal pas_big_shr1
+        addiu $s7, $s7, -1
+        b pas_read_real_bit
+pas_read_real_divided:
+        # the bits past 53, or past the least subnormal real, are dropped
+        # into a round bit ($t6) and whether any other is set ($t5)
+        la $a0, pas_big_r
+        jal pas_big_bits
+        sltu $t5, $zero, $v0
+        li $t6, 0
+        clz $t0, $s4
+        li $s7, 11
+        subu $s7, $s7, $t0
+        addiu $t0, $s6, -1074
+        slt $t1, $s7, $t0
+        beqz $t1, pas_read_real_most
+        move $s7, $t0
+pas_read_real_most:
+        slti $t1, $s7, 61
+        bnez $t1, pas_read_real_drop
+        li $s7, 60
+pas_read_real_drop:
+        move $t7, $s7
+pas_read_real_dropping:
+        beqz $t7, pas_read_real_round
+        or $t5, $t5, $t6
+        andi $t6, $s3, 1
+        srl $s3, $s3, 1
+        sll $t0, $s4, 31
+        or $s3, $s3, $t0
+        srl $s4, $s4, 1
+        addiu $t7, $t7, -1
+        b pas_read_real_dropping
+pas_read_real_round:
+        andi $t0, $s3, 1
+        or $t0, $t0, $t5
+        and $t0, $t0, $t6
+        beqz $t0, pas_read_real_convert
+        addiu $s3, $s3, 1
+        sltiu $t0, $s3, 1
+        addu $s4, $s4, $t0
+pas_read_real_convert:
+        move $a0, $s3
+        move $a1, $s4
+        jal pas_widen
+        mov.d $f12, $f0
+        subu $a0, $s7, $s6
+        jal pas_ldexp
+pas_read_real_sign:
+        beqz $s2, pas_read_real_end
+        neg.d $f0, $f0
+pas_read_real_end:
+        lw $ra, 0($sp)
+        lw $s0, 4($sp)
+        lw $s1, 8($sp)
+        lw $s2, 12($sp)
+        lw $s3, 16($sp)
+        lw $s4, 20($sp)
+        lw $s5, 24($sp)
+        lw $s6, 28($sp)
+        lw $s7, 32($sp)
+        addiu $sp, $sp, 40
+        jr $ra
+pas_read_real_invalid:
+        la $a0, pas_m_real
+        move $a1, $s0
+        b pas_invalid_input
+
+pas_readln:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+pas_readln_next:
+        jal pas_next
+        li $t0, -1
+        beq $v0, $t0, pas_end_of_input
+        li $t0, 10
+        bne $v0, $t0, pas_readln_next
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 8
+        jr $ra
+
+# Numbers of 48 words, at $a0
+
+# Sets a number to the one in $a1 and $a2
+pas_big_set:
+        li $t0, 0
+pas_big_set_next:
+        addu $t1, $a0, $t0
+        sw $zero, 0($t1)
+        addiu $t0, $t0, 4
+        sltiu $t1, $t0, 192
+        bnez $t1, pas_big_set_next
+        sw $a1, 0($a0)
+        sw $a2, 4($a0)
+        jr $ra
+
+# Multiplies a number by $a1, adding $a2
+pas_big_mul:
+        li $t0, 0
+pas_big_mul_next:
+        addu $t1, $a0, $t0
+        lw $t2, 0($t1)
+        multu $t2, $a1
+        mflo $t3
+        mfhi $t4
+        addu $t3, $t3, $a2
+        sltu $t5, $t3, $a2
+        addu $a2, $t4, $t5
+        sw $t3, 0($t1)
+        addiu $t0, $t0, 4
+        sltiu $t1, $t0, 192
+        bnez $t1, pas_big_mul_next
+        jr $ra
+
+# Multiplies a number by 2 to the power of $a1
+pas_big_shl:
+        addiu $sp, $sp, -16
+        sw $ra, 0($sp)
+        sw $s0, 4($sp)
+        sw $s1, 8($sp)
+        move $s0, $a0
+        move $s1, $a1
+pas_big_shl_next:
+        sltiu $t0, $s1, 31
+        bnez $t0, pas_big_shl_last
+        move $a0, $s0
+        li $a1, 0x80000000
+        li $a2, 0
+        jal pas_big_mul
+        addiu $s1, $s1, -31
+        b pas_big_shl_next
+pas_big_shl_last:
+        move $a0, $s0
+        li $a1, 1
+        sllv $a1, $a1, $s1
+        li $a2, 0
+        jal pas_big_mul
+        lw $ra, 0($sp)
+        lw $s0, 4($sp)
+        lw $s1, 8($sp)
+        addiu $sp, $sp, 16
+        jr $ra
+
+pas_big_shr1:
+        li $t0, 0
+pas_big_shr1_next:
+        addu $t1, $a0, $t0
+        lw $t2, 0($t1)
+        srl $t2, $t2, 1
+        sltiu $t3, $t0, 188
+        beqz $t3, pas_big_shr1_store
+        lw $t3, 4($t1)
+        sll $t3, $t3, 31
+        or $t2, $t2, $t3
+pas_big_shr1_store:
+        sw $t2, 0($t1)
+        addiu $t0, $t0, 4
+        sltiu $t1, $t0, 192
+        bnez $t1, pas_big_shr1_next
+        jr $ra
+
+# Multiplies a number by 10 to the power of $a1
+pas_big_pow10:
+        addiu $sp, $sp, -16
+        sw $ra, 0($sp)
+        sw $s0, 4($sp)
+        sw $s1, 8($sp)
+        move $s0, $a0
+        move $s1, $a1
+pas_big_pow10_billion:
+        slti $t0, $s1, 9
+        bnez $t0, pas_big_pow10_ten
+        move $a0, $s0
+        li $a1, 1000000000
+        li $a2, 0
+        jal pas_big_mul
+        addiu $s1, $s1, -9
+        b pas_big_pow10_billion
+pas_big_pow10_ten:
+        blez $s1, pas_big_pow10_end
+        move $a0, $s0
+        li $a1, 10
+        li $a2, 0
+        jal pas_big_mul
+        addiu $s1, $s1, -1
+        b pas_big_pow10_ten
+pas_big_pow10_end:
+        lw $ra, 0($sp)
+        lw $s0, 4($sp)
+        lw $s1, 8($sp)
+        addiu $sp, $sp, 16
+        jr $ra
+
+# r, m- and m+ of pas_put_real times 10
+pas_big_times10:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        la $a0, pas_big_r
+        li $a1, 10
+        li $a2, 0
+        jal pas_big_mul
+        la $a0, pas_big_minus
+        li $a1, 10
+        li $a2, 0
+        jal pas_big_mul
+        la $a0, pas_big_plus
+        li $a1, 10
+        li $a2, 0
+        jal pas_big_mul
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 8
+        jr $ra
+
+# Sets the number at $a0 to the sum of the ones at $a1 and $a2
+pas_big_add:
+        li $t0, 0
+        li $t6, 0
+pas_big_add_next:
+        addu $t1, $a1, $t0
+        lw $t2, 0($t1)
+        addu $t1, $a2, $t0
+        lw $t3, 0($t1)
+        addu $t4, $t2, $t3
+        sltu $t5, $t4, $t2
+        addu $t4, $t4, $t6
+        sltu $t7, $t4, $t6
+        or $t6, $t5, $t7
+        addu $t1, $a0, $t0
+        sw $t4, 0($t1)
+        addiu $t0, $t0, 4
+        sltiu $t1, $t0, 192
+        bnez $t1, pas_big_add_next
+        jr $ra
+
+# Subtracts the number at $a1 from the one at $a0, which is at least as
+# large
+pas_big_sub:
+        li $t0, 0
+        li $t6, 0
+pas_big_sub_next:
+        addu $t1, $a0, $t0
+        lw $t2, 0($t1)
+        addu $t3, $a1, $t0
+        lw $t3, 0($t3)
+        subu $t4, $t2, $t3
+        sltu $t5, $t2, $t3
+        sltu $t7, $t4, $t6
+        subu $t4, $t4, $t6
+        or $t6, $t5, $t7
+        sw $t4, 0($t1)
+        addiu $t0, $t0, 4
+        sltiu $t1, $t0, 192
+        bnez $t1, pas_big_sub_next
+        jr $ra
+
+# Compares the numbers at $a0 and $a1, giving -1, 0 or 1
+pas_big_compare:
+        li $t0, 192
+pas_big_compare_next:
+        beqz $t0, pas_big_compare_equal
+        addiu $t0, $t0, -4
+        addu $t1, $a0, $t0
+        lw $t1, 0($t1)
+        addu $t2, $a1, $t0
+        lw $t2, 0($t2)
+        beq $t1, $t2, pas_big_compare_next
+        li $v0, 1
+        sltu $t3, $t1, $t2
+        beqz $t3, pas_big_compare_end
+        li $v0, -1
+        jr $ra
+pas_big_compare_equal:
+        li $v0, 0
+pas_big_compare_end:
+        jr $ra
+
+# The number of bits of a number
+pas_big_bits:
+        li $t0, 192
+pas_big_bits_next:
+        beqz $t0, pas_big_bits_zero
+        addiu $t0, $t0, -4
+        addu $t1, $a0, $t0
+        lw $t1, 0($t1)
+        beqz $t1, pas_big_bits_next
+        sll $v0, $t0, 3
+        addiu $v0, $v0, 32
+        clz $t1, $t1
+        subu $v0, $v0, $t1
+        jr $ra
+pas_big_bits_zero:
+        li $v0, 0
+        jr $ra
+
+# Arithmetic on integers, in pairs of registers
+
+# The quotient of the unsigned $a0 and $a1 by $a2 and $a3, with the
+# remainder in $t0 and $t1
+pas_udivmod:
+        li $v0, 0
+        li $v1, 0
+        li $t0, 0
+        li $t1, 0
+        li $t2, 64
+pas_udivmod_next:
+        sll $t1, $t1, 1
+        srl $t3, $t0, 31
+        or $t1, $t1, $t3
+        sll $t0, $t0, 1
+        srl $t3, $a1, 31
+        or $t0, $t0, $t3
+        sll $a1, $a1, 1
+        srl $t3, $a0, 31
+        or $a1, $a1, $t3
+        sll $a0, $a0, 1
+        sll $v1, $v1, 1
+        srl $t3, $v0, 31
+        or $v1, $v1, $t3
+        sll $v0, $v0, 1
+        sltu $t3, $t1, $a3
+        bnez $t3, pas_udivmod_skip
+        bne $t1, $a3, pas_udivmod_take
+        sltu $t3, $t0, $a2
+        bnez $t3, pas_udivmod_skip
+pas_udivmod_take:
+        sltu $t3, $t0, $a2
+        subu $t0, $t0, $a2
+        subu $t1, $t1, $a3
+        subu $t1, $t1, $t3
+        ori $v0, $v0, 1
+pas_udivmod_skip:
+        addiu $t2, $t2, -1
+        bnez $t2, pas_udivmod_next
+        jr $ra
+
+# Makes $a0 to $a3 the magnitudes of the integers in them
+pas_magnitudes:
+        bgez $a1, pas_magnitudes_right
+        sltu $t0, $zero, $a0
+        subu $a0, $zero, $a0
+        subu $a1, $zero, $a1
+        subu $a1, $a1, $t0
+pas_magnitudes_right:
+        bgez $a3, pas_magnitudes_end
+        sltu $t0, $zero, $a2
+        subu $a2, $zero, $a2
+        subu $a3, $zero, $a3
+        subu $a3, $a3, $t0
+pas_magnitudes_end:
+        jr $ra
+
+pas_division_by_zero:
+        la $a1, pas_m_zero
+        b pas_fail
+
+pas_div:
+        or $t0, $a2, $a3
+        beqz $t0, pas_division_by_zero
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        xor $t0, $a1, $a3
+        sw $t0, 4($sp)
+        jal pas_magnitudes
+        jal pas_udivmod
+        lw $t0, 4($sp)
+        bgez $t0, pas_div_end
+        sltu $t0, $zero, $v0
+        subu $v0, $zero, $v0
+        subu $v1, $zero, $v1
+        subu $v1, $v1, $t0
+pas_div_end:
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 8
+        jr $ra
+
+# The remainder takes the sign of the divisor, as in the interpreter
+pas_mod:
+        or $t0, $a2, $a3
+        beqz $t0, pas_division_by_zero
+        addiu $sp, $sp, -16
+        sw $ra, 0($sp)
+        sw $a1, 4($sp)
+        sw $a2, 8($sp)
+        sw $a3, 12($sp)
+        jal pas_magnitudes
+        jal pas_udivmod
+        move $v0, $t0
+        move $v1, $t1
+        lw $t0, 4($sp)
+        bgez $t0, pas_mod_signed
+        sltu $t0, $zero, $v0
+        subu $v0, $zero, $v0
+        subu $v1, $zero, $v1
+        subu $v1, $v1, $t0
+pas_mod_signed:
+        bgez $v1, pas_mod_end
+        lw $a2, 8($sp)
+        lw $a3, 12($sp)
+        bltz $a3, pas_mod_subtract
+        addu $v0, $v0, $a2
+        sltu $t0, $v0, $a2
+        addu $v1, $v1, $a3
+        addu $v1, $v1, $t0
+        b pas_mod_end
+pas_mod_subtract:
+        sltu $t0, $v0, $a2
+        subu $v0, $v0, $a2
+        subu $v1, $v1, $a3
+        subu $v1, $v1, $t0
+pas_mod_end:
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 16
+        jr $ra
+
+pas_overflow:
+        la $a1, pas_m_overflow
+        b pas_fail
+
+# The operations stopping the program when their result does not fit, the
+# operands being in $a0 and $a1 and in $a2 and $a3
+pas_add_checked:
+        addu $v0, $a0, $a2
+        sltu $t0, $v0, $a0
+        addu $v1, $a1, $a3
+        addu $v1, $v1, $t0
+        xor $t0, $v1, $a1
+        xor $t1, $v1, $a3
+        and $t0, $t0, $t1
+        bltz $t0, pas_overflow
+        jr $ra
+
+pas_sub_checked:
+        sltu $t0, $a0, $a2
+        subu $v0, $a0, $a2
+        subu $v1, $a1, $a3
+        subu $v1, $v1, $t0
+        xor $t0, $a1, $a3
+        xor $t1, $v1, $a1
+        and $t0, $t0, $t1
+        bltz $t0, pas_overflow
+        jr $ra
+
+pas_neg_checked:
+        move $a2, $a0
+        move $a3, $a1
+        li $a0, 0
+        li $a1, 0
+        b pas_sub_checked
+
+pas_abs_checked:
+        bltz $a1, pas_neg_checked
+        move $v0, $a0
+        move $v1, $a1
+        jr $ra
+
+# Only the smallest integer divided by -1 overflows
+pas_div_checked:
+        and $t0, $a2, $a3
+        addiu $t0, $t0, 1
+        bnez $t0, pas_div
+        lui $t0, 0x8000
+        bne $a1, $t0, pas_div
+        bnez $a0, pas_div
+        b pas_overflow
+
+# The magnitudes are multiplied, one of them having to fit in 32 bits, and
+# the sign is given back to a product that fits
+pas_mul_checked:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        xor $t0, $a1, $a3
+        sw $t0, 4($sp)
+        jal pas_magnitudes
+        lw $ra, 0($sp)
+        lw $t4, 4($sp)
+        addiu $sp, $sp, 8
+        beqz $a1, pas_mul_checked_low
+        bnez $a3, pas_overflow
+        multu $a1, $a2
+        b pas_mul_checked_cross
+pas_mul_checked_low:
+        multu $a3, $a0
+pas_mul_checked_cross:
+        mfhi $t0
+        bnez $t0, pas_overflow
+        mflo $t1
+        multu $a0, $a2
+        mflo $v0
+        mfhi $v1
+        addu $v1, $v1, $t1
+        sltu $t0, $v1, $t1
+        bnez $t0, pas_overflow
+        bltz $t4, pas_mul_checked_negative
+        bltz $v1, pas_overflow
+        jr $ra
+pas_mul_checked_negative:
+        lui $t0, 0x8000
+        sltu $t1, $t0, $v1
+        bnez $t1, pas_overflow
+        bne $v1, $t0, pas_mul_checked_negate
+        bnez $v0, pas_overflow
+pas_mul_checked_negate:
+        sltu $t0, $zero, $v0
+        subu $v0, $zero, $v0
+        subu $v1, $zero, $v1
+        subu $v1, $v1, $t0
+        jr $ra
+
+pas_abs:
+        move $v0, $a0
+        move $v1, $a1
+        bgez $v1, pas_abs_end
+        sltu $t0, $zero, $v0
+        subu $v0, $zero, $v0
+        subu $v1, $zero, $v1
+        subu $v1, $v1, $t0
+pas_abs_end:
+        jr $ra
+
+# Reals
+
+# The integer in $a0 and $a1 as a real, rounded once
+pas_widen:
+        mtc1 $a1, $f0
+        cvt.d.w $f0, $f0
+        lui $t0, 0x41f0
+        mtc1 $t0, $f3
+        mtc1 $zero, $f2
+        mul.d $f0, $f0, $f2
+        mtc1 $a0, $f4
+        cvt.d.w $f4, $f4
+        bgez $a0, pas_widen_low
+        add.d $f4, $f4, $f2
+pas_widen_low:
+        add.d $f0, $f0, $f4
+        jr $ra
+
+# The real in $f12 without its fraction, reals out of the range of
+# integers saturating and NaN becoming 0
+pas_trunc:
+        mfc1 $t0, $f12
+        mfc1 $t1, $f13
+        li $v0, 0
+        li $v1, 0
+        srl $t2, $t1, 20
+        andi $t2, $t2, 0x7ff
+        slti $t3, $t2, 1023
+        bnez $t3, pas_trunc_end
+        li $t3, 0x7ff
+        bne $t2, $t3, pas_trunc_number
+        sll $t4, $t1, 12
+        or $t4, $t4, $t0
+        bnez $t4, pas_trunc_end
+pas_trunc_number:
+        slti $t3, $t2, 1086
+        bnez $t3, pas_trunc_mantissa
+        li $v1, 0x80000000
+        bltz $t1, pas_trunc_end
+        li $v0, -1
+        li $v1, 0x7fffffff
+        jr $ra
+pas_trunc_mantissa:
+        li $t4, 0x000fffff
+        and $t5, $t1, $t4
+        lui $t4, 0x10
+        or $t5, $t5, $t4
+        li $t4, 1075
+        subu $t6, $t4, $t2
+pas_trunc_right:
+        blez $t6, pas_trunc_left
+        srl $t0, $t0, 1
+        sll $t4, $t5, 31
+        or $t0, $t0, $t4
+        srl $t5, $t5, 1
+        addiu $t6, $t6, -1
+        b pas_trunc_right
+pas_trunc_left:
+        bgez $t6, pas_trunc_sign
+        sll $t5, $t5, 1
+        srl $t4, $t0, 31
+        or $t5, $t5, $t4
+        sll $t0, $t0, 1
+        addiu $t6, $t6, 1
+        b pas_trunc_left
+pas_trunc_sign:
+        move $v0, $t0
+        move $v1, $t5
+        bgez $t1, pas_trunc_end
+        sltu $t0, $zero, $v0
+        subu $v0, $zero, $v0
+        subu $v1, $zero, $v1
+        subu $v1, $v1, $t0
+pas_trunc_end:
+        jr $ra
+
+# Halves are rounded away from 0
+pas_round:
+        addiu $sp, $sp, -16
+        sw $ra, 0($sp)
+        jal pas_trunc
+        sw $v0, 8($sp)
+        sw $v1, 12($sp)
+        # reals from 2 to the power of 52 on have no fraction
+        abs.d $f2, $f12
+        la $t0, pas_turn_constants
+        ldc1 $f4, 8($t0)
+        c.lt.d $f2, $f4
+        bc1f pas_round_end
+        move $a0, $v0
+        move $a1, $v1
+        jal pas_widen
+        sub.d $f2, $f12, $f0
+        lui $t0, 0x3fe0
+        mtc1 $t0, $f5
+        mtc1 $zero, $f4
+        lw $v0, 8($sp)
+        lw $v1, 12($sp)
+        c.le.d $f4, $f2
+        bc1f pas_round_down
+        addiu $v0, $v0, 1
+        sltiu $t0, $v0, 1
+        addu $v1, $v1, $t0
+        b pas_round_end
+pas_round_down:
+        neg.d $f4, $f4
+        c.le.d $f2, $f4
+        bc1f pas_round_end
+        sltiu $t0, $v0, 1
+        addiu $v0, $v0, -1
+        subu $v1, $v1, $t0
+pas_round_end:
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 16
+        jr $ra
+
+pas_rdiv:
+        mtc1 $zero, $f0
+        mtc1 $zero, $f1
+        c.eq.d $f14, $f0
+        bc1t pas_division_by_zero
+        div.d $f0, $f12, $f14
+        jr $ra
+
+# Fails as the function named at $a1 is not defined for $f12
+pas_domain:
+        move $s0, $a1
+        jal pas_fail_begin
+        li $a0, 2
+        li $a1, 39
+        jal pas_put
+        li $a0, 2
+        move $a1, $s0
+        jal pas_put_string
+        li $a0, 2
+        la $a1, pas_m_defined
+        jal pas_put_string
+        li $a0, 2
+        jal pas_put_real
+        b pas_fail_end
+
+pas_sqrt:
+        mtc1 $zero, $f0
+        mtc1 $zero, $f1
+        la $a1, pas_m_sqrt
+        c.lt.d $f12, $f0
+        bc1t pas_domain
+        sqrt.d $f0, $f12
+        jr $ra
+
+pas_ln:
+        mtc1 $zero, $f0
+        mtc1 $zero, $f1
+        la $a1, pas_m_ln
+        c.le.d $f12, $f0
+        bc1t pas_domain
+        b pas_log
+
+# The standard functions MIPS has no instruction for, after the ones of
+# fdlibm, within an ulp or so of the C library. They keep their values in
+# registers from $f6 on, which pas_poly leaves alone.
+
+# The polynomial at $f12 with the $a1 coefficients at $a0, the highest
+# first; it uses $f0 and $f2
+pas_poly:
+        ldc1 $f0, 0($a0)
+pas_poly_next:
+        addiu $a1, $a1, -1
+        beqz $a1, pas_poly_end
+        addiu $a0, $a0, 8
+        mul.d $f0, $f0, $f12
+        ldc1 $f2, 0($a0)
+        add.d $f0, $f0, $f2
+        b pas_poly_next
+pas_poly_end:
+        jr $ra
+
+# $f12 times 2 to the power of $a0
+pas_ldexp:
+        mov.d $f0, $f12
+pas_ldexp_high:
+        slti $t0, $a0, 1024
+        bnez $t0, pas_ldexp_low
+        lui $t0, 0x7fe0
+        mtc1 $t0, $f3
+        mtc1 $zero, $f2
+        mul.d $f0, $f0, $f2
+        addiu $a0, $a0, -1023
+        b pas_ldexp_high
+pas_ldexp_low:
+        slti $t0, $a0, -1022
+        beqz $t0, pas_ldexp_end
+        lui $t0, 0x10
+        mtc1 $t0, $f3
+        mtc1 $zero, $f2
+        mul.d $f0, $f0, $f2
+        addiu $a0, $a0, 1022
+        b pas_ldexp_low
+pas_ldexp_end:
+        addiu $t0, $a0, 1023
+        sll $t0, $t0, 20
+        mtc1 $t0, $f3
+        mtc1 $zero, $f2
+        mul.d $f0, $f0, $f2
+        jr $ra
+
+pas_exp:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        mov.d $f0, $f12
+        c.eq.d $f12, $f12
+        bc1f pas_exp_end
+        la $t0, pas_exp_constants
+        ldc1 $f2, 0($t0)
+        c.lt.d $f2, $f12
+        bc1f pas_exp_finite
+        lui $t0, 0x7ff0
+        mtc1 $t0, $f1
+        mtc1 $zero, $f0
+        b pas_exp_end
+pas_exp_finite:
+        ldc1 $f2, 8($t0)
+        mtc1 $zero, $f0
+        mtc1 $zero, $f1
+        c.lt.d $f12, $f2
+        bc1t pas_exp_end
+        # x = k ln 2 + r, with r at most half of ln 2
+        ldc1 $f2, 16($t0)
+        mul.d $f2, $f12, $f2
+        round.w.d $f4, $f2
+        mfc1 $t1, $f4
+        sw $t1, 4($sp)
+        cvt.d.w $f6, $f4
+        ldc1 $f2, 24($t0)
+        mul.d $f2, $f6, $f2
+        sub.d $f8, $f12, $f2
+        ldc1 $f2, 32($t0)
+        mul.d $f10, $f6, $f2
+        sub.d $f16, $f8, $f10
+        mul.d $f18, $f16, $f16
+        mov.d $f12, $f18
+        la $a0, pas_exp_poly
+        li $a1, 5
+        jal pas_poly
+        mul.d $f0, $f18, $f0
+        sub.d $f20, $f16, $f0
+        # 1 - ((lo - r c / (2 - c)) - hi)
+        mul.d $f0, $f16, $f20
+        lui $t0, 0x4000
+        mtc1 $t0, $f3
+        mtc1 $zero, $f2
+        sub.d $f2, $f2, $f20
+        div.d $f0, $f0, $f2
+        sub.d $f0, $f10, $f0
+        sub.d $f0, $f0, $f8
+        lui $t0, 0x3ff0
+        mtc1 $t0, $f3
+        mtc1 $zero, $f2
+        sub.d $f12, $f2, $f0
+        lw $a0, 4($sp)
+        jal pas_ldexp
+pas_exp_end:
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 8
+        jr $ra
+
+# The natural logarithm of a positive real
+pas_log:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        mov.d $f0, $f12
+        c.eq.d $f12, $f12
+        bc1f pas_log_end
+        mfc1 $t1, $f13
+        li $t0, 0x7ff00000
+        beq $t1, $t0, pas_log_end
+        # subnormals are scaled to normal reals first
+        li $t5, 0
+        srl $t2, $t1, 20
+        bnez $t2, pas_log_normal
+        lui $t0, 0x4350
+        mtc1 $t0, $f3
+        mtc1 $zero, $f2
+        mul.d $f12, $f12, $f2
+        li $t5, -54
+pas_log_normal:
+        # x = 2^k m, with m between the square root of 2 and its half
+        mfc1 $t1, $f13
+        srl $t2, $t1, 20
+        addu $t5, $t5, $t2
+        addiu $t5, $t5, -1023
+        li $t0, 0x000fffff
+        and $t1, $t1, $t0
+        lui $t0, 0x3ff0
+        or $t1, $t1, $t0
+        mtc1 $t1, $f13
+        la $t0, pas_log_constants
+        ldc1 $f2, 0($t0)
+        c.lt.d $f2, $f12
+        bc1f pas_log_reduced
+        lui $t0, 0x3fe0
+        mtc1 $t0, $f3
+        mtc1 $zero, $f2
+        mul.d $f12, $f12, $f2
+        addiu $t5, $t5, 1
+pas_log_reduced:
+        mtc1 $t5, $f22
+        cvt.d.w $f22, $f22
+        lui $t0, 0x3ff0
+        mtc1 $t0, $f3
+        mtc1 $zero, $f2
+        sub.d $f6, $f12, $f2
+        add.d $f2, $f2, $f2
+        add.d $f2, $f2, $f6
+        div.d $f8, $f6, $f2
+        mul.d $f10, $f8, $f8
+        mul.d $f16, $f10, $f10
+        mov.d $f12, $f16
+        la $a0, pas_log_even
+        li $a1, 3
+        jal pas_poly
+        mul.d $f18, $f16, $f0
+        la $a0, pas_log_odd
+        li $a1, 4
+        jal pas_poly
+        mul.d $f0, $f10, $f0
+        add.d $f18, $f18, $f0
+        # k ln2hi - ((half - (s (half + r) + k ln2lo)) - f)
+        mul.d $f20, $f6, $f6
+        lui $t0, 0x3fe0
+        mtc1 $t0, $f3
+        mtc1 $zero, $f2
+        mul.d $f20, $f20, $f2
+        add.d $f0, $f20, $f18
+        mul.d $f0, $f8, $f0
+        la $t0, pas_log_constants
+        ldc1 $f2, 16($t0)
+        mul.d $f2, $f22, $f2
+        add.d $f0, $f0, $f2
+        sub.d $f0, $f20, $f0
+        sub.d $f0, $f0, $f6
+        ldc1 $f2, 8($t0)
+        mul.d $f2, $f22, $f2
+        sub.d $f0, $f2, $f0
+pas_log_end:
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 8
+        jr $ra
+
+# Sine and cosine of $f12 + $f14 on the quarter of a turn around 0, the
+# tail $f14 being below an ulp of $f12
+pas_sin_kernel:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        mov.d $f16, $f12
+        mul.d $f6, $f12, $f12
+        mul.d $f8, $f6, $f12
+        mov.d $f12, $f6
+        la $a0, pas_sin_poly
+        li $a1, 5
+        jal pas_poly
+        # x - ((z (tail / 2 - v r) - tail) - v S1)
+        lui $t0, 0x3fe0
+        mtc1 $t0, $f3
+        mtc1 $zero, $f2
+        mul.d $f2, $f2, $f14
+        mul.d $f4, $f8, $f0
+        sub.d $f2, $f2, $f4
+        mul.d $f2, $f6, $f2
+        sub.d $f2, $f2, $f14
+        la $t0, pas_sin_constants
+        ldc1 $f4, 0($t0)
+        mul.d $f4, $f8, $f4
+        sub.d $f2, $f2, $f4
+        sub.d $f0, $f16, $f2
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 8
+        jr $ra
+
+pas_cos_kernel:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        mov.d $f16, $f12
+        mul.d $f6, $f12, $f12
+        mov.d $f12, $f6
+        la $a0, pas_cos_poly
+        li $a1, 6
+        jal pas_poly
+        mul.d $f8, $f6, $f0
+        # w + (((1 - w) - half) + (z r - x tail)), with w = 1 - half
+        lui $t0, 0x3fe0
+        mtc1 $t0, $f3
+        mtc1 $zero, $f2
+        mul.d $f10, $f2, $f6
+        lui $t0, 0x3ff0
+        mtc1 $t0, $f3
+        sub.d $f18, $f2, $f10
+        sub.d $f0, $f2, $f18
+        sub.d $f0, $f0, $f10
+        mul.d $f2, $f6, $f8
+        mul.d $f4, $f16, $f14
+        sub.d $f2, $f2, $f4
+        add.d $f0, $f0, $f2
+        add.d $f0, $f18, $f0
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 8
+        jr $ra
+
+# The sine of $f12 plus $a0 quarter turns. The real is taken to within a
+# quarter turn of 0 less the nearest multiple of one, every product of
+# the quarter turn being exact after Dekker, which is exact enough while
+# the multiple fits 53 bits.
+pas_turn:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        sw $a0, 4($sp)
+        # infinities and NaN
+        sub.d $f0, $f12, $f12
+        mtc1 $zero, $f2
+        mtc1 $zero, $f3
+        c.eq.d $f0, $f2
+        bc1f pas_turn_end
+        mov.d $f20, $f12
+        la $t8, pas_turn_constants
+        ldc1 $f2, 0($t8)
+        mul.d $f22, $f12, $f2
+        abs.d $f2, $f22
+        ldc1 $f4, 8($t8)
+        c.lt.d $f2, $f4
+        bc1f pas_turn_nearest
+        add.d $f2, $f2, $f4
+        sub.d $f2, $f2, $f4
+        mtc1 $zero, $f4
+        mtc1 $zero, $f5
+        c.lt.d $f22, $f4
+        bc1f pas_turn_positive
+        neg.d $f2, $f2
+pas_turn_positive:
+        mov.d $f22, $f2
+pas_turn_nearest:
+        mov.d $f12, $f22
+        jal pas_trunc
+        lw $t0, 4($sp)
+        addu $t0, $t0, $v0
+        andi $t0, $t0, 3
+        sw $t0, 4($sp)
+        la $t8, pas_turn_constants
+        ldc1 $f8, 16($t8)
+        mul.d $f4, $f22, $f8
+        sub.d $f6, $f4, $f22
+        sub.d $f24, $f4, $f6
+        sub.d $f26, $f22, $f24
+        mov.d $f28, $f20
+        mtc1 $zero, $f30
+        mtc1 $zero, $f31
+        li $t1, 0
+pas_turn_part:
+        addu $t2, $t8, $t1
+        ldc1 $f2, 24($t2)
+        mul.d $f4, $f2, $f8
+        sub.d $f6, $f4, $f2
+        sub.d $f4, $f4, $f6
+        sub.d $f6, $f2, $f4
+        # the product is $f10 + $f16 exactly
+        mul.d $f10, $f22, $f2
+        mul.d $f16, $f24, $f4
+        sub.d $f16, $f16, $f10
+        mul.d $f0, $f24, $f6
+        add.d $f16, $f16, $f0
+        mul.d $f0, $f26, $f4
+        add.d $f16, $f16, $f0
+        mul.d $f0, $f26, $f6
+        add.d $f16, $f16, $f0
+        # y + tail less it
+        sub.d $f18, $f28, $f10
+        sub.d $f0, $f18, $f28
+        sub.d $f2, $f18, $f0
+        sub.d $f2, $f28, $f2
+        add.d $f4, $f10, $f0
+        sub.d $f2, $f2, $f4
+        sub.d $f30, $f30, $f16
+        add.d $f30, $f30, $f2
+        add.d $f28, $f18, $f30
+        sub.d $f2, $f28, $f18
+        sub.d $f30, $f30, $f2
+        addiu $t1, $t1, 8
+        slti $t2, $t1, 32
+        bnez $t2, pas_turn_part
+        mov.d $f12, $f28
+        mov.d $f14, $f30
+        lw $t0, 4($sp)
+        andi $t1, $t0, 1
+        bnez $t1, pas_turn_cosine
+        jal pas_sin_kernel
+        b pas_turn_quadrant
+pas_turn_cosine:
+        jal pas_cos_kernel
+pas_turn_quadrant:
+        lw $t0, 4($sp)
+        slti $t0, $t0, 2
+        bnez $t0, pas_turn_end
+        neg.d $f0, $f0
+pas_turn_end:
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 8
+        jr $ra
+
+pas_sin:
+        li $a0, 0
+        b pas_turn
+
+pas_cos:
+        li $a0, 1
+        b pas_turn
+
+pas_atan:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        mov.d $f0, $f12
+        c.eq.d $f12, $f12
+        bc1f pas_atan_end
+        mov.d $f20, $f12
+        abs.d $f6, $f12
+        la $t8, pas_atan_constants
+        lui $t0, 0x4410
+        mtc1 $t0, $f3
+        mtc1 $zero, $f2
+        c.le.d $f2, $f6
+        bc1f pas_atan_reduce
+        ldc1 $f0, 48($t8)
+        ldc1 $f2, 56($t8)
+        add.d $f0, $f0, $f2
+        b pas_atan_sign
+pas_atan_reduce:
+        # the argument is reduced around 0.5, 1, 1.5 or infinity, $t7
+        # being the offset of the arctangent of which, -1 for none
+        li $t7, -1
+        lui $t0, 0x3fdc
+        mtc1 $t0, $f3
+        c.lt.d $f6, $f2
+        bc1t pas_atan_series
+        lui $t0, 0x3ff3
+        mtc1 $t0, $f3
+        c.lt.d $f6, $f2
+        bc1f pas_atan_large
+        lui $t0, 0x3fe6
+        mtc1 $t0, $f3
+        c.lt.d $f6, $f2
+        bc1f pas_atan_one
+        li $t7, 0
+        lui $t0, 0x4000
+        mtc1 $t0, $f3
+        add.d $f4, $f6, $f6
+        lui $t0, 0x3ff0
+        mtc1 $t0, $f3
+        sub.d $f4, $f4, $f2
+        lui $t0, 0x4000
+        mtc1 $t0, $f3
+        add.d $f2, $f2, $f6
+        div.d $f6, $f4, $f2
+        b pas_atan_series
+pas_atan_one:
+        li $t7, 16
+        lui $t0, 0x3ff0
+        mtc1 $t0, $f3
+        sub.d $f4, $f6, $f2
+        add.d $f2, $f6, $f2
+        div.d $f6, $f4, $f2
+        b pas_atan_series
+pas_atan_large:
+        lui $t0, 0x4003
+        ori $t0, $t0, 0x8000
+        mtc1 $t0, $f3
+        c.lt.d $f6, $f2
+        bc1f pas_atan_infinity
+        li $t7, 32
+        lui $t0, 0x3ff8
+        mtc1 $t0, $f3
+        sub.d $f4, $f6, $f2
+        mul.d $f2, $f2, $f6
+        lui $t0, 0x3ff0
+        mtc1 $t0, $f11
+        mtc1 $zero, $f10
+        add.d $f2, $f10, $f2
+        div.d $f6, $f4, $f2
+        b pas_atan_series
+pas_atan_infinity:
+        li $t7, 48
+        lui $t0, 0xbff0
+        mtc1 $t0, $f3
+        div.d $f6, $f2, $f6
+pas_atan_series:
+        mul.d $f8, $f6, $f6
+        mul.d $f10, $f8, $f8
+        mov.d $f12, $f10
+        la $a0, pas_atan_even
+        li $a1, 6
+        jal pas_poly
+        mul.d $f16, $f8, $f0
+        la $a0, pas_atan_odd
+        li $a1, 5
+        jal pas_poly
+        mul.d $f0, $f10, $f0
+        add.d $f16, $f16, $f0
+        mul.d $f16, $f6, $f16
+        bgez $t7, pas_atan_offset
+        sub.d $f0, $f6, $f16
+        b pas_atan_sign
+pas_atan_offset:
+        # hi - ((a s - lo) - a)
+        la $t8, pas_atan_constants
+        addu $t8, $t8, $t7
+        ldc1 $f2, 8($t8)
+        sub.d $f16, $f16, $f2
+        sub.d $f16, $f16, $f6
+        ldc1 $f2, 0($t8)
+        sub.d $f0, $f2, $f16
+pas_atan_sign:
+        mfc1 $t0, $f21
+        bgez $t0, pas_atan_end
+        neg.d $f0, $f0
+pas_atan_end:
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 8
+        jr $ra
+
+# Checks
+
+# Whether the integer in $a0 and $a1 is within the bounds at $a2, the
+# lowest and the highest integer
+pas_within:
+        li $v0, 0
+        lw $t0, 0($a2)
+        lw $t1, 4($a2)
+        slt $t2, $a1, $t1
+        bnez $t2, pas_within_end
+        bne $a1, $t1, pas_within_high
+        sltu $t2, $a0, $t0
+        bnez $t2, pas_within_end
+pas_within_high:
+        lw $t0, 8($a2)
+        lw $t1, 12($a2)
+        slt $t2, $t1, $a1
+        bnez $t2, pas_within_end
+        bne $a1, $t1, pas_within_yes
+        sltu $t2, $t0, $a0
+        bnez $t2, pas_within_end
+pas_within_yes:
+        li $v0, 1
+pas_within_end:
+        jr $ra
+
+# Checks that the integer in $a0 and $a1 is of the type at $a2: its
+# bounds, and the address and the length of its name
+pas_check:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        jal pas_within
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 8
+        beqz $v0, pas_check_fail
+        move $v0, $a0
+        move $v1, $a1
+        jr $ra
+pas_check_fail:
+        move $s0, $a0
+        move $s1, $a1
+        move $s2, $a2
+        jal pas_fail_begin
+        li $a0, 2
+        la $a1, pas_m_value
+        jal pas_put_string
+        li $a0, 2
+        move $a2, $s0
+        move $a3, $s1
+        jal pas_put_integer
+        li $a0, 2
+        la $a1, pas_m_outside
+        jal pas_put_string
+        li $a0, 2
+        lw $a1, 16($s2)
+        lw $a2, 20($s2)
+        jal pas_put_text
+        b pas_fail_end
+
+# Checks that the integer in $a0 and $a1 is within the bounds of an array
+# at $a2, giving how far it is from the lowest
+pas_index:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        jal pas_within
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 8
+        beqz $v0, pas_index_fail
+        lw $t0, 0($a2)
+        subu $v0, $a0, $t0
+        jr $ra
+pas_index_fail:
+        move $s0, $a0
+        move $s1, $a1
+        move $s2, $a2
+        jal pas_fail_begin
+        li $a0, 2
+        la $a1, pas_m_index
+        jal pas_put_string
+        li $a0, 2
+        move $a2, $s0
+        move $a3, $s1
+        jal pas_put_integer
+        li $a0, 2
+        la $a1, pas_m_bounds
+        jal pas_put_string
+        li $a0, 2
+        lw $a2, 0($s2)
+        lw $a3, 4($s2)
+        jal pas_put_integer
+        li $a0, 2
+        la $a1, pas_m_dots
+        jal pas_put_string
+        li $a0, 2
+        lw $a2, 8($s2)
+        lw $a3, 12($s2)
+        jal pas_put_integer
+        b pas_fail_end
+
+# Fails as no case label matches the value in $a0 and $a1, whose type is
+# at $a2: 'i' for integers, 'c' for characters, 'b' for booleans and 'e'
+# for enumerations, with the table of the address and the length of the
+# names of their literals and its length
+pas_no_case:
+        move $s0, $a0
+        move $s1, $a1
+        move $s2, $a2
+        jal pas_fail_begin
+        li $a0, 2
+        la $a1, pas_m_case
+        jal pas_put_string
+        lw $t0, 0($s2)
+        li $t1, 99
+        bne $t0, $t1, pas_no_case_boolean
+        li $a0, 2
+        li $a1, 39
+        jal pas_put
+        li $a0, 2
+        move $a1, $s0
+        jal pas_put
+        li $a0, 2
+        li $a1, 39
+        jal pas_put
+        b pas_fail_end
+pas_no_case_boolean:
+        li $t1, 98
+        bne $t0, $t1, pas_no_case_enumeration
+        li $a0, 2
+        move $a2, $s0
+        jal pas_put_boolean
+        b pas_fail_end
+pas_no_case_enumeration:
+        li $t1, 101
+        bne $t0, $t1, pas_no_case_integer
+        bnez $s1, pas_no_case_integer
+        lw $t1, 8($s2)
+        sltu $t1, $s0, $t1
+        beqz $t1, pas_no_case_integer
+        lw $t1, 4($s2)
+        sll $t0, $s0, 3
+        addu $t1, $t1, $t0
+        li $a0, 2
+        lw $a2, 4($t1)
+        lw $a1, 0($t1)
+        jal pas_put_text
+        b pas_fail_end
+pas_no_case_integer:
+        li $a0, 2
+        move $a2, $s0
+        move $a3, $s1
+        jal pas_put_integer
+        b pas_fail_end
+
+# Compares the strings of $a2 characters at $a0 and $a1, giving -1, 0 or 1
+pas_compare:
+        li $v0, 0
+pas_compare_next:
+        beqz $a2, pas_compare_end
+        lw $t0, 0($a0)
+        lw $t1, 0($a1)
+        addiu $a0, $a0, 8
+        addiu $a1, $a1, 8
+        addiu $a2, $a2, -1
+        beq $t0, $t1, pas_compare_next
+        li $v0, 1
+        sltu $t2, $t0, $t1
+        beqz $t2, pas_compare_end
+        li $v0, -1
+pas_compare_end:
+        jr $ra
+
+# Sets of the ordinals from 0 to 255, as eight words
+
+# Adds the member in $a2 and $a3 to the set at $a0
+pas_set_add:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        sw $a0, 4($sp)
+        move $a0, $a2
+        move $a1, $a3
+        la $a2, pas_members
+        jal pas_check
+        lw $a0, 4($sp)
+        srl $t0, $v0, 5
+        sll $t0, $t0, 2
+        addu $t0, $a0, $t0
+        lw $t1, 0($t0)
+        li $t2, 1
+        sllv $t2, $t2, $v0
+        or $t1, $t1, $t2
+        sw $t1, 0($t0)
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 8
+        jr $ra
+
+pas_set_has:
+        li $v0, 0
+        li $v1, 0
+        bnez $a3, pas_set_has_end
+        sltiu $t0, $a2, 256
+        beqz $t0, pas_set_has_end
+        srl $t0, $a2, 5
+        sll $t0, $t0, 2
+        addu $t0, $a0, $t0
+        lw $t1, 0($t0)
+        srlv $t1, $t1, $a2
+        andi $v0, $t1, 1
+pas_set_has_end:
+        jr $ra
+
+# The word-wise operation $a3, 0 (union), 1 (difference) or 2
+# (intersection), of the sets at $a1 and $a2, into the one at $a0
+pas_set_combine:
+        li $t0, 0
+pas_set_combine_next:
+        addu $t1, $a1, $t0
+        lw $t1, 0($t1)
+        addu $t2, $a2, $t0
+        lw $t2, 0($t2)
+        bnez $a3, pas_set_combine_and
+        or $t1, $t1, $t2
+        b pas_set_combine_store
+pas_set_combine_and:
+        li $t3, 1
+        bne $a3, $t3, pas_set_combine_both
+        nor $t2, $t2, $zero
+pas_set_combine_both:
+        and $t1, $t1, $t2
+pas_set_combine_store:
+        addu $t2, $a0, $t0
+        sw $t1, 0($t2)
+        addiu $t0, $t0, 4
+        sltiu $t1, $t0, 32
+        bnez $t1, pas_set_combine_next
+        jr $ra
+
+pas_set_eq:
+        li $v0, 1
+        li $v1, 0
+        li $t0, 0
+pas_set_eq_next:
+        addu $t1, $a0, $t0
+        lw $t1, 0($t1)
+        addu $t2, $a1, $t0
+        lw $t2, 0($t2)
+        bne $t1, $t2, pas_set_eq_not
+        addiu $t0, $t0, 4
+        sltiu $t1, $t0, 32
+        bnez $t1, pas_set_eq_next
+        jr $ra
+pas_set_eq_not:
+        li $v0, 0
+        jr $ra
+
+# Whether the set at $a0 is a subset of the one at $a1
+pas_set_le:
+        li $v0, 1
+        li $v1, 0
+        li $t0, 0
+pas_set_le_next:
+        addu $t1, $a0, $t0
+        lw $t1, 0($t1)
+        addu $t2, $a1, $t0
+        lw $t2, 0($t2)
+        nor $t2, $t2, $zero
+        and $t1, $t1, $t2
+        bnez $t1, pas_set_le_not
+        addiu $t0, $t0, 4
+        sltiu $t1, $t0, 32
+        bnez $t1, pas_set_le_next
+        jr $ra
+pas_set_le_not:
+        li $v0, 0
+        jr $ra
+
+# Memory
+
+# Copies $a2 bytes, a multiple of 4, from $a1 to $a0
+pas_copy:
+        beqz $a2, pas_copy_end
+        lw $t0, 0($a1)
+        sw $t0, 0($a0)
+        addiu $a0, $a0, 4
+        addiu $a1, $a1, 4
+        addiu $a2, $a2, -4
+        b pas_copy
+pas_copy_end:
+        jr $ra
+
+# Zeroes $a1 bytes, a multiple of 4, from $a0
+pas_zero:
+        beqz $a1, pas_zero_end
+        sw $zero, 0($a0)
+        addiu $a0, $a0, 4
+        addiu $a1, $a1, -4
+        b pas_zero
+pas_zero_end:
+        jr $ra
+
+# Heap variables of $a0 bytes, after a header telling whether they are
+# live; they are never freed, so that a disposed one is caught. sbrk only
+# keeps words aligned, and reals want 8 bytes.
+pas_new:
+        addiu $a0, $a0, 16
+        li $v0, 9
+        syscall
+        addiu $v0, $v0, 15
+        li $t0, -8
+        and $v0, $v0, $t0
+        li $t0, 1
+        sw $t0, -8($v0)
+        jr $ra
+
+pas_deref:
+        la $a1, pas_m_nil
+        beqz $a0, pas_fail
+        lw $t0, -8($a0)
+        la $a1, pas_m_disposed
+        beqz $t0, pas_fail
+        move $v0, $a0
+        jr $ra
+
+pas_dispose:
+        addiu $sp, $sp, -8
+        sw $ra, 0($sp)
+        jal pas_deref
+        sw $zero, -8($v0)
+        lw $ra, 0($sp)
+        addiu $sp, $sp, 8
+        jr $ra
@@ -0,0 +1,716 @@
+//! A simulator of the MIPS instructions the translation and its runtime
+//! use, with the syscalls of SPIM, so that the tests run what `--emit mips`
+//! gives without SPIM or MARS. Like them it has no delay slots, and it takes
+//! the pseudo-instructions as single ones.
+
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+
+/// Where the code is, each instruction taking a word as in SPIM
+const TEXT: u32 = 0x0040_0000;
+/// Where the data segment starts, the heap following it
+const DATA: u32 = 0x1001_0000;
+/// The lowest address of the stack, which ends below 2 GiB
+const STACK: u32 = 0x7c00_0000;
+const STACK_END: u32 = 0x8000_0000;
+/// Where $sp starts, as in SPIM
+const STACK_POINTER: u32 = 0x7fff_effc;
+/// The instructions a program may run before it is taken to loop forever
+const BUDGET: u64 = 2_000_000_000;
+
+/// Runs a program in the assembly of SPIM, giving its output, and the
+/// error output if it ends with a status other than 0. Assembly the
+/// simulator does not know, and faults such as unaligned loads, panic.
+pub fn run(source: &str, input: &str) -> (String, Option<String>) {
+    let (code, data) = assemble(source);
+    let mut machine = Machine {
+        registers: [0; 32],
+        floats: [0; 32],
+        condition: false,
+        hi: 0,
+        lo: 0,
+        memory: Memory { data, stack: vec![0; (STACK_END - STACK) as usize] },
+        input: input.as_bytes(),
+        output: vec![],
+        error: vec![]
+    };
+    let status = machine.run(&code);
+    let output = String::from_utf8_lossy(&machine.output).to_string();
+    (output, if status == 0 { None } else { Some(String::from_utf8_lossy(&machine.error).to_string()) })
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Op {
+    Addu, Subu, And, Or, Xor, Nor, Slt, Sltu, Sllv, Srlv, Srav, Mul,
+    Addiu, Andi, Ori, Xori, Slti, Sltiu, Sll, Srl, Sra, Lui, Li, Move, Clz,
+    Lw, Sw, Lbu, Lb, Sb, Ldc1, Sdc1,
+    Mult, Multu, Div, Divu, Mflo, Mfhi,
+    B, J, Jal, Jr, Jalr, Beq, Bne, Beqz, Bnez, Bgez, Bgtz, Blez, Bltz,
+    AddD, SubD, MulD, DivD, AbsD, NegD, MovD, SqrtD, CEqD, CLtD, CLeD, Bc1t, Bc1f,
+    Mtc1, Mfc1, CvtDW, RoundWD, CeilWD, FloorWD, TruncWD,
+    Syscall
+}
+
+/// The operation of a mnemonic, and its operands: `r` for a register, `f`
+/// for a floating-point one and `i` for an immediate or a label, a memory
+/// operand `offset(base)` being `ir`
+fn decode(mnemonic: &str) -> Option<(Op, &'static str)> {
+    use Op::*;
+    Some(match mnemonic {
+        "addu" => (Addu, "rrr"),
+        "subu" => (Subu, "rrr"),
+        "and" => (And, "rrr"),
+        "or" => (Or, "rrr"),
+        "xor" => (Xor, "rrr"),
+        "nor" => (Nor, "rrr"),
+        "slt" => (Slt, "rrr"),
+        "sltu" => (Sltu, "rrr"),
+        "sllv" => (Sllv, "rrr"),
+        "srlv" => (Srlv, "rrr"),
+        "srav" => (Srav, "rrr"),
+        "mul" => (Mul, "rrr"),
+        "addiu" => (Addiu, "rri"),
+        "andi" => (Andi, "rri"),
+        "ori" => (Ori, "rri"),
+        "xori" => (Xori, "rri"),
+        "slti" => (Slti, "rri"),
+        "sltiu" => (Sltiu, "rri"),
+        "sll" => (Sll, "rri"),
+        "srl" => (Srl, "rri"),
+        "sra" => (Sra, "rri"),
+        "lui" => (Lui, "ri"),
+        "li" | "la" => (Li, "ri"),
+        "move" => (Move, "rr"),
+        "clz" => (Clz, "rr"),
+        "lw" => (Lw, "rir"),
+        "sw" => (Sw, "rir"),
+        "lbu" => (Lbu, "rir"),
+        "lb" => (Lb, "rir"),
+        "sb" => (Sb, "rir"),
+        "ldc1" => (Ldc1, "fir"),
+        "sdc1" => (Sdc1, "fir"),
+        "mult" => (Mult, "rr"),
+        "multu" => (Multu, "rr"),
+        "div" => (Div, "rr"),
+        "divu" => (Divu, "rr"),
+        "mflo" => (Mflo, "r"),
+        "mfhi" => (Mfhi, "r"),
+        "b" => (B, "i"),
+        "j" => (J, "i"),
+        "jal" => (Jal, "i"),
+        "jr" => (Jr, "r"),
+        "jalr" => (Jalr, "r"),
+        "beq" => (Beq, "rri"),
+        "bne" => (Bne, "rri"),
+        "beqz" => (Beqz, "ri"),
+        "bnez" => (Bnez, "ri"),
+        "bgez" => (Bgez, "ri"),
+        "bgtz" => (Bgtz, "ri"),
+        "blez" => (Blez, "ri"),
+        "bltz" => (Bltz, "ri"),
+        "add.d" => (AddD, "fff"),
+        "sub.d" => (SubD, "fff"),
+        "mul.d" => (MulD, "fff"),
+        "div.d" => (DivD, "fff"),
+        "abs.d" => (AbsD, "ff"),
+        "neg.d" => (NegD, "ff"),
+        "mov.d" => (MovD, "ff"),
+        "sqrt.d" => (SqrtD, "ff"),
+        "c.eq.d" => (CEqD, "ff"),
+        "c.lt.d" => (CLtD, "ff"),
+        "c.le.d" => (CLeD, "ff"),
+        "bc1t" => (Bc1t, "i"),
+        "bc1f" => (Bc1f, "i"),
+        "mtc1" => (Mtc1, "rf"),
+        "mfc1" => (Mfc1, "rf"),
+        "cvt.d.w" => (CvtDW, "ff"),
+        "round.w.d" => (RoundWD, "ff"),
+        "ceil.w.d" => (CeilWD, "ff"),
+        "floor.w.d" => (FloorWD, "ff"),
+        "trunc.w.d" => (TruncWD, "ff"),
+        "syscall" => (Syscall, ""),
+        _ => return None
+    })
+}
+
+/// An instruction with its registers in order and its immediate, labels
+/// being resolved to their addresses
+#[derive(Clone, Debug)]
+struct Instruction {
+    op: Op,
+    registers: [usize; 3],
+    immediate: u32,
+    line: usize
+}
+
+enum Operand {
+    Register(usize),
+    Float(usize),
+    Immediate(i64),
+    Label(String),
+    Memory(Box<Operand>, usize)
+}
+
+fn register(name: &str) -> Option<usize> {
+    const NAMES: [&str; 32] = [
+        "zero", "at", "v0", "v1", "a0", "a1", "a2", "a3", "t0", "t1", "t2", "t3", "t4", "t5", "t6", "t7",
+        "s0", "s1", "s2", "s3", "s4", "s5", "s6", "s7", "t8", "t9", "k0", "k1", "gp", "sp", "fp", "ra"
+    ];
+    let name = name.strip_prefix('$')?;
+    NAMES.iter().position(|known| *known == name)
+        .or_else(|| name.parse().ok().filter(|number| *number < 32))
+}
+
+fn number(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text)
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None if digits.starts_with(|c: char| c.is_ascii_digit()) => digits.parse().ok()?,
+        None => return None
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn operand(text: &str) -> Result<Operand, String> {
+    if let Some(float) = text.strip_prefix("$f").and_then(|number| number.parse().ok()) {
+        return if float < 32 { Ok(Operand::Float(float)) } else { Err(format!("no register {}", text)) };
+    }
+    if text.starts_with('$') {
+        return register(text).map(Operand::Register).ok_or_else(|| format!("no register {}", text));
+    }
+    if let (Some(open), true) = (text.find('('), text.ends_with(')')) {
+        let base = register(&text[open + 1..text.len() - 1]).ok_or_else(|| format!("no base in {}", text))?;
+        let offset = if open == 0 { Operand::Immediate(0) } else { operand(&text[..open])? };
+        return Ok(Operand::Memory(Box::new(offset), base));
+    }
+    if let Some(value) = number(text) {
+        return Ok(Operand::Immediate(value));
+    }
+    if is_label(text) {
+        return Ok(Operand::Label(text.to_string()));
+    }
+    Err(format!("cannot read the operand {}", text))
+}
+
+fn is_label(text: &str) -> bool {
+    !text.is_empty()
+        && !text.starts_with(|c: char| c.is_ascii_digit())
+        && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// A line without its comment
+fn code(line: &str) -> &str {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// The operands of a line, split at the commas outside strings
+fn operands(text: &str) -> Vec<String> {
+    let mut operands = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut escaped = false;
+    for c in text.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                operands.push(current.trim().to_string());
+                current.clear();
+                continue;
+            },
+            _ => {}
+        }
+        current.push(c);
+    }
+    if !current.trim().is_empty() {
+        operands.push(current.trim().to_string());
+    }
+    operands
+}
+
+/// The bytes of a string literal of `.ascii` and `.asciiz`
+fn string(text: &str) -> Result<Vec<u8>, String> {
+    let inner = text.strip_prefix('"').and_then(|text| text.strip_suffix('"'))
+        .ok_or_else(|| format!("{} is not a string", text))?;
+    let mut bytes = vec![];
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        bytes.push(match c {
+            '\\' => match chars.next() {
+                Some('n') => b'\n',
+                Some('t') => b'\t',
+                Some('0') => 0,
+                Some(c @ ('\\' | '"')) => c as u8,
+                other => return Err(format!("unknown escape \\{:?}", other))
+            },
+            c => c as u8
+        });
+    }
+    Ok(bytes)
+}
+
+/// The instructions and the data segment of a program
+fn assemble(source: &str) -> (Vec<Instruction>, Vec<u8>) {
+    match try_assemble(source) {
+        Ok(program) => program,
+        Err((line, message)) => panic!("line {} of the assembly: {}", line, message)
+    }
+}
+
+fn try_assemble(source: &str) -> Result<(Vec<Instruction>, Vec<u8>), (usize, String)> {
+    let mut labels = HashMap::new();
+    let mut data = vec![];
+    // the words of the data segment that hold labels
+    let mut fixups = vec![];
+    let mut text = vec![];
+    let mut in_text = true;
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let fail = |message: String| (line_number, message);
+        let mut rest = code(line).trim();
+        let mut pending = vec![];
+        while let Some(colon) = rest.find(':') {
+            if !is_label(&rest[..colon]) {
+                break;
+            }
+            pending.push(rest[..colon].to_string());
+            rest = rest[colon + 1..].trim();
+        }
+        let (head, tail) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let arguments = operands(tail);
+        let align = |data: &mut Vec<u8>, to: usize| while !data.len().is_multiple_of(to) {
+            data.push(0);
+        };
+        match head {
+            ".data" => in_text = false,
+            ".text" => in_text = true,
+            ".globl" => {},
+            ".align" if !in_text => {
+                let power = arguments.first().and_then(|power| number_u32(power))
+                    .ok_or_else(|| fail("no alignment".to_string()))?;
+                align(&mut data, 1 << power);
+            },
+            ".word" | ".double" => align(&mut data, if head == ".word" { 4 } else { 8 }),
+            _ => {}
+        }
+        let address = if in_text { TEXT + 4 * text.len() as u32 } else { DATA + data.len() as u32 };
+        for label in pending {
+            if labels.insert(label.clone(), address).is_some() {
+                return Err(fail(format!("{} is defined twice", label)));
+            }
+        }
+        match head {
+            "" | ".data" | ".text" | ".globl" | ".align" => {},
+            ".word" => for argument in &arguments {
+                match number(argument) {
+                    Some(value) => data.extend((value as u32).to_le_bytes()),
+                    None => {
+                        fixups.push((data.len(), argument.clone(), line_number));
+                        data.extend([0; 4]);
+                    }
+                }
+            },
+            ".byte" => for argument in &arguments {
+                data.push(number(argument).ok_or_else(|| fail(format!("{} is no byte", argument)))? as u8);
+            },
+            ".double" => for argument in &arguments {
+                let value = argument.parse::<f64>().map_err(|_| fail(format!("{} is no real", argument)))?;
+                data.extend(value.to_bits().to_le_bytes());
+            },
+            ".space" => {
+                let size = arguments.first().and_then(|size| number_u32(size))
+                    .ok_or_else(|| fail("no size".to_string()))?;
+                data.extend(std::iter::repeat_n(0, size as usize));
+            },
+            ".ascii" | ".asciiz" => {
+                data.extend(string(tail.trim()).map_err(fail)?);
+                if head == ".asciiz" {
+                    data.push(0);
+                }
+            },
+            directive if directive.starts_with('.') => return Err(fail(format!("unknown directive {}", directive))),
+            _ if !in_text => return Err(fail("an instruction in the data segment".to_string())),
+            mnemonic => text.push((line_number, mnemonic.to_string(), arguments))
+        }
+    }
+
+    for (offset, label, line) in fixups {
+        let address = labels.get(&label).ok_or_else(|| (line, format!("no label {}", label)))?;
+        data[offset..offset + 4].copy_from_slice(&address.to_le_bytes());
+    }
+    let code = text.into_iter()
+        .map(|(line, mnemonic, arguments)| instruction(&labels, &mnemonic, &arguments).map(|(op, registers, immediate)|
+            Instruction { op, registers, immediate, line }).map_err(|message| (line, message)))
+        .collect::<Result<_, _>>()?;
+    Ok((code, data))
+}
+
+fn number_u32(text: &str) -> Option<u32> {
+    number(text).and_then(|value| u32::try_from(value).ok())
+}
+
+fn instruction(labels: &HashMap<String, u32>, mnemonic: &str, arguments: &[String])
+    -> Result<(Op, [usize; 3], u32), String> {
+    let (op, shape) = decode(mnemonic).ok_or_else(|| format!("unknown instruction {}", mnemonic))?;
+    let mut kinds = String::new();
+    let mut registers = vec![];
+    let mut immediate = 0;
+    let mut value = |operand: Operand, kinds: &mut String, registers: &mut Vec<usize>| -> Result<(), String> {
+        match operand {
+            Operand::Register(number) => {
+                kinds.push('r');
+                registers.push(number);
+            },
+            Operand::Float(number) => {
+                kinds.push('f');
+                registers.push(number);
+            },
+            Operand::Immediate(number) => {
+                kinds.push('i');
+                immediate = number as u32;
+            },
+            Operand::Label(label) => {
+                kinds.push('i');
+                immediate = *labels.get(&label).ok_or_else(|| format!("no label {}", label))?;
+            },
+            Operand::Memory(..) => unreachable!("memory operands are taken apart")
+        }
+        Ok(())
+    };
+    for argument in arguments {
+        match operand(argument)? {
+            Operand::Memory(offset, base) => {
+                value(*offset, &mut kinds, &mut registers)?;
+                value(Operand::Register(base), &mut kinds, &mut registers)?;
+            },
+            operand => value(operand, &mut kinds, &mut registers)?
+        }
+    }
+    if kinds != shape {
+        return Err(format!("{} takes {}, not {}", mnemonic, shape, kinds));
+    }
+    let mut fixed = [0; 3];
+    fixed[..registers.len()].copy_from_slice(&registers);
+    Ok((op, fixed, immediate))
+}
+
+/// The data segment and the heap after it, and the stack
+struct Memory {
+    data: Vec<u8>,
+    stack: Vec<u8>
+}
+
+impl Memory {
+    fn bytes(&mut self, address: u32, size: u32) -> Result<&mut [u8], String> {
+        if !address.is_multiple_of(size) {
+            return Err(format!("unaligned access of {} bytes at {:#x}", size, address));
+        }
+        let (memory, start) = if address >= STACK {
+            (&mut self.stack, address - STACK)
+        } else if address >= DATA {
+            (&mut self.data, address - DATA)
+        } else {
+            return Err(format!("access outside memory at {:#x}", address));
+        };
+        let start = start as usize;
+        memory.get_mut(start..start + size as usize).ok_or_else(|| format!("access outside memory at {:#x}", address))
+    }
+
+    fn word(&mut self, address: u32) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.bytes(address, 4)?.try_into().unwrap()))
+    }
+
+    fn set_word(&mut self, address: u32, value: u32) -> Result<(), String> {
+        self.bytes(address, 4)?.copy_from_slice(&value.to_le_bytes());
+        Ok(())
+    }
+
+    fn byte(&mut self, address: u32) -> Result<u8, String> {
+        Ok(self.bytes(address, 1)?[0])
+    }
+}
+
+struct Machine<'a> {
+    registers: [u32; 32],
+    /// The floating-point registers, a real being in an even one and the
+    /// next, its low word first
+    floats: [u32; 32],
+    condition: bool,
+    hi: u32,
+    lo: u32,
+    memory: Memory,
+    input: &'a [u8],
+    output: Vec<u8>,
+    error: Vec<u8>
+}
+
+/// What running an instruction does to the flow of the program
+enum Flow {
+    Next,
+    Jump(u32),
+    Exit(i32)
+}
+
+impl<'a> Machine<'a> {
+    /// Runs a program from `main`, the first instruction, giving the status
+    /// it exits with
+    fn run(&mut self, code: &[Instruction]) -> i32 {
+        self.registers[29] = STACK_POINTER;
+        self.registers[28] = DATA + 0x8000;
+        let mut pc = 0;
+        for _ in 0..BUDGET {
+            let instruction = code.get(pc).unwrap_or_else(|| panic!("running past the code at {:#x}", TEXT + 4 * pc as u32));
+            let flow = self.step(instruction, TEXT + 4 * (pc as u32 + 1))
+                .unwrap_or_else(|message| panic!("line {} of the assembly: {}", instruction.line, message));
+            self.registers[0] = 0;
+            match flow {
+                Flow::Next => pc += 1,
+                Flow::Jump(address) => {
+                    if address < TEXT || address % 4 != 0 {
+                        panic!("line {} of the assembly: jump to {:#x}", instruction.line, address);
+                    }
+                    pc = ((address - TEXT) / 4) as usize;
+                },
+                Flow::Exit(status) => return status
+            }
+        }
+        panic!("the program runs more than {} instructions", BUDGET)
+    }
+
+    fn double(&self, register: usize) -> f64 {
+        f64::from_bits(self.floats[register] as u64 | (self.floats[register + 1] as u64) << 32)
+    }
+
+    fn set_double(&mut self, register: usize, value: f64) {
+        let bits = value.to_bits();
+        self.floats[register] = bits as u32;
+        self.floats[register + 1] = (bits >> 32) as u32;
+    }
+
+    /// Sets a floating-point register to a real converted to a word, which
+    /// is 2^31 - 1 if it is out of range as MIPS has it
+    fn set_converted(&mut self, register: usize, value: f64) {
+        self.floats[register] = if !(-2147483648.0..2147483648.0).contains(&value) {
+            i32::MAX as u32
+        } else {
+            value as i32 as u32
+        };
+    }
+
+    fn step(&mut self, instruction: &Instruction, next: u32) -> Result<Flow, String> {
+        use Op::*;
+        let [a, b, c] = instruction.registers;
+        let immediate = instruction.immediate;
+        let r = self.registers;
+        let branch = |taken: bool| if taken { Flow::Jump(immediate) } else { Flow::Next };
+        match instruction.op {
+            Addu => self.registers[a] = r[b].wrapping_add(r[c]),
+            Subu => self.registers[a] = r[b].wrapping_sub(r[c]),
+            And => self.registers[a] = r[b] & r[c],
+            Or => self.registers[a] = r[b] | r[c],
+            Xor => self.registers[a] = r[b] ^ r[c],
+            Nor => self.registers[a] = !(r[b] | r[c]),
+            Slt => self.registers[a] = ((r[b] as i32) < (r[c] as i32)) as u32,
+            Sltu => self.registers[a] = (r[b] < r[c]) as u32,
+            Sllv => self.registers[a] = r[b] << (r[c] & 31),
+            Srlv => self.registers[a] = r[b] >> (r[c] & 31),
+            Srav => self.registers[a] = ((r[b] as i32) >> (r[c] & 31)) as u32,
+            Mul => self.registers[a] = r[b].wrapping_mul(r[c]),
+            Addiu => self.registers[a] = r[b].wrapping_add(immediate),
+            Andi => self.registers[a] = r[b] & immediate,
+            Ori => self.registers[a] = r[b] | immediate,
+            Xori => self.registers[a] = r[b] ^ immediate,
+            Slti => self.registers[a] = ((r[b] as i32) < (immediate as i32)) as u32,
+            Sltiu => self.registers[a] = (r[b] < immediate) as u32,
+            Sll => self.registers[a] = r[b] << (immediate & 31),
+            Srl => self.registers[a] = r[b] >> (immediate & 31),
+            Sra => self.registers[a] = ((r[b] as i32) >> (immediate & 31)) as u32,
+            Lui => self.registers[a] = immediate << 16,
+            Li => self.registers[a] = immediate,
+            Move => self.registers[a] = r[b],
+            Clz => self.registers[a] = r[b].leading_zeros(),
+            Lw => self.registers[a] = self.memory.word(r[b].wrapping_add(immediate))?,
+            Sw => self.memory.set_word(r[b].wrapping_add(immediate), r[a])?,
+            Lbu => self.registers[a] = self.memory.byte(r[b].wrapping_add(immediate))? as u32,
+            Lb => self.registers[a] = self.memory.byte(r[b].wrapping_add(immediate))? as i8 as u32,
+            Sb => self.memory.bytes(r[b].wrapping_add(immediate), 1)?[0] = r[a] as u8,
+            Ldc1 => {
+                let address = r[b].wrapping_add(immediate);
+                let bits = u64::from_le_bytes(self.memory.bytes(address, 8)?.try_into().unwrap());
+                self.set_double(a, f64::from_bits(bits));
+            },
+            Sdc1 => {
+                let bits = self.double(a).to_bits();
+                self.memory.bytes(r[b].wrapping_add(immediate), 8)?.copy_from_slice(&bits.to_le_bytes());
+            },
+            Mult => {
+                let product = r[a] as i32 as i64 * r[b] as i32 as i64;
+                self.lo = product as u32;
+                self.hi = (product >> 32) as u32;
+            },
+            Multu => {
+                let product = r[a] as u64 * r[b] as u64;
+                self.lo = product as u32;
+                self.hi = (product >> 32) as u32;
+            },
+            Div | Divu if r[b] == 0 => return Err("division by zero".to_string()),
+            Div => {
+                self.lo = (r[a] as i32).wrapping_div(r[b] as i32) as u32;
+                self.hi = (r[a] as i32).wrapping_rem(r[b] as i32) as u32;
+            },
+            Divu => {
+                self.lo = r[a] / r[b];
+                self.hi = r[a] % r[b];
+            },
+            Mflo => self.registers[a] = self.lo,
+            Mfhi => self.registers[a] = self.hi,
+            B | J => return Ok(Flow::Jump(immediate)),
+            Jal => {
+                self.registers[31] = next;
+                return Ok(Flow::Jump(immediate));
+            },
+            Jr => return Ok(Flow::Jump(r[a])),
+            Jalr => {
+                self.registers[31] = next;
+                return Ok(Flow::Jump(r[a]));
+            },
+            Beq => return Ok(branch(r[a] == r[b])),
+            Bne => return Ok(branch(r[a] != r[b])),
+            Beqz => return Ok(branch(r[a] == 0)),
+            Bnez => return Ok(branch(r[a] != 0)),
+            Bgez => return Ok(branch(r[a] as i32 >= 0)),
+            Bgtz => return Ok(branch(r[a] as i32 > 0)),
+            Blez => return Ok(branch(r[a] as i32 <= 0)),
+            Bltz => return Ok(branch((r[a] as i32) < 0)),
+            AddD => self.set_double(a, self.double(b) + self.double(c)),
+            SubD => self.set_double(a, self.double(b) - self.double(c)),
+            MulD => self.set_double(a, self.double(b) * self.double(c)),
+            DivD => self.set_double(a, self.double(b) / self.double(c)),
+            AbsD => self.set_double(a, self.double(b).abs()),
+            NegD => self.set_double(a, -self.double(b)),
+            MovD => self.set_double(a, self.double(b)),
+            SqrtD => self.set_double(a, self.double(b).sqrt()),
+            CEqD => self.condition = self.double(a) == self.double(b),
+            CLtD => self.condition = self.double(a) < self.double(b),
+            CLeD => self.condition = self.double(a) <= self.double(b),
+            Bc1t => return Ok(branch(self.condition)),
+            Bc1f => return Ok(branch(!self.condition)),
+            Mtc1 => self.floats[b] = r[a],
+            Mfc1 => self.registers[a] = self.floats[b],
+            CvtDW => self.set_double(a, self.floats[b] as i32 as f64),
+            RoundWD => self.set_converted(a, self.double(b).round_ties_even()),
+            CeilWD => self.set_converted(a, self.double(b).ceil()),
+            FloorWD => self.set_converted(a, self.double(b).floor()),
+            TruncWD => self.set_converted(a, self.double(b).trunc()),
+            Syscall => return self.syscall()
+        }
+        Ok(Flow::Next)
+    }
+
+    /// The next byte of the input, if any
+    fn getc(&mut self) -> Option<u8> {
+        let (first, rest) = self.input.split_first()?;
+        self.input = rest;
+        Some(*first)
+    }
+
+    /// The syscalls of SPIM that print and read, and those for the heap,
+    /// files 0 to 2 and exiting
+    fn syscall(&mut self) -> Result<Flow, String> {
+        let [a0, a1, a2] = [self.registers[4], self.registers[5], self.registers[6]];
+        match self.registers[2] {
+            1 => self.output.extend((a0 as i32).to_string().bytes()),
+            3 => self.output.extend(self.double(12).to_string().bytes()),
+            4 => {
+                let mut address = a0;
+                loop {
+                    let byte = self.memory.byte(address)?;
+                    if byte == 0 {
+                        break;
+                    }
+                    self.output.push(byte);
+                    address += 1;
+                }
+            },
+            5 => {
+                // a line holding an integer
+                let mut line = vec![];
+                while let Some(byte) = self.getc() {
+                    if byte == b'\n' {
+                        break;
+                    }
+                    line.push(byte);
+                }
+                self.registers[2] = String::from_utf8_lossy(&line).trim().parse::<i32>().unwrap_or(0) as u32;
+            },
+            8 => {
+                // at most $a1 - 1 bytes of a line, as fgets reads them
+                let mut address = a0;
+                for _ in 1..a1.max(1) {
+                    match self.getc() {
+                        Some(byte) => {
+                            self.memory.bytes(address, 1)?[0] = byte;
+                            address += 1;
+                            if byte == b'\n' {
+                                break;
+                            }
+                        },
+                        None => break
+                    }
+                }
+                self.memory.bytes(address, 1)?[0] = 0;
+            },
+            9 => {
+                // the break is kept to words, as in SPIM
+                let data = &mut self.memory.data;
+                data.resize(data.len().div_ceil(4) * 4, 0);
+                self.registers[2] = DATA + data.len() as u32;
+                let size = self.memory.data.len() + a0 as usize;
+                if DATA as usize + size > STACK as usize {
+                    return Err("the heap is full".to_string());
+                }
+                self.memory.data.resize(size, 0);
+            },
+            10 => return Ok(Flow::Exit(0)),
+            11 => self.output.push(a0 as u8),
+            12 => self.registers[2] = self.getc().unwrap_or(0) as u32,
+            14 if a0 == 0 => {
+                let mut count = 0;
+                while count < a2 {
+                    match self.getc() {
+                        Some(byte) => self.memory.bytes(a1 + count, 1)?[0] = byte,
+                        None => break
+                    }
+                    count += 1;
+                }
+                self.registers[2] = count;
+            },
+            15 if a0 == 1 || a0 == 2 => {
+                let mut bytes = vec![];
+                for offset in 0..a2 {
+                    bytes.push(self.memory.byte(a1 + offset)?);
+                }
+                if a0 == 1 { &mut self.output } else { &mut self.error }.extend(bytes);
+                self.registers[2] = a2;
+            },
+            17 => return Ok(Flow::Exit(a0 as i32)),
+            call => return Err(format!("unknown syscall {} with $a0 = {}", call, a0))
+        }
+        Ok(Flow::Next)
+    }
+}
@@ -0,0 +1,373 @@
+pub mod c;
+pub mod llvm;
+pub mod asm;
+pub mod wat;
+pub mod mips;
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use crate::ast::expression::Expression;
+use crate::ast::expression::variables::VarRef;
+use crate::ast::program::{Program, ScopeId, Symbol};
+use crate::ast::subprogram::Subprogram;
+use crate::ast::types::Type;
+use crate::ir::{Arg, Op, Quad};
+use crate::ir::lower::function_name;
+
+/// The size of a set, one bit for each of the ordinals 0 to 255
+pub const SET: i32 = 32;
+
+/// The size in bytes of values of type `ty`
+pub fn size(ty: &Type) -> i32 {
+    match ty {
+        Type::SetOf(_) => SET,
+        Type::ArrayOf(..) => {
+            let (dim, element) = ty.dimension().expect("arrays have a dimension");
+            cardinality(&dim) as i32 * size(&element)
+        },
+        Type::Record(_) => ty.fields().into_iter().map(|(_, ty)| size(ty)).sum(),
+        _ => 8
+    }
+}
+
+/// Every subprogram of a program, in the order of the source
+pub fn subprograms(program: &Program) -> Vec<&Subprogram> {
+    let mut subprograms = program.scopes.iter()
+        .flat_map(|scope| scope.subprograms.values())
+        .collect::<Vec<&Subprogram>>();
+    subprograms.sort_by_key(|subprogram| subprogram.range.start);
+    subprograms
+}
+
+/// The subprogram whose body is the scope `scope`, none for the main
+/// program
+pub fn subprogram_of(program: &Program, scope: ScopeId) -> Option<&Subprogram> {
+    program.scopes.iter()
+        .flat_map(|outer| outer.subprograms.values())
+        .find(|subprogram| subprogram.scope == scope)
+}
+
+/// The subprogram a quadruple calls by the name of its procedure, none for
+/// a routine of the runtime
+pub fn callee<'a>(program: &'a Program, name: &str) -> Option<&'a Subprogram> {
+    program.scopes.iter()
+        .flat_map(|scope| scope.subprograms.values())
+        .find(|subprogram| function_name(subprogram) == name)
+}
+
+/// How deep every scope is nested, the main program being at level 0
+pub fn levels(program: &Program) -> Vec<usize> {
+    let mut levels = vec![0; program.scopes.len()];
+    // scopes are created after the scope they are in
+    for scope in Program::GLOBAL + 1..program.scopes.len() {
+        let parent = program.scopes[scope].parent.expect("subprogram scopes have a parent");
+        levels[scope] = levels[parent] + 1;
+    }
+    levels
+}
+
+/// Whether values of the type are handled through their address, sets of
+/// 256 bits among them
+pub fn is_block(ty: &Type) -> bool {
+    matches!(ty, Type::ArrayOf(..) | Type::Record(_) | Type::SetOf(_))
+}
+
+/// The `var` parameters of every subprogram, as the scope they are
+/// declared in and their name, which frames hold the address of
+pub fn references(program: &Program) -> HashSet<(ScopeId, String)> {
+    subprograms(program).into_iter()
+        .flat_map(|subprogram| subprogram.params.iter()
+            .filter(|param| param.by_ref)
+            .map(move |param| (subprogram.scope, param.name.clone())))
+        .collect()
+}
+
+/// Where the variables of a scope are in its frame. A frame of a
+/// subprogram starts with the static link, the address of the frame of the
+/// scope the subprogram is declared in; a parameter passed by reference
+/// takes the 8 bytes of an address, and the result of a function comes last.
+pub struct Layout {
+    pub fields: HashMap<String, i32>,
+    pub result: i32,
+    pub size: i32
+}
+
+impl Layout {
+    /// The frame of every scope of a program
+    pub fn all(program: &Program) -> Vec<Layout> {
+        let references = references(program);
+        (0..program.scopes.len())
+            .map(|scope| Layout::new(program, scope, &references))
+            .collect()
+    }
+
+    fn new(program: &Program, scope: ScopeId, references: &HashSet<(ScopeId, String)>) -> Layout {
+        let mut variables = program.scopes[scope].variables.iter().collect::<Vec<_>>();
+        variables.sort_by_key(|(name, _)| *name);
+        let mut offset = if scope == Program::GLOBAL { 0 } else { 8 };
+        let mut fields = HashMap::new();
+        for (name, ty) in variables {
+            fields.insert(name.clone(), offset);
+            offset += if references.contains(&(scope, name.clone())) { 8 } else { size(ty) };
+        }
+        let result = offset;
+        if let Some(ty) = subprogram_of(program, scope).and_then(|subprogram| subprogram.result.as_ref()) {
+            offset += size(ty);
+        }
+        Layout { fields, result, size: offset }
+    }
+}
+
+/// How a routine of the runtime the quadruples call is declared in the C
+/// runtime and the WebAssembly one: the kind of each parameter, `i` an
+/// integer, `f` a real and `p` an address, and of the result
+pub struct Routine {
+    pub function: &'static str,
+    pub params: &'static str,
+    pub result: Option<char>,
+    /// Whether it takes the place it reports runtime errors at, last
+    pub checked: bool
+}
+
+pub fn routine(name: &str) -> Routine {
+    let (function, params, result, checked) = match name {
+        "write_integer" => ("pas_write_integer", "i", None, false),
+        "write_real" => ("pas_write_real", "f", None, false),
+        "write_char" => ("pas_write_char", "i", None, false),
+        "write_boolean" => ("pas_write_boolean", "i", None, false),
+        "write_string" => ("pas_write_string", "pi", None, false),
+        "write_integer_width" => ("pas_write_integer_width", "ii", None, true),
+        "write_real_width" => ("pas_write_real_width", "fi", None, true),
+        "write_real_fixed" => ("pas_write_real_fixed", "fii", None, true),
+        "write_char_width" => ("pas_write_char_width", "ii", None, true),
+        "write_boolean_width" => ("pas_write_boolean_width", "ii", None, true),
+        "write_string_width" => ("pas_write_string_width", "pii", None, true),
+        "writeln" => ("pas_writeln", "", None, false),
+        "read_integer" => ("pas_read_integer", "", Some('i'), true),
+        "read_real" => ("pas_read_real", "", Some('f'), true),
+        "read_char" => ("pas_read_char", "", Some('i'), true),
+        "readln" => ("pas_readln", "", None, true),
+        "eof" => ("pas_eof", "", Some('i'), false),
+        "eoln" => ("pas_eoln", "", Some('i'), false),
+        "new" => ("pas_new", "i", Some('p'), false),
+        "dispose" => ("pas_dispose", "p", None, true),
+        "zero" => ("pas_zero", "pi", None, false),
+        "copy" => ("pas_copy", "ppi", None, false),
+        "set_add" => ("pas_set_add", "pi", None, true),
+        "set_has" => ("pas_set_has", "pi", Some('i'), false),
+        "set_combine" => ("pas_set_combine", "pppi", None, false),
+        "set_eq" => ("pas_set_eq", "pp", Some('i'), false),
+        "set_le" => ("pas_set_le", "pp", Some('i'), false),
+        "compare" => ("pas_compare", "ppi", Some('i'), false),
+        "add_checked" => ("pas_add_checked", "ii", Some('i'), true),
+        "sub_checked" => ("pas_sub_checked", "ii", Some('i'), true),
+        "mul_checked" => ("pas_mul_checked", "ii", Some('i'), true),
+        "div_checked" => ("pas_div_checked", "ii", Some('i'), true),
+        "neg_checked" => ("pas_neg_checked", "i", Some('i'), true),
+        "abs_checked" => ("pas_abs_checked", "i", Some('i'), true),
+        name => unreachable!("the runtime has no routine '{}'", name)
+    };
+    Routine { function, params, result, checked }
+}
+
+/// The type of what an assignment stores to, which for the result of a
+/// function with parameters is not the type of the name as a value
+pub fn target_type(program: &Program, target: &VarRef) -> Type {
+    match target {
+        VarRef::Immediate(name, scope) => match program.lookup(*scope, name) {
+            Some((_, Symbol::Subprogram(subprogram))) => subprogram.result.clone().unwrap_or(Type::Invalid),
+            _ => target.get_type(program)
+        },
+        _ => target.get_type(program)
+    }
+}
+
+/// The line of the source the start of `range` is on, counting from 1
+pub fn source_line(program: &Program, range: &Range<usize>) -> usize {
+    let file = &program.positioner.file;
+    file[..range.start.min(file.len())].matches('\n').count() + 1
+}
+
+/// The places of the source a compiled program can stop at with a runtime
+/// error, numbered in the order they are first met. The runtime is given
+/// the number of the place and prints its text after the message, the
+/// trace the interpreter prints.
+#[derive(Default)]
+pub struct Sites {
+    ranges: Vec<Range<usize>>,
+    numbers: HashMap<Range<usize>, usize>
+}
+
+impl Sites {
+    /// The number of the place of a quadruple that can fail: one checking
+    /// or dereferencing a value, dividing or calling a checked routine of
+    /// the runtime. Any other is not numbered but given 0, which it never
+    /// passes on.
+    pub fn of(&mut self, program: &Program, quad: &Quad) -> usize {
+        let fails = match quad.op {
+            Op::Div | Op::Mod | Op::FDiv | Op::Sqrt | Op::Ln | Op::Check | Op::Bounds | Op::NoCase | Op::Deref => true,
+            Op::Call => match &quad.arg1 {
+                Arg::Name(name) => callee(program, name).is_none() && routine(name).checked,
+                _ => false
+            },
+            _ => false
+        };
+        if fails { self.number(&quad.range) } else { 0 }
+    }
+
+    pub fn number(&mut self, range: &Range<usize>) -> usize {
+        if let Some(number) = self.numbers.get(range) {
+            return *number;
+        }
+        self.ranges.push(range.clone());
+        self.numbers.insert(range.clone(), self.ranges.len() - 1);
+        self.ranges.len() - 1
+    }
+
+    /// The texts of the places, by number
+    pub fn texts(&self, program: &Program) -> Vec<String> {
+        self.ranges.iter().map(|range| site(program, range)).collect()
+    }
+}
+
+/// What a runtime error at `range` is printed with after its message: the
+/// trace of `Printable` without colors, and the newline of `eprintln!`
+pub fn site(program: &Program, range: &Range<usize>) -> String {
+    format!(" at {}\n", program.positioner.pos(range.clone()).plain_trace())
+}
+
+/// The lowest and highest index of an array dimension
+pub fn limits(dim: &Type) -> (i64, i64) {
+    dim.bounds().map_or((0, -1), |bounds| (bounds.start as i64, bounds.end as i64))
+}
+
+/// The number of values of an ordinal type with bounds
+pub fn cardinality(ty: &Type) -> usize {
+    ty.bounds()
+        .map(|bounds| (bounds.end - bounds.start + 1).max(0) as usize)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::path::{Path, PathBuf};
+    use std::process::{Command, Stdio};
+    use std::io::Write;
+    use crate::ast::program::Program;
+    use crate::error::Throwable;
+    use crate::error::runtime_error::RuntimeError;
+    use crate::interpreter::Interpreter;
+    use super::site;
+
+    pub fn check(src: &str) -> Program {
+        let (program, errors) = Program::new(src.to_string());
+        assert!(errors.is_empty(), "{:?}", errors);
+        let diagnostics = program.validate();
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        program
+    }
+
+    /// The programs in test/, with what each reads from its `.in` file
+    pub fn samples() -> Vec<(String, Program, String)> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("test");
+        let mut samples = std::fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().is_some_and(|extension| extension == "p"))
+            .map(|path| {
+                let name = path.file_stem().unwrap().to_string_lossy().to_string();
+                let program = check(&std::fs::read_to_string(&path).unwrap());
+                let input = std::fs::read_to_string(path.with_extension("in")).unwrap_or_default();
+                (name, program, input)
+            })
+            .collect::<Vec<_>>();
+        samples.sort_by(|left, right| left.0.cmp(&right.0));
+        samples
+    }
+
+    /// What the interpreter writes, and the runtime error it stops with as
+    /// `report` gives it
+    pub fn interpret(program: &Program, input: &str) -> (String, Option<String>) {
+        std::thread::scope(|scope| std::thread::Builder::new()
+            .stack_size(crate::interpreter::limits::STACK)
+            .spawn_scoped(scope, || {
+                let mut output = vec![];
+                let outcome = Interpreter::new(program, input.as_bytes(), &mut output).run();
+                (String::from_utf8(output).unwrap(), outcome.err().map(|err| report(program, &err)))
+            })
+            .unwrap()
+            .join()
+            .unwrap())
+    }
+
+    /// A runtime error as a compiled program prints it
+    pub fn report(program: &Program, err: &RuntimeError) -> String {
+        format!("{} {}{}", err.title(), err.description(), site(program, &err.range))
+    }
+
+    /// A fresh directory for the files of a test
+    pub fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("mini-pascal-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Stops a test that needs a tool which is not installed, so that it
+    /// fails rather than passes without running
+    pub fn require(tool: &str) {
+        let found = Command::new(tool).arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok();
+        assert!(found, "{} is not installed, which the test runs", tool);
+    }
+
+    /// Runs a command on `input`, returning its standard output and, if it
+    /// failed, its standard error
+    pub fn execute(command: &mut Command, input: &str) -> (String, Option<String>) {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+        let output = child.wait_with_output().unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        (String::from_utf8_lossy(&output.stdout).to_string(), if output.status.success() { None } else { Some(stderr) })
+    }
+
+    /// The programs that stop with a runtime error, and the error
+    pub const FAULTS: &[&str] = &[
+        "i := 1 div (i - i)",
+        "a[i + 4] := 0",
+        "p^ := 1",
+        "begin new(p); dispose(p); p^ := 1 end",
+        "begin p := nil; i := 1 + p^ end",
+        "case i of 1: end",
+        "read(i)",
+        "i := ord(pred(false))",
+        "writeln(sqrt(-2.5))",
+        "begin i := 4; s := i end",
+        "i := ord(chr(i - 1))",
+        "{$Q+} begin i := 3037000500; i := i * i end",
+        "{$Q+} begin i := 9223372036854775807; i := i + 1 end",
+        "{$Q+} begin i := -9223372036854775807; i := i - 2 end",
+        "{$Q+} begin i := -9223372036854775807 - 1; i := -i end",
+        "{$Q+} begin i := -9223372036854775807 - 1; i := abs(i) end",
+        "{$Q+} begin i := -9223372036854775807 - 1; i := i div (i - i - 1) end",
+        "{$Q+} begin i := -3037000500; i := i * 3037000500 end",
+        "writeln(i:i)",
+        "begin i := -2; writeln(2.5:3:i) end",
+    ];
+
+    pub fn fault(body: &str) -> Program {
+        check(&format!("
+            program faults;
+            var i: integer; a: array [1..3] of integer; p: ^integer; s: 1..3;
+            begin
+                {}
+            end.
+        ", body))
+    }
+}
@@ -0,0 +1,574 @@
+/*
+ * The runtime of compiled Pascal programs: input and output, sets, heap
+ * variables and the checks that stop a program with a runtime error. The
+ * messages are the ones the interpreter gives.
+ */
+#include <errno.h>
+#include <inttypes.h>
+#include <math.h>
+#include <stdarg.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#ifndef PAS_API
+#define PAS_API static
+#endif
+
+/* The sets of ordinals from 0 to 255 */
+typedef struct {
+    uint64_t bits[4];
+} pas_set;
+
+/* The field a real is written in when write gives it no width */
+#define PAS_REAL_WIDTH 22
+
+/* What a runtime error at each of the places of the source a check is made
+   at is printed with after its message, given by the program; routines
+   that can fail take the number of the place */
+extern const char *const pas_sites[];
+
+PAS_API void pas_fail(int site, const char *format, ...) {
+    va_list args;
+    fflush(stdout);
+    fputs("Runtime Error ", stderr);
+    va_start(args, format);
+    vfprintf(stderr, format, args);
+    va_end(args);
+    fputs(pas_sites[site], stderr);
+    exit(1);
+}
+
+/* Formats a real as the shortest decimal that reads back as the same real,
+   without an exponent */
+PAS_API void pas_format_real(char *out, double value) {
+    char buffer[32], digits[32];
+    int precision, exponent, count = 0, point, i;
+    char *c;
+    if (isnan(value)) {
+        strcpy(out, "NaN");
+        return;
+    }
+    if (signbit(value)) {
+        *out++ = '-';
+        value = -value;
+    }
+    if (isinf(value)) {
+        strcpy(out, "inf");
+        return;
+    }
+    if (value == 0) {
+        strcpy(out, "0");
+        return;
+    }
+    for (precision = 1; precision < 17; precision++) {
+        snprintf(buffer, sizeof buffer, "%.*e", precision - 1, value);
+        if (strtod(buffer, NULL) == value) {
+            break;
+        }
+    }
+    snprintf(buffer, sizeof buffer, "%.*e", precision - 1, value);
+    for (c = buffer; *c != 'e'; c++) {
+        if (*c != '.') {
+            digits[count++] = *c;
+        }
+    }
+    while (count > 1 && digits[count - 1] == '0') {
+        count--;
+    }
+    exponent = atoi(c + 1);
+    point = exponent + 1;
+    if (point <= 0) {
+        *out++ = '0';
+        *out++ = '.';
+        for (i = 0; i < -point; i++) {
+            *out++ = '0';
+        }
+        for (i = 0; i < count; i++) {
+            *out++ = digits[i];
+        }
+    } else {
+        for (i = 0; i < count || i < point; i++) {
+            if (i == point) {
+                *out++ = '.';
+            }
+            *out++ = i < count ? digits[i] : '0';
+        }
+    }
+    *out = '\0';
+}
+
+/* Input is read a character ahead, a last line without a newline ending
+   like the others */
+static int pas_ahead = -2;
+static int pas_in_line = 0;
+
+PAS_API int pas_peek(void) {
+    if (pas_ahead == -2) {
+        int c = getchar();
+        if (c == '\r') {
+            c = getchar();
+            if (c != '\n') {
+                ungetc(c, stdin);
+                c = '\r';
+            }
+        }
+        if (c == EOF && pas_in_line) {
+            c = '\n';
+        }
+        pas_ahead = c;
+    }
+    return pas_ahead;
+}
+
+static int pas_next(void) {
+    int c = pas_peek();
+    pas_ahead = -2;
+    if (c != EOF) {
+        pas_in_line = c != '\n';
+    }
+    return c;
+}
+
+PAS_API int64_t pas_eof(void) {
+    return pas_peek() == EOF;
+}
+
+PAS_API int64_t pas_eoln(void) {
+    return pas_peek() == '\n' || pas_peek() == EOF;
+}
+
+PAS_API int64_t pas_read_char(int site) {
+    int c = pas_next();
+    if (c == EOF) {
+        pas_fail(site, "Read past the end of the input");
+    }
+    return c == '\n' ? ' ' : c;
+}
+
+/* Reads the characters of a number, or of the word that stands where one
+   was expected */
+static void pas_read_number(char *out, size_t size, int real, int site) {
+    size_t length = 0;
+    int c;
+#define PAS_TAKE(accepts) \
+    while ((c = pas_peek()) != EOF && (accepts) && length + 1 < size) { \
+        out[length++] = (char) pas_next(); \
+    }
+    while ((c = pas_peek()) != EOF && (c == ' ' || c == '\t' || c == '\n' || c == '\r' || c == '\f' || c == '\v')) {
+        pas_next();
+    }
+    if (pas_peek() == EOF) {
+        pas_fail(site, "Read past the end of the input");
+    }
+    PAS_TAKE(c == '+' || c == '-')
+    PAS_TAKE(c >= '0' && c <= '9')
+    if (real) {
+        if (pas_peek() == '.') {
+            out[length++] = (char) pas_next();
+            PAS_TAKE(c >= '0' && c <= '9')
+        }
+        if (pas_peek() == 'e' || pas_peek() == 'E') {
+            pas_next();
+            out[length++] = 'e';
+            PAS_TAKE(c == '+' || c == '-')
+            PAS_TAKE(c >= '0' && c <= '9')
+        }
+    }
+    if (length == 0) {
+        PAS_TAKE(c != ' ' && c != '\t' && c != '\n' && c != '\r')
+    }
+#undef PAS_TAKE
+    out[length] = '\0';
+}
+
+PAS_API int64_t pas_read_integer(int site) {
+    char number[64], *end;
+    long long value;
+    pas_read_number(number, sizeof number, 0, site);
+    errno = 0;
+    value = strtoll(number, &end, 10);
+    if (*number == '\0' || *end != '\0' || errno == ERANGE) {
+        pas_fail(site, "Expected Integer in the input, got '%s'", number);
+    }
+    return value;
+}
+
+PAS_API double pas_read_real(int site) {
+    char number[64], *end;
+    double value;
+    pas_read_number(number, sizeof number, 1, site);
+    value = strtod(number, &end);
+    if (*number == '\0' || *end != '\0') {
+        pas_fail(site, "Expected Real in the input, got '%s'", number);
+    }
+    return value;
+}
+
+PAS_API void pas_readln(int site) {
+    int c;
+    do {
+        c = pas_next();
+        if (c == EOF) {
+            pas_fail(site, "Read past the end of the input");
+        }
+    } while (c != '\n');
+}
+
+PAS_API void pas_write_integer(int64_t value) {
+    printf("%" PRId64, value);
+}
+
+PAS_API void pas_write_char(int64_t value) {
+    putchar((int) value);
+}
+
+PAS_API void pas_write_boolean(int64_t value) {
+    fputs(value ? "true" : "false", stdout);
+}
+
+PAS_API void pas_write_string(const int64_t *chars, int64_t length) {
+    int64_t i;
+    for (i = 0; i < length; i++) {
+        putchar((int) chars[i]);
+    }
+}
+
+/* Checks a field width or number of decimals given to write */
+PAS_API int64_t pas_field(int64_t value, int site) {
+    if (value < 1) {
+        pas_fail(site, "Field widths and decimals must be at least 1, got %" PRId64, value);
+    }
+    return value;
+}
+
+/* Writes the spaces that right-align `length` characters in a field of
+   `width` characters */
+PAS_API void pas_pad(int64_t width, int64_t length) {
+    for (; width > length; width--) {
+        putchar(' ');
+    }
+}
+
+/* Writes `length` characters in exactly `width`, cutting them if need be,
+   as strings and booleans are written */
+PAS_API void pas_fit(const char *text, int64_t length, int64_t width) {
+    pas_pad(width, length);
+    fwrite(text, 1, (size_t) (width < length ? width : length), stdout);
+}
+
+PAS_API void pas_write_integer_width(int64_t value, int64_t width, int site) {
+    char out[24];
+    snprintf(out, sizeof out, "%" PRId64, value);
+    pas_pad(pas_field(width, site), (int64_t) strlen(out));
+    fputs(out, stdout);
+}
+
+/* A real in floating-point form: a sign, one digit before the point and
+   as many after it as fill the field, then an exponent of 2 digits or more */
+PAS_API void pas_write_real_width(double value, int64_t width, int site) {
+    char out[400];
+    width = pas_field(width, site);
+    if (!isfinite(value)) {
+        pas_format_real(out, value);
+        pas_pad(width, (int64_t) strlen(out));
+        fputs(out, stdout);
+        return;
+    }
+    if (width < 8) {
+        width = 8;
+    }
+    putchar(value < 0 ? '-' : ' ');
+    printf("%.*E", (int) (width - 7), fabs(value));
+}
+
+/* A real written without a field width, in floating-point form in the
+   default field */
+PAS_API void pas_write_real(double value) {
+    pas_write_real_width(value, PAS_REAL_WIDTH, 0);
+}
+
+/* A real in fixed-point form, which -0.0 is written in without a sign */
+PAS_API void pas_write_real_fixed(double value, int64_t width, int64_t decimals, int site) {
+    char out[400];
+    width = pas_field(width, site);
+    decimals = pas_field(decimals, site);
+    if (!isfinite(value)) {
+        pas_format_real(out, value);
+        pas_pad(width, (int64_t) strlen(out));
+        fputs(out, stdout);
+        return;
+    }
+    value += 0.0;
+    pas_pad(width, snprintf(NULL, 0, "%.*f", (int) decimals, value));
+    printf("%.*f", (int) decimals, value);
+}
+
+PAS_API void pas_write_char_width(int64_t value, int64_t width, int site) {
+    pas_pad(pas_field(width, site), 1);
+    putchar((int) value);
+}
+
+PAS_API void pas_write_boolean_width(int64_t value, int64_t width, int site) {
+    pas_fit(value ? "true" : "false", value ? 4 : 5, pas_field(width, site));
+}
+
+PAS_API void pas_write_string_width(const int64_t *chars, int64_t length, int64_t width, int site) {
+    int64_t i;
+    width = pas_field(width, site);
+    pas_pad(width, length);
+    for (i = 0; i < length && i < width; i++) {
+        putchar((int) chars[i]);
+    }
+}
+
+PAS_API void pas_writeln(void) {
+    putchar('\n');
+}
+
+PAS_API int pas_exit(void) {
+    if (fflush(stdout) != 0) {
+        fputs("Runtime Error Could not write the output\n", stderr);
+        return 1;
+    }
+    return 0;
+}
+
+/* Arithmetic */
+
+PAS_API int64_t pas_div(int64_t left, int64_t right, int site) {
+    if (right == 0) {
+        pas_fail(site, "Division by zero");
+    }
+    return right == -1 ? (int64_t) (0 - (uint64_t) left) : left / right;
+}
+
+PAS_API int64_t pas_mod(int64_t left, int64_t right, int site) {
+    int64_t rest;
+    if (right == 0) {
+        pas_fail(site, "Division by zero");
+    }
+    if (right == -1) {
+        return 0;
+    }
+    rest = left % right;
+    return rest < 0 ? (right < 0 ? rest - right : rest + right) : rest;
+}
+
+PAS_API double pas_rdiv(double left, double right, int site) {
+    if (right == 0) {
+        pas_fail(site, "Division by zero");
+    }
+    return left / right;
+}
+
+PAS_API int64_t pas_abs(int64_t value) {
+    return value < 0 ? (int64_t) (0 - (uint64_t) value) : value;
+}
+
+/* The integer operations of {$Q+}, which stop the program where the others
+   wrap around */
+PAS_API int64_t pas_add_checked(int64_t left, int64_t right, int site) {
+    int64_t result;
+    if (__builtin_add_overflow(left, right, &result)) {
+        pas_fail(site, "Integer overflow");
+    }
+    return result;
+}
+
+PAS_API int64_t pas_sub_checked(int64_t left, int64_t right, int site) {
+    int64_t result;
+    if (__builtin_sub_overflow(left, right, &result)) {
+        pas_fail(site, "Integer overflow");
+    }
+    return result;
+}
+
+PAS_API int64_t pas_mul_checked(int64_t left, int64_t right, int site) {
+    int64_t result;
+    if (__builtin_mul_overflow(left, right, &result)) {
+        pas_fail(site, "Integer overflow");
+    }
+    return result;
+}
+
+PAS_API int64_t pas_div_checked(int64_t left, int64_t right, int site) {
+    if (left == INT64_MIN && right == -1) {
+        pas_fail(site, "Integer overflow");
+    }
+    return pas_div(left, right, site);
+}
+
+PAS_API int64_t pas_neg_checked(int64_t value, int site) {
+    return pas_sub_checked(0, value, site);
+}
+
+PAS_API int64_t pas_abs_checked(int64_t value, int site) {
+    return value < 0 ? pas_neg_checked(value, site) : value;
+}
+
+static void pas_domain(const char *function, double argument, int site) {
+    char out[400];
+    pas_format_real(out, argument);
+    pas_fail(site, "'%s' is not defined for %s", function, out);
+}
+
+PAS_API double pas_sqrt(double value, int site) {
+    if (value < 0) {
+        pas_domain("sqrt", value, site);
+    }
+    return sqrt(value);
+}
+
+PAS_API double pas_ln(double value, int site) {
+    if (value <= 0) {
+        pas_domain("ln", value, site);
+    }
+    return log(value);
+}
+
+/* Reals out of the range of integers saturate, NaN becoming 0 */
+PAS_API int64_t pas_trunc(double value) {
+    if (isnan(value)) {
+        return 0;
+    }
+    if (value >= 9223372036854775807.0) {
+        return INT64_MAX;
+    }
+    if (value <= -9223372036854775808.0) {
+        return INT64_MIN;
+    }
+    return (int64_t) value;
+}
+
+PAS_API int64_t pas_round(double value) {
+    return pas_trunc(round(value));
+}
+
+/* Checks */
+
+PAS_API int64_t pas_check(int64_t value, int64_t low, int64_t high, const char *type, int site) {
+    if (value < low || value > high) {
+        pas_fail(site, "The value %" PRId64 " is outside of %s", value, type);
+    }
+    return value;
+}
+
+PAS_API int64_t pas_index(int64_t index, int64_t low, int64_t high, int site) {
+    if (index < low || index > high) {
+        pas_fail(site, "Index %" PRId64 " is outside of the bounds %" PRId64 "..%" PRId64, index, low, high);
+    }
+    return index;
+}
+
+/* `kind` is 'i' for integers, 'c' for characters, 'b' for booleans and 'e'
+   for enumerations, whose literals are `names` */
+PAS_API void pas_no_case(int64_t value, char kind, const char *const *names, int64_t count, int site) {
+    switch (kind) {
+    case 'c':
+        pas_fail(site, "No case label matches '%c'", (int) (unsigned char) value);
+        break;
+    case 'b':
+        pas_fail(site, "No case label matches %s", value ? "true" : "false");
+        break;
+    case 'e':
+        if (value >= 0 && value < count) {
+            pas_fail(site, "No case label matches %s", names[value]);
+        }
+        break;
+    }
+    pas_fail(site, "No case label matches %" PRId64, value);
+}
+
+/* Compares two strings, giving -1, 0 or 1 */
+PAS_API int64_t pas_compare(const int64_t *left, const int64_t *right, int64_t length) {
+    int64_t i;
+    for (i = 0; i < length; i++) {
+        if (left[i] != right[i]) {
+            return left[i] < right[i] ? -1 : 1;
+        }
+    }
+    return 0;
+}
+
+/* Arrays, records and sets, which the code handles through their address */
+
+PAS_API void pas_zero(void *to, int64_t size) {
+    memset(to, 0, (size_t) size);
+}
+
+PAS_API void pas_copy(void *to, const void *from, int64_t size) {
+    memmove(to, from, (size_t) size);
+}
+
+/* Sets */
+
+PAS_API void pas_set_add(pas_set *set, int64_t member, int site) {
+    if (member < 0 || member > 255) {
+        pas_fail(site, "The value %" PRId64 " is outside of 0..255", member);
+    }
+    set->bits[member / 64] |= (uint64_t) 1 << member % 64;
+}
+
+PAS_API int64_t pas_set_has(const pas_set *set, int64_t member) {
+    return member >= 0 && member <= 255 && (set->bits[member / 64] >> member % 64 & 1);
+}
+
+/* The union (0), difference (1) or intersection (2) of two sets, any of
+   which may be the result */
+PAS_API void pas_set_combine(pas_set *result, const pas_set *left, const pas_set *right, int64_t operation) {
+    int i;
+    for (i = 0; i < 4; i++) {
+        uint64_t bits = right->bits[i];
+        result->bits[i] = operation == 0 ? left->bits[i] | bits
+                        : operation == 1 ? left->bits[i] & ~bits
+                        : left->bits[i] & bits;
+    }
+}
+
+PAS_API int64_t pas_set_eq(const pas_set *left, const pas_set *right) {
+    return memcmp(left->bits, right->bits, sizeof left->bits) == 0;
+}
+
+/* Whether `left` is a subset of `right` */
+PAS_API int64_t pas_set_le(const pas_set *left, const pas_set *right) {
+    int i;
+    for (i = 0; i < 4; i++) {
+        if (left->bits[i] & ~right->bits[i]) {
+            return 0;
+        }
+    }
+    return 1;
+}
+
+/* Heap variables, which are never freed so that a disposed one is caught */
+
+typedef union {
+    int64_t live;
+    double align;
+} pas_header;
+
+PAS_API void *pas_new(size_t size) {
+    pas_header *header = calloc(1, sizeof(pas_header) + size);
+    if (header == NULL) {
+        fputs("Runtime Error Out of memory\n", stderr);
+        exit(1);
+    }
+    header->live = 1;
+    return header + 1;
+}
+
+PAS_API void *pas_deref(void *pointer, int site) {
+    if (pointer == NULL) {
+        pas_fail(site, "The pointer does not point to a variable");
+    }
+    if (!((pas_header *) pointer - 1)->live) {
+        pas_fail(site, "The variable the pointer points to has been disposed");
+    }
+    return pointer;
+}
+
+PAS_API void pas_dispose(void *pointer, int site) {
+    ((pas_header *) pas_deref(pointer, site) - 1)->live = 0;
+}
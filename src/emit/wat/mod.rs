@@ -0,0 +1,710 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use crate::ast::program::{Program, ScopeId};
+use crate::ast::subprogram::Subprogram;
+use crate::ast::types::Type;
+use crate::emit::{callee, is_block, levels, routine, size, subprogram_of, Layout, Sites};
+use crate::ir::{Arg, Module, Op, Procedure, Quad};
+use crate::ir::lower::lower;
+use crate::ir::opt::optimize;
+
+#[cfg(test)]
+mod simulator;
+
+/// The runtime every module includes, in which strings are written
+/// `(string "...")`
+const RUNTIME: &str = include_str!("runtime.wat");
+
+/// The shim that gives the host functions on top of WASI
+pub const WASI: &str = include_str!("wasi.wat");
+
+/// All the input and output of a module: the next byte of the input, -1 at
+/// its end; writing a byte to the output (1) or the error output (2); and
+/// ending the program with a status
+const IMPORTS: &str = "\
+  (import \"host\" \"read\" (func $host_read (result i32)))
+  (import \"host\" \"write\" (func $host_write (param i32 i32)))
+  (import \"host\" \"exit\" (func $host_exit (param i32)))
+";
+
+/// Where the strings and tables start. Memory below is never a variable,
+/// so that 0 is no pointer, and the runtime reads numbers to 16 and works
+/// out the digits of reals from 96.
+const DATA: i32 = 1088;
+/// The size of the stack of frames
+const STACK: i32 = 1 << 20;
+const PAGE: i32 = 1 << 16;
+
+/// Translates a checked program into a WebAssembly text module from its
+/// quadruples, optimized at `level`. Temporaries are `i64` locals, a real
+/// being kept as its bits, and the blocks of a procedure are the arms of a
+/// `br_table` in a loop, a jump setting the block to run next. Linear
+/// memory holds the strings, the frame of the main program, a stack of the
+/// frames of subprograms and the heap, in that order; frames are laid out
+/// as in the other translations, every subprogram taking the frame of the
+/// scope it is declared in. The module imports nothing but the host
+/// functions of `IMPORTS`, and exports `_start` and its memory. To run it
+/// under WASI, preload the shim from `--emit wasi` as the module `host`.
+pub fn emit(program: &Program, level: u8) -> String {
+    let mut generator = Generator {
+        program,
+        levels: levels(program),
+        layouts: Layout::all(program),
+        data: vec![],
+        strings: HashMap::new(),
+        sites: Sites::default(),
+        out: String::new(),
+        depth: 0,
+        frame: 0,
+        scope: Program::GLOBAL,
+        slots: vec![],
+        blocks: HashMap::new(),
+        params: vec![]
+    };
+    let (module, _) = optimize(lower(program), level);
+    generator.generate(module)
+}
+
+struct Generator<'a> {
+    program: &'a Program,
+    levels: Vec<usize>,
+    layouts: Vec<Layout>,
+    /// The bytes from `DATA` on
+    data: Vec<u8>,
+    /// The addresses of the strings laid out so far
+    strings: HashMap<String, i32>,
+    sites: Sites,
+    /// The code of the function being generated
+    out: String,
+    depth: usize,
+    /// The size of the frame of the function being generated, with its
+    /// memory temporaries
+    frame: i32,
+    scope: ScopeId,
+    /// Where each memory temporary of the procedure is in its frame
+    slots: Vec<i32>,
+    /// The index of the block of each label of the procedure
+    blocks: HashMap<usize, usize>,
+    /// The parameters of the next call
+    params: Vec<Arg>
+}
+
+/// The immediate of a load or store at `offset` from its address
+fn memarg(offset: i32) -> String {
+    if offset == 0 { String::new() } else { format!(" offset={}", offset) }
+}
+
+/// A string in the text format, escaping all but printable ASCII
+fn quote(bytes: &[u8]) -> String {
+    bytes.iter()
+        .map(|byte| match byte {
+            b' '..=b'~' if *byte != b'"' && *byte != b'\\' => (*byte as char).to_string(),
+            _ => format!("\\{:02x}", byte)
+        })
+        .collect()
+}
+
+impl<'a> Generator<'a> {
+    fn generate(&mut self, module: Module) -> String {
+        let program = self.program;
+        let runtime = self.runtime();
+        let (main, subprograms) = module.procedures.split_last().expect("the main program is lowered last");
+        let mut functions = String::new();
+        for procedure in subprograms {
+            let subprogram = subprogram_of(program, procedure.scope).expect("procedures are lowered from subprograms");
+            functions.push_str(&self.subprogram(subprogram, procedure));
+        }
+        functions.push_str(&self.main(main));
+        let sites = self.sites.texts(program);
+        let sites = self.table(&sites);
+
+        let globals = (DATA + self.data.len() as i32 + 7) / 8 * 8;
+        let stack = (globals + self.frame + 15) / 16 * 16;
+        let heap = stack + STACK;
+        format!(";; program {0}, compiled from Pascal\n\
+                 ;; run with wasmtime run --preload host=wasi.wat {0}.wat, the shim being from --emit wasi\n\
+                 (module\n{1}\
+                 \x20 (memory (export \"memory\") {2})\n\
+                 \x20 (global $pas_globals i32 (i32.const {3}))\n\
+                 \x20 (global $pas_sp (mut i32) (i32.const {4}))\n\
+                 \x20 (global $pas_stack_end i32 (i32.const {5}))\n\
+                 \x20 (global $pas_heap (mut i32) (i32.const {5}))\n\
+                 \x20 (global $pas_sites i32 (i32.const {6}))\n\n\
+                 {7}\n{8}\
+                 \x20 (data (i32.const {9}) \"{10}\"))\n",
+                program.name, IMPORTS, (heap + PAGE - 1) / PAGE, globals, stack, heap, sites,
+                runtime, functions, DATA, quote(&self.data))
+    }
+
+    /// The runtime, with its strings laid out
+    fn runtime(&mut self) -> String {
+        const STRING: &str = "(string \"";
+        let mut out = String::new();
+        let mut rest = RUNTIME;
+        while let Some(start) = rest.find(STRING) {
+            out.push_str(&rest[..start]);
+            rest = &rest[start + STRING.len()..];
+            let end = rest.find("\")").expect("strings in the runtime are closed");
+            let (address, length) = self.string(&rest[..end]);
+            let _ = write!(out, "(i32.const {}) (i32.const {})", address, length);
+            rest = &rest[end + 2..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    fn op(&mut self, text: &str) {
+        let _ = writeln!(self.out, "{:indent$}{}", "", text, indent = 4 + 2 * self.depth);
+    }
+
+    /// Starts a block, a loop or an `if`
+    fn open(&mut self, text: &str) {
+        self.op(text);
+        self.depth += 1;
+    }
+
+    fn close(&mut self) {
+        self.depth -= 1;
+        self.op("end");
+    }
+
+    /// The address and the length of a string in memory
+    fn string(&mut self, text: &str) -> (i32, i32) {
+        let length = text.len() as i32;
+        if let Some(address) = self.strings.get(text) {
+            return (*address, length);
+        }
+        let address = DATA + self.data.len() as i32;
+        self.data.extend(text.bytes());
+        self.strings.insert(text.to_string(), address);
+        (address, length)
+    }
+
+    /// A table of strings, the names of the literals of an enumeration or
+    /// the texts of the places of runtime errors, with the address and the
+    /// length of each
+    fn table(&mut self, texts: &[String]) -> i32 {
+        let strings = texts.iter().map(|text| self.string(text)).collect::<Vec<_>>();
+        while !self.data.len().is_multiple_of(4) {
+            self.data.push(0);
+        }
+        let address = DATA + self.data.len() as i32;
+        for (text, length) in strings {
+            self.data.extend(text.to_le_bytes());
+            self.data.extend(length.to_le_bytes());
+        }
+        address
+    }
+
+    /// Starts the code of a procedure, laying out its memory temporaries
+    /// after the variables of its scope
+    fn begin(&mut self, procedure: &Procedure) {
+        self.out.clear();
+        self.depth = 0;
+        self.scope = procedure.scope;
+        let mut offset = (self.layouts[procedure.scope].size + 7) / 8 * 8;
+        self.slots.clear();
+        for size in &procedure.slots {
+            self.slots.push(offset);
+            offset += (size + 7) / 8 * 8;
+        }
+        self.frame = offset;
+        self.blocks = procedure.blocks.iter().enumerate().map(|(index, block)| (block.label, index)).collect();
+    }
+
+    /// The text of a function, with its temporaries, the code setting up
+    /// its frame and the code generated since `begin`
+    fn finish(&mut self, header: &str, procedure: &Procedure, frame: &str) -> String {
+        let temps = (1..=procedure.temps).map(|temp| format!(" (local $t{} i64)", temp)).collect::<String>();
+        format!("  (func {}\n    (local $f i32) (local $block i32){}\n{}    local.set $f\n{}  )\n\n",
+                header, temps, frame, self.out)
+    }
+
+    /// The blocks of a procedure, each entered by a branch to the label of
+    /// the `block` that ends right before it
+    fn body(&mut self, procedure: &Procedure) {
+        self.open("loop $dispatch");
+        for block in procedure.blocks.iter().rev() {
+            self.open(&format!("block $L{}", block.label));
+        }
+        self.op("local.get $block");
+        let table = procedure.blocks.iter().map(|block| format!("$L{}", block.label)).collect::<Vec<_>>();
+        self.op(&format!("br_table {}", table.join(" ")));
+        for (index, block) in procedure.blocks.iter().enumerate() {
+            self.close();
+            for (at, quad) in block.quads.iter().enumerate() {
+                // a jump to the next block falls through to it
+                let last = at + 1 == block.quads.len();
+                if !(last && quad.op == Op::Goto && quad.target().map(|target| self.blocks[&target]) == Some(index + 1)) {
+                    self.quad(procedure, quad);
+                }
+            }
+        }
+        self.close();
+        self.op("unreachable");
+    }
+
+    fn subprogram(&mut self, subprogram: &Subprogram, procedure: &Procedure) -> String {
+        self.begin(procedure);
+        let mut header = format!("${} (param $link i32)", procedure.name);
+        for index in 1..=subprogram.params.len() {
+            let _ = write!(header, " (param $p{} i64)", index);
+        }
+        if subprogram.result.is_some() {
+            header.push_str(" (result i64)");
+        }
+
+        self.op("local.get $f");
+        self.op("local.get $link");
+        self.op("i32.store");
+        for (index, param) in subprogram.params.iter().enumerate() {
+            let offset = self.layouts[subprogram.scope].fields[&param.name];
+            self.op("local.get $f");
+            if is_block(&param.r#type) && !param.by_ref {
+                self.op(&format!("i32.const {}", offset));
+                self.op("i32.add");
+                self.op(&format!("local.get $p{}", index + 1));
+                self.op("i32.wrap_i64");
+                self.op(&format!("i32.const {}", size(&param.r#type)));
+                self.op("memory.copy");
+            } else {
+                self.op(&format!("local.get $p{}", index + 1));
+                self.op(&format!("i64.store{}", memarg(offset)));
+            }
+        }
+        self.body(procedure);
+        let frame = format!("    i32.const {}\n    call $pas_enter\n", self.frame);
+        self.finish(&header, procedure, &frame)
+    }
+
+    /// The main program, whose frame is static, its memory temporaries
+    /// included
+    fn main(&mut self, procedure: &Procedure) -> String {
+        self.begin(procedure);
+        self.body(procedure);
+        self.finish("$main (export \"_start\")", procedure, "    global.get $pas_globals\n")
+    }
+
+    /// Pushes the address of the frame of `scope`, the innermost one
+    /// reachable from the running procedure
+    fn frame_of(&mut self, scope: ScopeId) {
+        if scope == Program::GLOBAL && self.scope != Program::GLOBAL {
+            self.op("global.get $pas_globals");
+            return;
+        }
+        self.op("local.get $f");
+        // the link is at the start of a frame
+        for _ in self.levels[scope]..self.levels[self.scope] {
+            self.op("i32.load");
+        }
+    }
+
+    /// Pushes the address of the frame a variable or a memory temporary is
+    /// in, and gives where it is in that frame
+    fn place(&mut self, arg: &Arg) -> i32 {
+        match arg {
+            Arg::Var(scope, name) => {
+                self.frame_of(*scope);
+                let layout = &self.layouts[*scope];
+                layout.fields.get(name).copied().unwrap_or(layout.result)
+            },
+            Arg::Slot(slot) => {
+                self.op("local.get $f");
+                self.slots[slot - 1]
+            },
+            arg => unreachable!("{:?} is not in memory", arg)
+        }
+    }
+
+    /// Pushes the value of a field, the bits of a real
+    fn value(&mut self, arg: &Arg) {
+        match arg {
+            Arg::Int(value) => self.op(&format!("i64.const {}", value)),
+            Arg::Real(value) => self.op(&format!("i64.const {}", value.to_bits() as i64)),
+            Arg::Temp(temp) => self.op(&format!("local.get $t{}", temp)),
+            arg => {
+                let offset = self.place(arg);
+                self.op(&format!("i64.load{}", memarg(offset)));
+            }
+        }
+    }
+
+    /// Pushes the real of a field
+    fn real(&mut self, arg: &Arg) {
+        match arg {
+            Arg::Int(value) => self.op(&format!("f64.const {:?}", *value as f64)),
+            Arg::Real(value) if value.is_finite() => self.op(&format!("f64.const {:?}", value)),
+            arg => {
+                self.value(arg);
+                self.op("f64.reinterpret_i64");
+            }
+        }
+    }
+
+    /// Pushes the address a field holds
+    fn pointer(&mut self, arg: &Arg) {
+        self.value(arg);
+        self.op("i32.wrap_i64");
+    }
+
+    /// Pushes what storing to a field needs before its value, and gives the
+    /// offset of the store
+    fn target(&mut self, result: &Arg) -> i32 {
+        match result {
+            Arg::Temp(_) => 0,
+            variable => self.place(variable)
+        }
+    }
+
+    /// Stores the value on the stack to a field, after `target`
+    fn set(&mut self, result: &Arg, offset: i32) {
+        match result {
+            Arg::Temp(temp) => self.op(&format!("local.set $t{}", temp)),
+            _ => self.op(&format!("i64.store{}", memarg(offset)))
+        }
+    }
+
+    /// Makes the block of `label` the one to run, and runs it
+    fn jump(&mut self, label: usize) {
+        let index = self.blocks[&label];
+        self.op(&format!("i32.const {}", index));
+        self.op("local.set $block");
+        self.op("br $dispatch");
+    }
+
+    fn quad(&mut self, procedure: &Procedure, quad: &Quad) {
+        let (arg1, arg2, result) = (&quad.arg1, &quad.arg2, &quad.result);
+        let site = format!("i32.const {}", self.sites.of(self.program, quad));
+        let integer = |op| match op {
+            Op::Add => Some("i64.add"),
+            Op::Sub => Some("i64.sub"),
+            Op::Mul => Some("i64.mul"),
+            Op::Eq => Some("i64.eq"),
+            Op::Ne => Some("i64.ne"),
+            Op::Lt => Some("i64.lt_s"),
+            Op::Le => Some("i64.le_s"),
+            Op::Gt => Some("i64.gt_s"),
+            Op::Ge => Some("i64.ge_s"),
+            _ => None
+        };
+        let real = |op| match op {
+            Op::FAdd => Some("f64.add"),
+            Op::FSub => Some("f64.sub"),
+            Op::FMul => Some("f64.mul"),
+            Op::FEq => Some("f64.eq"),
+            Op::FNe => Some("f64.ne"),
+            Op::FLt => Some("f64.lt"),
+            Op::FLe => Some("f64.le"),
+            Op::FGt => Some("f64.gt"),
+            Op::FGe => Some("f64.ge"),
+            _ => None
+        };
+        let unary = |op| match op {
+            Op::FNeg => Some("f64.neg"),
+            Op::FAbs => Some("f64.abs"),
+            Op::Sin => Some("call $pas_sin"),
+            Op::Cos => Some("call $pas_cos"),
+            Op::Exp => Some("call $pas_exp"),
+            Op::Arctan => Some("call $pas_atan"),
+            _ => None
+        };
+        // comparisons give an i32, and operations on reals a real
+        let compares = matches!(quad.op, Op::Eq | Op::Ne | Op::Lt | Op::Le | Op::Gt | Op::Ge
+            | Op::FEq | Op::FNe | Op::FLt | Op::FLe | Op::FGt | Op::FGe);
+        if let Some(instruction) = integer(quad.op) {
+            let offset = self.target(result);
+            self.value(arg1);
+            self.value(arg2);
+            self.op(instruction);
+            if compares {
+                self.op("i64.extend_i32_u");
+            }
+            return self.set(result, offset);
+        }
+        if let Some(instruction) = real(quad.op) {
+            let offset = self.target(result);
+            self.real(arg1);
+            self.real(arg2);
+            self.op(instruction);
+            self.op(if compares { "i64.extend_i32_u" } else { "i64.reinterpret_f64" });
+            return self.set(result, offset);
+        }
+        if let Some(instruction) = unary(quad.op) {
+            let offset = self.target(result);
+            self.real(arg1);
+            self.op(instruction);
+            self.op("i64.reinterpret_f64");
+            return self.set(result, offset);
+        }
+        let offset = quad.def().map_or(0, |result| self.target(result));
+        match quad.op {
+            Op::Assign => self.value(arg1),
+            Op::Address => {
+                let offset = self.place(arg1);
+                self.op(&format!("i32.const {}", offset));
+                self.op("i32.add");
+                self.op("i64.extend_i32_u");
+            },
+            Op::Load => {
+                self.pointer(arg1);
+                self.op("i64.load");
+            },
+            Op::Store => {
+                self.pointer(result);
+                self.value(arg1);
+                return self.op("i64.store");
+            },
+            Op::Div | Op::Mod => {
+                self.value(arg1);
+                self.value(arg2);
+                self.op(&site);
+                self.op(if quad.op == Op::Div { "call $pas_div" } else { "call $pas_mod" });
+            },
+            Op::Neg => {
+                self.op("i64.const 0");
+                self.value(arg1);
+                self.op("i64.sub");
+            },
+            Op::FDiv => {
+                self.real(arg1);
+                self.real(arg2);
+                self.op(&site);
+                self.op("call $pas_rdiv");
+                self.op("i64.reinterpret_f64");
+            },
+            Op::Not | Op::Odd => {
+                self.value(arg1);
+                self.op("i64.const 1");
+                self.op(if quad.op == Op::Not { "i64.xor" } else { "i64.and" });
+            },
+            Op::Abs => {
+                self.value(arg1);
+                self.op("call $pas_abs");
+            },
+            Op::Float => {
+                self.value(arg1);
+                self.op("f64.convert_i64_s");
+                self.op("i64.reinterpret_f64");
+            },
+            Op::Trunc => {
+                self.real(arg1);
+                self.op("i64.trunc_sat_f64_s");
+            },
+            Op::Round => {
+                self.real(arg1);
+                self.op("call $pas_round");
+            },
+            Op::Sqrt | Op::Ln => {
+                self.real(arg1);
+                self.op(&site);
+                self.op(if quad.op == Op::Sqrt { "call $pas_sqrt" } else { "call $pas_ln" });
+                self.op("i64.reinterpret_f64");
+            },
+            Op::Check | Op::Bounds | Op::NoCase => {
+                let ty = match arg2 {
+                    Arg::Type(ty) => ty,
+                    arg => unreachable!("{:?} is not a type", arg)
+                };
+                self.value(arg1);
+                if quad.op == Op::NoCase {
+                    self.no_case(ty);
+                    self.op(&site);
+                    self.op("call $pas_no_case");
+                    return self.op("unreachable");
+                }
+                let bounds = ty.bounds().expect("checked types have bounds");
+                self.op(&format!("i64.const {}", bounds.start));
+                self.op(&format!("i64.const {}", bounds.end));
+                if quad.op == Op::Check {
+                    let (address, length) = self.string(&ty.to_string());
+                    self.op(&format!("i32.const {}", address));
+                    self.op(&format!("i32.const {}", length));
+                    self.op(&site);
+                    self.op("call $pas_check");
+                } else {
+                    self.op(&site);
+                    self.op("call $pas_index");
+                }
+                return self.op("drop");
+            },
+            Op::Deref => {
+                self.pointer(arg1);
+                self.op(&site);
+                self.op("call $pas_deref");
+                self.op("i64.extend_i32_u");
+            },
+            Op::Goto => return self.jump(quad.target().expect("jumps have a target")),
+            Op::If | Op::IfFalse => {
+                self.value(arg1);
+                if quad.op == Op::If {
+                    self.op("i64.const 0");
+                    self.op("i64.ne");
+                } else {
+                    self.op("i64.eqz");
+                }
+                self.open("if");
+                self.jump(quad.target().expect("jumps have a target"));
+                return self.close();
+            },
+            Op::Param => return self.params.push(arg1.clone()),
+            Op::Call => {
+                if !self.call(quad) {
+                    return;
+                }
+            },
+            Op::Return => return self.exit(procedure, arg1),
+            op => unreachable!("{} is translated above", op)
+        }
+        self.set(result, offset);
+    }
+
+    /// Pushes what the runtime reports of a value no case label matches,
+    /// after the value: its kind, and the names of the literals of an
+    /// enumeration
+    fn no_case(&mut self, ty: &Type) {
+        let (kind, names, count) = match ty {
+            Type::Char => ('c', 0, 0),
+            Type::Boolean => ('b', 0, 0),
+            Type::Enum(names) => ('e', self.table(names), names.len()),
+            _ => ('i', 0, 0)
+        };
+        self.op(&format!("i32.const {}", kind as u32));
+        self.op(&format!("i32.const {}", names));
+        self.op(&format!("i32.const {}", count));
+    }
+
+    /// Leaves a procedure, popping the frame of a subprogram
+    fn exit(&mut self, procedure: &Procedure, value: &Arg) {
+        if procedure.scope == Program::GLOBAL {
+            self.op("call $pas_exit");
+            return self.op("return");
+        }
+        if !value.is_none() {
+            self.value(value);
+        }
+        self.op("local.get $f");
+        self.op("global.set $pas_sp");
+        self.op("return");
+    }
+
+    /// A call to a subprogram, with the parameters of the quadruples before
+    /// it, or to a routine of the runtime, giving whether it leaves a value
+    /// to store to its result
+    fn call(&mut self, quad: &Quad) -> bool {
+        let params = std::mem::take(&mut self.params);
+        let name = match &quad.arg1 {
+            Arg::Name(name) => name,
+            arg => unreachable!("{:?} is not a procedure", arg)
+        };
+        let gives = match callee(self.program, name) {
+            Some(subprogram) => {
+                let parent = self.program.scopes[subprogram.scope].parent.expect("subprograms are in a scope");
+                self.frame_of(parent);
+                for param in &params {
+                    self.value(param);
+                }
+                self.op(&format!("call ${}", name));
+                subprogram.result.is_some()
+            },
+            None => {
+                let routine = routine(name);
+                for (param, kind) in params.iter().zip(routine.params.chars()) {
+                    match kind {
+                        'f' => self.real(param),
+                        'p' => self.pointer(param),
+                        _ => self.value(param)
+                    }
+                }
+                if routine.checked {
+                    let site = self.sites.number(&quad.range);
+                    self.op(&format!("i32.const {}", site));
+                }
+                self.op(&format!("call ${}", routine.function));
+                match routine.result {
+                    Some('f') => self.op("i64.reinterpret_f64"),
+                    Some('p') => self.op("i64.extend_i32_u"),
+                    _ => {}
+                }
+                routine.result.is_some()
+            }
+        };
+        if gives && quad.result.is_none() {
+            self.op("drop");
+        }
+        gives && !quad.result.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+    use super::*;
+    use crate::emit::tests::*;
+
+    /// Runs a program under wasmtime, with the WASI shim as its host
+    fn run(name: &str, program: &Program, input: &str) -> (String, Option<String>) {
+        let dir = scratch(&format!("wat-{}", name));
+        std::fs::write(dir.join("program.wat"), emit(program, 0)).unwrap();
+        std::fs::write(dir.join("wasi.wat"), WASI).unwrap();
+        let outcome = execute(Command::new("wasmtime")
+            .args(["run", "--preload", "host=wasi.wat", "program.wat"])
+            .current_dir(&dir), input);
+        std::fs::remove_dir_all(dir).unwrap();
+        outcome
+    }
+
+    #[test]
+    fn samples_behave_as_interpreted() {
+        for (name, program, input) in samples() {
+            assert_eq!(simulator::run(&emit(&program, 0), &input), interpret(&program, &input), "{}", name);
+        }
+    }
+
+    #[test]
+    fn runtime_errors() {
+        for body in FAULTS {
+            let program = fault(body);
+            let (_, error) = simulator::run(&emit(&program, 0), "");
+            let (_, expected) = interpret(&program, "");
+            assert_eq!(error, expected, "{}", body);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs wasmtime"]
+    fn samples_behave_as_interpreted_under_wasmtime() {
+        require("wasmtime");
+        for (name, program, input) in samples() {
+            assert_eq!(run(&name, &program, &input), interpret(&program, &input), "{}", name);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs wasmtime"]
+    fn runtime_errors_under_wasmtime() {
+        require("wasmtime");
+        for (index, body) in FAULTS.iter().enumerate() {
+            let program = fault(body);
+            let (_, error) = run(&format!("fault-{}", index), &program, "");
+            let (_, expected) = interpret(&program, "");
+            assert_eq!(error, expected, "{}", body);
+        }
+    }
+
+    #[test]
+    fn input_and_output_are_imported_from_the_host() {
+        let module = emit(&check("
+            program hello;
+            var c: char;
+            begin
+              read(c);
+              writeln(c, 1.5)
+            end.
+        "), 0);
+        let imports = module.lines().filter(|line| line.contains("(import ")).collect::<Vec<_>>();
+        assert_eq!(imports.len(), 3, "{}", module);
+        assert!(imports.iter().all(|import| import.contains("(import \"host\" ")), "{}", module);
+        assert!(!module.contains("(string "), "{}", module);
+        assert!(module.contains("(func $main (export \"_start\")"), "{}", module);
+    }
+}
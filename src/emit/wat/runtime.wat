@@ -0,0 +1,1358 @@
+  ;; The runtime of compiled Pascal programs: input and output on top of the
+  ;; host functions, arithmetic, sets, heap variables, the standard functions
+  ;; WebAssembly lacks and the checks that stop a program with a runtime
+  ;; error. The messages are the ones the interpreter gives. The strings are
+  ;; written (string "...") and laid out with the ones of the program.
+
+  ;; Input is read a character ahead, a last line without a newline ending
+  ;; like the others
+  (global $pas_ahead (mut i32) (i32.const -2))
+  (global $pas_pending (mut i32) (i32.const -2))
+  (global $pas_in_line (mut i32) (i32.const 0))
+
+  ;; Output, to the output (1) or the error output (2)
+
+  (func $pas_put (param $stream i32) (param $byte i32)
+    (call $host_write (local.get $stream) (local.get $byte)))
+
+  (func $pas_put_text (param $stream i32) (param $text i32) (param $length i32)
+    (local $i i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $length)))
+        (call $pas_put (local.get $stream) (i32.load8_u (i32.add (local.get $text) (local.get $i))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next))))
+
+  (func $pas_put_integer (param $stream i32) (param $value i64)
+    (local $magnitude i64) (local $power i64)
+    (local.set $magnitude (local.get $value))
+    (if (i64.lt_s (local.get $value) (i64.const 0))
+      (then
+        (call $pas_put (local.get $stream) (i32.const 45))
+        (local.set $magnitude (i64.sub (i64.const 0) (local.get $value)))))
+    ;; the magnitude is unsigned, the one of the lowest integer included
+    (local.set $power (i64.const 1))
+    (block $found
+      (loop $next
+        (br_if $found (i64.lt_u (i64.div_u (local.get $magnitude) (local.get $power)) (i64.const 10)))
+        (local.set $power (i64.mul (local.get $power) (i64.const 10)))
+        (br $next)))
+    (loop $digit
+      (call $pas_put (local.get $stream)
+        (i32.add (i32.const 48) (i32.wrap_i64 (i64.rem_u (i64.div_u (local.get $magnitude) (local.get $power)) (i64.const 10)))))
+      (local.set $power (i64.div_u (local.get $power) (i64.const 10)))
+      (br_if $digit (i64.ne (local.get $power) (i64.const 0)))))
+
+  (func $pas_put_boolean (param $stream i32) (param $value i64)
+    (if (i64.eqz (local.get $value))
+      (then (call $pas_put_text (local.get $stream) (string "false")))
+      (else (call $pas_put_text (local.get $stream) (string "true")))))
+
+  ;; Reals are written and read exactly, in numbers of 48 words in memory at
+  ;; 128, 320, 512, 704 and 896
+
+  (func $pas_big_set (param $big i32) (param $value i64)
+    (memory.fill (local.get $big) (i32.const 0) (i32.const 192))
+    (i64.store (local.get $big) (local.get $value)))
+
+  ;; Multiplies a number by a factor below 2 to the power of 31, adding a
+  ;; carry
+  (func $pas_big_mul (param $big i32) (param $factor i64) (param $carry i64)
+    (local $i i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (i32.const 192)))
+        (local.set $carry (i64.add (i64.mul (i64.load32_u (i32.add (local.get $big) (local.get $i))) (local.get $factor))
+                                   (local.get $carry)))
+        (i64.store32 (i32.add (local.get $big) (local.get $i)) (local.get $carry))
+        (local.set $carry (i64.shr_u (local.get $carry) (i64.const 32)))
+        (local.set $i (i32.add (local.get $i) (i32.const 4)))
+        (br $next))))
+
+  (func $pas_big_shl (param $big i32) (param $bits i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.lt_s (local.get $bits) (i32.const 30)))
+        (call $pas_big_mul (local.get $big) (i64.const 0x40000000) (i64.const 0))
+        (local.set $bits (i32.sub (local.get $bits) (i32.const 30)))
+        (br $next)))
+    (call $pas_big_mul (local.get $big) (i64.shl (i64.const 1) (i64.extend_i32_u (local.get $bits))) (i64.const 0)))
+
+  (func $pas_big_shr1 (param $big i32)
+    (local $i i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (i32.const 188)))
+        (i64.store32 (i32.add (local.get $big) (local.get $i))
+          (i64.shr_u (i64.load (i32.add (local.get $big) (local.get $i))) (i64.const 1)))
+        (local.set $i (i32.add (local.get $i) (i32.const 4)))
+        (br $next)))
+    (i32.store (i32.add (local.get $big) (i32.const 188))
+      (i32.shr_u (i32.load (i32.add (local.get $big) (i32.const 188))) (i32.const 1))))
+
+  ;; Multiplies a number by 10 to the power of an exponent
+  (func $pas_big_pow10 (param $big i32) (param $exponent i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.lt_s (local.get $exponent) (i32.const 9)))
+        (call $pas_big_mul (local.get $big) (i64.const 1000000000) (i64.const 0))
+        (local.set $exponent (i32.sub (local.get $exponent) (i32.const 9)))
+        (br $next)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.le_s (local.get $exponent) (i32.const 0)))
+        (call $pas_big_mul (local.get $big) (i64.const 10) (i64.const 0))
+        (local.set $exponent (i32.sub (local.get $exponent) (i32.const 1)))
+        (br $next))))
+
+  (func $pas_big_add (param $into i32) (param $left i32) (param $right i32)
+    (local $i i32) (local $carry i64)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (i32.const 192)))
+        (local.set $carry (i64.add (i64.add (i64.load32_u (i32.add (local.get $left) (local.get $i)))
+                                            (i64.load32_u (i32.add (local.get $right) (local.get $i))))
+                                   (local.get $carry)))
+        (i64.store32 (i32.add (local.get $into) (local.get $i)) (local.get $carry))
+        (local.set $carry (i64.shr_u (local.get $carry) (i64.const 32)))
+        (local.set $i (i32.add (local.get $i) (i32.const 4)))
+        (br $next))))
+
+  ;; Subtracts a number from one at least as large
+  (func $pas_big_sub (param $big i32) (param $other i32)
+    (local $i i32) (local $borrow i64)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (i32.const 192)))
+        (local.set $borrow (i64.add (i64.sub (i64.load32_u (i32.add (local.get $big) (local.get $i)))
+                                             (i64.load32_u (i32.add (local.get $other) (local.get $i))))
+                                    (local.get $borrow)))
+        (i64.store32 (i32.add (local.get $big) (local.get $i)) (local.get $borrow))
+        (local.set $borrow (i64.shr_s (local.get $borrow) (i64.const 32)))
+        (local.set $i (i32.add (local.get $i) (i32.const 4)))
+        (br $next))))
+
+  ;; Compares two numbers, giving -1, 0 or 1
+  (func $pas_big_compare (param $left i32) (param $right i32) (result i32)
+    (local $i i32) (local $a i32) (local $b i32)
+    (local.set $i (i32.const 192))
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $i)))
+        (local.set $i (i32.sub (local.get $i) (i32.const 4)))
+        (local.set $a (i32.load (i32.add (local.get $left) (local.get $i))))
+        (local.set $b (i32.load (i32.add (local.get $right) (local.get $i))))
+        (if (i32.ne (local.get $a) (local.get $b))
+          (then (return (select (i32.const -1) (i32.const 1) (i32.lt_u (local.get $a) (local.get $b))))))
+        (br $next)))
+    (i32.const 0))
+
+  ;; The number of bits of a number
+  (func $pas_big_bits (param $big i32) (result i32)
+    (local $i i32) (local $word i32)
+    (local.set $i (i32.const 192))
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $i)))
+        (local.set $i (i32.sub (local.get $i) (i32.const 4)))
+        (local.set $word (i32.load (i32.add (local.get $big) (local.get $i))))
+        (if (local.get $word)
+          (then (return (i32.sub (i32.add (i32.mul (local.get $i) (i32.const 8)) (i32.const 32)) (i32.clz (local.get $word))))))
+        (br $next)))
+    (i32.const 0))
+
+  ;; Writes a real as the shortest decimal that reads back as the same real,
+  ;; the nearest of them if there are several, without an exponent. The
+  ;; digits are generated as Burger and Dybvig do: the real is r / s, with r
+  ;; at 128 and s at 320, and the decimals reading back as it are the ones
+  ;; from (r - m-) / s to (r + m+) / s, with m- at 512 and m+ at 704. They
+  ;; are written to 96.
+  (func $pas_put_real (param $stream i32) (param $value f64)
+    (local $bits i64) (local $field i32) (local $mantissa i64) (local $exponent i32) (local $shift i32)
+    (local $boundary i32) (local $even i32) (local $digit i32) (local $low i32) (local $high i32) (local $order i32)
+    (local $count i32) (local $point i32) (local $i i32)
+    (if (f64.ne (local.get $value) (local.get $value))
+      (then
+        (call $pas_put_text (local.get $stream) (string "NaN"))
+        (return)))
+    (if (i64.lt_s (i64.reinterpret_f64 (local.get $value)) (i64.const 0))
+      (then
+        (call $pas_put (local.get $stream) (i32.const 45))
+        (local.set $value (f64.neg (local.get $value)))))
+    (if (f64.eq (local.get $value) (f64.const inf))
+      (then
+        (call $pas_put_text (local.get $stream) (string "inf"))
+        (return)))
+    (if (f64.eq (local.get $value) (f64.const 0))
+      (then
+        (call $pas_put (local.get $stream) (i32.const 48))
+        (return)))
+    (local.set $bits (i64.reinterpret_f64 (local.get $value)))
+    (local.set $field (i32.wrap_i64 (i64.shr_u (local.get $bits) (i64.const 52))))
+    (local.set $mantissa (i64.and (local.get $bits) (i64.const 0xfffffffffffff)))
+    ;; the real below a power of two is nearer than the one above
+    (local.set $boundary (i32.and (i64.eqz (local.get $mantissa)) (i32.gt_u (local.get $field) (i32.const 1))))
+    (if (local.get $field)
+      (then
+        (local.set $mantissa (i64.or (local.get $mantissa) (i64.const 0x10000000000000)))
+        (local.set $exponent (i32.sub (local.get $field) (i32.const 1075))))
+      (else (local.set $exponent (i32.const -1074))))
+    ;; halfway reads back as the real with an even mantissa
+    (local.set $even (i64.eqz (i64.and (local.get $mantissa) (i64.const 1))))
+    (local.set $shift (select (local.get $exponent) (i32.const 0) (i32.gt_s (local.get $exponent) (i32.const 0))))
+    (call $pas_big_set (i32.const 128) (local.get $mantissa))
+    (call $pas_big_shl (i32.const 128) (i32.add (i32.add (local.get $shift) (i32.const 1)) (local.get $boundary)))
+    (call $pas_big_set (i32.const 320) (i64.const 1))
+    (call $pas_big_shl (i32.const 320)
+      (i32.add (i32.add (i32.sub (local.get $shift) (local.get $exponent)) (i32.const 1)) (local.get $boundary)))
+    (call $pas_big_set (i32.const 512) (i64.const 1))
+    (call $pas_big_shl (i32.const 512) (local.get $shift))
+    (call $pas_big_set (i32.const 704) (i64.const 1))
+    (call $pas_big_shl (i32.const 704) (i32.add (local.get $shift) (local.get $boundary)))
+    ;; the power of ten above the real, estimated from its binary exponent
+    ;; and one too low at worst
+    (local.set $point (i32.trunc_f64_s (f64.ceil (f64.sub
+      (f64.mul (f64.convert_i32_s (i32.sub (i32.add (local.get $exponent) (i32.const 63))
+                                           (i32.wrap_i64 (i64.clz (local.get $mantissa)))))
+               (f64.const 0.30102999566398120))
+      (f64.const 1e-10)))))
+    (if (i32.ge_s (local.get $point) (i32.const 0))
+      (then (call $pas_big_pow10 (i32.const 320) (local.get $point)))
+      (else
+        (call $pas_big_pow10 (i32.const 128) (i32.sub (i32.const 0) (local.get $point)))
+        (call $pas_big_pow10 (i32.const 512) (i32.sub (i32.const 0) (local.get $point)))
+        (call $pas_big_pow10 (i32.const 704) (i32.sub (i32.const 0) (local.get $point)))))
+    (call $pas_big_add (i32.const 896) (i32.const 128) (i32.const 704))
+    (if (i32.gt_s (call $pas_big_compare (i32.const 896) (i32.const 320)) (i32.sub (i32.const 0) (local.get $even)))
+      (then (local.set $point (i32.add (local.get $point) (i32.const 1))))
+      (else
+        (call $pas_big_mul (i32.const 128) (i64.const 10) (i64.const 0))
+        (call $pas_big_mul (i32.const 512) (i64.const 10) (i64.const 0))
+        (call $pas_big_mul (i32.const 704) (i64.const 10) (i64.const 0))))
+    (block $last
+      (loop $next
+        (local.set $digit (i32.const 0))
+        (block $done
+          (loop $subtract
+            (br_if $done (i32.lt_s (call $pas_big_compare (i32.const 128) (i32.const 320)) (i32.const 0)))
+            (call $pas_big_sub (i32.const 128) (i32.const 320))
+            (local.set $digit (i32.add (local.get $digit) (i32.const 1)))
+            (br $subtract)))
+        (local.set $low (i32.lt_s (call $pas_big_compare (i32.const 128) (i32.const 512)) (local.get $even)))
+        (call $pas_big_add (i32.const 896) (i32.const 128) (i32.const 704))
+        (local.set $high (i32.gt_s (call $pas_big_compare (i32.const 896) (i32.const 320)) (i32.sub (i32.const 0) (local.get $even))))
+        (br_if $last (i32.or (local.get $low) (local.get $high)))
+        (i32.store8 (i32.add (i32.const 96) (local.get $count)) (i32.add (i32.const 48) (local.get $digit)))
+        (local.set $count (i32.add (local.get $count) (i32.const 1)))
+        (call $pas_big_mul (i32.const 128) (i64.const 10) (i64.const 0))
+        (call $pas_big_mul (i32.const 512) (i64.const 10) (i64.const 0))
+        (call $pas_big_mul (i32.const 704) (i64.const 10) (i64.const 0))
+        (br $next)))
+    ;; the last digit rounds the rest, up when it is half
+    (if (i32.and (local.get $low) (local.get $high))
+      (then
+        (call $pas_big_add (i32.const 896) (i32.const 128) (i32.const 128))
+        (local.set $order (call $pas_big_compare (i32.const 896) (i32.const 320)))
+        (local.set $high (i32.ge_s (local.get $order) (i32.const 0)))))
+    (i32.store8 (i32.add (i32.const 96) (local.get $count)) (i32.add (i32.add (i32.const 48) (local.get $digit)) (local.get $high)))
+    (local.set $count (i32.add (local.get $count) (i32.const 1)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.le_u (local.get $count) (i32.const 1)))
+        (br_if $done (i32.ne (i32.load8_u (i32.add (i32.const 95) (local.get $count))) (i32.const 48)))
+        (local.set $count (i32.sub (local.get $count) (i32.const 1)))
+        (br $next)))
+    (if (i32.le_s (local.get $point) (i32.const 0))
+      (then
+        (call $pas_put (local.get $stream) (i32.const 48))
+        (call $pas_put (local.get $stream) (i32.const 46))
+        (block $done
+          (loop $next
+            (br_if $done (i32.ge_s (local.get $i) (i32.sub (i32.const 0) (local.get $point))))
+            (call $pas_put (local.get $stream) (i32.const 48))
+            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+            (br $next)))
+        (local.set $point (i32.const 0))
+        (local.set $i (i32.const 0))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.and (i32.ge_s (local.get $i) (local.get $count)) (i32.ge_s (local.get $i) (local.get $point))))
+        (if (i32.and (i32.eq (local.get $i) (local.get $point)) (i32.gt_s (local.get $point) (i32.const 0)))
+          (then (call $pas_put (local.get $stream) (i32.const 46))))
+        (call $pas_put (local.get $stream)
+          (if (result i32) (i32.lt_s (local.get $i) (local.get $count))
+            (then (i32.load8_u (i32.add (i32.const 96) (local.get $i))))
+            (else (i32.const 48))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next))))
+
+  (func $pas_write_integer (param $value i64)
+    (call $pas_put_integer (i32.const 1) (local.get $value)))
+
+  ;; A real written without a field width, in floating-point form in the
+  ;; default field of 22 characters
+  (func $pas_write_real (param $value f64)
+    (call $pas_write_real_width (local.get $value) (i64.const 22) (i32.const 0)))
+
+  (func $pas_write_char (param $value i64)
+    (call $pas_put (i32.const 1) (i32.wrap_i64 (local.get $value))))
+
+  (func $pas_write_boolean (param $value i64)
+    (call $pas_put_boolean (i32.const 1) (local.get $value)))
+
+  (func $pas_write_string (param $chars i32) (param $length i64)
+    (local $i i64)
+    (block $done
+      (loop $next
+        (br_if $done (i64.ge_u (local.get $i) (local.get $length)))
+        (call $pas_put (i32.const 1) (i32.wrap_i64 (i64.load (i32.add (local.get $chars) (i32.wrap_i64 (i64.shl (local.get $i) (i64.const 3)))))))
+        (local.set $i (i64.add (local.get $i) (i64.const 1)))
+        (br $next))))
+
+  (func $pas_writeln
+    (call $pas_put (i32.const 1) (i32.const 10)))
+
+  ;; Field widths
+
+  ;; Checks that a field width or number of decimals is at least 1, giving
+  ;; it as a count of characters
+  (func $pas_field (param $value i64) (param $site i32) (result i32)
+    (if (i64.lt_s (local.get $value) (i64.const 1))
+      (then
+        (call $pas_fail_begin)
+        (call $pas_put_text (i32.const 2) (string "Field widths and decimals must be at least 1, got "))
+        (call $pas_put_integer (i32.const 2) (local.get $value))
+        (call $pas_fail_end (local.get $site))))
+    (if (i64.gt_s (local.get $value) (i64.const 0x7fffffff))
+      (then (return (i32.const 0x7fffffff))))
+    (i32.wrap_i64 (local.get $value)))
+
+  ;; Writes the spaces that right-align a number of characters in a field
+  (func $pas_pad (param $width i32) (param $length i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_s (local.get $length) (local.get $width)))
+        (call $pas_put (i32.const 1) (i32.const 32))
+        (local.set $length (i32.add (local.get $length) (i32.const 1)))
+        (br $next))))
+
+  ;; Writes text in a field, cut to its first characters if the field is
+  ;; narrower
+  (func $pas_fit (param $text i32) (param $length i32) (param $width i32)
+    (if (i32.lt_s (local.get $width) (local.get $length))
+      (then (local.set $length (local.get $width))))
+    (call $pas_pad (local.get $width) (local.get $length))
+    (call $pas_put_text (i32.const 1) (local.get $text) (local.get $length)))
+
+  ;; The number of characters an integer is written with
+  (func $pas_integer_length (param $value i64) (result i32)
+    (local $magnitude i64) (local $length i32)
+    (local.set $magnitude (local.get $value))
+    (if (i64.lt_s (local.get $value) (i64.const 0))
+      (then
+        (local.set $length (i32.const 1))
+        (local.set $magnitude (i64.sub (i64.const 0) (local.get $value)))))
+    (loop $digit
+      (local.set $length (i32.add (local.get $length) (i32.const 1)))
+      (local.set $magnitude (i64.div_u (local.get $magnitude) (i64.const 10)))
+      (br_if $digit (i64.ne (local.get $magnitude) (i64.const 0))))
+    (local.get $length))
+
+  (func $pas_write_integer_width (param $value i64) (param $width i64) (param $site i32)
+    (call $pas_pad (call $pas_field (local.get $width) (local.get $site)) (call $pas_integer_length (local.get $value)))
+    (call $pas_put_integer (i32.const 1) (local.get $value)))
+
+  (func $pas_write_char_width (param $value i64) (param $width i64) (param $site i32)
+    (call $pas_pad (call $pas_field (local.get $width) (local.get $site)) (i32.const 1))
+    (call $pas_put (i32.const 1) (i32.wrap_i64 (local.get $value))))
+
+  (func $pas_write_boolean_width (param $value i64) (param $width i64) (param $site i32)
+    (local $field i32)
+    (local.set $field (call $pas_field (local.get $width) (local.get $site)))
+    (if (i64.eqz (local.get $value))
+      (then (call $pas_fit (string "false") (local.get $field)))
+      (else (call $pas_fit (string "true") (local.get $field)))))
+
+  (func $pas_write_string_width (param $chars i32) (param $count i64) (param $width i64) (param $site i32)
+    (local $field i32) (local $length i32)
+    (local.set $length (i32.wrap_i64 (local.get $count)))
+    (local.set $field (call $pas_field (local.get $width) (local.get $site)))
+    (if (i32.lt_s (local.get $field) (local.get $length))
+      (then (local.set $length (local.get $field))))
+    (call $pas_pad (local.get $field) (local.get $length))
+    (call $pas_write_string (local.get $chars) (i64.extend_i32_u (local.get $length))))
+
+  ;; Writes NaN, inf or -inf in a field, giving whether the real is one of
+  ;; them
+  (func $pas_put_special (param $value f64) (param $width i32) (result i32)
+    (if (f64.ne (local.get $value) (local.get $value))
+      (then (call $pas_pad (local.get $width) (i32.const 3)))
+      (else
+        (if (f64.ne (f64.abs (local.get $value)) (f64.const inf))
+          (then (return (i32.const 0))))
+        (call $pas_pad (local.get $width)
+          (i32.add (i32.const 3) (f64.lt (local.get $value) (f64.const 0))))))
+    (call $pas_put_real (i32.const 1) (local.get $value))
+    (i32.const 1))
+
+  ;; Reals are written with a number of digits exactly, the real, positive
+  ;; and finite, being r / s from 0.1 up to 1 times a power of ten. Zero is
+  ;; 0 / 1 times 10. The digits are generated twice, first to find how they
+  ;; round, with r saved at 512.
+
+  ;; Whether the last digits generated round up
+  (global $pas_round_up (mut i32) (i32.const 0))
+
+  ;; Sets r and s to a real, giving the power of ten
+  (func $pas_exact (param $value f64) (result i32)
+    (local $bits i64) (local $field i32) (local $mantissa i64) (local $exponent i32) (local $point i32)
+    (call $pas_big_set (i32.const 320) (i64.const 1))
+    (if (f64.eq (local.get $value) (f64.const 0))
+      (then
+        (call $pas_big_set (i32.const 128) (i64.const 0))
+        (return (i32.const 1))))
+    (local.set $bits (i64.reinterpret_f64 (local.get $value)))
+    (local.set $field (i32.wrap_i64 (i64.shr_u (local.get $bits) (i64.const 52))))
+    (local.set $mantissa (i64.and (local.get $bits) (i64.const 0xfffffffffffff)))
+    (if (local.get $field)
+      (then
+        (local.set $mantissa (i64.or (local.get $mantissa) (i64.const 0x10000000000000)))
+        (local.set $exponent (i32.sub (local.get $field) (i32.const 1075))))
+      (else (local.set $exponent (i32.const -1074))))
+    (call $pas_big_set (i32.const 128) (local.get $mantissa))
+    (if (i32.ge_s (local.get $exponent) (i32.const 0))
+      (then (call $pas_big_shl (i32.const 128) (local.get $exponent)))
+      (else (call $pas_big_shl (i32.const 320) (i32.sub (i32.const 0) (local.get $exponent)))))
+    ;; the power of ten above the real, estimated as $pas_put_real does
+    (local.set $point (i32.trunc_f64_s (f64.ceil (f64.sub
+      (f64.mul (f64.convert_i32_s (i32.sub (i32.add (local.get $exponent) (i32.const 63))
+                                           (i32.wrap_i64 (i64.clz (local.get $mantissa)))))
+               (f64.const 0.30102999566398120))
+      (f64.const 1e-10)))))
+    (if (i32.ge_s (local.get $point) (i32.const 0))
+      (then (call $pas_big_pow10 (i32.const 320) (local.get $point)))
+      (else (call $pas_big_pow10 (i32.const 128) (i32.sub (i32.const 0) (local.get $point)))))
+    ;; an estimate one too low leaves r / s at 1 or above
+    (if (i32.ge_s (call $pas_big_compare (i32.const 128) (i32.const 320)) (i32.const 0))
+      (then
+        (call $pas_big_mul (i32.const 320) (i64.const 10) (i64.const 0))
+        (local.set $point (i32.add (local.get $point) (i32.const 1)))))
+    (local.get $point))
+
+  ;; Multiplies r by 10 and takes the next digit of r / s out of it
+  (func $pas_digit (result i32)
+    (local $digit i32)
+    (call $pas_big_mul (i32.const 128) (i64.const 10) (i64.const 0))
+    (block $done
+      (loop $next
+        (br_if $done (i32.lt_s (call $pas_big_compare (i32.const 128) (i32.const 320)) (i32.const 0)))
+        (call $pas_big_sub (i32.const 128) (i32.const 320))
+        (local.set $digit (i32.add (local.get $digit) (i32.const 1)))
+        (br $next)))
+    (local.get $digit))
+
+  ;; Generates a number of digits, giving the last of them that is not 9,
+  ;; -1 if there is none, and setting whether they round up: when the rest
+  ;; is above half of the last one, or half with the last one odd
+  (func $pas_rounding (param $count i32) (result i32)
+    (local $i i32) (local $digit i32) (local $last i32)
+    (memory.copy (i32.const 512) (i32.const 128) (i32.const 192))
+    (local.set $last (i32.const -1))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_s (local.get $i) (local.get $count)))
+        (local.set $digit (call $pas_digit))
+        (if (i32.ne (local.get $digit) (i32.const 9))
+          (then (local.set $last (local.get $i))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (call $pas_big_add (i32.const 896) (i32.const 128) (i32.const 128))
+    (global.set $pas_round_up (i32.gt_s
+      (i32.add (i32.and (local.get $digit) (i32.const 1)) (call $pas_big_compare (i32.const 896) (i32.const 320)))
+      (i32.const 0)))
+    (local.get $last))
+
+  ;; Writes a character of the digits, then the point after the first
+  ;; characters, giving the number of characters written
+  (func $pas_put_digit (param $c i32) (param $written i32) (param $point i32) (result i32)
+    (call $pas_put (i32.const 1) (local.get $c))
+    (local.set $written (i32.add (local.get $written) (i32.const 1)))
+    (if (i32.eq (local.get $written) (local.get $point))
+      (then (call $pas_put (i32.const 1) (i32.const 46))))
+    (local.get $written))
+
+  ;; Writes the digits $pas_rounding generated, rounded as it found, and a
+  ;; point after the first characters. A 1 comes first when all of them
+  ;; roll over.
+  (func $pas_put_digits (param $count i32) (param $last i32) (param $point i32)
+    (local $i i32) (local $digit i32) (local $written i32)
+    (memory.copy (i32.const 128) (i32.const 512) (i32.const 192))
+    (if (i32.and (global.get $pas_round_up) (i32.lt_s (local.get $last) (i32.const 0)))
+      (then (local.set $written (call $pas_put_digit (i32.const 49) (local.get $written) (local.get $point)))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_s (local.get $i) (local.get $count)))
+        (local.set $digit (call $pas_digit))
+        (if (global.get $pas_round_up)
+          (then
+            (if (i32.lt_s (local.get $last) (local.get $i))
+              (then (local.set $digit (i32.const 0))))
+            (if (i32.eq (local.get $last) (local.get $i))
+              (then (local.set $digit (i32.add (local.get $digit) (i32.const 1)))))))
+        (local.set $written (call $pas_put_digit (i32.add (i32.const 48) (local.get $digit)) (local.get $written) (local.get $point)))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next))))
+
+  ;; Writes a real in floating-point form: a sign, one digit before the
+  ;; point and as many after it as fill the field, then an exponent of 2
+  ;; digits or more
+  (func $pas_write_real_width (param $value f64) (param $width i64) (param $site i32)
+    (local $field i32) (local $count i32) (local $exponent i32) (local $last i32)
+    (local.set $field (call $pas_field (local.get $width) (local.get $site)))
+    (if (call $pas_put_special (local.get $value) (local.get $field))
+      (then (return)))
+    ;; the field is 8 characters at least, 6 of them not digits
+    (if (i32.lt_s (local.get $field) (i32.const 8))
+      (then (local.set $field (i32.const 8))))
+    (local.set $count (i32.sub (local.get $field) (i32.const 6)))
+    (call $pas_put (i32.const 1) (select (i32.const 45) (i32.const 32) (f64.lt (local.get $value) (f64.const 0))))
+    (local.set $exponent (i32.sub (call $pas_exact (f64.abs (local.get $value))) (i32.const 1)))
+    (local.set $last (call $pas_rounding (local.get $count)))
+    (if (i32.and (global.get $pas_round_up) (i32.lt_s (local.get $last) (i32.const 0)))
+      (then
+        ;; all the digits roll over to 1 and zeros, a power of ten higher
+        (local.set $count (i32.sub (local.get $count) (i32.const 1)))
+        (local.set $exponent (i32.add (local.get $exponent) (i32.const 1)))))
+    (call $pas_put_digits (local.get $count) (local.get $last) (i32.const 1))
+    (call $pas_put (i32.const 1) (i32.const 69))
+    (call $pas_put (i32.const 1) (select (i32.const 45) (i32.const 43) (i32.lt_s (local.get $exponent) (i32.const 0))))
+    (if (i32.lt_s (local.get $exponent) (i32.const 0))
+      (then (local.set $exponent (i32.sub (i32.const 0) (local.get $exponent)))))
+    (if (i32.lt_s (local.get $exponent) (i32.const 10))
+      (then (call $pas_put (i32.const 1) (i32.const 48))))
+    (call $pas_put_integer (i32.const 1) (i64.extend_i32_s (local.get $exponent))))
+
+  ;; Writes a real in fixed-point form with a number of decimals
+  (func $pas_write_real_fixed (param $value f64) (param $width i64) (param $decimals i64) (param $site i32)
+    (local $field i32) (local $places i32) (local $negative i32) (local $count i32) (local $last i32)
+    (local $length i32) (local $before i32) (local $i i32)
+    (local.set $field (call $pas_field (local.get $width) (local.get $site)))
+    (local.set $places (call $pas_field (local.get $decimals) (local.get $site)))
+    (if (call $pas_put_special (local.get $value) (local.get $field))
+      (then (return)))
+    (local.set $negative (f64.lt (local.get $value) (f64.const 0)))
+    ;; the digits up to the last decimal, none if the real is below it
+    (local.set $count (i32.add (call $pas_exact (f64.abs (local.get $value))) (local.get $places)))
+    (local.set $last (i32.const -1))
+    (global.set $pas_round_up (i32.const 0))
+    (if (i32.lt_s (local.get $count) (i32.const 0))
+      (then
+        (local.set $count (i32.const 0))
+        (memory.copy (i32.const 512) (i32.const 128) (i32.const 192)))
+      (else (local.set $last (call $pas_rounding (local.get $count)))))
+    ;; one more digit is written when all of them roll over, and 0 when
+    ;; none comes before the point
+    (local.set $length (i32.add (local.get $count)
+      (i32.and (global.get $pas_round_up) (i32.lt_s (local.get $last) (i32.const 0)))))
+    (local.set $before (i32.sub (local.get $length) (local.get $places)))
+    (call $pas_pad (local.get $field)
+      (i32.add (i32.add (select (local.get $before) (i32.const 1) (i32.gt_s (local.get $before) (i32.const 0)))
+                        (local.get $places))
+               (i32.add (i32.const 1) (local.get $negative))))
+    (if (local.get $negative)
+      (then (call $pas_put (i32.const 1) (i32.const 45))))
+    (if (i32.gt_s (local.get $before) (i32.const 0))
+      (then (call $pas_put_digits (local.get $count) (local.get $last) (local.get $before)))
+      (else
+        (call $pas_put (i32.const 1) (i32.const 48))
+        (call $pas_put (i32.const 1) (i32.const 46))
+        (block $done
+          (loop $next
+            (br_if $done (i32.ge_s (local.get $i) (i32.sub (i32.const 0) (local.get $before))))
+            (call $pas_put (i32.const 1) (i32.const 48))
+            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+            (br $next)))
+        (call $pas_put_digits (local.get $count) (local.get $last) (i32.const -1)))))
+
+  (func $pas_exit
+    (call $host_exit (i32.const 0)))
+
+  ;; Runtime errors, written as "Runtime Error " and a message that ends
+  ;; with the text of the place, from the table at $pas_sites
+
+  (func $pas_fail_begin
+    (call $pas_put_text (i32.const 2) (string "Runtime Error ")))
+
+  (func $pas_fail_end (param $site i32)
+    (local $entry i32)
+    (local.set $entry (i32.add (global.get $pas_sites) (i32.mul (local.get $site) (i32.const 8))))
+    (call $pas_put_text (i32.const 2) (i32.load (local.get $entry)) (i32.load offset=4 (local.get $entry)))
+    (call $host_exit (i32.const 1))
+    (unreachable))
+
+  (func $pas_fail (param $text i32) (param $length i32) (param $site i32)
+    (call $pas_fail_begin)
+    (call $pas_put_text (i32.const 2) (local.get $text) (local.get $length))
+    (call $pas_fail_end (local.get $site)))
+
+  ;; Input
+
+  (func $pas_getc (result i32)
+    (local $c i32)
+    (if (i32.ne (global.get $pas_pending) (i32.const -2))
+      (then
+        (local.set $c (global.get $pas_pending))
+        (global.set $pas_pending (i32.const -2))
+        (return (local.get $c))))
+    (call $host_read))
+
+  (func $pas_peek (result i32)
+    (local $c i32)
+    (if (i32.eq (global.get $pas_ahead) (i32.const -2))
+      (then
+        (local.set $c (call $pas_getc))
+        (if (i32.eq (local.get $c) (i32.const 13))
+          (then
+            (local.set $c (call $pas_getc))
+            (if (i32.ne (local.get $c) (i32.const 10))
+              (then
+                (global.set $pas_pending (local.get $c))
+                (local.set $c (i32.const 13))))))
+        (if (i32.and (i32.eq (local.get $c) (i32.const -1)) (global.get $pas_in_line))
+          (then (local.set $c (i32.const 10))))
+        (global.set $pas_ahead (local.get $c))))
+    (global.get $pas_ahead))
+
+  (func $pas_next (result i32)
+    (local $c i32)
+    (local.set $c (call $pas_peek))
+    (global.set $pas_ahead (i32.const -2))
+    (if (i32.ne (local.get $c) (i32.const -1))
+      (then (global.set $pas_in_line (i32.ne (local.get $c) (i32.const 10)))))
+    (local.get $c))
+
+  (func $pas_eof (result i64)
+    (i64.extend_i32_u (i32.eq (call $pas_peek) (i32.const -1))))
+
+  (func $pas_eoln (result i64)
+    (i64.extend_i32_u (i32.or (i32.eq (call $pas_peek) (i32.const 10)) (i32.eq (call $pas_peek) (i32.const -1)))))
+
+  (func $pas_end_of_input (param $site i32)
+    (call $pas_fail (string "Read past the end of the input") (local.get $site)))
+
+  (func $pas_read_char (param $site i32) (result i64)
+    (local $c i32)
+    (local.set $c (call $pas_next))
+    (if (i32.eq (local.get $c) (i32.const -1))
+      (then (call $pas_end_of_input (local.get $site))))
+    (i64.extend_i32_u (select (i32.const 32) (local.get $c) (i32.eq (local.get $c) (i32.const 10)))))
+
+  ;; Whether a character is a sign (0), a digit (1) or in a word (2)
+  (func $pas_accepts (param $kind i32) (param $c i32) (result i32)
+    (if (i32.eqz (local.get $kind))
+      (then (return (i32.or (i32.eq (local.get $c) (i32.const 43)) (i32.eq (local.get $c) (i32.const 45))))))
+    (if (i32.eq (local.get $kind) (i32.const 1))
+      (then (return (i32.lt_u (i32.sub (local.get $c) (i32.const 48)) (i32.const 10)))))
+    (i32.eqz (i32.or (i32.or (i32.eq (local.get $c) (i32.const 32)) (i32.eq (local.get $c) (i32.const 9)))
+                     (i32.or (i32.eq (local.get $c) (i32.const 10)) (i32.eq (local.get $c) (i32.const 13))))))
+
+  ;; Appends the characters of a kind ahead to the word at 16, giving its
+  ;; length
+  (func $pas_take (param $kind i32) (param $length i32) (result i32)
+    (local $c i32)
+    (block $done
+      (loop $next
+        (local.set $c (call $pas_peek))
+        (br_if $done (i32.eq (local.get $c) (i32.const -1)))
+        (br_if $done (i32.eqz (call $pas_accepts (local.get $kind) (local.get $c))))
+        (br_if $done (i32.ge_u (i32.add (local.get $length) (i32.const 1)) (i32.const 64)))
+        (i32.store8 (i32.add (i32.const 16) (local.get $length)) (call $pas_next))
+        (local.set $length (i32.add (local.get $length) (i32.const 1)))
+        (br $next)))
+    (local.get $length))
+
+  ;; Reads the characters of a number, or of the word that stands where one
+  ;; was expected, to 16, giving their count
+  (func $pas_read_number (param $real i32) (param $site i32) (result i32)
+    (local $length i32) (local $c i32)
+    (block $done
+      (loop $next
+        (local.set $c (call $pas_peek))
+        (br_if $done (i32.and (i32.ne (local.get $c) (i32.const 32))
+                              (i32.ge_u (i32.sub (local.get $c) (i32.const 9)) (i32.const 5))))
+        (drop (call $pas_next))
+        (br $next)))
+    (if (i32.eq (call $pas_peek) (i32.const -1))
+      (then (call $pas_end_of_input (local.get $site))))
+    (local.set $length (call $pas_take (i32.const 1) (call $pas_take (i32.const 0) (i32.const 0))))
+    (if (local.get $real)
+      (then
+        (if (i32.eq (call $pas_peek) (i32.const 46))
+          (then
+            (i32.store8 (i32.add (i32.const 16) (local.get $length)) (call $pas_next))
+            (local.set $length (call $pas_take (i32.const 1) (i32.add (local.get $length) (i32.const 1))))))
+        (if (i32.eq (i32.or (call $pas_peek) (i32.const 32)) (i32.const 101))
+          (then
+            (drop (call $pas_next))
+            (i32.store8 (i32.add (i32.const 16) (local.get $length)) (i32.const 101))
+            (local.set $length (call $pas_take (i32.const 1)
+              (call $pas_take (i32.const 0) (i32.add (local.get $length) (i32.const 1)))))))))
+    (if (i32.eqz (local.get $length))
+      (then (local.set $length (call $pas_take (i32.const 2) (i32.const 0)))))
+    (local.get $length))
+
+  (func $pas_invalid_input (param $type i32) (param $type_length i32) (param $length i32) (param $site i32)
+    (call $pas_fail_begin)
+    (call $pas_put_text (i32.const 2) (string "Expected "))
+    (call $pas_put_text (i32.const 2) (local.get $type) (local.get $type_length))
+    (call $pas_put_text (i32.const 2) (string " in the input, got '"))
+    (call $pas_put_text (i32.const 2) (i32.const 16) (local.get $length))
+    (call $pas_put (i32.const 2) (i32.const 39))
+    (call $pas_fail_end (local.get $site)))
+
+  (func $pas_read_integer (param $site i32) (result i64)
+    (local $length i32) (local $i i32) (local $negative i32) (local $digit i64) (local $magnitude i64)
+    (local.set $length (call $pas_read_number (i32.const 0) (local.get $site)))
+    (if (call $pas_accepts (i32.const 0) (i32.load8_u (i32.const 16)))
+      (then
+        (local.set $negative (i32.eq (i32.load8_u (i32.const 16)) (i32.const 45)))
+        (local.set $i (i32.const 1))))
+    (if (i32.ge_u (local.get $i) (local.get $length))
+      (then (call $pas_invalid_input (string "Integer") (local.get $length) (local.get $site))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $length)))
+        (local.set $digit (i64.extend_i32_u (i32.sub (i32.load8_u (i32.add (i32.const 16) (local.get $i))) (i32.const 48))))
+        ;; the magnitude of the lowest integer is one more than the highest
+        (if (i32.or (i64.gt_u (local.get $digit) (i64.const 9))
+                    (i32.or (i64.gt_u (local.get $magnitude) (i64.const 922337203685477580))
+                            (i32.and (i64.eq (local.get $magnitude) (i64.const 922337203685477580))
+                                     (i64.gt_u (local.get $digit) (i64.extend_i32_u (i32.add (i32.const 7) (local.get $negative)))))))
+          (then (call $pas_invalid_input (string "Integer") (local.get $length) (local.get $site))))
+        (local.set $magnitude (i64.add (i64.mul (local.get $magnitude) (i64.const 10)) (local.get $digit)))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (select (i64.sub (i64.const 0) (local.get $magnitude)) (local.get $magnitude) (local.get $negative)))
+
+  ;; Reads a real, rounded once to the nearest. The digits make a number at
+  ;; 128 and a power of ten one at 320, whose quotient is taken to 58 bits
+  ;; and rounded with whether anything remains.
+  (func $pas_read_real (param $site i32) (result f64)
+    (local $length i32) (local $i i32) (local $c i32) (local $negative i32) (local $significant i32)
+    (local $digits i32) (local $exponent i32) (local $power i32) (local $power_negative i32) (local $power_digits i32)
+    (local $shift i32) (local $bit i32) (local $quotient i64) (local $drop i32) (local $rest i64) (local $half i64) (local $value f64)
+    (local.set $length (call $pas_read_number (i32.const 1) (local.get $site)))
+    (call $pas_big_set (i32.const 128) (i64.const 0))
+    (if (call $pas_accepts (i32.const 0) (i32.load8_u (i32.const 16)))
+      (then
+        (local.set $negative (i32.eq (i32.load8_u (i32.const 16)) (i32.const 45)))
+        (local.set $i (i32.const 1))))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $length)))
+        (local.set $c (i32.sub (i32.load8_u (i32.add (i32.const 16) (local.get $i))) (i32.const 48)))
+        (br_if $done (i32.gt_u (local.get $c) (i32.const 9)))
+        (call $pas_big_mul (i32.const 128) (i64.const 10) (i64.extend_i32_u (local.get $c)))
+        (if (i32.or (local.get $significant) (local.get $c))
+          (then (local.set $significant (i32.add (local.get $significant) (i32.const 1)))))
+        (local.set $digits (i32.add (local.get $digits) (i32.const 1)))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (if (i32.and (i32.lt_u (local.get $i) (local.get $length)) (i32.eq (i32.load8_u (i32.add (i32.const 16) (local.get $i))) (i32.const 46)))
+      (then
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (block $done
+          (loop $next
+            (br_if $done (i32.ge_u (local.get $i) (local.get $length)))
+            (local.set $c (i32.sub (i32.load8_u (i32.add (i32.const 16) (local.get $i))) (i32.const 48)))
+            (br_if $done (i32.gt_u (local.get $c) (i32.const 9)))
+            (call $pas_big_mul (i32.const 128) (i64.const 10) (i64.extend_i32_u (local.get $c)))
+            (if (i32.or (local.get $significant) (local.get $c))
+              (then (local.set $significant (i32.add (local.get $significant) (i32.const 1)))))
+            (local.set $exponent (i32.sub (local.get $exponent) (i32.const 1)))
+            (local.set $digits (i32.add (local.get $digits) (i32.const 1)))
+            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+            (br $next)))))
+    (if (i32.eqz (local.get $digits))
+      (then (call $pas_invalid_input (string "Real") (local.get $length) (local.get $site))))
+    (if (i32.and (i32.lt_u (local.get $i) (local.get $length)) (i32.eq (i32.load8_u (i32.add (i32.const 16) (local.get $i))) (i32.const 101)))
+      (then
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (if (i32.and (i32.lt_u (local.get $i) (local.get $length))
+                     (call $pas_accepts (i32.const 0) (i32.load8_u (i32.add (i32.const 16) (local.get $i)))))
+          (then
+            (local.set $power_negative (i32.eq (i32.load8_u (i32.add (i32.const 16) (local.get $i))) (i32.const 45)))
+            (local.set $i (i32.add (local.get $i) (i32.const 1)))))
+        (block $done
+          (loop $next
+            (br_if $done (i32.ge_u (local.get $i) (local.get $length)))
+            (local.set $c (i32.sub (i32.load8_u (i32.add (i32.const 16) (local.get $i))) (i32.const 48)))
+            (br_if $done (i32.gt_u (local.get $c) (i32.const 9)))
+            ;; past any real, however many digits there are
+            (if (i32.lt_u (local.get $power) (i32.const 100000))
+              (then (local.set $power (i32.add (i32.mul (local.get $power) (i32.const 10)) (local.get $c)))))
+            (local.set $power_digits (i32.add (local.get $power_digits) (i32.const 1)))
+            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+            (br $next)))
+        (if (i32.eqz (local.get $power_digits))
+          (then (call $pas_invalid_input (string "Real") (local.get $length) (local.get $site))))))
+    (if (i32.ne (local.get $i) (local.get $length))
+      (then (call $pas_invalid_input (string "Real") (local.get $length) (local.get $site))))
+    (local.set $exponent (i32.add (local.get $exponent)
+      (select (i32.sub (i32.const 0) (local.get $power)) (local.get $power) (local.get $power_negative))))
+    (local.set $value (f64.const 0))
+    (block $rounded
+      (br_if $rounded (i32.or (i32.eqz (local.get $significant))
+                              (i32.lt_s (i32.add (local.get $significant) (local.get $exponent)) (i32.const -324))))
+      (if (i32.gt_s (i32.add (local.get $significant) (local.get $exponent)) (i32.const 310))
+        (then
+          (local.set $value (f64.const inf))
+          (br $rounded)))
+      (call $pas_big_set (i32.const 320) (i64.const 1))
+      (if (i32.ge_s (local.get $exponent) (i32.const 0))
+        (then (call $pas_big_pow10 (i32.const 128) (local.get $exponent)))
+        (else (call $pas_big_pow10 (i32.const 320) (i32.sub (i32.const 0) (local.get $exponent)))))
+      ;; a quotient from 2 to the power of 56 to 2 to the power of 58
+      (local.set $shift (i32.sub (i32.add (i32.const 57) (call $pas_big_bits (i32.const 320)))
+                                 (call $pas_big_bits (i32.const 128))))
+      (if (i32.ge_s (local.get $shift) (i32.const 0))
+        (then (call $pas_big_shl (i32.const 128) (local.get $shift)))
+        (else (call $pas_big_shl (i32.const 320) (i32.sub (i32.const 0) (local.get $shift)))))
+      (memory.copy (i32.const 512) (i32.const 320) (i32.const 192))
+      (call $pas_big_shl (i32.const 512) (i32.const 57))
+      (local.set $bit (i32.const 57))
+      (block $done
+        (loop $next
+          (if (i32.ge_s (call $pas_big_compare (i32.const 128) (i32.const 512)) (i32.const 0))
+            (then
+              (call $pas_big_sub (i32.const 128) (i32.const 512))
+              (local.set $quotient (i64.or (local.get $quotient) (i64.shl (i64.const 1) (i64.extend_i32_u (local.get $bit)))))))
+          (br_if $done (i32.eqz (local.get $bit)))
+          (call $pas_big_shr1 (i32.const 512))
+          (local.set $bit (i32.sub (local.get $bit) (i32.const 1)))
+          (br $next)))
+      ;; the bits past 53, or past the least subnormal real
+      (local.set $drop (i32.sub (i32.const 11) (i32.wrap_i64 (i64.clz (local.get $quotient)))))
+      (if (i32.lt_s (local.get $drop) (i32.add (i32.const -1074) (local.get $shift)))
+        (then (local.set $drop (i32.add (i32.const -1074) (local.get $shift)))))
+      (if (i32.gt_s (local.get $drop) (i32.const 60))
+        (then (local.set $drop (i32.const 60))))
+      (local.set $half (i64.shl (i64.const 1) (i64.extend_i32_u (i32.sub (local.get $drop) (i32.const 1)))))
+      (local.set $rest (i64.and (local.get $quotient) (i64.sub (i64.shl (local.get $half) (i64.const 1)) (i64.const 1))))
+      (local.set $quotient (i64.shr_u (local.get $quotient) (i64.extend_i32_u (local.get $drop))))
+      (if (i32.or (i64.gt_u (local.get $rest) (local.get $half))
+                  (i32.and (i64.eq (local.get $rest) (local.get $half))
+                           (i32.or (i32.ne (call $pas_big_bits (i32.const 128)) (i32.const 0))
+                                   (i32.wrap_i64 (i64.and (local.get $quotient) (i64.const 1))))))
+        (then (local.set $quotient (i64.add (local.get $quotient) (i64.const 1)))))
+      (local.set $value (call $pas_ldexp (f64.convert_i64_u (local.get $quotient))
+                                         (i32.sub (local.get $drop) (local.get $shift)))))
+    (f64.copysign (local.get $value) (select (f64.const -1) (f64.const 1) (local.get $negative))))
+
+  (func $pas_readln (param $site i32)
+    (local $c i32)
+    (loop $next
+      (local.set $c (call $pas_next))
+      (if (i32.eq (local.get $c) (i32.const -1))
+        (then (call $pas_end_of_input (local.get $site))))
+      (br_if $next (i32.ne (local.get $c) (i32.const 10)))))
+
+  ;; Arithmetic
+
+  (func $pas_div (param $left i64) (param $right i64) (param $site i32) (result i64)
+    (if (i64.eqz (local.get $right))
+      (then (call $pas_fail (string "Division by zero") (local.get $site))))
+    (if (i64.eq (local.get $right) (i64.const -1))
+      (then (return (i64.sub (i64.const 0) (local.get $left)))))
+    (i64.div_s (local.get $left) (local.get $right)))
+
+  (func $pas_mod (param $left i64) (param $right i64) (param $site i32) (result i64)
+    (local $rest i64)
+    (if (i64.eqz (local.get $right))
+      (then (call $pas_fail (string "Division by zero") (local.get $site))))
+    (local.set $rest (i64.rem_s (local.get $left) (local.get $right)))
+    (if (result i64) (i64.lt_s (local.get $rest) (i64.const 0))
+      (then (select (i64.sub (local.get $rest) (local.get $right)) (i64.add (local.get $rest) (local.get $right))
+                    (i64.lt_s (local.get $right) (i64.const 0))))
+      (else (local.get $rest))))
+
+  (func $pas_rdiv (param $left f64) (param $right f64) (param $site i32) (result f64)
+    (if (f64.eq (local.get $right) (f64.const 0))
+      (then (call $pas_fail (string "Division by zero") (local.get $site))))
+    (f64.div (local.get $left) (local.get $right)))
+
+  ;; The integer operations of {$Q+}, which stop the program where the
+  ;; others wrap around
+
+  (func $pas_overflow (param $site i32)
+    (call $pas_fail (string "Integer overflow") (local.get $site)))
+
+  (func $pas_add_checked (param $left i64) (param $right i64) (param $site i32) (result i64)
+    (local $result i64)
+    (local.set $result (i64.add (local.get $left) (local.get $right)))
+    (if (i64.lt_s (i64.and (i64.xor (local.get $left) (local.get $result)) (i64.xor (local.get $right) (local.get $result)))
+                  (i64.const 0))
+      (then (call $pas_overflow (local.get $site))))
+    (local.get $result))
+
+  (func $pas_sub_checked (param $left i64) (param $right i64) (param $site i32) (result i64)
+    (local $result i64)
+    (local.set $result (i64.sub (local.get $left) (local.get $right)))
+    (if (i64.lt_s (i64.and (i64.xor (local.get $left) (local.get $right)) (i64.xor (local.get $left) (local.get $result)))
+                  (i64.const 0))
+      (then (call $pas_overflow (local.get $site))))
+    (local.get $result))
+
+  (func $pas_mul_checked (param $left i64) (param $right i64) (param $site i32) (result i64)
+    (local $result i64)
+    (local.set $result (i64.mul (local.get $left) (local.get $right)))
+    (if (i64.eqz (local.get $left))
+      (then (return (local.get $result))))
+    ;; dividing the only product that does not fit by -1 would trap
+    (if (i32.and (i64.eq (local.get $left) (i64.const -1)) (i64.eq (local.get $right) (i64.const 0x8000000000000000)))
+      (then (call $pas_overflow (local.get $site))))
+    (if (i64.ne (i64.div_s (local.get $result) (local.get $left)) (local.get $right))
+      (then (call $pas_overflow (local.get $site))))
+    (local.get $result))
+
+  (func $pas_div_checked (param $left i64) (param $right i64) (param $site i32) (result i64)
+    (if (i32.and (i64.eq (local.get $left) (i64.const 0x8000000000000000)) (i64.eq (local.get $right) (i64.const -1)))
+      (then (call $pas_overflow (local.get $site))))
+    (call $pas_div (local.get $left) (local.get $right) (local.get $site)))
+
+  (func $pas_neg_checked (param $value i64) (param $site i32) (result i64)
+    (call $pas_sub_checked (i64.const 0) (local.get $value) (local.get $site)))
+
+  (func $pas_abs_checked (param $value i64) (param $site i32) (result i64)
+    (if (result i64) (i64.lt_s (local.get $value) (i64.const 0))
+      (then (call $pas_neg_checked (local.get $value) (local.get $site)))
+      (else (local.get $value))))
+
+  (func $pas_abs (param $value i64) (result i64)
+    (select (i64.sub (i64.const 0) (local.get $value)) (local.get $value) (i64.lt_s (local.get $value) (i64.const 0))))
+
+  ;; Halves are rounded away from 0, reals out of the range of integers
+  ;; saturating and NaN becoming 0
+  (func $pas_round (param $value f64) (result i64)
+    (local $whole f64)
+    (local.set $whole (f64.trunc (local.get $value)))
+    (if (f64.ge (f64.abs (f64.sub (local.get $value) (local.get $whole))) (f64.const 0.5))
+      (then (local.set $whole (f64.add (local.get $whole) (f64.copysign (f64.const 1) (local.get $value))))))
+    (i64.trunc_sat_f64_s (local.get $whole)))
+
+  (func $pas_domain (param $function i32) (param $length i32) (param $argument f64) (param $site i32)
+    (call $pas_fail_begin)
+    (call $pas_put (i32.const 2) (i32.const 39))
+    (call $pas_put_text (i32.const 2) (local.get $function) (local.get $length))
+    (call $pas_put_text (i32.const 2) (string "' is not defined for "))
+    (call $pas_put_real (i32.const 2) (local.get $argument))
+    (call $pas_fail_end (local.get $site)))
+
+  (func $pas_sqrt (param $value f64) (param $site i32) (result f64)
+    (if (f64.lt (local.get $value) (f64.const 0))
+      (then (call $pas_domain (string "sqrt") (local.get $value) (local.get $site))))
+    (f64.sqrt (local.get $value)))
+
+  (func $pas_ln (param $value f64) (param $site i32) (result f64)
+    (if (f64.le (local.get $value) (f64.const 0))
+      (then (call $pas_domain (string "ln") (local.get $value) (local.get $site))))
+    (call $pas_log (local.get $value)))
+
+  ;; The standard functions WebAssembly has no instruction for, after the
+  ;; ones of fdlibm, within an ulp or so of the C library
+
+  ;; A real times 2 to the power of an exponent
+  (func $pas_ldexp (param $value f64) (param $exponent i32) (result f64)
+    (block $done
+      (loop $next
+        (br_if $done (i32.le_s (local.get $exponent) (i32.const 1023)))
+        (local.set $value (f64.mul (local.get $value) (f64.const 0x1p1023)))
+        (local.set $exponent (i32.sub (local.get $exponent) (i32.const 1023)))
+        (br $next)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_s (local.get $exponent) (i32.const -1022)))
+        (local.set $value (f64.mul (local.get $value) (f64.const 0x1p-1022)))
+        (local.set $exponent (i32.add (local.get $exponent) (i32.const 1022)))
+        (br $next)))
+    (f64.mul (local.get $value)
+      (f64.reinterpret_i64 (i64.shl (i64.extend_i32_s (i32.add (local.get $exponent) (i32.const 1023))) (i64.const 52)))))
+
+  (func $pas_exp (param $x f64) (result f64)
+    (local $k f64) (local $hi f64) (local $lo f64) (local $r f64) (local $t f64) (local $c f64)
+    (if (f64.ne (local.get $x) (local.get $x))
+      (then (return (local.get $x))))
+    (if (f64.gt (local.get $x) (f64.const 709.782712893383973096))
+      (then (return (f64.const inf))))
+    (if (f64.lt (local.get $x) (f64.const -745.13321910194110842))
+      (then (return (f64.const 0))))
+    ;; x = k ln 2 + r, with r at most half of ln 2
+    (local.set $k (f64.nearest (f64.mul (local.get $x) (f64.const 1.44269504088896338700))))
+    (local.set $hi (f64.sub (local.get $x) (f64.mul (local.get $k) (f64.const 6.93147180369123816490e-01))))
+    (local.set $lo (f64.mul (local.get $k) (f64.const 1.90821492927058770002e-10)))
+    (local.set $r (f64.sub (local.get $hi) (local.get $lo)))
+    (local.set $t (f64.mul (local.get $r) (local.get $r)))
+    (local.set $c (f64.sub (local.get $r) (f64.mul (local.get $t)
+      (f64.add (f64.const 1.66666666666666019037e-01) (f64.mul (local.get $t)
+      (f64.add (f64.const -2.77777777770155933842e-03) (f64.mul (local.get $t)
+      (f64.add (f64.const 6.61375632143793436117e-05) (f64.mul (local.get $t)
+      (f64.add (f64.const -1.65339022054652515390e-06) (f64.mul (local.get $t) (f64.const 4.13813679705723846039e-08))))))))))))
+    (call $pas_ldexp
+      (f64.sub (f64.const 1) (f64.sub (f64.sub (local.get $lo)
+        (f64.div (f64.mul (local.get $r) (local.get $c)) (f64.sub (f64.const 2) (local.get $c)))) (local.get $hi)))
+      (i32.trunc_f64_s (local.get $k))))
+
+  ;; The natural logarithm of a positive real
+  (func $pas_log (param $x f64) (result f64)
+    (local $bits i64) (local $k i32) (local $f f64) (local $s f64) (local $z f64) (local $w f64)
+    (local $r f64) (local $half f64) (local $dk f64)
+    (if (i32.or (f64.ne (local.get $x) (local.get $x)) (f64.eq (local.get $x) (f64.const inf)))
+      (then (return (local.get $x))))
+    ;; subnormals are scaled to normal reals first
+    (if (f64.lt (local.get $x) (f64.const 0x1p-1022))
+      (then
+        (local.set $x (f64.mul (local.get $x) (f64.const 0x1p54)))
+        (local.set $k (i32.const -54))))
+    ;; x = 2^k m, with m between the square root of 2 and its half
+    (local.set $bits (i64.reinterpret_f64 (local.get $x)))
+    (local.set $k (i32.add (local.get $k) (i32.sub (i32.wrap_i64 (i64.shr_u (local.get $bits) (i64.const 52))) (i32.const 1023))))
+    (local.set $x (f64.reinterpret_i64 (i64.or (i64.and (local.get $bits) (i64.const 0x000fffffffffffff)) (i64.const 0x3ff0000000000000))))
+    (if (f64.gt (local.get $x) (f64.const 1.41421356237309504880))
+      (then
+        (local.set $x (f64.mul (local.get $x) (f64.const 0.5)))
+        (local.set $k (i32.add (local.get $k) (i32.const 1)))))
+    (local.set $f (f64.sub (local.get $x) (f64.const 1)))
+    (local.set $s (f64.div (local.get $f) (f64.add (f64.const 2) (local.get $f))))
+    (local.set $z (f64.mul (local.get $s) (local.get $s)))
+    (local.set $w (f64.mul (local.get $z) (local.get $z)))
+    (local.set $r (f64.add
+      (f64.mul (local.get $w)
+        (f64.add (f64.const 3.999999999940941908e-01) (f64.mul (local.get $w)
+        (f64.add (f64.const 2.222219843214978396e-01) (f64.mul (local.get $w) (f64.const 1.531383769920937332e-01))))))
+      (f64.mul (local.get $z)
+        (f64.add (f64.const 6.666666666666735130e-01) (f64.mul (local.get $w)
+        (f64.add (f64.const 2.857142874366239149e-01) (f64.mul (local.get $w)
+        (f64.add (f64.const 1.818357216161805012e-01) (f64.mul (local.get $w) (f64.const 1.479819860511658591e-01))))))))))
+    (local.set $half (f64.mul (f64.const 0.5) (f64.mul (local.get $f) (local.get $f))))
+    (local.set $dk (f64.convert_i32_s (local.get $k)))
+    (f64.sub (f64.mul (local.get $dk) (f64.const 6.93147180369123816490e-01))
+      (f64.sub (f64.sub (local.get $half)
+        (f64.add (f64.mul (local.get $s) (f64.add (local.get $half) (local.get $r)))
+                 (f64.mul (local.get $dk) (f64.const 1.90821492927058770002e-10))))
+        (local.get $f))))
+
+  ;; Sine and cosine of y + tail on the quarter of a turn around 0, the tail
+  ;; being below an ulp of y
+  (func $pas_sin_kernel (param $x f64) (param $tail f64) (result f64)
+    (local $z f64) (local $v f64) (local $r f64)
+    (local.set $z (f64.mul (local.get $x) (local.get $x)))
+    (local.set $v (f64.mul (local.get $z) (local.get $x)))
+    (local.set $r
+      (f64.add (f64.const 8.33333333332248946124e-03) (f64.mul (local.get $z)
+      (f64.add (f64.const -1.98412698298579493134e-04) (f64.mul (local.get $z)
+      (f64.add (f64.const 2.75573137070700676789e-06) (f64.mul (local.get $z)
+      (f64.add (f64.const -2.50507602534068634195e-08) (f64.mul (local.get $z) (f64.const 1.58969099521155010221e-10))))))))))
+    (f64.sub (local.get $x)
+      (f64.sub (f64.sub (f64.mul (local.get $z) (f64.sub (f64.mul (f64.const 0.5) (local.get $tail)) (f64.mul (local.get $v) (local.get $r))))
+                        (local.get $tail))
+               (f64.mul (local.get $v) (f64.const -1.66666666666666324348e-01)))))
+
+  (func $pas_cos_kernel (param $x f64) (param $tail f64) (result f64)
+    (local $z f64) (local $r f64) (local $half f64) (local $w f64)
+    (local.set $z (f64.mul (local.get $x) (local.get $x)))
+    (local.set $r (f64.mul (local.get $z)
+      (f64.add (f64.const 4.16666666666666019037e-02) (f64.mul (local.get $z)
+      (f64.add (f64.const -1.38888888888741095749e-03) (f64.mul (local.get $z)
+      (f64.add (f64.const 2.48015872894767294178e-05) (f64.mul (local.get $z)
+      (f64.add (f64.const -2.75573143513906633035e-07) (f64.mul (local.get $z)
+      (f64.add (f64.const 2.08757232129817482790e-09) (f64.mul (local.get $z) (f64.const -1.13596475577881948265e-11)))))))))))))
+    (local.set $half (f64.mul (f64.const 0.5) (local.get $z)))
+    (local.set $w (f64.sub (f64.const 1) (local.get $half)))
+    (f64.add (local.get $w) (f64.add (f64.sub (f64.sub (f64.const 1) (local.get $w)) (local.get $half))
+                                     (f64.sub (f64.mul (local.get $z) (local.get $r)) (f64.mul (local.get $x) (local.get $tail))))))
+
+  ;; The sine of x plus a number of quarter turns. x is taken to within a
+  ;; quarter turn of 0 less the nearest multiple of one, the quarter turn
+  ;; in four reals making 212 bits and every product of it exact after
+  ;; Dekker, which is exact enough while the multiple fits 53 bits.
+  (func $pas_turn (param $x f64) (param $quarters i32) (result f64)
+    (local $n f64) (local $n_high f64) (local $n_low f64) (local $i i32) (local $part f64) (local $high f64)
+    (local $low f64) (local $product f64) (local $error f64) (local $y f64) (local $tail f64) (local $sum f64)
+    (local $bias f64) (local $quadrant i32)
+    ;; infinities and NaN
+    (if (f64.ne (f64.sub (local.get $x) (local.get $x)) (f64.const 0))
+      (then (return (f64.sub (local.get $x) (local.get $x)))))
+    (local.set $n (f64.nearest (f64.mul (local.get $x) (f64.const 6.36619772367581382433e-01))))
+    (local.set $high (f64.mul (local.get $n) (f64.const 134217729)))
+    (local.set $n_high (f64.sub (local.get $high) (f64.sub (local.get $high) (local.get $n))))
+    (local.set $n_low (f64.sub (local.get $n) (local.get $n_high)))
+    (local.set $y (local.get $x))
+    (block $done
+      (loop $next
+        (br_if $done (i32.eq (local.get $i) (i32.const 4)))
+        (local.set $part
+          (select (f64.const 0x1.921fb54442d18p+0)
+            (select (f64.const 0x1.1a62633145c07p-54)
+              (select (f64.const -0x1.f1976b7ed8fbcp-110) (f64.const 0x1.4cf98e804177dp-164) (i32.eq (local.get $i) (i32.const 2)))
+              (i32.eq (local.get $i) (i32.const 1)))
+            (i32.eqz (local.get $i))))
+        (local.set $high (f64.mul (local.get $part) (f64.const 134217729)))
+        (local.set $high (f64.sub (local.get $high) (f64.sub (local.get $high) (local.get $part))))
+        (local.set $low (f64.sub (local.get $part) (local.get $high)))
+        (local.set $product (f64.mul (local.get $n) (local.get $part)))
+        (local.set $error (f64.add (f64.add (f64.add (f64.sub (f64.mul (local.get $n_high) (local.get $high)) (local.get $product))
+                                                     (f64.mul (local.get $n_high) (local.get $low)))
+                                            (f64.mul (local.get $n_low) (local.get $high)))
+                                   (f64.mul (local.get $n_low) (local.get $low))))
+        ;; y + tail less product + error
+        (local.set $sum (f64.sub (local.get $y) (local.get $product)))
+        (local.set $bias (f64.sub (local.get $sum) (local.get $y)))
+        (local.set $tail (f64.add (f64.sub (local.get $tail) (local.get $error))
+                                  (f64.sub (f64.sub (local.get $y) (f64.sub (local.get $sum) (local.get $bias)))
+                                           (f64.add (local.get $product) (local.get $bias)))))
+        (local.set $y (f64.add (local.get $sum) (local.get $tail)))
+        (local.set $tail (f64.sub (local.get $tail) (f64.sub (local.get $y) (local.get $sum))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (local.set $quadrant (i32.and (i32.add (i32.wrap_i64 (i64.trunc_sat_f64_s (local.get $n))) (local.get $quarters)) (i32.const 3)))
+    (if (i32.eqz (local.get $quadrant))
+      (then (return (call $pas_sin_kernel (local.get $y) (local.get $tail)))))
+    (if (i32.eq (local.get $quadrant) (i32.const 1))
+      (then (return (call $pas_cos_kernel (local.get $y) (local.get $tail)))))
+    (if (i32.eq (local.get $quadrant) (i32.const 2))
+      (then (return (f64.neg (call $pas_sin_kernel (local.get $y) (local.get $tail))))))
+    (f64.neg (call $pas_cos_kernel (local.get $y) (local.get $tail))))
+
+  (func $pas_sin (param $x f64) (result f64)
+    (call $pas_turn (local.get $x) (i32.const 0)))
+
+  (func $pas_cos (param $x f64) (result f64)
+    (call $pas_turn (local.get $x) (i32.const 1)))
+
+  (func $pas_atan (param $x f64) (result f64)
+    (local $a f64) (local $hi f64) (local $lo f64) (local $z f64) (local $w f64) (local $s f64)
+    (if (f64.ne (local.get $x) (local.get $x))
+      (then (return (local.get $x))))
+    (local.set $a (f64.abs (local.get $x)))
+    (if (f64.ge (local.get $a) (f64.const 0x1p66))
+      (then (return (f64.copysign (f64.add (f64.const 1.57079632679489655800) (f64.const 6.12323399573676603587e-17))
+                                  (local.get $x)))))
+    ;; the argument is reduced around 0.5, 1, 1.5 or infinity
+    (if (f64.ge (local.get $a) (f64.const 0.4375))
+      (then
+        (if (f64.lt (local.get $a) (f64.const 1.1875))
+          (then
+            (if (f64.lt (local.get $a) (f64.const 0.6875))
+              (then
+                (local.set $hi (f64.const 4.63647609000806093515e-01))
+                (local.set $lo (f64.const 2.26987774529616870924e-17))
+                (local.set $a (f64.div (f64.sub (f64.mul (f64.const 2) (local.get $a)) (f64.const 1))
+                                       (f64.add (f64.const 2) (local.get $a)))))
+              (else
+                (local.set $hi (f64.const 7.85398163397448278999e-01))
+                (local.set $lo (f64.const 3.06161699786838301793e-17))
+                (local.set $a (f64.div (f64.sub (local.get $a) (f64.const 1)) (f64.add (local.get $a) (f64.const 1)))))))
+          (else
+            (if (f64.lt (local.get $a) (f64.const 2.4375))
+              (then
+                (local.set $hi (f64.const 9.82793723247329054082e-01))
+                (local.set $lo (f64.const 1.39033110312309984516e-17))
+                (local.set $a (f64.div (f64.sub (local.get $a) (f64.const 1.5))
+                                       (f64.add (f64.const 1) (f64.mul (f64.const 1.5) (local.get $a))))))
+              (else
+                (local.set $hi (f64.const 1.57079632679489655800))
+                (local.set $lo (f64.const 6.12323399573676603587e-17))
+                (local.set $a (f64.div (f64.const -1) (local.get $a)))))))))
+    (local.set $z (f64.mul (local.get $a) (local.get $a)))
+    (local.set $w (f64.mul (local.get $z) (local.get $z)))
+    (local.set $s (f64.add
+      (f64.mul (local.get $z)
+        (f64.add (f64.const 3.33333333333329318027e-01) (f64.mul (local.get $w)
+        (f64.add (f64.const 1.42857142725034663711e-01) (f64.mul (local.get $w)
+        (f64.add (f64.const 9.09088713343650656196e-02) (f64.mul (local.get $w)
+        (f64.add (f64.const 6.66107313738753120669e-02) (f64.mul (local.get $w)
+        (f64.add (f64.const 4.97687799461593236017e-02) (f64.mul (local.get $w) (f64.const 1.62858201153657823623e-02))))))))))))
+      (f64.mul (local.get $w)
+        (f64.add (f64.const -1.99999999998764832476e-01) (f64.mul (local.get $w)
+        (f64.add (f64.const -1.11111104054623557880e-01) (f64.mul (local.get $w)
+        (f64.add (f64.const -7.69187620504482999495e-02) (f64.mul (local.get $w)
+        (f64.add (f64.const -5.83357013379057348645e-02) (f64.mul (local.get $w) (f64.const -3.65315727442169155270e-02))))))))))))
+    (if (f64.eq (local.get $hi) (f64.const 0))
+      (then (return (f64.copysign (f64.sub (local.get $a) (f64.mul (local.get $a) (local.get $s))) (local.get $x)))))
+    (f64.copysign (f64.sub (local.get $hi) (f64.sub (f64.sub (f64.mul (local.get $a) (local.get $s)) (local.get $lo)) (local.get $a)))
+                  (local.get $x)))
+
+  ;; Checks
+
+  (func $pas_check (param $value i64) (param $low i64) (param $high i64) (param $type i32) (param $length i32) (param $site i32)
+    (result i64)
+    (if (i32.or (i64.lt_s (local.get $value) (local.get $low)) (i64.gt_s (local.get $value) (local.get $high)))
+      (then
+        (call $pas_fail_begin)
+        (call $pas_put_text (i32.const 2) (string "The value "))
+        (call $pas_put_integer (i32.const 2) (local.get $value))
+        (call $pas_put_text (i32.const 2) (string " is outside of "))
+        (call $pas_put_text (i32.const 2) (local.get $type) (local.get $length))
+        (call $pas_fail_end (local.get $site))))
+    (local.get $value))
+
+  (func $pas_index (param $index i64) (param $low i64) (param $high i64) (param $site i32) (result i64)
+    (if (i32.or (i64.lt_s (local.get $index) (local.get $low)) (i64.gt_s (local.get $index) (local.get $high)))
+      (then
+        (call $pas_fail_begin)
+        (call $pas_put_text (i32.const 2) (string "Index "))
+        (call $pas_put_integer (i32.const 2) (local.get $index))
+        (call $pas_put_text (i32.const 2) (string " is outside of the bounds "))
+        (call $pas_put_integer (i32.const 2) (local.get $low))
+        (call $pas_put_text (i32.const 2) (string ".."))
+        (call $pas_put_integer (i32.const 2) (local.get $high))
+        (call $pas_fail_end (local.get $site))))
+    (local.get $index))
+
+  ;; `kind` is 'i' for integers, 'c' for characters, 'b' for booleans and
+  ;; 'e' for enumerations, whose literals are in the table at `names` of
+  ;; the address and the length of each
+  (func $pas_no_case (param $value i64) (param $kind i32) (param $names i32) (param $count i32) (param $site i32)
+    (local $name i32)
+    (call $pas_fail_begin)
+    (call $pas_put_text (i32.const 2) (string "No case label matches "))
+    (block $written
+      (if (i32.eq (local.get $kind) (i32.const 99))
+        (then
+          (call $pas_put (i32.const 2) (i32.const 39))
+          (call $pas_put (i32.const 2) (i32.wrap_i64 (local.get $value)))
+          (call $pas_put (i32.const 2) (i32.const 39))
+          (br $written)))
+      (if (i32.eq (local.get $kind) (i32.const 98))
+        (then
+          (call $pas_put_boolean (i32.const 2) (local.get $value))
+          (br $written)))
+      (if (i32.and (i32.eq (local.get $kind) (i32.const 101)) (i64.lt_u (local.get $value) (i64.extend_i32_u (local.get $count))))
+        (then
+          (local.set $name (i32.add (local.get $names) (i32.shl (i32.wrap_i64 (local.get $value)) (i32.const 3))))
+          (call $pas_put_text (i32.const 2) (i32.load (local.get $name)) (i32.load offset=4 (local.get $name)))
+          (br $written)))
+      (call $pas_put_integer (i32.const 2) (local.get $value)))
+    (call $pas_fail_end (local.get $site)))
+
+  ;; Compares two strings, giving -1, 0 or 1
+  (func $pas_compare (param $left i32) (param $right i32) (param $length i64) (result i64)
+    (local $a i64) (local $b i64)
+    (block $done
+      (loop $next
+        (br_if $done (i64.eqz (local.get $length)))
+        (local.set $a (i64.load (local.get $left)))
+        (local.set $b (i64.load (local.get $right)))
+        (if (i64.ne (local.get $a) (local.get $b))
+          (then (return (select (i64.const -1) (i64.const 1) (i64.lt_s (local.get $a) (local.get $b))))))
+        (local.set $left (i32.add (local.get $left) (i32.const 8)))
+        (local.set $right (i32.add (local.get $right) (i32.const 8)))
+        (local.set $length (i64.sub (local.get $length) (i64.const 1)))
+        (br $next)))
+    (i64.const 0))
+
+  ;; Arrays, records and sets, which the code handles through their address
+
+  (func $pas_zero (param $to i32) (param $size i64)
+    (memory.fill (local.get $to) (i32.const 0) (i32.wrap_i64 (local.get $size))))
+
+  (func $pas_copy (param $to i32) (param $from i32) (param $size i64)
+    (memory.copy (local.get $to) (local.get $from) (i32.wrap_i64 (local.get $size))))
+
+  ;; Sets of the ordinals from 0 to 255, as four words in memory
+
+  (func $pas_set_add (param $set i32) (param $member i64) (param $site i32)
+    (local $word i32)
+    (drop (call $pas_check (local.get $member) (i64.const 0) (i64.const 255) (string "0..255") (local.get $site)))
+    (local.set $word (i32.add (local.get $set) (i32.shl (i32.wrap_i64 (i64.shr_u (local.get $member) (i64.const 6))) (i32.const 3))))
+    (i64.store (local.get $word) (i64.or (i64.load (local.get $word)) (i64.shl (i64.const 1) (local.get $member)))))
+
+  (func $pas_set_has (param $set i32) (param $member i64) (result i64)
+    (if (result i64) (i64.gt_u (local.get $member) (i64.const 255))
+      (then (i64.const 0))
+      (else (i64.and (i64.const 1) (i64.shr_u
+        (i64.load (i32.add (local.get $set) (i32.shl (i32.wrap_i64 (i64.shr_u (local.get $member) (i64.const 6))) (i32.const 3))))
+        (local.get $member))))))
+
+  ;; The word-wise operation 0 (union), 1 (difference) or 2 (intersection)
+  ;; of two sets, into a third
+  (func $pas_set_combine (param $into i32) (param $left i32) (param $right i32) (param $operation i64)
+    (local $i i32) (local $a i64) (local $b i64)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (i32.const 32)))
+        (local.set $a (i64.load (i32.add (local.get $left) (local.get $i))))
+        (local.set $b (i64.load (i32.add (local.get $right) (local.get $i))))
+        (i64.store (i32.add (local.get $into) (local.get $i))
+          (if (result i64) (i64.eqz (local.get $operation))
+            (then (i64.or (local.get $a) (local.get $b)))
+            (else (i64.and (local.get $a)
+              (select (i64.xor (local.get $b) (i64.const -1)) (local.get $b) (i64.eq (local.get $operation) (i64.const 1)))))))
+        (local.set $i (i32.add (local.get $i) (i32.const 8)))
+        (br $next))))
+
+  (func $pas_set_eq (param $left i32) (param $right i32) (result i64)
+    (i64.extend_i32_u (i32.and
+      (i32.and (i64.eq (i64.load (local.get $left)) (i64.load (local.get $right)))
+               (i64.eq (i64.load offset=8 (local.get $left)) (i64.load offset=8 (local.get $right))))
+      (i32.and (i64.eq (i64.load offset=16 (local.get $left)) (i64.load offset=16 (local.get $right)))
+               (i64.eq (i64.load offset=24 (local.get $left)) (i64.load offset=24 (local.get $right)))))))
+
+  ;; Whether `left` is a subset of `right`
+  (func $pas_set_le (param $left i32) (param $right i32) (result i64)
+    (local $i i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (i32.const 32)))
+        (if (i64.ne (i64.and (i64.load (i32.add (local.get $left) (local.get $i)))
+                             (i64.xor (i64.load (i32.add (local.get $right) (local.get $i))) (i64.const -1)))
+                    (i64.const 0))
+          (then (return (i64.const 0))))
+        (local.set $i (i32.add (local.get $i) (i32.const 8)))
+        (br $next)))
+    (i64.const 1))
+
+  ;; Frames, on a stack of their own in memory
+
+  (func $pas_enter (param $size i32) (result i32)
+    (local $frame i32)
+    (local.set $frame (global.get $pas_sp))
+    (global.set $pas_sp (i32.add (local.get $frame) (local.get $size)))
+    (if (i32.gt_u (global.get $pas_sp) (global.get $pas_stack_end))
+      (then (unreachable)))
+    (memory.fill (local.get $frame) (i32.const 0) (local.get $size))
+    (local.get $frame))
+
+  ;; Heap variables, after a header telling whether they are live; they are
+  ;; never freed, so that a disposed one is caught
+
+  (func $pas_new (param $size i64) (result i32)
+    (local $header i32) (local $end i32) (local $limit i32)
+    (local.set $header (global.get $pas_heap))
+    (local.set $end (i32.add (i32.add (local.get $header) (i32.const 8))
+                             (i32.and (i32.add (i32.wrap_i64 (local.get $size)) (i32.const 7)) (i32.const -8))))
+    ;; memory is grown to fit, zeroed
+    (local.set $limit (i32.mul (memory.size) (i32.const 65536)))
+    (if (i32.gt_u (local.get $end) (local.get $limit))
+      (then
+        (if (i32.eq (memory.grow (i32.shr_u (i32.add (i32.sub (local.get $end) (local.get $limit)) (i32.const 65535)) (i32.const 16)))
+                    (i32.const -1))
+          (then
+            (call $pas_put_text (i32.const 2) (string "Runtime Error Out of memory"))
+            (call $pas_put (i32.const 2) (i32.const 10))
+            (call $host_exit (i32.const 1))
+            (unreachable)))))
+    (global.set $pas_heap (local.get $end))
+    (i64.store (local.get $header) (i64.const 1))
+    (i32.add (local.get $header) (i32.const 8)))
+
+  (func $pas_deref (param $pointer i32) (param $site i32) (result i32)
+    (if (i32.eqz (local.get $pointer))
+      (then (call $pas_fail (string "The pointer does not point to a variable") (local.get $site))))
+    (if (i64.eqz (i64.load (i32.sub (local.get $pointer) (i32.const 8))))
+      (then (call $pas_fail (string "The variable the pointer points to has been disposed") (local.get $site))))
+    (local.get $pointer))
+
+  (func $pas_dispose (param $pointer i32) (param $site i32)
+    (i64.store (i32.sub (call $pas_deref (local.get $pointer) (local.get $site)) (i32.const 8)) (i64.const 0)))
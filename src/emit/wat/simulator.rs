@@ -0,0 +1,1064 @@
+//! A simulator of the WebAssembly text the translation and its runtime
+//! use, with the host functions of `IMPORTS`, so that the tests run what
+//! `--emit wat` gives without wasmtime. It runs the text as it is written,
+//! folded or not, without checking its types: instructions it does not
+//! know, and traps, panic.
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+
+const PAGE: usize = 1 << 16;
+/// The pages memory may grow to, past which `memory.grow` fails
+const MAX_PAGES: usize = 1 << 14;
+/// The instructions a program may run before it is taken to loop forever
+const BUDGET: u64 = 4_000_000_000;
+
+/// Runs a module from its export `_start`, giving its output, and the error
+/// output if it exits with a status other than 0
+pub fn run(source: &str, input: &str) -> (String, Option<String>) {
+    let module = parse(&tokens(source)).unwrap_or_else(|message| panic!("{}", message));
+    let mut machine = Machine {
+        memory: module.memory,
+        globals: module.globals,
+        stack: vec![],
+        labels: vec![],
+        input: input.as_bytes(),
+        output: vec![],
+        error: vec![]
+    };
+    let status = machine.run(&module.functions, module.start);
+    let output = String::from_utf8_lossy(&machine.output).to_string();
+    (output, if status == 0 { None } else { Some(String::from_utf8_lossy(&machine.error).to_string()) })
+}
+
+/// An s-expression of the text
+#[derive(Debug, Clone, PartialEq)]
+enum Sexp {
+    Atom(String),
+    Text(Vec<u8>),
+    List(Vec<Sexp>)
+}
+
+impl Sexp {
+    fn atom(&self) -> Option<&str> {
+        match self {
+            Sexp::Atom(atom) => Some(atom),
+            _ => None
+        }
+    }
+
+    /// The keyword a list starts with
+    fn head(&self) -> Option<&str> {
+        match self {
+            Sexp::List(items) => items.first().and_then(Sexp::atom),
+            _ => None
+        }
+    }
+}
+
+/// The s-expressions of a text, comments left out
+fn tokens(source: &str) -> Vec<Sexp> {
+    let bytes = source.as_bytes();
+    let mut lists: Vec<Vec<Sexp>> = vec![vec![]];
+    let mut at = 0;
+    while at < bytes.len() {
+        match bytes[at] {
+            b';' if bytes.get(at + 1) == Some(&b';') => {
+                while at < bytes.len() && bytes[at] != b'\n' {
+                    at += 1;
+                }
+            },
+            b'(' if bytes.get(at + 1) == Some(&b';') => {
+                let end = source[at..].find(";)").expect("block comments are closed");
+                at += end + 2;
+            },
+            b'(' => {
+                lists.push(vec![]);
+                at += 1;
+            },
+            b')' => {
+                let list = lists.pop().expect("parentheses match");
+                lists.last_mut().expect("parentheses match").push(Sexp::List(list));
+                at += 1;
+            },
+            b'"' => {
+                let mut text = vec![];
+                at += 1;
+                while bytes[at] != b'"' {
+                    if bytes[at] == b'\\' {
+                        let escaped = match bytes[at + 1] {
+                            b'n' => b'\n',
+                            b't' => b'\t',
+                            b'\\' | b'"' | b'\'' => bytes[at + 1],
+                            _ => {
+                                let hex = std::str::from_utf8(&bytes[at + 1..at + 3]).unwrap();
+                                at += 1;
+                                u8::from_str_radix(hex, 16).expect("escapes are hexadecimal")
+                            }
+                        };
+                        text.push(escaped);
+                        at += 2;
+                    } else {
+                        text.push(bytes[at]);
+                        at += 1;
+                    }
+                }
+                lists.last_mut().unwrap().push(Sexp::Text(text));
+                at += 1;
+            },
+            byte if byte.is_ascii_whitespace() => at += 1,
+            _ => {
+                let start = at;
+                while at < bytes.len() && !bytes[at].is_ascii_whitespace() && !b"()\";".contains(&bytes[at]) {
+                    at += 1;
+                }
+                lists.last_mut().unwrap().push(Sexp::Atom(source[start..at].to_string()));
+            }
+        }
+    }
+    assert_eq!(lists.len(), 1, "parentheses match");
+    lists.pop().unwrap()
+}
+
+/// How wide a load or a store is, narrow loads extending with zeros
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Access {
+    I32,
+    I64,
+    F64,
+    I32Byte,
+    I64Word,
+    I64Byte
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Num {
+    I32Add, I32Sub, I32Mul, I32DivS, I32DivU, I32RemS, I32RemU, I32And, I32Or, I32Xor, I32Shl, I32ShrS, I32ShrU,
+    I32Eq, I32Ne, I32LtS, I32LtU, I32GtS, I32GtU, I32LeS, I32LeU, I32GeS, I32GeU, I32Eqz, I32Clz,
+    I32WrapI64, I32TruncF64S,
+    I64Add, I64Sub, I64Mul, I64DivS, I64DivU, I64RemS, I64RemU, I64And, I64Or, I64Xor, I64Shl, I64ShrS, I64ShrU,
+    I64Eq, I64Ne, I64LtS, I64LtU, I64GtS, I64GtU, I64LeS, I64LeU, I64GeS, I64GeU, I64Eqz, I64Clz,
+    I64ExtendI32S, I64ExtendI32U, I64ReinterpretF64, I64TruncSatF64S, I64TruncF64S,
+    F64Add, F64Sub, F64Mul, F64Div, F64Min, F64Max, F64Copysign, F64Neg, F64Abs, F64Sqrt, F64Ceil, F64Floor,
+    F64Trunc, F64Nearest, F64Eq, F64Ne, F64Lt, F64Gt, F64Le, F64Ge,
+    F64ConvertI64S, F64ConvertI64U, F64ConvertI32S, F64ConvertI32U, F64ReinterpretI64
+}
+
+fn numeric(name: &str) -> Option<Num> {
+    use Num::*;
+    Some(match name {
+        "i32.add" => I32Add,
+        "i32.sub" => I32Sub,
+        "i32.mul" => I32Mul,
+        "i32.div_s" => I32DivS,
+        "i32.div_u" => I32DivU,
+        "i32.rem_s" => I32RemS,
+        "i32.rem_u" => I32RemU,
+        "i32.and" => I32And,
+        "i32.or" => I32Or,
+        "i32.xor" => I32Xor,
+        "i32.shl" => I32Shl,
+        "i32.shr_s" => I32ShrS,
+        "i32.shr_u" => I32ShrU,
+        "i32.eq" => I32Eq,
+        "i32.ne" => I32Ne,
+        "i32.lt_s" => I32LtS,
+        "i32.lt_u" => I32LtU,
+        "i32.gt_s" => I32GtS,
+        "i32.gt_u" => I32GtU,
+        "i32.le_s" => I32LeS,
+        "i32.le_u" => I32LeU,
+        "i32.ge_s" => I32GeS,
+        "i32.ge_u" => I32GeU,
+        "i32.eqz" => I32Eqz,
+        "i32.clz" => I32Clz,
+        "i32.wrap_i64" => I32WrapI64,
+        "i32.trunc_f64_s" => I32TruncF64S,
+        "i64.add" => I64Add,
+        "i64.sub" => I64Sub,
+        "i64.mul" => I64Mul,
+        "i64.div_s" => I64DivS,
+        "i64.div_u" => I64DivU,
+        "i64.rem_s" => I64RemS,
+        "i64.rem_u" => I64RemU,
+        "i64.and" => I64And,
+        "i64.or" => I64Or,
+        "i64.xor" => I64Xor,
+        "i64.shl" => I64Shl,
+        "i64.shr_s" => I64ShrS,
+        "i64.shr_u" => I64ShrU,
+        "i64.eq" => I64Eq,
+        "i64.ne" => I64Ne,
+        "i64.lt_s" => I64LtS,
+        "i64.lt_u" => I64LtU,
+        "i64.gt_s" => I64GtS,
+        "i64.gt_u" => I64GtU,
+        "i64.le_s" => I64LeS,
+        "i64.le_u" => I64LeU,
+        "i64.ge_s" => I64GeS,
+        "i64.ge_u" => I64GeU,
+        "i64.eqz" => I64Eqz,
+        "i64.clz" => I64Clz,
+        "i64.extend_i32_s" => I64ExtendI32S,
+        "i64.extend_i32_u" => I64ExtendI32U,
+        "i64.reinterpret_f64" => I64ReinterpretF64,
+        "i64.trunc_sat_f64_s" => I64TruncSatF64S,
+        "i64.trunc_f64_s" => I64TruncF64S,
+        "f64.add" => F64Add,
+        "f64.sub" => F64Sub,
+        "f64.mul" => F64Mul,
+        "f64.div" => F64Div,
+        "f64.min" => F64Min,
+        "f64.max" => F64Max,
+        "f64.copysign" => F64Copysign,
+        "f64.neg" => F64Neg,
+        "f64.abs" => F64Abs,
+        "f64.sqrt" => F64Sqrt,
+        "f64.ceil" => F64Ceil,
+        "f64.floor" => F64Floor,
+        "f64.trunc" => F64Trunc,
+        "f64.nearest" => F64Nearest,
+        "f64.eq" => F64Eq,
+        "f64.ne" => F64Ne,
+        "f64.lt" => F64Lt,
+        "f64.gt" => F64Gt,
+        "f64.le" => F64Le,
+        "f64.ge" => F64Ge,
+        "f64.convert_i64_s" => F64ConvertI64S,
+        "f64.convert_i64_u" => F64ConvertI64U,
+        "f64.convert_i32_s" => F64ConvertI32S,
+        "f64.convert_i32_u" => F64ConvertI32U,
+        "f64.reinterpret_i64" => F64ReinterpretI64,
+        _ => return None
+    })
+}
+
+/// An instruction, the structured ones knowing where they end
+#[derive(Clone, Debug, PartialEq)]
+enum Ins {
+    Block { end: usize, arity: usize },
+    Loop,
+    If { otherwise: usize, end: usize, arity: usize },
+    Else { end: usize },
+    End,
+    Br(usize),
+    BrIf(usize),
+    /// The depths of the labels of each index, the last one being that of
+    /// any other
+    BrTable(Vec<usize>),
+    Return,
+    Call(usize),
+    Drop,
+    Select,
+    Unreachable,
+    LocalGet(usize),
+    LocalSet(usize),
+    LocalTee(usize),
+    GlobalGet(usize),
+    GlobalSet(usize),
+    Const(u64),
+    Load(Access, usize),
+    Store(Access, usize),
+    MemoryCopy,
+    MemoryFill,
+    MemorySize,
+    MemoryGrow,
+    Num(Num)
+}
+
+/// The host functions a module imports
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Host {
+    Read,
+    Write,
+    Exit
+}
+
+enum Body {
+    Host(Host),
+    Code(Vec<Ins>)
+}
+
+struct Function {
+    params: usize,
+    /// The locals after the parameters
+    locals: usize,
+    results: usize,
+    body: Body
+}
+
+struct Module {
+    functions: Vec<Function>,
+    start: usize,
+    memory: Vec<u8>,
+    globals: Vec<u64>
+}
+
+/// The value of an integer constant, in decimal or hexadecimal
+fn integer(text: &str) -> Option<i128> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text.strip_prefix('+').unwrap_or(text))
+    };
+    let digits = digits.replace('_', "");
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => i128::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<i128>().ok()?
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn real(text: &str) -> Option<f64> {
+    let (negative, rest) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text))
+    };
+    let magnitude = if rest == "inf" {
+        f64::INFINITY
+    } else if rest == "nan" || rest.starts_with("nan:") {
+        f64::NAN
+    } else if let Some(hex) = rest.strip_prefix("0x") {
+        let (mantissa, exponent) = match hex.split_once(['p', 'P']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i32>().ok()?),
+            None => (hex, 0)
+        };
+        let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let digits = u64::from_str_radix(&format!("{}{}", whole, fraction), 16).ok()?;
+        digits as f64 * 2f64.powi(exponent - 4 * fraction.len() as i32)
+    } else {
+        rest.parse::<f64>().ok()?
+    };
+    Some(if negative { -magnitude } else { magnitude })
+}
+
+fn parse(items: &[Sexp]) -> Result<Module, String> {
+    let fields = match items {
+        [module] if module.head() == Some("module") => match module {
+            Sexp::List(fields) => &fields[1..],
+            _ => unreachable!()
+        },
+        _ => return Err("the text is not a module".to_string())
+    };
+    let list = |sexp: &Sexp| match sexp {
+        Sexp::List(items) => items.clone(),
+        _ => vec![]
+    };
+    // the functions and globals are named before any code refers to them
+    let mut functions = HashMap::new();
+    let mut globals = HashMap::new();
+    let mut signatures = vec![];
+    for field in fields {
+        let items = list(field);
+        match field.head() {
+            Some("import") => {
+                let (name, host) = match items.get(2) {
+                    Some(Sexp::Text(name)) if name == b"read" => ("$host_read", Host::Read),
+                    Some(Sexp::Text(name)) if name == b"write" => ("$host_write", Host::Write),
+                    Some(Sexp::Text(name)) if name == b"exit" => ("$host_exit", Host::Exit),
+                    import => return Err(format!("unknown import {:?}", import))
+                };
+                let declared = items.get(3).map(list).unwrap_or_default();
+                let name = declared.get(1).and_then(Sexp::atom).unwrap_or(name).to_string();
+                functions.insert(name, signatures.len());
+                signatures.push((Some(host), field.clone()));
+            },
+            Some("func") => {
+                if let Some(name) = items.get(1).and_then(Sexp::atom) {
+                    functions.insert(name.to_string(), signatures.len());
+                }
+                signatures.push((None, field.clone()));
+            },
+            Some("global") => {
+                let name = items.get(1).and_then(Sexp::atom).ok_or("globals are named")?;
+                globals.insert(name.to_string(), globals.len());
+            },
+            _ => {}
+        }
+    }
+    let mut module = Module { functions: vec![], start: usize::MAX, memory: vec![], globals: vec![0; globals.len()] };
+    let mut data = vec![];
+    for field in fields {
+        let items = list(field);
+        match field.head() {
+            Some("memory") => {
+                let pages = items.iter().rev().find_map(|item| item.atom().and_then(integer))
+                    .ok_or("the memory has a size")?;
+                module.memory = vec![0; pages as usize * PAGE];
+            },
+            Some("global") => {
+                let name = items[1].atom().unwrap();
+                let value = match items.last().map(list).as_deref() {
+                    Some([Sexp::Atom(op), Sexp::Atom(value)]) => constant(op, value)?,
+                    _ => return Err(format!("global {} is not a constant", name))
+                };
+                module.globals[globals[name]] = value;
+            },
+            Some("data") => {
+                let offset = match items.get(1).map(list).as_deref() {
+                    Some([Sexp::Atom(op), Sexp::Atom(value)]) => constant(op, value)? as usize,
+                    _ => return Err("data is at a constant offset".to_string())
+                };
+                let bytes = items[2..].iter()
+                    .flat_map(|item| match item {
+                        Sexp::Text(text) => text.clone(),
+                        _ => vec![]
+                    })
+                    .collect::<Vec<u8>>();
+                data.push((offset, bytes));
+            },
+            _ => {}
+        }
+    }
+    for (offset, bytes) in data {
+        module.memory.get_mut(offset..offset + bytes.len()).ok_or("data is outside memory")?.copy_from_slice(&bytes);
+    }
+    for (index, (host, field)) in signatures.iter().enumerate() {
+        let items = list(field);
+        let declaration = if host.is_some() { items.get(3).map(list).unwrap_or_default() } else { items };
+        let mut names = HashMap::new();
+        let (mut params, mut locals, mut results) = (0, 0, 0);
+        let mut body = vec![];
+        for item in declaration.iter().skip(1) {
+            let parts = list(item);
+            let count = |parts: &[Sexp]| match parts.get(1).and_then(Sexp::atom) {
+                Some(name) if name.starts_with('$') => (Some(name.to_string()), 1),
+                _ => (None, parts.len() - 1)
+            };
+            match item.head() {
+                Some("export") => {
+                    if parts.get(1) == Some(&Sexp::Text(b"_start".to_vec())) {
+                        module.start = index;
+                    }
+                },
+                Some("param") => {
+                    let (name, count) = count(&parts);
+                    if let Some(name) = name {
+                        names.insert(name, params);
+                    }
+                    params += count;
+                },
+                Some("result") => results += parts.len() - 1,
+                Some("local") => {
+                    let (name, count) = count(&parts);
+                    if let Some(name) = name {
+                        names.insert(name, params + locals);
+                    }
+                    locals += count;
+                },
+                _ if item.atom().is_some_and(|atom| atom.starts_with('$')) && body.is_empty() => {},
+                _ => body.push(item.clone())
+            }
+        }
+        let body = match host {
+            Some(host) => Body::Host(*host),
+            None => {
+                let mut compiler = Compiler { functions: &functions, globals: &globals, locals: &names, code: vec![], open: vec![] };
+                compiler.sequence(&body)?;
+                Body::Code(compiler.code)
+            }
+        };
+        module.functions.push(Function { params, locals, results, body });
+    }
+    if module.start == usize::MAX {
+        return Err("the module exports no _start".to_string());
+    }
+    Ok(module)
+}
+
+/// The bits of a constant
+fn constant(op: &str, value: &str) -> Result<u64, String> {
+    let bad = || format!("{} {} is not a constant", op, value);
+    match op {
+        "i32.const" => integer(value).map(|value| value as u32 as u64).ok_or_else(bad),
+        "i64.const" => integer(value).map(|value| value as u64).ok_or_else(bad),
+        "f64.const" => real(value).map(f64::to_bits).ok_or_else(bad),
+        _ => Err(bad())
+    }
+}
+
+/// Turns the instructions of a function into code
+struct Compiler<'a> {
+    functions: &'a HashMap<String, usize>,
+    globals: &'a HashMap<String, usize>,
+    locals: &'a HashMap<String, usize>,
+    code: Vec<Ins>,
+    /// The structured instructions not ended yet, where they are and their
+    /// label
+    open: Vec<(usize, Option<String>)>
+}
+
+impl<'a> Compiler<'a> {
+    /// Compiles instructions written one after the other, any of which may
+    /// be folded
+    fn sequence(&mut self, items: &[Sexp]) -> Result<(), String> {
+        let mut at = 0;
+        while at < items.len() {
+            match &items[at] {
+                Sexp::List(_) => self.folded(&items[at])?,
+                Sexp::Atom(op) => {
+                    // the immediates follow a plain instruction, as does the
+                    // label and the result of a structured one
+                    let mut immediates = vec![];
+                    at += 1;
+                    while let Some(item) = items.get(at) {
+                        let immediate = match item {
+                            Sexp::Atom(atom) => !(numeric(atom).is_some() || is_keyword(atom)),
+                            Sexp::List(_) => matches!(item.head(), Some("result")) && is_structured(op),
+                            Sexp::Text(_) => false
+                        };
+                        if !immediate {
+                            break;
+                        }
+                        immediates.push(item.clone());
+                        at += 1;
+                    }
+                    self.instruction(op, &immediates)?;
+                    continue;
+                },
+                Sexp::Text(_) => return Err("a string is not an instruction".to_string())
+            }
+            at += 1;
+        }
+        Ok(())
+    }
+
+    /// Compiles a folded instruction, its operands before it
+    fn folded(&mut self, sexp: &Sexp) -> Result<(), String> {
+        let items = match sexp {
+            Sexp::List(items) => items,
+            _ => unreachable!()
+        };
+        let op = items.first().and_then(Sexp::atom).ok_or("an instruction starts with its name")?;
+        let mut rest = &items[1..];
+        let mut immediates = vec![];
+        while let Some(item) = rest.first() {
+            let immediate = match item {
+                Sexp::Atom(_) => true,
+                Sexp::List(_) => item.head() == Some("result"),
+                Sexp::Text(_) => false
+            };
+            if !immediate {
+                break;
+            }
+            immediates.push(item.clone());
+            rest = &rest[1..];
+        }
+        match op {
+            "block" | "loop" => {
+                self.instruction(op, &immediates)?;
+                self.sequence(rest)?;
+                self.instruction("end", &[])
+            },
+            "if" => {
+                let mut branches = rest.iter().filter(|item| matches!(item.head(), Some("then" | "else")));
+                for condition in rest.iter().filter(|item| !matches!(item.head(), Some("then" | "else"))) {
+                    self.folded(condition)?;
+                }
+                self.instruction("if", &immediates)?;
+                for branch in branches.by_ref() {
+                    if branch.head() == Some("else") {
+                        self.instruction("else", &[])?;
+                    }
+                    if let Sexp::List(body) = branch {
+                        self.sequence(&body[1..])?;
+                    }
+                }
+                self.instruction("end", &[])
+            },
+            _ => {
+                for operand in rest {
+                    self.folded(operand)?;
+                }
+                self.instruction(op, &immediates)
+            }
+        }
+    }
+
+    /// The depth of the label a branch names
+    fn depth(&self, label: &str) -> Result<usize, String> {
+        if let Some(depth) = integer(label) {
+            return Ok(depth as usize);
+        }
+        self.open.iter().rev().position(|(_, name)| name.as_deref() == Some(label))
+            .ok_or_else(|| format!("unknown label {}", label))
+    }
+
+    fn instruction(&mut self, op: &str, immediates: &[Sexp]) -> Result<(), String> {
+        let atom = |index: usize| immediates.get(index).and_then(Sexp::atom)
+            .ok_or_else(|| format!("{} needs an immediate", op));
+        let index = |names: &HashMap<String, usize>, name: &str| match integer(name) {
+            Some(index) => Ok(index as usize),
+            None => names.get(name).copied().ok_or_else(|| format!("unknown name {} of {}", name, op))
+        };
+        let access = match op {
+            "i32.load" | "i32.store" => Some(Access::I32),
+            "i64.load" | "i64.store" => Some(Access::I64),
+            "f64.load" | "f64.store" => Some(Access::F64),
+            "i32.load8_u" | "i32.store8" => Some(Access::I32Byte),
+            "i64.load32_u" | "i64.store32" => Some(Access::I64Word),
+            "i64.load8_u" | "i64.store8" => Some(Access::I64Byte),
+            _ => None
+        };
+        let ins = match op {
+            "block" | "loop" | "if" => {
+                let label = immediates.iter().find_map(Sexp::atom).map(str::to_string);
+                let arity = immediates.iter().filter(|item| item.head() == Some("result"))
+                    .map(|item| match item {
+                        Sexp::List(types) => types.len() - 1,
+                        _ => 0
+                    })
+                    .sum();
+                self.open.push((self.code.len(), label));
+                match op {
+                    "block" => Ins::Block { end: 0, arity },
+                    "loop" => Ins::Loop,
+                    _ => Ins::If { otherwise: 0, end: 0, arity }
+                }
+            },
+            "else" => {
+                let (start, _) = *self.open.last().ok_or("else is in an if")?;
+                let at = self.code.len();
+                if let Ins::If { otherwise, .. } = &mut self.code[start] {
+                    *otherwise = at;
+                }
+                Ins::Else { end: 0 }
+            },
+            "end" => {
+                let (start, _) = self.open.pop().ok_or("end closes an instruction")?;
+                let end = self.code.len();
+                for ins in &mut self.code[start..end] {
+                    match ins {
+                        Ins::Block { end: at, .. } | Ins::Else { end: at } if *at == 0 => *at = end,
+                        Ins::If { otherwise, end: at, .. } if *at == 0 => {
+                            if *otherwise == 0 {
+                                *otherwise = end;
+                            }
+                            *at = end;
+                        },
+                        _ => {}
+                    }
+                }
+                Ins::End
+            },
+            "br" => Ins::Br(self.depth(atom(0)?)?),
+            "br_if" => Ins::BrIf(self.depth(atom(0)?)?),
+            "br_table" => Ins::BrTable(immediates.iter().filter_map(Sexp::atom)
+                .map(|label| self.depth(label))
+                .collect::<Result<_, _>>()?),
+            "return" => Ins::Return,
+            "call" => Ins::Call(index(self.functions, atom(0)?)?),
+            "drop" => Ins::Drop,
+            "select" => Ins::Select,
+            "unreachable" => Ins::Unreachable,
+            "nop" => return Ok(()),
+            "local.get" => Ins::LocalGet(index(self.locals, atom(0)?)?),
+            "local.set" => Ins::LocalSet(index(self.locals, atom(0)?)?),
+            "local.tee" => Ins::LocalTee(index(self.locals, atom(0)?)?),
+            "global.get" => Ins::GlobalGet(index(self.globals, atom(0)?)?),
+            "global.set" => Ins::GlobalSet(index(self.globals, atom(0)?)?),
+            "i32.const" | "i64.const" | "f64.const" => Ins::Const(constant(op, atom(0)?)?),
+            "memory.copy" => Ins::MemoryCopy,
+            "memory.fill" => Ins::MemoryFill,
+            "memory.size" => Ins::MemorySize,
+            "memory.grow" => Ins::MemoryGrow,
+            _ if access.is_some() => {
+                let offset = immediates.iter().filter_map(Sexp::atom)
+                    .find_map(|immediate| immediate.strip_prefix("offset="))
+                    .map_or(Some(0), |offset| integer(offset).map(|offset| offset as usize))
+                    .ok_or_else(|| format!("{} has a bad offset", op))?;
+                if op.contains("load") {
+                    Ins::Load(access.unwrap(), offset)
+                } else {
+                    Ins::Store(access.unwrap(), offset)
+                }
+            },
+            _ => Ins::Num(numeric(op).ok_or_else(|| format!("unknown instruction {}", op))?)
+        };
+        self.code.push(ins);
+        Ok(())
+    }
+}
+
+/// Whether a word is an instruction that takes no immediate
+fn is_keyword(word: &str) -> bool {
+    matches!(word, "block" | "loop" | "if" | "else" | "end" | "return" | "drop" | "select" | "unreachable" | "nop"
+        | "memory.copy" | "memory.fill" | "memory.size" | "memory.grow" | "br" | "br_if" | "br_table" | "call" | "local.get"
+        | "local.set" | "local.tee" | "global.get" | "global.set" | "i32.const" | "i64.const" | "f64.const")
+        || word.contains(".load") || word.contains(".store")
+}
+
+fn is_structured(op: &str) -> bool {
+    matches!(op, "block" | "loop" | "if")
+}
+
+/// A structured instruction being run: where a branch to it goes, and the
+/// height of the stack and the values it keeps
+struct Label {
+    target: usize,
+    height: usize,
+    arity: usize,
+    /// Whether the label is that of a loop, which a branch stays in
+    repeats: bool
+}
+
+/// A call being run
+struct Frame {
+    function: usize,
+    pc: usize,
+    locals: Vec<u64>,
+    /// Where the labels and the values of the call start
+    labels: usize,
+    height: usize
+}
+
+struct Machine<'a> {
+    memory: Vec<u8>,
+    globals: Vec<u64>,
+    stack: Vec<u64>,
+    labels: Vec<Label>,
+    input: &'a [u8],
+    output: Vec<u8>,
+    error: Vec<u8>
+}
+
+/// Calls the operation of a binary numeric instruction on the two values
+/// on top of the stack
+macro_rules! binary {
+    ($machine:expr, $from:ident, $to:ident, $f:expr) => {{
+        let right = $from($machine.pop());
+        let left = $from($machine.pop());
+        let f = $f;
+        $machine.stack.push($to(f(left, right)));
+    }};
+}
+
+macro_rules! unary {
+    ($machine:expr, $from:ident, $to:ident, $f:expr) => {{
+        let value = $from($machine.pop());
+        let f = $f;
+        $machine.stack.push($to(f(value)));
+    }};
+}
+
+fn i32_of(bits: u64) -> i32 { bits as u32 as i32 }
+fn i64_of(bits: u64) -> i64 { bits as i64 }
+fn f64_of(bits: u64) -> f64 { f64::from_bits(bits) }
+fn of_i32(value: i32) -> u64 { value as u32 as u64 }
+fn of_i64(value: i64) -> u64 { value as u64 }
+fn of_f64(value: f64) -> u64 { value.to_bits() }
+fn of_bool(value: bool) -> u64 { value as u64 }
+
+impl<'a> Machine<'a> {
+    fn pop(&mut self) -> u64 {
+        self.stack.pop().expect("the stack has an operand")
+    }
+
+    fn bytes(&mut self, address: u64, size: usize) -> &mut [u8] {
+        let start = address as usize;
+        let length = self.memory.len();
+        self.memory.get_mut(start..start + size)
+            .unwrap_or_else(|| panic!("access of {} bytes at {} outside a memory of {}", size, start, length))
+    }
+
+    /// Runs a function to its end, giving the status the program exits with
+    fn run(&mut self, functions: &[Function], start: usize) -> i32 {
+        let mut frames: Vec<Frame> = vec![];
+        let mut frame = Frame { function: start, pc: 0, locals: vec![0; functions[start].locals], labels: 0, height: 0 };
+        let mut code = match &functions[start].body {
+            Body::Code(code) => code,
+            Body::Host(_) => panic!("the start is not a host function")
+        };
+        for _ in 0..BUDGET {
+            let ins = match code.get(frame.pc) {
+                Some(ins) => ins,
+                None => {
+                    // the end of a function returns from it
+                    match self.leave(functions, &mut frames, &mut frame) {
+                        Some(next) => {
+                            code = next;
+                            continue;
+                        },
+                        None => return 0
+                    }
+                }
+            };
+            frame.pc += 1;
+            match ins {
+                Ins::Block { end, arity } => self.labels.push(Label { target: end + 1, height: self.stack.len(), arity: *arity, repeats: false }),
+                Ins::Loop => self.labels.push(Label { target: frame.pc, height: self.stack.len(), arity: 0, repeats: true }),
+                Ins::If { otherwise, end, arity } => {
+                    let condition = i32_of(self.pop());
+                    self.labels.push(Label { target: end + 1, height: self.stack.len(), arity: *arity, repeats: false });
+                    if condition == 0 {
+                        if otherwise == end {
+                            self.labels.pop();
+                            frame.pc = end + 1;
+                        } else {
+                            frame.pc = otherwise + 1;
+                        }
+                    }
+                },
+                Ins::Else { end } => {
+                    self.labels.pop();
+                    frame.pc = end + 1;
+                },
+                Ins::End => {
+                    self.labels.pop();
+                },
+                Ins::Br(depth) => {
+                    if !self.branch(&mut frame, *depth) {
+                        match self.leave(functions, &mut frames, &mut frame) {
+                            Some(next) => code = next,
+                            None => return 0
+                        }
+                    }
+                },
+                Ins::BrIf(depth) => {
+                    if i32_of(self.pop()) != 0 && !self.branch(&mut frame, *depth) {
+                        match self.leave(functions, &mut frames, &mut frame) {
+                            Some(next) => code = next,
+                            None => return 0
+                        }
+                    }
+                },
+                Ins::BrTable(depths) => {
+                    let index = (i32_of(self.pop()) as u32 as usize).min(depths.len() - 1);
+                    if !self.branch(&mut frame, depths[index]) {
+                        match self.leave(functions, &mut frames, &mut frame) {
+                            Some(next) => code = next,
+                            None => return 0
+                        }
+                    }
+                },
+                Ins::Return => match self.leave(functions, &mut frames, &mut frame) {
+                    Some(next) => code = next,
+                    None => return 0
+                },
+                Ins::Call(callee) => {
+                    let function = &functions[*callee];
+                    match &function.body {
+                        Body::Host(Host::Read) => {
+                            let byte = match self.input.split_first() {
+                                Some((first, rest)) => {
+                                    self.input = rest;
+                                    *first as i32
+                                },
+                                None => -1
+                            };
+                            self.stack.push(of_i32(byte));
+                        },
+                        Body::Host(Host::Write) => {
+                            let byte = self.pop() as u8;
+                            let stream = i32_of(self.pop());
+                            if stream == 1 { &mut self.output } else { &mut self.error }.push(byte);
+                        },
+                        Body::Host(Host::Exit) => return i32_of(self.pop()),
+                        Body::Code(callee_code) => {
+                            let mut locals = self.stack.split_off(self.stack.len() - function.params);
+                            locals.resize(function.params + function.locals, 0);
+                            let height = self.stack.len();
+                            let caller = std::mem::replace(&mut frame, Frame {
+                                function: *callee,
+                                pc: 0,
+                                locals,
+                                labels: self.labels.len(),
+                                height
+                            });
+                            frames.push(caller);
+                            code = callee_code;
+                        }
+                    }
+                },
+                Ins::Drop => {
+                    self.pop();
+                },
+                Ins::Select => {
+                    let condition = i32_of(self.pop());
+                    let otherwise = self.pop();
+                    let value = self.pop();
+                    self.stack.push(if condition != 0 { value } else { otherwise });
+                },
+                Ins::Unreachable => panic!("unreachable reached in function {}", frame.function),
+                Ins::LocalGet(index) => self.stack.push(frame.locals[*index]),
+                Ins::LocalSet(index) => frame.locals[*index] = self.pop(),
+                Ins::LocalTee(index) => frame.locals[*index] = *self.stack.last().expect("the stack has an operand"),
+                Ins::GlobalGet(index) => self.stack.push(self.globals[*index]),
+                Ins::GlobalSet(index) => self.globals[*index] = self.pop(),
+                Ins::Const(bits) => self.stack.push(*bits),
+                Ins::Load(access, offset) => {
+                    let address = self.pop() as u32 as u64 + *offset as u64;
+                    let value = match access {
+                        Access::I32 => u32::from_le_bytes(self.bytes(address, 4).try_into().unwrap()) as u64,
+                        Access::I64 | Access::F64 => u64::from_le_bytes(self.bytes(address, 8).try_into().unwrap()),
+                        Access::I32Byte | Access::I64Byte => self.bytes(address, 1)[0] as u64,
+                        Access::I64Word => u32::from_le_bytes(self.bytes(address, 4).try_into().unwrap()) as u64
+                    };
+                    self.stack.push(value);
+                },
+                Ins::Store(access, offset) => {
+                    let value = self.pop();
+                    let address = self.pop() as u32 as u64 + *offset as u64;
+                    match access {
+                        Access::I32 | Access::I64Word => self.bytes(address, 4).copy_from_slice(&(value as u32).to_le_bytes()),
+                        Access::I64 | Access::F64 => self.bytes(address, 8).copy_from_slice(&value.to_le_bytes()),
+                        Access::I32Byte | Access::I64Byte => self.bytes(address, 1)[0] = value as u8
+                    }
+                },
+                Ins::MemoryCopy => {
+                    let length = self.pop() as u32 as usize;
+                    let from = self.pop() as u32 as u64;
+                    let to = self.pop() as u32 as u64;
+                    let bytes = self.bytes(from, length).to_vec();
+                    self.bytes(to, length).copy_from_slice(&bytes);
+                },
+                Ins::MemoryFill => {
+                    let length = self.pop() as u32 as usize;
+                    let value = self.pop() as u8;
+                    let to = self.pop() as u32 as u64;
+                    self.bytes(to, length).fill(value);
+                },
+                Ins::MemorySize => self.stack.push((self.memory.len() / PAGE) as u64),
+                Ins::MemoryGrow => {
+                    let pages = self.pop() as u32 as usize;
+                    let old = self.memory.len() / PAGE;
+                    if old + pages > MAX_PAGES {
+                        self.stack.push(of_i32(-1));
+                    } else {
+                        self.memory.resize((old + pages) * PAGE, 0);
+                        self.stack.push(old as u64);
+                    }
+                },
+                Ins::Num(num) => self.numeric(*num)
+            }
+        }
+        panic!("the program runs more than {} instructions", BUDGET)
+    }
+
+    /// Branches to the label at `depth`, or tells that the branch leaves
+    /// the function
+    fn branch(&mut self, frame: &mut Frame, depth: usize) -> bool {
+        if depth >= self.labels.len() - frame.labels {
+            return false;
+        }
+        let index = self.labels.len() - 1 - depth;
+        let label = &self.labels[index];
+        let kept = self.stack.split_off(self.stack.len() - label.arity);
+        self.stack.truncate(label.height);
+        self.stack.extend(kept);
+        frame.pc = label.target;
+        let repeats = label.repeats;
+        self.labels.truncate(if repeats { index + 1 } else { index });
+        true
+    }
+
+    /// Returns from the running function to its caller, giving the code of
+    /// the caller, none when the function is the start
+    fn leave<'f>(&mut self, functions: &'f [Function], frames: &mut Vec<Frame>, frame: &mut Frame) -> Option<&'f Vec<Ins>> {
+        let results = functions[frame.function].results;
+        let kept = self.stack.split_off(self.stack.len() - results);
+        self.stack.truncate(frame.height);
+        self.stack.extend(kept);
+        self.labels.truncate(frame.labels);
+        *frame = frames.pop()?;
+        match &functions[frame.function].body {
+            Body::Code(code) => Some(code),
+            Body::Host(_) => unreachable!("host functions have no frames")
+        }
+    }
+
+    fn numeric(&mut self, num: Num) {
+        use Num::*;
+        let shift32 = |value: i32| value as u32 & 31;
+        let shift64 = |value: i64| value as u32 & 63;
+        match num {
+            I32Add => binary!(self, i32_of, of_i32, |a: i32, b: i32| a.wrapping_add(b)),
+            I32Sub => binary!(self, i32_of, of_i32, |a: i32, b: i32| a.wrapping_sub(b)),
+            I32Mul => binary!(self, i32_of, of_i32, |a: i32, b: i32| a.wrapping_mul(b)),
+            I32DivS => binary!(self, i32_of, of_i32, |a: i32, b: i32| a.checked_div(b).expect("i32.div_s traps")),
+            I32DivU => binary!(self, i32_of, of_i32, |a: i32, b: i32| (a as u32).checked_div(b as u32).expect("i32.div_u traps") as i32),
+            I32RemS => binary!(self, i32_of, of_i32, |a: i32, b: i32| if b == 0 { panic!("i32.rem_s traps") } else { a.wrapping_rem(b) }),
+            I32RemU => binary!(self, i32_of, of_i32, |a: i32, b: i32| (a as u32).checked_rem(b as u32).expect("i32.rem_u traps") as i32),
+            I32And => binary!(self, i32_of, of_i32, |a: i32, b: i32| a & b),
+            I32Or => binary!(self, i32_of, of_i32, |a: i32, b: i32| a | b),
+            I32Xor => binary!(self, i32_of, of_i32, |a: i32, b: i32| a ^ b),
+            I32Shl => binary!(self, i32_of, of_i32, |a: i32, b: i32| a << shift32(b)),
+            I32ShrS => binary!(self, i32_of, of_i32, |a: i32, b: i32| a >> shift32(b)),
+            I32ShrU => binary!(self, i32_of, of_i32, |a: i32, b: i32| ((a as u32) >> shift32(b)) as i32),
+            I32Eq => binary!(self, i32_of, of_bool, |a: i32, b: i32| a == b),
+            I32Ne => binary!(self, i32_of, of_bool, |a: i32, b: i32| a != b),
+            I32LtS => binary!(self, i32_of, of_bool, |a: i32, b: i32| a < b),
+            I32LtU => binary!(self, i32_of, of_bool, |a: i32, b: i32| (a as u32) < b as u32),
+            I32GtS => binary!(self, i32_of, of_bool, |a: i32, b: i32| a > b),
+            I32GtU => binary!(self, i32_of, of_bool, |a: i32, b: i32| a as u32 > b as u32),
+            I32LeS => binary!(self, i32_of, of_bool, |a: i32, b: i32| a <= b),
+            I32LeU => binary!(self, i32_of, of_bool, |a: i32, b: i32| a as u32 <= b as u32),
+            I32GeS => binary!(self, i32_of, of_bool, |a: i32, b: i32| a >= b),
+            I32GeU => binary!(self, i32_of, of_bool, |a: i32, b: i32| a as u32 >= b as u32),
+            I32Eqz => unary!(self, i32_of, of_bool, |a: i32| a == 0),
+            I32Clz => unary!(self, i32_of, of_i32, |a: i32| a.leading_zeros() as i32),
+            I32WrapI64 => unary!(self, i64_of, of_i32, |a: i64| a as i32),
+            I32TruncF64S => unary!(self, f64_of, of_i32, |a: f64| {
+                assert!(a.trunc() >= i32::MIN as f64 && a.trunc() <= i32::MAX as f64, "i32.trunc_f64_s traps on {}", a);
+                a as i32
+            }),
+            I64Add => binary!(self, i64_of, of_i64, |a: i64, b: i64| a.wrapping_add(b)),
+            I64Sub => binary!(self, i64_of, of_i64, |a: i64, b: i64| a.wrapping_sub(b)),
+            I64Mul => binary!(self, i64_of, of_i64, |a: i64, b: i64| a.wrapping_mul(b)),
+            I64DivS => binary!(self, i64_of, of_i64, |a: i64, b: i64| a.checked_div(b).expect("i64.div_s traps")),
+            I64DivU => binary!(self, i64_of, of_i64, |a: i64, b: i64| (a as u64).checked_div(b as u64).expect("i64.div_u traps") as i64),
+            I64RemS => binary!(self, i64_of, of_i64, |a: i64, b: i64| if b == 0 { panic!("i64.rem_s traps") } else { a.wrapping_rem(b) }),
+            I64RemU => binary!(self, i64_of, of_i64, |a: i64, b: i64| (a as u64).checked_rem(b as u64).expect("i64.rem_u traps") as i64),
+            I64And => binary!(self, i64_of, of_i64, |a: i64, b: i64| a & b),
+            I64Or => binary!(self, i64_of, of_i64, |a: i64, b: i64| a | b),
+            I64Xor => binary!(self, i64_of, of_i64, |a: i64, b: i64| a ^ b),
+            I64Shl => binary!(self, i64_of, of_i64, |a: i64, b: i64| a << shift64(b)),
+            I64ShrS => binary!(self, i64_of, of_i64, |a: i64, b: i64| a >> shift64(b)),
+            I64ShrU => binary!(self, i64_of, of_i64, |a: i64, b: i64| ((a as u64) >> shift64(b)) as i64),
+            I64Eq => binary!(self, i64_of, of_bool, |a: i64, b: i64| a == b),
+            I64Ne => binary!(self, i64_of, of_bool, |a: i64, b: i64| a != b),
+            I64LtS => binary!(self, i64_of, of_bool, |a: i64, b: i64| a < b),
+            I64LtU => binary!(self, i64_of, of_bool, |a: i64, b: i64| (a as u64) < b as u64),
+            I64GtS => binary!(self, i64_of, of_bool, |a: i64, b: i64| a > b),
+            I64GtU => binary!(self, i64_of, of_bool, |a: i64, b: i64| a as u64 > b as u64),
+            I64LeS => binary!(self, i64_of, of_bool, |a: i64, b: i64| a <= b),
+            I64LeU => binary!(self, i64_of, of_bool, |a: i64, b: i64| a as u64 <= b as u64),
+            I64GeS => binary!(self, i64_of, of_bool, |a: i64, b: i64| a >= b),
+            I64GeU => binary!(self, i64_of, of_bool, |a: i64, b: i64| a as u64 >= b as u64),
+            I64Eqz => unary!(self, i64_of, of_bool, |a: i64| a == 0),
+            I64Clz => unary!(self, i64_of, of_i64, |a: i64| a.leading_zeros() as i64),
+            I64ExtendI32S => unary!(self, i32_of, of_i64, |a: i32| a as i64),
+            I64ExtendI32U => unary!(self, i32_of, of_i64, |a: i32| a as u32 as i64),
+            I64ReinterpretF64 => {},
+            I64TruncSatF64S => unary!(self, f64_of, of_i64, |a: f64| a as i64),
+            I64TruncF64S => unary!(self, f64_of, of_i64, |a: f64| {
+                assert!(a.trunc() >= -9223372036854775808.0 && a.trunc() < 9223372036854775808.0, "i64.trunc_f64_s traps on {}", a);
+                a as i64
+            }),
+            F64Add => binary!(self, f64_of, of_f64, |a: f64, b: f64| a + b),
+            F64Sub => binary!(self, f64_of, of_f64, |a: f64, b: f64| a - b),
+            F64Mul => binary!(self, f64_of, of_f64, |a: f64, b: f64| a * b),
+            F64Div => binary!(self, f64_of, of_f64, |a: f64, b: f64| a / b),
+            F64Min => binary!(self, f64_of, of_f64, |a: f64, b: f64| if a.is_nan() || b.is_nan() { f64::NAN } else { a.min(b) }),
+            F64Max => binary!(self, f64_of, of_f64, |a: f64, b: f64| if a.is_nan() || b.is_nan() { f64::NAN } else { a.max(b) }),
+            F64Copysign => binary!(self, f64_of, of_f64, |a: f64, b: f64| a.copysign(b)),
+            F64Neg => unary!(self, f64_of, of_f64, |a: f64| -a),
+            F64Abs => unary!(self, f64_of, of_f64, |a: f64| a.abs()),
+            F64Sqrt => unary!(self, f64_of, of_f64, |a: f64| a.sqrt()),
+            F64Ceil => unary!(self, f64_of, of_f64, |a: f64| a.ceil()),
+            F64Floor => unary!(self, f64_of, of_f64, |a: f64| a.floor()),
+            F64Trunc => unary!(self, f64_of, of_f64, |a: f64| a.trunc()),
+            F64Nearest => unary!(self, f64_of, of_f64, |a: f64| a.round_ties_even()),
+            F64Eq => binary!(self, f64_of, of_bool, |a: f64, b: f64| a == b),
+            F64Ne => binary!(self, f64_of, of_bool, |a: f64, b: f64| a != b),
+            F64Lt => binary!(self, f64_of, of_bool, |a: f64, b: f64| a < b),
+            F64Gt => binary!(self, f64_of, of_bool, |a: f64, b: f64| a > b),
+            F64Le => binary!(self, f64_of, of_bool, |a: f64, b: f64| a <= b),
+            F64Ge => binary!(self, f64_of, of_bool, |a: f64, b: f64| a >= b),
+            F64ConvertI64S => unary!(self, i64_of, of_f64, |a: i64| a as f64),
+            F64ConvertI64U => unary!(self, i64_of, of_f64, |a: i64| a as u64 as f64),
+            F64ConvertI32S => unary!(self, i32_of, of_f64, |a: i32| a as f64),
+            F64ConvertI32U => unary!(self, i32_of, of_f64, |a: i32| a as u32 as f64),
+            F64ReinterpretI64 => {}
+        }
+    }
+}
@@ -0,0 +1,72 @@
+;; A tiny WASI shim giving compiled Pascal programs the host functions they
+;; import, so that they run under a WASI runtime such as wasmtime:
+;;
+;;   wasmtime run --preload host=wasi.wat program.wat < input
+;;
+;; The output is buffered, and written out before the program waits for
+;; input, writes an error or ends.
+(module
+  (import "wasi_snapshot_preview1" "fd_read" (func $fd_read (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "fd_write" (func $fd_write (param i32 i32 i32 i32) (result i32)))
+  (import "wasi_snapshot_preview1" "proc_exit" (func $proc_exit (param i32)))
+
+  ;; 0: an iovec, 8: the count of bytes read or written, 16: a byte of the
+  ;; error output, 1024: the input, 8192: the output
+  (memory (export "memory") 1)
+  (global $input (mut i32) (i32.const 0))
+  (global $input_end (mut i32) (i32.const 0))
+  (global $ended (mut i32) (i32.const 0))
+  (global $output (mut i32) (i32.const 0))
+
+  (func $write_all (param $fd i32) (param $start i32) (param $length i32)
+    (block $done
+      (loop $next
+        (br_if $done (i32.eqz (local.get $length)))
+        (i32.store (i32.const 0) (local.get $start))
+        (i32.store (i32.const 4) (local.get $length))
+        (br_if $done (call $fd_write (local.get $fd) (i32.const 0) (i32.const 1) (i32.const 8)))
+        (local.set $start (i32.add (local.get $start) (i32.load (i32.const 8))))
+        (local.set $length (i32.sub (local.get $length) (i32.load (i32.const 8))))
+        (br $next))))
+
+  (func $flush
+    (call $write_all (i32.const 1) (i32.const 8192) (global.get $output))
+    (global.set $output (i32.const 0)))
+
+  ;; The next byte of the input, -1 at its end
+  (func (export "read") (result i32)
+    (local $byte i32)
+    (if (i32.ge_u (global.get $input) (global.get $input_end))
+      (then
+        (call $flush)
+        (if (global.get $ended)
+          (then (return (i32.const -1))))
+        (i32.store (i32.const 0) (i32.const 1024))
+        (i32.store (i32.const 4) (i32.const 7168))
+        (if (i32.or (call $fd_read (i32.const 0) (i32.const 0) (i32.const 1) (i32.const 8))
+                    (i32.eqz (i32.load (i32.const 8))))
+          (then
+            (global.set $ended (i32.const 1))
+            (return (i32.const -1))))
+        (global.set $input (i32.const 0))
+        (global.set $input_end (i32.load (i32.const 8)))))
+    (local.set $byte (i32.load8_u (i32.add (i32.const 1024) (global.get $input))))
+    (global.set $input (i32.add (global.get $input) (i32.const 1)))
+    (local.get $byte))
+
+  ;; Writes a byte to the output (1) or the error output (2)
+  (func (export "write") (param $stream i32) (param $byte i32)
+    (if (i32.eq (local.get $stream) (i32.const 1))
+      (then
+        (if (i32.eq (global.get $output) (i32.const 57344))
+          (then (call $flush)))
+        (i32.store8 (i32.add (i32.const 8192) (global.get $output)) (local.get $byte))
+        (global.set $output (i32.add (global.get $output) (i32.const 1)))
+        (return)))
+    (call $flush)
+    (i32.store8 (i32.const 16) (local.get $byte))
+    (call $write_all (i32.const 2) (i32.const 16) (i32.const 1)))
+
+  (func (export "exit") (param $status i32)
+    (call $flush)
+    (call $proc_exit (local.get $status))))
@@ -1,6 +1,7 @@
 // pub mod warning;
 pub mod parse_error;
 pub mod semantic_error;
+pub mod runtime_error;
 pub mod io_error;
 // pub mod semantic_error;
 // pub mod type_error;
@@ -22,26 +23,38 @@ pub struct Position {
 }
 
 impl Position {
-    fn trace(&self) -> String {
-        let diff = self.offset.end - self.offset.start;
-        format!(r#"{}
+    pub fn trace(&self) -> String {
+        let (before, marked, after) = self.marks();
+        layout(self.to_string().blue(), &self.line, before.green(), marked.red().bold(), after.green())
+    }
+
+    /// The trace without colors, as compiled programs print it
+    pub fn plain_trace(&self) -> String {
+        let (before, marked, after) = self.marks();
+        layout(self, &self.line, before, marked, after)
+    }
+
+    /// The dashes before the offset, the carets under it and the dashes after
+    fn marks(&self) -> (String, String, String) {
+        ("-".repeat(self.offset.start),
+         "^".repeat(self.offset.end - self.offset.start),
+         "-".repeat(self.line.len() + 1 - self.offset.end))
+    }
+}
+
+fn layout(position: impl std::fmt::Display, line: &str, before: impl std::fmt::Display,
+          marked: impl std::fmt::Display, after: impl std::fmt::Display) -> String {
+    format!(r#"{}
 
     {}
     {}{}{}
 
-"#,
-                self.to_string().blue(),
-                self.line,
-                "-".repeat(self.offset.start).green(),
-                "^".repeat(diff).red().bold(),
-                "-".repeat(self.line.len() + 1 - self.offset.end).green()
-        )
-    }
+"#, position, line, before, marked, after)
 }
 
 impl std::fmt::Display for Position {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "line {}, {:?}", self.line_no, self.offset)
+        write!(f, "line {}, {:?}", self.line_no + 1, self.offset)
     }
 }
 
@@ -49,7 +62,7 @@ pub struct PositionBuilder {
     pub file: String
 }
 
-pub fn get_position<'input, L, T, E> (err: &ErrorRecovery<L, T, E>) -> Range<usize>
+pub fn get_position<L, T, E> (err: &ErrorRecovery<L, T, E>) -> Range<usize>
     where
         usize: From<L>,
         L: Copy + Clone + std::ops::Add<usize, Output=L>
@@ -79,8 +92,8 @@ impl PositionBuilder {
         let reversed = &*predicate.chars().rev().collect::<String>();
         let empty_space = re.find(reversed);
         if let Some(es) = empty_space {
-            offset.end = offset.end - es.end();
-            offset.start = offset.start - es.end();
+            offset.end -= es.end();
+            offset.start -= es.end();
             predicate = &predicate[..offset.end];
         }
         let lines = predicate.split("\n");
@@ -106,7 +119,7 @@ impl PositionBuilder {
     }
 }
 
-impl<'input, L, T, E> From<ErrorRecovery<L, T, E>> for ParsingError<String>
+impl<L, T, E> From<ErrorRecovery<L, T, E>> for ParsingError<String>
     where
         L: std::fmt::Debug + Copy + Clone + std::ops::Add<usize, Output=L>,
         T: std::fmt::Debug + std::fmt::Display + Clone,
@@ -121,7 +134,7 @@ impl<'input, L, T, E> From<ErrorRecovery<L, T, E>> for ParsingError<String>
             position: get_position(&e),
             expected: match &e.error {
                 ParseError::UnrecognizedEOF { expected, .. } |
-                ParseError::UnrecognizedToken { expected, .. } => expected.into_iter()
+                ParseError::UnrecognizedToken { expected, .. } => expected.iter()
                     .map(|t| {
                         TOKEN_REGEX.captures(t)
                             .map(|t| t[1].to_string())
@@ -131,7 +144,7 @@ impl<'input, L, T, E> From<ErrorRecovery<L, T, E>> for ParsingError<String>
                 ,
                 _ => vec![]
             },
-            dropped: (&e.dropped_tokens).into_iter()
+            dropped: e.dropped_tokens.iter()
                 .map(|el| el.1.to_string())
                 .collect::<Vec<String>>(),
             token: match &e.error {
@@ -162,6 +175,11 @@ pub trait Throwable {
     fn description(&self) -> String;
     fn notes(&self) -> Vec<String>;
 
+    /// Other places in the source that explain the error, each with a note
+    fn related(&self) -> Vec<(String, Range<usize>)> {
+        vec![]
+    }
+
     fn static_print(&self) -> String {
         format!("{} {}",
                 self.title(),
@@ -169,33 +187,40 @@ pub trait Throwable {
     }
 
     fn format(&self, f: &mut Formatter<'_>, positioner: &PositionBuilder) -> std::fmt::Result {
-        write!(f, "{} {} at {}{}",
+        write!(f, "{} {} at {}{}{}",
                self.title(),
                self.description(),
                self.position(positioner).trace(),
                self.notes().into_iter()
                    .map(|s| format!("{} {}", &*NOTE, s))
                    .collect::<Vec<String>>()
-                   .join("\n")
+                   .join("\n"),
+               self.related().into_iter()
+                   .map(|(s, range)| format!("{} {} at {}", &*NOTE, s, positioner.pos(range).trace()))
+                   .collect::<String>()
         )
     }
 }
 
 impl<T: Throwable + ?Sized> Throwable for Box<T> {
     fn position(&self, positioner: &PositionBuilder) -> Position {
-        (&**self).position(positioner)
+        (**self).position(positioner)
     }
 
     fn title(&self) -> String {
-        (&**self).title()
+        (**self).title()
     }
 
     fn description(&self) -> String {
-        (&**self).description()
+        (**self).description()
     }
 
     fn notes(&self) -> Vec<String> {
-        (&**self).notes()
+        (**self).notes()
+    }
+
+    fn related(&self) -> Vec<(String, Range<usize>)> {
+        (**self).related()
     }
 }
 
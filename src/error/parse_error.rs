@@ -82,9 +82,9 @@ impl<T: std::fmt::Debug + std::fmt::Display + Clone> Throwable for ParsingError<
     }
 }
 
-impl Into<ParsingError<Token>> for ParseError<usize, Token, ParsingError<Token>> {
-    fn into(self) -> ParsingError<Token> {
-        match self {
+impl From<ParseError<usize, Token, ParsingError<Token>>> for ParsingError<Token> {
+    fn from(err: ParseError<usize, Token, ParsingError<Token>>) -> ParsingError<Token> {
+        match err {
             ParseError::InvalidToken { location } => ParsingError {
                 dropped: vec![],
                 position: location..location,
@@ -0,0 +1,145 @@
+use crate::ast::types::Type;
+use crate::error::{Position, PositionBuilder, Throwable};
+
+/// An error that stops a running program, at the expression or statement
+/// that caused it
+#[derive(Debug, Clone)]
+pub struct RuntimeError {
+    pub range: std::ops::Range<usize>,
+    pub kind: RuntimeErrorKind
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RuntimeErrorKind {
+    DivisionByZero,
+    /// Integer arithmetic whose result does not fit in an integer
+    Overflow,
+    IndexOutOfRange {
+        index: i64,
+        low: i64,
+        high: i64
+    },
+    /// An ordinal value outside of the values of its type
+    OutOfRange {
+        value: i64,
+        r#type: Type
+    },
+    NilPointer,
+    DanglingPointer,
+    NoCaseLabel(String),
+    /// An argument outside of the domain of a standard function
+    Domain {
+        function: String,
+        argument: f64
+    },
+    InvalidInput {
+        expected: Type,
+        got: String
+    },
+    EndOfInput,
+    Output(String),
+    /// A field width or number of decimals below 1 given to `write`
+    FieldWidth(i64),
+    /// A file read from without `reset` or written to without `rewrite`
+    NotReading,
+    NotWriting,
+    /// A file of the program that could not be opened
+    File {
+        path: String,
+        message: String
+    },
+    /// More steps than `--max-steps` allows, in the activation named
+    StepLimit {
+        steps: u64,
+        scope: String
+    },
+    TimeLimit {
+        time: std::time::Duration,
+        scope: String
+    },
+    /// More subprogram activations at once than `--max-depth` allows
+    DepthLimit {
+        depth: usize,
+        scope: String
+    },
+    /// More words of variables, in activations or allocated by `new`, than
+    /// `--max-heap` allows
+    HeapLimit(usize),
+    /// More bytes written than `--max-output` allows
+    OutputLimit(usize),
+    /// The debugger was told to quit before the program ended
+    Quit
+}
+
+impl RuntimeError {
+    pub fn new (range: std::ops::Range<usize>, kind: RuntimeErrorKind) -> RuntimeError {
+        RuntimeError {
+            range, kind
+        }
+    }
+}
+
+impl Throwable for RuntimeError {
+    fn position(&self, positioner: &PositionBuilder) -> Position {
+        positioner.pos(self.range.clone())
+    }
+
+    fn title(&self) -> String {
+        "Runtime Error".to_string()
+    }
+
+    fn description(&self) -> String {
+        match &self.kind {
+            RuntimeErrorKind::DivisionByZero => "Division by zero".to_string(),
+            RuntimeErrorKind::Overflow => "Integer overflow".to_string(),
+            RuntimeErrorKind::IndexOutOfRange { index, low, high } =>
+                format!("Index {} is outside of the bounds {}..{}", index, low, high),
+            RuntimeErrorKind::OutOfRange { value, r#type } =>
+                format!("The value {} is outside of {}", value, r#type),
+            RuntimeErrorKind::NilPointer => "The pointer does not point to a variable".to_string(),
+            RuntimeErrorKind::DanglingPointer => "The variable the pointer points to has been disposed".to_string(),
+            RuntimeErrorKind::NoCaseLabel(value) => format!("No case label matches {}", value),
+            RuntimeErrorKind::Domain { function, argument } =>
+                format!("'{}' is not defined for {}", function, argument),
+            RuntimeErrorKind::InvalidInput { expected, got } =>
+                format!("Expected {} in the input, got '{}'", expected, got),
+            RuntimeErrorKind::EndOfInput => "Read past the end of the input".to_string(),
+            RuntimeErrorKind::Output(err) => format!("Could not write the output: {}", err),
+            RuntimeErrorKind::FieldWidth(value) =>
+                format!("Field widths and decimals must be at least 1, got {}", value),
+            RuntimeErrorKind::NotReading => "The file has not been opened for reading with reset".to_string(),
+            RuntimeErrorKind::NotWriting => "The file has not been opened for writing with rewrite".to_string(),
+            RuntimeErrorKind::File { path, message } => format!("Could not open {}: {}", path, message),
+            RuntimeErrorKind::StepLimit { steps, scope } =>
+                format!("Infinite loop suspected after {} steps in {}", count(*steps), scope),
+            RuntimeErrorKind::TimeLimit { time, scope } =>
+                format!("Time limit of {}s exceeded in {}", time.as_secs_f64(), scope),
+            RuntimeErrorKind::DepthLimit { depth, scope } =>
+                format!("Recursion deeper than {} calls in {}", count(*depth as u64), scope),
+            RuntimeErrorKind::HeapLimit(words) =>
+                format!("The variables take more than {} words", count(*words as u64)),
+            RuntimeErrorKind::OutputLimit(bytes) =>
+                format!("The program wrote more than {} bytes", count(*bytes as u64)),
+            RuntimeErrorKind::Quit => "The program was stopped from the debugger".to_string()
+        }
+    }
+
+    fn notes(&self) -> Vec<String> {
+        vec![]
+    }
+}
+
+/// A limit as it is read most easily, powers of ten such as 10^8 included
+fn count(value: u64) -> String {
+    let mut power = 0;
+    let mut rest = value;
+    while rest >= 10 && rest.is_multiple_of(10) {
+        rest /= 10;
+        power += 1;
+    }
+    if rest == 1 && power > 3 {
+        format!("10^{}", power)
+    } else {
+        value.to_string()
+    }
+}
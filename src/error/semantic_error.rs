@@ -1,26 +1,69 @@
-use crate::ast::expression::ExBox;
 use crate::ast::types::Type;
 use crate::error::{Position, PositionBuilder, Throwable};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SemanticError {
     pub range: std::ops::Range<usize>,
     pub kind: SemanticErrorKind
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum SemanticErrorKind {
     TypeError {
         expected: Vec<Type>,
         got: Type,
     },
-    InvalidLimit
+    UnsupportedOperation {
+        operation: String,
+        operand: Type
+    },
+    UndeclaredIdentifier(String),
+    UnknownField {
+        record: Type,
+        field: String
+    },
+    NotAssignable(String),
+    Redeclaration {
+        name: String,
+        first: std::ops::Range<usize>
+    },
+    ArgumentCount {
+        name: String,
+        expected: usize,
+        got: usize
+    },
+    /// An expression passed for a `var` parameter
+    VariableRequired(String),
+    NotCallable(String),
+    NotAValue(String),
+    InvalidLimit,
+    /// A field width given to an argument that is not written to text
+    MisplacedFormat,
+    /// A subrange whose lower limit is above its upper one
+    EmptyRange {
+        low: String,
+        high: String
+    },
+    /// A Turbo Pascal extension used in a program written in standard Pascal
+    Extension(String),
+    /// A `string[n]` of a length strings cannot have
+    StringSize(isize),
+    /// A constant expression that cannot be worked out, such as one that
+    /// divides by zero
+    InvalidConstant(String),
+    /// A `file of` a type whose values are not written one to a line
+    FileComponent(Type),
+    /// An error of a subexpression that already knows its own position
+    Located(Box<SemanticError>)
 }
 
 impl SemanticError {
     pub fn new (range: std::ops::Range<usize>, kind: SemanticErrorKind) -> SemanticError {
-        SemanticError {
-            range, kind
+        match kind {
+            SemanticErrorKind::Located(err) => *err,
+            kind => SemanticError {
+                range, kind
+            }
         }
     }
 }
@@ -31,9 +74,25 @@ impl Throwable for SemanticError {
     }
 
     fn title(&self) -> String {
-        match self.kind {
-            SemanticErrorKind::TypeError { .. } => "Type Error".to_string(),
-            SemanticErrorKind::InvalidLimit => "Invalid Limit".to_string()
+        match &self.kind {
+            SemanticErrorKind::TypeError { .. } |
+            SemanticErrorKind::UnsupportedOperation { .. } |
+            SemanticErrorKind::UnknownField { .. } |
+            SemanticErrorKind::FileComponent(_) => "Type Error".to_string(),
+            SemanticErrorKind::UndeclaredIdentifier(_) => "Undeclared Identifier".to_string(),
+            SemanticErrorKind::NotAssignable(_) => "Invalid Assignment".to_string(),
+            SemanticErrorKind::Redeclaration { .. } => "Redeclaration".to_string(),
+            SemanticErrorKind::ArgumentCount { .. } |
+            SemanticErrorKind::VariableRequired(_) |
+            SemanticErrorKind::NotCallable(_) |
+            SemanticErrorKind::NotAValue(_) |
+            SemanticErrorKind::MisplacedFormat => "Invalid Call".to_string(),
+            SemanticErrorKind::InvalidLimit |
+            SemanticErrorKind::EmptyRange { .. } |
+            SemanticErrorKind::StringSize(_) => "Invalid Limit".to_string(),
+            SemanticErrorKind::Extension(_) => "Extension".to_string(),
+            SemanticErrorKind::InvalidConstant(_) => "Invalid Constant".to_string(),
+            SemanticErrorKind::Located(err) => err.title()
         }
     }
 
@@ -43,25 +102,79 @@ impl Throwable for SemanticError {
                 expected,
                 got
             } => format!("Expected {}{}, got {}",
-                         expected.into_iter()
+                         expected.iter()
                              .take(3)
                              .map(|t| t.to_string())
                              .collect::<Vec<String>>()
                              .join("/"),
                          if expected.len() > 3 { "..." } else { "" },
                          got),
+            SemanticErrorKind::UnsupportedOperation {
+                operation,
+                operand
+            } => format!("'{}' cannot be applied to {}", operation, operand),
+            SemanticErrorKind::UndeclaredIdentifier(name) =>
+                format!("'{}' has not been declared", name),
+            SemanticErrorKind::UnknownField {
+                record,
+                field
+            } => format!("{} has no field '{}'", record, field),
+            SemanticErrorKind::NotAssignable(name) =>
+                format!("'{}' cannot be assigned to", name),
+            SemanticErrorKind::Redeclaration { name, .. } =>
+                format!("'{}' has already been declared in this scope", name),
+            SemanticErrorKind::ArgumentCount {
+                name,
+                expected,
+                got
+            } => format!("'{}' takes {} argument{}, got {}",
+                         name, expected, if *expected == 1 { "" } else { "s" }, got),
+            SemanticErrorKind::VariableRequired(param) =>
+                format!("The var parameter '{}' must be given a variable", param),
+            SemanticErrorKind::NotCallable(name) =>
+                format!("'{}' is not a procedure or function", name),
+            SemanticErrorKind::NotAValue(name) =>
+                format!("'{}' does not have a value", name),
+            SemanticErrorKind::MisplacedFormat =>
+                "Only the values write and writeln write to text, and the value str converts, can be given a field width".to_string(),
             SemanticErrorKind::InvalidLimit =>
-                "This expression cannot be used as a limit".to_string()
+                "This expression cannot be used as a limit".to_string(),
+            SemanticErrorKind::EmptyRange { low, high } =>
+                format!("The range {}..{} holds no value", low, high),
+            SemanticErrorKind::Extension(name) =>
+                format!("'{}' is a Turbo Pascal extension", name),
+            SemanticErrorKind::StringSize(size) =>
+                format!("Strings hold from 1 to 255 characters, not {}", size),
+            SemanticErrorKind::InvalidConstant(fault) =>
+                format!("{} in a constant expression", fault),
+            SemanticErrorKind::FileComponent(ty) =>
+                format!("Files hold integers, reals or chars, one to a line, not {}", ty),
+            SemanticErrorKind::Located(err) => err.description()
         }
     }
 
     fn notes(&self) -> Vec<String> {
-        vec![]
+        match &self.kind {
+            SemanticErrorKind::Extension(_) => vec!["--dialect turbo enables it".to_string()],
+            SemanticErrorKind::FileComponent(_) =>
+                vec!["a file of integers, reals or chars can hold the fields or items one at a time".to_string()],
+            SemanticErrorKind::Located(err) => err.notes(),
+            _ => vec![]
+        }
+    }
+
+    fn related(&self) -> Vec<(String, std::ops::Range<usize>)> {
+        match &self.kind {
+            SemanticErrorKind::Redeclaration { name, first } =>
+                vec![(format!("'{}' was first declared here", name), first.clone())],
+            SemanticErrorKind::Located(err) => err.related(),
+            _ => vec![]
+        }
     }
 }
 
 impl From<SemanticError> for SemanticErrorKind {
     fn from(e: SemanticError) -> SemanticErrorKind {
-        e.kind
+        SemanticErrorKind::Located(Box::new(e))
     }
-}
\ No newline at end of file
+}
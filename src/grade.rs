@@ -0,0 +1,397 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use crate::ast::program::{Checks, Dialect, Program};
+use crate::error::Throwable;
+use crate::error::runtime_error::{RuntimeError, RuntimeErrorKind};
+use crate::interpreter::Interpreter;
+use crate::interpreter::limits::Limits;
+use crate::pcode::compiler::compile;
+use crate::pcode::machine::Machine;
+
+/// How much of the whitespace of an output has to match the expected one
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum Whitespace {
+    /// Every character
+    Exact,
+    /// The lines, leaving out the whitespace they end with and the empty
+    /// lines at the end
+    #[default]
+    Lines,
+    /// The words, however they are spaced
+    Words
+}
+
+impl Whitespace {
+    pub fn parse(name: &str) -> Option<Whitespace> {
+        match name {
+            "exact" => Some(Whitespace::Exact),
+            "lines" => Some(Whitespace::Lines),
+            "words" => Some(Whitespace::Words),
+            _ => None
+        }
+    }
+}
+
+/// How an output is compared with the expected one
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Rules {
+    pub whitespace: Whitespace,
+    /// How far apart two numbers may be, or how far apart relative to the
+    /// expected one when it is larger than 1, and still match
+    pub tolerance: Option<f64>
+}
+
+impl Rules {
+    /// Where `output` first differs from `expected`, if it does
+    pub fn compare(&self, expected: &str, output: &str) -> Option<String> {
+        if self.whitespace == Whitespace::Words {
+            let (mut expected, mut output) = (expected.split_whitespace(), output.split_whitespace());
+            for word in 1.. {
+                match (expected.next(), output.next()) {
+                    (None, None) => return None,
+                    (Some(want), Some(got)) if self.matches(want, got) => {},
+                    (want, got) => return Some(format!("word {}: expected {}, got {}", word, quote(want), quote(got)))
+                }
+            }
+        }
+        let (expected, output) = (self.lines(expected), self.lines(output));
+        for line in 0..expected.len().max(output.len()) {
+            let (want, got) = (expected.get(line).copied(), output.get(line).copied());
+            let same = match (want, got) {
+                (Some(want), Some(got)) if self.tolerance.is_some() => want.split_whitespace().count() == got.split_whitespace().count()
+                    && want.split_whitespace().zip(got.split_whitespace()).all(|(want, got)| self.matches(want, got)),
+                (want, got) => want == got
+            };
+            if !same {
+                return Some(format!("line {}: expected {}, got {}", line + 1, quote(want), quote(got)));
+            }
+        }
+        None
+    }
+
+    fn lines<'t>(&self, text: &'t str) -> Vec<&'t str> {
+        let mut lines = text.split('\n').collect::<Vec<&str>>();
+        if self.whitespace == Whitespace::Lines {
+            lines.iter_mut().for_each(|line| *line = line.trim_end());
+            while lines.last() == Some(&"") {
+                lines.pop();
+            }
+        }
+        lines
+    }
+
+    fn matches(&self, want: &str, got: &str) -> bool {
+        want == got || match (self.tolerance, want.parse::<f64>(), got.parse::<f64>()) {
+            (Some(tolerance), Ok(want), Ok(got)) => (want - got).abs() <= tolerance * want.abs().max(1.0),
+            _ => false
+        }
+    }
+}
+
+fn quote(text: Option<&str>) -> String {
+    text.map_or_else(|| "the end".to_string(), |text| format!("'{}'", text))
+}
+
+/// How a program did on a case
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Ok,
+    /// With where the output first differs from the expected one
+    WrongAnswer(String),
+    RuntimeError(String),
+    /// Stopped by the time or step limit
+    TimeLimit(String),
+    CompileError
+}
+
+impl Verdict {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Verdict::Ok => "OK",
+            Verdict::WrongAnswer(_) => "wrong answer",
+            Verdict::RuntimeError(_) => "runtime error",
+            Verdict::TimeLimit(_) => "time limit",
+            Verdict::CompileError => "compile error"
+        }
+    }
+
+    fn detail(&self) -> Option<&str> {
+        match self {
+            Verdict::WrongAnswer(detail) | Verdict::RuntimeError(detail) | Verdict::TimeLimit(detail) => Some(detail),
+            Verdict::Ok | Verdict::CompileError => None
+        }
+    }
+}
+
+/// An input to run a program on, with the output it should write
+#[derive(Debug, Clone)]
+pub struct Case {
+    pub name: String,
+    pub input: String,
+    pub expected: String
+}
+
+/// The cases in `dir`: every `name.in` with its `name.out`, by name
+pub fn cases(dir: &Path) -> std::io::Result<Vec<Case>> {
+    let mut inputs = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<PathBuf>>>()?;
+    inputs.retain(|path| path.extension().is_some_and(|extension| extension == "in"));
+    inputs.sort();
+    inputs.into_iter()
+        .map(|input| {
+            let output = input.with_extension("out");
+            let expected = std::fs::read_to_string(&output).map_err(|err|
+                std::io::Error::new(err.kind(), format!("{}: {}", output.display(), err)))?;
+            Ok(Case {
+                name: input.file_stem().map_or(String::new(), |stem| stem.to_string_lossy().into_owned()),
+                input: std::fs::read_to_string(&input)?,
+                expected
+            })
+        })
+        .collect()
+}
+
+/// A diagnostic that kept the program from compiling
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub message: String
+}
+
+/// The verdicts on every case, and why the program did not compile if it
+/// did not
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub source: String,
+    pub diagnostics: Vec<Diagnostic>,
+    pub verdicts: Vec<(String, Verdict, Duration)>
+}
+
+impl Report {
+    pub fn passed(&self) -> usize {
+        self.verdicts.iter().filter(|(_, verdict, _)| *verdict == Verdict::Ok).count()
+    }
+
+    pub fn text(&self) -> String {
+        let mut out = format!("Grading {}\n", self.source);
+        for diagnostic in &self.diagnostics {
+            let _ = writeln!(out, "  line {}: {}", diagnostic.line, diagnostic.message);
+        }
+        for (name, verdict, time) in &self.verdicts {
+            let _ = write!(out, "  {:<16} {:<14} {:>9.3} ms", name, verdict.name(), time.as_secs_f64() * 1000.0);
+            let _ = writeln!(out, "{}", verdict.detail().map_or(String::new(), |detail| format!("  {}", detail)));
+        }
+        let _ = writeln!(out, "{}/{} passed", self.passed(), self.verdicts.len());
+        out
+    }
+
+    pub fn json(&self) -> String {
+        let diagnostics = self.diagnostics.iter()
+            .map(|diagnostic| format!("{{\"line\": {}, \"message\": {}}}", diagnostic.line, json(&diagnostic.message)))
+            .collect::<Vec<String>>();
+        let cases = self.verdicts.iter()
+            .map(|(name, verdict, time)| format!("{{\"name\": {}, \"verdict\": {}, \"ms\": {:.3}, \"detail\": {}}}",
+                                                 json(name), json(verdict.name()), time.as_secs_f64() * 1000.0,
+                                                 verdict.detail().map_or("null".to_string(), json)))
+            .collect::<Vec<String>>();
+        format!("{{\n  \"source\": {},\n  \"diagnostics\": [{}],\n  \"cases\": [\n    {}\n  ],\n  \"passed\": {},\n  \"total\": {}\n}}\n",
+                json(&self.source), diagnostics.join(", "), cases.join(",\n    "), self.passed(), self.verdicts.len())
+    }
+}
+
+/// A string as a JSON literal
+fn json(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => { let _ = write!(out, "\\u{:04x}", c as u32); },
+            c => out.push(c)
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// What grading runs a program with
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Setup {
+    pub rules: Rules,
+    pub limits: Limits,
+    pub checks: Checks,
+    pub dialect: Dialect,
+    /// Runs the program on the p-code machine rather than the interpreter
+    pub vm: bool,
+    /// The files of the parameters of the program other than `input` and
+    /// `output`, which every case reads from and writes to
+    pub bindings: Vec<(String, String)>
+}
+
+/// Compiles `src`, which was read from `source`, and runs it on every case
+pub fn grade(source: &str, src: String, cases: &[Case], setup: &Setup) -> Report {
+    let (mut program, errors) = Program::with_dialect(src, setup.dialect);
+    program.checks = setup.checks;
+    let mut diagnostics = program.diagnostics(errors).into_iter()
+        .map(|err| Diagnostic {
+            line: err.position(&program.positioner).line_no + 1,
+            message: err.static_print()
+        })
+        .collect::<Vec<Diagnostic>>();
+    if diagnostics.is_empty() && setup.vm && (program.uses_files() || program.uses_strings()) {
+        diagnostics.push(Diagnostic {
+            line: 1,
+            message: "programs using files or strings only run in the interpreter, without --vm".to_string()
+        });
+    }
+    if let (true, Err(message)) = (diagnostics.is_empty(), program.check_bindings(&setup.bindings)) {
+        diagnostics.push(Diagnostic { line: 1, message });
+    }
+    let verdicts = cases.iter()
+        .map(|case| if diagnostics.is_empty() {
+            let start = Instant::now();
+            let verdict = run(&program, case, setup);
+            (case.name.clone(), verdict, start.elapsed())
+        } else {
+            (case.name.clone(), Verdict::CompileError, Duration::ZERO)
+        })
+        .collect();
+    Report {
+        source: source.to_string(),
+        diagnostics,
+        verdicts
+    }
+}
+
+fn run(program: &Program, case: &Case, setup: &Setup) -> Verdict {
+    let mut output = vec![];
+    let outcome = if setup.vm {
+        let code = compile(program);
+        let mut machine = Machine::new(&code, case.input.as_bytes(), &mut output);
+        machine.limit(setup.limits);
+        machine.run()
+    } else {
+        let mut interpreter = Interpreter::new(program, case.input.as_bytes(), &mut output);
+        interpreter.limit(setup.limits);
+        for (name, path) in &setup.bindings {
+            interpreter.map(name, path.into());
+        }
+        interpreter.run()
+    };
+    match outcome {
+        Ok(()) => match setup.rules.compare(&case.expected, &String::from_utf8_lossy(&output)) {
+            None => Verdict::Ok,
+            Some(difference) => Verdict::WrongAnswer(difference)
+        },
+        Err(err) => {
+            let detail = describe(&err, program);
+            match err.kind {
+                RuntimeErrorKind::StepLimit { .. } | RuntimeErrorKind::TimeLimit { .. } => Verdict::TimeLimit(detail),
+                _ => Verdict::RuntimeError(detail)
+            }
+        }
+    }
+}
+
+fn describe(err: &RuntimeError, program: &Program) -> String {
+    format!("{} at {}", err.description(), err.position(&program.positioner))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comparing_outputs() {
+        let lines = Rules::default();
+        assert_eq!(lines.compare("1 2\n3\n", "1 2  \n3\n\n"), None);
+        assert_eq!(lines.compare("1 2\n3\n", "1  2\n3\n"), Some("line 1: expected '1 2', got '1  2'".to_string()));
+        assert_eq!(lines.compare("1\n2\n", "1\n"), Some("line 2: expected '2', got the end".to_string()));
+        let exact = Rules { whitespace: Whitespace::Exact, ..Rules::default() };
+        assert!(exact.compare("1\n", "1 \n").is_some());
+        let words = Rules { whitespace: Whitespace::Words, ..Rules::default() };
+        assert_eq!(words.compare("1 2\n3\n", "1\n2 3"), None);
+        assert_eq!(words.compare("1 2 3", "1 2 4"), Some("word 3: expected '3', got '4'".to_string()));
+        let close = Rules { tolerance: Some(1e-3), ..Rules::default() };
+        assert_eq!(close.compare("x 3.14159\n", "x 3.1416\n"), None);
+        assert_eq!(close.compare("2000.0\n", "2001.5\n"), None);
+        assert!(close.compare("2.0\n", "2.01\n").is_some());
+        assert!(close.compare("pi\n", "pie\n").is_some());
+    }
+
+    #[test]
+    fn verdicts() {
+        let src = "
+            program halve;
+            var n: integer;
+            begin
+                read(n);
+                while n = 0 do n := 0;
+                writeln(100 div n)
+            end.
+        ";
+        let case = |name: &str, input: &str, expected: &str| Case {
+            name: name.to_string(),
+            input: input.to_string(),
+            expected: expected.to_string()
+        };
+        let cases = [case("ok", "4", "25\n"), case("wrong", "3", "34\n"), case("loop", "0", ""), case("empty", "", "")];
+        let setup = Setup {
+            limits: Limits { steps: Some(10_000), ..Limits::default() },
+            ..Setup::default()
+        };
+        for vm in [false, true] {
+            let report = grade("halve.p", src.to_string(), &cases, &Setup { vm, ..setup.clone() });
+            let verdicts = report.verdicts.iter()
+                .map(|(name, verdict, _)| (name.as_str(), verdict.name()))
+                .collect::<Vec<_>>();
+            assert_eq!(verdicts, vec![("ok", "OK"), ("wrong", "wrong answer"), ("loop", "time limit"), ("empty", "runtime error")]);
+            assert_eq!(report.verdicts[1].1, Verdict::WrongAnswer("line 1: expected '34', got '33'".to_string()));
+            assert!(report.text().ends_with("1/4 passed\n"), "{}", report.text());
+        }
+        let report = grade("broken.p", "program broken; begin x := 1 end.".to_string(), &cases[..1], &setup);
+        assert_eq!(report.verdicts[0].1, Verdict::CompileError);
+        assert_eq!(report.diagnostics.len(), 1);
+        assert!(report.json().contains("\"verdict\": \"compile error\""), "{}", report.json());
+        assert!(report.json().contains("\"passed\": 0,\n  \"total\": 1\n"), "{}", report.json());
+        // the undeclared name is found first, but is listed after the type
+        // error before it on its line
+        let src = "program broken; var i: integer; begin i := 'a'; x := 1 end.";
+        let report = grade("broken.p", src.to_string(), &cases[..1], &setup);
+        let messages = report.diagnostics.iter()
+            .map(|diagnostic| diagnostic.message.split(' ').next().unwrap_or_default())
+            .collect::<Vec<_>>();
+        assert_eq!(messages, vec!["Type", "Undeclared"]);
+    }
+
+    #[test]
+    fn files_bound_to_parameters() {
+        let src = "
+            program total(input, output, data);
+            var data: text; n, sum: integer;
+            begin
+                reset(data); read(n); sum := n;
+                while not eof(data) do begin readln(data, n); sum := sum + n end;
+                writeln(sum)
+            end.
+        ";
+        let path = std::env::temp_dir().join(format!("mini-pascal500-grade-{}.txt", std::process::id()));
+        std::fs::write(&path, "1\n2\n3\n").unwrap();
+        let cases = [Case { name: "sum".to_string(), input: "10".to_string(), expected: "16\n".to_string() }];
+        let setup = Setup {
+            bindings: vec![("data".to_string(), path.to_string_lossy().into_owned())],
+            ..Setup::default()
+        };
+        let report = grade("total.p", src.to_string(), &cases, &setup);
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(report.verdicts[0].1, Verdict::Ok, "{}", report.text());
+        // without the file, the program cannot be run
+        let report = grade("total.p", src.to_string(), &cases, &Setup::default());
+        assert_eq!(report.verdicts[0].1, Verdict::CompileError);
+        assert_eq!(report.diagnostics[0].message, "the parameter 'data' needs a file, given with --bind data=<file>");
+    }
+}
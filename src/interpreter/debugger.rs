@@ -0,0 +1,500 @@
+use std::cell::RefCell;
+use std::io::{BufRead, Read, Write};
+use std::ops::Range;
+use std::rc::Rc;
+use crate::ast::expression::{ExBox, Expression};
+use crate::ast::program::{Program, ScopeId, Symbol};
+use crate::ast::statement::{Statement, StatementKind};
+use crate::ast::types::Type;
+use crate::error::{Printable, PositionBuilder, Throwable};
+use crate::error::runtime_error::{RuntimeError, RuntimeErrorKind};
+use super::{head, Interpreter, Fallible};
+use super::value::Value;
+
+const HELP: &str = "\
+break LINE | break NAME   stop at a line, or on entering a procedure or function
+delete N                  remove breakpoint N
+step                      run to the next statement, entering calls
+next                      run to the next statement of this activation or its callers
+finish                    run until the running subprogram returns
+continue                  run to the next breakpoint or watchpoint
+print VARIABLE            show a variable, such as a[i].f or p^.next
+watch VARIABLE            stop when a variable changes
+backtrace                 show the activations, each with the one its static link is to
+quit                      stop the program
+";
+
+/// A reader the debugger and the program both take lines from, each read
+/// stopping at the end of a line so that neither buffers what the other
+/// is about to read
+pub struct SharedInput<R> {
+    reader: Rc<RefCell<R>>
+}
+
+impl<R: BufRead> SharedInput<R> {
+    pub fn new(reader: R) -> SharedInput<R> {
+        SharedInput {
+            reader: Rc::new(RefCell::new(reader))
+        }
+    }
+}
+
+impl<R> Clone for SharedInput<R> {
+    fn clone(&self) -> SharedInput<R> {
+        SharedInput {
+            reader: self.reader.clone()
+        }
+    }
+}
+
+impl<R: BufRead> Read for SharedInput<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut reader = self.reader.borrow_mut();
+        let available = reader.fill_buf()?;
+        let line = available.iter()
+            .position(|byte| *byte == b'\n')
+            .map_or(available.len(), |end| end + 1);
+        let length = line.min(buf.len());
+        buf[..length].copy_from_slice(&available[..length]);
+        reader.consume(length);
+        Ok(length)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Breakpoint {
+    Line(usize),
+    /// The first statement of a procedure or function
+    Subprogram(String)
+}
+
+/// A variable that stops the program when its value changes
+struct Watch {
+    text: String,
+    variable: ExBox,
+    /// The scope the variable was written in, from which it must be visible
+    scope: ScopeId,
+    value: Option<Value>
+}
+
+/// How far the program runs before the debugger stops it again, if no
+/// breakpoint or watchpoint stops it first
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Continue,
+    Step,
+    /// To a statement of an activation at most this deep
+    Next(usize),
+    /// To a statement of an activation shallower than this
+    Finish(usize)
+}
+
+/// Stops an interpreted program at breakpoints, watchpoints and steps,
+/// and takes commands to inspect it
+pub struct Debugger<'a> {
+    commands: Box<dyn BufRead + 'a>,
+    output: Box<dyn Write + 'a>,
+    /// Numbered from 1, deleted ones being left as `None`
+    breakpoints: Vec<Option<Breakpoint>>,
+    watches: Vec<Watch>,
+    mode: Mode,
+    /// The statement each activation is at, the innermost last
+    positions: Vec<Range<usize>>,
+    /// The subprogram just called, until its first statement
+    entered: Option<String>,
+    /// The line and extent of the last statement, so that a statement
+    /// nested in one on the same line does not stop the program again
+    last: Option<(usize, Range<usize>)>
+}
+
+impl<'a> Debugger<'a> {
+    /// A debugger that starts with the program stopped at its first statement
+    pub fn new(commands: impl BufRead + 'a, output: impl Write + 'a) -> Debugger<'a> {
+        Debugger {
+            commands: Box::new(commands),
+            output: Box::new(output),
+            breakpoints: vec![],
+            watches: vec![],
+            mode: Mode::Step,
+            positions: vec![],
+            entered: None,
+            last: None
+        }
+    }
+
+    /// Notes that a subprogram was called, before its first statement
+    pub fn enter(&mut self, name: &str) {
+        self.entered = Some(name.to_string());
+    }
+
+    /// Called before every statement: stops there if anything says to and
+    /// takes commands until the program is to go on. Tells whether the
+    /// debugger stays attached, which it does not once the commands end.
+    pub fn pause(&mut self, interpreter: &mut Interpreter, statement: &Statement) -> Fallible<bool> {
+        let depth = interpreter.frames.len();
+        self.positions.truncate(depth);
+        self.positions.resize(depth, statement.range.clone());
+        self.positions[depth - 1] = statement.range.clone();
+        if matches!(statement.kind, StatementKind::Compound(_) | StatementKind::Empty) {
+            return Ok(true);
+        }
+        let program = interpreter.program;
+        let head = head(&program.positioner.file, &statement.range);
+        let line = program.positioner.pos(head).line_no + 1;
+
+        let mut reasons = self.changes(interpreter);
+        let entered = self.entered.take();
+        let nested = match &self.last {
+            Some((at, last)) => *at == line && last.start <= statement.range.start
+                && statement.range.end <= last.end && *last != statement.range,
+            None => false
+        };
+        for (number, breakpoint) in self.breakpoints.iter().enumerate() {
+            let hit = match breakpoint {
+                Some(Breakpoint::Line(at)) => *at == line && !nested,
+                Some(Breakpoint::Subprogram(name)) => entered.as_ref() == Some(name),
+                None => false
+            };
+            if hit {
+                reasons.push(format!("Breakpoint {}", number + 1));
+            }
+        }
+        self.last = Some((line, statement.range.clone()));
+        let stepped = match self.mode {
+            Mode::Continue => false,
+            Mode::Step => true,
+            Mode::Next(at) => depth <= at,
+            Mode::Finish(at) => depth < at
+        };
+        if reasons.is_empty() && !stepped {
+            return Ok(true);
+        }
+
+        let reason = if reasons.is_empty() { "Stopped".to_string() } else { reasons.join(", ") };
+        self.stop(interpreter, statement, &reason)
+    }
+
+    /// Called after every statement that ran to its end: stops there if a
+    /// watched variable changed, whatever the mode, so that the change is
+    /// reported where it is made. Tells whether the debugger stays
+    /// attached.
+    pub fn watch(&mut self, interpreter: &mut Interpreter, statement: &Statement) -> Fallible<bool> {
+        if matches!(statement.kind, StatementKind::Compound(_) | StatementKind::Empty) {
+            return Ok(true);
+        }
+        let reasons = self.changes(interpreter);
+        if reasons.is_empty() {
+            return Ok(true);
+        }
+        self.stop(interpreter, statement, &reasons.join(", "))
+    }
+
+    /// Reports why the program stopped at a statement and takes commands
+    /// until it is to go on
+    fn stop(&mut self, interpreter: &mut Interpreter, statement: &Statement, reason: &str) -> Fallible<bool> {
+        let program = interpreter.program;
+        let depth = interpreter.frames.len();
+        let head = head(&program.positioner.file, &statement.range);
+        interpreter.files[super::OUTPUT].flush()
+            .map_err(|err| RuntimeError::new(statement.range.clone(), RuntimeErrorKind::Output(err.to_string())))?;
+        let name = scope_name(program, interpreter.frames[depth - 1].scope);
+        self.say(&format!("{} in {} at {}", reason, name, program.positioner.pos(head).trace()));
+        loop {
+            let _ = write!(self.output, "(debug) ");
+            let _ = self.output.flush();
+            let mut command = String::new();
+            match self.commands.read_line(&mut command) {
+                Ok(0) | Err(_) => return Ok(false),
+                Ok(_) => {}
+            }
+            let command = command.trim();
+            let (word, argument) = match command.find(char::is_whitespace) {
+                Some(split) => (&command[..split], command[split..].trim()),
+                None => (command, "")
+            };
+            self.mode = match word {
+                "" => continue,
+                "step" | "s" => Mode::Step,
+                "next" | "n" => Mode::Next(depth),
+                "finish" | "f" => Mode::Finish(depth),
+                "continue" | "c" => Mode::Continue,
+                "break" | "b" => {
+                    self.set_breakpoint(program, argument);
+                    continue;
+                },
+                "delete" | "d" => {
+                    match argument.parse::<usize>().ok().and_then(|number| self.breakpoints.get_mut(number.wrapping_sub(1))) {
+                        Some(breakpoint @ Some(_)) => *breakpoint = None,
+                        _ => self.say(&format!("No breakpoint {}\n", argument))
+                    }
+                    continue;
+                },
+                "print" | "p" => {
+                    if let Some((variable, ty)) = self.variable(interpreter, argument) {
+                        // a variable that cannot be evaluated is not named
+                        let shown = match interpreter.eval(&variable) {
+                            Ok(value) => format!("{} = {}", argument, show(&value, &ty)),
+                            Err(err) => err.static_print()
+                        };
+                        self.say(&format!("{}\n", shown));
+                    }
+                    continue;
+                },
+                "watch" | "w" => {
+                    if let Some((variable, _)) = self.variable(interpreter, argument) {
+                        let value = interpreter.eval(&variable).ok();
+                        self.watches.push(Watch {
+                            text: argument.to_string(),
+                            variable,
+                            scope: interpreter.frames[depth - 1].scope,
+                            value
+                        });
+                        self.say(&format!("Watchpoint {}: {}\n", self.watches.len(), argument));
+                    }
+                    continue;
+                },
+                "backtrace" | "bt" | "where" => {
+                    self.backtrace(interpreter);
+                    continue;
+                },
+                "quit" | "q" => return Err(RuntimeError::new(statement.range.clone(), RuntimeErrorKind::Quit)),
+                "help" | "h" => {
+                    self.say(HELP);
+                    continue;
+                },
+                _ => {
+                    self.say(&format!("Unknown command '{}', try help\n", word));
+                    continue;
+                }
+            };
+            return Ok(true);
+        }
+    }
+
+    fn say(&mut self, text: &str) {
+        let _ = write!(self.output, "{}", text);
+    }
+
+    fn set_breakpoint(&mut self, program: &Program, argument: &str) {
+        let breakpoint = match argument.parse::<usize>() {
+            Ok(line) => Breakpoint::Line(line),
+            Err(_) if program.scopes.iter().any(|scope| scope.subprograms.contains_key(argument)) =>
+                Breakpoint::Subprogram(argument.to_string()),
+            Err(_) => return self.say(&format!("No line or subprogram '{}'\n", argument))
+        };
+        let place = match &breakpoint {
+            Breakpoint::Line(line) => format!("line {}", line),
+            Breakpoint::Subprogram(name) => name.clone()
+        };
+        self.breakpoints.push(Some(breakpoint));
+        self.say(&format!("Breakpoint {} at {}\n", self.breakpoints.len(), place));
+    }
+
+    /// Parses and checks a variable written in the running activation,
+    /// along with its type, reporting it if it is not one
+    fn variable(&mut self, interpreter: &Interpreter, text: &str) -> Option<(ExBox, Type)> {
+        let program = interpreter.program;
+        let scope = interpreter.frames.last().expect("a program is running").scope;
+        let positioner = PositionBuilder::new(text.to_string());
+        let variable = match program.reference(text, scope) {
+            Ok(variable) => variable,
+            Err(err) => {
+                self.say(&Printable::new(err, &positioner).to_string());
+                return None;
+            }
+        };
+        if let Some(err) = variable.validate(program).into_iter().next() {
+            self.say(&Printable::new(err, &positioner).to_string());
+            return None;
+        }
+        let name = variable.as_variable().expect("references are variables").name().to_string();
+        match program.lookup(scope, &name) {
+            Some((_, Symbol::Variable(_))) => {
+                let ty = variable.get_type(program);
+                Some((variable, ty))
+            },
+            _ => {
+                self.say(&format!("'{}' is not a variable\n", name));
+                None
+            }
+        }
+    }
+
+    /// The watched variables whose values changed since they were last
+    /// looked at, as the reasons for stopping
+    fn changes(&mut self, interpreter: &mut Interpreter) -> Vec<String> {
+        let program = interpreter.program;
+        let scope = interpreter.frames.last().expect("a program is running").scope;
+        let mut reasons = vec![];
+        for (number, watch) in self.watches.iter_mut().enumerate() {
+            if !program.encloses(watch.scope, scope) {
+                continue;
+            }
+            let value = interpreter.eval(&watch.variable).ok();
+            if value != watch.value {
+                let ty = watch.variable.get_type(program);
+                let shown = |value: &Option<Value>| value.as_ref()
+                    .map_or("nothing".to_string(), |value| show(value, &ty));
+                reasons.push(format!("Watchpoint {}: {} changed from {} to {}",
+                                     number + 1, watch.text, shown(&watch.value), shown(&value)));
+                watch.value = value;
+            }
+        }
+        reasons
+    }
+
+    fn backtrace(&mut self, interpreter: &Interpreter) {
+        let program = interpreter.program;
+        let depth = interpreter.frames.len();
+        for (index, frame) in interpreter.frames.iter().enumerate().rev() {
+            let at = head(&program.positioner.file, &self.positions[index]);
+            let mut text = format!("#{} {} at {}", depth - 1 - index, scope_name(program, frame.scope),
+                                   program.positioner.pos(at));
+            if let Some(link) = frame.link {
+                text.push_str(&format!(", static link to #{} {}", depth - 1 - link,
+                                       scope_name(program, interpreter.frames[link].scope)));
+            }
+            self.say(&format!("{}\n", text));
+        }
+    }
+}
+
+/// The name of the subprogram a scope belongs to, or of the program
+fn scope_name(program: &Program, scope: ScopeId) -> String {
+    program.scopes[scope].parent
+        .and_then(|parent| program.scopes[parent].subprograms.values()
+            .find(|subprogram| subprogram.scope == scope))
+        .map_or_else(|| program.name.clone(), |subprogram| subprogram.name.clone())
+}
+
+/// A value as it would be written in Pascal, structures included
+fn show(value: &Value, ty: &Type) -> String {
+    match (value, ty) {
+        (Value::Real(value), _) => value.to_string(),
+        (Value::Set(members), Type::SetOf(member)) => format!("[{}]", members.iter()
+            .map(|ordinal| member.ordinal_name(*ordinal as isize))
+            .collect::<Vec<String>>()
+            .join(", ")),
+        (Value::Array { .. }, _) if ty.is_string() =>
+            format!("'{}'", value.chars().unwrap_or_default()),
+        (Value::Array { items, .. }, _) => {
+            let element = ty.dimension().map(|(_, element)| element).unwrap_or(Type::Invalid);
+            format!("({})", items.iter()
+                .map(|item| show(item, &element))
+                .collect::<Vec<String>>()
+                .join(", "))
+        },
+        (Value::Record(values), _) => format!("({})", ty.fields().iter()
+            .map(|(name, ty)| format!("{}: {}", name, show(&values[*name], ty)))
+            .collect::<Vec<String>>()
+            .join("; ")),
+        (Value::Pointer(_), _) => value.to_string(),
+        (value, ty) if ty.is_ordinal() => ty.ordinal_name(value.ordinal() as isize),
+        (value, _) => value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = "
+        program demo;
+        type point = record x, y: integer end;
+        var a: array [1..3] of point; i, total: integer;
+        function fact(n: integer): integer;
+            procedure note;
+            begin
+                total := total + n
+            end;
+        begin
+            note;
+            if n < 2 then fact := 1 else fact := n * fact(n - 1)
+        end;
+        begin
+            for i := 1 to 3 do
+                a[i].x := i * 10;
+            writeln(fact(3), ' ', total)
+        end.
+    ";
+
+    /// Runs the program under the debugger, returning what the debugger
+    /// wrote and what the program did
+    fn debug(commands: &str) -> (String, String) {
+        let (program, errors) = Program::new(PROGRAM.to_string());
+        assert!(errors.is_empty(), "{:?}", errors);
+        let (mut transcript, mut output) = (vec![], vec![]);
+        let mut interpreter = Interpreter::new(&program, "".as_bytes(), &mut output);
+        interpreter.attach(Debugger::new(commands.as_bytes(), &mut transcript));
+        let outcome = interpreter.run();
+        drop(interpreter);
+        assert!(outcome.is_ok() || outcome.unwrap_err().kind == RuntimeErrorKind::Quit);
+        (String::from_utf8(transcript).unwrap(), String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn breakpoints_and_backtraces() {
+        let (transcript, output) = debug("break 16\nbreak note\nc\nc\nprint i\ndelete 1\nc\nprint n\nbacktrace\n\
+                                          print a\ndelete 2\nc\n");
+        assert!(transcript.starts_with("Stopped in demo at "), "{}", transcript);
+        // the body of the loop stops the program on every iteration
+        assert_eq!(transcript.matches("Breakpoint 1 in demo at ").count(), 2, "{}", transcript);
+        assert!(transcript.contains("i = 2\n"), "{}", transcript);
+        assert!(transcript.contains("Breakpoint 2 in note at "), "{}", transcript);
+        assert!(transcript.contains("n = 3\n"), "{}", transcript);
+        assert!(transcript.contains("#0 note at line 8, 16..34, static link to #1 fact\n\
+                                     #1 fact at line 11, 12..16, static link to #2 demo\n\
+                                     #2 demo at line 17, 12..40\n"), "{}", transcript);
+        assert!(transcript.contains("a = ((x: 10; y: 0), (x: 20; y: 0), (x: 30; y: 0))\n"), "{}", transcript);
+        assert_eq!(transcript.matches("Breakpoint 2 in").count(), 1, "{}", transcript);
+        assert_eq!(output, "6 6\n");
+    }
+
+    #[test]
+    fn stepping() {
+        let (transcript, _) = debug("break note\nc\nfinish\nnext\nprint n\nstep\nstep\nprint n\nquit\n");
+        let stops = transcript.split("(debug) ").collect::<Vec<_>>();
+        // finishing `note` goes back to `fact`, next skips over the call in the `if`
+        assert!(stops[3].starts_with("Stopped in fact at "), "{}", stops[3]);
+        assert!(stops[4].starts_with("Stopped in fact at "), "{}", stops[4]);
+        assert!(stops[5].starts_with("n = 3"), "{}", stops[5]);
+        assert!(stops[6].starts_with("Stopped in fact at "), "{}", stops[6]);
+        assert!(stops[7].starts_with("Breakpoint 1 in note at "), "{}", stops[7]);
+        assert!(stops[8].starts_with("n = 2"), "{}", stops[8]);
+    }
+
+    #[test]
+    fn watchpoints_and_variables() {
+        let (transcript, _) = debug("watch a[2].x\nwatch total\nc\nprint a[i].x\nprint a[4]\nprint q\nprint fact\nc\n");
+        // the program stops at the assignment, before the loop variable moves on
+        assert!(transcript.contains("Watchpoint 1: a[2].x changed from 0 to 20 in demo at line 16, "), "{}", transcript);
+        assert!(transcript.contains("a[i].x = 20\n"), "{}", transcript);
+        assert!(transcript.contains("(debug) Runtime Error Index 4 is outside of the bounds 1..3\n"), "{}", transcript);
+        assert!(!transcript.contains("a[4] ="), "{}", transcript);
+        assert!(transcript.contains("'q' has not been declared"), "{}", transcript);
+        assert!(transcript.contains("'fact' takes 1 argument"), "{}", transcript);
+        assert!(transcript.contains("Watchpoint 2: total changed from 0 to 3 in note at line 8, "), "{}", transcript);
+    }
+
+    #[test]
+    fn stepping_over_a_watched_assignment() {
+        let (transcript, _) = debug("watch total\nbreak 17\nc\nnext\nprint n\nnext\nquit\n");
+        let stops = transcript.split("(debug) ").collect::<Vec<_>>();
+        // next would run the whole call, but stops where the call changes total
+        assert!(stops[3].starts_with("Breakpoint 1 in demo at line 17, "), "{}", stops[3]);
+        assert!(stops[4].starts_with("Watchpoint 1: total changed from 0 to 3 in note at line 8, "), "{}", stops[4]);
+        assert!(stops[5].starts_with("n = 3"), "{}", stops[5]);
+        assert!(stops[6].starts_with("Stopped in fact at line 12, "), "{}", stops[6]);
+    }
+
+    #[test]
+    fn shared_input_stops_at_lines() {
+        let input = SharedInput::new("step\n42\nquit\n".as_bytes());
+        let (mut first, mut second) = (std::io::BufReader::new(input.clone()), std::io::BufReader::new(input));
+        let mut line = String::new();
+        first.read_line(&mut line).unwrap();
+        second.read_line(&mut line).unwrap();
+        first.read_line(&mut line).unwrap();
+        assert_eq!(line, "step\n42\nquit\n");
+    }
+}
@@ -0,0 +1,227 @@
+use std::cell::RefCell;
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Cursor, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+use crate::ast::types::Type;
+use crate::error::runtime_error::RuntimeErrorKind;
+use super::io::Input;
+use super::value::Value;
+
+/// What a file not mapped to one on disk holds
+type Contents = Rc<RefCell<Vec<u8>>>;
+
+/// Appends what is written to the contents of a file kept in memory
+struct Memory(Contents);
+
+impl Write for Memory {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+enum Mode<'a> {
+    Closed,
+    Reading(Input<'a>),
+    Writing(Box<dyn Write + 'a>)
+}
+
+/// A file of the running program. Text files hold their characters as
+/// they are, while the other files hold one element to a line, so that
+/// both can be written and read with a text editor.
+pub struct Stream<'a> {
+    /// The file on disk a program parameter is mapped to; the other files
+    /// only live in memory
+    path: Option<PathBuf>,
+    contents: Contents,
+    mode: Mode<'a>,
+    /// The standard input and output, which are open from the start
+    standard: bool,
+    /// The type of the elements, as of the last `reset` or `rewrite`
+    element: Type,
+    /// The buffer variable `f^`
+    buffer: Value,
+    /// Whether the buffer holds the next element of a typed file being read
+    full: bool
+}
+
+impl Default for Stream<'_> {
+    fn default() -> Self {
+        Stream {
+            path: None,
+            contents: Contents::default(),
+            mode: Mode::Closed,
+            standard: false,
+            element: Type::Char,
+            buffer: Value::Char(' '),
+            full: false
+        }
+    }
+}
+
+impl<'a> Stream<'a> {
+    pub fn standard_input(input: Input<'a>) -> Stream<'a> {
+        Stream {
+            mode: Mode::Reading(input),
+            standard: true,
+            ..Stream::default()
+        }
+    }
+
+    pub fn standard_output(output: impl Write + 'a) -> Stream<'a> {
+        Stream {
+            mode: Mode::Writing(Box::new(output)),
+            standard: true,
+            ..Stream::default()
+        }
+    }
+
+    /// Has the file read from and written to `path` rather than memory
+    pub fn map(&mut self, path: PathBuf) {
+        self.path = Some(path);
+    }
+
+    fn failed(&self, err: io::Error) -> RuntimeErrorKind {
+        RuntimeErrorKind::File {
+            path: self.path.as_ref().map(|path| path.display().to_string()).unwrap_or_default(),
+            message: err.to_string()
+        }
+    }
+
+    /// Opens the file for reading from its start, as `reset` does
+    pub fn reset(&mut self, element: Type) -> Result<(), RuntimeErrorKind> {
+        if self.standard {
+            return Ok(());
+        }
+        self.flush().map_err(|err| RuntimeErrorKind::Output(err.to_string()))?;
+        let input = match &self.path {
+            Some(path) => Input::new(BufReader::new(fs::File::open(path).map_err(|err| self.failed(err))?)),
+            None => Input::new(Cursor::new(self.contents.borrow().clone()))
+        };
+        self.mode = Mode::Reading(input);
+        self.buffer = Value::default_of(&element);
+        self.element = element;
+        self.full = false;
+        if self.element != Type::Char {
+            self.fill()?;
+        }
+        Ok(())
+    }
+
+    /// Empties the file and opens it for writing, as `rewrite` does
+    pub fn rewrite(&mut self, element: Type) -> Result<(), RuntimeErrorKind> {
+        if self.standard {
+            return Ok(());
+        }
+        self.flush().map_err(|err| RuntimeErrorKind::Output(err.to_string()))?;
+        self.mode = Mode::Writing(match &self.path {
+            Some(path) => Box::new(BufWriter::new(fs::File::create(path).map_err(|err| self.failed(err))?)),
+            None => {
+                self.contents.borrow_mut().clear();
+                Box::new(Memory(self.contents.clone()))
+            }
+        });
+        self.buffer = Value::default_of(&element);
+        self.element = element;
+        self.full = false;
+        Ok(())
+    }
+
+    pub fn input(&mut self) -> Result<&mut Input<'a>, RuntimeErrorKind> {
+        match &mut self.mode {
+            Mode::Reading(input) => Ok(input),
+            _ => Err(RuntimeErrorKind::NotReading)
+        }
+    }
+
+    pub fn output(&mut self) -> Result<&mut (dyn Write + 'a), RuntimeErrorKind> {
+        match &mut self.mode {
+            Mode::Writing(output) => Ok(output.as_mut()),
+            _ => Err(RuntimeErrorKind::NotWriting)
+        }
+    }
+
+    /// Whether a file being read has nothing left, a file being written
+    /// always being at its end
+    pub fn eof(&mut self) -> Result<bool, RuntimeErrorKind> {
+        let full = self.full;
+        match self.mode {
+            Mode::Writing(_) => Ok(true),
+            _ if self.element != Type::Char => self.input().map(|_| !full),
+            _ => self.input().map(Input::eof)
+        }
+    }
+
+    pub fn eoln(&mut self) -> Result<bool, RuntimeErrorKind> {
+        self.input().map(Input::eoln)
+    }
+
+    /// The buffer variable, which shows the next character of a text being
+    /// read, the end of a line showing as a space
+    pub fn buffer(&mut self) -> &mut Value {
+        if self.element == Type::Char {
+            if let Mode::Reading(input) = &mut self.mode {
+                self.buffer = Value::Char(match input.peek() {
+                    Some('\n') | None => ' ',
+                    Some(c) => c
+                });
+            }
+        }
+        &mut self.buffer
+    }
+
+    /// Moves on to the next element of a file being read
+    pub fn get(&mut self) -> Result<(), RuntimeErrorKind> {
+        if self.element == Type::Char {
+            self.input()?.read_char().map(|_| ())
+        } else if self.full {
+            self.fill()
+        } else {
+            self.input().and(Err(RuntimeErrorKind::EndOfInput))
+        }
+    }
+
+    /// Reads the next element of a typed file into the buffer, if there is one
+    fn fill(&mut self) -> Result<(), RuntimeErrorKind> {
+        let element = self.element.host();
+        let input = self.input()?;
+        if element != Type::Char {
+            input.skip_whitespace();
+        }
+        if input.eof() {
+            self.full = false;
+            return Ok(());
+        }
+        let value = match element {
+            Type::Integer => Value::Integer(input.read_integer()?),
+            Type::Real => Value::Real(input.read_real()?),
+            _ => Value::Char(input.read_char()?)
+        };
+        input.skip_line()?;
+        self.buffer = value;
+        self.full = true;
+        Ok(())
+    }
+
+    /// The text `put` writes for the buffer
+    pub fn encoded(&self) -> String {
+        match (&self.element, &self.buffer) {
+            (Type::Char, value) => value.to_string(),
+            // reals are written so that they read back as they were
+            (_, Value::Real(value)) => format!("{:e}\n", value),
+            (_, value) => format!("{}\n", value)
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        match &mut self.mode {
+            Mode::Writing(output) => output.flush(),
+            _ => Ok(())
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use crate::error::runtime_error::RuntimeErrorKind;
+use crate::interpreter::value::Value;
+
+/// The digits of the exponent of a real in floating-point form, more only
+/// being written when the exponent needs them
+const EXPONENT_DIGITS: usize = 2;
+
+/// The field a real is written in when `write` gives it no width
+pub const REAL_WIDTH: usize = 22;
+
+/// A field width or a number of decimals, which must be at least 1
+pub fn field(value: i64) -> Result<usize, RuntimeErrorKind> {
+    if value < 1 {
+        Err(RuntimeErrorKind::FieldWidth(value))
+    } else {
+        Ok(value as usize)
+    }
+}
+
+/// `text` right-aligned in a field of `width` characters, as integers and
+/// characters are written; text longer than the field is written whole
+pub fn pad(text: &str, width: usize) -> String {
+    format!("{:>width$}", text, width = width)
+}
+
+/// `text` in exactly `width` characters, as strings and booleans are
+/// written: right-aligned, or cut to its first `width` characters
+pub fn fit(text: &str, width: usize) -> String {
+    pad(&text.chars().take(width).collect::<String>(), width)
+}
+
+/// A real in floating-point form, `x:width`: a sign, one digit before the
+/// point and as many after it as fill `width`, then the exponent
+pub fn floating(value: f64, width: usize) -> String {
+    if !value.is_finite() {
+        return pad(&value.to_string(), width);
+    }
+    let width = width.max(EXPONENT_DIGITS + 6);
+    let text = format!("{:.*e}", width - EXPONENT_DIGITS - 5, value.abs());
+    let (mantissa, exponent) = text.split_once('e').expect("the exponent is written");
+    let exponent: i32 = exponent.parse().expect("the exponent is a number");
+    format!("{}{}E{}{:0digits$}",
+        if value < 0.0 { '-' } else { ' ' },
+        mantissa,
+        if exponent < 0 { '-' } else { '+' },
+        exponent.abs(),
+        digits = EXPONENT_DIGITS)
+}
+
+/// A real in fixed-point form, `x:width:decimals`, right-aligned
+pub fn fixed(value: f64, width: usize, decimals: usize) -> String {
+    // -0.0 + 0.0 is 0.0, which is written without a sign
+    pad(&format!("{:.*}", decimals, value + 0.0), width)
+}
+
+/// The number `val` reads from `text`, an integer or, if `real`, a real
+/// in Pascal notation after any leading spaces; otherwise the position
+/// from 1 of the character that does not fit, one past the end if the
+/// number is cut short
+pub fn number(text: &str, real: bool) -> Result<Value, usize> {
+    let chars = text.chars().collect::<Vec<char>>();
+    let mut at = chars.iter().take_while(|c| **c == ' ').count();
+    let start = at;
+    let sign = |at: &mut usize| if matches!(chars.get(*at), Some('+') | Some('-')) { *at += 1 };
+    let digits = |at: &mut usize| {
+        let from = *at;
+        while chars.get(*at).is_some_and(char::is_ascii_digit) {
+            *at += 1;
+        }
+        *at > from
+    };
+    sign(&mut at);
+    let mut valid = digits(&mut at);
+    if real {
+        if chars.get(at) == Some(&'.') {
+            at += 1;
+            valid |= digits(&mut at);
+        }
+        if valid && matches!(chars.get(at), Some('e') | Some('E')) {
+            at += 1;
+            sign(&mut at);
+            valid = digits(&mut at);
+        }
+    }
+    if !valid || at < chars.len() {
+        return Err(at + 1);
+    }
+    let number = chars[start..].iter().collect::<String>();
+    if real {
+        number.parse().map(Value::Real).map_err(|_| at)
+    } else {
+        // too large a number does not fit at its last digit
+        number.parse().map(Value::Integer).map_err(|_| at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conformance() {
+        let cases = [
+            (pad("42", 5), "   42"),
+            (pad("-42", 2), "-42"),
+            (pad("a", 3), "  a"),
+            (fit("true", 6), "  true"),
+            (fit("false", 3), "fal"),
+            (fit("hello", 5), "hello"),
+            (floating(1.5, 10), " 1.500E+00"),
+            (floating(-1.5, 10), "-1.500E+00"),
+            (floating(0.0, 10), " 0.000E+00"),
+            (floating(-0.0, 10), " 0.000E+00"),
+            (floating(123456.789, 1), " 1.2E+05"),
+            (floating(1e-7, 12), " 1.00000E-07"),
+            (floating(9.99999, 8), " 1.0E+01"),
+            (floating(1e300, 9), " 1.00E+300"),
+            (floating(f64::INFINITY, 5), "  inf"),
+            (floating(1.0 / 3.0, REAL_WIDTH), " 3.333333333333333E-01"),
+            (floating(3.5, REAL_WIDTH), " 3.500000000000000E+00"),
+            (floating(-1e-300, REAL_WIDTH), "-1.000000000000000E-300"),
+            (fixed(12.3456, 10, 2), "     12.35"),
+            (fixed(-12.3456, 1, 3), "-12.346"),
+            (fixed(0.125, 5, 2), " 0.12"),
+            (fixed(0.375, 5, 2), " 0.38"),
+            (fixed(2.5, 1, 1), "2.5"),
+            (fixed(-0.001, 6, 2), " -0.00"),
+            (fixed(-0.0, 6, 2), "  0.00"),
+            (fixed(1e21, 1, 1), "1000000000000000000000.0"),
+            (fixed(f64::NAN, 5, 2), "  NaN"),
+        ];
+        for (got, expected) in &cases {
+            assert_eq!(got, expected);
+        }
+        assert_eq!(field(0), Err(RuntimeErrorKind::FieldWidth(0)));
+        assert_eq!(field(3), Ok(3));
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(number("42", false), Ok(Value::Integer(42)));
+        assert_eq!(number("  -7", false), Ok(Value::Integer(-7)));
+        assert_eq!(number("1.5e2", true), Ok(Value::Real(150.0)));
+        assert_eq!(number("2.", true), Ok(Value::Real(2.0)));
+        assert_eq!(number("12a", false), Err(3));
+        assert_eq!(number("1.5", false), Err(2));
+        assert_eq!(number("", false), Err(1));
+        assert_eq!(number("-", true), Err(2));
+        assert_eq!(number("3e", true), Err(3));
+        assert_eq!(number("99999999999999999999", false), Err(20));
+    }
+}
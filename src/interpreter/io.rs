@@ -0,0 +1,143 @@
+use std::io::BufRead;
+use crate::ast::types::Type;
+use crate::error::runtime_error::RuntimeErrorKind;
+
+/// The text a program reads, a line at a time, so that `eoln` and `eof` can
+/// look ahead without blocking on more input than the current line
+pub struct Input<'a> {
+    reader: Box<dyn BufRead + 'a>,
+    line: Vec<char>,
+    position: usize,
+    ended: bool
+}
+
+impl<'a> Input<'a> {
+    pub fn new(reader: impl BufRead + 'a) -> Input<'a> {
+        Input {
+            reader: Box::new(reader),
+            line: vec![],
+            position: 0,
+            ended: false
+        }
+    }
+
+    /// The next character, without consuming it
+    pub fn peek(&mut self) -> Option<char> {
+        if self.position >= self.line.len() && !self.ended {
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Ok(0) | Err(_) => self.ended = true,
+                Ok(_) => {
+                    // a last line without a newline still ends like the others
+                    if !line.ends_with('\n') {
+                        line.push('\n');
+                    }
+                    self.line = line.replace("\r\n", "\n").chars().collect();
+                    self.position = 0;
+                }
+            }
+        }
+        self.line.get(self.position).copied()
+    }
+
+    pub fn eof(&mut self) -> bool {
+        self.peek().is_none()
+    }
+
+    pub fn eoln(&mut self) -> bool {
+        matches!(self.peek(), Some('\n') | None)
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.position += 1;
+        c
+    }
+
+    pub fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(c) if c.is_whitespace()) {
+            self.next();
+        }
+    }
+
+    /// Takes characters for as long as `accepts` holds for them
+    fn take_while(&mut self, accepts: impl Fn(char) -> bool) -> String {
+        let mut out = String::new();
+        while let Some(c) = self.peek().filter(|c| accepts(*c)) {
+            out.push(c);
+            self.next();
+        }
+        out
+    }
+
+    /// Reads a character, the end of a line reading as a space
+    pub fn read_char(&mut self) -> Result<char, RuntimeErrorKind> {
+        match self.next() {
+            Some('\n') => Ok(' '),
+            Some(c) => Ok(c),
+            None => Err(RuntimeErrorKind::EndOfInput)
+        }
+    }
+
+    fn read_number(&mut self, expected: Type) -> Result<String, RuntimeErrorKind> {
+        self.skip_whitespace();
+        if self.eof() {
+            return Err(RuntimeErrorKind::EndOfInput);
+        }
+        let mut number = self.take_while(|c| c == '+' || c == '-');
+        number.push_str(&self.take_while(|c| c.is_ascii_digit()));
+        if expected == Type::Real {
+            if self.peek() == Some('.') {
+                self.next();
+                number.push('.');
+                number.push_str(&self.take_while(|c| c.is_ascii_digit()));
+            }
+            if matches!(self.peek(), Some('e') | Some('E')) {
+                self.next();
+                number.push('e');
+                number.push_str(&self.take_while(|c| c == '+' || c == '-'));
+                number.push_str(&self.take_while(|c| c.is_ascii_digit()));
+            }
+        }
+        if number.is_empty() {
+            number = self.take_while(|c| !c.is_whitespace());
+        }
+        Ok(number)
+    }
+
+    pub fn read_integer(&mut self) -> Result<i64, RuntimeErrorKind> {
+        let number = self.read_number(Type::Integer)?;
+        number.parse().map_err(|_| RuntimeErrorKind::InvalidInput {
+            expected: Type::Integer,
+            got: number
+        })
+    }
+
+    pub fn read_real(&mut self) -> Result<f64, RuntimeErrorKind> {
+        let number = self.read_number(Type::Real)?;
+        number.parse().map_err(|_| RuntimeErrorKind::InvalidInput {
+            expected: Type::Real,
+            got: number
+        })
+    }
+
+    /// Reads the rest of the current line into a string, leaving its end
+    /// for `readln` to skip
+    pub fn read_rest(&mut self) -> Result<String, RuntimeErrorKind> {
+        if self.eof() {
+            return Err(RuntimeErrorKind::EndOfInput);
+        }
+        Ok(self.take_while(|c| c != '\n'))
+    }
+
+    /// Skips the rest of the current line, as `readln` does
+    pub fn skip_line(&mut self) -> Result<(), RuntimeErrorKind> {
+        loop {
+            match self.next() {
+                Some('\n') => return Ok(()),
+                Some(_) => {},
+                None => return Err(RuntimeErrorKind::EndOfInput)
+            }
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::time::{Duration, Instant};
+use crate::error::runtime_error::RuntimeErrorKind;
+
+/// The stack of the thread programs are interpreted on, which deeply
+/// recursive programs need more of than the main thread has, and builds
+/// without optimizations more still
+pub const STACK: usize = if cfg!(debug_assertions) { 2048 } else { 512 } * 1024 * 1024;
+
+/// Generously, the stack an activation of a subprogram takes as it is
+/// interpreted, its statements nested a few deep
+const ACTIVATION: usize = if cfg!(debug_assertions) { 160 * 1024 } else { 16 * 1024 };
+
+/// How much a program may do before it is stopped, so that untrusted
+/// programs can be run. A limit that is not set is not checked, but for
+/// the depth, which is always kept within what `STACK` holds, and the
+/// heap, which is always kept within `HEAP`.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct Limits {
+    /// The statements the interpreter, or the instructions the p-code
+    /// machine, may run
+    pub steps: Option<u64>,
+    /// The words the variables may take: those of the running activations,
+    /// the program's own among them, and those allocated by `new` and not
+    /// disposed of yet, no more than `HEAP`
+    pub heap: Option<usize>,
+    /// The subprogram activations that may be running at once, no more
+    /// than `DEPTH`
+    pub depth: Option<usize>,
+    /// The bytes the program may write
+    pub output: Option<usize>,
+    pub time: Option<Duration>
+}
+
+impl Limits {
+    /// The limits of `--sandbox`, for the ones not given on their own
+    pub const SANDBOX: Limits = Limits {
+        steps: Some(100_000_000),
+        heap: Some(1 << 24),
+        depth: Some(10_000),
+        output: Some(1 << 20),
+        time: Some(Duration::from_secs(10))
+    };
+
+    /// The activations the interpreter has the stack for
+    pub const DEPTH: usize = STACK / ACTIVATION;
+
+    /// The words any program may take, so that one too big for the memory
+    /// of the machine is stopped with an error rather than aborted as it
+    /// fails to allocate
+    pub const HEAP: usize = 1 << 26;
+
+    /// These limits, with the ones not set taken from `other`
+    pub fn or(self, other: Limits) -> Limits {
+        Limits {
+            steps: self.steps.or(other.steps),
+            heap: self.heap.or(other.heap),
+            depth: self.depth.or(other.depth),
+            output: self.output.or(other.output),
+            time: self.time.or(other.time)
+        }
+    }
+}
+
+/// How many steps between two looks at the clock
+const CLOCK_INTERVAL: u64 = 1024;
+
+/// Keeps count of what a running program has used against its limits
+#[derive(Debug)]
+pub struct Meter {
+    limits: Limits,
+    steps: u64,
+    heap: usize,
+    depth: usize,
+    output: usize,
+    start: Instant
+}
+
+impl Meter {
+    pub fn new(limits: Limits) -> Meter {
+        Meter {
+            limits: Limits {
+                depth: Some(limits.depth.map_or(Limits::DEPTH, |depth| depth.min(Limits::DEPTH))),
+                heap: Some(limits.heap.map_or(Limits::HEAP, |heap| heap.min(Limits::HEAP))),
+                ..limits
+            },
+            steps: 0,
+            heap: 0,
+            depth: 0,
+            output: 0,
+            start: Instant::now()
+        }
+    }
+
+    /// Counts a step of the activation `scope` names, and looks at the
+    /// clock every so often
+    pub fn step(&mut self, scope: impl FnOnce() -> String) -> Result<(), RuntimeErrorKind> {
+        self.steps += 1;
+        match self.limits.steps {
+            Some(steps) if self.steps > steps => return Err(RuntimeErrorKind::StepLimit { steps, scope: scope() }),
+            _ => {}
+        }
+        match self.limits.time {
+            Some(time) if self.steps.is_multiple_of(CLOCK_INTERVAL) && self.start.elapsed() > time =>
+                Err(RuntimeErrorKind::TimeLimit { time, scope: scope() }),
+            _ => Ok(())
+        }
+    }
+
+    /// Counts an activation of the subprogram `scope` names
+    pub fn enter(&mut self, scope: impl FnOnce() -> String) -> Result<(), RuntimeErrorKind> {
+        self.depth += 1;
+        match self.limits.depth {
+            Some(depth) if self.depth > depth => Err(RuntimeErrorKind::DepthLimit { depth, scope: scope() }),
+            _ => Ok(())
+        }
+    }
+
+    pub fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    /// Counts the words of an activation or of a variable allocated by
+    /// `new`, before they are allocated
+    pub fn allocate(&mut self, words: usize) -> Result<(), RuntimeErrorKind> {
+        self.heap = self.heap.saturating_add(words);
+        match self.limits.heap {
+            Some(heap) if self.heap > heap => Err(RuntimeErrorKind::HeapLimit(heap)),
+            _ => Ok(())
+        }
+    }
+
+    /// Gives back the words of an activation left or a variable disposed of
+    pub fn free(&mut self, words: usize) {
+        self.heap = self.heap.saturating_sub(words);
+    }
+
+    /// Counts the bytes of some output before it is written
+    pub fn write(&mut self, bytes: usize) -> Result<(), RuntimeErrorKind> {
+        self.output += bytes;
+        match self.limits.output {
+            Some(output) if self.output > output => Err(RuntimeErrorKind::OutputLimit(output)),
+            _ => Ok(())
+        }
+    }
+}
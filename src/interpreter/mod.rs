@@ -0,0 +1,1349 @@
+pub mod value;
+pub mod io;
+pub mod debugger;
+pub mod profiler;
+pub mod limits;
+pub mod files;
+pub mod format;
+
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::io::{BufRead, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+use crate::ast::expression::{ExBox, Expression, Node};
+use crate::ast::expression::operators::{Call, CompOp, Format, LogicOp, ProdOp, SumOp};
+use crate::ast::expression::variables::VarRef;
+use crate::ast::program::{Program, ScopeId, Symbol};
+use crate::ast::standard::Builtin;
+use crate::ast::statement::{Direction, Statement, StatementKind};
+use crate::ast::subprogram::Subprogram;
+use crate::ast::types::Type;
+use crate::error::runtime_error::{RuntimeError, RuntimeErrorKind};
+use crate::pcode::compiler::size;
+use debugger::Debugger;
+use files::Stream;
+use io::Input;
+use limits::{Limits, Meter};
+use profiler::Profiler;
+use value::Value;
+
+type Fallible<T> = Result<T, RuntimeError>;
+
+/// The files of the standard input and output
+const INPUT: usize = 0;
+const OUTPUT: usize = 1;
+
+/// Where a variable lives: a local of some activation, the result of a
+/// function being run, a variable allocated by `new`, one of the standard
+/// files or the buffer variable of a file
+#[derive(Debug, Clone, PartialEq)]
+enum Root {
+    Local(usize, String),
+    Result(usize),
+    Heap(usize),
+    Standard(String),
+    Buffer(usize)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Index(i64),
+    Field(String)
+}
+
+/// A variable, or a component of one, that can be read and written
+#[derive(Debug, Clone, PartialEq)]
+struct Place {
+    root: Root,
+    path: Vec<Step>
+}
+
+impl Place {
+    fn new(root: Root) -> Place {
+        Place {
+            root,
+            path: vec![]
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Slot {
+    Value(Value),
+    /// A `var` parameter, standing for the variable it was given
+    Ref(Place)
+}
+
+/// The activation of the main program or of a subprogram
+#[derive(Debug)]
+struct Frame {
+    scope: ScopeId,
+    /// The activation of the enclosing subprogram, through which the
+    /// variables of the enclosing scopes are found
+    link: Option<usize>,
+    slots: HashMap<String, Slot>,
+    result: Option<Value>
+}
+
+/// Runs a checked program by walking its statements
+pub struct Interpreter<'a> {
+    program: &'a Program,
+    frames: Vec<Frame>,
+    heap: Vec<Option<Value>>,
+    /// The files of the program, by the number their variables hold
+    files: Vec<Stream<'a>>,
+    /// The variables of the standard files `input` and `output`
+    standard: HashMap<String, Value>,
+    /// The files on disk the parameters of the program are read from and
+    /// written to
+    paths: HashMap<String, PathBuf>,
+    debugger: Option<Debugger<'a>>,
+    profiler: Option<Profiler<'a>>,
+    meter: Meter
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(program: &'a Program, input: impl BufRead + 'a, output: impl Write + 'a) -> Interpreter<'a> {
+        Interpreter {
+            program,
+            frames: vec![],
+            heap: vec![],
+            files: vec![Stream::standard_input(Input::new(input)), Stream::standard_output(output)],
+            standard: [("input", INPUT), ("output", OUTPUT)].iter()
+                .map(|(name, file)| (name.to_string(), Value::File(Some(*file))))
+                .collect(),
+            paths: HashMap::new(),
+            debugger: None,
+            profiler: None,
+            meter: Meter::new(Limits::default())
+        }
+    }
+
+    /// Has the program run under a debugger, which stops it before its
+    /// first statement
+    pub fn attach(&mut self, debugger: Debugger<'a>) {
+        self.debugger = Some(debugger);
+    }
+
+    /// Has the program counted and timed as it runs
+    pub fn profile(&mut self) {
+        self.profiler = Some(Profiler::new(self.program));
+    }
+
+    /// Stops the program once it goes past one of `limits`
+    pub fn limit(&mut self, limits: Limits) {
+        self.meter = Meter::new(limits);
+    }
+
+    /// Has the file the program parameter `parameter` stands for read from
+    /// and written to `path`, rather than kept in memory
+    pub fn map(&mut self, parameter: &str, path: PathBuf) {
+        self.paths.insert(parameter.to_string(), path);
+    }
+
+    /// What the profiler counted, once the program has run
+    pub fn profiler(&self) -> Option<&Profiler<'a>> {
+        self.profiler.as_ref()
+    }
+
+    pub fn run(&mut self) -> Result<(), RuntimeError> {
+        let program = self.program;
+        self.push_frame(Program::GLOBAL, None)
+            .map_err(|kind| RuntimeError::new(0..0, kind))?;
+        let mapped = self.paths.iter()
+            .filter_map(|(name, path)| match self.frames[0].slots.get(name) {
+                Some(Slot::Value(Value::File(Some(file)))) => Some((*file, path.clone())),
+                _ => None
+            })
+            .collect::<Vec<_>>();
+        for (file, path) in mapped {
+            self.files[file].map(path);
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.enter(Program::GLOBAL);
+        }
+        let outcome = self.exec_all(&program.body);
+        if let Some(profiler) = &mut self.profiler {
+            profiler.leave();
+        }
+        self.frames.clear();
+        let flushed = self.files.iter_mut()
+            .try_for_each(Stream::flush)
+            .map_err(|err| RuntimeError::new(0..0, RuntimeErrorKind::Output(err.to_string())));
+        outcome.and(flushed)
+    }
+
+    /// The value a variable of type `ty` starts with, the files in it
+    /// included
+    fn create(&mut self, ty: &Type) -> Value {
+        let mut value = Value::default_of(ty);
+        if ty.has_file() {
+            self.open_files(&mut value);
+        }
+        value
+    }
+
+    fn open_files(&mut self, value: &mut Value) {
+        match value {
+            Value::File(file @ None) => {
+                self.files.push(Stream::default());
+                *file = Some(self.files.len() - 1);
+            },
+            Value::Array { items, .. } => items.iter_mut().for_each(|item| self.open_files(item)),
+            Value::Record(fields) => fields.values_mut().for_each(|field| self.open_files(field)),
+            _ => {}
+        }
+    }
+
+    /// Creates the variables of an activation of `scope`, once the words
+    /// they take are counted against the limits
+    fn push_frame(&mut self, scope: ScopeId, link: Option<usize>) -> Result<(), RuntimeErrorKind> {
+        let program = self.program;
+        self.meter.allocate(frame_size(program, scope))?;
+        let slots = program.scopes[scope].variables.iter()
+            .map(|(name, ty)| (name.clone(), Slot::Value(self.create(ty))))
+            .collect();
+        self.frames.push(Frame {
+            scope,
+            link,
+            slots,
+            result: None
+        });
+        Ok(())
+    }
+
+    /// The innermost activation of `scope` reachable from the running one
+    fn frame_of(&self, scope: ScopeId) -> usize {
+        let mut frame = self.frames.len() - 1;
+        while self.frames[frame].scope != scope {
+            frame = self.frames[frame].link
+                .expect("the scope of a name encloses the scope it is used in");
+        }
+        frame
+    }
+
+    fn exec_all(&mut self, statements: &[Statement]) -> Fallible<()> {
+        statements.iter()
+            .try_for_each(|statement| self.exec(statement))
+    }
+
+    fn exec(&mut self, statement: &Statement) -> Fallible<()> {
+        if self.debugger.is_some() {
+            self.debug(statement, Debugger::pause)?;
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.statement(statement);
+        }
+        let (program, scope) = (self.program, self.frames.last().expect("a frame is running").scope);
+        self.meter.step(|| activation(program, scope))
+            .map_err(|kind| RuntimeError::new(head(&program.positioner.file, &statement.range), kind))?;
+        let range = &statement.range;
+        let outcome = match &statement.kind {
+            StatementKind::Empty => Ok(()),
+            StatementKind::Assignment { target, target_range, value: expr } => {
+                let value = self.eval(expr)?;
+                let place = self.place(target, target_range)?;
+                let ty = self.target_type(target);
+                if self.program.checks_at(expr.range.start).range {
+                    if let Some(bounds) = ty.narrowing(&expr.get_type(self.program)) {
+                        check(value.ordinal(), &bounds, &ty)
+                            .map_err(|kind| RuntimeError::new(expr.range.clone(), kind))?;
+                    }
+                }
+                self.store(&place, value.convert(&ty), target_range)
+            },
+            StatementKind::Call(call) => self.call(call, range).map(|_| ()),
+            StatementKind::Compound(body) => self.exec_all(body),
+            StatementKind::If { condition, then, otherwise } => {
+                let holds = self.eval(condition)?.boolean();
+                self.branch(statement, !holds as usize);
+                if holds {
+                    self.exec(then)
+                } else if let Some(otherwise) = otherwise {
+                    self.exec(otherwise)
+                } else {
+                    Ok(())
+                }
+            },
+            StatementKind::While { condition, body } => {
+                while self.eval(condition)?.boolean() {
+                    self.branch(statement, 0);
+                    self.exec(body)?;
+                }
+                self.branch(statement, 1);
+                Ok(())
+            },
+            StatementKind::Repeat { body, condition } => {
+                loop {
+                    self.exec_all(body)?;
+                    if self.eval(condition)?.boolean() {
+                        self.branch(statement, 1);
+                        break Ok(());
+                    }
+                    self.branch(statement, 0);
+                }
+            },
+            StatementKind::For { variable, from, direction, to, body } => {
+                let from = self.eval(from)?.ordinal();
+                let to = self.eval(to)?.ordinal();
+                let ty = variable.get_type(self.program);
+                let place = self.place(variable, range)?;
+                let values: Box<dyn Iterator<Item = i64>> = match direction {
+                    Direction::To => Box::new(from..=to),
+                    Direction::Downto => Box::new((to..=from).rev())
+                };
+                for value in values {
+                    self.branch(statement, 0);
+                    self.store(&place, Value::from_ordinal(&ty, value), range)?;
+                    self.exec(body)?;
+                }
+                self.branch(statement, 1);
+                Ok(())
+            },
+            StatementKind::Case { selector, arms } => {
+                let value = self.eval(selector)?.ordinal();
+                let program = self.program;
+                let arm = arms.iter().position(|arm| arm.labels.iter()
+                    .any(|label| label.as_number(program).ok() == Some(value)));
+                match arm {
+                    Some(arm) => {
+                        self.branch(statement, arm);
+                        self.exec(&arms[arm].body)
+                    },
+                    None => Err(RuntimeError::new(selector.range.clone(), RuntimeErrorKind::NoCaseLabel(
+                        selector.get_type(program).ordinal_name(value as isize))))
+                }
+            }
+        };
+        outcome?;
+        if self.debugger.is_some() {
+            self.debug(statement, Debugger::watch)?;
+        }
+        Ok(())
+    }
+
+    /// Has the debugger look at a statement, before or after it runs. The
+    /// debugger is set aside while it does, so that what it evaluates is
+    /// not debugged in turn.
+    fn debug(&mut self, statement: &Statement,
+             look: fn(&mut Debugger<'a>, &mut Interpreter<'a>, &Statement) -> Fallible<bool>) -> Fallible<()> {
+        if let Some(mut debugger) = self.debugger.take() {
+            if look(&mut debugger, self, statement)? {
+                self.debugger = Some(debugger);
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts the arm a decision took, if the program is being profiled
+    fn branch(&mut self, statement: &Statement, arm: usize) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.branch(statement, arm);
+        }
+    }
+
+    /// The type of the variable an assignment stores into
+    fn target_type(&self, target: &VarRef) -> Type {
+        match (target, self.program.lookup(target.scope(), target.name())) {
+            (VarRef::Immediate(..), Some((_, Symbol::Subprogram(subprogram)))) =>
+                subprogram.result.clone().unwrap_or(Type::Invalid),
+            _ => target.get_type(self.program)
+        }
+    }
+
+    fn place(&mut self, variable: &VarRef, range: &Range<usize>) -> Fallible<Place> {
+        match variable {
+            VarRef::Immediate(name, scope) => match self.program.lookup(*scope, name) {
+                Some((Program::STANDARD, Symbol::Variable(_))) => Ok(Place::new(Root::Standard(name.clone()))),
+                Some((declared, Symbol::Variable(_))) => {
+                    let frame = self.frame_of(declared);
+                    Ok(match &self.frames[frame].slots[name] {
+                        Slot::Ref(place) => place.clone(),
+                        Slot::Value(_) => Place::new(Root::Local(frame, name.clone()))
+                    })
+                },
+                Some((_, Symbol::Subprogram(subprogram))) =>
+                    Ok(Place::new(Root::Result(self.frame_of(subprogram.scope)))),
+                _ => unreachable!("'{}' is checked to be a variable", name)
+            },
+            VarRef::Field(record, field) => {
+                let mut place = self.place(record, range)?;
+                place.path.push(Step::Field(field.clone()));
+                Ok(place)
+            },
+            VarRef::Index(array, indices) => {
+                let mut place = self.place(array, range)?;
+                let mut ty = array.get_type(self.program);
+                for index in indices {
+                    let value = self.eval(index)?.ordinal();
+                    // the bounds of arrays are checked here, where the index
+                    // is known, and those of strings as they are followed
+                    let (dim, element) = ty.dimension().expect("indexed values are checked to be arrays");
+                    if let Some(bounds) = dim.bounds() {
+                        let (low, high) = (bounds.start as i64, bounds.end as i64);
+                        if value < low || value > high {
+                            return Err(RuntimeError::new(index.range.clone(),
+                                RuntimeErrorKind::IndexOutOfRange { index: value, low, high }));
+                        }
+                    }
+                    place.path.push(Step::Index(value));
+                    ty = element;
+                }
+                Ok(place)
+            },
+            VarRef::Deref(pointer) => {
+                let pointer = self.place(pointer, range)?;
+                match self.load(&pointer, range)? {
+                    Value::Pointer(Some(address)) => Ok(Place::new(Root::Heap(address))),
+                    Value::File(Some(file)) => Ok(Place::new(Root::Buffer(file))),
+                    _ => Err(RuntimeError::new(range.clone(), RuntimeErrorKind::NilPointer))
+                }
+            }
+        }
+    }
+
+    fn slot(&mut self, place: &Place, range: &Range<usize>) -> Fallible<&mut Value> {
+        let fail = |kind| RuntimeError::new(range.clone(), kind);
+        let mut value = match &place.root {
+            Root::Local(frame, name) => match self.frames[*frame].slots.get_mut(name) {
+                Some(Slot::Value(value)) => value,
+                _ => unreachable!("references are resolved by `place`")
+            },
+            Root::Result(frame) => self.frames[*frame].result.as_mut()
+                .expect("functions have a result"),
+            Root::Heap(address) => self.heap[*address].as_mut()
+                .ok_or_else(|| fail(RuntimeErrorKind::DanglingPointer))?,
+            Root::Standard(name) => self.standard.get_mut(name)
+                .expect("the standard files are there from the start"),
+            Root::Buffer(file) => self.files[*file].buffer()
+        };
+        for step in &place.path {
+            value = match (step, value) {
+                (Step::Index(index), Value::Array { low, items }) => {
+                    let high = *low + items.len() as i64 - 1;
+                    if *index < *low || *index > high {
+                        return Err(fail(RuntimeErrorKind::IndexOutOfRange {
+                            index: *index,
+                            low: *low,
+                            high
+                        }));
+                    }
+                    &mut items[(*index - *low) as usize]
+                },
+                (Step::Field(field), Value::Record(fields)) => fields.get_mut(field)
+                    .expect("fields are checked"),
+                (step, value) => unreachable!("{:?} of {:?}", step, value)
+            };
+        }
+        Ok(value)
+    }
+
+    fn load(&mut self, place: &Place, range: &Range<usize>) -> Fallible<Value> {
+        self.slot(place, range).map(|value| value.clone())
+    }
+
+    fn store(&mut self, place: &Place, value: Value, range: &Range<usize>) -> Fallible<()> {
+        *self.slot(place, range)? = value;
+        Ok(())
+    }
+
+    fn eval(&mut self, expr: &ExBox) -> Fallible<Value> {
+        let range = &expr.range;
+        let fail = |kind| RuntimeError::new(range.clone(), kind);
+        let overflow = self.program.checks_at(range.start).overflow;
+        Ok(match expr.node() {
+            Node::Integer(value) => Value::Integer(value),
+            Node::Real(value) => Value::Real(value),
+            Node::Char(value) => Value::Char(value),
+            Node::Str(value) => Value::string(value),
+            Node::Boolean(value) => Value::Boolean(value),
+            Node::Nil => Value::Pointer(None),
+            Node::Enumerator(literal) => Value::Enum(literal.ordinal),
+            Node::Variable(variable) => return self.value_of(variable, range),
+            Node::Call(call) => self.call(call, range)?
+                .expect("functions return a value"),
+            Node::Set(members) => Value::Set(members.iter()
+                .map(|member| self.eval(member).map(|value| value.ordinal()))
+                .collect::<Fallible<BTreeSet<i64>>>()?),
+            Node::In(op) => {
+                let sample = self.eval(&op.sample)?.ordinal();
+                match self.eval(&op.set)? {
+                    Value::Set(members) => Value::Boolean(members.contains(&sample)),
+                    value => unreachable!("'in' {:?}", value)
+                }
+            },
+            Node::Comparison(op) => {
+                let left = self.eval(&op.left)?;
+                let right = self.eval(&op.right)?;
+                Value::Boolean(compare(&op.op, &left, &right))
+            },
+            Node::Sum(op) => {
+                let left = self.eval(&op.left)?;
+                let right = self.eval(&op.right)?;
+                sum(&op.op, left, right, overflow).map_err(fail)?
+            },
+            Node::Signed(op) => match (&op.op, self.eval(&op.operand)?) {
+                (SumOp::Sub, Value::Integer(value)) => Value::Integer(arithmetic(value.overflowing_neg(), overflow)
+                    .map_err(fail)?),
+                (SumOp::Sub, Value::Real(value)) => Value::Real(-value),
+                (_, value) => value
+            },
+            Node::Product(op) => {
+                let left = self.eval(&op.left)?;
+                let right = self.eval(&op.right)?;
+                product(&op.op, left, right, overflow).map_err(fail)?
+            },
+            Node::Not(op) => Value::Boolean(!self.eval(&op.0)?.boolean()),
+            Node::Logic(op) => {
+                let left = self.eval(&op.left)?.boolean();
+                Value::Boolean(match op.op {
+                    LogicOp::And => left && self.eval(&op.right)?.boolean(),
+                    LogicOp::Or => left || self.eval(&op.right)?.boolean()
+                })
+            },
+            Node::Invalid => unreachable!("invalid expressions are not run")
+        })
+    }
+
+    /// The value a name stands for, which may be a constant or a function
+    /// called without arguments as well as a variable
+    fn value_of(&mut self, variable: &VarRef, range: &Range<usize>) -> Fallible<Value> {
+        let program = self.program;
+        if let VarRef::Immediate(name, scope) = variable {
+            match program.lookup(*scope, name) {
+                Some((_, Symbol::Constant(constant))) => return self.eval(constant),
+                Some((_, Symbol::Subprogram(subprogram))) => return self.invoke(subprogram, &[], range)
+                    .map(|result| result.expect("functions return a value")),
+                None => return self.builtin(Builtin::lookup(name).expect("names are checked"), &[], &[], range)
+                    .map(|result| result.expect("functions return a value")),
+                _ => {}
+            }
+        }
+        let place = self.place(variable, range)?;
+        self.load(&place, range)
+    }
+
+    fn call(&mut self, call: &Call, range: &Range<usize>) -> Fallible<Option<Value>> {
+        let program = self.program;
+        match program.lookup(call.scope, &call.name) {
+            Some((_, Symbol::Subprogram(subprogram))) => self.invoke(subprogram, &call.args, range),
+            _ => {
+                let builtin = Builtin::lookup(&call.name).expect("calls are checked");
+                self.builtin(builtin, &call.args, &call.formats, range)
+            }
+        }
+    }
+
+    /// Runs a subprogram, returning the result of a function
+    fn invoke(&mut self, subprogram: &Subprogram, args: &[ExBox], range: &Range<usize>) -> Fallible<Option<Value>> {
+        let mut slots = vec![];
+        for (param, arg) in subprogram.params.iter().zip(args) {
+            let slot = if param.by_ref {
+                let variable = arg.as_variable().expect("var arguments are checked to be variables");
+                Slot::Ref(self.place(variable, &arg.range)?)
+            } else {
+                Slot::Value(self.eval(arg)?.convert(&param.r#type))
+            };
+            slots.push((param.name.clone(), slot));
+        }
+        let parent = self.program.scopes[subprogram.scope].parent
+            .expect("subprograms are declared in a scope");
+        let link = self.frame_of(parent);
+        let program = self.program;
+        self.meter.enter(|| activation(program, subprogram.scope))
+            .map_err(|kind| RuntimeError::new(range.clone(), kind))?;
+        self.push_frame(subprogram.scope, Some(link))
+            .map_err(|kind| RuntimeError::new(range.clone(), kind))?;
+        if let Some(debugger) = &mut self.debugger {
+            debugger.enter(&subprogram.name);
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.enter(subprogram.scope);
+        }
+        let frame = self.frames.last_mut().expect("the frame was just pushed");
+        frame.slots.extend(slots);
+        frame.result = subprogram.result.as_ref().map(Value::default_of);
+        let outcome = self.exec_all(&subprogram.body);
+        if let Some(profiler) = &mut self.profiler {
+            profiler.leave();
+        }
+        self.meter.leave();
+        self.meter.free(frame_size(program, subprogram.scope));
+        let frame = self.frames.pop().expect("the frame is still there");
+        outcome.map(|_| frame.result)
+    }
+
+    fn builtin(&mut self, builtin: Builtin, args: &[ExBox], formats: &[Option<Format>], range: &Range<usize>)
+        -> Fallible<Option<Value>> {
+        let fail = |kind| RuntimeError::new(range.clone(), kind);
+        let program = self.program;
+        let domain = |argument: f64| fail(RuntimeErrorKind::Domain {
+            function: builtin.name().to_string(),
+            argument
+        });
+        if builtin.is_extension() {
+            return self.strings(builtin, args, formats, range);
+        }
+        if !builtin.is_function() {
+            self.procedure(builtin, args, formats, range)?;
+            return Ok(None);
+        }
+        if let Builtin::Eof | Builtin::Eoln = builtin {
+            let (file, _, _) = self.file(args, INPUT)?;
+            let file = &mut self.files[file];
+            let end = if builtin == Builtin::Eof { file.eof() } else { file.eoln() };
+            return Ok(Some(Value::Boolean(end.map_err(fail)?)));
+        }
+        let arg = self.eval(&args[0])?;
+        let real = arg.real();
+        let checks = program.checks_at(range.start);
+        Ok(Some(match (builtin, arg) {
+            (Builtin::Abs, Value::Integer(value)) =>
+                Value::Integer(arithmetic(value.overflowing_abs(), checks.overflow).map_err(fail)?),
+            (Builtin::Sqr, Value::Integer(value)) =>
+                Value::Integer(arithmetic(value.overflowing_mul(value), checks.overflow).map_err(fail)?),
+            (Builtin::Abs, _) => Value::Real(real.abs()),
+            (Builtin::Sqr, _) => Value::Real(real * real),
+            (Builtin::Sqrt, _) if real < 0.0 => return Err(domain(real)),
+            (Builtin::Sqrt, _) => Value::Real(real.sqrt()),
+            (Builtin::Sin, _) => Value::Real(real.sin()),
+            (Builtin::Cos, _) => Value::Real(real.cos()),
+            (Builtin::Arctan, _) => Value::Real(real.atan()),
+            (Builtin::Exp, _) => Value::Real(real.exp()),
+            (Builtin::Ln, _) if real <= 0.0 => return Err(domain(real)),
+            (Builtin::Ln, _) => Value::Real(real.ln()),
+            (Builtin::Trunc, _) => Value::Integer(real.trunc() as i64),
+            (Builtin::Round, _) => Value::Integer(real.round() as i64),
+            (Builtin::Odd, arg) => Value::Boolean(arg.ordinal() % 2 != 0),
+            (Builtin::Ord, arg) => Value::Integer(arg.ordinal()),
+            (Builtin::Chr, arg) => {
+                let value = arg.ordinal();
+                if checks.range {
+                    check(value, &(0..255), &Type::Char).map_err(fail)?;
+                }
+                Value::from_ordinal(&Type::Char, value)
+            },
+            (Builtin::Succ, arg) | (Builtin::Pred, arg) => {
+                let ty = args[0].get_type(program).host();
+                let step = if builtin == Builtin::Succ { 1 } else { -1 };
+                let value = arithmetic(arg.ordinal().overflowing_add(step), checks.overflow).map_err(fail)?;
+                match ty.bounds() {
+                    Some(bounds) if checks.range => check(value, &bounds, &ty).map_err(fail)?,
+                    _ => {}
+                }
+                Value::from_ordinal(&ty, value)
+            },
+            (builtin, _) => unreachable!("{} is a function", builtin.name())
+        }))
+    }
+
+    /// Runs one of the string subprograms of Turbo Pascal, which count the
+    /// characters of a string from 1
+    fn strings(&mut self, builtin: Builtin, args: &[ExBox], formats: &[Option<Format>], range: &Range<usize>)
+        -> Fallible<Option<Value>> {
+        let program = self.program;
+        let text = |interpreter: &mut Self, index: usize| -> Fallible<Vec<char>> {
+            Ok(interpreter.eval(&args[index])?.text().expect("strings are checked").chars().collect())
+        };
+        let integer = |interpreter: &mut Self, index: usize| -> Fallible<i64> {
+            Ok(interpreter.eval(&args[index])?.ordinal())
+        };
+        // stores into the variable at `index`, cut short to fit its type
+        let assign = |interpreter: &mut Self, index: usize, value: Value| -> Fallible<()> {
+            let variable = args[index].as_variable().expect("var arguments are checked to be variables");
+            let place = interpreter.place(variable, &args[index].range)?;
+            interpreter.store(&place, value.convert(&variable.get_type(program)), range)
+        };
+        let result = match builtin {
+            Builtin::Length => Value::Integer(text(self, 0)?.len() as i64),
+            Builtin::Copy => {
+                let string = text(self, 0)?;
+                let (index, count) = (integer(self, 1)?, integer(self, 2)?);
+                let start = (index.max(1) - 1) as usize;
+                Value::string(&string.iter().skip(start).take(count.max(0) as usize).collect::<String>())
+            },
+            Builtin::Pos => {
+                let (part, string) = (text(self, 0)?, text(self, 1)?);
+                let found = if part.is_empty() {
+                    None
+                } else {
+                    string.windows(part.len()).position(|window| window == &part[..])
+                };
+                Value::Integer(found.map_or(0, |at| at as i64 + 1))
+            },
+            Builtin::Concat => {
+                let mut joined = String::new();
+                for index in 0..args.len() {
+                    joined.extend(text(self, index)?);
+                }
+                Value::string(&joined).convert(&Type::String(255))
+            },
+            Builtin::Insert => {
+                let (part, mut string) = (text(self, 0)?, text(self, 1)?);
+                let at = (integer(self, 2)?.max(1) as usize - 1).min(string.len());
+                string.splice(at..at, part);
+                assign(self, 1, Value::string(&string.iter().collect::<String>()))?;
+                return Ok(None);
+            },
+            Builtin::Delete => {
+                let mut string = text(self, 0)?;
+                let (index, count) = (integer(self, 1)?, integer(self, 2)?);
+                if index >= 1 && index as usize <= string.len() && count > 0 {
+                    let start = index as usize - 1;
+                    string.drain(start..(start + count as usize).min(string.len()));
+                }
+                assign(self, 0, Value::string(&string.iter().collect::<String>()))?;
+                return Ok(None);
+            },
+            Builtin::Str => {
+                let written = self.written(&args[0], formats.first().and_then(Option::as_ref))?;
+                assign(self, 1, Value::string(&written))?;
+                return Ok(None);
+            },
+            Builtin::Val => {
+                let string = text(self, 0)?.iter().collect::<String>();
+                let real = args[1].get_type(program).host() == Type::Real;
+                let code = match format::number(&string, real) {
+                    Ok(value) => {
+                        assign(self, 1, value)?;
+                        0
+                    },
+                    Err(position) => position as i64
+                };
+                assign(self, 2, Value::Integer(code))?;
+                return Ok(None);
+            },
+            builtin => unreachable!("{} is not a string subprogram", builtin.name())
+        };
+        Ok(Some(result))
+    }
+
+    /// The file the arguments of a standard subprogram start with, the type
+    /// of its elements and the arguments after it, or the standard file
+    /// `standard` and all of the arguments
+    fn file<'e>(&mut self, args: &'e [ExBox], standard: usize) -> Fallible<(usize, Type, &'e [ExBox])> {
+        match args.first().map(|arg| arg.get_type(self.program)) {
+            Some(Type::File(element)) => match self.eval(&args[0])? {
+                Value::File(Some(file)) => Ok((file, *element, &args[1..])),
+                value => unreachable!("{:?} is a file", value)
+            },
+            _ => Ok((standard, Type::Char, args))
+        }
+    }
+
+    /// Writes `text` to `file`, counting it against the output limit
+    fn emit(&mut self, file: usize, text: &str, range: &Range<usize>) -> Fallible<()> {
+        let fail = |kind| RuntimeError::new(range.clone(), kind);
+        self.meter.write(text.len()).map_err(fail)?;
+        self.files[file].output().map_err(fail)?
+            .write_all(text.as_bytes())
+            .map_err(|err| fail(RuntimeErrorKind::Output(err.to_string())))
+    }
+
+    /// The text `write` gives `arg`, laid out as its format says
+    fn written(&mut self, arg: &ExBox, format: Option<&Format>) -> Fallible<String> {
+        let value = self.eval(arg)?;
+        let (width, decimals) = match format {
+            Some(Format { width, decimals }) => (width, decimals),
+            None => return Ok(match value {
+                Value::Real(value) => format::floating(value, format::REAL_WIDTH),
+                value => value.to_string()
+            })
+        };
+        // a field is reported with the value it is the field of
+        let range = arg.range.start..decimals.as_ref().unwrap_or(width).range.end;
+        let width = self.field(width, &range)?;
+        Ok(match (value, decimals) {
+            (Value::Real(value), Some(decimals)) => format::fixed(value, width, self.field(decimals, &range)?),
+            (Value::Real(value), None) => format::floating(value, width),
+            // a Turbo Pascal string is padded but never cut short
+            (value @ Value::Array { .. }, _) if matches!(arg.get_type(self.program), Type::String(_)) =>
+                format::pad(&value.to_string(), width),
+            (value @ Value::Boolean(_), _) | (value @ Value::Array { .. }, _) =>
+                format::fit(&value.to_string(), width),
+            (value, _) => format::pad(&value.to_string(), width)
+        })
+    }
+
+    /// A field width or number of decimals given to `write`, for the value
+    /// written at `range`
+    fn field(&mut self, expr: &ExBox, range: &Range<usize>) -> Fallible<usize> {
+        match self.eval(expr)? {
+            Value::Integer(value) => format::field(value).map_err(|kind| RuntimeError::new(range.clone(), kind)),
+            value => unreachable!("{:?} is an integer", value)
+        }
+    }
+
+    fn procedure(&mut self, builtin: Builtin, args: &[ExBox], formats: &[Option<Format>], range: &Range<usize>)
+        -> Fallible<()> {
+        let fail = |kind| RuntimeError::new(range.clone(), kind);
+        let program = self.program;
+        match builtin {
+            Builtin::Read | Builtin::Readln => {
+                let (file, element, args) = self.file(args, INPUT)?;
+                for arg in args {
+                    let at = |kind| RuntimeError::new(arg.range.clone(), kind);
+                    let variable = arg.as_variable().expect("read takes variables");
+                    let place = self.place(variable, &arg.range)?;
+                    let ty = variable.get_type(program);
+                    let file = &mut self.files[file];
+                    let value = if element == Type::Char {
+                        let input = file.input().map_err(at)?;
+                        match ty.host() {
+                            Type::Integer => input.read_integer().map(Value::Integer),
+                            Type::Real => input.read_real().map(Value::Real),
+                            Type::String(_) => input.read_rest().map(|text| Value::string(&text).convert(&ty)),
+                            _ => input.read_char().map(Value::Char)
+                        }.map_err(at)?
+                    } else {
+                        // read(f, v) is v := f^; get(f)
+                        if file.eof().map_err(at)? {
+                            return Err(at(RuntimeErrorKind::EndOfInput));
+                        }
+                        let value = file.buffer().clone();
+                        file.get().map_err(at)?;
+                        value.convert(&ty)
+                    };
+                    self.store(&place, value, &arg.range)?;
+                }
+                if builtin == Builtin::Readln {
+                    self.files[file].input()
+                        .and_then(Input::skip_line)
+                        .map_err(fail)?;
+                }
+            },
+            Builtin::Write | Builtin::Writeln => {
+                let all = args.len();
+                let (file, element, args) = self.file(args, OUTPUT)?;
+                // the formats of the values written, the file not having one
+                let formats = formats.get(all - args.len()..).unwrap_or_default();
+                if element != Type::Char {
+                    // write(f, e) is f^ := e; put(f)
+                    for arg in args {
+                        let value = self.eval(arg)?.convert(&element);
+                        *self.files[file].buffer() = value;
+                        let text = self.files[file].encoded();
+                        self.emit(file, &text, &arg.range)?;
+                    }
+                    return Ok(());
+                }
+                let mut text = String::new();
+                for (index, arg) in args.iter().enumerate() {
+                    let written = self.written(arg, formats.get(index).and_then(Option::as_ref))?;
+                    text.push_str(&written);
+                }
+                if builtin == Builtin::Writeln {
+                    text.push('\n');
+                }
+                self.emit(file, &text, range)?;
+            },
+            Builtin::Reset | Builtin::Rewrite => {
+                let (file, element, _) = self.file(args, INPUT)?;
+                let file = &mut self.files[file];
+                if builtin == Builtin::Reset { file.reset(element) } else { file.rewrite(element) }
+                    .map_err(fail)?;
+            },
+            Builtin::Get => {
+                let (file, _, _) = self.file(args, INPUT)?;
+                self.files[file].get().map_err(fail)?;
+            },
+            Builtin::Put => {
+                let (file, _, _) = self.file(args, OUTPUT)?;
+                let text = self.files[file].encoded();
+                self.emit(file, &text, range)?;
+            },
+            Builtin::New => {
+                let variable = args[0].as_variable().expect("new takes a variable");
+                let place = self.place(variable, &args[0].range)?;
+                let ty = program.pointee(&variable.get_type(program));
+                self.meter.allocate(size(&ty)).map_err(fail)?;
+                let value = self.create(&ty);
+                self.heap.push(Some(value));
+                self.store(&place, Value::Pointer(Some(self.heap.len() - 1)), range)?;
+            },
+            Builtin::Dispose => match self.eval(&args[0])? {
+                Value::Pointer(Some(address)) if self.heap[address].is_some() => {
+                    self.meter.free(size(&program.pointee(&args[0].get_type(program))));
+                    self.heap[address] = None;
+                },
+                Value::Pointer(Some(_)) => return Err(fail(RuntimeErrorKind::DanglingPointer)),
+                _ => return Err(fail(RuntimeErrorKind::NilPointer))
+            },
+            Builtin::Pack | Builtin::Unpack => {
+                // pack(a, i, z) and unpack(z, a, i) copy between `a` from `i` on and all of `z`
+                let (array, index, packed) = if builtin == Builtin::Pack { (0, 1, 2) } else { (1, 2, 0) };
+                let start = self.eval(&args[index])?.ordinal();
+                let target = if builtin == Builtin::Pack { packed } else { array };
+                let variable = args[target].as_variable().expect("the target is a variable");
+                let place = self.place(variable, &args[target].range)?;
+                let source = self.eval(&args[if builtin == Builtin::Pack { array } else { packed }])?;
+                let mut destination = self.load(&place, range)?;
+                match (&source, &mut destination) {
+                    (Value::Array { low, items }, Value::Array { items: packed_items, .. })
+                        if builtin == Builtin::Pack => {
+                        for (offset, item) in packed_items.iter_mut().enumerate() {
+                            *item = element(items, *low, start + offset as i64).map_err(fail)?.clone();
+                        }
+                    },
+                    (Value::Array { items: packed_items, .. }, Value::Array { low, items }) => {
+                        for (offset, item) in packed_items.iter().enumerate() {
+                            let index = start + offset as i64;
+                            element(items, *low, index).map_err(fail)?;
+                            items[(index - *low) as usize] = item.clone();
+                        }
+                    },
+                    (source, destination) => unreachable!("packing {:?} into {:?}", source, destination)
+                }
+                self.store(&place, destination, range)?;
+            },
+            builtin => unreachable!("{} is a procedure", builtin.name())
+        }
+        Ok(())
+    }
+}
+
+/// The activation of `scope` as the limits name it, such as `procedure foo`
+fn activation(program: &Program, scope: ScopeId) -> String {
+    program.scopes[scope].parent
+        .and_then(|parent| program.scopes[parent].subprograms.values()
+            .find(|subprogram| subprogram.scope == scope))
+        .map_or_else(|| format!("program {}", program.name), |subprogram| format!("{} {}",
+            if subprogram.is_function() { "function" } else { "procedure" }, subprogram.name))
+}
+
+/// The words the variables of an activation of `scope` take
+fn frame_size(program: &Program, scope: ScopeId) -> usize {
+    program.scopes[scope].variables.values()
+        .map(size)
+        .fold(0, usize::saturating_add)
+}
+
+/// The part of a statement on its first line, without the whitespace
+/// around it, which is where the debugger and the profiler place it
+fn head(file: &str, range: &Range<usize>) -> Range<usize> {
+    let text = &file[range.clone()];
+    let start = range.start + (text.len() - text.trim_start().len());
+    let line = file[start..range.end].lines().next().unwrap_or("").trim_end();
+    start..start + line.len().max(1)
+}
+
+/// The element at `index` of the items of an array starting at `low`
+fn element(items: &[Value], low: i64, index: i64) -> Result<&Value, RuntimeErrorKind> {
+    let high = low + items.len() as i64 - 1;
+    if index < low || index > high {
+        Err(RuntimeErrorKind::IndexOutOfRange { index, low, high })
+    } else {
+        Ok(&items[(index - low) as usize])
+    }
+}
+
+fn compare(op: &CompOp, left: &Value, right: &Value) -> bool {
+    let ordering = match (left, right) {
+        (Value::Set(left), Value::Set(right)) => return match op {
+            CompOp::Eq => left == right,
+            CompOp::Neq => left != right,
+            CompOp::Lte => left.is_subset(right),
+            CompOp::Bge => left.is_superset(right),
+            _ => unreachable!("sets are not ordered")
+        },
+        (Value::Pointer(_), Value::Pointer(_)) => return (left == right) == (*op == CompOp::Eq),
+        (Value::Real(_), _) | (_, Value::Real(_)) => left.real().partial_cmp(&right.real()),
+        // a character compares with a string as a string of its own
+        (Value::Array { .. }, _) | (_, Value::Array { .. }) => left.text().partial_cmp(&right.text()),
+        _ => Some(left.ordinal().cmp(&right.ordinal()))
+    };
+    match ordering {
+        Some(ordering) => match op {
+            CompOp::Bg => ordering == Ordering::Greater,
+            CompOp::Lt => ordering == Ordering::Less,
+            CompOp::Bge => ordering != Ordering::Less,
+            CompOp::Lte => ordering != Ordering::Greater,
+            CompOp::Neq => ordering != Ordering::Equal,
+            CompOp::Eq => ordering == Ordering::Equal
+        },
+        // NaN compares unequal to everything
+        None => *op == CompOp::Neq
+    }
+}
+
+/// Checks that an ordinal is within the bounds of its type `ty`
+fn check(value: i64, bounds: &Range<isize>, ty: &Type) -> Result<(), RuntimeErrorKind> {
+    if (bounds.start as i64..=bounds.end as i64).contains(&value) {
+        Ok(())
+    } else {
+        Err(RuntimeErrorKind::OutOfRange { value, r#type: ty.clone() })
+    }
+}
+
+/// The result of an `overflowing_` operation, which wraps around unless
+/// overflow is checked
+fn arithmetic((value, overflowed): (i64, bool), checked: bool) -> Result<i64, RuntimeErrorKind> {
+    if overflowed && checked {
+        Err(RuntimeErrorKind::Overflow)
+    } else {
+        Ok(value)
+    }
+}
+
+fn sum(op: &SumOp, left: Value, right: Value, overflow: bool) -> Result<Value, RuntimeErrorKind> {
+    Ok(match (op, left, right) {
+        (SumOp::Add, Value::Integer(left), Value::Integer(right)) =>
+            Value::Integer(arithmetic(left.overflowing_add(right), overflow)?),
+        (SumOp::Sub, Value::Integer(left), Value::Integer(right)) =>
+            Value::Integer(arithmetic(left.overflowing_sub(right), overflow)?),
+        (SumOp::Add, Value::Set(left), Value::Set(right)) => Value::Set(&left | &right),
+        (SumOp::Add, left @ Value::Array { .. }, right) | (SumOp::Add, left @ Value::Char(_), right) => {
+            let joined = left.text().unwrap_or_default() + &right.text().unwrap_or_default();
+            Value::string(&joined).convert(&Type::String(255))
+        },
+        (SumOp::Sub, Value::Set(left), Value::Set(right)) => Value::Set(&left - &right),
+        (SumOp::Add, left, right) => Value::Real(left.real() + right.real()),
+        (SumOp::Sub, left, right) => Value::Real(left.real() - right.real())
+    })
+}
+
+fn product(op: &ProdOp, left: Value, right: Value, overflow: bool) -> Result<Value, RuntimeErrorKind> {
+    Ok(match (op, left, right) {
+        (ProdOp::Mul, Value::Integer(left), Value::Integer(right)) =>
+            Value::Integer(arithmetic(left.overflowing_mul(right), overflow)?),
+        (ProdOp::Mul, Value::Set(left), Value::Set(right)) => Value::Set(&left & &right),
+        (ProdOp::Mul, left, right) => Value::Real(left.real() * right.real()),
+        (_, _, Value::Integer(0)) => return Err(RuntimeErrorKind::DivisionByZero),
+        (ProdOp::RDiv, _, right) if right.real() == 0.0 => return Err(RuntimeErrorKind::DivisionByZero),
+        (ProdOp::RDiv, left, right) => Value::Real(left.real() / right.real()),
+        (ProdOp::Div, left, right) => Value::Integer(arithmetic(left.ordinal().overflowing_div(right.ordinal()), overflow)?),
+        (ProdOp::Mod, left, right) => Value::Integer(left.ordinal().wrapping_rem_euclid(right.ordinal()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::ast::program::Dialect;
+
+    /// Runs a program that must be valid on `input`, returning what it wrote
+    fn run(src: &str, input: &str) -> Result<String, RuntimeErrorKind> {
+        let (program, errors) = Program::new(src.to_string());
+        assert!(errors.is_empty(), "{:?}", errors);
+        let diagnostics = program.validate();
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let mut output = vec![];
+        Interpreter::new(&program, input.as_bytes(), &mut output).run()
+            .map_err(|err| err.kind)?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn arithmetic_and_ordinals() {
+        assert_eq!(run("
+            program arithmetic;
+            type color = (red, green, blue);
+            var c: color; r: real;
+            begin
+                writeln(1 + 2 * 3, ' ', 7 div 2, ' ', 7 mod 3, ' ', -7 div 2);
+                r := 7 / 2; writeln(r, ' ', trunc(r), ' ', round(r), ' ', abs(-2));
+                for c := blue downto red do write(ord(c));
+                writeln(succ('a'), chr(ord('A') + 1), odd(3), green < blue)
+            end.
+        ", ""), Ok("7 3 1 -3\n 3.500000000000000E+00 3 4 2\n210bBtruetrue\n".to_string()));
+    }
+
+    #[test]
+    fn subprograms() {
+        assert_eq!(run("
+            program subprograms;
+            var a: array [1..3] of integer; n: integer;
+            function fib(n: integer): integer;
+            begin
+                if n < 2 then fib := n else fib := fib(n - 1) + fib(n - 2)
+            end;
+            procedure swap(var x, y: integer);
+            var t: integer;
+            begin
+                t := x; x := y; y := t
+            end;
+            procedure count(times: integer);
+            var total: integer;
+                procedure add;
+                begin
+                    total := total + times; n := n + 1
+                end;
+            begin
+                total := 0;
+                while total < 10 do add;
+                times := 0
+            end;
+            begin
+                a[1] := 1; a[3] := 3;
+                swap(a[1], a[3]);
+                n := 4; count(n);
+                writeln(fib(15), ' ', a[1], a[3], ' ', n)
+            end.
+        ", ""), Ok("610 31 7\n".to_string()));
+    }
+
+    #[test]
+    fn structured_values() {
+        assert_eq!(run("
+            program structured;
+            type point = record x, y: integer end;
+                 ref = ^point;
+            var p: point; q: ref; grid: array [1..2, boolean] of char;
+                s: set of char; t: array [1..2] of point;
+            begin
+                p.x := 1; t[2] := p; p.x := 2;
+                new(q); q^.y := t[2].x + p.x;
+                grid[2, true] := 'z';
+                s := ['a', 'b'] + ['c'] - ['a'];
+                writeln(q^.y, grid[2, true], 'a' in s, 'b' in s, s <= ['b', 'c', 'd']);
+                dispose(q)
+            end.
+        ", ""), Ok("3zfalsetruetrue\n".to_string()));
+    }
+
+    #[test]
+    fn linked_lists() {
+        assert_eq!(run("
+            program lists;
+            type list = ^node;
+                 node = record value: integer; next: list end;
+            var head, p: list; i: integer;
+            begin
+                head := nil;
+                for i := 1 to 5 do begin
+                    new(p); p^.value := i; p^.next := head; head := p
+                end;
+                p := head;
+                while p <> nil do begin write(p^.value, ' '); p := p^.next end;
+                while head <> nil do begin
+                    p := head; head := head^.next; dispose(p)
+                end;
+                writeln(head = nil, p <> nil)
+            end.
+        ", ""), Ok("5 4 3 2 1 truetrue\n".to_string()));
+    }
+
+    #[test]
+    fn standard_input() {
+        assert_eq!(run("
+            program input;
+            var i, j: integer; r: real; c: char;
+            begin
+                read(i, c); readln; readln(r);
+                while not eof do begin read(j); i := i + j; readln end;
+                writeln(i, c, r)
+            end.
+        ", "12x rest\n 2.5e1\n1\n2\n"), Ok("15x 2.500000000000000E+01\n".to_string()));
+    }
+
+    #[test]
+    fn files() {
+        // the files that are not mapped to ones on disk are kept in memory
+        assert_eq!(run("
+            program files(input, output);
+            var t: text; squares: file of integer; reals: file of real;
+                c: char; i, n: integer; r: real;
+            begin
+                rewrite(t);
+                writeln(t, 12, ' ', 30);
+                write(t, 'a'); t^ := 'b'; put(t); writeln(t);
+                reset(t);
+                read(t, i, n); readln(t);
+                write(i + n, t^); get(t); writeln(t^, eoln(t));
+                readln(t); writeln(eof(t));
+                rewrite(squares);
+                for i := 1 to 4 do write(squares, i * i);
+                reset(squares);
+                n := 0;
+                while not eof(squares) do begin n := n + squares^; get(squares) end;
+                rewrite(reals); write(reals, 0.1, 2); reset(reals); read(reals, r, r);
+                read(input, c); readln; read(c);
+                writeln(n, ' ', r, c, input^, eoln)
+            end.
+        ", "x\nyz\n"), Ok("42abfalse\ntrue\n30  2.000000000000000E+00yzfalse\n".to_string()));
+        let fails = |body: &str| run(&format!("
+            program faults;
+            var t: text; f: file of integer; i: integer;
+            begin {} end.
+        ", body), "").unwrap_err();
+        assert_eq!(fails("read(t, i)"), RuntimeErrorKind::NotReading);
+        assert_eq!(fails("begin reset(t); writeln(t) end"), RuntimeErrorKind::NotWriting);
+        assert_eq!(fails("begin rewrite(f); reset(f); read(f, i) end"), RuntimeErrorKind::EndOfInput);
+    }
+
+    #[test]
+    fn mapped_files() {
+        let dir = std::env::temp_dir().join(format!("pascal-files-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("numbers.txt"), "3 4\n5\n").unwrap();
+        let (program, errors) = Program::new("
+            program sums(numbers, total);
+            var numbers, total: text; n, sum: integer;
+            begin
+                reset(numbers); rewrite(total);
+                while not eof(numbers) do begin
+                    read(numbers, n); sum := sum + n;
+                    if eoln(numbers) then readln(numbers)
+                end;
+                writeln(total, sum)
+            end.
+        ".to_string());
+        assert!(errors.is_empty() && program.validate().is_empty());
+        let mut output = vec![];
+        let mut interpreter = Interpreter::new(&program, "".as_bytes(), &mut output);
+        interpreter.map("numbers", dir.join("numbers.txt"));
+        interpreter.map("total", dir.join("total.txt"));
+        assert!(interpreter.run().is_ok());
+        assert_eq!(std::fs::read_to_string(dir.join("total.txt")).unwrap(), "12\n");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn runtime_errors() {
+        // each error points at what failed
+        let cases = [
+            ("i := 1 div (i - i)", "1 div (i - i)", RuntimeErrorKind::DivisionByZero),
+            ("a[i + 4] := 0", "i + 4", RuntimeErrorKind::IndexOutOfRange { index: 4, low: 1, high: 3 }),
+            ("p^ := 1", "p^", RuntimeErrorKind::NilPointer),
+            ("i := 1 + p^", "p^", RuntimeErrorKind::NilPointer),
+            ("begin new(p); dispose(p); p^ := 1 end", "p^", RuntimeErrorKind::DanglingPointer),
+            ("case i of 1: end", "i", RuntimeErrorKind::NoCaseLabel("0".to_string())),
+            ("read(i)", "i", RuntimeErrorKind::EndOfInput),
+            ("writeln(2.5:3:i)", "2.5:3:i", RuntimeErrorKind::FieldWidth(0)),
+        ];
+        for (body, fault, expected) in cases.iter() {
+            let src = format!("
+                program faults;
+                var i: integer; a: array [1..3] of integer; p: ^integer;
+                begin {} end.
+            ", body);
+            let (program, _) = Program::new(src.clone());
+            let err = Interpreter::new(&program, "".as_bytes(), vec![]).run().unwrap_err();
+            assert_eq!(err.kind, *expected, "{}", body);
+            assert_eq!(&src[err.range], *fault, "{}", body);
+        }
+    }
+
+    #[test]
+    fn limits_stop_programs() {
+        let src = "
+            program greedy;
+            var p: ^integer; n: integer;
+            procedure spin;
+            begin
+                while true do n := n + 1
+            end;
+            procedure deeper(k: integer);
+            begin
+                if k > 0 then deeper(k - 1)
+            end;
+            function forever(k: integer): integer;
+            begin
+                forever := forever(k + 1)
+            end;
+            begin
+                deeper(50); {}
+            end.
+        ";
+        let cases = [
+            ("spin", Limits { steps: Some(1000), ..Limits::default() },
+             RuntimeErrorKind::StepLimit { steps: 1000, scope: "procedure spin".to_string() }),
+            ("spin", Limits { time: Some(Duration::from_millis(20)), ..Limits::default() },
+             RuntimeErrorKind::TimeLimit { time: Duration::from_millis(20), scope: "procedure spin".to_string() }),
+            ("deeper(100)", Limits { depth: Some(80), ..Limits::default() },
+             RuntimeErrorKind::DepthLimit { depth: 80, scope: "procedure deeper".to_string() }),
+            ("for n := 1 to 1000 do begin new(p); dispose(p) end; while true do new(p)",
+             Limits { heap: Some(100), ..Limits::default() }, RuntimeErrorKind::HeapLimit(100)),
+            ("while true do writeln(n)", Limits { output: Some(40), ..Limits::default() },
+             RuntimeErrorKind::OutputLimit(40)),
+        ];
+        for (body, limits, expected) in cases {
+            let (program, errors) = Program::new(src.replace("{}", body));
+            assert!(errors.is_empty(), "{:?}", errors);
+            let mut output = vec![];
+            let mut interpreter = Interpreter::new(&program, "".as_bytes(), &mut output);
+            interpreter.limit(limits);
+            assert_eq!(interpreter.run().map_err(|err| err.kind), Err(expected), "{}", body);
+        }
+        // variables are counted before they are made, however many there are
+        let (program, _) = Program::new("
+            program huge;
+            var a: array [1..1000000000000] of integer;
+            begin a[1] := 1 end.
+        ".to_string());
+        let mut output = vec![];
+        let mut interpreter = Interpreter::new(&program, "".as_bytes(), &mut output);
+        interpreter.limit(Limits::SANDBOX);
+        assert_eq!(interpreter.run().map_err(|err| err.kind), Err(RuntimeErrorKind::HeapLimit(1 << 24)));
+        // even when no limit is given
+        let mut output = vec![];
+        let outcome = Interpreter::new(&program, "".as_bytes(), &mut output).run();
+        assert_eq!(outcome.map_err(|err| err.kind), Err(RuntimeErrorKind::HeapLimit(Limits::HEAP)));
+        // and recursion is always stopped before the stack runs out
+        let (program, _) = Program::new(src.replace("{}", "n := forever(0)"));
+        let outcome = std::thread::scope(|scope| std::thread::Builder::new()
+            .stack_size(limits::STACK)
+            .spawn_scoped(scope, || {
+                let mut output = vec![];
+                let outcome = Interpreter::new(&program, "".as_bytes(), &mut output).run();
+                outcome.map_err(|err| err.kind)
+            })
+            .unwrap()
+            .join()
+            .unwrap());
+        assert_eq!(outcome, Err(RuntimeErrorKind::DepthLimit {
+            depth: Limits::DEPTH,
+            scope: "function forever".to_string()
+        }));
+    }
+
+    #[test]
+    fn turbo_strings() {
+        let (program, errors) = Program::with_dialect("
+            program strings;
+            var s, u: string; t: string[5]; w: packed array [1..3] of char;
+                n, code: integer; r: real;
+            begin
+                readln(s); t := s; w := 'abc';
+                writeln(length(s), ' ', t, ' ', s[2], ' ', t + w, ' ', length(''));
+                writeln(copy(s, 3, 4), '|', copy(s, 20, 1), '|', pos('lo', s), pos('zz', s), pos('', s));
+                u := w; insert('XY', u, 2); delete(s, 1, 6); delete(t, 9, 1);
+                writeln(u, ' ', s, ' ', t, ' ', concat(t, '-', s, 'x'));
+                str(n + 42:5, s); str(2.5:6:2, t); writeln('[', s, '][', t, ']');
+                val('123', n, code); write(n, ' ', code, ' ');
+                val('12x', n, code); write(n, ' ', code, ' ');
+                val(' 2.5e1', r, code); writeln(r:4:1, ' ', code);
+                writeln(s < t, 'abc' = w, 'ab' < 'abc', s[1] = ' ')
+            end.
+        ".to_string(), Dialect::Turbo);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let diagnostics = program.validate();
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        let mut output = vec![];
+        Interpreter::new(&program, "hello world\n".as_bytes(), &mut output).run().unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "\
+            11 hello e helloabc 0\n\
+            llo ||400\n\
+            aXYbc world hello hello-worldx\n\
+            [   42][  2.5]\n\
+            123 0 123 3 25.0 0\n\
+            truetruetruetrue\n");
+    }
+
+    #[test]
+    fn directives_switch_checks() {
+        let run = |body: &str| run(&format!("
+            program checks;
+            var i: integer; s: 1..3;
+            begin {} end.
+        ", body), "");
+        assert_eq!(run("{$R-} s := 5; writeln(s)"), Ok("5\n".to_string()));
+        assert_eq!(run("begin {$R-} s := 5; {$R+} i := s; s := i end").unwrap_err(),
+                   RuntimeErrorKind::OutOfRange { value: 5, r#type: Type::Range(Box::new(Type::Integer), 1..3) });
+        assert_eq!(run("i := 9223372036854775807; i := i + 1; writeln(i < 0)"), Ok("true\n".to_string()));
+        assert_eq!(run("{$Q+} begin i := 9223372036854775807; i := i - 1 + 1; i := i + 1 end").unwrap_err(),
+                   RuntimeErrorKind::Overflow);
+    }
+}
@@ -0,0 +1,297 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+use crate::ast::program::{Program, ScopeId};
+use crate::ast::statement::{Statement, StatementKind};
+use super::head;
+
+/// A statement that chooses between arms: which arm of an `if` or `case`
+/// was taken, and whether a loop went round again or ended
+struct Decision {
+    line: usize,
+    taken: Vec<u64>
+}
+
+/// The calls and time of the main program or of a subprogram
+#[derive(Default)]
+struct Activations {
+    calls: u64,
+    /// Including the time of what it called
+    total: Duration,
+    /// Leaving out the time of what it called
+    own: Duration
+}
+
+/// Counts how often each line of a program runs, how often each
+/// subprogram is called and how long it takes, and which way each
+/// decision goes, for `--profile` and `--coverage`
+pub struct Profiler<'a> {
+    program: &'a Program,
+    /// The line of each statement, by where it starts
+    lines: HashMap<usize, usize>,
+    counts: BTreeMap<usize, u64>,
+    /// By where the deciding statement starts
+    decisions: BTreeMap<usize, Decision>,
+    activations: HashMap<ScopeId, Activations>,
+    /// The activations running, with when they started and how long their
+    /// callees have taken, the innermost last
+    stack: Vec<(ScopeId, Instant, Duration)>
+}
+
+impl<'a> Profiler<'a> {
+    pub fn new(program: &'a Program) -> Profiler<'a> {
+        let mut profiler = Profiler {
+            program,
+            lines: HashMap::new(),
+            counts: BTreeMap::new(),
+            decisions: BTreeMap::new(),
+            activations: HashMap::new(),
+            stack: vec![]
+        };
+        profiler.find(&program.body);
+        for scope in &program.scopes {
+            for subprogram in scope.subprograms.values() {
+                profiler.find(&subprogram.body);
+            }
+        }
+        profiler
+    }
+
+    /// Finds the lines and decisions of statements, none of which has run
+    /// yet. A line is counted by the first statement on it, so that the
+    /// statements nested in an `if` on one line count it only once.
+    fn find(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            match &statement.kind {
+                StatementKind::Empty => continue,
+                StatementKind::Compound(body) => {
+                    self.find(body);
+                    continue;
+                },
+                _ => {}
+            }
+            let positioner = &self.program.positioner;
+            let line = positioner.pos(head(&positioner.file, &statement.range)).line_no + 1;
+            if !self.counts.contains_key(&line) {
+                self.lines.insert(statement.range.start, line);
+                self.counts.insert(line, 0);
+            }
+            let arms = match &statement.kind {
+                StatementKind::Empty | StatementKind::Compound(_)
+                | StatementKind::Assignment { .. } | StatementKind::Call(_) => 0,
+                StatementKind::If { then, otherwise, .. } => {
+                    self.find(std::slice::from_ref(then));
+                    if let Some(otherwise) = otherwise {
+                        self.find(std::slice::from_ref(otherwise));
+                    }
+                    2
+                },
+                StatementKind::While { body, .. } | StatementKind::For { body, .. } => {
+                    self.find(std::slice::from_ref(body));
+                    2
+                },
+                StatementKind::Repeat { body, .. } => {
+                    self.find(body);
+                    2
+                },
+                StatementKind::Case { arms, .. } => {
+                    for arm in arms {
+                        self.find(std::slice::from_ref(&arm.body));
+                    }
+                    arms.len()
+                }
+            };
+            if arms > 0 {
+                self.decisions.insert(statement.range.start, Decision {
+                    line,
+                    taken: vec![0; arms]
+                });
+            }
+        }
+    }
+
+    /// Counts a statement about to run
+    pub fn statement(&mut self, statement: &Statement) {
+        if let Some(line) = self.lines.get(&statement.range.start) {
+            *self.counts.entry(*line).or_default() += 1;
+        }
+    }
+
+    /// Counts the arm a decision took: the `then` or `else` of an `if`, an
+    /// arm of a `case`, or whether a loop runs its body again or ends
+    pub fn branch(&mut self, statement: &Statement, arm: usize) {
+        if let Some(decision) = self.decisions.get_mut(&statement.range.start) {
+            decision.taken[arm] += 1;
+        }
+    }
+
+    /// Starts timing an activation of the main program or of a subprogram
+    pub fn enter(&mut self, scope: ScopeId) {
+        self.activations.entry(scope).or_default().calls += 1;
+        self.stack.push((scope, Instant::now(), Duration::ZERO));
+    }
+
+    /// Stops timing the innermost activation
+    pub fn leave(&mut self) {
+        let (scope, start, callees) = self.stack.pop().expect("an activation is running");
+        let elapsed = start.elapsed();
+        let recursive = self.stack.iter().any(|(caller, ..)| *caller == scope);
+        let activations = self.activations.entry(scope).or_default();
+        // a recursive call is already timed by the outermost one
+        if !recursive {
+            activations.total += elapsed;
+        }
+        activations.own += elapsed.saturating_sub(callees);
+        if let Some((_, _, callees)) = self.stack.last_mut() {
+            *callees += elapsed;
+        }
+    }
+
+    /// The subprograms of the program, in the order they were declared,
+    /// with the names of nested ones qualified by the ones they are in
+    fn subprograms(&self) -> Vec<(String, usize, ScopeId)> {
+        let program = self.program;
+        let mut subprograms = program.scopes.iter()
+            .flat_map(|scope| scope.subprograms.values())
+            .map(|subprogram| {
+                let mut name = subprogram.name.clone();
+                let mut scope = program.scopes[subprogram.scope].parent;
+                while let Some(outer) = scope.filter(|scope| *scope != Program::GLOBAL) {
+                    let parent = program.scopes[outer].parent.expect("subprograms are declared in a scope");
+                    if let Some(enclosing) = program.scopes[parent].subprograms.values()
+                        .find(|subprogram| subprogram.scope == outer) {
+                        name = format!("{}.{}", enclosing.name, name);
+                    }
+                    scope = Some(parent);
+                }
+                let line = program.positioner.pos(subprogram.range.clone()).line_no + 1;
+                (name, line, subprogram.scope)
+            })
+            .collect::<Vec<_>>();
+        subprograms.sort_by_key(|(_, line, _)| *line);
+        subprograms
+    }
+
+    /// The calls and times of the subprograms and the count of every line,
+    /// as `--profile` shows them
+    pub fn report(&self) -> String {
+        let mut out = format!("Profile of {}\n\n{:>8}  {:>12}  {:>12}  subprogram\n", self.program.name, "calls", "total ms", "self ms");
+        let rows = std::iter::once((self.program.name.clone(), Program::GLOBAL))
+            .chain(self.subprograms().into_iter().map(|(name, _, scope)| (name, scope)));
+        for (name, scope) in rows {
+            let activations = self.activations.get(&scope);
+            let millis = |duration: Duration| duration.as_secs_f64() * 1000.0;
+            let _ = writeln!(out, "{:>8}  {:>12.3}  {:>12.3}  {}",
+                             activations.map_or(0, |activations| activations.calls),
+                             activations.map_or(0.0, |activations| millis(activations.total)),
+                             activations.map_or(0.0, |activations| millis(activations.own)),
+                             name);
+        }
+        let _ = write!(out, "\n{:>8}  {:>6}\n", "count", "line");
+        let source = self.program.positioner.file.lines().collect::<Vec<&str>>();
+        for (line, count) in &self.counts {
+            let _ = writeln!(out, "{:>8}  {:>6}  {}", count, line, source.get(line - 1).map_or("", |text| text.trim()));
+        }
+        out
+    }
+
+    /// The coverage of the program in the lcov trace format, `file` being
+    /// where its source was read from
+    pub fn lcov(&self, file: &str) -> String {
+        let mut out = format!("TN:\nSF:{}\n", file);
+        let subprograms = self.subprograms();
+        for (name, line, _) in &subprograms {
+            let _ = writeln!(out, "FN:{},{}", line, name);
+        }
+        let mut hit = 0;
+        for (name, _, scope) in &subprograms {
+            let calls = self.activations.get(scope).map_or(0, |activations| activations.calls);
+            hit += (calls > 0) as usize;
+            let _ = writeln!(out, "FNDA:{},{}", calls, name);
+        }
+        let _ = writeln!(out, "FNF:{}\nFNH:{}", subprograms.len(), hit);
+        let (mut branches, mut taken) = (0, 0);
+        for (block, decision) in self.decisions.values().enumerate() {
+            let reached = decision.taken.iter().any(|count| *count > 0);
+            for (arm, count) in decision.taken.iter().enumerate() {
+                branches += 1;
+                taken += (*count > 0) as usize;
+                // lcov writes `-` for the arms of a decision that never ran
+                let count = if reached { count.to_string() } else { "-".to_string() };
+                let _ = writeln!(out, "BRDA:{},{},{},{}", decision.line, block, arm, count);
+            }
+        }
+        let _ = writeln!(out, "BRF:{}\nBRH:{}", branches, taken);
+        for (line, count) in &self.counts {
+            let _ = writeln!(out, "DA:{},{}", line, count);
+        }
+        let lines_hit = self.counts.values().filter(|count| **count > 0).count();
+        let _ = write!(out, "LF:{}\nLH:{}\nend_of_record\n", self.counts.len(), lines_hit);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+
+    const PROGRAM: &str = "program demo;
+var i, total: integer;
+function fact(n: integer): integer;
+    procedure note;
+    begin
+        total := total + n
+    end;
+begin
+    note;
+    if n < 2 then fact := 1 else fact := n * fact(n - 1)
+end;
+begin
+    for i := 1 to 3 do
+        total := total + i;
+    if total < 0 then total := 0;
+    writeln(fact(3))
+end.
+";
+
+    fn profile() -> (String, String) {
+        let (program, errors) = Program::new(PROGRAM.to_string());
+        assert!(errors.is_empty(), "{:?}", errors);
+        let mut output = vec![];
+        let mut interpreter = Interpreter::new(&program, "".as_bytes(), &mut output);
+        interpreter.profile();
+        interpreter.run().unwrap();
+        let profiler = interpreter.profiler().unwrap();
+        (profiler.report(), profiler.lcov("demo.p"))
+    }
+
+    #[test]
+    fn counts_calls_and_lines() {
+        let (report, _) = profile();
+        let calls = report.lines()
+            .take_while(|line| !line.trim_start().starts_with("count"))
+            .filter_map(|line| {
+                let columns = line.split_whitespace().collect::<Vec<&str>>();
+                Some((columns.get(3)?.to_string(), columns[0].parse::<u64>().ok()?))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(calls, vec![("demo".to_string(), 1), ("fact".to_string(), 3), ("fact.note".to_string(), 3)]);
+        assert!(report.contains("       3      14  total := total + i;"), "{}", report);
+        assert!(report.contains("       3      10  if n < 2 then fact := 1 else fact := n * fact(n - 1)"), "{}", report);
+        assert!(report.contains("       1      15  if total < 0 then total := 0;"), "{}", report);
+    }
+
+    #[test]
+    fn writes_lcov() {
+        let (_, lcov) = profile();
+        let expected = "TN:\nSF:demo.p\nFN:3,fact\nFN:4,fact.note\nFNDA:3,fact\nFNDA:3,fact.note\nFNF:2\nFNH:2\n";
+        assert!(lcov.starts_with(expected), "{}", lcov);
+        // the for loop goes round three times and ends once, and the if
+        // in the main program never takes its then
+        assert!(lcov.contains("BRDA:13,1,0,3\nBRDA:13,1,1,1\n"), "{}", lcov);
+        assert!(lcov.contains("BRDA:15,2,0,0\nBRDA:15,2,1,1\n"), "{}", lcov);
+        assert!(lcov.contains("DA:6,3\n") && lcov.contains("DA:16,1\n"), "{}", lcov);
+        assert!(lcov.ends_with("end_of_record\n"), "{}", lcov);
+    }
+}
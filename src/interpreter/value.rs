@@ -0,0 +1,159 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
+use crate::ast::types::Type;
+
+/// A value of a running program
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    Real(f64),
+    Boolean(bool),
+    Char(char),
+    /// The ordinal of an enumeration literal
+    Enum(i64),
+    /// The ordinals of the members
+    Set(BTreeSet<i64>),
+    /// The elements of one dimension, `low` being the index of the first one
+    Array {
+        low: i64,
+        items: Vec<Value>
+    },
+    Record(HashMap<String, Value>),
+    /// An address on the heap, `None` for a pointer that was never set
+    Pointer(Option<usize>),
+    /// The file a file variable stands for, `None` until the variable is
+    /// given one as it is created
+    File(Option<usize>)
+}
+
+impl Value {
+    /// The value a variable of type `ty` starts with
+    pub fn default_of(ty: &Type) -> Value {
+        match ty {
+            // a named type is only pointed to, never held
+            Type::Integer | Type::Named(..) | Type::Invalid => Value::Integer(0),
+            Type::Real => Value::Real(0.0),
+            Type::Boolean => Value::Boolean(false),
+            Type::Char => Value::Char('\0'),
+            Type::Enum(_) => Value::Enum(0),
+            Type::Range(host, range) => Value::from_ordinal(host, range.start as i64),
+            Type::SetOf(_) => Value::Set(BTreeSet::new()),
+            Type::ArrayOf(dims, element) => {
+                let (dim, rest) = dims.split_first()
+                    .expect("arrays have at least one dimension");
+                let element = if rest.is_empty() {
+                    Value::default_of(element)
+                } else {
+                    Value::default_of(&Type::ArrayOf(rest.to_vec(), element.clone()))
+                };
+                let bounds = dim.bounds().unwrap_or(0..0);
+                Value::Array {
+                    low: bounds.start as i64,
+                    items: vec![element; (bounds.end - bounds.start + 1).max(0) as usize]
+                }
+            },
+            Type::Record(fields) => Value::Record(fields.iter()
+                .map(|(name, ty)| (name.clone(), Value::default_of(ty)))
+                .collect()),
+            Type::Pointer(_) | Type::Nil => Value::Pointer(None),
+            Type::File(_) => Value::File(None),
+            Type::String(_) => Value::string("")
+        }
+    }
+
+    /// A string, an array of characters from index 1
+    pub fn string(text: &str) -> Value {
+        Value::Array {
+            low: 1,
+            items: text.chars().map(Value::Char).collect()
+        }
+    }
+
+    /// The value of ordinal type `ty` with ordinal `ordinal`
+    pub fn from_ordinal(ty: &Type, ordinal: i64) -> Value {
+        match ty.host() {
+            Type::Boolean => Value::Boolean(ordinal != 0),
+            Type::Char => Value::Char(char::from_u32(ordinal as u32).unwrap_or('\0')),
+            Type::Enum(_) => Value::Enum(ordinal),
+            _ => Value::Integer(ordinal)
+        }
+    }
+
+    pub fn ordinal(&self) -> i64 {
+        match self {
+            Value::Integer(value) | Value::Enum(value) => *value,
+            Value::Boolean(value) => *value as i64,
+            Value::Char(value) => *value as i64,
+            value => unreachable!("{:?} is not ordinal", value)
+        }
+    }
+
+    pub fn real(&self) -> f64 {
+        match self {
+            Value::Real(value) => *value,
+            value => value.ordinal() as f64
+        }
+    }
+
+    pub fn boolean(&self) -> bool {
+        self.ordinal() != 0
+    }
+
+    /// Converts a value assigned to a variable of type `ty`, integers becoming
+    /// reals where a real is expected and text too long for a string being
+    /// cut short
+    pub fn convert(self, ty: &Type) -> Value {
+        match (ty, self) {
+            (Type::Real, Value::Integer(value)) => Value::Real(value as f64),
+            (Type::String(capacity), value) => match value.text() {
+                Some(text) => Value::string(&text.chars().take(*capacity).collect::<String>()),
+                None => value
+            },
+            (_, value) => value
+        }
+    }
+
+    /// The characters of a string, an array of characters
+    pub fn chars(&self) -> Option<String> {
+        match self {
+            Value::Array { items, .. } => items.iter()
+                .map(|item| match item {
+                    Value::Char(c) => Some(*c),
+                    _ => None
+                })
+                .collect(),
+            _ => None
+        }
+    }
+
+    /// The characters of a string or of a single character
+    pub fn text(&self) -> Option<String> {
+        match self {
+            Value::Char(c) => Some(c.to_string()),
+            value => value.chars()
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Integer(value) | Value::Enum(value) => write!(f, "{}", value),
+            Value::Real(value) => write!(f, "{}", value),
+            Value::Boolean(value) => write!(f, "{}", value),
+            Value::Char(value) => write!(f, "{}", value),
+            Value::Set(members) => write!(f, "[{}]", members.iter()
+                .map(|member| member.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")),
+            Value::Array { .. } => match self.chars() {
+                Some(chars) => write!(f, "{}", chars),
+                None => write!(f, "array")
+            },
+            Value::Record(_) => write!(f, "record"),
+            Value::Pointer(Some(address)) => write!(f, "^{}", address),
+            Value::Pointer(None) => write!(f, "nil"),
+            Value::File(_) => write!(f, "file")
+        }
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+use super::{Op, Procedure};
+
+/// The control-flow graph of a procedure, its blocks numbered by their
+/// index, the first being the entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct Graph {
+    pub successors: Vec<Vec<usize>>,
+    pub predecessors: Vec<Vec<usize>>
+}
+
+impl Graph {
+    pub fn new(procedure: &Procedure) -> Graph {
+        let indices = indices(procedure);
+        let count = procedure.blocks.len();
+        let mut successors = vec![vec![]; count];
+        for (index, block) in procedure.blocks.iter().enumerate() {
+            let next = (index + 1 < count).then_some(index + 1);
+            let jump = block.quads.last().and_then(|quad| quad.target()).map(|label| indices[&label]);
+            let targets = match block.quads.last().map(|quad| quad.op) {
+                Some(Op::Goto) => vec![jump],
+                Some(Op::If | Op::IfFalse) => vec![next, jump],
+                Some(Op::Return | Op::NoCase) => vec![],
+                _ => vec![next]
+            };
+            for target in targets.into_iter().flatten() {
+                if !successors[index].contains(&target) {
+                    successors[index].push(target);
+                }
+            }
+        }
+        let mut predecessors = vec![vec![]; count];
+        for (index, targets) in successors.iter().enumerate() {
+            for target in targets {
+                predecessors[*target].push(index);
+            }
+        }
+        Graph { successors, predecessors }
+    }
+
+    /// The blocks in reverse postorder from the entry, which puts a block
+    /// after its dominators; blocks the entry does not reach are left out
+    pub fn reverse_postorder(&self) -> Vec<usize> {
+        let count = self.successors.len();
+        let mut order = Vec::with_capacity(count);
+        if count == 0 {
+            return order;
+        }
+        let mut visited = vec![false; count];
+        let mut stack = vec![(0, 0)];
+        visited[0] = true;
+        while let Some((block, next)) = stack.pop() {
+            match self.successors[block].get(next) {
+                Some(&successor) => {
+                    stack.push((block, next + 1));
+                    if !visited[successor] {
+                        visited[successor] = true;
+                        stack.push((successor, 0));
+                    }
+                },
+                None => order.push(block)
+            }
+        }
+        order.reverse();
+        order
+    }
+}
+
+/// The index of each block of a procedure by its label
+pub fn indices(procedure: &Procedure) -> HashMap<usize, usize> {
+    procedure.blocks.iter().enumerate().map(|(index, block)| (block.label, index)).collect()
+}
+
+/// Removes the blocks the entry does not reach, and the arguments of the
+/// φ-functions that come from them, telling whether there were any
+pub fn prune(procedure: &mut Procedure) -> bool {
+    let mut reached = vec![false; procedure.blocks.len()];
+    for block in Graph::new(procedure).reverse_postorder() {
+        reached[block] = true;
+    }
+    if reached.iter().all(|reached| *reached) {
+        return false;
+    }
+    let mut index = 0;
+    procedure.blocks.retain(|_| {
+        index += 1;
+        reached[index - 1]
+    });
+    let labels = procedure.blocks.iter().map(|block| block.label).collect::<Vec<_>>();
+    for block in &mut procedure.blocks {
+        for phi in &mut block.phis {
+            phi.args.retain(|(label, _)| labels.contains(label));
+        }
+    }
+    true
+}
+
+/// The dominator tree of a graph all the blocks of which the entry reaches
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dominators {
+    /// The immediate dominator of each block, the entry being its own
+    pub idom: Vec<usize>,
+    /// The blocks each block immediately dominates
+    pub children: Vec<Vec<usize>>,
+    /// The dominance frontier of each block: the blocks it does not strictly
+    /// dominate but does one of their predecessors
+    pub frontiers: Vec<Vec<usize>>
+}
+
+impl Dominators {
+    /// Finds the dominators as Cooper, Harvey and Kennedy do, going over the
+    /// blocks in reverse postorder until the dominators settle
+    pub fn new(graph: &Graph) -> Dominators {
+        let count = graph.successors.len();
+        let order = graph.reverse_postorder();
+        let mut position = vec![usize::MAX; count];
+        for (index, block) in order.iter().enumerate() {
+            position[*block] = index;
+        }
+        let mut idom: Vec<Option<usize>> = vec![None; count];
+        if count > 0 {
+            idom[0] = Some(0);
+        }
+        let intersect = |idom: &[Option<usize>], mut left: usize, mut right: usize| {
+            while left != right {
+                while position[left] > position[right] {
+                    left = idom[left].expect("processed blocks have a dominator");
+                }
+                while position[right] > position[left] {
+                    right = idom[right].expect("processed blocks have a dominator");
+                }
+            }
+            left
+        };
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &block in order.iter().skip(1) {
+                let mut processed = graph.predecessors[block].iter().filter(|pred| idom[**pred].is_some());
+                let first = *processed.next().expect("a block after the entry has a processed predecessor");
+                let dominator = processed.fold(first, |dominator, pred| intersect(&idom, *pred, dominator));
+                if idom[block] != Some(dominator) {
+                    idom[block] = Some(dominator);
+                    changed = true;
+                }
+            }
+        }
+        let idom = idom.into_iter().map(|dominator| dominator.unwrap_or(0)).collect::<Vec<_>>();
+        let mut children = vec![vec![]; count];
+        for &block in order.iter().skip(1) {
+            children[idom[block]].push(block);
+        }
+        let mut frontiers = vec![vec![]; count];
+        for (block, preds) in graph.predecessors.iter().enumerate() {
+            if preds.len() < 2 {
+                continue;
+            }
+            for pred in preds {
+                let mut runner = *pred;
+                while runner != idom[block] {
+                    if !frontiers[runner].contains(&block) {
+                        frontiers[runner].push(block);
+                    }
+                    runner = idom[runner];
+                }
+            }
+        }
+        Dominators { idom, children, frontiers }
+    }
+
+    /// Whether the block `dominator` is on every path from the entry to
+    /// `block`
+    pub fn dominates(&self, dominator: usize, mut block: usize) -> bool {
+        loop {
+            if block == dominator {
+                return true;
+            }
+            if self.idom[block] == block {
+                return false;
+            }
+            block = self.idom[block];
+        }
+    }
+}
+
+/// A natural loop: its header, which dominates every block of the loop,
+/// and the latches, the blocks of the loop that jump back to the header
+#[derive(Debug, Clone, PartialEq)]
+pub struct Loop {
+    pub header: usize,
+    pub latches: Vec<usize>,
+    /// The blocks of the loop in order, the header among them
+    pub blocks: Vec<usize>
+}
+
+impl Loop {
+    pub fn contains(&self, block: usize) -> bool {
+        self.blocks.binary_search(&block).is_ok()
+    }
+}
+
+/// The natural loops of a graph, one for each header whatever the number of
+/// edges back to it, the loops nested in another coming before it
+pub fn loops(graph: &Graph, dominators: &Dominators) -> Vec<Loop> {
+    let mut loops: Vec<Loop> = vec![];
+    for (latch, successors) in graph.successors.iter().enumerate() {
+        for &header in successors.iter().filter(|header| dominators.dominates(**header, latch)) {
+            // the loop of a back edge is what reaches its latch without
+            // going through its header
+            let mut blocks = vec![header];
+            let mut work = vec![latch];
+            while let Some(block) = work.pop() {
+                if !blocks.contains(&block) {
+                    blocks.push(block);
+                    work.extend(&graph.predecessors[block]);
+                }
+            }
+            match loops.iter_mut().find(|found| found.header == header) {
+                Some(found) => {
+                    found.latches.push(latch);
+                    for block in blocks {
+                        if !found.blocks.contains(&block) {
+                            found.blocks.push(block);
+                        }
+                    }
+                },
+                None => loops.push(Loop { header, latches: vec![latch], blocks })
+            }
+        }
+    }
+    for found in &mut loops {
+        found.blocks.sort_unstable();
+    }
+    loops.sort_by_key(|found| found.blocks.len());
+    loops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parse::procedure;
+
+    const LOOP: &str = "
+        procedure main
+        L1:
+          :=  0  i
+        L2:
+          <   i  10  t1
+          iffalse  t1  L5
+        L3:
+          odd  i  t2
+          if  t2  L2
+        L4:
+          +  i  1  t3
+          :=  t3  i
+          goto  L2
+        L5:
+          return
+    ";
+
+    #[test]
+    fn edges_follow_jumps_and_fall_through() {
+        let graph = Graph::new(&procedure(LOOP));
+        assert_eq!(graph.successors, vec![vec![1], vec![2, 4], vec![3, 1], vec![1], vec![]]);
+        assert_eq!(graph.predecessors, vec![vec![], vec![0, 2, 3], vec![1], vec![2], vec![1]]);
+        assert_eq!(graph.reverse_postorder(), vec![0, 1, 4, 2, 3]);
+    }
+
+    #[test]
+    fn dominator_trees_and_frontiers() {
+        let dominators = Dominators::new(&Graph::new(&procedure(LOOP)));
+        assert_eq!(dominators.idom, vec![0, 0, 1, 2, 1]);
+        assert_eq!(dominators.children, vec![vec![1], vec![4, 2], vec![3], vec![], vec![]]);
+        assert_eq!(dominators.frontiers, vec![vec![], vec![1], vec![1], vec![1], vec![]]);
+        assert!(dominators.dominates(1, 3) && !dominators.dominates(3, 4));
+    }
+
+    #[test]
+    fn back_edges_make_loops() {
+        let graph = Graph::new(&procedure(LOOP));
+        let found = loops(&graph, &Dominators::new(&graph));
+        assert_eq!(found, vec![Loop { header: 1, latches: vec![2, 3], blocks: vec![1, 2, 3] }]);
+        let nested = procedure("
+            procedure main
+            L1:
+              :=  0  i
+            L2:
+              :=  0  j
+            L3:
+              +  j  1  t1
+              :=  t1  j
+              <  j  i  t2
+              if  t2  L3
+            L4:
+              +  i  1  t3
+              :=  t3  i
+              <  i  10  t4
+              if  t4  L2
+            L5:
+              return
+        ");
+        let graph = Graph::new(&nested);
+        let found = loops(&graph, &Dominators::new(&graph));
+        assert_eq!(found, vec![
+            Loop { header: 2, latches: vec![2], blocks: vec![2] },
+            Loop { header: 1, latches: vec![3], blocks: vec![1, 2, 3] }
+        ]);
+    }
+
+    #[test]
+    fn unreached_blocks_are_pruned() {
+        let mut pruned = procedure("
+            procedure main
+            L1:
+              goto  L3
+            L2:
+              :=  1  x
+            L3:
+              phi  0@L1,1@L2  t1
+              return
+        ");
+        assert!(prune(&mut pruned));
+        assert_eq!(pruned.to_string(), procedure("
+            procedure main
+            L1:
+              goto  L3
+            L3:
+              phi  0@L1  t1
+              return
+        ").to_string());
+        assert!(!prune(&mut pruned));
+    }
+}
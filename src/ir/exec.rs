@@ -0,0 +1,688 @@
+//! Runs the quadruples of a program, as `-O1` and `-O2` run the optimized
+//! ones. Memory is laid out as in the translations: words of 8 bytes at
+//! byte addresses, the frame of the main program first and those of the
+//! subprograms after it, each starting with its static link, and the
+//! variables `new` allocates on a heap of their own. The address 0 is that
+//! of no variable, which pointers start as.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use crate::ast::program::{Program, ScopeId};
+use crate::ast::types::Type;
+use crate::emit::{callee, is_block, levels, routine, size, subprogram_of, Layout};
+use crate::error::runtime_error::{RuntimeError, RuntimeErrorKind};
+use crate::interpreter::format;
+use crate::interpreter::io::Input;
+use crate::interpreter::limits::{Limits, Meter};
+use super::{Arg, Module, Op, Quad};
+
+/// Addresses from this one on are on the heap rather than on the stack
+const HEAP_BASE: usize = 1 << 48;
+
+/// Where the frame of the main program starts, after the word of no
+/// variable
+const GLOBALS: usize = 8;
+
+type Fallible<T> = Result<T, RuntimeErrorKind>;
+
+/// What a procedure needs of its frame
+struct Shape {
+    /// The index of the block of each label
+    blocks: HashMap<usize, usize>,
+    /// Where each memory temporary is in the frame
+    slots: Vec<usize>,
+    /// The words of the frame, memory temporaries included
+    words: usize,
+    /// How the activation is named when it goes past a limit
+    name: String
+}
+
+/// A running activation of a procedure, at the quadruple it runs next. A
+/// caller stays at its call until the callee returns.
+struct Frame {
+    procedure: usize,
+    block: usize,
+    quad: usize,
+    base: usize,
+    temps: Vec<i64>
+}
+
+/// What the program does after a quadruple
+enum Flow {
+    Next,
+    Jump(usize),
+    Call(usize),
+    Return(i64)
+}
+
+/// Runs the quadruples of a checked program
+pub struct Executor<'a> {
+    program: &'a Program,
+    module: &'a Module,
+    levels: Vec<usize>,
+    layouts: Vec<Layout>,
+    shapes: Vec<Shape>,
+    procedures: HashMap<&'a str, usize>,
+    stack: Vec<i64>,
+    heap: Vec<i64>,
+    /// The addresses of the heap variables not disposed of yet, with the
+    /// words they take
+    live: HashMap<usize, usize>,
+    frames: Vec<Frame>,
+    /// The parameters of the next call
+    params: Vec<i64>,
+    input: Input<'a>,
+    output: Box<dyn Write + 'a>,
+    meter: Meter
+}
+
+fn words(bytes: i32) -> usize {
+    (bytes.max(0) as usize).div_ceil(8)
+}
+
+impl<'a> Executor<'a> {
+    pub fn new(program: &'a Program, module: &'a Module, input: impl BufRead + 'a, output: impl Write + 'a)
+            -> Executor<'a> {
+        let layouts = Layout::all(program);
+        let shapes = module.procedures.iter()
+            .map(|procedure| {
+                let mut offset = words(layouts[procedure.scope].size);
+                let slots = procedure.slots.iter()
+                    .map(|size| {
+                        offset += words(*size);
+                        (offset - words(*size)) * 8
+                    })
+                    .collect();
+                let name = match subprogram_of(program, procedure.scope) {
+                    Some(subprogram) if subprogram.is_function() => format!("function {}", subprogram.name),
+                    Some(subprogram) => format!("procedure {}", subprogram.name),
+                    None => format!("program {}", program.name)
+                };
+                Shape {
+                    blocks: procedure.blocks.iter().enumerate().map(|(index, block)| (block.label, index)).collect(),
+                    slots,
+                    words: offset.max(1),
+                    name
+                }
+            })
+            .collect();
+        Executor {
+            program,
+            module,
+            levels: levels(program),
+            layouts,
+            shapes,
+            procedures: module.procedures.iter().enumerate()
+                .map(|(index, procedure)| (procedure.name.as_str(), index))
+                .collect(),
+            stack: vec![],
+            heap: vec![],
+            live: HashMap::new(),
+            frames: vec![],
+            params: vec![],
+            input: Input::new(input),
+            output: Box::new(output),
+            meter: Meter::new(Limits::default())
+        }
+    }
+
+    /// Stops the program once it goes past one of `limits`
+    pub fn limit(&mut self, limits: Limits) {
+        self.meter = Meter::new(limits);
+    }
+
+    pub fn run(&mut self) -> Result<(), RuntimeError> {
+        let main = self.module.procedures.len() - 1;
+        let words = self.shapes[main].words;
+        self.stack = vec![0; GLOBALS / 8 + words];
+        self.frames = vec![Frame {
+            procedure: main,
+            block: 0,
+            quad: 0,
+            base: GLOBALS,
+            temps: vec![0; self.module.procedures[main].temps + 1]
+        }];
+        let outcome = self.meter.allocate(words).and_then(|()| self.execute());
+        let outcome = outcome.map_err(|kind| {
+            RuntimeError::new(self.current().map_or(0..0, |quad| quad.range.clone()), kind)
+        });
+        self.frames.clear();
+        let flushed = self.output.flush()
+            .map_err(|err| RuntimeError::new(0..0, RuntimeErrorKind::Output(err.to_string())));
+        outcome.and(flushed)
+    }
+
+    /// The quadruple the innermost activation runs next
+    fn current(&self) -> Option<&'a Quad> {
+        let module = self.module;
+        let frame = self.frames.last()?;
+        module.procedures[frame.procedure].blocks.get(frame.block)?.quads.get(frame.quad)
+    }
+
+    fn frame(&self) -> &Frame {
+        self.frames.last().expect("an activation is running")
+    }
+
+    fn execute(&mut self) -> Fallible<()> {
+        loop {
+            let (procedure, block) = (self.frame().procedure, self.frame().block);
+            let Some(quad) = self.current() else {
+                // a block that does not end with a jump goes on to the next
+                let frame = self.frames.last_mut().expect("an activation is running");
+                frame.block = block + 1;
+                frame.quad = 0;
+                continue;
+            };
+            let shapes = &self.shapes;
+            self.meter.step(|| shapes[procedure].name.clone())?;
+            match self.quad(quad)? {
+                Flow::Next => self.frames.last_mut().expect("an activation is running").quad += 1,
+                Flow::Jump(label) => {
+                    let frame = self.frames.last_mut().expect("an activation is running");
+                    frame.block = self.shapes[procedure].blocks[&label];
+                    frame.quad = 0;
+                },
+                Flow::Call(callee) => self.enter(callee)?,
+                Flow::Return(value) => {
+                    let frame = self.frames.pop().expect("an activation is running");
+                    if self.frames.is_empty() {
+                        return Ok(());
+                    }
+                    self.meter.free(self.shapes[frame.procedure].words);
+                    self.meter.leave();
+                    // the frame is only taken back by the next call, so that
+                    // a block the function gives can still be copied out of it
+                    let call = self.current().expect("the caller is at its call");
+                    self.assign(&call.result, value);
+                    self.frames.last_mut().expect("an activation is running").quad += 1;
+                }
+            }
+        }
+    }
+
+    /// Starts an activation of the procedure `index` with the parameters of
+    /// the call
+    fn enter(&mut self, index: usize) -> Fallible<()> {
+        let procedure = &self.module.procedures[index];
+        let subprogram = subprogram_of(self.program, procedure.scope).expect("procedures are lowered from subprograms");
+        let parent = self.program.scopes[subprogram.scope].parent.expect("subprograms are in a scope");
+        let link = self.frame_of(parent);
+        let shapes = &self.shapes;
+        self.meter.enter(|| shapes[index].name.clone())?;
+        self.meter.allocate(shapes[index].words)?;
+        let top = self.frames.iter()
+            .map(|frame| frame.base / 8 + self.shapes[frame.procedure].words)
+            .max()
+            .unwrap_or(0);
+        self.stack.truncate(top);
+        let base = self.stack.len() * 8;
+        self.stack.resize(self.stack.len() + self.shapes[index].words, 0);
+        self.store(base, link as i64);
+        let params = std::mem::take(&mut self.params);
+        for (param, value) in subprogram.params.iter().zip(params) {
+            let at = base + self.layouts[subprogram.scope].fields[&param.name] as usize;
+            if is_block(&param.r#type) && !param.by_ref {
+                self.copy(at, value as usize, size(&param.r#type));
+            } else {
+                self.store(at, value);
+            }
+        }
+        self.frames.push(Frame { procedure: index, block: 0, quad: 0, base, temps: vec![0; procedure.temps + 1] });
+        Ok(())
+    }
+
+    /// The address of the frame of `scope`, the innermost one reachable from
+    /// the running procedure
+    fn frame_of(&self, scope: ScopeId) -> usize {
+        let frame = self.frame();
+        let own = self.module.procedures[frame.procedure].scope;
+        if scope == own {
+            return frame.base;
+        }
+        if scope == Program::GLOBAL {
+            return GLOBALS;
+        }
+        // the link is at the start of a frame
+        (self.levels[scope]..self.levels[own]).fold(frame.base, |base, _| self.load(base) as usize)
+    }
+
+    fn word(&mut self, address: usize) -> &mut i64 {
+        if address >= HEAP_BASE {
+            &mut self.heap[(address - HEAP_BASE) / 8]
+        } else {
+            &mut self.stack[address / 8]
+        }
+    }
+
+    fn load(&self, address: usize) -> i64 {
+        if address >= HEAP_BASE {
+            self.heap[(address - HEAP_BASE) / 8]
+        } else {
+            self.stack[address / 8]
+        }
+    }
+
+    fn store(&mut self, address: usize, value: i64) {
+        *self.word(address) = value;
+    }
+
+    fn copy(&mut self, to: usize, from: usize, bytes: i32) {
+        let values = (0..words(bytes)).map(|index| self.load(from + index * 8)).collect::<Vec<_>>();
+        for (index, value) in values.into_iter().enumerate() {
+            self.store(to + index * 8, value);
+        }
+    }
+
+    /// The address of a variable or a memory temporary
+    fn address(&self, arg: &Arg) -> usize {
+        match arg {
+            Arg::Var(scope, name) => {
+                let layout = &self.layouts[*scope];
+                let offset = layout.fields.get(name).copied().unwrap_or(layout.result);
+                self.frame_of(*scope) + offset as usize
+            },
+            Arg::Slot(slot) => self.frame().base + self.shapes[self.frame().procedure].slots[slot - 1],
+            arg => unreachable!("{:?} is not in memory", arg)
+        }
+    }
+
+    /// The value of a field, the bits of a real
+    fn value(&self, arg: &Arg) -> i64 {
+        match arg {
+            Arg::Int(value) => *value,
+            Arg::Real(value) => value.to_bits() as i64,
+            Arg::Temp(temp) => self.frame().temps[*temp],
+            Arg::Var(..) | Arg::Slot(_) => self.load(self.address(arg)),
+            Arg::None | Arg::Label(_) | Arg::Name(_) | Arg::Type(_) => 0
+        }
+    }
+
+    fn real(&self, arg: &Arg) -> f64 {
+        match arg {
+            Arg::Int(value) => *value as f64,
+            Arg::Real(value) => *value,
+            arg => f64::from_bits(self.value(arg) as u64)
+        }
+    }
+
+    /// Assigns a temporary or a variable
+    fn assign(&mut self, place: &Arg, value: i64) {
+        match place {
+            Arg::None => {},
+            Arg::Temp(temp) => self.frames.last_mut().expect("an activation is running").temps[*temp] = value,
+            variable => {
+                let address = self.address(variable);
+                self.store(address, value);
+            }
+        }
+    }
+
+    fn quad(&mut self, quad: &'a Quad) -> Fallible<Flow> {
+        let (arg1, arg2) = (&quad.arg1, &quad.arg2);
+        let (a, b) = (self.value(arg1), self.value(arg2));
+        let (x, y) = (self.real(arg1), self.real(arg2));
+        let real = |value: f64| value.to_bits() as i64;
+        let domain = |function: &str, argument: f64| RuntimeErrorKind::Domain { function: function.to_string(), argument };
+        let value = match quad.op {
+            Op::Assign => a,
+            Op::Address => self.address(arg1) as i64,
+            Op::Load => self.load(a as usize),
+            Op::Store => {
+                self.store(self.value(&quad.result) as usize, a);
+                return Ok(Flow::Next);
+            },
+            Op::Add => a.wrapping_add(b),
+            Op::Sub => a.wrapping_sub(b),
+            Op::Mul => a.wrapping_mul(b),
+            Op::Div | Op::Mod if b == 0 => return Err(RuntimeErrorKind::DivisionByZero),
+            Op::Div => a.wrapping_div(b),
+            Op::Mod => a.wrapping_rem_euclid(b),
+            Op::Neg => a.wrapping_neg(),
+            Op::FAdd => real(x + y),
+            Op::FSub => real(x - y),
+            Op::FMul => real(x * y),
+            Op::FDiv if y == 0.0 => return Err(RuntimeErrorKind::DivisionByZero),
+            Op::FDiv => real(x / y),
+            Op::FNeg => real(-x),
+            Op::Eq => (a == b) as i64,
+            Op::Ne => (a != b) as i64,
+            Op::Lt => (a < b) as i64,
+            Op::Le => (a <= b) as i64,
+            Op::Gt => (a > b) as i64,
+            Op::Ge => (a >= b) as i64,
+            Op::FEq => (x == y) as i64,
+            Op::FNe => (x != y) as i64,
+            Op::FLt => (x < y) as i64,
+            Op::FLe => (x <= y) as i64,
+            Op::FGt => (x > y) as i64,
+            Op::FGe => (x >= y) as i64,
+            Op::Not => a ^ 1,
+            Op::Odd => a & 1,
+            Op::Abs => a.wrapping_abs(),
+            Op::FAbs => real(x.abs()),
+            Op::Float => real(a as f64),
+            // reals out of the range of integers saturate, NaN becoming 0
+            Op::Trunc => x as i64,
+            Op::Round => x.round() as i64,
+            Op::Sqrt if x < 0.0 => return Err(domain("sqrt", x)),
+            Op::Sqrt => real(x.sqrt()),
+            Op::Ln if x <= 0.0 => return Err(domain("ln", x)),
+            Op::Ln => real(x.ln()),
+            Op::Sin => real(x.sin()),
+            Op::Cos => real(x.cos()),
+            Op::Exp => real(x.exp()),
+            Op::Arctan => real(x.atan()),
+            Op::Check | Op::Bounds | Op::NoCase => {
+                let ty = match arg2 {
+                    Arg::Type(ty) => ty,
+                    arg => unreachable!("{:?} is not a type", arg)
+                };
+                if quad.op == Op::NoCase {
+                    return Err(RuntimeErrorKind::NoCaseLabel(ty.ordinal_name(a as isize)));
+                }
+                let bounds = ty.bounds().expect("checked types have bounds");
+                let (low, high) = (bounds.start as i64, bounds.end as i64);
+                if a < low || a > high {
+                    return Err(if quad.op == Op::Check {
+                        RuntimeErrorKind::OutOfRange { value: a, r#type: ty.clone() }
+                    } else {
+                        RuntimeErrorKind::IndexOutOfRange { index: a, low, high }
+                    });
+                }
+                return Ok(Flow::Next);
+            },
+            Op::Deref => {
+                self.check_pointer(a)?;
+                a
+            },
+            Op::Goto => return Ok(Flow::Jump(quad.target().expect("jumps have a target"))),
+            Op::If | Op::IfFalse => return Ok(match (a != 0) == (quad.op == Op::If) {
+                true => Flow::Jump(quad.target().expect("jumps have a target")),
+                false => Flow::Next
+            }),
+            Op::Param => {
+                self.params.push(a);
+                return Ok(Flow::Next);
+            },
+            Op::Call => return self.call(quad),
+            Op::Return => return Ok(Flow::Return(a))
+        };
+        self.assign(&quad.result, value);
+        Ok(Flow::Next)
+    }
+
+    /// Checks that a pointer points to a variable that was not disposed of
+    fn check_pointer(&self, pointer: i64) -> Fallible<()> {
+        if pointer == 0 {
+            Err(RuntimeErrorKind::NilPointer)
+        } else if !self.live.contains_key(&(pointer as usize)) {
+            Err(RuntimeErrorKind::DanglingPointer)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn write(&mut self, text: &str) -> Fallible<()> {
+        self.meter.write(text.len())?;
+        self.output.write_all(text.as_bytes())
+            .map_err(|err| RuntimeErrorKind::Output(err.to_string()))
+    }
+
+    /// The `length` characters from `address`
+    fn string(&self, address: i64, length: i64) -> String {
+        (0..length.max(0) as usize)
+            .map(|index| char::from_u32(self.load(address as usize + index * 8) as u32).unwrap_or('\0'))
+            .collect()
+    }
+
+    /// The 4 words of the bits of the set at `address`
+    fn set(&self, address: i64) -> [u64; 4] {
+        std::array::from_fn(|index| self.load(address as usize + index * 8) as u64)
+    }
+
+    /// A call to a subprogram, with the parameters of the quadruples before
+    /// it, or to a routine of the runtime
+    fn call(&mut self, quad: &'a Quad) -> Fallible<Flow> {
+        let name = match &quad.arg1 {
+            Arg::Name(name) => name,
+            arg => unreachable!("{:?} is not a procedure", arg)
+        };
+        if callee(self.program, name).is_some() {
+            return Ok(Flow::Call(self.procedures[name.as_str()]));
+        }
+        let params = std::mem::take(&mut self.params);
+        let int = |index: usize| params[index];
+        let real = |index: usize| f64::from_bits(params[index] as u64);
+        let routine = routine(name);
+        let value = match name.as_str() {
+            "write_integer" => self.write(&int(0).to_string()).map(|()| 0)?,
+            "write_real" => self.write(&format::floating(real(0), format::REAL_WIDTH)).map(|()| 0)?,
+            "write_char" => self.write(&character(int(0)).to_string()).map(|()| 0)?,
+            "write_boolean" => self.write(&(int(0) != 0).to_string()).map(|()| 0)?,
+            "write_string" => {
+                let text = self.string(int(0), int(1));
+                self.write(&text).map(|()| 0)?
+            },
+            "write_integer_width" => self.write(&format::pad(&int(0).to_string(), format::field(int(1))?)).map(|()| 0)?,
+            "write_real_width" => self.write(&format::floating(real(0), format::field(int(1))?)).map(|()| 0)?,
+            "write_real_fixed" => {
+                let (width, decimals) = (format::field(int(1))?, format::field(int(2))?);
+                self.write(&format::fixed(real(0), width, decimals)).map(|()| 0)?
+            },
+            "write_char_width" => self.write(&format::pad(&character(int(0)).to_string(), format::field(int(1))?))
+                .map(|()| 0)?,
+            "write_boolean_width" => self.write(&format::fit(&(int(0) != 0).to_string(), format::field(int(1))?))
+                .map(|()| 0)?,
+            "write_string_width" => {
+                let width = format::field(int(2))?;
+                let text = self.string(int(0), int(1));
+                self.write(&format::fit(&text, width)).map(|()| 0)?
+            },
+            "writeln" => self.write("\n").map(|()| 0)?,
+            "read_integer" => self.input.read_integer()?,
+            "read_real" => self.input.read_real()?.to_bits() as i64,
+            "read_char" => self.input.read_char()? as i64,
+            "readln" => self.input.skip_line().map(|()| 0)?,
+            "eof" => self.input.eof() as i64,
+            "eoln" => self.input.eoln() as i64,
+            "new" => {
+                let words = words(int(0) as i32).max(1);
+                self.meter.allocate(words)?;
+                let address = HEAP_BASE + self.heap.len() * 8;
+                self.heap.resize(self.heap.len() + words, 0);
+                self.live.insert(address, words);
+                address as i64
+            },
+            "dispose" => {
+                self.check_pointer(int(0))?;
+                if let Some(words) = self.live.remove(&(int(0) as usize)) {
+                    self.meter.free(words);
+                }
+                0
+            },
+            "zero" => {
+                for index in 0..words(int(1) as i32) {
+                    self.store(int(0) as usize + index * 8, 0);
+                }
+                0
+            },
+            "copy" => {
+                self.copy(int(0) as usize, int(1) as usize, int(2) as i32);
+                0
+            },
+            "set_add" => {
+                let member = int(1);
+                if !(0..=255).contains(&member) {
+                    let r#type = Type::Range(Box::new(Type::Integer), 0..255);
+                    return Err(RuntimeErrorKind::OutOfRange { value: member, r#type });
+                }
+                let at = int(0) as usize + member as usize / 64 * 8;
+                *self.word(at) |= 1 << (member % 64);
+                0
+            },
+            "set_has" => {
+                let member = int(1);
+                ((0..=255).contains(&member) && self.set(int(0))[member as usize / 64] >> (member % 64) & 1 == 1) as i64
+            },
+            "set_combine" => {
+                let (left, right) = (self.set(int(1)), self.set(int(2)));
+                for (index, (left, right)) in left.iter().zip(&right).enumerate() {
+                    let bits = match int(3) {
+                        0 => left | right,
+                        1 => left & !right,
+                        _ => left & right
+                    };
+                    self.store(int(0) as usize + index * 8, bits as i64);
+                }
+                0
+            },
+            "set_eq" => (self.set(int(0)) == self.set(int(1))) as i64,
+            "set_le" => self.set(int(0)).iter().zip(&self.set(int(1))).all(|(left, right)| left & !right == 0) as i64,
+            "compare" => {
+                let at = |start: i64, index: usize| self.load(start as usize + index * 8);
+                (0..int(2).max(0) as usize)
+                    .map(|index| at(int(0), index).cmp(&at(int(1), index)))
+                    .find(|ordering| ordering.is_ne())
+                    .map_or(0, |ordering| ordering as i64)
+            },
+            "add_checked" => int(0).checked_add(int(1)).ok_or(RuntimeErrorKind::Overflow)?,
+            "sub_checked" => int(0).checked_sub(int(1)).ok_or(RuntimeErrorKind::Overflow)?,
+            "mul_checked" => int(0).checked_mul(int(1)).ok_or(RuntimeErrorKind::Overflow)?,
+            "div_checked" if int(1) == 0 => return Err(RuntimeErrorKind::DivisionByZero),
+            "div_checked" => int(0).checked_div(int(1)).ok_or(RuntimeErrorKind::Overflow)?,
+            "neg_checked" => int(0).checked_neg().ok_or(RuntimeErrorKind::Overflow)?,
+            "abs_checked" => int(0).checked_abs().ok_or(RuntimeErrorKind::Overflow)?,
+            name => unreachable!("the runtime has no routine '{}'", name)
+        };
+        if routine.result.is_some() {
+            self.assign(&quad.result, value);
+        }
+        Ok(Flow::Next)
+    }
+}
+
+fn character(value: i64) -> char {
+    char::from_u32(value as u32).unwrap_or('\0')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emit::tests::{check, fault, interpret, report, samples, FAULTS};
+    use crate::ir::lower::lower;
+    use crate::ir::opt::optimize;
+
+    /// What a program writes as its quadruples optimized at `level` run,
+    /// and the runtime error it stops with
+    fn run(program: &Program, level: u8, limits: Limits, input: &str) -> (String, Option<RuntimeError>) {
+        let (module, _) = optimize(lower(program), level);
+        let mut output = vec![];
+        let mut executor = Executor::new(program, &module, input.as_bytes(), &mut output);
+        executor.limit(limits);
+        let outcome = executor.run();
+        drop(executor);
+        (String::from_utf8(output).unwrap(), outcome.err())
+    }
+
+    #[test]
+    fn samples_behave_as_interpreted() {
+        for (name, program, input) in samples() {
+            let expected = interpret(&program, &input);
+            for level in 0..=2 {
+                let (output, error) = run(&program, level, Limits::default(), &input);
+                assert_eq!((output, error.map(|err| report(&program, &err))), expected, "{} at -O{}", name, level);
+            }
+        }
+    }
+
+    #[test]
+    fn runtime_errors() {
+        for body in FAULTS {
+            let program = fault(body);
+            let (_, expected) = interpret(&program, "");
+            for level in 0..=2 {
+                let (_, error) = run(&program, level, Limits::default(), "");
+                assert_eq!(error.map(|err| report(&program, &err)), expected, "{} at -O{}", body, level);
+            }
+        }
+    }
+
+    #[test]
+    fn tail_calls_made_jumps_take_no_frames() {
+        let program = check("
+            program deep;
+            function count(n, total: integer): integer;
+            begin
+              if n = 0 then count := total
+              else count := count(n - 1, total + 1)
+            end;
+            begin
+              writeln(count(100000, 0))
+            end.
+        ");
+        let limits = Limits { depth: Some(100), ..Limits::default() };
+        let (_, error) = run(&program, 0, limits, "");
+        assert!(matches!(error.map(|err| err.kind), Some(RuntimeErrorKind::DepthLimit { .. })));
+        assert_eq!(run(&program, 2, limits, "").0, "100000\n");
+    }
+
+    #[test]
+    fn tail_recursive_list_walks_take_no_frames() {
+        let program = check("
+            program walks;
+            type list = ^node;
+                 node = record value: integer; next: list end;
+            var head, p: list; i, total: integer;
+            function length(p: list; n: integer): integer;
+            begin
+              if p = nil then length := n
+              else length := length(p^.next, n + 1)
+            end;
+            procedure sum(p: list);
+            begin
+              if p <> nil then begin
+                total := total + p^.value;
+                sum(p^.next)
+              end
+            end;
+            begin
+              head := nil;
+              for i := 1 to 10000 do begin
+                new(p); p^.value := i; p^.next := head; head := p
+              end;
+              total := 0;
+              sum(head);
+              writeln(length(head, 0), ' ', total)
+            end.
+        ");
+        let limits = Limits { depth: Some(100), ..Limits::default() };
+        let (_, error) = run(&program, 0, limits, "");
+        assert!(matches!(error.map(|err| err.kind), Some(RuntimeErrorKind::DepthLimit { .. })));
+        let (output, error) = run(&program, 2, limits, "");
+        assert!(error.is_none(), "{:?}", error);
+        assert_eq!(output, "10000 50005000\n");
+    }
+
+    #[test]
+    fn loops_over_arrays_run_fewer_quadruples_at_o2() {
+        let program = check("
+            program sums;
+            var a: array [1..1000] of integer;
+                i, s: integer;
+            begin
+              for i := 1 to 1000 do a[i] := 2 * i;
+              s := 0;
+              for i := 1 to 1000 do s := s + a[i];
+              writeln(s)
+            end.
+        ");
+        let limits = Limits { steps: Some(20_000), ..Limits::default() };
+        for level in 0..=1 {
+            let (_, error) = run(&program, level, limits, "");
+            assert!(matches!(error.map(|err| err.kind), Some(RuntimeErrorKind::StepLimit { .. })), "-O{}", level);
+        }
+        let (output, error) = run(&program, 2, limits, "");
+        assert!(error.is_none(), "{:?}", error);
+        assert_eq!(output, "1001000\n");
+    }
+}
@@ -0,0 +1,448 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use crate::ast::program::ScopeId;
+use super::{Arg, Block, Module, Op, Phi, Procedure, Quad};
+use super::cfg::indices;
+use super::opt::{iterate, Pass};
+
+/// How big a procedure inlined into its callers may be, in quadruples and
+/// φ-functions
+pub const INLINE_LIMIT: usize = 40;
+
+/// What inlining and the elimination of tail calls did to a module, as
+/// `--opt-report` writes it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Report {
+    pub program: String,
+    /// The procedure inlined, the procedure it was inlined into and the
+    /// line of the call
+    pub inlined: Vec<(String, String, usize)>,
+    /// The procedure and the line of each call to itself made a jump
+    pub tail_calls: Vec<(String, usize)>
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "optimization report for {}", self.program)?;
+        for (callee, caller, line) in &self.inlined {
+            writeln!(f, "  inlined {} into {} at line {}", callee, caller, line)?;
+        }
+        for (procedure, line) in &self.tail_calls {
+            writeln!(f, "  made the tail call of {} at line {} a jump", procedure, line)?;
+        }
+        let (inlined, tail_calls) = (self.inlined.len(), self.tail_calls.len());
+        writeln!(f, "  {} call{} inlined, {} tail call{} eliminated",
+                 inlined, if inlined == 1 { "" } else { "s" }, tail_calls, if tail_calls == 1 { "" } else { "s" })
+    }
+}
+
+/// Makes each call of a subprogram to itself after which it only returns
+/// what the call gives a jump back to its start, the arguments being
+/// assigned to its parameters and its other variables cleared as a new
+/// frame would have them, so that it runs in constant stack. A subprogram
+/// with a variable the address of which is taken is left alone, as its
+/// arrays, records and sets would have to be copied or cleared.
+///
+/// This is done before the procedures are put in SSA form, where the
+/// parameters are still variables.
+pub fn tail_calls(module: &mut Module, report: &mut Report) {
+    let mut variables: HashMap<ScopeId, Vec<String>> = HashMap::new();
+    let mut addressed = HashSet::new();
+    for quad in module.procedures.iter().flat_map(|procedure| procedure.blocks.iter().flat_map(|block| &block.quads)) {
+        for field in [&quad.arg1, &quad.arg2, &quad.result] {
+            if let Arg::Var(scope, name) = field {
+                let names = variables.entry(*scope).or_default();
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+                if quad.op == Op::Address {
+                    addressed.insert(*scope);
+                }
+            }
+        }
+    }
+    for procedure in &mut module.procedures {
+        if procedure.parent.is_none() || addressed.contains(&procedure.scope) {
+            continue;
+        }
+        let cleared = variables.get(&procedure.scope).into_iter().flatten()
+            .filter(|name| !procedure.params.contains(name))
+            .cloned()
+            .collect::<Vec<_>>();
+        for block in 0..procedure.blocks.len() {
+            if let Some(at) = tail_call(procedure, block) {
+                report.tail_calls.push((procedure.name.clone(), procedure.blocks[block].quads[at].line));
+                jump(procedure, block, at, &cleared);
+            }
+        }
+    }
+}
+
+/// The index of the call of a procedure to itself in one of its blocks,
+/// when all that follows it is copying what it gives and returning that
+fn tail_call(procedure: &Procedure, block: usize) -> Option<usize> {
+    let quads = &procedure.blocks[block].quads;
+    let at = quads.iter().rposition(|quad| quad.op == Op::Call)?;
+    let (call, count) = (&quads[at], procedure.params.len());
+    let passed = at >= count && quads[at - count..at].iter().all(|quad| quad.op == Op::Param);
+    if call.arg1 != Arg::Name(procedure.name.clone()) || call.arg2 != Arg::Int(count as i64) || !passed {
+        return None;
+    }
+    let indices = indices(procedure);
+    let mut results = vec![call.result.clone()];
+    let mut visited = HashSet::new();
+    let (mut block, mut start) = (block, at + 1);
+    while visited.insert((block, start)) {
+        let quads = &procedure.blocks[block].quads;
+        let mut next = block + 1;
+        for quad in &quads[start..] {
+            match quad.op {
+                // what is assigned to goes with the frame
+                Op::Assign if !call.result.is_none() && results.contains(&quad.arg1)
+                    && match &quad.result {
+                        Arg::Var(scope, _) => *scope == procedure.scope,
+                        result => matches!(result, Arg::Temp(_))
+                    } =>
+                    results.push(quad.result.clone()),
+                Op::Goto => next = indices[&quad.target()?],
+                Op::Return if results.contains(&quad.arg1) => return Some(at),
+                _ => return None
+            }
+        }
+        if next == procedure.blocks.len() {
+            return None;
+        }
+        (block, start) = (next, 0);
+    }
+    None
+}
+
+/// Replaces a call of a procedure to itself and what follows it in its
+/// block with a jump to its start
+fn jump(procedure: &mut Procedure, block: usize, at: usize, cleared: &[String]) {
+    let count = procedure.params.len();
+    let (line, range) = (procedure.blocks[block].quads[at].line, procedure.blocks[block].quads[at].range.clone());
+    let args = procedure.blocks[block].quads.drain(at - count..).take(count).map(|quad| quad.arg1).collect::<Vec<_>>();
+    let mut quads = vec![];
+    let mut assign = |arg1: Arg, result: Arg| quads.push(Quad { op: Op::Assign, arg1, arg2: Arg::None, result, line, range: range.clone() });
+    // the arguments are all read before any parameter is assigned
+    let mut values = vec![];
+    for arg in args {
+        if matches!(arg, Arg::Var(..)) {
+            let temp = procedure.fresh_temp();
+            assign(arg, temp.clone());
+            values.push(temp);
+        } else {
+            values.push(arg);
+        }
+    }
+    let scope = procedure.scope;
+    for (param, value) in procedure.params.iter().zip(values) {
+        assign(value, Arg::Var(scope, param.clone()));
+    }
+    for name in cleared {
+        assign(Arg::Int(0), Arg::Var(scope, name.clone()));
+    }
+    let start = procedure.blocks[0].label;
+    quads.push(Quad { op: Op::Goto, arg1: Arg::None, arg2: Arg::None, result: Arg::Label(start), line, range });
+    procedure.blocks[block].quads.extend(quads);
+}
+
+/// The number of quadruples and φ-functions of a procedure
+fn size(procedure: &Procedure) -> usize {
+    procedure.blocks.iter().map(|block| block.phis.len() + block.quads.len()).sum()
+}
+
+/// Inlines the calls to small subprograms that do not call themselves,
+/// directly or through others, into their callers, which are optimized
+/// again with `passes`. The procedures are in SSA form, and their callees
+/// are inlined into them first, so that what those inline goes along.
+///
+/// A subprogram is only inlined where all it reads and calls can be seen
+/// from the caller, and when the variables of its own scope are all kept in
+/// temporaries: the copies of them on entry then take the arguments, or 0
+/// as a new frame would have, and a parameter passed by reference, which
+/// holds the address of the argument, keeps aliasing it. Each return
+/// becomes a jump to the rest of the block of the call, where a φ-function
+/// merges the results.
+pub fn inline(module: &mut Module, shared: &HashSet<(ScopeId, String)>, passes: &[Pass], report: &mut Report) {
+    let procedures = &module.procedures;
+    let numbers = procedures.iter().enumerate()
+        .map(|(index, procedure)| (procedure.name.clone(), index))
+        .collect::<HashMap<_, _>>();
+    let parents = procedures.iter().map(|procedure| (procedure.scope, procedure.parent)).collect::<HashMap<_, _>>();
+    let calls = procedures.iter()
+        .map(|procedure| {
+            let mut callees = vec![];
+            for quad in procedure.blocks.iter().flat_map(|block| &block.quads) {
+                match (quad.op, &quad.arg1) {
+                    (Op::Call, Arg::Name(name)) if numbers.contains_key(name) && !callees.contains(&numbers[name]) =>
+                        callees.push(numbers[name]),
+                    _ => {}
+                }
+            }
+            callees
+        })
+        .collect::<Vec<_>>();
+    let recursive = (0..procedures.len())
+        .map(|start| {
+            let mut reached = HashSet::new();
+            let mut work = calls[start].clone();
+            while let Some(callee) = work.pop() {
+                if reached.insert(callee) {
+                    work.extend(&calls[callee]);
+                }
+            }
+            reached.contains(&start)
+        })
+        .collect::<Vec<_>>();
+    // the callees before their callers
+    let mut order = vec![];
+    let mut visited = vec![false; procedures.len()];
+    for start in 0..procedures.len() {
+        let mut stack = vec![(start, 0)];
+        visited[start] |= true;
+        while let Some((procedure, next)) = stack.pop() {
+            match calls[procedure].get(next) {
+                Some(&callee) => {
+                    stack.push((procedure, next + 1));
+                    if !visited[callee] {
+                        visited[callee] = true;
+                        stack.push((callee, 0));
+                    }
+                },
+                None => if !order.contains(&procedure) {
+                    order.push(procedure)
+                }
+            }
+        }
+    }
+    for caller in order {
+        let mut enclosing = vec![];
+        let mut scope = Some(module.procedures[caller].scope);
+        while let Some(outer) = scope {
+            enclosing.push(outer);
+            scope = parents.get(&outer).copied().flatten();
+        }
+        let mut changed = false;
+        while let Some((block, at, callee)) = site(&module.procedures, caller, &numbers, &recursive, &enclosing, shared) {
+            let callee = module.procedures[callee].clone();
+            let line = module.procedures[caller].blocks[block].quads[at].line;
+            splice(&mut module.procedures[caller], block, at, &callee);
+            report.inlined.push((callee.name, module.procedures[caller].name.clone(), line));
+            changed = true;
+        }
+        if changed {
+            iterate(&mut module.procedures[caller], passes);
+        }
+    }
+}
+
+/// Where a procedure calls one that can be inlined into it: the block, the
+/// index of the call and the callee
+fn site(procedures: &[Procedure], caller: usize, numbers: &HashMap<String, usize>, recursive: &[bool],
+        enclosing: &[ScopeId], shared: &HashSet<(ScopeId, String)>) -> Option<(usize, usize, usize)> {
+    procedures[caller].blocks.iter().enumerate().find_map(|(index, block)| {
+        block.quads.iter().enumerate().find_map(|(at, quad)| {
+            let callee = match (quad.op, &quad.arg1) {
+                (Op::Call, Arg::Name(name)) => *numbers.get(name)?,
+                _ => return None
+            };
+            let count = procedures[callee].params.len();
+            let passed = at >= count && block.quads[at - count..at].iter().all(|quad| quad.op == Op::Param);
+            let inlined = callee != caller && !recursive[callee] && passed
+                && inlinable(procedures, callee, numbers, enclosing, shared);
+            inlined.then_some((index, at, callee))
+        })
+    })
+}
+
+/// Whether a procedure is small enough to be inlined, into a procedure
+/// that sees the scopes `enclosing`
+fn inlinable(procedures: &[Procedure], callee: usize, numbers: &HashMap<String, usize>, enclosing: &[ScopeId],
+             shared: &HashSet<(ScopeId, String)>) -> bool {
+    let callee = &procedures[callee];
+    let visible = |quad: &Quad| [&quad.arg1, &quad.arg2, &quad.result].iter().enumerate().all(|(index, field)| match field {
+        // the copies on entry
+        Arg::Var(scope, name) if *scope == callee.scope =>
+            index == 0 && quad.op == Op::Assign && !shared.contains(&(*scope, name.clone())),
+        Arg::Var(scope, _) => enclosing.contains(scope),
+        Arg::Name(name) => numbers.get(name).is_none_or(|index| {
+            procedures[*index].parent.is_some_and(|parent| enclosing.contains(&parent))
+        }),
+        _ => true
+    });
+    size(callee) <= INLINE_LIMIT && callee.parent.is_some()
+        && callee.blocks.iter().flat_map(|block| &block.quads).all(visible)
+}
+
+/// Replaces the call at `at` in a block of a procedure, and the parameters
+/// before it, with the blocks of the callee, the rest of the block
+/// following them
+fn splice(procedure: &mut Procedure, block: usize, at: usize, callee: &Procedure) {
+    let label = procedure.blocks[block].label;
+    let first = procedure.fresh_label();
+    let rest = first + callee.blocks.len();
+    for phi in procedure.blocks.iter_mut().flat_map(|block| &mut block.phis) {
+        for (from, _) in &mut phi.args {
+            if *from == label {
+                *from = rest;
+            }
+        }
+    }
+    let labels = callee.blocks.iter().enumerate()
+        .map(|(index, block)| (block.label, first + index))
+        .collect::<HashMap<_, _>>();
+    let (temps, slots) = (procedure.temps, procedure.slots.len());
+    procedure.temps += callee.temps;
+    procedure.slots.extend(&callee.slots);
+    let quads = &mut procedure.blocks[block].quads;
+    let after = quads.split_off(at + 1);
+    let call = quads.pop().expect("a call is inlined");
+    let args = quads.split_off(at - callee.params.len()).into_iter().map(|quad| quad.arg1).collect::<Vec<_>>();
+    let field = |arg: &Arg| match arg {
+        Arg::Temp(temp) => Arg::Temp(temp + temps),
+        Arg::Slot(slot) => Arg::Slot(slot + slots),
+        Arg::Label(label) => Arg::Label(labels[label]),
+        Arg::Var(scope, name) if *scope == callee.scope => callee.params.iter()
+            .position(|param| param == name)
+            .map_or(Arg::Int(0), |index| args[index].clone()),
+        arg => arg.clone()
+    };
+    let mut returns = vec![];
+    let mut blocks = vec![];
+    for block in &callee.blocks {
+        let label = labels[&block.label];
+        let phis = block.phis.iter()
+            .map(|phi| Phi {
+                result: field(&phi.result),
+                args: phi.args.iter().map(|(from, arg)| (labels[from], field(arg))).collect()
+            })
+            .collect();
+        let mut quads = vec![];
+        for quad in &block.quads {
+            quads.push(if quad.op == Op::Return {
+                returns.push((label, field(&quad.arg1)));
+                Quad { op: Op::Goto, arg1: Arg::None, arg2: Arg::None, result: Arg::Label(rest), line: quad.line, range: quad.range.clone() }
+            } else {
+                Quad { op: quad.op, arg1: field(&quad.arg1), arg2: field(&quad.arg2), result: field(&quad.result), line: quad.line,
+                       range: quad.range.clone() }
+            });
+        }
+        blocks.push(Block { label, phis, quads });
+    }
+    let phis = match call.result {
+        Arg::None => vec![],
+        result => vec![Phi { result, args: returns }]
+    };
+    blocks.push(Block { label: rest, phis, quads: after });
+    procedure.blocks.splice(block + 1..block + 1, blocks);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::lower::lower;
+    use super::super::opt::optimize;
+    use crate::emit::tests::check;
+
+    #[test]
+    fn small_subprograms_are_inlined() {
+        let (module, report) = optimize(lower(&check("
+            program inlined;
+            var x, y: integer;
+            function sqr(v: integer): integer;
+            begin
+              sqr := v * v
+            end;
+            procedure swap(var p, q: integer);
+            var t: integer;
+            begin
+              t := p; p := q; q := t
+            end;
+            function fact(n: integer): integer;
+            begin
+              if n = 0 then fact := 1 else fact := n * fact(n - 1)
+            end;
+            begin
+              x := 3; y := 4;
+              swap(x, y);
+              writeln(sqr(x) + sqr(y), fact(5))
+            end.
+        ")), 2);
+        // swap goes through the addresses of x and y, and fact calls itself
+        assert_eq!(module.procedures.last().unwrap().to_string(), "\
+procedure main
+      op      arg1           arg2    result
+L7:
+ (1)  :=      3                      x
+ (2)  :=      4                      y
+ (3)  &       x                      t1
+ (4)  &       y                      t2
+ (5)  load    t1                     t3
+ (6)  load    t2                     t4
+ (7)  store   t4                     t1
+ (8)  store   t3                     t2
+ (9)  :=      x                      t5
+(10)  *       t5             t5      t6
+(11)  :=      y                      t7
+(12)  *       t7             t7      t8
+(13)  +       t6             t8      t9
+(14)  param   t9
+(15)  call    write_integer  1
+(16)  param   5
+(17)  call    fact.4         1       t10
+(18)  param   t10
+(19)  call    write_integer  1
+(20)  call    writeln        0
+(21)  return
+");
+        assert_eq!(report.to_string(), "\
+optimization report for inlined
+  inlined swap.3 into main at line 19
+  inlined sqr.2 into main at line 20
+  inlined sqr.2 into main at line 20
+  3 calls inlined, 0 tail calls eliminated
+");
+    }
+
+    #[test]
+    fn tail_calls_become_jumps() {
+        let (module, report) = optimize(lower(&check("
+            program tails;
+            function gcd(a, b: integer): integer;
+            begin
+              if b = 0 then
+                gcd := a
+              else
+                gcd := gcd(b, a mod b)
+            end;
+            begin
+              writeln(gcd(1071, 462))
+            end.
+        ")), 2);
+        assert_eq!(module.procedures[0].to_string(), "\
+procedure gcd.2
+      op       arg1    arg2    result
+L1:
+ (1)  :=       b               t1
+ (2)  :=       a               t2
+ (3)  :=       t1              t3
+ (4)  :=       t2              t4
+L2:
+ (5)  =        t3      0       t5
+ (6)  iffalse  t5              L4
+L3:
+ (7)  goto                     L5
+L4:
+ (8)  mod      t4      t3      t6
+ (9)  :=       t3              t4
+(10)  :=       t6              t3
+(11)  goto                     L2
+L5:
+(12)  return   t4
+");
+        // once it no longer calls itself, it is small enough to inline
+        assert_eq!(report.tail_calls, vec![("gcd.2".to_string(), 8)]);
+        assert_eq!(report.inlined, vec![("gcd.2".to_string(), "main".to_string(), 11)]);
+    }
+}
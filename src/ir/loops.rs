@@ -0,0 +1,682 @@
+use std::collections::{HashMap, HashSet};
+use super::{Arg, Block, Op, Phi, Procedure, Quad};
+use super::cfg::{indices, loops, Dominators, Graph, Loop};
+use super::opt::{critical, int, substitute_all, Pass};
+
+/// The passes `-O2` runs with those of `-O1`
+pub const LOOP: &[Pass] = &[preheaders, invariants, inductions, ranges];
+
+/// The graph of a procedure, its dominators and its loops
+fn analyze(procedure: &Procedure) -> (Graph, Dominators, Vec<Loop>) {
+    let graph = Graph::new(procedure);
+    let dominators = Dominators::new(&graph);
+    let found = loops(&graph, &dominators);
+    (graph, dominators, found)
+}
+
+/// The preheader of a loop: the only block outside it going to its header,
+/// and going nowhere else
+fn preheader(graph: &Graph, found: &Loop) -> Option<usize> {
+    let outside = graph.predecessors[found.header].iter().filter(|pred| !found.contains(**pred)).collect::<Vec<_>>();
+    match outside[..] {
+        [pred] if graph.successors[*pred].len() == 1 => Some(*pred),
+        _ => None
+    }
+}
+
+/// The temporaries a loop assigns
+fn assigned(procedure: &Procedure, found: &Loop) -> HashSet<usize> {
+    found.blocks.iter()
+        .flat_map(|block| {
+            let block = &procedure.blocks[*block];
+            block.phis.iter().map(|phi| &phi.result).chain(block.quads.iter().filter_map(|quad| quad.def()))
+        })
+        .filter_map(|result| match result {
+            Arg::Temp(temp) => Some(*temp),
+            _ => None
+        })
+        .collect()
+}
+
+/// Where a temporary is assigned by a quadruple: its block and its index
+fn definition(procedure: &Procedure, temp: usize) -> Option<(usize, usize)> {
+    procedure.blocks.iter().enumerate().find_map(|(block, found)| found.quads.iter()
+        .position(|quad| quad.def() == Some(&Arg::Temp(temp)))
+        .map(|index| (block, index)))
+}
+
+/// Inserts a quadruple at the end of a block, before the jump ending it
+fn append(block: &mut Block, quad: Quad) {
+    let at = match block.quads.last() {
+        Some(last) if last.op.is_terminator() => block.quads.len() - 1,
+        _ => block.quads.len()
+    };
+    block.quads.insert(at, quad);
+}
+
+/// Gives each loop a preheader, a block going only to its header that the
+/// jumps from outside the loop go to instead, where what is taken out of
+/// the loop is computed. The φ-functions of the header merge what comes
+/// from outside in the preheader.
+pub fn preheaders(procedure: &mut Procedure) -> bool {
+    let mut changed = false;
+    loop {
+        let (graph, _, found) = analyze(procedure);
+        match found.into_iter().find(|found| preheader(&graph, found).is_none()) {
+            Some(found) => insert_preheader(procedure, &graph, &found),
+            None => return changed
+        }
+        changed = true;
+    }
+}
+
+fn insert_preheader(procedure: &mut Procedure, graph: &Graph, found: &Loop) {
+    let header = found.header;
+    let target = procedure.blocks[header].label;
+    let label = procedure.fresh_label();
+    let outside = graph.predecessors[header].iter()
+        .filter(|pred| !found.contains(**pred))
+        .map(|pred| procedure.blocks[*pred].label)
+        .collect::<Vec<_>>();
+    for block in &mut procedure.blocks {
+        if let Some(last) = block.quads.last_mut() {
+            if outside.contains(&block.label) && last.target() == Some(target) {
+                last.result = Arg::Label(label);
+            }
+        }
+    }
+    let mut phis = vec![];
+    for index in 0..procedure.blocks[header].phis.len() {
+        let (entering, mut args): (Vec<_>, Vec<_>) = procedure.blocks[header].phis[index].args.iter().cloned()
+            .partition(|(from, _)| outside.contains(from));
+        let value = match &entering[..] {
+            [(_, value)] => value.clone(),
+            _ => {
+                let result = procedure.fresh_temp();
+                phis.push(Phi { result: result.clone(), args: entering });
+                result
+            }
+        };
+        args.push((label, value));
+        procedure.blocks[header].phis[index].args = args;
+    }
+    procedure.blocks.insert(header, Block { label, phis, quads: vec![] });
+    // a block of the loop falling through to the header keeps going to it
+    let before = &procedure.blocks[header - 1];
+    let (line, range) = before.quads.last().map_or((0, 0..0), |quad| (quad.line, quad.range.clone()));
+    let goto = Quad { op: Op::Goto, arg1: Arg::None, arg2: Arg::None, result: Arg::Label(target), line, range };
+    if found.contains(header - 1) {
+        match before.quads.last().map(|quad| quad.op) {
+            Some(Op::If | Op::IfFalse) => {
+                let label = procedure.fresh_label();
+                procedure.blocks.insert(header, Block { label, phis: vec![], quads: vec![goto] });
+            },
+            Some(op) if op.is_terminator() => {},
+            _ => procedure.blocks[header - 1].quads.push(goto)
+        }
+    }
+}
+
+/// Whether a quadruple may be computed before it would be: it has no
+/// effect, cannot stop the program and does not read memory
+fn hoistable(quad: &Quad) -> bool {
+    let reads = quad.op == Op::Load
+        || quad.op != Op::Address && quad.uses().iter().any(|field| matches!(field, Arg::Var(..)));
+    matches!(quad.def(), Some(Arg::Temp(_))) && !critical(quad) && !reads
+}
+
+/// Loop-invariant code motion: what a loop computes from values assigned
+/// outside it is computed once in its preheader, inner loops first so that
+/// it may then leave the loops around them
+pub fn invariants(procedure: &mut Procedure) -> bool {
+    let (graph, _, found) = analyze(procedure);
+    let mut changed = false;
+    for found in &found {
+        let preheader = match preheader(&graph, found) {
+            Some(preheader) => preheader,
+            None => continue
+        };
+        let mut varying = assigned(procedure, found);
+        let mut moved = true;
+        while moved {
+            moved = false;
+            for &block in &found.blocks {
+                let mut index = 0;
+                while index < procedure.blocks[block].quads.len() {
+                    let quad = &procedure.blocks[block].quads[index];
+                    let invariant = quad.uses().iter().all(|field| !matches!(field, Arg::Temp(temp) if varying.contains(temp)));
+                    if !(invariant && hoistable(quad)) {
+                        index += 1;
+                        continue;
+                    }
+                    let quad = procedure.blocks[block].quads.remove(index);
+                    if let Arg::Temp(temp) = quad.result {
+                        varying.remove(&temp);
+                    }
+                    append(&mut procedure.blocks[preheader], quad);
+                    moved = true;
+                    changed = true;
+                }
+            }
+        }
+    }
+    changed
+}
+
+/// A value that is `factor` times a basic induction variable of a loop,
+/// plus `offset` and a value `base` the loop does not change
+#[derive(Debug, Clone, PartialEq)]
+struct Induction {
+    basic: usize,
+    factor: i64,
+    offset: i64,
+    base: Option<Arg>
+}
+
+/// A basic induction variable: a φ-function of the header of a loop taking
+/// `start` from the preheader and itself plus `step` from the latch, as
+/// `next`
+#[derive(Debug, Clone, PartialEq)]
+struct Basic {
+    start: Arg,
+    next: usize,
+    step: i64
+}
+
+/// The basic induction variables of a loop with a preheader and one latch
+fn basics(procedure: &Procedure, graph: &Graph, found: &Loop) -> HashMap<usize, Basic> {
+    let mut basics = HashMap::new();
+    let (preheader, latch) = match (preheader(graph, found), &found.latches[..]) {
+        (Some(preheader), [latch]) => (procedure.blocks[preheader].label, procedure.blocks[*latch].label),
+        _ => return basics
+    };
+    let assigned = assigned(procedure, found);
+    for phi in &procedure.blocks[found.header].phis {
+        let variable = match phi.result {
+            Arg::Temp(temp) => temp,
+            _ => continue
+        };
+        let start = phi.args.iter().find(|(from, _)| *from == preheader).map(|(_, arg)| arg.clone());
+        let next = phi.args.iter().find_map(|(from, arg)| match arg {
+            Arg::Temp(next) if *from == latch && assigned.contains(next) => Some(*next),
+            _ => None
+        });
+        let (start, next) = match (start, next) {
+            (Some(start), Some(next)) if phi.args.len() == 2 => (start, next),
+            _ => continue
+        };
+        let step = definition(procedure, next).and_then(|(block, index)| {
+            let quad = &procedure.blocks[block].quads[index];
+            let this = Arg::Temp(variable);
+            match quad.op {
+                Op::Add if quad.arg1 == this => int(&quad.arg2),
+                Op::Add if quad.arg2 == this => int(&quad.arg1),
+                Op::Sub if quad.arg1 == this => int(&quad.arg2).map(i64::wrapping_neg),
+                _ => None
+            }
+        });
+        if let Some(step) = step {
+            basics.insert(variable, Basic { start, next, step });
+        }
+    }
+    basics
+}
+
+/// The induction variable a quadruple computes from another, if it does
+fn derive(quad: &Quad, inductions: &HashMap<usize, Induction>, varying: &HashSet<usize>) -> Option<Induction> {
+    let induction = |arg: &Arg| match arg {
+        Arg::Temp(temp) => inductions.get(temp).cloned(),
+        _ => None
+    };
+    let add = |induction: Induction, other: &Arg| match (int(other), other) {
+        (Some(value), _) => Some(Induction { offset: induction.offset.wrapping_add(value), ..induction }),
+        (None, Arg::Temp(temp)) if !varying.contains(temp) && induction.base.is_none() =>
+            Some(Induction { base: Some(other.clone()), ..induction }),
+        _ => None
+    };
+    let multiply = |induction: Induction, factor: i64| match induction.base {
+        None => Some(Induction {
+            factor: induction.factor.wrapping_mul(factor),
+            offset: induction.offset.wrapping_mul(factor),
+            ..induction
+        }),
+        Some(_) => None
+    };
+    match (quad.op, induction(&quad.arg1), induction(&quad.arg2)) {
+        (Op::Assign, Some(induction), _) => Some(induction),
+        (Op::Add, Some(induction), None) => add(induction, &quad.arg2),
+        (Op::Add, None, Some(induction)) => add(induction, &quad.arg1),
+        (Op::Sub, Some(induction), None) => int(&quad.arg2).map(|value| Induction {
+            offset: induction.offset.wrapping_sub(value),
+            ..induction
+        }),
+        (Op::Mul, Some(induction), None) => multiply(induction, int(&quad.arg2)?),
+        (Op::Mul, None, Some(induction)) => multiply(induction, int(&quad.arg1)?),
+        _ => None
+    }
+}
+
+/// Induction-variable strength reduction: a value a loop computes as a
+/// multiple of a basic induction variable, as the address of an element of
+/// an array indexed by it is, becomes an induction variable of its own,
+/// computed in the preheader and stepped by an addition with the basic one
+pub fn inductions(procedure: &mut Procedure) -> bool {
+    let (graph, _, found) = analyze(procedure);
+    for found in &found {
+        let basics = basics(procedure, &graph, found);
+        if basics.is_empty() {
+            continue;
+        }
+        let varying = assigned(procedure, found);
+        let mut inductions = basics.keys()
+            .map(|basic| (*basic, Induction { basic: *basic, factor: 1, offset: 0, base: None }))
+            .collect::<HashMap<_, _>>();
+        let mut derived = vec![];
+        for block in graph.reverse_postorder().into_iter().filter(|block| found.contains(*block)) {
+            for quad in &procedure.blocks[block].quads {
+                if let (Arg::Temp(result), Some(induction)) = (&quad.result, derive(quad, &inductions, &varying)) {
+                    inductions.insert(*result, induction);
+                    derived.push(*result);
+                }
+            }
+        }
+        // a value only read to compute other induction variables is left to
+        // be dropped once they are reduced
+        let read = |temp: usize| procedure.blocks.iter().any(|block| {
+            block.phis.iter().any(|phi| phi.args.iter().any(|(_, arg)| *arg == Arg::Temp(temp)))
+                || block.quads.iter().any(|quad| quad.uses().contains(&&Arg::Temp(temp))
+                    && !matches!(quad.def(), Some(Arg::Temp(result)) if inductions.contains_key(result)))
+        });
+        let reduced = derived.into_iter()
+            .filter(|temp| {
+                let induction = &inductions[temp];
+                (induction.factor != 1 || induction.base.is_some()) && read(*temp)
+            })
+            .collect::<Vec<_>>();
+        if reduced.is_empty() {
+            continue;
+        }
+        let preheader = preheader(&graph, found).expect("loops with basic induction variables have a preheader");
+        let (entering, latch) = (procedure.blocks[preheader].label, procedure.blocks[found.latches[0]].label);
+        let mut replaced = HashMap::new();
+        for temp in reduced {
+            let Induction { basic, factor, offset, base } = inductions[&temp].clone();
+            let Basic { start, next, step } = basics[&basic].clone();
+            let (line, range) = definition(procedure, temp).map_or((0, 0..0), |(block, index)| {
+                let quad = &procedure.blocks[block].quads[index];
+                (quad.line, quad.range.clone())
+            });
+            let compute = |procedure: &mut Procedure, op: Op, arg1: Arg, arg2: Arg| {
+                let result = procedure.fresh_temp();
+                append(&mut procedure.blocks[preheader], Quad { op, arg1, arg2, result: result.clone(), line, range: range.clone() });
+                result
+            };
+            let mut first = match int(&start) {
+                Some(start) => Arg::Int(start.wrapping_mul(factor).wrapping_add(offset)),
+                None => {
+                    let mut first = start;
+                    if factor != 1 {
+                        first = compute(procedure, Op::Mul, first, Arg::Int(factor));
+                    }
+                    if offset != 0 {
+                        first = compute(procedure, Op::Add, first, Arg::Int(offset));
+                    }
+                    first
+                }
+            };
+            if let Some(base) = base {
+                first = match first {
+                    Arg::Int(0) => base,
+                    first => compute(procedure, Op::Add, base, first)
+                };
+            }
+            let (variable, stepped) = (procedure.fresh_temp(), procedure.fresh_temp());
+            procedure.blocks[found.header].phis.push(Phi {
+                result: variable.clone(),
+                args: vec![(entering, first), (latch, stepped.clone())]
+            });
+            let (block, index) = definition(procedure, next).expect("the next value of a basic induction variable is computed");
+            procedure.blocks[block].quads.insert(index + 1, Quad {
+                op: Op::Add,
+                arg1: variable.clone(),
+                arg2: Arg::Int(factor.wrapping_mul(step)),
+                result: stepped,
+                line,
+                range: range.clone()
+            });
+            replaced.insert(temp, variable);
+        }
+        substitute_all(procedure, &replaced);
+        return true;
+    }
+    false
+}
+
+/// The comparison that holds when the operands of `op` are swapped
+fn swapped(op: Op) -> Op {
+    match op {
+        Op::Lt => Op::Gt,
+        Op::Le => Op::Ge,
+        Op::Gt => Op::Lt,
+        Op::Ge => Op::Le,
+        op => op
+    }
+}
+
+/// The comparison that holds when `op` does not
+fn negated(op: Op) -> Op {
+    match op {
+        Op::Eq => Op::Ne,
+        Op::Ne => Op::Eq,
+        Op::Lt => Op::Ge,
+        Op::Le => Op::Gt,
+        Op::Gt => Op::Le,
+        _ => Op::Lt
+    }
+}
+
+/// The values a basic induction variable stepping by 1 or -1 takes, when a
+/// comparison of it with a constant decides whether the loop goes on every
+/// time it goes back to its header
+fn range(procedure: &Procedure, graph: &Graph, dominators: &Dominators, found: &Loop, variable: usize, basic: &Basic)
+         -> Option<(i64, i64)> {
+    let start = int(&basic.start)?;
+    let latch = found.latches[0];
+    let indices = indices(procedure);
+    found.blocks.iter().find_map(|&block| {
+        let branch = procedure.blocks[block].quads.last()?;
+        let condition = match (&branch.op, &branch.arg1) {
+            (Op::If | Op::IfFalse, Arg::Temp(condition)) => *condition,
+            _ => return None
+        };
+        if !dominators.dominates(block, latch) || graph.successors[block].len() != 2 {
+            return None;
+        }
+        let (at, index) = definition(procedure, condition)?;
+        let comparison = &procedure.blocks[at].quads[index];
+        let this = Arg::Temp(variable);
+        let (op, limit) = match comparison.op {
+            Op::Eq | Op::Ne | Op::Lt | Op::Le | Op::Gt | Op::Ge if comparison.arg1 == this => (comparison.op, int(&comparison.arg2)?),
+            Op::Eq | Op::Ne | Op::Lt | Op::Le | Op::Gt | Op::Ge if comparison.arg2 == this =>
+                (swapped(comparison.op), int(&comparison.arg1)?),
+            _ => return None
+        };
+        let target = indices[&branch.target()?];
+        let jumps_in = found.contains(target);
+        let falls_in = graph.successors[block].iter().any(|successor| *successor != target && found.contains(*successor));
+        let holds = match (jumps_in, falls_in) {
+            (true, false) => branch.op == Op::If,
+            (false, true) => branch.op == Op::IfFalse,
+            _ => return None
+        };
+        let op = if holds { op } else { negated(op) };
+        match (basic.step, op) {
+            (1, Op::Ne) if start <= limit => Some((start, limit)),
+            (1, Op::Lt) => Some((start, start.max(limit))),
+            (1, Op::Le) => Some((start, start.max(limit.checked_add(1)?))),
+            (-1, Op::Ne) if start >= limit => Some((limit, start)),
+            (-1, Op::Gt) => Some((start.min(limit), start)),
+            (-1, Op::Ge) => Some((start.min(limit.checked_sub(1)?), start)),
+            _ => None
+        }
+    })
+}
+
+/// Removes the range checks of values a loop keeps within the range: the
+/// basic induction variables stepping by 1 or -1 from a constant to a
+/// constant limit, and these plus or minus constants
+pub fn ranges(procedure: &mut Procedure) -> bool {
+    let (graph, dominators, found) = analyze(procedure);
+    let mut known = HashMap::new();
+    for found in &found {
+        for (variable, basic) in basics(procedure, &graph, found) {
+            if let Some(range) = range(procedure, &graph, &dominators, found, variable, &basic) {
+                known.insert(variable, range);
+            }
+        }
+    }
+    if known.is_empty() {
+        return false;
+    }
+    for block in graph.reverse_postorder() {
+        for quad in &procedure.blocks[block].quads {
+            let offset = match (quad.op, &quad.arg1, &quad.arg2) {
+                (Op::Add, Arg::Temp(temp), value) | (Op::Add, value, Arg::Temp(temp)) => int(value).map(|value| (*temp, value)),
+                (Op::Sub, Arg::Temp(temp), value) => int(value).and_then(|value| value.checked_neg()).map(|value| (*temp, value)),
+                _ => None
+            };
+            let shifted = offset.and_then(|(temp, offset)| {
+                let (low, high) = known.get(&temp)?;
+                Some((low.checked_add(offset)?, high.checked_add(offset)?))
+            });
+            if let (Some(range), Arg::Temp(result)) = (shifted, &quad.result) {
+                known.insert(*result, range);
+            }
+        }
+    }
+    let mut changed = false;
+    for block in &mut procedure.blocks {
+        let count = block.quads.len();
+        block.quads.retain(|quad| match (quad.op, &quad.arg1, &quad.arg2) {
+            (Op::Check | Op::Bounds, Arg::Temp(temp), Arg::Type(ty)) => match (known.get(temp), ty.bounds()) {
+                (Some((low, high)), Some(bounds)) => *low < bounds.start as i64 || *high > bounds.end as i64,
+                _ => true
+            },
+            _ => true
+        });
+        changed |= block.quads.len() != count;
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::lower::lower;
+    use super::super::opt::optimize;
+    use super::super::parse::procedure;
+    use crate::emit::tests::check;
+
+    fn assert_pass(pass: Pass, before: &str, after: &str) {
+        let mut optimized = procedure(before);
+        assert!(pass(&mut optimized));
+        assert_eq!(optimized.to_string(), procedure(after).to_string());
+        assert!(!pass(&mut optimized));
+    }
+
+    #[test]
+    fn loops_get_preheaders() {
+        assert_pass(preheaders, "
+            procedure main
+            L1:
+              :=  0  t1
+              if  x  L3
+            L2:
+              :=  1  t2
+            L3:
+              phi  t1@L1,t2@L2,t4@L3  t3
+              +  t3  1  t4
+              <  t4  10  t5
+              if  t5  L3
+            L4:
+              return
+        ", "
+            procedure main
+            L1:
+              :=  0  t1
+              if  x  L5
+            L2:
+              :=  1  t2
+            L5:
+              phi  t1@L1,t2@L2  t6
+            L3:
+              phi  t4@L3,t6@L5  t3
+              +  t3  1  t4
+              <  t4  10  t5
+              if  t5  L3
+            L4:
+              return
+        ");
+    }
+
+    #[test]
+    fn invariants_leave_loops() {
+        assert_pass(invariants, "
+            procedure main
+            L1:
+              :=  0  t1
+              &  a  t2
+            L2:
+              phi  t1@L1,t6@L2  t3
+              *  n  2  t4
+              +  t2  8  t5
+              *  t5  3  t7
+              +  t3  t7  t6
+              div  t6  t5  t8
+              store  t8  t5
+              <  t6  100  t9
+              if  t9  L2
+            L3:
+              return
+        ", "
+            procedure main
+            L1:
+              :=  0  t1
+              &  a  t2
+              +  t2  8  t5
+              *  t5  3  t7
+            L2:
+              phi  t1@L1,t6@L2  t3
+              *  n  2  t4
+              +  t3  t7  t6
+              div  t6  t5  t8
+              store  t8  t5
+              <  t6  100  t9
+              if  t9  L2
+            L3:
+              return
+        ");
+    }
+
+    #[test]
+    fn addresses_are_stepped() {
+        assert_pass(inductions, "
+            procedure main
+            L1:
+              &  a  t1
+            L2:
+              phi  1@L1,t6@L2  t2
+              -  t2  1  t3
+              *  t3  8  t4
+              +  t1  t4  t5
+              store  t2  t5
+              +  t2  1  t6
+              <>  t2  10  t7
+              if  t7  L2
+            L3:
+              return
+        ", "
+            procedure main
+            L1:
+              &  a  t1
+            L2:
+              phi  1@L1,t6@L2  t2
+              phi  t1@L1,t9@L2  t8
+              -  t2  1  t3
+              *  t3  8  t4
+              +  t1  t4  t5
+              store  t2  t8
+              +  t2  1  t6
+              +  t8  8  t9
+              <>  t2  10  t7
+              if  t7  L2
+            L3:
+              return
+        ");
+    }
+
+    #[test]
+    fn checks_of_counters_in_range_are_removed() {
+        assert_pass(ranges, "
+            procedure main
+            L1:
+              :=  0  t1
+            L2:
+              phi  10@L1,t4@L3  t2
+              bounds  t2  Integer[1..10]
+              +  t2  1  t5
+              check  t5  Integer[1..10]
+              -  t2  1  t6
+              check  t6  Integer[0..10]
+              =  t2  1  t3
+              if  t3  L4
+            L3:
+              -  t2  1  t4
+              goto  L2
+            L4:
+              bounds  t2  Integer[1..1]
+              return
+        ", "
+            procedure main
+            L1:
+              :=  0  t1
+            L2:
+              phi  10@L1,t4@L3  t2
+              +  t2  1  t5
+              check  t5  Integer[1..10]
+              -  t2  1  t6
+              =  t2  1  t3
+              if  t3  L4
+            L3:
+              -  t2  1  t4
+              goto  L2
+            L4:
+              bounds  t2  Integer[1..1]
+              return
+        ");
+    }
+
+    #[test]
+    fn for_loops_over_arrays_step_their_addresses() {
+        let module = optimize(lower(&check("
+            program sums;
+            var a: array [1..10] of integer;
+                i, s: integer;
+            begin
+              s := 0;
+              for i := 1 to 10 do
+                s := s + a[i];
+              writeln(s)
+            end.
+        ")), 2).0;
+        // the address of the element is stepped and the index left unchecked
+        assert_eq!(module.to_string(), "\
+program sums
+
+procedure main
+      op      arg1           arg2    result
+L1:
+ (1)  &       a                      t1
+ (2)  :=      0                      t2
+ (3)  :=      1                      t3
+ (4)  :=      t1                     t4
+L2:
+ (5)  load    t4                     t5
+ (6)  +       t2             t5      t6
+ (7)  =       t3             10      t7
+ (8)  if      t7                     L4
+L3:
+ (9)  +       t3             1       t8
+(10)  +       t4             8       t9
+(11)  :=      t6                     t2
+(12)  :=      t8                     t3
+(13)  :=      t9                     t4
+(14)  goto                           L2
+L4:
+(15)  param   t6
+(16)  call    write_integer  1
+(17)  call    writeln        0
+(18)  return
+");
+    }
+}
@@ -0,0 +1,894 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use crate::ast::expression::{ExBox, Expression, Node};
+use crate::ast::expression::operators::{Call, CompOp, Format, LogicOp, ProdOp, SumOp};
+use crate::ast::expression::variables::VarRef;
+use crate::ast::program::{Checks, Program, ScopeId, Symbol};
+use crate::ast::standard::Builtin;
+use crate::ast::statement::{Direction, Statement, StatementKind};
+use crate::ast::subprogram::Subprogram;
+use crate::ast::types::Type;
+use crate::emit::{cardinality, is_block, limits, references, size, source_line, subprograms, target_type, SET};
+use super::{Arg, Block, Module, Op, Procedure, Quad};
+
+/// Lowers a checked program to quadruples
+pub fn lower(program: &Program) -> Module {
+    let references = references(program);
+    let mut lowering = Lowering {
+        program,
+        references,
+        blocks: vec![],
+        open: false,
+        temps: 0,
+        slots: vec![],
+        labels: 0,
+        numbered: 0,
+        scope: Program::GLOBAL,
+        range: 0..0,
+        checks: program.checks
+    };
+    let mut procedures = subprograms(program).into_iter()
+        .map(|subprogram| lowering.subprogram(subprogram))
+        .collect::<Vec<_>>();
+    procedures.push(lowering.main());
+    Module { name: program.name.clone(), procedures }
+}
+
+/// The name of the procedure of a subprogram
+pub fn function_name(subprogram: &Subprogram) -> String {
+    format!("{}.{}", subprogram.name, subprogram.scope)
+}
+
+/// Where a variable is: a variable of a scope the quadruples name, or an
+/// address computed into a field
+enum Place {
+    Named(Arg),
+    At(Arg)
+}
+
+struct Lowering<'a> {
+    program: &'a Program,
+    /// The parameters passed by reference, by scope
+    references: HashSet<(ScopeId, String)>,
+    /// The blocks of the procedure being lowered, the last taking the
+    /// quadruples unless it has ended with a jump
+    blocks: Vec<Block>,
+    open: bool,
+    temps: usize,
+    slots: Vec<i32>,
+    /// The labels handed out while lowering
+    labels: usize,
+    /// The labels of the blocks of the module so far, which are numbered
+    /// in order once their procedure is lowered
+    numbered: usize,
+    scope: ScopeId,
+    /// The source of the statement or the expression being lowered
+    range: Range<usize>,
+    /// The checks in effect at the expression being lowered
+    checks: Checks
+}
+
+impl<'a> Lowering<'a> {
+    fn label(&mut self) -> usize {
+        self.labels += 1;
+        self.labels
+    }
+
+    fn temp(&mut self) -> Arg {
+        self.temps += 1;
+        Arg::Temp(self.temps)
+    }
+
+    /// Memory for a temporary block of `size` bytes
+    fn slot(&mut self, size: i32) -> Arg {
+        self.slots.push(size);
+        Arg::Slot(self.slots.len())
+    }
+
+    /// Starts a block at `label`
+    fn place(&mut self, label: usize) {
+        self.blocks.push(Block { label, phis: vec![], quads: vec![] });
+        self.open = true;
+    }
+
+    fn emit(&mut self, op: Op, arg1: Arg, arg2: Arg, result: Arg) {
+        if !self.open {
+            // what follows a jump is only reached through a label
+            let label = self.label();
+            self.place(label);
+        }
+        let (line, range) = (source_line(self.program, &self.range), self.range.clone());
+        self.blocks.last_mut().expect("a block is open").quads.push(Quad { op, arg1, arg2, result, line, range });
+        self.open = !op.is_terminator() || matches!(op, Op::If | Op::IfFalse);
+        if matches!(op, Op::If | Op::IfFalse) {
+            let label = self.label();
+            self.place(label);
+        }
+    }
+
+    /// A quadruple whose result goes to a new temporary
+    fn compute(&mut self, op: Op, arg1: Arg, arg2: Arg) -> Arg {
+        let result = self.temp();
+        self.emit(op, arg1, arg2, result.clone());
+        result
+    }
+
+    fn jump(&mut self, op: Op, condition: Arg, label: usize) {
+        self.emit(op, condition, Arg::None, Arg::Label(label));
+    }
+
+    /// Calls a routine of the runtime with `args`, giving its result if it
+    /// has one
+    fn runtime(&mut self, routine: &str, args: Vec<Arg>, result: bool) -> Arg {
+        let count = args.len() as i64;
+        for arg in args {
+            self.emit(Op::Param, arg, Arg::None, Arg::None);
+        }
+        let result = if result { self.temp() } else { Arg::None };
+        self.emit(Op::Call, Arg::Name(routine.to_string()), Arg::Int(count), result.clone());
+        result
+    }
+
+    fn begin(&mut self, scope: ScopeId) {
+        self.scope = scope;
+        self.blocks.clear();
+        self.temps = 0;
+        self.slots.clear();
+        let entry = self.label();
+        self.place(entry);
+    }
+
+    /// The procedure lowered since `begin`, without the blocks nothing is
+    /// in, which go on to the next, and with its labels in order
+    fn finish(&mut self, name: String, params: Vec<String>) -> Procedure {
+        let mut blocks = std::mem::take(&mut self.blocks);
+        let mut index = 0;
+        while index + 1 < blocks.len() {
+            if blocks[index].quads.is_empty() {
+                let (from, to) = (blocks[index].label, blocks[index + 1].label);
+                for quad in blocks.iter_mut().flat_map(|block| block.quads.iter_mut()) {
+                    if quad.result == Arg::Label(from) {
+                        quad.result = Arg::Label(to);
+                    }
+                }
+                blocks.remove(index);
+            } else {
+                index += 1;
+            }
+        }
+        let mut numbers = HashMap::new();
+        for block in &mut blocks {
+            self.numbered += 1;
+            numbers.insert(block.label, self.numbered);
+            block.label = self.numbered;
+        }
+        for quad in blocks.iter_mut().flat_map(|block| block.quads.iter_mut()) {
+            if let Arg::Label(label) = &mut quad.result {
+                *label = numbers[label];
+            }
+        }
+        Procedure {
+            name,
+            scope: self.scope,
+            parent: self.program.scopes[self.scope].parent,
+            params,
+            temps: self.temps,
+            slots: std::mem::take(&mut self.slots),
+            blocks
+        }
+    }
+
+    fn subprogram(&mut self, subprogram: &Subprogram) -> Procedure {
+        self.begin(subprogram.scope);
+        self.statements(&subprogram.body);
+        let result = match &subprogram.result {
+            Some(ty) => {
+                let variable = Arg::Var(subprogram.scope, subprogram.name.clone());
+                if is_block(ty) { self.compute(Op::Address, variable, Arg::None) } else { variable }
+            },
+            None => Arg::None
+        };
+        self.emit(Op::Return, result, Arg::None, Arg::None);
+        self.finish(function_name(subprogram), subprogram.params.iter().map(|param| param.name.clone()).collect())
+    }
+
+    fn main(&mut self) -> Procedure {
+        self.begin(Program::GLOBAL);
+        self.statements(&self.program.body);
+        self.emit(Op::Return, Arg::None, Arg::None, Arg::None);
+        self.finish("main".to_string(), vec![])
+    }
+
+    /// Whether evaluating an expression may call a subprogram of the
+    /// program, which may change any variable
+    fn calls(&self, expr: &ExBox) -> bool {
+        let program = self.program;
+        match expr.node() {
+            Node::Variable(variable) => self.changes(variable)
+                || matches!(program.lookup(variable.scope(), variable.name()), Some((_, Symbol::Subprogram(_)))),
+            Node::Call(call) => call.args.iter().any(|arg| self.calls(arg))
+                || matches!(program.lookup(call.scope, &call.name), Some((_, Symbol::Subprogram(_)))),
+            Node::Set(members) => members.iter().any(|member| self.calls(member)),
+            Node::In(op) => self.calls(&op.sample) || self.calls(&op.set),
+            Node::Comparison(op) => self.calls(&op.left) || self.calls(&op.right),
+            Node::Sum(op) => self.calls(&op.left) || self.calls(&op.right),
+            Node::Product(op) => self.calls(&op.left) || self.calls(&op.right),
+            Node::Logic(op) => self.calls(&op.left) || self.calls(&op.right),
+            Node::Signed(op) => self.calls(&op.operand),
+            Node::Not(op) => self.calls(&op.0),
+            _ => false
+        }
+    }
+
+    /// Whether the indices of a variable may call a subprogram
+    fn changes(&self, variable: &VarRef) -> bool {
+        match variable {
+            VarRef::Immediate(..) => false,
+            VarRef::Field(record, _) => self.changes(record),
+            VarRef::Deref(pointer) => self.changes(pointer),
+            VarRef::Index(array, indices) => self.changes(array) || indices.iter().any(|index| self.calls(index))
+        }
+    }
+
+    /// Copies a variable read as an operand to a temporary if what is
+    /// evaluated before the operand is used may change it
+    fn settle(&mut self, value: Arg, later: &[&ExBox]) -> Arg {
+        if matches!(value, Arg::Var(..)) && later.iter().any(|expr| self.calls(expr)) {
+            self.compute(Op::Assign, value, Arg::None)
+        } else {
+            value
+        }
+    }
+
+    fn place_of(&mut self, variable: &VarRef) -> Place {
+        let program = self.program;
+        match variable {
+            VarRef::Immediate(name, scope) => match program.lookup(*scope, name) {
+                Some((declared, Symbol::Variable(ty))) => {
+                    let variable = Arg::Var(declared, name.clone());
+                    if self.references.contains(&(declared, name.clone())) {
+                        Place::At(variable)
+                    } else if is_block(ty) {
+                        Place::At(self.compute(Op::Address, variable, Arg::None))
+                    } else {
+                        Place::Named(variable)
+                    }
+                },
+                Some((_, Symbol::Subprogram(subprogram))) => {
+                    let variable = Arg::Var(subprogram.scope, subprogram.name.clone());
+                    match &subprogram.result {
+                        Some(ty) if is_block(ty) => Place::At(self.compute(Op::Address, variable, Arg::None)),
+                        _ => Place::Named(variable)
+                    }
+                },
+                _ => unreachable!("'{}' is checked to be a variable", name)
+            },
+            VarRef::Field(record, field) => {
+                let base = self.address(record);
+                let ty = record.get_type(program);
+                let fields = ty.fields();
+                let index = fields.iter().position(|(name, _)| *name == field)
+                    .expect("fields are checked to be in their record");
+                let offset = fields[..index].iter().map(|(_, ty)| size(ty)).sum::<i32>();
+                if offset == 0 {
+                    Place::At(base)
+                } else {
+                    Place::At(self.compute(Op::Add, base, Arg::Int(offset as i64)))
+                }
+            },
+            VarRef::Index(array, indices) => {
+                let mut base = self.address(array);
+                let mut ty = array.get_type(program);
+                for index in indices {
+                    let (dim, element) = ty.dimension().expect("indexed values are checked to be arrays");
+                    let (low, high) = limits(&dim);
+                    base = self.element(base, index, low, high, &element);
+                    ty = element;
+                }
+                Place::At(base)
+            },
+            VarRef::Deref(pointer) => {
+                let pointer = self.value(pointer, &Type::Pointer(Box::new(Type::Invalid)));
+                Place::At(self.compute(Op::Deref, pointer, Arg::None))
+            }
+        }
+    }
+
+    /// The address of the element at `index` of the array at `base`, the
+    /// index being checked to be from `low` to `high`
+    fn element(&mut self, base: Arg, index: &ExBox, low: i64, high: i64, element: &Type) -> Arg {
+        let value = self.expr(index);
+        if self.program.checks_at(index.range.start).range {
+            let outer = std::mem::replace(&mut self.range, index.range.clone());
+            let bounds = Type::Range(Box::new(Type::Integer), low as isize..high as isize);
+            self.emit(Op::Bounds, value.clone(), Arg::Type(bounds), Arg::None);
+            self.range = outer;
+        }
+        let offset = if low == 0 { value } else { self.compute(Op::Sub, value, Arg::Int(low)) };
+        let offset = self.compute(Op::Mul, offset, Arg::Int(size(element) as i64));
+        self.compute(Op::Add, base, offset)
+    }
+
+    /// The address of a variable
+    fn address(&mut self, variable: &VarRef) -> Arg {
+        match self.place_of(variable) {
+            Place::Named(variable) => self.compute(Op::Address, variable, Arg::None),
+            Place::At(address) => address
+        }
+    }
+
+    /// The value of a variable of type `ty`, or its address for a block
+    fn value(&mut self, variable: &VarRef, ty: &Type) -> Arg {
+        match self.place_of(variable) {
+            Place::Named(variable) => variable,
+            Place::At(address) if is_block(ty) => address,
+            Place::At(address) => self.compute(Op::Load, address, Arg::None)
+        }
+    }
+
+    /// Stores a value of type `ty`, a block being copied from its address
+    fn store(&mut self, place: Place, value: Arg, ty: &Type) {
+        match place {
+            Place::Named(variable) => self.emit(Op::Assign, value, Arg::None, variable),
+            Place::At(address) if is_block(ty) => {
+                self.runtime("copy", vec![address, value, Arg::Int(size(ty) as i64)], false);
+            },
+            Place::At(address) => self.emit(Op::Store, value, Arg::None, address)
+        }
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    /// Jumps to `label` if a boolean expression is false, evaluating `and`
+    /// and `or` no further than what decides them
+    fn unless(&mut self, condition: &ExBox, label: usize) {
+        match condition.node() {
+            Node::Logic(op) if op.op == LogicOp::And => {
+                self.unless(&op.left, label);
+                self.unless(&op.right, label);
+            },
+            Node::Logic(op) => {
+                let holds = self.label();
+                self.when(&op.left, holds);
+                self.unless(&op.right, label);
+                self.place(holds);
+            },
+            Node::Not(op) => self.when(&op.0, label),
+            _ => {
+                let value = self.expr(condition);
+                self.jump(Op::IfFalse, value, label);
+            }
+        }
+    }
+
+    /// Jumps to `label` if a boolean expression is true
+    fn when(&mut self, condition: &ExBox, label: usize) {
+        match condition.node() {
+            Node::Logic(op) if op.op == LogicOp::Or => {
+                self.when(&op.left, label);
+                self.when(&op.right, label);
+            },
+            Node::Logic(op) => {
+                let fails = self.label();
+                self.unless(&op.left, fails);
+                self.when(&op.right, label);
+                self.place(fails);
+            },
+            Node::Not(op) => self.unless(&op.0, label),
+            _ => {
+                let value = self.expr(condition);
+                self.jump(Op::If, value, label);
+            }
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        self.range = statement.range.clone();
+        match &statement.kind {
+            StatementKind::Empty => {},
+            StatementKind::Assignment { target, target_range, value: expr } => {
+                let ty = target_type(self.program, target);
+                let value = self.convert(expr, &ty);
+                if self.program.checks_at(expr.range.start).range && ty.narrowing(&expr.get_type(self.program)).is_some() {
+                    self.range = expr.range.clone();
+                    self.check(value.clone(), ty.clone());
+                }
+                // what goes wrong in following the target points at it
+                self.range = target_range.clone();
+                let place = self.place_of(target);
+                self.store(place, value, &ty);
+            },
+            StatementKind::Call(call) => match self.program.lookup(call.scope, &call.name) {
+                Some((_, Symbol::Subprogram(subprogram))) => {
+                    self.invoke(subprogram, &call.args);
+                },
+                _ => self.procedure(Builtin::lookup(&call.name).expect("calls are checked"), &call.args, &call.formats)
+            },
+            StatementKind::Compound(body) => self.statements(body),
+            StatementKind::If { condition, then, otherwise } => {
+                let (otherwise_label, end) = (self.label(), self.label());
+                self.unless(condition, otherwise_label);
+                self.statement(then);
+                if let Some(otherwise) = otherwise {
+                    self.emit(Op::Goto, Arg::None, Arg::None, Arg::Label(end));
+                    self.place(otherwise_label);
+                    self.statement(otherwise);
+                    self.place(end);
+                } else {
+                    self.place(otherwise_label);
+                }
+            },
+            StatementKind::While { condition, body } => {
+                let (repeat, end) = (self.label(), self.label());
+                self.place(repeat);
+                self.unless(condition, end);
+                self.statement(body);
+                self.emit(Op::Goto, Arg::None, Arg::None, Arg::Label(repeat));
+                self.place(end);
+            },
+            StatementKind::Repeat { body, condition } => {
+                let repeat = self.label();
+                self.place(repeat);
+                self.statements(body);
+                self.range = statement.range.clone();
+                self.unless(condition, repeat);
+            },
+            StatementKind::For { variable, from, direction, to, body } => {
+                // the variable is never stepped past the limit, so that it
+                // cannot overflow its type
+                let (repeat, end) = (self.label(), self.label());
+                let first = self.expr(from);
+                let first = self.settle(first, &[to]);
+                let limit = match self.expr(to) {
+                    // the body may change the variables the limit is read from
+                    limit @ Arg::Var(..) => self.compute(Op::Assign, limit, Arg::None),
+                    limit => limit
+                };
+                let passed = match direction {
+                    Direction::To => self.compute(Op::Lt, limit.clone(), first.clone()),
+                    Direction::Downto => self.compute(Op::Lt, first.clone(), limit.clone())
+                };
+                self.jump(Op::If, passed, end);
+                let place = self.place_of(variable);
+                self.store(place, first, &Type::Integer);
+                self.place(repeat);
+                self.statement(body);
+                self.range = statement.range.clone();
+                let current = self.value(variable, &Type::Integer);
+                let done = self.compute(Op::Eq, current.clone(), limit);
+                self.jump(Op::If, done, end);
+                let step = match direction {
+                    Direction::To => Op::Add,
+                    Direction::Downto => Op::Sub
+                };
+                let next = self.compute(step, current, Arg::Int(1));
+                let place = self.place_of(variable);
+                self.store(place, next, &Type::Integer);
+                self.emit(Op::Goto, Arg::None, Arg::None, Arg::Label(repeat));
+                self.place(end);
+            },
+            StatementKind::Case { selector, arms } => {
+                // the labels are all tested before the arm they lead to
+                let ty = selector.get_type(self.program);
+                let value = self.expr(selector);
+                let end = self.label();
+                let bodies = arms.iter().map(|_| self.label()).collect::<Vec<_>>();
+                for (arm, body) in arms.iter().zip(&bodies) {
+                    for label in &arm.labels {
+                        let label = label.as_number(self.program).expect("labels are checked to be constant");
+                        let matches = self.compute(Op::Eq, value.clone(), Arg::Int(label));
+                        self.jump(Op::If, matches, *body);
+                    }
+                }
+                self.range = selector.range.clone();
+                self.emit(Op::NoCase, value, Arg::Type(ty.host()), Arg::None);
+                for (arm, body) in arms.iter().zip(bodies) {
+                    self.place(body);
+                    self.statement(&arm.body);
+                    self.emit(Op::Goto, Arg::None, Arg::None, Arg::Label(end));
+                }
+                self.place(end);
+            }
+        }
+    }
+
+    /// An expression converted to the type of `ty`, which only widens
+    /// integers to reals
+    fn convert(&mut self, expr: &ExBox, ty: &Type) -> Arg {
+        let value = self.expr(expr);
+        if ty.host() == Type::Real && expr.get_type(self.program).host() != Type::Real {
+            self.compute(Op::Float, value, Arg::None)
+        } else {
+            value
+        }
+    }
+
+    /// Two operands converted to the type of `ty`
+    fn operands(&mut self, left: &ExBox, right: &ExBox, ty: &Type) -> (Arg, Arg) {
+        let left_value = self.convert(left, ty);
+        let left_value = self.settle(left_value, &[right]);
+        (left_value, self.convert(right, ty))
+    }
+
+    /// Checks that an integer is of the type `ty`
+    fn check(&mut self, value: Arg, ty: Type) {
+        self.emit(Op::Check, value, Arg::Type(ty), Arg::None);
+    }
+
+    /// An integer operation, left to the runtime where overflow is checked
+    fn arithmetic(&mut self, op: Op, left: Arg, right: Arg) -> Arg {
+        let routine = match op {
+            Op::Add => "add_checked",
+            Op::Sub => "sub_checked",
+            Op::Mul => "mul_checked",
+            Op::Div => "div_checked",
+            Op::Neg => "neg_checked",
+            Op::Abs => "abs_checked",
+            _ => return self.compute(op, left, right)
+        };
+        if !self.checks.overflow {
+            return self.compute(op, left, right);
+        }
+        let args = if op.is_binary() { vec![left, right] } else { vec![left] };
+        self.runtime(routine, args, true)
+    }
+
+    /// The value of an expression, the address of a block
+    fn expr(&mut self, expr: &ExBox) -> Arg {
+        let outer = (std::mem::replace(&mut self.range, expr.range.clone()), self.checks);
+        self.checks = self.program.checks_at(expr.range.start);
+        let value = self.evaluate(expr);
+        (self.range, self.checks) = outer;
+        value
+    }
+
+    fn evaluate(&mut self, expr: &ExBox) -> Arg {
+        let program = self.program;
+        match expr.node() {
+            Node::Integer(value) => Arg::Int(value),
+            Node::Real(value) => Arg::Real(value),
+            Node::Char(value) => Arg::Int(value as i64),
+            Node::Str(text) => {
+                let string = self.slot(8 * text.chars().count() as i32);
+                let string = self.compute(Op::Address, string, Arg::None);
+                for (index, c) in text.chars().enumerate() {
+                    let at = self.compute(Op::Add, string.clone(), Arg::Int(8 * index as i64));
+                    self.emit(Op::Store, Arg::Int(c as i64), Arg::None, at);
+                }
+                string
+            },
+            Node::Boolean(value) => Arg::Int(value as i64),
+            Node::Nil => Arg::Int(0),
+            Node::Enumerator(literal) => Arg::Int(literal.ordinal),
+            Node::Variable(variable) => match variable {
+                VarRef::Immediate(name, scope) => match program.lookup(*scope, name) {
+                    Some((_, Symbol::Constant(constant))) => self.expr(constant),
+                    Some((_, Symbol::Subprogram(subprogram))) => self.invoke(subprogram, &[]),
+                    Some(_) => self.value(variable, &expr.get_type(program)),
+                    None => self.function(Builtin::lookup(name).expect("names are checked"), &[])
+                },
+                _ => self.value(variable, &expr.get_type(program))
+            },
+            Node::Call(call) => self.call(call),
+            Node::Set(members) => {
+                let set = self.slot(SET);
+                let set = self.compute(Op::Address, set, Arg::None);
+                self.runtime("zero", vec![set.clone(), Arg::Int(SET as i64)], false);
+                for member in members {
+                    let value = self.expr(member);
+                    let outer = std::mem::replace(&mut self.range, member.range.clone());
+                    self.runtime("set_add", vec![set.clone(), value], false);
+                    self.range = outer;
+                }
+                set
+            },
+            Node::In(op) => {
+                let sample = self.expr(&op.sample);
+                let sample = self.settle(sample, &[&op.set]);
+                let set = self.expr(&op.set);
+                self.runtime("set_has", vec![set, sample], true)
+            },
+            Node::Comparison(op) => {
+                let ty = op.left.get_type(program).host();
+                let real = ty == Type::Real || op.right.get_type(program).host() == Type::Real;
+                let (left, right) = self.operands(&op.left, &op.right, if real { &Type::Real } else { &ty });
+                match (&ty, &op.op) {
+                    (ty, comparison) if ty.is_string() => {
+                        let length = cardinality(&ty.dimension().unwrap().0) as i64;
+                        let order = self.runtime("compare", vec![left, right, Arg::Int(length)], true);
+                        self.compute(int_comparison(comparison), order, Arg::Int(0))
+                    },
+                    (Type::SetOf(_), CompOp::Eq) => self.runtime("set_eq", vec![left, right], true),
+                    (Type::SetOf(_), CompOp::Neq) => {
+                        let equal = self.runtime("set_eq", vec![left, right], true);
+                        self.compute(Op::Not, equal, Arg::None)
+                    },
+                    // a set is included in another if it has no member the other lacks
+                    (Type::SetOf(_), CompOp::Bge | CompOp::Bg) => self.runtime("set_le", vec![right, left], true),
+                    (Type::SetOf(_), _) => self.runtime("set_le", vec![left, right], true),
+                    (_, comparison) if real => {
+                        let op = match comparison {
+                            CompOp::Eq => Op::FEq,
+                            CompOp::Neq => Op::FNe,
+                            CompOp::Lt => Op::FLt,
+                            CompOp::Lte => Op::FLe,
+                            CompOp::Bg => Op::FGt,
+                            CompOp::Bge => Op::FGe
+                        };
+                        self.compute(op, left, right)
+                    },
+                    (_, comparison) => self.compute(int_comparison(comparison), left, right)
+                }
+            },
+            Node::Sum(op) => {
+                let ty = expr.get_type(program);
+                let operation = match (ty.host(), &op.op) {
+                    (Type::SetOf(_), SumOp::Add) => return self.combine(&op.left, &op.right, 0),
+                    (Type::SetOf(_), SumOp::Sub) => return self.combine(&op.left, &op.right, 1),
+                    (Type::Real, SumOp::Add) => Op::FAdd,
+                    (Type::Real, SumOp::Sub) => Op::FSub,
+                    (_, SumOp::Add) => Op::Add,
+                    (_, SumOp::Sub) => Op::Sub
+                };
+                let (left, right) = self.operands(&op.left, &op.right, &ty);
+                self.arithmetic(operation, left, right)
+            },
+            Node::Signed(op) => {
+                let value = self.expr(&op.operand);
+                match (&op.op, expr.get_type(program).host()) {
+                    (SumOp::Add, _) => value,
+                    (SumOp::Sub, Type::Real) => self.compute(Op::FNeg, value, Arg::None),
+                    (SumOp::Sub, _) => self.arithmetic(Op::Neg, value, Arg::None)
+                }
+            },
+            Node::Product(op) => {
+                let ty = match op.op {
+                    ProdOp::RDiv => Type::Real,
+                    _ => expr.get_type(program).host()
+                };
+                let operation = match (&ty, &op.op) {
+                    (Type::SetOf(_), _) => return self.combine(&op.left, &op.right, 2),
+                    (_, ProdOp::RDiv) => Op::FDiv,
+                    (Type::Real, _) => Op::FMul,
+                    (_, ProdOp::Mul) => Op::Mul,
+                    (_, ProdOp::Div) => Op::Div,
+                    (_, ProdOp::Mod) => Op::Mod
+                };
+                let (left, right) = self.operands(&op.left, &op.right, &ty);
+                self.arithmetic(operation, left, right)
+            },
+            Node::Not(op) => {
+                let value = self.expr(&op.0);
+                self.compute(Op::Not, value, Arg::None)
+            },
+            Node::Logic(op) => {
+                // the right operand is only evaluated if it decides the result
+                let (result, end) = (self.temp(), self.label());
+                let left = self.expr(&op.left);
+                self.emit(Op::Assign, left, Arg::None, result.clone());
+                let decided = if op.op == LogicOp::And { Op::IfFalse } else { Op::If };
+                self.jump(decided, result.clone(), end);
+                let right = self.expr(&op.right);
+                self.emit(Op::Assign, right, Arg::None, result.clone());
+                self.place(end);
+                result
+            },
+            Node::Invalid => unreachable!("invalid expressions are not compiled")
+        }
+    }
+
+    /// The address of the union (0), difference (1) or intersection (2) of
+    /// two sets
+    fn combine(&mut self, left: &ExBox, right: &ExBox, operation: i64) -> Arg {
+        let (left, right) = (self.expr(left), self.expr(right));
+        let set = self.slot(SET);
+        let set = self.compute(Op::Address, set, Arg::None);
+        self.runtime("set_combine", vec![set.clone(), left, right, Arg::Int(operation)], false);
+        set
+    }
+
+    fn call(&mut self, call: &Call) -> Arg {
+        match self.program.lookup(call.scope, &call.name) {
+            Some((_, Symbol::Subprogram(subprogram))) => self.invoke(subprogram, &call.args),
+            _ => self.function(Builtin::lookup(&call.name).expect("calls are checked"), &call.args)
+        }
+    }
+
+    /// Calls a subprogram, giving its result, a block being copied out of
+    /// the frame of the call
+    fn invoke(&mut self, subprogram: &Subprogram, args: &[ExBox]) -> Arg {
+        let mut values = vec![];
+        for (index, (param, arg)) in subprogram.params.iter().zip(args).enumerate() {
+            let value = if param.by_ref {
+                self.address(arg.as_variable().expect("var arguments are checked to be variables"))
+            } else {
+                self.convert(arg, &param.r#type)
+            };
+            let later = args[index + 1..].iter().collect::<Vec<_>>();
+            values.push(self.settle(value, &later));
+        }
+        let count = values.len() as i64;
+        for value in values {
+            self.emit(Op::Param, value, Arg::None, Arg::None);
+        }
+        let name = Arg::Name(function_name(subprogram));
+        match &subprogram.result {
+            Some(ty) => {
+                let result = self.compute(Op::Call, name, Arg::Int(count));
+                if is_block(ty) {
+                    let copy = self.slot(size(ty));
+                    let copy = self.compute(Op::Address, copy, Arg::None);
+                    self.runtime("copy", vec![copy.clone(), result, Arg::Int(size(ty) as i64)], false);
+                    copy
+                } else {
+                    result
+                }
+            },
+            None => {
+                self.emit(Op::Call, name, Arg::Int(count), Arg::None);
+                Arg::None
+            }
+        }
+    }
+
+    /// The result of a standard function
+    fn function(&mut self, builtin: Builtin, args: &[ExBox]) -> Arg {
+        let ty = args.first().map(|arg| arg.get_type(self.program).host());
+        let real = ty == Some(Type::Real);
+        let unary = match builtin {
+            Builtin::Abs if real => Some(Op::FAbs),
+            Builtin::Abs => Some(Op::Abs),
+            Builtin::Odd => Some(Op::Odd),
+            Builtin::Trunc => Some(Op::Trunc),
+            Builtin::Round => Some(Op::Round),
+            Builtin::Sqrt => Some(Op::Sqrt),
+            Builtin::Ln => Some(Op::Ln),
+            Builtin::Sin => Some(Op::Sin),
+            Builtin::Cos => Some(Op::Cos),
+            Builtin::Exp => Some(Op::Exp),
+            Builtin::Arctan => Some(Op::Arctan),
+            _ => None
+        };
+        if let Some(op) = unary {
+            let takes_real = !matches!(op, Op::Abs | Op::FAbs | Op::Odd);
+            let value = if takes_real { self.convert(&args[0], &Type::Real) } else { self.expr(&args[0]) };
+            return self.arithmetic(op, value, Arg::None);
+        }
+        match builtin {
+            Builtin::Eof | Builtin::Eoln => self.runtime(builtin.name(), vec![], true),
+            Builtin::Sqr => {
+                let value = self.expr(&args[0]);
+                self.arithmetic(if real { Op::FMul } else { Op::Mul }, value.clone(), value)
+            },
+            Builtin::Ord => self.expr(&args[0]),
+            Builtin::Chr => {
+                let value = self.expr(&args[0]);
+                if self.checks.range {
+                    self.check(value.clone(), Type::Char);
+                }
+                value
+            },
+            Builtin::Succ | Builtin::Pred => {
+                let value = self.expr(&args[0]);
+                let ty = args[0].get_type(self.program);
+                let step = if builtin == Builtin::Succ { Op::Add } else { Op::Sub };
+                let value = self.arithmetic(step, value, Arg::Int(1));
+                if ty.bounds().is_some() && self.checks.range {
+                    self.check(value.clone(), ty);
+                }
+                value
+            },
+            procedure => unreachable!("{} is a procedure", procedure.name())
+        }
+    }
+
+    /// A call to a standard procedure
+    fn procedure(&mut self, builtin: Builtin, args: &[ExBox], formats: &[Option<Format>]) {
+        let program = self.program;
+        let variable = |index: usize| args[index].as_variable().expect("the argument is checked to be a variable");
+        match builtin {
+            Builtin::Read | Builtin::Readln => {
+                for (index, arg) in args.iter().enumerate() {
+                    let ty = arg.get_type(program).host();
+                    let routine = match ty {
+                        Type::Integer => "read_integer",
+                        Type::Real => "read_real",
+                        _ => "read_char"
+                    };
+                    let outer = std::mem::replace(&mut self.range, arg.range.clone());
+                    let value = self.runtime(routine, vec![], true);
+                    let place = self.place_of(variable(index));
+                    self.store(place, value, &ty);
+                    self.range = outer;
+                }
+                if builtin == Builtin::Readln {
+                    self.runtime("readln", vec![], false);
+                }
+            },
+            Builtin::Write | Builtin::Writeln => {
+                for (index, arg) in args.iter().enumerate() {
+                    let ty = arg.get_type(program).host();
+                    let value = self.expr(arg);
+                    if let Some(Some(Format { width, decimals })) = formats.get(index) {
+                        // a field is reported with the value it is the field of
+                        let range = arg.range.start..decimals.as_ref().unwrap_or(width).range.end;
+                        let width = self.expr(width);
+                        let outer = std::mem::replace(&mut self.range, range);
+                        match (ty, decimals) {
+                            (ty, _) if ty.is_string() => {
+                                let length = cardinality(&ty.dimension().unwrap().0) as i64;
+                                self.runtime("write_string_width", vec![value, Arg::Int(length), width], false);
+                            },
+                            (Type::Real, Some(decimals)) => {
+                                let decimals = self.expr(decimals);
+                                self.runtime("write_real_fixed", vec![value, width, decimals], false);
+                            },
+                            (Type::Real, None) => { self.runtime("write_real_width", vec![value, width], false); },
+                            (Type::Char, _) => { self.runtime("write_char_width", vec![value, width], false); },
+                            (Type::Boolean, _) => { self.runtime("write_boolean_width", vec![value, width], false); },
+                            _ => { self.runtime("write_integer_width", vec![value, width], false); }
+                        }
+                        self.range = outer;
+                        continue;
+                    }
+                    match ty {
+                        ty if ty.is_string() => {
+                            let length = cardinality(&ty.dimension().unwrap().0) as i64;
+                            self.runtime("write_string", vec![value, Arg::Int(length)], false);
+                        },
+                        Type::Real => { self.runtime("write_real", vec![value], false); },
+                        Type::Char => { self.runtime("write_char", vec![value], false); },
+                        Type::Boolean => { self.runtime("write_boolean", vec![value], false); },
+                        _ => { self.runtime("write_integer", vec![value], false); }
+                    }
+                }
+                if builtin == Builtin::Writeln {
+                    self.runtime("writeln", vec![], false);
+                }
+            },
+            Builtin::New => {
+                let ty = args[0].get_type(program);
+                let place = self.place_of(variable(0));
+                let pointer = self.runtime("new", vec![Arg::Int(size(&program.pointee(&ty)) as i64)], true);
+                self.store(place, pointer, &ty);
+            },
+            Builtin::Dispose => {
+                let pointer = self.expr(&args[0]);
+                self.runtime("dispose", vec![pointer], false);
+            },
+            Builtin::Pack | Builtin::Unpack => {
+                // pack(a, i, z) and unpack(z, a, i) copy between `a` from `i` on and all of `z`
+                let (array, index, packed) = if builtin == Builtin::Pack { (0, 1, 2) } else { (1, 2, 0) };
+                let array_type = args[array].get_type(program);
+                let (dim, element) = array_type.dimension().expect("pack takes arrays");
+                let packed_type = args[packed].get_type(program);
+                let (packed_dim, _) = packed_type.dimension().expect("pack takes arrays");
+                let (low, high) = limits(&dim);
+                let high = high + 1 - cardinality(&packed_dim) as i64;
+                let base = self.address(variable(array));
+                let from = self.element(base, &args[index], low, high, &element);
+                let to = self.address(variable(packed));
+                let (to, from) = if builtin == Builtin::Pack { (to, from) } else { (from, to) };
+                self.runtime("copy", vec![to, from, Arg::Int(size(&packed_type) as i64)], false);
+            },
+            function => {
+                self.function(function, args);
+            }
+        }
+    }
+}
+
+fn int_comparison(comparison: &CompOp) -> Op {
+    match comparison {
+        CompOp::Eq => Op::Eq,
+        CompOp::Neq => Op::Ne,
+        CompOp::Lt => Op::Lt,
+        CompOp::Lte => Op::Le,
+        CompOp::Bg => Op::Gt,
+        CompOp::Bge => Op::Ge
+    }
+}
@@ -0,0 +1,503 @@
+pub mod lower;
+pub mod cfg;
+pub mod ssa;
+pub mod opt;
+pub mod loops;
+pub mod inline;
+pub mod exec;
+#[cfg(test)]
+mod parse;
+
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use crate::ast::program::ScopeId;
+use crate::ast::types::Type;
+
+/// The operators of the quadruples. Characters, booleans, enumeration
+/// literals and addresses are all integers; the operators ending in a dot
+/// act on reals, and comparisons give 1 or 0.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Op {
+    /// Copies `arg1` to `result`
+    Assign,
+    /// The address of the variable or the memory temporary `arg1`
+    Address,
+    /// The value at the address `arg1`
+    Load,
+    /// Stores `arg1` at the address `result`
+    Store,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Neg,
+    FAdd,
+    FSub,
+    FMul,
+    FDiv,
+    FNeg,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    FEq,
+    FNe,
+    FLt,
+    FLe,
+    FGt,
+    FGe,
+    Not,
+    Odd,
+    Abs,
+    FAbs,
+    /// An integer widened to a real
+    Float,
+    Trunc,
+    Round,
+    Sqrt,
+    Ln,
+    Sin,
+    Cos,
+    Exp,
+    Arctan,
+    /// Stops the program unless `arg1` is a value of the type `arg2`
+    Check,
+    /// Stops the program unless the index `arg1` is within the bounds of
+    /// the type `arg2`
+    Bounds,
+    /// The pointer `arg1`, stopping the program unless it points to a
+    /// variable
+    Deref,
+    /// Stops the program as no case label matches `arg1`, of the type `arg2`
+    NoCase,
+    Goto,
+    If,
+    IfFalse,
+    /// Passes `arg1` to the next call, the parameters of which come right
+    /// before it
+    Param,
+    /// Calls the procedure or the runtime routine `arg1` with `arg2`
+    /// parameters, its result going to `result` if it has one
+    Call,
+    /// Leaves the procedure, with `arg1` as its result if it has one
+    Return
+}
+
+impl Op {
+    pub const ALL: &'static [Op] = &[Op::Assign, Op::Address, Op::Load, Op::Store, Op::Add, Op::Sub, Op::Mul, Op::Div,
+        Op::Mod, Op::Neg, Op::FAdd, Op::FSub, Op::FMul, Op::FDiv, Op::FNeg, Op::Eq, Op::Ne, Op::Lt, Op::Le, Op::Gt,
+        Op::Ge, Op::FEq, Op::FNe, Op::FLt, Op::FLe, Op::FGt, Op::FGe, Op::Not, Op::Odd, Op::Abs, Op::FAbs, Op::Float,
+        Op::Trunc, Op::Round, Op::Sqrt, Op::Ln, Op::Sin, Op::Cos, Op::Exp, Op::Arctan, Op::Check, Op::Bounds,
+        Op::Deref, Op::NoCase, Op::Goto, Op::If, Op::IfFalse, Op::Param, Op::Call, Op::Return];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Op::Assign => ":=",
+            Op::Address => "&",
+            Op::Load => "load",
+            Op::Store => "store",
+            Op::Add => "+",
+            Op::Sub => "-",
+            Op::Mul => "*",
+            Op::Div => "div",
+            Op::Mod => "mod",
+            Op::Neg => "neg",
+            Op::FAdd => "+.",
+            Op::FSub => "-.",
+            Op::FMul => "*.",
+            Op::FDiv => "/",
+            Op::FNeg => "neg.",
+            Op::Eq => "=",
+            Op::Ne => "<>",
+            Op::Lt => "<",
+            Op::Le => "<=",
+            Op::Gt => ">",
+            Op::Ge => ">=",
+            Op::FEq => "=.",
+            Op::FNe => "<>.",
+            Op::FLt => "<.",
+            Op::FLe => "<=.",
+            Op::FGt => ">.",
+            Op::FGe => ">=.",
+            Op::Not => "not",
+            Op::Odd => "odd",
+            Op::Abs => "abs",
+            Op::FAbs => "abs.",
+            Op::Float => "float",
+            Op::Trunc => "trunc",
+            Op::Round => "round",
+            Op::Sqrt => "sqrt",
+            Op::Ln => "ln",
+            Op::Sin => "sin",
+            Op::Cos => "cos",
+            Op::Exp => "exp",
+            Op::Arctan => "arctan",
+            Op::Check => "check",
+            Op::Bounds => "bounds",
+            Op::Deref => "deref",
+            Op::NoCase => "nocase",
+            Op::Goto => "goto",
+            Op::If => "if",
+            Op::IfFalse => "iffalse",
+            Op::Param => "param",
+            Op::Call => "call",
+            Op::Return => "return"
+        }
+    }
+
+    /// Whether the operator takes two operands
+    pub fn is_binary(self) -> bool {
+        matches!(self, Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Mod | Op::FAdd | Op::FSub | Op::FMul
+            | Op::FDiv | Op::Eq | Op::Ne | Op::Lt | Op::Le | Op::Gt | Op::Ge | Op::FEq | Op::FNe | Op::FLt
+            | Op::FLe | Op::FGt | Op::FGe)
+    }
+
+    /// Whether the quadruple ends its basic block
+    pub fn is_terminator(self) -> bool {
+        matches!(self, Op::Goto | Op::If | Op::IfFalse | Op::NoCase | Op::Return)
+    }
+
+    /// Whether the operands of the operator can be swapped
+    pub fn is_commutative(self) -> bool {
+        matches!(self, Op::Add | Op::Mul | Op::Eq | Op::Ne | Op::FAdd | Op::FMul | Op::FEq | Op::FNe)
+    }
+}
+
+impl Display for Op {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A field of a quadruple
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arg {
+    None,
+    /// A temporary, numbered in its procedure
+    Temp(usize),
+    /// A variable of a scope, which is that of the procedure or one it is
+    /// nested in: a declared variable, a parameter or the result of a
+    /// function. A parameter passed by reference holds an address.
+    Var(ScopeId, String),
+    /// Memory in the frame for a temporary array, record or set, which are
+    /// handled through their address
+    Slot(usize),
+    Int(i64),
+    Real(f64),
+    Label(usize),
+    /// A procedure of the program or a routine of the runtime
+    Name(String),
+    Type(Type)
+}
+
+impl Arg {
+    pub fn is_none(&self) -> bool {
+        *self == Arg::None
+    }
+}
+
+/// How a type the checks name is written in a field: its host, with the
+/// bounds of a subrange in brackets
+pub fn type_name(ty: &Type) -> String {
+    match ty {
+        Type::Range(host, range) => format!("{}[{}..{}]", type_name(host), range.start, range.end),
+        Type::Enum(names) => format!("({})", names.join(",")),
+        ty => ty.to_string()
+    }
+}
+
+/// A quadruple: an operator, its operands and where its result goes, or
+/// what it stores to or jumps to. `range` is the source it was lowered
+/// from, which runtime errors point at, and `line` the line it is on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quad {
+    pub op: Op,
+    pub arg1: Arg,
+    pub arg2: Arg,
+    pub result: Arg,
+    pub line: usize,
+    pub range: Range<usize>
+}
+
+impl Quad {
+    /// The label the quadruple may jump to
+    pub fn target(&self) -> Option<usize> {
+        match (self.op, &self.result) {
+            (Op::Goto | Op::If | Op::IfFalse, Arg::Label(label)) => Some(*label),
+            _ => None
+        }
+    }
+
+    /// The temporary or the variable the quadruple assigns
+    pub fn def(&self) -> Option<&Arg> {
+        match self.op {
+            Op::Store | Op::Check | Op::Bounds | Op::NoCase | Op::Goto | Op::If | Op::IfFalse | Op::Param
+            | Op::Return => None,
+            _ if self.result.is_none() => None,
+            _ => Some(&self.result)
+        }
+    }
+
+    /// The fields the quadruple reads, the address a store goes to among them
+    pub fn uses(&self) -> Vec<&Arg> {
+        let mut uses = vec![&self.arg1, &self.arg2];
+        if self.op == Op::Store {
+            uses.push(&self.result);
+        }
+        uses
+    }
+
+    pub fn uses_mut(&mut self) -> Vec<&mut Arg> {
+        let mut uses = vec![&mut self.arg1, &mut self.arg2];
+        if self.op == Op::Store {
+            uses.push(&mut self.result);
+        }
+        uses
+    }
+}
+
+/// A φ-function at the start of a block in SSA form: `result` takes the
+/// value from the predecessor the block is entered from
+#[derive(Debug, Clone, PartialEq)]
+pub struct Phi {
+    pub result: Arg,
+    /// The label of each predecessor and the value from it
+    pub args: Vec<(usize, Arg)>
+}
+
+/// A basic block: quadruples run one after the other from the first, the
+/// last of which may jump. A block that does not end with a jump or a
+/// return goes on to the next. Only in SSA form does a block start with
+/// φ-functions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub label: usize,
+    pub phis: Vec<Phi>,
+    pub quads: Vec<Quad>
+}
+
+/// The quadruples of a subprogram or of the main program
+#[derive(Debug, Clone, PartialEq)]
+pub struct Procedure {
+    /// The name of a subprogram followed by its scope, or `main`
+    pub name: String,
+    pub scope: ScopeId,
+    /// The scope a subprogram is declared in, none for the main program
+    pub parent: Option<ScopeId>,
+    /// The parameters of a subprogram in order, which the `param`s before a
+    /// call to it give
+    pub params: Vec<String>,
+    /// How many temporaries the procedure numbers, from 1
+    pub temps: usize,
+    /// The size in bytes of each memory temporary
+    pub slots: Vec<i32>,
+    pub blocks: Vec<Block>
+}
+
+impl Procedure {
+    /// A label no block of the procedure has
+    pub fn fresh_label(&self) -> usize {
+        self.blocks.iter().map(|block| block.label).max().unwrap_or(0) + 1
+    }
+
+    pub fn fresh_temp(&mut self) -> Arg {
+        self.temps += 1;
+        Arg::Temp(self.temps)
+    }
+
+    /// How a field is written, a variable of another scope being followed
+    /// by that scope, as is one whose name a temporary or a label could have
+    fn field(&self, arg: &Arg) -> String {
+        match arg {
+            Arg::None => String::new(),
+            Arg::Temp(temp) => format!("t{}", temp),
+            Arg::Var(scope, name) => {
+                let generated = name.len() > 1 && name.starts_with(['t', 'm', 'L'])
+                    && name[1..].bytes().all(|byte| byte.is_ascii_digit());
+                if *scope == self.scope && !generated {
+                    name.clone()
+                } else {
+                    format!("{}.{}", name, scope)
+                }
+            },
+            Arg::Slot(slot) => format!("m{}", slot),
+            Arg::Int(value) => value.to_string(),
+            Arg::Real(value) => format!("{:?}", value),
+            Arg::Label(label) => format!("L{}", label),
+            Arg::Name(name) => name.clone(),
+            Arg::Type(ty) => type_name(ty)
+        }
+    }
+}
+
+/// Writes the quadruples as a table, numbered in order and with the label
+/// of each block before it, its φ-functions written as rows first
+impl Display for Procedure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut rows = vec![(None, ["op".to_string(), "arg1".to_string(), "arg2".to_string(), "result".to_string()])];
+        for block in &self.blocks {
+            rows.push((Some(block.label), Default::default()));
+            for phi in &block.phis {
+                let args = phi.args.iter()
+                    .map(|(label, arg)| format!("{}@L{}", self.field(arg), label))
+                    .collect::<Vec<_>>();
+                rows.push((None, ["phi".to_string(), args.join(","), String::new(), self.field(&phi.result)]));
+            }
+            for quad in &block.quads {
+                rows.push((None, [quad.op.to_string(), self.field(&quad.arg1), self.field(&quad.arg2), self.field(&quad.result)]));
+            }
+        }
+        let mut widths = [0; 4];
+        for (_, row) in &rows {
+            for (width, field) in widths.iter_mut().zip(row) {
+                *width = (*width).max(field.len() + 2).max(8);
+            }
+        }
+        let count = rows.len() - self.blocks.len() - 1;
+        let number = format!("({})", count).len() + 2;
+        writeln!(f, "procedure {}", self.name)?;
+        let mut index = 0;
+        for (label, row) in rows {
+            let line = match label {
+                Some(label) => format!("L{}:", label),
+                None => {
+                    let mut line = if index == 0 {
+                        " ".repeat(number)
+                    } else {
+                        format!("{:>width$}  ", format!("({})", index), width = number - 2)
+                    };
+                    for (width, field) in widths.iter().zip(&row) {
+                        line.push_str(&format!("{:width$}", field, width = width));
+                    }
+                    index += 1;
+                    line
+                }
+            };
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// The quadruples of a program, a procedure for each subprogram in the
+/// order of the source and the main program last
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    pub name: String,
+    pub procedures: Vec<Procedure>
+}
+
+impl Display for Module {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "program {}", self.name)?;
+        for procedure in &self.procedures {
+            write!(f, "\n{}", procedure)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::lower::lower;
+    use crate::emit::tests::{check, samples};
+
+    #[test]
+    fn dumps_a_table_for_each_procedure() {
+        let module = lower(&check("
+            program sums;
+            var i, s: integer;
+            procedure add(n: integer);
+            begin
+              s := s + n
+            end;
+            begin
+              s := 0;
+              for i := 1 to 10 do
+                if odd(i) and (i > 2) then add(i);
+              writeln(s)
+            end.
+        "));
+        assert_eq!(module.to_string(), "\
+program sums
+
+procedure add.2
+     op      arg1    arg2    result
+L1:
+(1)  +       s.1     n       t1
+(2)  :=      t1              s.1
+(3)  return
+
+procedure main
+      op       arg1           arg2    result
+L2:
+ (1)  :=       0                      s
+ (2)  <        10             1       t1
+ (3)  if       t1                     L9
+L3:
+ (4)  :=       1                      i
+L4:
+ (5)  odd      i                      t2
+ (6)  iffalse  t2                     L7
+L5:
+ (7)  >        i              2       t3
+ (8)  iffalse  t3                     L7
+L6:
+ (9)  param    i
+(10)  call     add.2          1
+L7:
+(11)  =        i              10      t4
+(12)  if       t4                     L9
+L8:
+(13)  +        i              1       t5
+(14)  :=       t5                     i
+(15)  goto                            L4
+L9:
+(16)  param    s
+(17)  call     write_integer  1
+(18)  call     writeln        0
+(19)  return
+");
+    }
+
+    #[test]
+    fn blocks_only_end_with_jumps() {
+        for (name, program, _) in samples() {
+            for procedure in lower(&program).procedures {
+                let labels = procedure.blocks.iter().map(|block| block.label).collect::<Vec<_>>();
+                for block in &procedure.blocks {
+                    let (last, body) = block.quads.split_last().expect("blocks are not empty");
+                    assert!(body.iter().all(|quad| !quad.op.is_terminator()), "{} {}", name, procedure);
+                    if let Some(target) = last.target() {
+                        assert!(labels.contains(&target), "{} {}", name, procedure);
+                    }
+                }
+                let exit = procedure.blocks.last().and_then(|block| block.quads.last()).map(|quad| quad.op);
+                assert_eq!(exit, Some(Op::Return), "{} {}", name, procedure);
+            }
+        }
+    }
+
+    #[test]
+    fn names_variables_of_other_scopes_by_their_scope() {
+        let procedure = Procedure {
+            name: "main".to_string(),
+            scope: 1,
+            parent: None,
+            params: vec![],
+            temps: 0,
+            slots: vec![],
+            blocks: vec![]
+        };
+        assert_eq!(procedure.field(&Arg::Var(1, "count".to_string())), "count");
+        assert_eq!(procedure.field(&Arg::Var(2, "count".to_string())), "count.2");
+        assert_eq!(procedure.field(&Arg::Var(1, "t1".to_string())), "t1.1");
+        assert_eq!(procedure.field(&Arg::Temp(1)), "t1");
+        assert_eq!(procedure.field(&Arg::Real(2.0)), "2.0");
+        assert_eq!(procedure.field(&Arg::Type(Type::Range(Box::new(Type::Char), 97..122))), "Character[97..122]");
+    }
+}
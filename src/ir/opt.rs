@@ -0,0 +1,819 @@
+use std::collections::{HashMap, HashSet};
+use super::{Arg, Block, Module, Op, Procedure, Quad};
+use super::cfg::{indices, Dominators, Graph};
+use super::inline::{inline, tail_calls, Report};
+use super::loops::LOOP;
+use super::ssa::{from_ssa, shared, to_ssa};
+
+/// A pass over a procedure in SSA form, telling whether it changed anything
+pub type Pass = fn(&mut Procedure) -> bool;
+
+/// The passes of `-O1`, run in turn until none of them changes anything
+pub const SCALAR: &[Pass] = &[constants, copies, common_subexpressions, dead_code];
+
+/// Optimizes the procedures of a module at a level of `-O`, from 1 on with
+/// the scalar passes over each in SSA form, from 2 on with the loop passes
+/// as well, inlining small subprograms and making tail calls jumps, which
+/// the report tells about
+pub fn optimize(mut module: Module, level: u8) -> (Module, Report) {
+    let mut report = Report { program: module.name.clone(), ..Report::default() };
+    if level == 0 {
+        return (module, report);
+    }
+    let passes = match level {
+        1 => SCALAR.to_vec(),
+        _ => SCALAR.iter().chain(LOOP).copied().collect()
+    };
+    if level >= 2 {
+        tail_calls(&mut module, &mut report);
+    }
+    let shared = shared(&module);
+    for procedure in &mut module.procedures {
+        to_ssa(procedure, &shared);
+        iterate(procedure, &passes);
+    }
+    if level >= 2 {
+        inline(&mut module, &shared, &passes, &mut report);
+    }
+    for procedure in &mut module.procedures {
+        from_ssa(procedure);
+    }
+    number_labels(&mut module);
+    (module, report)
+}
+
+/// Runs passes over a procedure in turn until none of them changes anything
+pub fn iterate(procedure: &mut Procedure, passes: &[Pass]) {
+    while passes.iter().fold(false, |changed, pass| pass(procedure) | changed) {}
+}
+
+/// Numbers the blocks of a module in order from 1, as lowering does
+fn number_labels(module: &mut Module) {
+    let mut count = 0;
+    for procedure in &mut module.procedures {
+        let mut numbers = HashMap::new();
+        for block in &mut procedure.blocks {
+            count += 1;
+            numbers.insert(block.label, count);
+            block.label = count;
+        }
+        for quad in procedure.blocks.iter_mut().flat_map(|block| block.quads.iter_mut()) {
+            if let Arg::Label(label) = &mut quad.result {
+                *label = numbers[label];
+            }
+        }
+    }
+}
+
+/// Whether two constants are the same, a real by its bits
+fn same(left: &Arg, right: &Arg) -> bool {
+    match (left, right) {
+        (Arg::Real(left), Arg::Real(right)) => left.to_bits() == right.to_bits(),
+        (left, right) => left == right
+    }
+}
+
+/// An integer constant
+pub fn int(arg: &Arg) -> Option<i64> {
+    match arg {
+        Arg::Int(value) => Some(*value),
+        _ => None
+    }
+}
+
+/// A real constant, an integer in its place, as a temporary never assigned
+/// reads, being widened
+fn real(arg: &Arg) -> Option<f64> {
+    match arg {
+        Arg::Real(value) => Some(*value),
+        Arg::Int(value) => Some(*value as f64),
+        _ => None
+    }
+}
+
+/// The result of an operator on constants as the compiled program gives it,
+/// if it gives one: integers wrap, and there is none for a division by zero
+/// or an argument out of the domain of a function. The functions the
+/// runtime approximates, and conversions that could round otherwise than it
+/// does, are not folded.
+fn fold(op: Op, left: &Arg, right: &Arg) -> Option<Arg> {
+    let truth = |holds: bool| Some(Arg::Int(holds as i64));
+    let ints = || Some((int(left)?, int(right)?));
+    let reals = || Some((real(left)?, real(right)?));
+    match op {
+        Op::Add => ints().map(|(left, right)| Arg::Int(left.wrapping_add(right))),
+        Op::Sub => ints().map(|(left, right)| Arg::Int(left.wrapping_sub(right))),
+        Op::Mul => ints().map(|(left, right)| Arg::Int(left.wrapping_mul(right))),
+        Op::Div => ints().filter(|(_, right)| *right != 0).map(|(left, right)| Arg::Int(left.wrapping_div(right))),
+        Op::Mod => ints().filter(|(_, right)| *right != 0).map(|(left, right)| Arg::Int(left.wrapping_rem_euclid(right))),
+        Op::Neg => int(left).map(|value| Arg::Int(value.wrapping_neg())),
+        Op::Abs => int(left).map(|value| Arg::Int(value.wrapping_abs())),
+        Op::Not => int(left).map(|value| Arg::Int(value ^ 1)),
+        Op::Odd => int(left).map(|value| Arg::Int(value & 1)),
+        Op::Eq => ints().and_then(|(left, right)| truth(left == right)),
+        Op::Ne => ints().and_then(|(left, right)| truth(left != right)),
+        Op::Lt => ints().and_then(|(left, right)| truth(left < right)),
+        Op::Le => ints().and_then(|(left, right)| truth(left <= right)),
+        Op::Gt => ints().and_then(|(left, right)| truth(left > right)),
+        Op::Ge => ints().and_then(|(left, right)| truth(left >= right)),
+        Op::FAdd => reals().map(|(left, right)| Arg::Real(left + right)),
+        Op::FSub => reals().map(|(left, right)| Arg::Real(left - right)),
+        Op::FMul => reals().map(|(left, right)| Arg::Real(left * right)),
+        Op::FDiv => reals().filter(|(_, right)| *right != 0.0).map(|(left, right)| Arg::Real(left / right)),
+        Op::FNeg => real(left).map(|value| Arg::Real(-value)),
+        Op::FAbs => real(left).map(|value| Arg::Real(value.abs())),
+        Op::FEq => reals().and_then(|(left, right)| truth(left == right)),
+        Op::FNe => reals().and_then(|(left, right)| truth(left != right)),
+        Op::FLt => reals().and_then(|(left, right)| truth(left < right)),
+        Op::FLe => reals().and_then(|(left, right)| truth(left <= right)),
+        Op::FGt => reals().and_then(|(left, right)| truth(left > right)),
+        Op::FGe => reals().and_then(|(left, right)| truth(left >= right)),
+        // integers past 2 to the power of 53 are rounded to a real
+        Op::Float => int(left).filter(|value| value.unsigned_abs() < 1 << 53).map(|value| Arg::Real(value as f64)),
+        Op::Trunc => real(left).filter(|value| value.abs() < 9.0e18).map(|value| Arg::Int(value.trunc() as i64)),
+        Op::Round => real(left).filter(|value| value.abs() < 4.0e15).map(|value| Arg::Int(value.round() as i64)),
+        Op::Sqrt => real(left).filter(|value| *value >= 0.0).map(|value| Arg::Real(value.sqrt())),
+        _ => None
+    }
+}
+
+/// What constant propagation knows of a temporary: nothing yet, that it is
+/// a constant, or that it may vary
+#[derive(Debug, Clone)]
+enum Value {
+    Unknown,
+    Constant(Arg),
+    Varying
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Unknown, Value::Unknown) | (Value::Varying, Value::Varying) => true,
+            (Value::Constant(left), Value::Constant(right)) => same(left, right),
+            _ => false
+        }
+    }
+}
+
+impl Value {
+    fn of(arg: &Arg, values: &[Value]) -> Value {
+        match arg {
+            Arg::Temp(temp) => values[*temp].clone(),
+            Arg::Int(_) | Arg::Real(_) => Value::Constant(arg.clone()),
+            _ => Value::Varying
+        }
+    }
+
+    /// What is known of a temporary two values may reach
+    fn meet(&self, other: &Value) -> Value {
+        match (self, other) {
+            (Value::Unknown, value) | (value, Value::Unknown) => value.clone(),
+            (Value::Constant(left), Value::Constant(right)) if same(left, right) => self.clone(),
+            _ => Value::Varying
+        }
+    }
+}
+
+/// Lowers what is known of the temporary `result` by `value`, telling
+/// whether that changed it
+fn lower(values: &mut [Value], result: &Arg, value: Value) -> bool {
+    match result {
+        Arg::Temp(temp) => {
+            let lowered = values[*temp].meet(&value);
+            let changed = lowered != values[*temp];
+            values[*temp] = lowered;
+            changed
+        },
+        _ => false
+    }
+}
+
+/// What is known of the result of a quadruple
+fn evaluate(quad: &Quad, values: &[Value]) -> Value {
+    if quad.op == Op::Assign {
+        return Value::of(&quad.arg1, values);
+    }
+    let operands = if quad.op.is_binary() { vec![&quad.arg1, &quad.arg2] } else { vec![&quad.arg1] };
+    let mut constants = vec![];
+    for operand in operands {
+        match Value::of(operand, values) {
+            Value::Constant(constant) => constants.push(constant),
+            value => return value
+        }
+    }
+    let right = constants.get(1).cloned().unwrap_or(Arg::None);
+    fold(quad.op, &constants[0], &right).map_or(Value::Varying, Value::Constant)
+}
+
+/// Replaces a temporary by what a map gives for it, if anything
+fn substitute(field: &mut Arg, replaced: &HashMap<usize, Arg>) -> bool {
+    match field {
+        Arg::Temp(temp) => match replaced.get(temp) {
+            Some(value) => {
+                *field = value.clone();
+                true
+            },
+            None => false
+        },
+        _ => false
+    }
+}
+
+/// Replaces the temporaries a procedure reads by what a map gives for them
+pub fn substitute_all(procedure: &mut Procedure, replaced: &HashMap<usize, Arg>) -> bool {
+    let mut changed = false;
+    for block in &mut procedure.blocks {
+        for (_, arg) in block.phis.iter_mut().flat_map(|phi| phi.args.iter_mut()) {
+            changed |= substitute(arg, replaced);
+        }
+        for quad in &mut block.quads {
+            for field in quad.uses_mut() {
+                changed |= substitute(field, replaced);
+            }
+        }
+    }
+    changed
+}
+
+/// Sparse conditional constant propagation, after Wegman and Zadeck: the
+/// temporaries are taken to be constant until shown to vary, following
+/// only the edges the constants found so far let control take. The
+/// temporaries found constant are replaced by their value, as are the
+/// branches on them by jumps, and what control never reaches is dropped,
+/// with the checks of constants that pass.
+pub fn constants(procedure: &mut Procedure) -> bool {
+    let count = procedure.blocks.len();
+    if count == 0 {
+        return false;
+    }
+    let graph = Graph::new(procedure);
+    let indices = indices(procedure);
+    let mut values = vec![Value::Unknown; procedure.temps + 1];
+    let mut reached = vec![false; count];
+    let mut edges = HashSet::new();
+    reached[0] = true;
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for index in 0..count {
+            if !reached[index] {
+                continue;
+            }
+            let block = &procedure.blocks[index];
+            for phi in &block.phis {
+                let value = phi.args.iter()
+                    .filter(|(label, _)| indices.get(label).is_some_and(|pred| edges.contains(&(*pred, index))))
+                    .fold(Value::Unknown, |value, (_, arg)| value.meet(&Value::of(arg, &values)));
+                changed |= lower(&mut values, &phi.result, value);
+            }
+            for quad in &block.quads {
+                if let Some(result) = quad.def() {
+                    let value = evaluate(quad, &values);
+                    changed |= lower(&mut values, result, value);
+                }
+            }
+            let successors = match block.quads.last() {
+                Some(quad) if matches!(quad.op, Op::If | Op::IfFalse) => match Value::of(&quad.arg1, &values) {
+                    Value::Unknown => vec![],
+                    Value::Constant(condition) => {
+                        let jumps = (int(&condition) != Some(0)) == (quad.op == Op::If);
+                        let target = quad.target().map(|label| indices[&label]);
+                        vec![if jumps { target.expect("branches have a target") } else { index + 1 }]
+                    },
+                    Value::Varying => graph.successors[index].clone()
+                },
+                _ => graph.successors[index].clone()
+            };
+            for successor in successors {
+                changed |= edges.insert((index, successor));
+                changed |= !reached[successor];
+                reached[successor] = true;
+            }
+        }
+    }
+
+    let constant = |arg: &Arg| match arg {
+        Arg::Temp(temp) => matches!(values[*temp], Value::Constant(_)),
+        _ => false
+    };
+    let replaced = values.iter().enumerate()
+        .filter_map(|(temp, value)| match value {
+            Value::Constant(value) => Some((temp, value.clone())),
+            _ => None
+        })
+        .collect::<HashMap<_, _>>();
+    let mut changed = substitute_all(procedure, &replaced);
+    for (index, block) in procedure.blocks.iter_mut().enumerate() {
+        let (phis, quads) = (block.phis.len(), block.quads.len());
+        block.phis.retain(|phi| !constant(&phi.result));
+        for phi in &mut block.phis {
+            phi.args.retain(|(label, _)| indices.get(label).is_some_and(|pred| edges.contains(&(*pred, index))));
+        }
+        block.quads.retain(|quad| match quad.op {
+            Op::Call => true,
+            Op::Check | Op::Bounds => match (&quad.arg1, &quad.arg2) {
+                (Arg::Int(value), Arg::Type(ty)) => !ty.bounds().is_some_and(|bounds| {
+                    (bounds.start as i64..=bounds.end as i64).contains(value)
+                }),
+                _ => true
+            },
+            _ => !quad.def().is_some_and(constant)
+        });
+        if let Some(last) = block.quads.last_mut() {
+            if let (Op::If | Op::IfFalse, Arg::Int(condition)) = (last.op, &last.arg1) {
+                if (*condition != 0) == (last.op == Op::If) {
+                    last.op = Op::Goto;
+                    last.arg1 = Arg::None;
+                } else {
+                    block.quads.pop();
+                }
+            }
+        }
+        changed |= phis != block.phis.len() || quads != block.quads.len();
+    }
+    let mut index = 0;
+    procedure.blocks.retain(|_| {
+        index += 1;
+        reached[index - 1]
+    });
+    changed || procedure.blocks.len() != count
+}
+
+/// Copy propagation: the temporaries copied from another or from a
+/// constant, and the φ-functions merging a single value, are replaced by
+/// what they copy. A copy of a variable stays, as the variable is memory.
+pub fn copies(procedure: &mut Procedure) -> bool {
+    let mut replaced = HashMap::new();
+    let resolve = |arg: &Arg, replaced: &HashMap<usize, Arg>| {
+        let mut arg = arg.clone();
+        while let Arg::Temp(temp) = arg {
+            match replaced.get(&temp) {
+                Some(value) => arg = Arg::clone(value),
+                None => break
+            }
+        }
+        arg
+    };
+    let mut found = true;
+    while found {
+        found = false;
+        for block in &mut procedure.blocks {
+            block.quads.retain(|quad| match (quad.op, &quad.result, &quad.arg1) {
+                (Op::Assign, Arg::Temp(temp), source @ (Arg::Temp(_) | Arg::Int(_) | Arg::Real(_))) => {
+                    let source = resolve(source, &replaced);
+                    replaced.insert(*temp, source);
+                    found = true;
+                    false
+                },
+                _ => true
+            });
+            block.phis.retain(|phi| {
+                let mut sources = phi.args.iter()
+                    .map(|(_, arg)| resolve(arg, &replaced))
+                    .filter(|arg| *arg != phi.result);
+                let first = match sources.next() {
+                    Some(first) => first,
+                    None => return true
+                };
+                if !sources.all(|source| same(&source, &first)) {
+                    return true;
+                }
+                if let Arg::Temp(temp) = phi.result {
+                    replaced.insert(temp, first);
+                }
+                found = true;
+                false
+            });
+        }
+    }
+    let resolved = replaced.keys().map(|temp| (*temp, resolve(&Arg::Temp(*temp), &replaced))).collect();
+    substitute_all(procedure, &resolved);
+    !replaced.is_empty()
+}
+
+/// Whether a quadruple must stay though its result is not read: it stores,
+/// calls, checks or jumps, assigns a variable, or may stop the program
+pub fn critical(quad: &Quad) -> bool {
+    match quad.op {
+        Op::Div | Op::Mod => !matches!(quad.arg2, Arg::Int(divisor) if divisor != 0),
+        Op::FDiv => !real(&quad.arg2).is_some_and(|divisor| divisor != 0.0),
+        Op::Sqrt => !real(&quad.arg1).is_some_and(|value| value >= 0.0),
+        Op::Ln => !real(&quad.arg1).is_some_and(|value| value > 0.0),
+        Op::Store | Op::Call | Op::Param | Op::Check | Op::Bounds | Op::Deref | Op::NoCase | Op::Goto | Op::If
+        | Op::IfFalse | Op::Return => true,
+        _ => !matches!(quad.def(), Some(Arg::Temp(_)))
+    }
+}
+
+/// Dead code elimination: the temporaries critical quadruples read are
+/// live, as are those the quadruples and φ-functions assigning live ones
+/// read, and the rest are dropped with what assigns them
+pub fn dead_code(procedure: &mut Procedure) -> bool {
+    let temps = |fields: Vec<&Arg>| fields.into_iter()
+        .filter_map(|field| match field {
+            Arg::Temp(temp) => Some(*temp),
+            _ => None
+        })
+        .collect::<Vec<_>>();
+    let mut reads = HashMap::new();
+    let mut work = vec![];
+    for block in &procedure.blocks {
+        for phi in &block.phis {
+            if let Arg::Temp(temp) = phi.result {
+                reads.insert(temp, temps(phi.args.iter().map(|(_, arg)| arg).collect()));
+            }
+        }
+        for quad in &block.quads {
+            match quad.def() {
+                Some(Arg::Temp(temp)) if !critical(quad) => {
+                    reads.insert(*temp, temps(quad.uses()));
+                },
+                _ => work.extend(temps(quad.uses()))
+            }
+        }
+    }
+    let mut live = HashSet::new();
+    while let Some(temp) = work.pop() {
+        if live.insert(temp) {
+            work.extend(reads.get(&temp).into_iter().flatten());
+        }
+    }
+    let dead = |result: &Arg| matches!(result, Arg::Temp(temp) if !live.contains(temp));
+    let mut changed = false;
+    for block in &mut procedure.blocks {
+        let (phis, quads) = (block.phis.len(), block.quads.len());
+        block.phis.retain(|phi| !dead(&phi.result));
+        block.quads.retain(|quad| critical(quad) || !quad.def().is_some_and(dead));
+        changed |= phis != block.phis.len() || quads != block.quads.len();
+    }
+    changed
+}
+
+/// What identifies the value a quadruple computes, or the check it makes,
+/// if it only depends on its operands: its operator and operands, those of
+/// a commutative operator in order. An operand that is a variable may
+/// change in memory.
+fn expression(quad: &Quad) -> Option<String> {
+    let pure = quad.op.is_binary() || matches!(quad.op, Op::Neg | Op::FNeg | Op::Not | Op::Odd | Op::Abs | Op::FAbs
+        | Op::Float | Op::Trunc | Op::Round | Op::Sqrt | Op::Ln | Op::Sin | Op::Cos | Op::Exp | Op::Arctan);
+    let variable = matches!(quad.arg1, Arg::Var(..)) || matches!(quad.arg2, Arg::Var(..));
+    let computed = matches!(quad.result, Arg::Temp(_));
+    let identified = match quad.op {
+        Op::Address => computed,
+        Op::Check | Op::Bounds => !variable,
+        _ => pure && computed && !variable
+    };
+    if !identified {
+        return None;
+    }
+    let (mut left, mut right) = (format!("{:?}", quad.arg1), format!("{:?}", quad.arg2));
+    if quad.op.is_commutative() && left > right {
+        std::mem::swap(&mut left, &mut right);
+    }
+    Some(format!("{} {} {}", quad.op, left, right))
+}
+
+/// Common subexpression elimination over the dominator tree: a quadruple
+/// computing what one of a block dominating it already has is dropped, its
+/// result replaced by the earlier one, as is a check already made
+pub fn common_subexpressions(procedure: &mut Procedure) -> bool {
+    if procedure.blocks.is_empty() {
+        return false;
+    }
+    let dominators = Dominators::new(&Graph::new(procedure));
+    let mut replaced = HashMap::new();
+    let changed = eliminate(&mut procedure.blocks, &dominators.children, 0, &mut HashMap::new(), &mut replaced);
+    substitute_all(procedure, &replaced);
+    changed
+}
+
+/// Eliminates the common subexpressions of a block and the blocks it
+/// dominates, given the expressions available in the block
+fn eliminate(blocks: &mut [Block], children: &[Vec<usize>], index: usize, available: &mut HashMap<String, Arg>,
+             replaced: &mut HashMap<usize, Arg>) -> bool {
+    let mut added = vec![];
+    let count = blocks[index].quads.len();
+    blocks[index].quads.retain_mut(|quad| {
+        for field in quad.uses_mut() {
+            substitute(field, replaced);
+        }
+        let expression = match expression(quad) {
+            Some(expression) => expression,
+            None => return true
+        };
+        match available.get(&expression) {
+            Some(value) => {
+                if let Arg::Temp(temp) = quad.result {
+                    replaced.insert(temp, value.clone());
+                }
+                false
+            },
+            None => {
+                available.insert(expression.clone(), quad.result.clone());
+                added.push(expression);
+                true
+            }
+        }
+    });
+    let mut changed = blocks[index].quads.len() != count;
+    for &child in &children[index] {
+        changed |= eliminate(blocks, children, child, available, replaced);
+    }
+    for expression in added {
+        available.remove(&expression);
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::lower::lower;
+    use super::super::parse::procedure;
+    use crate::emit::tests::{check, samples};
+
+    /// Runs a pass on a fixture, comparing what it gives with the expected
+    /// fixture
+    fn assert_pass(pass: Pass, before: &str, after: &str) {
+        let mut optimized = procedure(before);
+        pass(&mut optimized);
+        assert_eq!(optimized.to_string(), procedure(after).to_string());
+    }
+
+    #[test]
+    fn constants_are_propagated_through_branches() {
+        assert_pass(constants, "
+            procedure main
+            L1:
+              :=  6  t1
+              *  t1  7  t2
+              check  t2  Integer[0..100]
+              >  t2  40  t3
+              iffalse  t3  L3
+            L2:
+              :=  1  t4
+              goto  L4
+            L3:
+              :=  2  t5
+            L4:
+              phi  t4@L2,t5@L3  t6
+              param  t6
+              call  write_integer  1
+              div  t2  0  t7
+              param  t7
+              call  write_integer  1
+              return
+        ", "
+            procedure main
+            L1:
+            L2:
+              goto  L4
+            L4:
+              param  1
+              call  write_integer  1
+              div  42  0  t7
+              param  t7
+              call  write_integer  1
+              return
+        ");
+    }
+
+    #[test]
+    fn constants_stop_at_what_may_vary() {
+        assert_pass(constants, "
+            procedure main
+            L1:
+              call  read_integer  0  t1
+              +  t1  1  t2
+              :=  t2  x
+              :=  1.5  t3
+              *.  t3  2.0  t4
+              sin  t4  t5
+              param  t5
+              call  write_real  1
+              check  300  Character
+              return
+        ", "
+            procedure main
+            L1:
+              call  read_integer  0  t1
+              +  t1  1  t2
+              :=  t2  x
+              sin  3.0  t5
+              param  t5
+              call  write_real  1
+              check  300  Character
+              return
+        ");
+    }
+
+    #[test]
+    fn loops_keep_what_they_change() {
+        assert_pass(constants, "
+            procedure main
+            L1:
+              :=  0  t1
+              :=  5  t2
+            L2:
+              phi  t1@L1,t5@L3  t3
+              phi  t2@L1,t2@L3  t4
+              <  t3  t4  t6
+              iffalse  t6  L4
+            L3:
+              +  t3  1  t5
+              goto  L2
+            L4:
+              param  t3
+              call  write_integer  1
+              return
+        ", "
+            procedure main
+            L1:
+            L2:
+              phi  0@L1,t5@L3  t3
+              <  t3  5  t6
+              iffalse  t6  L4
+            L3:
+              +  t3  1  t5
+              goto  L2
+            L4:
+              param  t3
+              call  write_integer  1
+              return
+        ");
+    }
+
+    #[test]
+    fn copies_are_propagated() {
+        assert_pass(copies, "
+            procedure main
+            L1:
+              :=  x  t1
+              :=  t1  t2
+              :=  3  t3
+            L2:
+              phi  t2@L1,t4@L2  t4
+              phi  t3@L1,t3@L2  t5
+              +  t4  t5  t6
+              :=  t6  x
+              if  t6  L2
+            L3:
+              return
+        ", "
+            procedure main
+            L1:
+              :=  x  t1
+            L2:
+              +  t1  3  t6
+              :=  t6  x
+              if  t6  L2
+            L3:
+              return
+        ");
+    }
+
+    #[test]
+    fn dead_code_is_eliminated() {
+        assert_pass(dead_code, "
+            procedure main
+            L1:
+              :=  x  t1
+              +  t1  1  t2
+              *  t2  2  t3
+              div  t1  t2  t4
+              div  t1  2  t5
+              ln  0.0  t6
+              sqrt  4.0  t7
+              &  y  t8
+              load  t8  t9
+              :=  t2  x
+            L2:
+              phi  t3@L1,t10@L2  t10
+              if  t1  L2
+            L3:
+              return
+        ", "
+            procedure main
+            L1:
+              :=  x  t1
+              +  t1  1  t2
+              div  t1  t2  t4
+              ln  0.0  t6
+              :=  t2  x
+            L2:
+              if  t1  L2
+            L3:
+              return
+        ");
+    }
+
+    #[test]
+    fn common_subexpressions_are_eliminated() {
+        assert_pass(common_subexpressions, "
+            procedure main
+            L1:
+              call  read_integer  0  t1
+              +  t1  1  t2
+              bounds  t2  Integer[1..10]
+              *  t2  8  t3
+              &  a  t4
+              +  t4  t3  t5
+              +  1  t1  t6
+              bounds  t6  Integer[1..10]
+              *  t6  8  t7
+              &  a  t8
+              +  t8  t7  t9
+              load  t9  t10
+              load  t5  t11
+              +  x  1  t12
+              +  x  1  t13
+              if  t10  L3
+            L2:
+              +  t1  1  t14
+              param  t14
+              call  write_integer  1
+            L3:
+              -  t1  1  t15
+              -  1  t1  t16
+              return
+        ", "
+            procedure main
+            L1:
+              call  read_integer  0  t1
+              +  t1  1  t2
+              bounds  t2  Integer[1..10]
+              *  t2  8  t3
+              &  a  t4
+              +  t4  t3  t5
+              load  t5  t10
+              load  t5  t11
+              +  x  1  t12
+              +  x  1  t13
+              if  t10  L3
+            L2:
+              param  t2
+              call  write_integer  1
+            L3:
+              -  t1  1  t15
+              -  1  t1  t16
+              return
+        ");
+    }
+
+    #[test]
+    fn optimizes_whole_programs() {
+        let module = optimize(lower(&check("
+            program folded;
+            const n = 10;
+            var i, s: integer;
+                a: array [1..n] of integer;
+            begin
+              s := 0;
+              i := n div 2;
+              a[i] := i * i;
+              if i > 3 then
+                s := s + a[i] + a[i]
+              else
+                s := -1;
+              writeln(s)
+            end.
+        ")), 1).0;
+        // the index and the address are folded, the check of the index with them
+        assert_eq!(module.to_string(), "\
+program folded
+
+procedure main
+      op      arg1           arg2    result
+L1:
+ (1)  &       a                      t1
+ (2)  +       t1             32      t2
+ (3)  store   25                     t2
+ (4)  load    t2                     t3
+ (5)  +       0              t3      t4
+ (6)  load    t2                     t5
+ (7)  +       t4             t5      t6
+ (8)  param   t6
+ (9)  call    write_integer  1
+(10)  call    writeln        0
+(11)  return
+");
+    }
+
+    #[test]
+    fn optimized_dumps_keep_their_shape() {
+        for (name, program, _) in samples() {
+            for procedure in (1..=2).flat_map(|level| optimize(lower(&program), level).0.procedures) {
+                let labels = procedure.blocks.iter().map(|block| block.label).collect::<Vec<_>>();
+                for block in &procedure.blocks {
+                    assert!(block.phis.is_empty(), "{} {}", name, procedure);
+                    let (last, body) = block.quads.split_last().expect("blocks are not empty");
+                    assert!(body.iter().all(|quad| !quad.op.is_terminator()), "{} {}", name, procedure);
+                    if let Some(target) = last.target() {
+                        assert!(labels.contains(&target), "{} {}", name, procedure);
+                    }
+                }
+            }
+        }
+    }
+}
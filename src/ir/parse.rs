@@ -0,0 +1,128 @@
+//! Reads a procedure back from its table, so that the passes can be tested
+//! on fixtures written as they are dumped. The numbers of the rows and the
+//! widths of the columns do not matter; which fields a row has follows from
+//! its operator.
+
+use crate::ast::program::{Program, ScopeId};
+use crate::ast::types::Type;
+use super::{Arg, Block, Op, Phi, Procedure, Quad};
+
+pub fn procedure(text: &str) -> Procedure {
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+    let name = lines.next().and_then(|line| line.strip_prefix("procedure ")).expect("a procedure").to_string();
+    let scope = name.rsplit_once('.').map_or(Program::GLOBAL, |(_, scope)| scope.parse().expect("a scope"));
+    let parent = (scope != Program::GLOBAL).then_some(Program::GLOBAL);
+    let mut procedure = Procedure { name, scope, parent, params: vec![], temps: 0, slots: vec![], blocks: vec![] };
+    for line in lines {
+        if let Some(label) = line.strip_prefix('L').and_then(|line| line.strip_suffix(':')) {
+            procedure.blocks.push(Block { label: label.parse().expect("a label"), phis: vec![], quads: vec![] });
+            continue;
+        }
+        let mut fields = line.split_whitespace().collect::<Vec<_>>();
+        if fields[0].starts_with('(') {
+            fields.remove(0);
+        }
+        if fields[0] == "op" {
+            continue;
+        }
+        let field = |index: usize| fields.get(index).map_or(Arg::None, |text| arg(text, scope));
+        let block = procedure.blocks.last_mut().expect("rows are in a block");
+        if fields[0] == "phi" {
+            let args = fields[1].split(',')
+                .map(|arg| {
+                    let (value, label) = arg.split_once("@L").expect("a value and a label");
+                    (label.parse().expect("a label"), self::arg(value, scope))
+                })
+                .collect();
+            block.phis.push(Phi { result: field(2), args });
+            continue;
+        }
+        let op = *Op::ALL.iter().find(|op| op.name() == fields[0]).expect("an operator");
+        let (arg1, arg2, result) = match op {
+            op if op.is_binary() => (field(1), field(2), field(3)),
+            Op::Check | Op::Bounds | Op::NoCase => (field(1), Arg::Type(ty(fields[2])), Arg::None),
+            Op::Goto => (Arg::None, Arg::None, field(1)),
+            Op::If | Op::IfFalse => (field(1), Arg::None, field(2)),
+            Op::Param | Op::Return => (field(1), Arg::None, Arg::None),
+            Op::Call => (Arg::Name(fields[1].to_string()), field(2), field(3)),
+            _ => (field(1), Arg::None, field(2))
+        };
+        block.quads.push(Quad { op, arg1, arg2, result, line: 0, range: 0..0 });
+    }
+    let fields = procedure.blocks.iter()
+        .flat_map(|block| block.phis.iter()
+            .flat_map(|phi| std::iter::once(&phi.result).chain(phi.args.iter().map(|(_, arg)| arg)))
+            .chain(block.quads.iter().flat_map(|quad| [&quad.arg1, &quad.arg2, &quad.result])))
+        .collect::<Vec<_>>();
+    for field in fields {
+        match field {
+            Arg::Temp(temp) => procedure.temps = procedure.temps.max(*temp),
+            Arg::Slot(slot) if procedure.slots.len() < *slot => procedure.slots.resize(*slot, 8),
+            _ => {}
+        }
+    }
+    procedure
+}
+
+/// A field: a temporary, a memory temporary, a label, a number, or a
+/// variable, of the scope of the procedure unless it is followed by one
+fn arg(text: &str, scope: ScopeId) -> Arg {
+    let numbered = |prefix: char| text.strip_prefix(prefix)
+        .filter(|number| !number.is_empty() && number.bytes().all(|byte| byte.is_ascii_digit()))
+        .map(|number| number.parse().expect("a number"));
+    if text.starts_with(|c: char| c.is_ascii_digit() || c == '-') || text == "inf" || text == "NaN" {
+        if text.contains(['.', 'e', 'i', 'N']) {
+            Arg::Real(text.parse().expect("a real"))
+        } else {
+            Arg::Int(text.parse().expect("an integer"))
+        }
+    } else if let Some((name, scope)) = text.rsplit_once('.') {
+        Arg::Var(scope.parse().expect("a scope"), name.to_string())
+    } else if let Some(temp) = numbered('t') {
+        Arg::Temp(temp)
+    } else if let Some(slot) = numbered('m') {
+        Arg::Slot(slot)
+    } else if let Some(label) = numbered('L') {
+        Arg::Label(label)
+    } else {
+        Arg::Var(scope, text.to_string())
+    }
+}
+
+/// A type as checks name it, an ordinal host with the bounds of a subrange
+/// in brackets
+fn ty(text: &str) -> Type {
+    let (host, bounds) = match text.split_once('[') {
+        Some((host, bounds)) => (host, Some(bounds.trim_end_matches(']'))),
+        None => (text, None)
+    };
+    let host = match host {
+        "Integer" => Type::Integer,
+        "Character" => Type::Char,
+        "Boolean" => Type::Boolean,
+        "Real" => Type::Real,
+        names => Type::Enum(names.trim_matches(['(', ')']).split(',').map(str::to_string).collect())
+    };
+    match bounds.and_then(|bounds| bounds.split_once("..")) {
+        Some((low, high)) => Type::Range(Box::new(host), low.parse().expect("a bound")..high.parse().expect("a bound")),
+        None => host
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::lower::lower;
+    use crate::emit::tests::samples;
+
+    #[test]
+    fn dumps_read_back() {
+        for (name, program, _) in samples() {
+            for lowered in lower(&program).procedures {
+                let parsed = procedure(&lowered.to_string());
+                assert_eq!(parsed.to_string(), lowered.to_string(), "{}", name);
+                assert_eq!(parsed.temps, lowered.temps, "{}", name);
+            }
+        }
+    }
+}
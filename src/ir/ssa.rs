@@ -0,0 +1,471 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use crate::ast::program::ScopeId;
+use super::{Arg, Block, Module, Op, Phi, Procedure, Quad};
+use super::cfg::{prune, Dominators, Graph};
+
+/// The variables a procedure cannot keep in temporaries: those read or
+/// written by a procedure of another scope, and those whose address is
+/// taken, which may be changed through it
+pub fn shared(module: &Module) -> HashSet<(ScopeId, String)> {
+    let mut shared = HashSet::new();
+    for procedure in &module.procedures {
+        for quad in procedure.blocks.iter().flat_map(|block| &block.quads) {
+            for field in [&quad.arg1, &quad.arg2, &quad.result] {
+                if let Arg::Var(scope, name) = field {
+                    if *scope != procedure.scope || quad.op == Op::Address {
+                        shared.insert((*scope, name.clone()));
+                    }
+                }
+            }
+        }
+    }
+    shared
+}
+
+/// Puts a procedure in SSA form, every temporary being assigned once and
+/// φ-functions merging them where control flow joins. The variables of its
+/// own scope no other procedure sees become temporaries, copied from the
+/// variables on entry, so that a variable left in a quadruple is memory.
+///
+/// φ-functions are placed at the iterated dominance frontiers of the
+/// blocks assigning a temporary, for the temporaries read in a block other
+/// than the one they are assigned in, and the temporaries are then renamed
+/// going down the dominator tree. Blocks the entry does not reach are
+/// dropped, and a temporary read before it is assigned reads 0.
+pub fn to_ssa(procedure: &mut Procedure, shared: &HashSet<(ScopeId, String)>) {
+    // the entry is not the target of a jump, so that nothing flows into it
+    let at = procedure.blocks.first().and_then(|block| block.quads.first())
+        .map_or((0, 0..0), |quad| (quad.line, quad.range.clone()));
+    let label = procedure.fresh_label();
+    procedure.blocks.insert(0, Block { label, phis: vec![], quads: vec![] });
+    promote(procedure, shared, at);
+    prune(procedure);
+
+    let graph = Graph::new(procedure);
+    let dominators = Dominators::new(&graph);
+    let count = procedure.temps + 1;
+    let mut assigned = vec![vec![]; count];
+    let mut global = vec![false; count];
+    for (index, block) in procedure.blocks.iter().enumerate() {
+        let mut killed = HashSet::new();
+        for quad in &block.quads {
+            for field in quad.uses() {
+                if let Arg::Temp(temp) = field {
+                    global[*temp] |= !killed.contains(temp);
+                }
+            }
+            if let Some(Arg::Temp(temp)) = quad.def() {
+                killed.insert(*temp);
+                if !assigned[*temp].contains(&index) {
+                    assigned[*temp].push(index);
+                }
+            }
+        }
+    }
+    let mut origins = vec![vec![]; procedure.blocks.len()];
+    for temp in (1..count).filter(|temp| global[*temp]) {
+        let mut placed = HashSet::new();
+        let mut work = assigned[temp].clone();
+        while let Some(block) = work.pop() {
+            for &frontier in &dominators.frontiers[block] {
+                if placed.insert(frontier) {
+                    procedure.blocks[frontier].phis.push(Phi { result: Arg::Temp(temp), args: vec![] });
+                    origins[frontier].push(temp);
+                    if !assigned[temp].contains(&frontier) {
+                        work.push(frontier);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut renaming = Renaming {
+        graph: &graph,
+        dominators: &dominators,
+        origins: &origins,
+        stacks: vec![vec![]; count],
+        temps: 0
+    };
+    if !procedure.blocks.is_empty() {
+        renaming.rename(&mut procedure.blocks, 0);
+    }
+    procedure.temps = renaming.temps;
+}
+
+/// Turns the variables of the scope of a procedure that no other procedure
+/// sees into temporaries, copied from the variables at the start of the
+/// entry
+fn promote(procedure: &mut Procedure, shared: &HashSet<(ScopeId, String)>, (line, range): (usize, Range<usize>)) {
+    let scope = procedure.scope;
+    let mut temps = procedure.temps;
+    let mut promoted = HashMap::new();
+    let mut copies = vec![];
+    for block in &mut procedure.blocks {
+        for quad in &mut block.quads {
+            for field in [&mut quad.arg1, &mut quad.arg2, &mut quad.result] {
+                if let Arg::Var(declared, name) = field {
+                    if *declared != scope || shared.contains(&(scope, name.clone())) {
+                        continue;
+                    }
+                    let temp = *promoted.entry(name.clone()).or_insert_with(|| {
+                        temps += 1;
+                        copies.push(Quad { op: Op::Assign, arg1: field.clone(), arg2: Arg::None, result: Arg::Temp(temps), line, range: range.clone() });
+                        temps
+                    });
+                    *field = Arg::Temp(temp);
+                }
+            }
+        }
+    }
+    procedure.temps = temps;
+    procedure.blocks[0].quads.splice(0..0, copies);
+}
+
+struct Renaming<'a> {
+    graph: &'a Graph,
+    dominators: &'a Dominators,
+    /// The temporary each φ-function of a block was placed for
+    origins: &'a [Vec<usize>],
+    /// The names each temporary has been given down to the block being
+    /// renamed, the latest last
+    stacks: Vec<Vec<usize>>,
+    temps: usize
+}
+
+impl<'a> Renaming<'a> {
+    fn current(&self, temp: usize) -> Arg {
+        self.stacks[temp].last().map_or(Arg::Int(0), |name| Arg::Temp(*name))
+    }
+
+    fn define(&mut self, temp: usize) -> Arg {
+        self.temps += 1;
+        self.stacks[temp].push(self.temps);
+        Arg::Temp(self.temps)
+    }
+
+    fn rename(&mut self, blocks: &mut [Block], index: usize) {
+        let mut defined = vec![];
+        for (phi, temp) in blocks[index].phis.iter_mut().zip(&self.origins[index]) {
+            phi.result = self.define(*temp);
+            defined.push(*temp);
+        }
+        for quad in &mut blocks[index].quads {
+            for field in quad.uses_mut() {
+                if let Arg::Temp(temp) = field {
+                    *field = self.current(*temp);
+                }
+            }
+            if let Some(&Arg::Temp(temp)) = quad.def() {
+                quad.result = self.define(temp);
+                defined.push(temp);
+            }
+        }
+        let label = blocks[index].label;
+        for &successor in &self.graph.successors[index] {
+            for (phi, temp) in blocks[successor].phis.iter_mut().zip(&self.origins[successor]) {
+                phi.args.push((label, self.current(*temp)));
+            }
+        }
+        for &child in &self.dominators.children[index] {
+            self.rename(blocks, child);
+        }
+        for temp in defined {
+            self.stacks[temp].pop();
+        }
+    }
+}
+
+/// Takes a procedure out of SSA form, each φ-function becoming copies at
+/// the end of the predecessors of its block. An edge from a block with
+/// other successors to a block with φ-functions is split first, so that
+/// the copies are only made on that edge, and the copies into a block are
+/// ordered so that none overwrites what another reads. The temporaries are
+/// then numbered again from 1, and the jumps to the next block dropped.
+pub fn from_ssa(procedure: &mut Procedure) {
+    split_edges(procedure);
+    let graph = Graph::new(procedure);
+    for index in 0..procedure.blocks.len() {
+        let label = procedure.blocks[index].label;
+        let copies = graph.successors[index].iter()
+            .flat_map(|successor| procedure.blocks[*successor].phis.iter()
+                .filter_map(|phi| phi.args.iter()
+                    .find(|(from, _)| *from == label)
+                    .map(|(_, arg)| (phi.result.clone(), arg.clone()))))
+            .collect::<Vec<_>>();
+        if copies.is_empty() {
+            continue;
+        }
+        let mut copies = sequence(procedure, copies);
+        let block = &procedure.blocks[index];
+        let (line, range) = block.quads.last().map_or((0, 0..0), |quad| (quad.line, quad.range.clone()));
+        let at = match block.quads.last() {
+            Some(quad) if quad.op.is_terminator() => block.quads.len() - 1,
+            _ => block.quads.len()
+        };
+        // a branch reads its condition after the copies are made
+        if let Some(condition) = block.quads.get(at).map(|quad| quad.arg1.clone()) {
+            if copies.iter().any(|(result, _)| *result == condition) {
+                let saved = procedure.fresh_temp();
+                copies.insert(0, (saved.clone(), condition));
+                procedure.blocks[index].quads[at].arg1 = saved;
+            }
+        }
+        let copies = copies.into_iter().map(|(result, arg1)| Quad { op: Op::Assign, arg1, arg2: Arg::None, result, line, range: range.clone() });
+        procedure.blocks[index].quads.splice(at..at, copies);
+    }
+    for block in &mut procedure.blocks {
+        block.phis.clear();
+    }
+    tidy(procedure);
+}
+
+/// Splits the edges from blocks with more than one successor to blocks
+/// with φ-functions, a block the first falls through to being placed right
+/// after it and one it jumps to at the end of the procedure
+fn split_edges(procedure: &mut Procedure) {
+    let graph = Graph::new(procedure);
+    let labels = procedure.blocks.iter().map(|block| block.label).collect::<Vec<_>>();
+    let mut label = procedure.fresh_label();
+    let mut after = vec![];
+    let mut appended = vec![];
+    for (index, successors) in graph.successors.iter().enumerate() {
+        if successors.len() < 2 {
+            continue;
+        }
+        for &successor in successors {
+            if procedure.blocks[successor].phis.is_empty() {
+                continue;
+            }
+            label += 1;
+            let label = label - 1;
+            let (from, to) = (labels[index], labels[successor]);
+            for phi in &mut procedure.blocks[successor].phis {
+                for (pred, _) in &mut phi.args {
+                    if *pred == from {
+                        *pred = label;
+                    }
+                }
+            }
+            let last = procedure.blocks[index].quads.last_mut().expect("a branching block ends with a jump");
+            let (line, range) = (last.line, last.range.clone());
+            if last.target() == Some(to) {
+                last.result = Arg::Label(label);
+                let goto = Quad { op: Op::Goto, arg1: Arg::None, arg2: Arg::None, result: Arg::Label(to), line, range };
+                appended.push(Block { label, phis: vec![], quads: vec![goto] });
+            } else {
+                after.push((index, Block { label, phis: vec![], quads: vec![] }));
+            }
+        }
+    }
+    for (index, block) in after.into_iter().rev() {
+        procedure.blocks.insert(index + 1, block);
+    }
+    procedure.blocks.extend(appended);
+}
+
+/// Orders parallel copies so that each is made before the value it reads
+/// is overwritten, a cycle being broken through a new temporary
+fn sequence(procedure: &mut Procedure, copies: Vec<(Arg, Arg)>) -> Vec<(Arg, Arg)> {
+    let mut pending = copies.into_iter().filter(|(result, arg)| result != arg).collect::<Vec<_>>();
+    let mut ordered = vec![];
+    while !pending.is_empty() {
+        match pending.iter().position(|(result, _)| pending.iter().all(|(_, arg)| arg != result)) {
+            Some(free) => ordered.push(pending.remove(free)),
+            None => {
+                let saved = procedure.fresh_temp();
+                let result = pending[0].0.clone();
+                for (_, arg) in &mut pending {
+                    if *arg == result {
+                        *arg = saved.clone();
+                    }
+                }
+                ordered.push((saved, result));
+            }
+        }
+    }
+    ordered
+}
+
+/// Drops the jumps to the next block, the blocks left empty and the labels
+/// of blocks only entered from the one before, and numbers the temporaries
+/// again in the order they appear
+fn tidy(procedure: &mut Procedure) {
+    for index in 0..procedure.blocks.len() {
+        let next = procedure.blocks.get(index + 1).map(|block| block.label);
+        let quads = &mut procedure.blocks[index].quads;
+        if quads.last().is_some_and(|quad| quad.op == Op::Goto && quad.target() == next) {
+            quads.pop();
+        }
+    }
+    let targets = procedure.blocks.iter()
+        .flat_map(|block| block.quads.iter().filter_map(|quad| quad.target()))
+        .collect::<HashSet<_>>();
+    for index in (1..procedure.blocks.len()).rev() {
+        let falls = procedure.blocks[index - 1].quads.last().is_none_or(|quad| !quad.op.is_terminator());
+        if falls && !targets.contains(&procedure.blocks[index].label) {
+            let block = procedure.blocks.remove(index);
+            procedure.blocks[index - 1].quads.extend(block.quads);
+        }
+    }
+    let mut index = 0;
+    while index + 1 < procedure.blocks.len() {
+        if procedure.blocks[index].quads.is_empty() {
+            let (from, to) = (procedure.blocks[index].label, procedure.blocks[index + 1].label);
+            for quad in procedure.blocks.iter_mut().flat_map(|block| block.quads.iter_mut()) {
+                if quad.result == Arg::Label(from) {
+                    quad.result = Arg::Label(to);
+                }
+            }
+            procedure.blocks.remove(index);
+        } else {
+            index += 1;
+        }
+    }
+    let mut numbers = HashMap::new();
+    for block in &mut procedure.blocks {
+        for phi in &mut block.phis {
+            for field in std::iter::once(&mut phi.result).chain(phi.args.iter_mut().map(|(_, arg)| arg)) {
+                renumber(field, &mut numbers);
+            }
+        }
+        for quad in &mut block.quads {
+            for field in [&mut quad.arg1, &mut quad.arg2, &mut quad.result] {
+                renumber(field, &mut numbers);
+            }
+        }
+    }
+    procedure.temps = numbers.len();
+}
+
+fn renumber(field: &mut Arg, numbers: &mut HashMap<usize, usize>) {
+    if let Arg::Temp(temp) = field {
+        let count = numbers.len();
+        *temp = *numbers.entry(*temp).or_insert(count + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parse::procedure;
+
+    #[test]
+    fn variables_of_the_scope_are_renamed_and_merged() {
+        let mut converted = procedure("
+            procedure main
+            L1:
+              :=  0  s
+              :=  1  i
+            L2:
+              >  i  n.2  t1
+              if  t1  L4
+            L3:
+              +  s  i  t2
+              :=  t2  s
+              +  i  1  t3
+              :=  t3  i
+              goto  L2
+            L4:
+              param  s
+              call  write_integer  1
+              return
+        ");
+        to_ssa(&mut converted, &HashSet::new());
+        assert_eq!(converted.to_string(), procedure("
+            procedure main
+            L5:
+              :=  s  t1
+              :=  i  t2
+            L1:
+              :=  0  t3
+              :=  1  t4
+            L2:
+              phi  t3@L1,t9@L3  t5
+              phi  t4@L1,t11@L3  t6
+              >  t6  n.2  t7
+              if  t7  L4
+            L3:
+              +  t5  t6  t8
+              :=  t8  t9
+              +  t6  1  t10
+              :=  t10  t11
+              goto  L2
+            L4:
+              param  t5
+              call  write_integer  1
+              return
+        ").to_string());
+    }
+
+    #[test]
+    fn shared_variables_stay_in_memory() {
+        let mut converted = procedure("
+            procedure add.2
+            L1:
+              +  s.1  n  t1
+              :=  t1  s.1
+              &  k  t2
+              param  t2
+              call  clear.3  1
+              :=  k  p
+              return  p
+        ");
+        to_ssa(&mut converted, &HashSet::from([(2, "k".to_string())]));
+        assert_eq!(converted.to_string(), procedure("
+            procedure add.2
+            L2:
+              :=  n  t1
+              :=  p  t2
+            L1:
+              +  s.1  t1  t3
+              :=  t3  s.1
+              &  k  t4
+              param  t4
+              call  clear.3  1
+              :=  k  t5
+              return  t5
+        ").to_string());
+    }
+
+    #[test]
+    fn copies_are_ordered_on_split_edges() {
+        // the two temporaries are swapped each time round the loop
+        let mut converted = procedure("
+            procedure main
+            L1:
+              :=  1  t1
+              :=  2  t2
+            L2:
+              phi  t1@L1,t4@L2  t3
+              phi  t2@L1,t3@L2  t4
+              <  t3  t4  t5
+              if  t5  L2
+            L3:
+              param  t3
+              call  write_integer  1
+              return
+        ");
+        from_ssa(&mut converted);
+        assert_eq!(converted.to_string(), procedure("
+            procedure main
+            L1:
+              :=  1  t1
+              :=  2  t2
+              :=  t1  t3
+              :=  t2  t4
+            L2:
+              <  t3  t4  t5
+              if  t5  L4
+            L3:
+              param  t3
+              call  write_integer  1
+              return
+            L4:
+              :=  t3  t6
+              :=  t4  t3
+              :=  t6  t4
+              goto  L2
+        ").to_string());
+    }
+}
@@ -15,15 +15,30 @@ pub enum Token {
     #[regex("(?i)var")]
     Var,
 
+    #[regex("(?i)const")]
+    Const,
+
+    #[regex("(?i)procedure")]
+    Procedure,
+
+    #[regex("(?i)function")]
+    Function,
+
     #[regex("(?i)array")]
     Array,
 
+    #[regex("(?i)packed")]
+    Packed,
+
     #[regex("(?i)set")]
     Set,
 
     #[regex("(?i)record")]
     Record,
 
+    #[regex("(?i)file")]
+    File,
+
     #[regex("(?i)of", priority = 3)]
     Of,
 
@@ -33,6 +48,42 @@ pub enum Token {
     #[regex("(?i)end")]
     End,
 
+    #[regex("(?i)begin")]
+    Begin,
+
+    #[regex("(?i)if", priority = 3)]
+    If,
+
+    #[regex("(?i)then")]
+    Then,
+
+    #[regex("(?i)else")]
+    Else,
+
+    #[regex("(?i)while")]
+    While,
+
+    #[regex("(?i)do", priority = 3)]
+    Do,
+
+    #[regex("(?i)repeat")]
+    Repeat,
+
+    #[regex("(?i)until")]
+    Until,
+
+    #[regex("(?i)for")]
+    For,
+
+    #[regex("(?i)to", priority = 3)]
+    To,
+
+    #[regex("(?i)downto")]
+    Downto,
+
+    #[regex("(?i)case")]
+    Case,
+
     #[token("(")]
     LParen,
 
@@ -51,6 +102,9 @@ pub enum Token {
     #[token(":")]
     Colon,
 
+    #[token(":=")]
+    Assign,
+
     #[token(",")]
     Comma,
 
@@ -79,8 +133,12 @@ pub enum Token {
     Lte,
 
     #[token("!")]
+    #[regex("(?i)not")]
     Not,
 
+    #[token("^")]
+    Caret,
+
     #[token("+")]
     Plus,
 
@@ -105,15 +163,23 @@ pub enum Token {
     #[regex("(?i)or", priority = 3)]
     Or,
 
+    #[regex("(?i)nil")]
+    Nil,
+
     #[regex("(?i)true|false", parse_bool)]
     Bool(bool),
 
-    #[regex(r"'([^']|\\['nfrbv\\])'", parse_char)]
+    #[regex(r"'([^']|''|\\['nfrbv\\])'", parse_char)]
     Char(char),
 
-    #[regex(r"[0-9]+((\.[0-9]*)|([Ee][-+]?[0-9]+))", parse_real)]
-    #[regex(r"(?i)0H[0-9A-Fa-f]+\.(([0-9A-Fa-f]*)|([Ee][-+]?[0-9A-Fa-f]+))", parse_real_hex)]
-    #[regex(r"(?i)0B[01]+\.(([01]*)|([Ee][-+]?[01]+))", parse_real_bin)]
+    /// A literal of no characters or of more than one, which is a string
+    /// rather than a `Char`
+    #[regex(r"'(([^'\\\n]|''|\\['nfrbv\\])([^'\\\n]|''|\\['nfrbv\\])+)?'", parse_string)]
+    Str(String),
+
+    #[regex(r"[0-9]+((\.[0-9]+([Ee][-+]?[0-9]+)?)|([Ee][-+]?[0-9]+))", parse_real)]
+    #[regex(r"(?i)0H[0-9A-Fa-f]+\.[0-9A-Fa-f]+", parse_real_hex)]
+    #[regex(r"(?i)0B[01]+((\.[01]+([Ee][-+]?[01]+)?)|([Ee][-+]?[01]+))", parse_real_bin)]
     Real(f64),
 
     #[regex(r"[0-9]+", parse_int)]
@@ -121,16 +187,31 @@ pub enum Token {
     #[regex(r"(?i)0B[01]+", parse_int_bin)]
     Integer(i64),
 
-    #[regex(r"_?[A-Za-z][a-zA-Z0-9_]*[a-zA-Z0-9]",
+    #[regex(r"_?[A-Za-z]([a-zA-Z0-9_]*[a-zA-Z0-9])?",
     |lex| lex.slice().to_lowercase().to_string(),
     priority = 0)]
     Ident(String),
 
+    /// A compiler directive such as `{$R-}`, which the lexer sets aside
+    /// rather than handing to the parser
+    #[regex(r"\{\$[^}]*\}", priority = 10)]
+    Directive,
+
     #[error]
     #[regex(r"[ \t\f\n]+", logos::skip)]
+    #[regex(r"\{[^}]*\}", logos::skip)]
+    #[token("(*", comment)]
     Error
 }
 
+/// Skips a `(* ... *)` comment, which runs to the end of the source if it
+/// is never closed
+fn comment(lex: &mut logos::Lexer<Token>) -> logos::Skip {
+    let length = lex.remainder().find("*)").map_or(lex.remainder().len(), |end| end + 2);
+    lex.bump(length);
+    logos::Skip
+}
+
 fn parse_bool(lex: &mut logos::Lexer<Token>) -> bool {
     let slice = lex.slice();
     slice == "true"
@@ -140,7 +221,20 @@ fn parse_char(lex: &mut logos::Lexer<Token>) -> char {
     let slice = lex.slice();
     let mut chars = slice.chars();
     chars.next();
-    match chars.next().unwrap() {
+    unescape(&mut chars).unwrap()
+}
+
+fn parse_string(lex: &mut logos::Lexer<Token>) -> String {
+    let slice = lex.slice();
+    let mut chars = slice[1..slice.len() - 1].chars();
+    std::iter::from_fn(|| unescape(&mut chars)).collect()
+}
+
+/// The next character of a literal, reading a backslash and what follows it,
+/// or a doubled quote, as one character
+fn unescape(chars: &mut std::str::Chars) -> Option<char> {
+    Some(match chars.next()? {
+        '\'' => chars.next().unwrap(),
         '\\' => match chars.next().unwrap() {
             '\'' => '\'',
             '\\' => '\\',
@@ -152,6 +246,20 @@ fn parse_char(lex: &mut logos::Lexer<Token>) -> char {
             c => c
         }
         c => c,
+    })
+}
+
+/// Writes a character as a literal would spell it, without the quotes
+fn escape(c: char) -> String {
+    match c {
+        '\'' => "\\'".to_string(),
+        '\\' => "\\\\".to_string(),
+        '\n' => "\\n".to_string(),
+        '\x0c' => "\\f".to_string(),
+        '\r' => "\\r".to_string(),
+        '\x08' => "\\b".to_string(),
+        '\x0b' => "\\v".to_string(),
+        c => c.to_string()
     }
 }
 
@@ -172,7 +280,7 @@ fn parse_real_bin(lex: &mut logos::Lexer<Token>) -> Result<f64, ParseFloatError>
 
 fn parse_int(lex: &mut logos::Lexer<Token>) -> Result<i64, ParseIntError> {
     let slice = lex.slice();
-    i64::from_str_radix(slice, 10)
+    slice.parse()
 }
 
 fn parse_int_hex(lex: &mut logos::Lexer<Token>) -> Result<i64, ParseIntError> {
@@ -191,18 +299,36 @@ impl std::fmt::Display for Token {
             Token::Program => "PROGRAM",
             Token::Type => "TYPE",
             Token::Var => "VAR",
+            Token::Const => "CONST",
+            Token::Procedure => "PROCEDURE",
+            Token::Function => "FUNCTION",
             Token::Array => "ARRAY",
+            Token::Packed => "PACKED",
             Token::Set => "SET",
             Token::Record => "RECORD",
+            Token::File => "FILE",
             Token::Of => "OF",
             Token::In => "IN",
             Token::End => "END",
+            Token::Begin => "BEGIN",
+            Token::If => "IF",
+            Token::Then => "THEN",
+            Token::Else => "ELSE",
+            Token::While => "WHILE",
+            Token::Do => "DO",
+            Token::Repeat => "REPEAT",
+            Token::Until => "UNTIL",
+            Token::For => "FOR",
+            Token::To => "TO",
+            Token::Downto => "DOWNTO",
+            Token::Case => "CASE",
             Token::LParen => "(",
             Token::RParen => ")",
             Token::LBrack => "[",
             Token::RBrack => "]",
             Token::Semi => ";",
             Token::Colon => ":",
+            Token::Assign => ":=",
             Token::Comma => ",",
             Token::Spread => "..",
             Token::Dot => ".",
@@ -213,6 +339,7 @@ impl std::fmt::Display for Token {
             Token::Bge => ">=",
             Token::Lte => "<=",
             Token::Not => "!",
+            Token::Caret => "^",
             Token::Plus => "+",
             Token::Minus => "-",
             Token::Mul => "*",
@@ -221,23 +348,14 @@ impl std::fmt::Display for Token {
             Token::Mod => "MOD",
             Token::And => "AND",
             Token::Or => "OR",
+            Token::Nil => "NIL",
             Token::Bool(b) => if *b { "TRUE" } else { "FALSE" },
-            Token::Char(c) => {
-                let cstr = &c.to_string()[..];
-                return write!(f, "'{}'", match c {
-                    '\'' => "\\'",
-                    '\\' => "\\",
-                    '\n' => "\\n",
-                    '\x0c' => "\\f",
-                    '\r' => "\\r",
-                    '\x08' => "\\b",
-                    '\x0b' => "\\v",
-                    _ => cstr
-                })
-            },
+            Token::Char(c) => return write!(f, "'{}'", escape(*c)),
+            Token::Str(s) => return write!(f, "'{}'", s.chars().map(escape).collect::<String>()),
             Token::Real(r) => return write!(f, "{}", r),
             Token::Integer(i) => return write!(f, "{}", i),
             Token::Ident(str) => str,
+            Token::Directive => "DIRECTIVE",
             Token::Error => "<???>"
         })
     }
@@ -245,6 +363,9 @@ impl std::fmt::Display for Token {
 
 pub struct Lexer<'input> {
     pub source: &'input str,
+    /// The directives met so far, with where they start and what is between
+    /// `{$` and `}`
+    pub directives: Vec<(usize, String)>,
     logos: logos::Lexer<'input, Token>
 }
 
@@ -252,7 +373,8 @@ impl<'input> Lexer<'input> {
     pub fn new (source: &'input str) -> Self {
         Lexer {
             source,
-            logos: logos::Lexer::new(&source[..]),
+            directives: vec![],
+            logos: logos::Lexer::new(source),
         }
     }
 }
@@ -263,7 +385,12 @@ impl<'input> Iterator for Lexer<'input> {
     type Item = Spanned<usize, Token, ParsingError<Token>>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let token = self.logos.next()?;
+        let mut token = self.logos.next()?;
+        while token == Token::Directive {
+            let span = self.logos.span();
+            self.directives.push((span.start, self.source[span.start + 2..span.end - 1].to_string()));
+            token = self.logos.next()?;
+        }
         let span = self.logos.span();
         Some(Ok((
             span.start,
@@ -1,3 +1,6 @@
+// array dimensions are ranges, so single-range vectors are intentional
+#![allow(clippy::single_range_in_vec_init)]
+
 extern crate lazy_static;
 extern crate regex;
 extern crate lalrpop_util;
@@ -5,39 +8,59 @@ extern crate colored;
 
 use std::env::args;
 use std::fs::File;
-use std::io::{Read};
-use lalrpop_util::{lalrpop_mod, ParseError};
-use crate::lexer::{Lexer, Token};
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use lalrpop_util::lalrpop_mod;
 use crate::ast::program::Program;
-use crate::error::parse_error::ParsingError;
-use crate::error::{PositionBuilder, Printable, Throwable};
+use crate::error::{Printable, Throwable, ERROR};
+use crate::error::runtime_error::RuntimeErrorKind;
+use crate::interpreter::{limits, Interpreter};
+use crate::interpreter::debugger::{Debugger, SharedInput};
+use crate::ir::exec::Executor;
+use crate::options::{Command, Emit, Options};
+use crate::pcode::machine::Machine;
+use crate::grade::Setup;
 
 pub mod utils;
 pub mod ast;
 pub mod store;
 pub mod lexer;
 pub mod error;
+pub mod interpreter;
+pub mod pcode;
+pub mod ir;
+pub mod options;
+pub mod emit;
+pub mod grade;
 
-lalrpop_mod!(grammar);
+lalrpop_mod!(#[allow(clippy::all, unused)] grammar);
 
 fn main() {
-    let files = args()
-        .skip(1)
-        .map(|filename| File::open(filename))
-        .filter(|res| res.is_ok())
-        .map(|res| res.unwrap())
-        .filter_map(|mut file| {
+    let options = Options::parse(args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{} {}", &*ERROR, err);
+        std::process::exit(2);
+    });
+    if options.command == Command::Grade {
+        std::process::exit(grade(&options));
+    }
+    let (filenames, programs): (Vec<&String>, Vec<Program>) = options.files.iter()
+        .filter_map(|filename| File::open(filename).ok().map(|file| (filename, file)))
+        .filter_map(|(filename, mut file)| {
             let mut str = String::new();
             let read = file.read_to_string(&mut str).unwrap_or_else(|err| {
                 println!("{}", err.static_print());
                 0
             });
-            if read > 0 { Some(str) } else { None }
+            if read > 0 { Some((filename, str)) } else { None }
+        })
+        .map(|(filename, src)| {
+            let (mut program, errors) = Program::with_dialect(src, options.dialect);
+            program.checks = options.checks;
+            (filename, program, errors)
         })
-        .map(Program::new)
-        .filter_map(|(program, errors)| {
-            if errors.len() == 0 {
-                Some(program)
+        .filter_map(|(filename, program, errors)| {
+            let errors = program.diagnostics(errors);
+            if errors.is_empty() {
+                Some((filename, program))
             } else {
                 errors.into_iter()
                     .map(|err| Printable::new(err, &program.positioner))
@@ -45,7 +68,214 @@ fn main() {
                 None
             }
         })
-        .collect::<Vec<Program>>();
+        .filter(|(filename, program)| match check_files(program, &options) {
+            Ok(()) => true,
+            Err(err) => {
+                eprintln!("{} {}: {}", &*ERROR, filename, err);
+                false
+            }
+        })
+        .unzip();
+
+    if options.emit == Some(Emit::Runtime) {
+        print!("#define PAS_API\n{}", emit::c::RUNTIME);
+        return;
+    }
+    if options.emit == Some(Emit::Wasi) {
+        print!("{}", emit::wat::WASI);
+        return;
+    }
+    if options.opt_report {
+        for program in &programs {
+            let (_, report) = ir::opt::optimize(ir::lower::lower(program), options.optimization);
+            eprint!("{}", report);
+        }
+    }
+    if options.dump_quads {
+        for program in &programs {
+            print!("{}", ir::opt::optimize(ir::lower::lower(program), options.optimization).0);
+        }
+        if programs.is_empty() {
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(emit) = options.emit {
+        for program in &programs {
+            match emit {
+                Emit::PCode => print!("{}", pcode::compiler::compile(program)),
+                Emit::C => print!("{}", emit::c::emit(program, options.optimization)),
+                Emit::Llvm => print!("{}", emit::llvm::emit(program, options.optimization)),
+                Emit::Asm => print!("{}", emit::asm::emit(program, options.optimization)),
+                Emit::Wat => print!("{}", emit::wat::emit(program, options.optimization)),
+                Emit::Mips => print!("{}", emit::mips::emit(program, options.optimization)),
+                Emit::Runtime | Emit::Wasi => unreachable!("the runtimes are written without a program")
+            }
+        }
+        if programs.is_empty() {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // deeply recursive programs need more stack than the main thread has
+    let mut coverage = String::new();
+    let failed = std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .stack_size(limits::STACK)
+            .spawn_scoped(scope, || programs.iter().zip(&filenames).any(|(program, filename)| {
+                let stdin = std::io::stdin();
+                let stdout = std::io::stdout();
+                let (input, output) = match standard_files(&options) {
+                    Ok(files) => files,
+                    Err(err) => {
+                        eprintln!("{} {}", &*ERROR, err);
+                        return true;
+                    }
+                };
+                let input = input.map(|input| Box::new(input) as Box<dyn BufRead>);
+                let output = BufWriter::new(match output {
+                    Some(file) => Box::new(file) as Box<dyn Write>,
+                    None => Box::new(stdout.lock())
+                });
+                let outcome = if options.vm {
+                    let code = pcode::compiler::compile(program);
+                    let mut machine = Machine::new(&code, input.unwrap_or_else(|| Box::new(stdin.lock())), output);
+                    machine.limit(options.limits);
+                    machine.run()
+                } else if options.optimization > 0 {
+                    let (module, _) = ir::opt::optimize(ir::lower::lower(program), options.optimization);
+                    let input = input.unwrap_or_else(|| Box::new(stdin.lock()));
+                    let mut executor = Executor::new(program, &module, input, output);
+                    executor.limit(options.limits);
+                    executor.run()
+                } else if options.command == Command::Debug {
+                    // the commands and the input of the program are lines of the same stream
+                    let commands = SharedInput::new(stdin.lock());
+                    let input = input.unwrap_or_else(|| Box::new(BufReader::new(commands.clone())));
+                    let mut interpreter = Interpreter::new(program, input, output);
+                    interpreter.limit(options.limits);
+                    map_files(&mut interpreter, &options);
+                    interpreter.attach(Debugger::new(BufReader::new(commands), std::io::stdout()));
+                    interpreter.run()
+                } else {
+                    let input = input.unwrap_or_else(|| Box::new(stdin.lock()));
+                    let mut interpreter = Interpreter::new(program, input, output);
+                    interpreter.limit(options.limits);
+                    map_files(&mut interpreter, &options);
+                    if options.profile || options.coverage.is_some() {
+                        interpreter.profile();
+                    }
+                    let outcome = interpreter.run();
+                    if let Some(profiler) = interpreter.profiler() {
+                        if options.profile {
+                            eprint!("{}", profiler.report());
+                        }
+                        coverage.push_str(&profiler.lcov(filename));
+                    }
+                    outcome
+                };
+                match outcome {
+                    Err(err) if err.kind == RuntimeErrorKind::Quit => false,
+                    outcome => outcome
+                        .map_err(|err| eprintln!("{}", Printable::new(err, &program.positioner)))
+                        .is_err()
+                }
+            }))
+            .and_then(|handle| handle.join().map_err(|_| std::io::Error::other("the interpreter panicked")))
+            .unwrap_or_else(|err| {
+                eprintln!("{}", err.static_print());
+                true
+            })
+    });
+    if let Some(path) = &options.coverage {
+        if let Err(err) = std::fs::write(path, coverage) {
+            eprintln!("{} Could not write {}: {}", &*ERROR, path, err);
+            std::process::exit(1);
+        }
+    }
+    if failed || programs.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// Checks that the parameters of `program` and the files bound with
+/// `--bind` match, that a program using files or strings is interpreted
+/// and that one using wide sets is not made quadruples of, which hold sets
+/// of 256 bits
+fn check_files(program: &Program, options: &Options) -> Result<(), String> {
+    let quadruples = options.emit.is_some() || options.dump_quads || options.optimization > 0;
+    if program.uses_files() && (options.vm || quadruples) {
+        return Err("programs using files only run in the interpreter, without --vm, --emit, --dump-quads, -O1 or -O2"
+            .to_string());
+    }
+    if program.uses_strings() && (options.vm || quadruples) {
+        return Err("programs using strings only run in the interpreter, without --vm, --emit, --dump-quads, -O1 or -O2"
+            .to_string());
+    }
+    if program.uses_wide_sets() && quadruples {
+        return Err("programs using sets of ordinals outside 0..255 are only run, without --emit, --dump-quads, -O1 or -O2"
+            .to_string());
+    }
+    program.check_bindings(&options.bindings)
+}
+
+/// The files `input` and `output` are bound to, when they are not the
+/// standard input and output
+fn standard_files(options: &Options) -> std::io::Result<(Option<BufReader<File>>, Option<File>)> {
+    let path = |name: &str| options.bindings.iter()
+        .find(|(binding, _)| binding == name)
+        .map(|(_, path)| path);
+    let open = |path: &String| File::open(path)
+        .map_err(|err| std::io::Error::new(err.kind(), format!("Could not open {}: {}", path, err)));
+    let create = |path: &String| File::create(path)
+        .map_err(|err| std::io::Error::new(err.kind(), format!("Could not create {}: {}", path, err)));
+    Ok((path("input").map(open).transpose()?.map(BufReader::new), path("output").map(create).transpose()?))
+}
+
+/// Has the interpreter read and write the files bound to the parameters
+/// of the program other than `input` and `output`
+fn map_files(interpreter: &mut Interpreter, options: &Options) {
+    for (name, path) in &options.bindings {
+        if name != "input" && name != "output" {
+            interpreter.map(name, path.into());
+        }
+    }
+}
 
-    println!("Hello, world!");
-}
\ No newline at end of file
+/// Grades the program named first on the cases in the directory named
+/// second, writing the report and returning the exit code
+fn grade(options: &Options) -> i32 {
+    let (source, dir) = (&options.files[0], std::path::Path::new(&options.files[1]));
+    let read = std::fs::read_to_string(source).and_then(|src| Ok((src, grade::cases(dir)?)));
+    let (src, cases) = match read {
+        Ok(read) => read,
+        Err(err) => {
+            eprintln!("{} {}", &*ERROR, err);
+            return 2;
+        }
+    };
+    let setup = Setup {
+        rules: options.rules,
+        limits: options.limits,
+        checks: options.checks,
+        dialect: options.dialect,
+        vm: options.vm,
+        bindings: options.bindings.clone()
+    };
+    // deeply recursive programs need more stack than the main thread has
+    let report = std::thread::scope(|scope| std::thread::Builder::new()
+        .stack_size(limits::STACK)
+        .spawn_scoped(scope, || grade::grade(source, src, &cases, &setup))
+        .and_then(|handle| handle.join().map_err(|_| std::io::Error::other("the interpreter panicked"))));
+    match report {
+        Ok(report) => {
+            print!("{}", if options.json { report.json() } else { report.text() });
+            (report.passed() < report.verdicts.len()) as i32
+        },
+        Err(err) => {
+            eprintln!("{}", err.static_print());
+            2
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use std::str::FromStr;
+use std::time::Duration;
+use crate::ast::program::{Checks, Dialect};
+use crate::grade::{Rules, Whitespace};
+use crate::interpreter::limits::Limits;
+
+/// What the compiler writes to the standard output instead of running the
+/// program
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Emit {
+    /// The p-code listing
+    PCode,
+    /// A standalone C99 translation
+    C,
+    /// Textual LLVM IR, to be linked with the runtime
+    Llvm,
+    /// x86-64 assembly for the GNU assembler, to be linked with the runtime
+    Asm,
+    /// A WebAssembly text module, importing its input and output
+    Wat,
+    /// MIPS assembly for SPIM and MARS, with its runtime
+    Mips,
+    /// The C runtime the LLVM IR calls into
+    Runtime,
+    /// The WASI shim that runs WebAssembly modules outside of a browser
+    Wasi
+}
+
+impl Emit {
+    fn parse(name: &str) -> Option<Emit> {
+        match name {
+            "pcode" => Some(Emit::PCode),
+            "c" => Some(Emit::C),
+            "llvm" => Some(Emit::Llvm),
+            "asm" => Some(Emit::Asm),
+            "wat" => Some(Emit::Wat),
+            "mips" => Some(Emit::Mips),
+            "runtime" => Some(Emit::Runtime),
+            "wasi" => Some(Emit::Wasi),
+            _ => None
+        }
+    }
+}
+
+/// What the compiler is asked to do, named by the first argument
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum Command {
+    /// Checks the programs and runs, compiles or dumps them, as the flags say
+    #[default]
+    Run,
+    /// Runs the programs in the interpreter under a debugger taking commands
+    /// from the standard input
+    Debug,
+    /// Runs a program on the `*.in` files of a directory, comparing what it
+    /// writes with the `*.out` files
+    Grade
+}
+
+/// The command line: the source files, and the flags given among them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Options {
+    pub command: Command,
+    pub files: Vec<String>,
+    pub emit: Option<Emit>,
+    /// Runs the programs on the p-code machine rather than the interpreter
+    pub vm: bool,
+    /// Writes the quadruples of the programs instead of running them
+    pub dump_quads: bool,
+    /// How far the quadruples are optimized, from `-O0`, not at all, on. A
+    /// program run with `-O1` or `-O2` runs from its quadruples rather than
+    /// in the interpreter.
+    pub optimization: u8,
+    /// Writes what inlining and the elimination of tail calls did to the
+    /// error stream
+    pub opt_report: bool,
+    /// The run-time checks the programs start with, before their own
+    /// directives
+    pub checks: Checks,
+    /// The Pascal the programs are written in
+    pub dialect: Dialect,
+    /// Writes how often each subprogram was called and each line was run,
+    /// and how long the subprograms took, to the error stream
+    pub profile: bool,
+    /// Where to write which lines, subprograms and branches of the programs
+    /// ran, in the lcov format
+    pub coverage: Option<String>,
+    /// The files on disk the parameters of the programs stand for, given as
+    /// `--bind name=path`; `input` and `output` default to the standard ones
+    pub bindings: Vec<(String, String)>,
+    /// How much the programs may do before they are stopped, `--sandbox`
+    /// filling in the limits not given
+    pub limits: Limits,
+    /// How `grade` compares outputs
+    pub rules: Rules,
+    /// Writes the report of `grade` as JSON rather than as text
+    pub json: bool
+}
+
+/// The number that follows `flag`
+fn number<T: FromStr>(flag: &str, arg: Option<String>) -> Result<T, String> {
+    let arg = arg.ok_or_else(|| format!("{} needs a number", flag))?;
+    arg.parse().map_err(|_| format!("'{}' is not a valid number for {}", arg, flag))
+}
+
+impl Options {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter().peekable();
+        let mut sandbox = false;
+        match args.peek().map(String::as_str) {
+            Some("debug") => options.command = Command::Debug,
+            Some("grade") => options.command = Command::Grade,
+            _ => {}
+        }
+        if options.command != Command::Run {
+            args.next();
+        }
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--vm" => options.vm = true,
+                "--dump-quads" => options.dump_quads = true,
+                "--opt-report" => options.opt_report = true,
+                "--profile" => options.profile = true,
+                "--coverage" => options.coverage = Some(args.next().ok_or("--coverage needs a file to write")?),
+                "--sandbox" => sandbox = true,
+                "--bind" => {
+                    let binding = args.next().ok_or("--bind needs a parameter and a file, as in data=data.txt")?;
+                    let (name, path) = binding.split_once('=')
+                        .filter(|(name, path)| !name.is_empty() && !path.is_empty())
+                        .ok_or_else(|| format!("'{}' is not of the form parameter=file for --bind", binding))?;
+                    options.bindings.push((name.to_lowercase(), path.to_string()));
+                },
+                "--json" => options.json = true,
+                "--tolerance" => options.rules.tolerance = Some(number(&arg, args.next())?),
+                "--whitespace" => {
+                    let name = args.next().ok_or("--whitespace needs exact, lines or words")?;
+                    options.rules.whitespace = Whitespace::parse(&name)
+                        .ok_or_else(|| format!("Unknown whitespace rule '{}' for --whitespace", name))?;
+                },
+                "--max-steps" => options.limits.steps = Some(number(&arg, args.next())?),
+                "--max-heap" => options.limits.heap = Some(number(&arg, args.next())?),
+                "--max-depth" => options.limits.depth = Some(number(&arg, args.next())?),
+                "--max-output" => options.limits.output = Some(number(&arg, args.next())?),
+                "--time-limit" => {
+                    let seconds = number::<f64>(&arg, args.next())?;
+                    options.limits.time = Some(Duration::try_from_secs_f64(seconds)
+                        .map_err(|_| format!("'{}' is not a valid time for --time-limit", seconds))?);
+                },
+                "-O0" => options.optimization = 0,
+                "-O1" => options.optimization = 1,
+                "-O2" => options.optimization = 2,
+                "--checks" => {
+                    let switches = args.next().ok_or("--checks needs switches such as R+,Q-")?;
+                    if !options.checks.apply(&switches) {
+                        return Err(format!("Unknown checks '{}' for --checks", switches));
+                    }
+                },
+                "--dialect" => {
+                    let name = args.next().ok_or("--dialect needs iso or turbo")?;
+                    options.dialect = Dialect::parse(&name)
+                        .ok_or_else(|| format!("Unknown dialect '{}' for --dialect", name))?;
+                },
+                "--emit" => {
+                    let name = args.next().ok_or("--emit needs a format")?;
+                    options.emit = Some(Emit::parse(&name)
+                        .ok_or_else(|| format!("Unknown format '{}' for --emit", name))?);
+                },
+                flag if flag.starts_with('-') => return Err(format!("Unknown option '{}'", flag)),
+                _ => options.files.push(arg)
+            }
+        }
+        // the programs graded are not trusted
+        if sandbox || options.command == Command::Grade {
+            options.limits = options.limits.or(Limits::SANDBOX);
+        }
+        let compiled = options.vm || options.emit.is_some() || options.dump_quads;
+        if options.command == Command::Debug && compiled {
+            return Err("debug runs programs in the interpreter, without --vm, --emit or --dump-quads".to_string());
+        }
+        if options.command == Command::Grade {
+            if options.files.len() != 2 {
+                return Err("grade needs a source file and a directory of cases".to_string());
+            }
+            if options.emit.is_some() || options.dump_quads || options.profile || options.coverage.is_some() {
+                return Err("grade runs programs, without --emit, --dump-quads, --profile or --coverage".to_string());
+            }
+        }
+        if (options.profile || options.coverage.is_some()) && compiled {
+            return Err("--profile and --coverage run programs in the interpreter, without --vm, --emit or --dump-quads"
+                .to_string());
+        }
+        if !options.bindings.is_empty() && compiled {
+            return Err("--bind maps the files of programs run in the interpreter, without --vm, --emit or --dump-quads"
+                .to_string());
+        }
+        let standard = |(name, _): &(String, String)| name == "input" || name == "output";
+        if options.command == Command::Grade && options.bindings.iter().any(standard) {
+            return Err("grade takes input and output from its cases, so --bind only maps the other parameters".to_string());
+        }
+        // the translations, the dump and runs with -O1 or -O2 work from the
+        // quadruples, which are what is optimized
+        let translated = matches!(options.emit, Some(Emit::C | Emit::Llvm | Emit::Asm | Emit::Wat | Emit::Mips));
+        let run = options.command == Command::Run && options.emit.is_none() && !options.dump_quads && !options.vm;
+        if options.optimization > 0 && !(translated || options.dump_quads || run) {
+            return Err("-O1 and -O2 optimize the quadruples of --emit, --dump-quads and run, \
+                        and not --vm, --emit pcode, debug or grade".to_string());
+        }
+        if options.optimization > 0 && (options.profile || options.coverage.is_some()) {
+            return Err("--profile and --coverage run programs in the interpreter, without -O1 or -O2".to_string());
+        }
+        let quadruples = translated || options.dump_quads || (run && options.optimization > 0);
+        if options.opt_report && !quadruples {
+            return Err("--opt-report reports on the quadruples of --emit, --dump-quads and -O1 or -O2, \
+                        and nothing else".to_string());
+        }
+        if options.limits != Limits::default() && (options.emit.is_some() || options.dump_quads) {
+            return Err("limits apply to programs being run, without --emit or --dump-quads".to_string());
+        }
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Options, String> {
+        Options::parse(args.split_whitespace().map(str::to_string))
+    }
+
+    #[test]
+    fn flags_and_files() {
+        assert_eq!(parse("a.p --vm b.p"), Ok(Options {
+            command: Command::Run,
+            files: vec!["a.p".to_string(), "b.p".to_string()],
+            emit: None,
+            vm: true,
+            dump_quads: false,
+            optimization: 0,
+            opt_report: false,
+            checks: Checks::default(),
+            dialect: Dialect::Iso,
+            profile: false,
+            coverage: None,
+            bindings: vec![],
+            limits: Limits::default(),
+            rules: Rules::default(),
+            json: false
+        }));
+        assert_eq!(parse("--emit pcode a.p").map(|options| options.emit), Ok(Some(Emit::PCode)));
+        assert_eq!(parse("a.p --emit c").map(|options| options.emit), Ok(Some(Emit::C)));
+        assert_eq!(parse("a.p --emit llvm").map(|options| options.emit), Ok(Some(Emit::Llvm)));
+        assert_eq!(parse("--emit asm a.p").map(|options| options.emit), Ok(Some(Emit::Asm)));
+        assert_eq!(parse("a.p --emit wat").map(|options| options.emit), Ok(Some(Emit::Wat)));
+        assert_eq!(parse("--emit wasi").map(|options| options.emit), Ok(Some(Emit::Wasi)));
+        assert_eq!(parse("--emit mips a.p").map(|options| options.emit), Ok(Some(Emit::Mips)));
+        assert_eq!(parse("--dump-quads a.p").map(|options| options.dump_quads), Ok(true));
+        assert_eq!(parse("-O1 --emit mips a.p").map(|options| options.optimization), Ok(1));
+        assert_eq!(parse("-O1 a.p -O0").map(|options| options.optimization), Ok(0));
+        assert_eq!(parse("-O2 --dump-quads a.p").map(|options| options.optimization), Ok(2));
+        assert_eq!(parse("-O2 --opt-report --dump-quads a.p").map(|options| options.opt_report), Ok(true));
+        assert_eq!(parse("-O1 a.p").map(|options| options.optimization), Ok(1));
+        assert_eq!(parse("-O2 --emit c a.p").map(|options| options.optimization), Ok(2));
+        assert_eq!(parse("-O2 --bind input=in.txt a.p").map(|options| options.optimization), Ok(2));
+        assert!(parse("-O2 --vm a.p").is_err());
+        assert!(parse("-O1 --emit pcode a.p").is_err());
+        assert!(parse("debug -O1 a.p").is_err());
+        assert!(parse("-O1 --profile a.p").is_err());
+        assert_eq!(parse("--opt-report -O1 --emit mips a.p").map(|options| options.opt_report), Ok(true));
+        assert_eq!(parse("--opt-report -O2 a.p").map(|options| options.opt_report), Ok(true));
+        assert_eq!(parse("--opt-report --emit llvm a.p").map(|options| options.opt_report), Ok(true));
+        assert!(parse("--opt-report a.p").is_err());
+        assert!(parse("--opt-report -O2 --vm a.p").is_err());
+        assert_eq!(parse("--checks R-,Q+ a.p").map(|options| options.checks),
+                   Ok(Checks { range: false, overflow: true }));
+        assert_eq!(parse("--checks Q+ a.p").map(|options| options.checks),
+                   Ok(Checks { range: true, overflow: true }));
+        assert!(parse("--checks a.p").is_err());
+        assert_eq!(parse("--dialect turbo a.p").map(|options| options.dialect), Ok(Dialect::Turbo));
+        assert!(parse("--dialect delphi a.p").is_err());
+        assert_eq!(parse("debug --checks Q+ a.p").map(|options| (options.command, options.files)),
+                   Ok((Command::Debug, vec!["a.p".to_string()])));
+        assert_eq!(parse("a.p debug").map(|options| options.files.len()), Ok(2));
+        assert!(parse("debug --vm a.p").is_err());
+        assert_eq!(parse("--profile --coverage out.info a.p").map(|options| (options.profile, options.coverage, options.files)),
+                   Ok((true, Some("out.info".to_string()), vec!["a.p".to_string()])));
+        assert!(parse("a.p --coverage").is_err());
+        assert!(parse("--profile --vm a.p").is_err());
+        assert_eq!(parse("--max-steps 1000 --time-limit 0.5 a.p").map(|options| options.limits), Ok(Limits {
+            steps: Some(1000),
+            time: Some(Duration::from_millis(500)),
+            ..Limits::default()
+        }));
+        assert_eq!(parse("--sandbox --max-depth 50 --vm a.p").map(|options| options.limits),
+                   Ok(Limits { depth: Some(50), ..Limits::SANDBOX }));
+        assert!(parse("--max-heap lots a.p").is_err());
+        assert!(parse("--time-limit -1 a.p").is_err());
+        assert!(parse("--sandbox --emit c a.p").is_err());
+        let grade = parse("grade a.p cases --json --whitespace words --tolerance 1e-6").unwrap();
+        assert_eq!((grade.command, grade.files.len(), grade.json), (Command::Grade, 2, true));
+        assert_eq!(grade.rules, Rules { whitespace: Whitespace::Words, tolerance: Some(1e-6) });
+        assert_eq!(grade.limits, Limits::SANDBOX);
+        assert!(parse("grade a.p").is_err());
+        assert!(parse("grade a.p cases --whitespace none").is_err());
+        assert_eq!(parse("--bind Data=in.txt a.p --bind output=out.txt").map(|options| options.bindings),
+                   Ok(vec![("data".to_string(), "in.txt".to_string()), ("output".to_string(), "out.txt".to_string())]));
+        assert!(parse("--bind data a.p").is_err());
+        assert!(parse("--bind data= a.p").is_err());
+        assert!(parse("--bind data=in.txt --vm a.p").is_err());
+        assert!(parse("grade a.p cases --bind data=in.txt").is_ok());
+        assert!(parse("grade a.p cases --bind input=in.txt").is_err());
+        assert!(parse("--emit").is_err());
+        assert!(parse("--emit x86 a.p").is_err());
+        assert!(parse("--fast a.p").is_err());
+        assert!(parse("-O9 a.p").is_err());
+    }
+}
@@ -0,0 +1,797 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ops::Range;
+use crate::ast::expression::{ExBox, Expression, Node};
+use crate::ast::expression::operators::{self, Call, CompOp, LogicOp, ProdOp, SumOp};
+use crate::ast::expression::variables::VarRef;
+use crate::ast::program::{Program, ScopeId, Symbol};
+use crate::ast::standard::Builtin;
+use crate::ast::statement::{Direction, Statement, StatementKind};
+use crate::ast::subprogram::Subprogram;
+use crate::ast::types::Type;
+use crate::emit::{cardinality, limits};
+use crate::pcode::{Code, Format, Instr, Word, NIL};
+
+/// The words of an activation before its parameters: the static link, the
+/// dynamic link, the return address and the result of a function
+pub const HEADER: usize = 4;
+pub const RESULT: usize = 3;
+
+/// The number of words a value of type `ty` takes
+pub fn size(ty: &Type) -> usize {
+    match ty {
+        Type::ArrayOf(dims, element) => dims.iter()
+            .map(cardinality)
+            .fold(size(element), usize::saturating_mul),
+        Type::Record(record) => record.values().map(size).fold(0, usize::saturating_add),
+        _ => 1
+    }
+}
+
+/// The words a variable of type `ty` starts with
+pub fn template(ty: &Type) -> Vec<Word> {
+    match ty {
+        Type::Real => vec![Word::Real(0.0)],
+        Type::SetOf(_) => vec![Word::Set(BTreeSet::new())],
+        Type::Pointer(_) => vec![Word::Int(NIL)],
+        Type::Range(_, range) => vec![Word::Int(range.start as i64)],
+        Type::ArrayOf(dims, element) => std::iter::repeat_n(template(element), dims.iter().map(cardinality).product())
+            .flatten()
+            .collect(),
+        Type::Record(_) => ty.fields().into_iter()
+            .flat_map(|(_, ty)| template(ty))
+            .collect(),
+        _ => vec![Word::Int(0)]
+    }
+}
+
+/// Arrays and records, which are handled through their address
+fn is_block(ty: &Type) -> bool {
+    matches!(ty, Type::ArrayOf(..) | Type::Record(_))
+}
+
+fn format(ty: &Type) -> Format {
+    match ty.host() {
+        Type::Real => Format::Real,
+        Type::Char => Format::Char,
+        Type::Boolean => Format::Boolean,
+        _ => Format::Integer
+    }
+}
+
+fn dimension(array: &Type) -> (Type, Type) {
+    array.dimension().expect("indexed values are checked to be arrays")
+}
+
+/// Where the variables of a scope are in its activations
+#[derive(Debug, Default)]
+struct Layout {
+    level: usize,
+    offsets: HashMap<String, usize>,
+    by_ref: HashSet<String>,
+    /// The words the parameters take
+    params: usize,
+    /// The types of the locals, after the parameters, temporaries among
+    /// them
+    locals: Vec<Type>
+}
+
+/// How a name used as a variable is reached
+enum Resolved {
+    Slot { up: usize, offset: usize, by_ref: bool },
+    Result { up: usize }
+}
+
+/// Compiles a checked program to p-code
+pub struct Compiler<'a> {
+    program: &'a Program,
+    code: Code,
+    layouts: HashMap<ScopeId, Layout>,
+    entries: HashMap<ScopeId, usize>,
+    /// Calls waiting for the entry point of the subprogram they call
+    fixups: Vec<(usize, ScopeId)>,
+    scope: ScopeId,
+    range: Range<usize>
+}
+
+pub fn compile(program: &Program) -> Code {
+    let mut compiler = Compiler {
+        program,
+        code: Code::default(),
+        layouts: HashMap::new(),
+        entries: HashMap::new(),
+        fixups: vec![],
+        scope: Program::GLOBAL,
+        range: 0..0
+    };
+    compiler.compile();
+    compiler.code
+}
+
+impl<'a> Compiler<'a> {
+    fn compile(&mut self) {
+        let program = self.program;
+        let mut subprograms = program.scopes.iter()
+            .flat_map(|scope| scope.subprograms.values())
+            .collect::<Vec<&Subprogram>>();
+        subprograms.sort_by_key(|subprogram| subprogram.range.start);
+        self.layout(Program::GLOBAL, 0, None);
+        // scopes are created after the scope they are in
+        for scope in Program::GLOBAL + 1..program.scopes.len() {
+            let parent = program.scopes[scope].parent.expect("subprogram scopes have a parent");
+            let level = self.layouts[&parent].level + 1;
+            let subprogram = subprograms.iter()
+                .find(|subprogram| subprogram.scope == scope)
+                .copied();
+            self.layout(scope, level, subprogram);
+        }
+
+        let start = self.emit(Instr::Jump(0));
+        for subprogram in subprograms {
+            self.subprogram(subprogram);
+        }
+        self.code.labels.insert(self.code.instrs.len(), format!("program {}", program.name));
+        self.patch(start, self.code.instrs.len());
+        self.scope = Program::GLOBAL;
+        self.body(&program.body, Instr::Halt);
+        for (at, scope) in std::mem::take(&mut self.fixups) {
+            if let Instr::Call { target, .. } = &mut self.code.instrs[at] {
+                *target = self.entries[&scope];
+            }
+        }
+    }
+
+    fn layout(&mut self, scope: ScopeId, level: usize, subprogram: Option<&Subprogram>) {
+        let mut layout = Layout {
+            level,
+            ..Layout::default()
+        };
+        let mut offset = HEADER;
+        for param in subprogram.iter().flat_map(|subprogram| subprogram.params.iter()) {
+            layout.offsets.insert(param.name.clone(), offset);
+            if param.by_ref {
+                layout.by_ref.insert(param.name.clone());
+                offset += 1;
+            } else {
+                offset += size(&param.r#type);
+            }
+        }
+        layout.params = offset - HEADER;
+        let mut locals = self.program.scopes[scope].variables.iter()
+            .filter(|(name, _)| !layout.offsets.contains_key(*name))
+            .collect::<Vec<_>>();
+        locals.sort_by_key(|(name, _)| *name);
+        for (name, ty) in locals {
+            layout.offsets.insert(name.clone(), offset);
+            offset += size(ty);
+            layout.locals.push(ty.clone());
+        }
+        self.layouts.insert(scope, layout);
+    }
+
+    fn subprogram(&mut self, subprogram: &Subprogram) {
+        self.scope = subprogram.scope;
+        self.range = subprogram.range.clone();
+        let kind = if subprogram.is_function() { "function" } else { "procedure" };
+        self.code.labels.insert(self.code.instrs.len(), format!("{} {}", kind, subprogram.name));
+        self.entries.insert(subprogram.scope, self.code.instrs.len());
+        self.body(&subprogram.body, Instr::Return { function: subprogram.is_function() });
+    }
+
+    /// Compiles the statements of an activation between its entry and `exit`
+    fn body(&mut self, body: &[Statement], exit: Instr) {
+        let enter = self.emit(Instr::Enter(vec![]));
+        self.statements(body);
+        self.emit(exit);
+        // the locals are only known once the temporaries of the body are
+        self.code.instrs[enter] = Instr::Enter(self.layouts[&self.scope].locals.clone());
+    }
+
+    fn emit(&mut self, instr: Instr) -> usize {
+        self.code.instrs.push(instr);
+        self.code.ranges.push(self.range.clone());
+        self.code.instrs.len() - 1
+    }
+
+    fn here(&self) -> usize {
+        self.code.instrs.len()
+    }
+
+    /// Points the jump at `at` to `target`
+    fn patch(&mut self, at: usize, to: usize) {
+        match &mut self.code.instrs[at] {
+            Instr::Jump(target) | Instr::JumpFalse(target) | Instr::JumpTrue(target) => *target = to,
+            instr => unreachable!("{} is not a jump", instr)
+        }
+    }
+
+    /// Reserves a word of the current activation
+    fn temporary(&mut self) -> usize {
+        let layout = self.layouts.get_mut(&self.scope).expect("scopes are laid out");
+        layout.locals.push(Type::Integer);
+        HEADER + layout.params + layout.locals.iter().map(size).sum::<usize>() - 1
+    }
+
+    /// The number of static links from the current scope to `scope`
+    fn up(&self, scope: ScopeId) -> usize {
+        self.layouts[&self.scope].level - self.layouts[&scope].level
+    }
+
+    fn resolve(&self, variable: &VarRef) -> Option<Resolved> {
+        match variable {
+            VarRef::Immediate(name, scope) => Some(match self.program.lookup(*scope, name) {
+                Some((declared, Symbol::Variable(_))) => {
+                    let layout = &self.layouts[&declared];
+                    Resolved::Slot {
+                        up: self.up(declared),
+                        offset: layout.offsets[name],
+                        by_ref: layout.by_ref.contains(name)
+                    }
+                },
+                Some((_, Symbol::Subprogram(subprogram))) => Resolved::Result { up: self.up(subprogram.scope) },
+                _ => unreachable!("'{}' is checked to be a variable", name)
+            }),
+            _ => None
+        }
+    }
+
+    /// Whether a name is a constant, which has no address to push
+    fn is_constant(&self, variable: &VarRef) -> bool {
+        match variable {
+            VarRef::Immediate(name, scope) => {
+                matches!(self.program.lookup(*scope, name), Some((_, Symbol::Constant(_))))
+            },
+            _ => false
+        }
+    }
+
+    /// Pushes the address of a variable
+    fn address(&mut self, variable: &VarRef) {
+        match variable {
+            VarRef::Immediate(..) => {
+                self.emit(match self.resolve(variable) {
+                    Some(Resolved::Slot { up, offset, by_ref: true }) => Instr::Load(up, offset),
+                    Some(Resolved::Slot { up, offset, .. }) => Instr::Address(up, offset),
+                    Some(Resolved::Result { up }) => Instr::Address(up, RESULT),
+                    None => unreachable!("names are resolved")
+                });
+            },
+            VarRef::Field(record, field) => {
+                self.address(record);
+                let offset = record.get_type(self.program).fields().into_iter()
+                    .take_while(|(name, _)| *name != field)
+                    .map(|(_, ty)| size(ty))
+                    .sum();
+                self.emit(Instr::Offset(offset));
+            },
+            VarRef::Index(array, indices) => {
+                self.address(array);
+                let mut ty = array.get_type(self.program);
+                for index in indices {
+                    let (dim, element) = dimension(&ty);
+                    self.expr(index);
+                    let (low, high) = limits(&dim);
+                    let outer = std::mem::replace(&mut self.range, index.range.clone());
+                    self.emit(Instr::Index { low, high, size: size(&element) });
+                    self.range = outer;
+                    ty = element;
+                }
+            },
+            VarRef::Deref(pointer) => {
+                self.load(pointer);
+                self.emit(Instr::Deref);
+            }
+        }
+    }
+
+    /// Pushes the value of a variable of a simple type
+    fn load(&mut self, variable: &VarRef) {
+        match self.resolve(variable) {
+            Some(Resolved::Slot { up, offset, by_ref: false }) => {
+                self.emit(Instr::Load(up, offset));
+            },
+            Some(Resolved::Result { up }) => {
+                self.emit(Instr::Load(up, RESULT));
+            },
+            _ => {
+                self.address(variable);
+                self.emit(Instr::LoadIndirect);
+            }
+        }
+    }
+
+    /// Stores the value `value` pushes into a variable of a simple type
+    fn store(&mut self, variable: &VarRef, value: impl FnOnce(&mut Self)) {
+        match self.resolve(variable) {
+            Some(Resolved::Slot { up, offset, by_ref: false }) => {
+                value(self);
+                self.emit(Instr::Store(up, offset));
+            },
+            Some(Resolved::Result { up }) => {
+                value(self);
+                self.emit(Instr::Store(up, RESULT));
+            },
+            _ => {
+                self.address(variable);
+                value(self);
+                self.emit(Instr::StoreIndirect);
+            }
+        }
+    }
+
+    /// Checks that the value of `value`, on the top of the stack, is one of
+    /// the subrange `ty` it is assigned to
+    fn narrow(&mut self, value: &ExBox, ty: &Type) {
+        if !self.program.checks_at(value.range.start).range {
+            return;
+        }
+        if let Some(bounds) = ty.narrowing(&value.get_type(self.program)) {
+            let outer = std::mem::replace(&mut self.range, value.range.clone());
+            self.emit(Instr::Check { low: bounds.start as i64, high: bounds.end as i64, ty: ty.clone() });
+            self.range = outer;
+        }
+    }
+
+    /// The integer operation `instr`, made to stop the program on overflow
+    /// where the expression being compiled is to be checked for it
+    fn arithmetic(&self, instr: Instr) -> Instr {
+        if !self.program.checks_at(self.range.start).overflow {
+            return instr;
+        }
+        match instr {
+            Instr::Add => Instr::AddChecked,
+            Instr::Sub => Instr::SubChecked,
+            Instr::Mul => Instr::MulChecked,
+            Instr::Div => Instr::DivChecked,
+            Instr::Neg => Instr::NegChecked,
+            instr => instr
+        }
+    }
+
+    /// The type of the variable an assignment stores into
+    fn target_type(&self, target: &VarRef) -> Type {
+        match (target, self.program.lookup(target.scope(), target.name())) {
+            (VarRef::Immediate(..), Some((_, Symbol::Subprogram(subprogram)))) =>
+                subprogram.result.clone().unwrap_or(Type::Invalid),
+            _ => target.get_type(self.program)
+        }
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        self.range = statement.range.clone();
+        match &statement.kind {
+            StatementKind::Empty => {},
+            StatementKind::Assignment { target, target_range, value } => {
+                // what goes wrong in following the target points at it
+                self.range = target_range.clone();
+                let ty = self.target_type(target);
+                if is_block(&ty) {
+                    self.address(target);
+                    self.value(value);
+                    self.emit(Instr::CopyBlock(size(&ty)));
+                } else {
+                    self.store(target, |compiler| {
+                        compiler.operand(value, ty == Type::Real);
+                        compiler.narrow(value, &ty);
+                    });
+                }
+            },
+            StatementKind::Call(call) => {
+                let function = self.call(call);
+                self.range = statement.range.clone();
+                if function {
+                    self.emit(Instr::Pop);
+                }
+            },
+            StatementKind::Compound(body) => self.statements(body),
+            StatementKind::If { condition, then, otherwise } => {
+                self.expr(condition);
+                let skip = self.emit(Instr::JumpFalse(0));
+                self.statement(then);
+                match otherwise {
+                    Some(otherwise) => {
+                        let end = self.emit(Instr::Jump(0));
+                        self.patch(skip, self.here());
+                        self.statement(otherwise);
+                        self.patch(end, self.here());
+                    },
+                    None => self.patch(skip, self.here())
+                }
+            },
+            StatementKind::While { condition, body } => {
+                let start = self.here();
+                self.expr(condition);
+                let exit = self.emit(Instr::JumpFalse(0));
+                self.statement(body);
+                self.emit(Instr::Jump(start));
+                self.patch(exit, self.here());
+            },
+            StatementKind::Repeat { body, condition } => {
+                let start = self.here();
+                self.statements(body);
+                self.expr(condition);
+                self.emit(Instr::JumpFalse(start));
+            },
+            StatementKind::For { variable, from, direction, to, body } => {
+                // the limit is computed once, and the variable is never
+                // stepped past it, so that it cannot overflow its type
+                let limit = self.temporary();
+                let (past, step) = match direction {
+                    Direction::To => (Instr::Le, Instr::Add),
+                    Direction::Downto => (Instr::Ge, Instr::Sub)
+                };
+                self.store(variable, |compiler| compiler.expr(from));
+                self.expr(to);
+                self.emit(Instr::Store(0, limit));
+                self.load(variable);
+                self.emit(Instr::Load(0, limit));
+                self.emit(past);
+                let skip = self.emit(Instr::JumpFalse(0));
+                let start = self.here();
+                self.statement(body);
+                self.range = statement.range.clone();
+                self.load(variable);
+                self.emit(Instr::Load(0, limit));
+                self.emit(Instr::Eq);
+                let exit = self.emit(Instr::JumpTrue(0));
+                self.store(variable, |compiler| {
+                    compiler.load(variable);
+                    compiler.emit(Instr::Lit(1));
+                    compiler.emit(step);
+                });
+                self.emit(Instr::Jump(start));
+                self.patch(skip, self.here());
+                self.patch(exit, self.here());
+            },
+            StatementKind::Case { selector, arms } => {
+                self.expr(selector);
+                let mut entries = vec![];
+                for arm in arms {
+                    let mut jumps = vec![];
+                    for label in &arm.labels {
+                        let value = label.as_number(self.program).expect("labels are checked to be constant");
+                        self.emit(Instr::Dup);
+                        self.emit(Instr::Lit(value));
+                        self.emit(Instr::Eq);
+                        jumps.push(self.emit(Instr::JumpTrue(0)));
+                    }
+                    entries.push(jumps);
+                }
+                self.range = selector.range.clone();
+                self.emit(Instr::NoCase(selector.get_type(self.program)));
+                let mut exits = vec![];
+                for (arm, jumps) in arms.iter().zip(entries) {
+                    for jump in jumps {
+                        self.patch(jump, self.here());
+                    }
+                    self.emit(Instr::Pop);
+                    self.statement(&arm.body);
+                    exits.push(self.emit(Instr::Jump(0)));
+                }
+                for exit in exits {
+                    self.patch(exit, self.here());
+                }
+            }
+        }
+    }
+
+    /// Pushes the value of an expression, converted to a real if `real`
+    fn operand(&mut self, expr: &ExBox, real: bool) {
+        self.expr(expr);
+        if real && expr.get_type(self.program).is_integer() {
+            self.emit(Instr::Float);
+        }
+    }
+
+    /// Pushes the value of an expression, or the address of an array or
+    /// record
+    fn value(&mut self, expr: &ExBox) {
+        match expr.as_variable() {
+            Some(variable) if is_block(&expr.get_type(self.program)) && !self.is_constant(variable) => {
+                self.address(variable)
+            },
+            _ => self.expr(expr)
+        }
+    }
+
+    fn expr(&mut self, expr: &ExBox) {
+        let outer = std::mem::replace(&mut self.range, expr.range.clone());
+        let program = self.program;
+        match expr.node() {
+            Node::Integer(value) => {
+                self.emit(Instr::Lit(value));
+            },
+            Node::Real(value) => {
+                self.emit(Instr::LitReal(value));
+            },
+            Node::Char(value) => {
+                self.emit(Instr::Lit(value as i64));
+            },
+            Node::Str(value) => {
+                self.emit(Instr::LitString(value.to_string()));
+            },
+            Node::Boolean(value) => {
+                self.emit(Instr::Lit(value as i64));
+            },
+            Node::Enumerator(literal) => {
+                self.emit(Instr::Lit(literal.ordinal));
+            },
+            Node::Nil => {
+                self.emit(Instr::Lit(NIL));
+            },
+            Node::Variable(variable) => match variable {
+                VarRef::Immediate(name, scope) => match program.lookup(*scope, name) {
+                    Some((_, Symbol::Constant(constant))) => self.expr(constant),
+                    Some((_, Symbol::Subprogram(subprogram))) => self.invoke(subprogram, &[]),
+                    Some(_) if is_block(&variable.get_type(program)) => self.address(variable),
+                    Some(_) => self.load(variable),
+                    None => {
+                        self.builtin(Builtin::lookup(name).expect("names are checked"), &[], &[]);
+                    }
+                },
+                _ if is_block(&variable.get_type(program)) => self.address(variable),
+                _ => self.load(variable)
+            },
+            Node::Call(call) => {
+                self.call(call);
+            },
+            Node::Set(members) => {
+                self.emit(Instr::EmptySet);
+                for member in members {
+                    self.expr(member);
+                    self.emit(Instr::Include);
+                }
+            },
+            Node::In(op) => {
+                self.expr(&op.sample);
+                self.expr(&op.set);
+                self.emit(Instr::In);
+            },
+            Node::Comparison(op) => {
+                let left = op.left.get_type(program);
+                let right = op.right.get_type(program);
+                if left.is_string() {
+                    self.value(&op.left);
+                    self.value(&op.right);
+                    self.emit(Instr::CompareBlock(size(&left)));
+                    self.emit(Instr::Lit(0));
+                } else {
+                    let real = left == Type::Real || right == Type::Real;
+                    self.operand(&op.left, real);
+                    self.operand(&op.right, real);
+                }
+                self.emit(match op.op {
+                    CompOp::Eq => Instr::Eq,
+                    CompOp::Neq => Instr::Neq,
+                    CompOp::Lt => Instr::Lt,
+                    CompOp::Bg => Instr::Gt,
+                    CompOp::Lte => Instr::Le,
+                    CompOp::Bge => Instr::Ge
+                });
+            },
+            Node::Sum(op) => {
+                let ty = expr.get_type(program);
+                self.operand(&op.left, ty == Type::Real);
+                self.operand(&op.right, ty == Type::Real);
+                self.emit(match (&op.op, ty) {
+                    (SumOp::Add, Type::SetOf(_)) => Instr::Union,
+                    (SumOp::Sub, Type::SetOf(_)) => Instr::Difference,
+                    (SumOp::Add, Type::Real) => Instr::AddReal,
+                    (SumOp::Sub, Type::Real) => Instr::SubReal,
+                    (SumOp::Add, _) => self.arithmetic(Instr::Add),
+                    (SumOp::Sub, _) => self.arithmetic(Instr::Sub)
+                });
+            },
+            Node::Signed(op) => {
+                self.expr(&op.operand);
+                match (&op.op, op.operand.get_type(program)) {
+                    (SumOp::Sub, Type::Real) => self.emit(Instr::NegReal),
+                    (SumOp::Sub, _) => self.emit(self.arithmetic(Instr::Neg)),
+                    _ => 0
+                };
+            },
+            Node::Product(op) => {
+                let ty = expr.get_type(program);
+                self.operand(&op.left, ty == Type::Real);
+                self.operand(&op.right, ty == Type::Real);
+                self.emit(match (&op.op, ty) {
+                    (ProdOp::Mul, Type::SetOf(_)) => Instr::Intersection,
+                    (ProdOp::Mul, Type::Real) => Instr::MulReal,
+                    (ProdOp::Mul, _) => self.arithmetic(Instr::Mul),
+                    (ProdOp::RDiv, _) => Instr::DivReal,
+                    (ProdOp::Div, _) => self.arithmetic(Instr::Div),
+                    (ProdOp::Mod, _) => Instr::Mod
+                });
+            },
+            Node::Not(op) => {
+                self.expr(&op.0);
+                self.emit(Instr::Not);
+            },
+            Node::Logic(op) => {
+                // the right operand is only evaluated when it decides the result
+                self.expr(&op.left);
+                self.emit(Instr::Dup);
+                let skip = self.emit(match op.op {
+                    LogicOp::And => Instr::JumpFalse(0),
+                    LogicOp::Or => Instr::JumpTrue(0)
+                });
+                self.emit(Instr::Pop);
+                self.expr(&op.right);
+                self.patch(skip, self.here());
+            },
+            Node::Invalid => unreachable!("invalid expressions are not compiled")
+        }
+        self.range = outer;
+    }
+
+    /// Compiles a call, returning whether it leaves a result on the stack
+    fn call(&mut self, call: &Call) -> bool {
+        match self.program.lookup(call.scope, &call.name) {
+            Some((_, Symbol::Subprogram(subprogram))) => {
+                self.invoke(subprogram, &call.args);
+                subprogram.is_function()
+            },
+            _ => self.builtin(Builtin::lookup(&call.name).expect("calls are checked"), &call.args, &call.formats)
+        }
+    }
+
+    fn invoke(&mut self, subprogram: &Subprogram, args: &[ExBox]) {
+        self.emit(Instr::Mark);
+        let mut params = 0;
+        for (param, arg) in subprogram.params.iter().zip(args) {
+            if param.by_ref {
+                self.address(arg.as_variable().expect("var arguments are checked to be variables"));
+                params += 1;
+            } else if is_block(&param.r#type) {
+                self.value(arg);
+                self.emit(Instr::LoadBlock(size(&param.r#type)));
+                params += size(&param.r#type);
+            } else {
+                self.operand(arg, param.r#type == Type::Real);
+                params += 1;
+            }
+        }
+        let at = self.emit(Instr::Call {
+            up: self.layouts[&self.scope].level + 1 - self.layouts[&subprogram.scope].level,
+            target: 0,
+            params
+        });
+        self.fixups.push((at, subprogram.scope));
+    }
+
+    /// Compiles a call to a standard subprogram, returning whether it leaves
+    /// a result on the stack
+    fn builtin(&mut self, builtin: Builtin, args: &[ExBox], formats: &[Option<operators::Format>]) -> bool {
+        let program = self.program;
+        let variable = |index: usize| args[index].as_variable().expect("the argument is checked to be a variable");
+        match builtin {
+            // the standard functions wrap around, so checked ones are spelled out
+            Builtin::Abs | Builtin::Sqr if program.checks_at(self.range.start).overflow
+                && args[0].get_type(program).host() == Type::Integer => {
+                self.expr(&args[0]);
+                self.emit(Instr::Dup);
+                if builtin == Builtin::Sqr {
+                    self.emit(Instr::MulChecked);
+                } else {
+                    self.emit(Instr::Lit(0));
+                    self.emit(Instr::Lt);
+                    let skip = self.emit(Instr::JumpFalse(0));
+                    self.emit(Instr::NegChecked);
+                    self.patch(skip, self.here());
+                }
+            },
+            Builtin::Abs | Builtin::Sqr | Builtin::Odd => {
+                self.expr(&args[0]);
+                self.emit(Instr::Std(builtin));
+            },
+            Builtin::Sqrt | Builtin::Sin | Builtin::Cos | Builtin::Arctan | Builtin::Exp
+            | Builtin::Ln | Builtin::Trunc | Builtin::Round => {
+                self.operand(&args[0], true);
+                self.emit(Instr::Std(builtin));
+            },
+            Builtin::Ord => self.expr(&args[0]),
+            Builtin::Chr => {
+                self.expr(&args[0]);
+                if program.checks_at(self.range.start).range {
+                    self.emit(Instr::Check { low: 0, high: 255, ty: Type::Char });
+                }
+            },
+            Builtin::Succ | Builtin::Pred => {
+                self.expr(&args[0]);
+                self.emit(Instr::Lit(1));
+                self.emit(self.arithmetic(if builtin == Builtin::Succ { Instr::Add } else { Instr::Sub }));
+                let ty = args[0].get_type(program).host();
+                match ty.bounds() {
+                    Some(bounds) if program.checks_at(self.range.start).range =>
+                        self.emit(Instr::Check { low: bounds.start as i64, high: bounds.end as i64, ty }),
+                    _ => 0
+                };
+            },
+            Builtin::Eof | Builtin::Eoln => {
+                self.emit(Instr::Std(builtin));
+            },
+            Builtin::Read | Builtin::Readln => {
+                for (index, arg) in args.iter().enumerate() {
+                    self.range = arg.range.clone();
+                    self.address(variable(index));
+                    self.emit(Instr::Read(format(&arg.get_type(program))));
+                }
+                if builtin == Builtin::Readln {
+                    self.emit(Instr::ReadLine);
+                }
+            },
+            Builtin::Write | Builtin::Writeln => {
+                for (index, arg) in args.iter().enumerate() {
+                    let ty = arg.get_type(program);
+                    if ty.is_string() {
+                        self.value(arg);
+                    } else {
+                        self.expr(arg);
+                    }
+                    let (width, decimals) = match formats.get(index) {
+                        Some(Some(operators::Format { width, decimals })) => (width, decimals),
+                        _ => {
+                            self.emit(if ty.is_string() { Instr::WriteString(size(&ty)) } else { Instr::Write(format(&ty)) });
+                            continue;
+                        }
+                    };
+                    // a field is reported with the value it is the field of
+                    let range = arg.range.start..decimals.as_ref().unwrap_or(width).range.end;
+                    for field in std::iter::once(width).chain(decimals) {
+                        self.expr(field);
+                        let outer = std::mem::replace(&mut self.range, range.clone());
+                        self.emit(Instr::Field);
+                        self.range = outer;
+                    }
+                    self.emit(if ty.is_string() {
+                        Instr::WriteStringWidth(size(&ty))
+                    } else if decimals.is_some() {
+                        Instr::WriteFixed
+                    } else {
+                        Instr::WriteWidth(format(&ty))
+                    });
+                }
+                if builtin == Builtin::Writeln {
+                    self.emit(Instr::WriteLine);
+                }
+            },
+            Builtin::New => {
+                self.address(variable(0));
+                self.emit(Instr::New(program.pointee(&args[0].get_type(program))));
+            },
+            Builtin::Dispose => {
+                self.expr(&args[0]);
+                self.emit(Instr::Dispose);
+            },
+            Builtin::Pack | Builtin::Unpack => {
+                // pack(a, i, z) and unpack(z, a, i) copy between `a` from `i` on and all of `z`
+                let (array, index, packed) = if builtin == Builtin::Pack { (0, 1, 2) } else { (1, 2, 0) };
+                let (dim, element) = dimension(&args[array].get_type(program));
+                let count = cardinality(&dimension(&args[packed].get_type(program)).0);
+                let (low, high) = limits(&dim);
+                let element = |compiler: &mut Self| {
+                    compiler.address(variable(array));
+                    compiler.expr(&args[index]);
+                    compiler.emit(Instr::Index { low, high: high + 1 - count as i64, size: size(&element) });
+                };
+                if builtin == Builtin::Pack {
+                    self.address(variable(packed));
+                    element(self);
+                } else {
+                    element(self);
+                    self.address(variable(packed));
+                }
+                self.emit(Instr::CopyBlock(size(&args[packed].get_type(program))));
+            },
+            Builtin::Reset | Builtin::Rewrite | Builtin::Get | Builtin::Put =>
+                unreachable!("programs using files are only interpreted"),
+            builtin => unreachable!("programs using {} are only interpreted", builtin.name())
+        }
+        builtin.is_function()
+    }
+}
@@ -0,0 +1,507 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use crate::ast::standard::Builtin;
+use crate::error::runtime_error::{RuntimeError, RuntimeErrorKind};
+use crate::interpreter::format;
+use crate::interpreter::io::Input;
+use crate::interpreter::limits::{Limits, Meter};
+use crate::pcode::{Code, Format, Instr, Word, NIL};
+use crate::pcode::compiler::{size, template, HEADER, RESULT};
+
+/// Addresses from this one on are on the heap rather than on the stack
+pub const HEAP_BASE: usize = 1 << 48;
+
+type Fallible<T> = Result<T, RuntimeErrorKind>;
+
+/// Runs p-code, keeping the activations on a stack of words and the
+/// variables allocated by `new` on a heap of words
+pub struct Machine<'a> {
+    code: &'a Code,
+    stack: Vec<Word>,
+    heap: Vec<Word>,
+    /// The addresses of the heap variables not disposed of yet, with the
+    /// words they take
+    live: HashMap<usize, usize>,
+    /// Where the string literal of each instruction that has run is on the heap
+    literals: HashMap<usize, usize>,
+    /// The entry points of the running activations, the innermost last
+    entries: Vec<usize>,
+    /// The base of the running activation
+    base: usize,
+    pc: usize,
+    input: Input<'a>,
+    output: Box<dyn Write + 'a>,
+    meter: Meter
+}
+
+fn int(word: Word) -> i64 {
+    match word {
+        Word::Int(value) => value,
+        word => unreachable!("{} is not an integer", word)
+    }
+}
+
+/// The running activation, as its entry point is labeled
+fn activation(code: &Code, entries: &[usize]) -> String {
+    let entry = entries.last().expect("an activation is running");
+    code.labels.get(entry).cloned().unwrap_or_default()
+}
+
+fn real(word: Word) -> f64 {
+    match word {
+        Word::Real(value) => value,
+        Word::Int(value) => value as f64,
+        word => unreachable!("{} is not a number", word)
+    }
+}
+
+impl<'a> Machine<'a> {
+    pub fn new(code: &'a Code, input: impl BufRead + 'a, output: impl Write + 'a) -> Machine<'a> {
+        Machine {
+            code,
+            stack: vec![],
+            heap: vec![],
+            live: HashMap::new(),
+            literals: HashMap::new(),
+            entries: vec![],
+            base: 0,
+            pc: 0,
+            input: Input::new(input),
+            output: Box::new(output),
+            meter: Meter::new(Limits::default())
+        }
+    }
+
+    /// Stops the program once it goes past one of `limits`
+    pub fn limit(&mut self, limits: Limits) {
+        self.meter = Meter::new(limits);
+    }
+
+    pub fn run(&mut self) -> Result<(), RuntimeError> {
+        // the main program runs in an activation with no links
+        self.stack = vec![Word::Int(0); HEADER];
+        self.base = 0;
+        self.pc = 0;
+        // the main program is compiled after the subprograms, so its entry comes last
+        self.entries = vec![self.code.labels.keys().last().copied().unwrap_or(0)];
+        let outcome = loop {
+            match self.step() {
+                Ok(true) => {},
+                Ok(false) => break Ok(()),
+                Err(kind) => break Err(RuntimeError::new(self.code.ranges[self.pc].clone(), kind))
+            }
+        };
+        self.stack.clear();
+        let flushed = self.output.flush()
+            .map_err(|err| RuntimeError::new(0..0, RuntimeErrorKind::Output(err.to_string())));
+        outcome.and(flushed)
+    }
+
+    fn push(&mut self, word: Word) {
+        self.stack.push(word);
+    }
+
+    fn pop(&mut self) -> Word {
+        self.stack.pop().expect("the stack holds the operands of every instruction")
+    }
+
+    fn pop_int(&mut self) -> i64 {
+        int(self.pop())
+    }
+
+    fn pop_address(&mut self) -> usize {
+        self.pop_int() as usize
+    }
+
+    /// The base of the activation `up` static links out of the running one
+    fn frame(&self, up: usize) -> usize {
+        (0..up).fold(self.base, |base, _| int(self.stack[base].clone()) as usize)
+    }
+
+    fn word(&mut self, address: usize) -> &mut Word {
+        if address >= HEAP_BASE {
+            &mut self.heap[address - HEAP_BASE]
+        } else {
+            &mut self.stack[address]
+        }
+    }
+
+    fn words(&mut self, address: usize, count: usize) -> Vec<Word> {
+        (address..address + count).map(|address| self.word(address).clone()).collect()
+    }
+
+    /// The `count` characters from `address`
+    fn string(&mut self, address: usize, count: usize) -> String {
+        self.words(address, count).into_iter()
+            .map(|word| char::from_u32(int(word) as u32).unwrap_or('\0'))
+            .collect()
+    }
+
+    /// Checks that a pointer points to a variable that was not disposed of
+    fn check_pointer(&self, pointer: i64) -> Fallible<()> {
+        if pointer == NIL {
+            Err(RuntimeErrorKind::NilPointer)
+        } else if !self.live.contains_key(&(pointer as usize)) {
+            Err(RuntimeErrorKind::DanglingPointer)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn write(&mut self, text: &str) -> Fallible<()> {
+        self.meter.write(text.len())?;
+        self.output.write_all(text.as_bytes())
+            .map_err(|err| RuntimeErrorKind::Output(err.to_string()))
+    }
+
+    /// Runs the instruction at `pc`, returning whether the program goes on
+    fn step(&mut self) -> Fallible<bool> {
+        let code = self.code;
+        let mut next = self.pc + 1;
+        let entries = &self.entries;
+        self.meter.step(|| activation(code, entries))?;
+        match &code.instrs[self.pc] {
+            Instr::Lit(value) => self.push(Word::Int(*value)),
+            Instr::LitReal(value) => self.push(Word::Real(*value)),
+            Instr::LitString(text) => {
+                let heap = &mut self.heap;
+                let address = *self.literals.entry(self.pc).or_insert_with(|| {
+                    let address = HEAP_BASE + heap.len();
+                    heap.extend(text.chars().map(|c| Word::Int(c as i64)));
+                    address
+                });
+                self.push(Word::Int(address as i64));
+            },
+            Instr::EmptySet => self.push(Word::Set(Default::default())),
+            Instr::Include => {
+                let member = self.pop_int();
+                if let Some(Word::Set(members)) = self.stack.last_mut() {
+                    members.insert(member);
+                }
+            },
+            Instr::Load(up, offset) => {
+                let word = self.stack[self.frame(*up) + offset].clone();
+                self.push(word);
+            },
+            Instr::Store(up, offset) => {
+                let word = self.pop();
+                let address = self.frame(*up) + offset;
+                self.stack[address] = word;
+            },
+            Instr::Address(up, offset) => self.push(Word::Int((self.frame(*up) + offset) as i64)),
+            Instr::LoadIndirect => {
+                let address = self.pop_address();
+                let word = self.word(address).clone();
+                self.push(word);
+            },
+            Instr::StoreIndirect => {
+                let word = self.pop();
+                let address = self.pop_address();
+                *self.word(address) = word;
+            },
+            Instr::LoadBlock(count) => {
+                let address = self.pop_address();
+                let words = self.words(address, *count);
+                self.stack.extend(words);
+            },
+            Instr::CopyBlock(count) => {
+                let source = self.pop_address();
+                let destination = self.pop_address();
+                for (offset, word) in self.words(source, *count).into_iter().enumerate() {
+                    *self.word(destination + offset) = word;
+                }
+            },
+            Instr::CompareBlock(count) => {
+                let right = self.pop_address();
+                let left = self.pop_address();
+                let right = self.words(right, *count).into_iter().map(int).collect::<Vec<_>>();
+                let left = self.words(left, *count).into_iter().map(int).collect::<Vec<_>>();
+                self.push(Word::Int(left.cmp(&right) as i64));
+            },
+            Instr::Index { low, high, size } => {
+                let index = self.pop_int();
+                let address = self.pop_address();
+                if index < *low || index > *high {
+                    return Err(RuntimeErrorKind::IndexOutOfRange { index, low: *low, high: *high });
+                }
+                self.push(Word::Int((address + (index - low) as usize * size) as i64));
+            },
+            Instr::Offset(offset) => {
+                let address = self.pop_address();
+                self.push(Word::Int((address + offset) as i64));
+            },
+            Instr::Deref => {
+                let pointer = self.pop_int();
+                self.check_pointer(pointer)?;
+                self.push(Word::Int(pointer));
+            },
+            Instr::Add | Instr::Sub | Instr::Mul | Instr::Div | Instr::Mod => {
+                let right = self.pop_int();
+                let left = self.pop_int();
+                self.push(Word::Int(match &code.instrs[self.pc] {
+                    Instr::Add => left.wrapping_add(right),
+                    Instr::Sub => left.wrapping_sub(right),
+                    Instr::Mul => left.wrapping_mul(right),
+                    _ if right == 0 => return Err(RuntimeErrorKind::DivisionByZero),
+                    Instr::Div => left.wrapping_div(right),
+                    _ => left.wrapping_rem_euclid(right)
+                }));
+            },
+            Instr::AddChecked | Instr::SubChecked | Instr::MulChecked | Instr::DivChecked => {
+                let right = self.pop_int();
+                let left = self.pop_int();
+                let result = match &code.instrs[self.pc] {
+                    Instr::AddChecked => left.checked_add(right),
+                    Instr::SubChecked => left.checked_sub(right),
+                    Instr::MulChecked => left.checked_mul(right),
+                    _ if right == 0 => return Err(RuntimeErrorKind::DivisionByZero),
+                    _ => left.checked_div(right)
+                };
+                self.push(Word::Int(result.ok_or(RuntimeErrorKind::Overflow)?));
+            },
+            Instr::Neg => {
+                let value = self.pop_int();
+                self.push(Word::Int(value.wrapping_neg()));
+            },
+            Instr::NegChecked => {
+                let value = self.pop_int();
+                self.push(Word::Int(value.checked_neg().ok_or(RuntimeErrorKind::Overflow)?));
+            },
+            Instr::AddReal | Instr::SubReal | Instr::MulReal | Instr::DivReal => {
+                let right = real(self.pop());
+                let left = real(self.pop());
+                self.push(Word::Real(match &code.instrs[self.pc] {
+                    Instr::AddReal => left + right,
+                    Instr::SubReal => left - right,
+                    Instr::MulReal => left * right,
+                    _ if right == 0.0 => return Err(RuntimeErrorKind::DivisionByZero),
+                    _ => left / right
+                }));
+            },
+            Instr::NegReal => {
+                let value = real(self.pop());
+                self.push(Word::Real(-value));
+            },
+            Instr::Float => {
+                let value = real(self.pop());
+                self.push(Word::Real(value));
+            },
+            Instr::Eq | Instr::Neq | Instr::Lt | Instr::Gt | Instr::Le | Instr::Ge => {
+                let right = self.pop();
+                let left = self.pop();
+                let holds = compare(&code.instrs[self.pc], left, right);
+                self.push(Word::Int(holds as i64));
+            },
+            Instr::Union | Instr::Difference | Instr::Intersection => {
+                let (right, left) = match (self.pop(), self.pop()) {
+                    (Word::Set(right), Word::Set(left)) => (right, left),
+                    operands => unreachable!("{:?} are not sets", operands)
+                };
+                self.push(Word::Set(match &code.instrs[self.pc] {
+                    Instr::Union => &left | &right,
+                    Instr::Difference => &left - &right,
+                    _ => &left & &right
+                }));
+            },
+            Instr::In => {
+                let set = self.pop();
+                let member = self.pop_int();
+                self.push(Word::Int(matches!(set, Word::Set(members) if members.contains(&member)) as i64));
+            },
+            Instr::Not => {
+                let value = self.pop_int();
+                self.push(Word::Int((value == 0) as i64));
+            },
+            Instr::Jump(target) => next = *target,
+            Instr::JumpFalse(target) => if self.pop_int() == 0 {
+                next = *target;
+            },
+            Instr::JumpTrue(target) => if self.pop_int() != 0 {
+                next = *target;
+            },
+            Instr::Dup => {
+                let word = self.stack.last().expect("DUP has an operand").clone();
+                self.push(word);
+            },
+            Instr::Pop => {
+                self.pop();
+            },
+            Instr::Mark => self.stack.extend(vec![Word::Int(0); HEADER]),
+            Instr::Call { up, target, params } => {
+                let base = self.stack.len() - params - HEADER;
+                self.stack[base] = Word::Int(self.frame(*up) as i64);
+                self.stack[base + 1] = Word::Int(self.base as i64);
+                self.stack[base + 2] = Word::Int(next as i64);
+                self.base = base;
+                next = *target;
+                self.entries.push(*target);
+                let entries = &self.entries;
+                self.meter.enter(|| activation(code, entries))?;
+            },
+            Instr::Enter(locals) => {
+                // the whole activation is counted, so that leaving it gives
+                // back what it took
+                let words = locals.iter().map(size).fold(0, usize::saturating_add);
+                self.meter.allocate(self.stack.len() - self.base + words)?;
+                self.stack.extend(locals.iter().flat_map(template));
+            },
+            Instr::Return { function } => {
+                let result = self.stack[self.base + RESULT].clone();
+                next = int(self.stack[self.base + 2].clone()) as usize;
+                let caller = int(self.stack[self.base + 1].clone()) as usize;
+                self.meter.free(self.stack.len() - self.base);
+                self.stack.truncate(self.base);
+                self.entries.pop();
+                self.meter.leave();
+                if *function {
+                    self.push(result);
+                }
+                self.base = caller;
+            },
+            Instr::Check { low, high, ty } => {
+                let value = int(self.stack.last().expect("CHK has an operand").clone());
+                if value < *low || value > *high {
+                    return Err(RuntimeErrorKind::OutOfRange { value, r#type: ty.clone() });
+                }
+            },
+            Instr::Std(builtin) => self.standard(*builtin)?,
+            Instr::Read(format) => {
+                let address = self.pop_address();
+                let word = match format {
+                    Format::Integer => Word::Int(self.input.read_integer()?),
+                    Format::Real => Word::Real(self.input.read_real()?),
+                    _ => Word::Int(self.input.read_char()? as i64)
+                };
+                *self.word(address) = word;
+            },
+            Instr::ReadLine => self.input.skip_line()?,
+            Instr::Write(format) => {
+                let word = self.pop();
+                let text = match format {
+                    Format::Integer => int(word).to_string(),
+                    Format::Real => format::floating(real(word), format::REAL_WIDTH),
+                    Format::Char => char::from_u32(int(word) as u32).unwrap_or('\0').to_string(),
+                    Format::Boolean => (int(word) != 0).to_string()
+                };
+                self.write(&text)?;
+            },
+            Instr::WriteString(count) => {
+                let address = self.pop_address();
+                let text = self.string(address, *count);
+                self.write(&text)?;
+            },
+            Instr::Field => {
+                format::field(int(self.stack.last().expect("FLD has an operand").clone()))?;
+            },
+            Instr::WriteWidth(kind) => {
+                let width = self.pop_int() as usize;
+                let word = self.pop();
+                let text = match kind {
+                    Format::Integer => format::pad(&int(word).to_string(), width),
+                    Format::Real => format::floating(real(word), width),
+                    Format::Char => format::pad(&char::from_u32(int(word) as u32).unwrap_or('\0').to_string(), width),
+                    Format::Boolean => format::fit(&(int(word) != 0).to_string(), width)
+                };
+                self.write(&text)?;
+            },
+            Instr::WriteFixed => {
+                let decimals = self.pop_int() as usize;
+                let width = self.pop_int() as usize;
+                let value = real(self.pop());
+                self.write(&format::fixed(value, width, decimals))?;
+            },
+            Instr::WriteStringWidth(count) => {
+                let width = self.pop_int() as usize;
+                let address = self.pop_address();
+                let text = self.string(address, *count);
+                self.write(&format::fit(&text, width))?;
+            },
+            Instr::WriteLine => self.write("\n")?,
+            Instr::New(ty) => {
+                let pointer = self.pop_address();
+                let address = HEAP_BASE + self.heap.len();
+                let words = size(ty);
+                self.meter.allocate(words)?;
+                self.heap.extend(template(ty));
+                self.live.insert(address, words);
+                *self.word(pointer) = Word::Int(address as i64);
+            },
+            Instr::Dispose => {
+                let pointer = self.pop_int();
+                self.check_pointer(pointer)?;
+                if let Some(words) = self.live.remove(&(pointer as usize)) {
+                    self.meter.free(words);
+                }
+            },
+            Instr::NoCase(ty) => {
+                let value = self.pop_int();
+                return Err(RuntimeErrorKind::NoCaseLabel(ty.ordinal_name(value as isize)));
+            },
+            Instr::Halt => return Ok(false)
+        }
+        self.pc = next;
+        Ok(true)
+    }
+
+    fn standard(&mut self, builtin: Builtin) -> Fallible<()> {
+        let domain = |argument: f64| RuntimeErrorKind::Domain {
+            function: builtin.name().to_string(),
+            argument
+        };
+        if let Builtin::Eof | Builtin::Eoln = builtin {
+            let holds = if builtin == Builtin::Eof { self.input.eof() } else { self.input.eoln() };
+            self.push(Word::Int(holds as i64));
+            return Ok(());
+        }
+        let arg = self.pop();
+        let value = real(arg.clone());
+        let result = match (builtin, arg) {
+            (Builtin::Abs, Word::Int(value)) => Word::Int(value.wrapping_abs()),
+            (Builtin::Sqr, Word::Int(value)) => Word::Int(value.wrapping_mul(value)),
+            (Builtin::Odd, Word::Int(value)) => Word::Int((value % 2 != 0) as i64),
+            (Builtin::Abs, _) => Word::Real(value.abs()),
+            (Builtin::Sqr, _) => Word::Real(value * value),
+            (Builtin::Sqrt, _) if value < 0.0 => return Err(domain(value)),
+            (Builtin::Sqrt, _) => Word::Real(value.sqrt()),
+            (Builtin::Sin, _) => Word::Real(value.sin()),
+            (Builtin::Cos, _) => Word::Real(value.cos()),
+            (Builtin::Arctan, _) => Word::Real(value.atan()),
+            (Builtin::Exp, _) => Word::Real(value.exp()),
+            (Builtin::Ln, _) if value <= 0.0 => return Err(domain(value)),
+            (Builtin::Ln, _) => Word::Real(value.ln()),
+            (Builtin::Trunc, _) => Word::Int(value.trunc() as i64),
+            (Builtin::Round, _) => Word::Int(value.round() as i64),
+            (builtin, arg) => unreachable!("{}({})", builtin.name(), arg)
+        };
+        self.push(result);
+        Ok(())
+    }
+}
+
+/// Applies a comparison instruction to two words of the same kind
+fn compare(op: &Instr, left: Word, right: Word) -> bool {
+    let ordering = match (left, right) {
+        (Word::Set(left), Word::Set(right)) => return match op {
+            Instr::Eq => left == right,
+            Instr::Neq => left != right,
+            Instr::Le => left.is_subset(&right),
+            Instr::Ge => left.is_superset(&right),
+            _ => unreachable!("sets are not ordered")
+        },
+        (Word::Int(left), Word::Int(right)) => Some(left.cmp(&right)),
+        (left, right) => real(left).partial_cmp(&real(right))
+    };
+    match ordering {
+        Some(ordering) => match op {
+            Instr::Eq => ordering == Ordering::Equal,
+            Instr::Neq => ordering != Ordering::Equal,
+            Instr::Lt => ordering == Ordering::Less,
+            Instr::Gt => ordering == Ordering::Greater,
+            Instr::Le => ordering != Ordering::Greater,
+            _ => ordering != Ordering::Less
+        },
+        // NaN compares unequal to everything
+        None => *op == Instr::Neq
+    }
+}
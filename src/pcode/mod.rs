@@ -0,0 +1,553 @@
+pub mod compiler;
+pub mod machine;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use std::ops::Range;
+use crate::ast::standard::Builtin;
+use crate::ast::types::Type;
+use crate::pcode::compiler::size;
+
+/// The value of a pointer that points nowhere
+pub const NIL: i64 = -1;
+
+/// A cell of the machine's memory. Characters, booleans, enumeration
+/// literals and addresses are all integers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Word {
+    Int(i64),
+    Real(f64),
+    Set(BTreeSet<i64>)
+}
+
+impl Display for Word {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Word::Int(value) => write!(f, "{}", value),
+            Word::Real(value) => write!(f, "{:?}", value),
+            Word::Set(members) => write!(f, "[{}]", members.iter()
+                .map(|member| member.to_string())
+                .collect::<Vec<String>>()
+                .join(","))
+        }
+    }
+}
+
+/// How `read` parses a value and `write` prints one
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Format {
+    Integer,
+    Real,
+    Char,
+    Boolean
+}
+
+/// The instructions of the machine. Operands are popped from the top of the
+/// stack, the right operand of a binary operation being the topmost.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instr {
+    /// Pushes an integer
+    Lit(i64),
+    LitReal(f64),
+    /// Pushes the address of the characters of a string literal, laid out
+    /// on the heap the first time the instruction runs
+    LitString(String),
+    /// Pushes the empty set
+    EmptySet,
+    /// Pops an ordinal and adds it to the set below it
+    Include,
+    /// Pushes the word at `offset` in the activation `up` static links out
+    Load(usize, usize),
+    /// Pops a word into `offset` in the activation `up` static links out
+    Store(usize, usize),
+    /// Pushes the address of `offset` in the activation `up` static links out
+    Address(usize, usize),
+    /// Pops an address and pushes the word at it
+    LoadIndirect,
+    /// Pops a word, then an address, and stores the word at the address
+    StoreIndirect,
+    /// Pops an address and pushes the `n` words from it
+    LoadBlock(usize),
+    /// Pops a source address, then a destination one, and copies `n` words
+    CopyBlock(usize),
+    /// Pops two addresses and pushes -1, 0 or 1 as the `n` words at the
+    /// first compare to those at the second
+    CompareBlock(usize),
+    /// Pops an index, then the address of an array, and pushes the address
+    /// of the element, each element taking `size` words
+    Index { low: i64, high: i64, size: usize },
+    /// Adds to the address on the top of the stack
+    Offset(usize),
+    /// Checks that the pointer on the top of the stack points to a variable
+    Deref,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Neg,
+    /// The integer operations that stop the program rather than overflow
+    AddChecked,
+    SubChecked,
+    MulChecked,
+    DivChecked,
+    NegChecked,
+    AddReal,
+    SubReal,
+    MulReal,
+    DivReal,
+    NegReal,
+    /// Converts the integer on the top of the stack to a real
+    Float,
+    Eq,
+    Neq,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Union,
+    Difference,
+    Intersection,
+    /// Pops a set, then an ordinal, and pushes whether the set holds it
+    In,
+    Not,
+    Jump(usize),
+    JumpFalse(usize),
+    JumpTrue(usize),
+    Dup,
+    Pop,
+    /// Reserves the links and result of an activation before its arguments
+    Mark,
+    /// Calls the subprogram at `target`, whose enclosing activation is `up`
+    /// static links out and whose arguments take the `params` words on the
+    /// top of the stack
+    Call { up: usize, target: usize, params: usize },
+    /// Pushes the initial words of the locals of an activation, of these
+    /// types
+    Enter(Vec<Type>),
+    /// Leaves an activation, pushing the result of a function
+    Return { function: bool },
+    /// Checks that the ordinal on the top of the stack is a value of `ty`
+    Check { low: i64, high: i64, ty: Type },
+    /// A standard function applied to the top of the stack
+    Std(Builtin),
+    /// Pops an address and reads a value into it
+    Read(Format),
+    ReadLine,
+    /// Pops a value and writes it
+    Write(Format),
+    /// Pops an address and writes the `n` characters from it
+    WriteString(usize),
+    /// Checks that the field width or number of decimals on the top of the
+    /// stack is at least 1
+    Field,
+    /// Pops a field width, then a value, and writes the value in a field
+    /// that wide, reals in floating-point form
+    WriteWidth(Format),
+    /// Pops a number of decimals, a field width, then a real, and writes
+    /// the real in fixed-point form
+    WriteFixed,
+    /// Pops a field width, then an address, and writes the `n` characters
+    /// from the address in a field that wide
+    WriteStringWidth(usize),
+    WriteLine,
+    /// Pops the address of a pointer and points it to a new variable of
+    /// this type
+    New(Type),
+    /// Pops a pointer and frees the variable it points to
+    Dispose,
+    /// Pops the selector of a `case` no label matched, of type `ty`
+    NoCase(Type),
+    Halt
+}
+
+impl Instr {
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instr::Lit(_) => "LIT",
+            Instr::LitReal(_) => "LRL",
+            Instr::LitString(_) => "LST",
+            Instr::EmptySet => "EMS",
+            Instr::Include => "INC",
+            Instr::Load(..) => "LOD",
+            Instr::Store(..) => "STO",
+            Instr::Address(..) => "LDA",
+            Instr::LoadIndirect => "LDI",
+            Instr::StoreIndirect => "STI",
+            Instr::LoadBlock(_) => "LDB",
+            Instr::CopyBlock(_) => "CPB",
+            Instr::CompareBlock(_) => "CMB",
+            Instr::Index { .. } => "IXA",
+            Instr::Offset(_) => "OFS",
+            Instr::Deref => "DRF",
+            Instr::Add => "ADI",
+            Instr::Sub => "SBI",
+            Instr::Mul => "MPI",
+            Instr::Div => "DVI",
+            Instr::Mod => "MOD",
+            Instr::Neg => "NGI",
+            Instr::AddChecked => "ADC",
+            Instr::SubChecked => "SBC",
+            Instr::MulChecked => "MPC",
+            Instr::DivChecked => "DVC",
+            Instr::NegChecked => "NGC",
+            Instr::AddReal => "ADR",
+            Instr::SubReal => "SBR",
+            Instr::MulReal => "MPR",
+            Instr::DivReal => "DVR",
+            Instr::NegReal => "NGR",
+            Instr::Float => "FLT",
+            Instr::Eq => "EQU",
+            Instr::Neq => "NEQ",
+            Instr::Lt => "LES",
+            Instr::Gt => "GRT",
+            Instr::Le => "LEQ",
+            Instr::Ge => "GEQ",
+            Instr::Union => "UNI",
+            Instr::Difference => "DIF",
+            Instr::Intersection => "INT",
+            Instr::In => "INN",
+            Instr::Not => "NOT",
+            Instr::Jump(_) => "UJP",
+            Instr::JumpFalse(_) => "FJP",
+            Instr::JumpTrue(_) => "TJP",
+            Instr::Dup => "DUP",
+            Instr::Pop => "POP",
+            Instr::Mark => "MST",
+            Instr::Call { .. } => "CAL",
+            Instr::Enter(_) => "ENT",
+            Instr::Return { .. } => "RET",
+            Instr::Check { .. } => "CHK",
+            Instr::Std(_) => "STD",
+            Instr::Read(_) => "RD",
+            Instr::ReadLine => "RLN",
+            Instr::Write(_) => "WR",
+            Instr::WriteString(_) => "WRS",
+            Instr::Field => "FLD",
+            Instr::WriteWidth(_) => "WRW",
+            Instr::WriteFixed => "WRF",
+            Instr::WriteStringWidth(_) => "WSW",
+            Instr::WriteLine => "WLN",
+            Instr::New(_) => "NEW",
+            Instr::Dispose => "DSP",
+            Instr::NoCase(_) => "NCS",
+            Instr::Halt => "HLT"
+        }
+    }
+}
+
+impl Display for Instr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let operands = match self {
+            Instr::Lit(value) => value.to_string(),
+            Instr::LitReal(value) => format!("{:?}", value),
+            Instr::LitString(text) => format!("{:?}", text),
+            Instr::Load(up, offset) | Instr::Store(up, offset) | Instr::Address(up, offset) =>
+                format!("{} {}", up, offset),
+            Instr::LoadBlock(n) | Instr::CopyBlock(n) | Instr::CompareBlock(n)
+            | Instr::Offset(n) | Instr::WriteString(n) | Instr::WriteStringWidth(n) => n.to_string(),
+            Instr::Index { low, high, size } => format!("{}..{} *{}", low, high, size),
+            Instr::Jump(target) | Instr::JumpFalse(target) | Instr::JumpTrue(target) =>
+                format!("L{}", target),
+            Instr::Call { up, target, params } => format!("{} L{} ({})", up, target, params),
+            Instr::Enter(locals) => locals.iter().map(size).sum::<usize>().to_string(),
+            Instr::New(ty) => size(ty).to_string(),
+            Instr::Return { function } => if *function { "F" } else { "P" }.to_string(),
+            Instr::Check { low, high, .. } => format!("{} {}", low, high),
+            Instr::Std(builtin) => builtin.name().to_string(),
+            Instr::Read(format) | Instr::Write(format) | Instr::WriteWidth(format) => format!("{:?}", format),
+            Instr::NoCase(ty) => ty.to_string(),
+            _ => String::new()
+        };
+        if operands.is_empty() {
+            write!(f, "{}", self.mnemonic())
+        } else {
+            write!(f, "{:<4}{}", self.mnemonic(), operands)
+        }
+    }
+}
+
+/// A compiled program, with the source range each instruction comes from
+#[derive(Debug, Default)]
+pub struct Code {
+    pub instrs: Vec<Instr>,
+    pub ranges: Vec<Range<usize>>,
+    /// The entry points of the main program and of every subprogram, named
+    /// as in `procedure foo`
+    pub labels: BTreeMap<usize, String>
+}
+
+impl Display for Code {
+    /// The listing, with the entry points and jump targets labeled
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let targets = self.instrs.iter()
+            .filter_map(|instr| match instr {
+                Instr::Jump(target) | Instr::JumpFalse(target) | Instr::JumpTrue(target)
+                | Instr::Call { target, .. } => Some(*target),
+                _ => None
+            })
+            .collect::<BTreeSet<usize>>();
+        for (address, instr) in self.instrs.iter().enumerate() {
+            if let Some(label) = self.labels.get(&address) {
+                writeln!(f, "{}:", label)?;
+            }
+            let target = if targets.contains(&address) { format!("L{}", address) } else { String::new() };
+            writeln!(f, "{:>6} {:>5}  {}", target, address, instr)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::program::Program;
+    use crate::error::runtime_error::RuntimeErrorKind;
+    use crate::interpreter::Interpreter;
+    use crate::interpreter::limits::Limits;
+    use crate::pcode::machine::Machine;
+
+    fn check(src: &str) -> Program {
+        let (program, errors) = Program::new(src.to_string());
+        assert!(errors.is_empty(), "{:?}", errors);
+        let diagnostics = program.validate();
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        program
+    }
+
+    /// Runs a valid program on the machine, checking that the interpreter
+    /// gives the same outcome, its runtime error pointing at the same source
+    fn run(src: &str, input: &str) -> Result<String, RuntimeErrorKind> {
+        let program = check(src);
+        let code = compiler::compile(&program);
+        let mut output = vec![];
+        let outcome = Machine::new(&code, input.as_bytes(), &mut output).run();
+        let outcome = outcome.map(|_| String::from_utf8(output).unwrap()).map_err(|err| (err.range, err.kind));
+        let mut output = vec![];
+        let expected = Interpreter::new(&program, input.as_bytes(), &mut output).run();
+        let expected = expected.map(|_| String::from_utf8(output).unwrap()).map_err(|err| (err.range, err.kind));
+        assert_eq!(outcome, expected, "{}", code);
+        outcome.map_err(|(_, kind)| kind)
+    }
+
+    #[test]
+    fn statements_and_expressions() {
+        assert_eq!(run("
+            program statements;
+            type color = (red, green, blue);
+            var c: color; r: real; i, j: integer; b: boolean;
+            begin
+                writeln(1 + 2 * 3, ' ', 7 div 2, ' ', -7 mod 3, ' ', -7 div 2);
+                r := 7 / 2; writeln(r, ' ', trunc(r), ' ', round(r), ' ', abs(-2), ' ', sqr(1.5));
+                for c := blue downto red do write(ord(c));
+                writeln(succ('a'), chr(ord('A') + 1), odd(3), green < blue);
+                i := 0; j := 0;
+                repeat i := i + 1; j := j + i until i = 10;
+                b := (i > 5) or (1 div (i - i) = 0);
+                case j mod 4 of 0, 1: write('x'); 3: write('y') end;
+                if b and not (r < 1) then writeln(j, ' ', 2 * r) else writeln
+            end.
+        ", ""), Ok("7 3 -1 -3\n 3.500000000000000E+00 3 4 2  2.250000000000000E+00\n210bBtruetrue\ny55  7.000000000000000E+00\n".to_string()));
+    }
+
+    #[test]
+    fn nested_subprograms() {
+        assert_eq!(run("
+            program subprograms;
+            type vector = array [1..3] of integer;
+            var a: vector; n: integer;
+            function fib(n: integer): integer;
+            begin
+                if n < 2 then fib := n else fib := fib(n - 1) + fib(n - 2)
+            end;
+            procedure swap(var x, y: integer);
+            var t: integer;
+            begin
+                t := x; x := y; y := t
+            end;
+            procedure count(times: integer);
+            var total: integer;
+                procedure add;
+                    procedure bump(var k: integer);
+                    begin
+                        k := k + times
+                    end;
+                begin
+                    bump(total); n := n + 1
+                end;
+            begin
+                total := 0;
+                while total < 10 do add;
+                times := 0
+            end;
+            function sum(v: vector): integer;
+            begin
+                v[2] := 10; sum := v[1] + v[2] + v[3]
+            end;
+            begin
+                a[1] := 1; a[3] := 3;
+                swap(a[1], a[3]);
+                n := 4; count(n);
+                writeln(fib(15), ' ', a[1], a[3], ' ', n, ' ', sum(a), a[2])
+            end.
+        ", ""), Ok("610 31 7 140\n".to_string()));
+    }
+
+    #[test]
+    fn structured_values() {
+        assert_eq!(run("
+            program structured;
+            type point = record x, y: integer end;
+                 ref = ^point;
+            var p: point; q: ref; grid: array [1..2, boolean] of char;
+                s: set of char; t: array [1..2] of point;
+                u, v: packed array [1..3] of char; w: array [0..5] of char; i: integer;
+            begin
+                p.x := 1; t[2] := p; p.x := 2;
+                new(q); q^.y := t[2].x + p.x;
+                grid[2, true] := 'z';
+                s := ['a', 'b'] + ['c'] - ['a'];
+                writeln(q^.y, grid[2, true], 'a' in s, 'b' in s, s <= ['b', 'c', 'd']);
+                dispose(q);
+                for i := 0 to 5 do w[i] := chr(ord('a') + i);
+                pack(w, 1, u); unpack(u, w, 3); v := u; v[3] := 'a';
+                writeln(u, ' ', w[5], ' ', u = v, u > v)
+            end.
+        ", ""), Ok("3zfalsetruetrue\nbcd d falsetrue\n".to_string()));
+    }
+
+    #[test]
+    fn standard_input() {
+        assert_eq!(run("
+            program input;
+            var i, j: integer; r: real; c: char;
+            begin
+                read(i, c); readln; readln(r);
+                while not eof do begin read(j); i := i + j; readln end;
+                writeln(i, c, r)
+            end.
+        ", "12x rest\n 2.5e1\n1\n2\n"), Ok("15x 2.500000000000000E+01\n".to_string()));
+    }
+
+    #[test]
+    fn runtime_errors() {
+        let cases = [
+            ("i := 1 div (i - i)", RuntimeErrorKind::DivisionByZero),
+            ("a[i + 4] := 0", RuntimeErrorKind::IndexOutOfRange { index: 4, low: 1, high: 3 }),
+            ("p^ := 1", RuntimeErrorKind::NilPointer),
+            ("begin new(p); dispose(p); p^ := 1 end", RuntimeErrorKind::DanglingPointer),
+            ("case i of 1: end", RuntimeErrorKind::NoCaseLabel("0".to_string())),
+            ("read(i)", RuntimeErrorKind::EndOfInput),
+            ("i := ord(pred(false))", RuntimeErrorKind::OutOfRange { value: -1, r#type: Type::Boolean }),
+            ("begin i := 4; s := i end", RuntimeErrorKind::OutOfRange { value: 4, r#type: Type::Range(Box::new(Type::Integer), 1..3) }),
+            ("{$Q+} begin i := 9223372036854775807; i := i + 1 end", RuntimeErrorKind::Overflow),
+            ("writeln(2.5:3:i)", RuntimeErrorKind::FieldWidth(0)),
+        ];
+        for (body, expected) in cases.iter() {
+            let src = format!("
+                program faults;
+                var i: integer; a: array [1..3] of integer; p: ^integer; s: 1..3;
+                begin {} end.
+            ", body);
+            assert_eq!(run(&src, "").unwrap_err(), *expected, "{}", body);
+        }
+    }
+
+    #[test]
+    fn limits() {
+        let program = check("
+            program greedy;
+            var p: ^integer;
+            function forever(k: integer): integer;
+            begin
+                new(p);
+                forever := forever(k + 1)
+            end;
+            begin
+                writeln(forever(0))
+            end.
+        ");
+        let code = compiler::compile(&program);
+        let cases = [
+            (Limits { steps: Some(500), ..Limits::default() },
+             RuntimeErrorKind::StepLimit { steps: 500, scope: "function forever".to_string() }),
+            (Limits { depth: Some(20), ..Limits::default() },
+             RuntimeErrorKind::DepthLimit { depth: 20, scope: "function forever".to_string() }),
+            (Limits { heap: Some(30), ..Limits::default() }, RuntimeErrorKind::HeapLimit(30)),
+            (Limits::default(),
+             RuntimeErrorKind::DepthLimit { depth: Limits::DEPTH, scope: "function forever".to_string() }),
+        ];
+        for (limits, expected) in cases {
+            let mut output = vec![];
+            let mut machine = Machine::new(&code, "".as_bytes(), &mut output);
+            machine.limit(limits);
+            assert_eq!(machine.run().map_err(|err| err.kind), Err(expected));
+        }
+        // as are the locals of each activation, before they are pushed
+        let code = compiler::compile(&check("
+            program deep;
+            procedure down(n: integer);
+            var a: array [1..1000] of integer;
+            begin
+                if n > 0 then down(n - 1)
+            end;
+            begin
+                down(5); down(50)
+            end.
+        "));
+        let mut output = vec![];
+        let mut machine = Machine::new(&code, "".as_bytes(), &mut output);
+        machine.limit(Limits { heap: Some(20_000), ..Limits::default() });
+        assert_eq!(machine.run().map_err(|err| err.kind), Err(RuntimeErrorKind::HeapLimit(20_000)));
+        // and no program takes more than the machine has room for
+        let code = compiler::compile(&check("
+            program huge;
+            var a: array [1..10000000000] of integer;
+            begin a[1] := 1 end.
+        "));
+        let mut output = vec![];
+        let mut machine = Machine::new(&code, "".as_bytes(), &mut output);
+        assert_eq!(machine.run().map_err(|err| err.kind), Err(RuntimeErrorKind::HeapLimit(Limits::HEAP)));
+    }
+
+    #[test]
+    fn listing() {
+        let code = compiler::compile(&check("
+            program listing;
+            var i: integer;
+            procedure twice(var n: integer);
+            begin
+                n := 2 * n
+            end;
+            begin
+                i := 1;
+                while i < 5 do twice(i)
+            end.
+        "));
+        assert_eq!(format!("\n{}", code), "
+           0  UJP L9
+procedure twice:
+    L1     1  ENT 0
+           2  LOD 0 4
+           3  LIT 2
+           4  LOD 0 4
+           5  LDI
+           6  MPI
+           7  STI
+           8  RET P
+program listing:
+    L9     9  ENT 1
+          10  LIT 1
+          11  STO 0 4
+   L12    12  LOD 0 4
+          13  LIT 5
+          14  LES
+          15  FJP L20
+          16  MST
+          17  LDA 0 4
+          18  CAL 0 L1 (1)
+          19  UJP L12
+   L20    20  HLT
+");
+    }
+}
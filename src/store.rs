@@ -63,6 +63,12 @@ impl<Content> Store<'_, Content> {
     }
 }
 
+impl<Content> Default for Store<'_, Content> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub struct Variable {
     pub name: String,
     pub r#type: Type
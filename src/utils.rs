@@ -40,25 +40,18 @@ impl FromStrRadix for f64 {
     type Error = ParseFloatError;
 
     fn from_str_radix(s: &str, radix: u32) -> Result<Self, ParseFloatError> {
-        let (base, exp) = match s.find(".") {
-            Some(dot) => {
-                let exp = s.len() - dot;
-                let mut int_str = s
-                    .to_string();
-                int_str.replace_range(dot..dot+1, "");
-                let int = i64::from_str_radix(&int_str, radix)?;
-                (int, exp)
-            }
-            None => {
-                let mut split = s.split(|c: char| c == 'e' || c == 'E');
-                let base = split.next()
-                    .ok_or(ParseFloatError::MissingPoint)?;
-                let exp = split.next()
-                    .ok_or(ParseFloatError::MissingPoint)?;
-                (i64::from_str_radix(base, radix).unwrap(), usize::from_str(exp).unwrap())
-            }
+        // 'e' is a digit from base 15 upwards
+        let (mantissa, exp) = match s.find(|c: char| radix < 15 && (c == 'e' || c == 'E')) {
+            Some(e) => (&s[..e], i32::from_str(s[e + 1..].trim_start_matches('+'))?),
+            None => (s, 0)
         };
-        Ok((radix as f64).powf(-(exp as f64)) * base as f64)
+        let (digits, scale) = match mantissa.find('.') {
+            Some(dot) => (mantissa.replacen('.', "", 1), mantissa.len() - dot - 1),
+            None if mantissa.len() < s.len() => (mantissa.to_string(), 0),
+            None => return Err(ParseFloatError::MissingPoint)
+        };
+        let base = i64::from_str_radix(&digits, radix)?;
+        Ok(base as f64 * (radix as f64).powi(exp - scale as i32))
     }
 }
 
@@ -0,0 +1,29 @@
+program arithmetic;
+const limit = 10;
+type color = (red, green, blue);
+     digit = 0..9;
+var c: color; r: real; i, j: integer; b: boolean; d: digit;
+begin
+    writeln(1 + 2 * 3, ' ', 7 div 2, ' ', -7 mod 3, ' ', (-7) mod 3, ' ', (-7) div 2);
+    r := 7 / 2;
+    writeln(r, ' ', trunc(r), ' ', round(r), ' ', round(-r), ' ', abs(-2), ' ', sqr(1.5), ' ', r * 1e10, ' ', 1 / 3);
+    writeln(sqrt(16), ' ', exp(0), ' ', ln(1), ' ', sin(0), ' ', cos(0), ' ', arctan(0), ' ', -0.25);
+    for c := blue downto red do write(ord(c));
+    writeln(succ('a'), chr(ord('A') + 1), odd(3), odd(-2), green < blue, pred(blue) = green);
+    i := 0; j := 0;
+    repeat i := i + 1; j := j + i until i = limit;
+    b := (i > 5) or (1 div (i - i) = 0);
+    case j mod 4 of
+        0, 1: write('x');
+        3: write('y')
+    end;
+    if b and not (r < 1) then writeln(j, ' ', 2 * r) else writeln;
+    d := 9;
+    while d > 0 do begin write(d); d := d - 3 end;
+    writeln;
+    for i := 1 to 3 do
+        for j := i downto 1 do
+            write(i * j, ' ');
+    writeln(1000000 * 1000000 div 7);
+    writeln(r = 3.5, r <> 3.5, r < 4, r >= 4, r = 7, 1 <> r)
+end.
@@ -0,0 +1,20 @@
+program format;
+type name = packed array [1..5] of char;
+var i, w: integer; r: real; b: boolean; c: char; s: name;
+    reals: array [1..12] of real;
+begin
+    s[1] := 'h'; s[2] := 'e'; s[3] := 'l'; s[4] := 'l'; s[5] := 'o';
+    writeln(42:5, -42:5, -42:1, 0:3, 9223372036854775807:22, '|');
+    writeln('x':3, 'y':1, true:6, false:6, true:2, false:1, '|');
+    writeln(s:8, s:5, s:3, s:1, '|');
+    reals[1] := 3.14159; reals[2] := -3.14159; reals[3] := 0.0; reals[4] := -0.0;
+    reals[5] := 0.125; reals[6] := 0.375; reals[7] := 2.5; reals[8] := 123456.789;
+    reals[9] := 1e-7; reals[10] := 9.99999; reals[11] := 1e30; reals[12] := -1e-300;
+    for i := 1 to 12 do begin
+        r := reals[i];
+        writeln(r:1, '|', r:10, '|', r:15, '|', r:1:1, '|', r:10:2, '|', r:6:0 + 1, '|', r:1:9)
+    end;
+    w := 3; b := w > 2; c := 'z';
+    for i := 1 to 4 do writeln(i * i:w + i, b:i, c:i, i / 3:w * i:i, sqrt(i):i + 8);
+    writeln(1e21:1:1, 1e22:30:3, -1e15:1:1, 1e300:12, -1e-300:9)
+end.
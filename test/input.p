@@ -0,0 +1,7 @@
+program input;
+var i, j: integer; r: real; c: char;
+begin
+    read(i, c); readln; readln(r);
+    while not eof do begin read(j); i := i + j; readln end;
+    writeln(i, c, r)
+end.
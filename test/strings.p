@@ -0,0 +1,33 @@
+program strings;
+const greeting = 'hello';
+type word = packed array [1..5] of char;
+var w, v: word; line: packed array [1..12] of char; i: integer;
+
+procedure show(label: word; text: word);
+begin
+    writeln(label, ': ', text, '|', text:7, '|', text:3, '|')
+end;
+
+function count(text: word; c: char): integer;
+var i, n: integer;
+begin
+    n := 0;
+    for i := 1 to 5 do
+        if text[i] = c then n := n + 1;
+    count := n
+end;
+
+begin
+    w := greeting;
+    v := 'world';
+    show('first', w);
+    show('other', v);
+    writeln(w < v, w = 'hello', v <> 'world', 'apple' < 'apply', greeting > 'help!');
+    writeln(count('level', 'l'), count(v, 'o'));
+    line := 'it''s a line!';
+    writeln(line, '|', line[3] = '''', ' ''q''');
+    for i := 1 to 5 do
+        if w[i] = v[i] then writeln('same at ', i);
+    writeln('tab\\slash', ' ', 'quote\'s');
+    writeln(greeting:8, 'done':2)
+end.
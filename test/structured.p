@@ -0,0 +1,21 @@
+program structured;
+type point = record x, y: integer end;
+     ref = ^point;
+     name = packed array [1..3] of char;
+var p: point; q, r: ref; grid: array [1..2, boolean] of char;
+    s: set of char; t: array [1..2] of point;
+    u, v: name; w: array [0..5] of char; i: integer;
+    m: array [-2..2] of integer;
+begin
+    p.x := 1; t[2] := p; p.x := 2;
+    new(q); q^.y := t[2].x + p.x; r := q;
+    grid[2, true] := 'z';
+    s := ['a', 'b'] + ['c'] - ['a'];
+    writeln(r^.y, grid[2, true], 'a' in s, 'b' in s, s <= ['b', 'c', 'd'], s = ['c', 'b'], q = r);
+    dispose(q);
+    for i := 0 to 5 do w[i] := chr(ord('a') + i);
+    pack(w, 1, u); unpack(u, w, 3); v := u; v[3] := 'a';
+    writeln(u, ' ', w[5], ' ', u = v, u > v, u < v);
+    for i := -2 to 2 do m[i] := i * i;
+    writeln(m[-2] + m[1], ' ', s * ['b'] = ['b'])
+end.
@@ -0,0 +1,56 @@
+program subprograms;
+type vector = array [1..3] of integer;
+var a: vector; n: integer;
+
+function fib(n: integer): integer;
+begin
+    if n < 2 then fib := n else fib := fib(n - 1) + fib(n - 2)
+end;
+
+procedure swap(var x, y: integer);
+var t: integer;
+begin
+    t := x; x := y; y := t
+end;
+
+procedure count(times: integer);
+var total: integer;
+    procedure add;
+        procedure bump(var k: integer);
+        begin
+            k := k + times
+        end;
+    begin
+        bump(total); n := n + 1
+    end;
+begin
+    total := 0;
+    while total < 10 do add;
+    times := 0
+end;
+
+function sum(v: vector): integer;
+var i, s: integer;
+begin
+    v[2] := 10; s := 0;
+    for i := 1 to 3 do s := s + v[i];
+    sum := s
+end;
+
+function average(x, y: real): real;
+begin
+    average := (x + y) / 2
+end;
+
+function depth(n: integer): integer;
+begin
+    if n = 0 then depth := 0 else depth := depth(n - 1) + 1
+end;
+
+begin
+    a[1] := 1; a[3] := 3;
+    swap(a[1], a[3]);
+    n := 4; count(n);
+    writeln(fib(20), ' ', a[1], a[3], ' ', n, ' ', sum(a), a[2]);
+    writeln(average(1, 2), ' ', depth(10000))
+end.